@@ -0,0 +1,88 @@
+# Status bar
+status-mode-normal = NORMAL
+status-mode-insert = INSERT
+status-mode-agentic = AGENTIC
+status-panel-files = FILES
+status-panel-editor = EDITOR
+status-panel-chat = CHAT
+status-panel-notifications = NOTIFICATIONS
+status-no-file = No file open
+status-file-count = ({ $count } files)
+status-cursor = Ln { $line }, Col { $col }
+status-plain-text = Plain Text
+
+# Command reference (Ctrl+H)
+commands-title = ⌨️  Command Reference - Ctrl+H
+commands-block-title = ⌨️  Commands
+commands-section-files = 🔧 File Operations:
+commands-new-file = New file
+commands-save-file = Save file
+commands-close-file = Close file
+commands-focus-explorer = Focus file explorer
+commands-new-folder = New folder
+commands-rename = Rename (selected file)
+commands-delete = Delete (selected file)
+commands-section-editor = 📝 Editor:
+commands-insert-mode = Insert mode
+commands-normal-mode = Normal mode
+commands-move-normal = Move cursor (normal mode)
+commands-word-motions = Next word / previous word / word end
+commands-line-motions = Line start / line end
+commands-buffer-motions = First line / last line
+commands-matching-bracket = Jump to matching bracket
+commands-marks = Set mark / jump to mark (List Bookmarks in the palette)
+commands-folds = Toggle fold / open all folds
+commands-indent = Indent / dedent line (Tab / Shift+Tab in insert mode)
+commands-move-line = Move line up / down
+commands-duplicate-join = Duplicate line / join with next line
+commands-count = Repeat a motion a number of times
+commands-toggle-comment = Comment / uncomment line
+commands-move = Move cursor
+commands-section-chat = 💬 AI Chat:
+commands-send = Send message
+commands-soft-newline = Newline (keeps indentation)
+commands-send-image = Send with image
+commands-send-file = Send with the open file
+commands-clear-chat = Clear chat
+commands-copy-answer = Copy last answer as plain text
+commands-clear-notifications = Clear notifications
+commands-section-navigation = 🔄 Navigation:
+commands-cycle-panels = Cycle panels
+commands-panel-access = Direct panel access
+commands-split = Split editor (side by side / stacked / off)
+commands-toggle-folder = Toggle folder (file explorer)
+commands-section-system = ⚙️  System:
+commands-palette = Command palette / calculator
+commands-emoji = Insert emoji / symbol
+commands-agentic = Toggle agentic mode
+commands-api-config = API configuration
+commands-suspend = Suspend to shell (fg to return)
+commands-quit = Quit
+commands-general-help = General help
+commands-close = Press Ctrl+H to close this help
+
+# General help (F1)
+help-title = 🦀 Rust Coding Agent - IDE Help
+help-block-title = ❓ Help
+help-section-start = 🎯 Getting Started:
+help-start-1 = 1. Use Alt+1 to focus file explorer
+help-start-2 = 2. Navigate with ↑/↓ or j/k keys
+help-start-3 = 3. Press Enter to open files
+help-start-4 = 4. Use 'i' in editor for insert mode
+help-start-5 = 5. Chat with AI using Alt+3
+help-section-features = 🔧 Main Features:
+help-feature-tabs = • Multi-tab file editing
+help-feature-chat = • Integrated AI chat with image support
+help-feature-vim = • Vim-like keyboard navigation
+help-feature-panels = • Resizable panels
+help-feature-agentic = • Agentic mode for file operations
+help-section-interface = 🎮 Interface:
+help-interface-left = Left: File explorer + AI chat
+help-interface-right = Right: Code editor with tabs
+help-interface-bottom = Bottom: Status bar with file info
+help-section-tips = 💡 Pro Tips:
+help-tip-commands = • Use Ctrl+H for detailed commands
+help-tip-mouse = • Mouse support for clicking
+help-tip-agentic = • Ctrl+A enables AI file operations
+help-tip-resize = • Ctrl+←→ to resize sidebar
+help-close = Press F1 or ? to close help
@@ -0,0 +1,88 @@
+# Barra de estado
+status-mode-normal = NORMAL
+status-mode-insert = INSERTAR
+status-mode-agentic = AGENTE
+status-panel-files = ARCHIVOS
+status-panel-editor = EDITOR
+status-panel-chat = CHAT
+status-panel-notifications = AVISOS
+status-no-file = Ningún archivo abierto
+status-file-count = ({ $count } archivos)
+status-cursor = Lín { $line }, Col { $col }
+status-plain-text = Texto plano
+
+# Referencia de comandos (Ctrl+H)
+commands-title = ⌨️  Referencia de comandos - Ctrl+H
+commands-block-title = ⌨️  Comandos
+commands-section-files = 🔧 Archivos:
+commands-new-file = Nuevo archivo
+commands-save-file = Guardar archivo
+commands-close-file = Cerrar archivo
+commands-focus-explorer = Ir al explorador de archivos
+commands-new-folder = Nueva carpeta
+commands-rename = Renombrar (archivo seleccionado)
+commands-delete = Eliminar (archivo seleccionado)
+commands-section-editor = 📝 Editor:
+commands-insert-mode = Modo inserción
+commands-normal-mode = Modo normal
+commands-move-normal = Mover el cursor (modo normal)
+commands-word-motions = Palabra siguiente / anterior / fin de palabra
+commands-line-motions = Inicio / fin de línea
+commands-buffer-motions = Primera / última línea
+commands-matching-bracket = Saltar al paréntesis correspondiente
+commands-marks = Poner marca / saltar a la marca (Listar marcadores en la paleta)
+commands-folds = Plegar / desplegar todo
+commands-indent = Sangrar / quitar sangría (Tab / Shift+Tab en modo inserción)
+commands-move-line = Mover la línea arriba / abajo
+commands-duplicate-join = Duplicar línea / unir con la siguiente
+commands-count = Repetir un movimiento varias veces
+commands-toggle-comment = Comentar / descomentar línea
+commands-move = Mover el cursor
+commands-section-chat = 💬 Chat con IA:
+commands-send = Enviar mensaje
+commands-soft-newline = Nueva línea (mantiene la sangría)
+commands-send-image = Enviar con imagen
+commands-send-file = Enviar con el archivo abierto
+commands-clear-chat = Borrar chat
+commands-copy-answer = Copiar la última respuesta como texto plano
+commands-clear-notifications = Borrar avisos
+commands-section-navigation = 🔄 Navegación:
+commands-cycle-panels = Cambiar de panel
+commands-panel-access = Acceso directo a paneles
+commands-split = Dividir el editor (en paralelo / apilado / no)
+commands-toggle-folder = Abrir/cerrar carpeta (explorador)
+commands-section-system = ⚙️  Sistema:
+commands-palette = Paleta de comandos / calculadora
+commands-emoji = Insertar emoji / símbolo
+commands-agentic = Activar/desactivar modo agente
+commands-api-config = Configuración de la API
+commands-suspend = Suspender al shell (fg para volver)
+commands-quit = Salir
+commands-general-help = Ayuda general
+commands-close = Pulsa Ctrl+H para cerrar esta ayuda
+
+# Ayuda general (F1)
+help-title = 🦀 Rust Coding Agent - Ayuda del IDE
+help-block-title = ❓ Ayuda
+help-section-start = 🎯 Primeros pasos:
+help-start-1 = 1. Alt+1 para ir al explorador de archivos
+help-start-2 = 2. Navega con ↑/↓ o las teclas j/k
+help-start-3 = 3. Pulsa Enter para abrir archivos
+help-start-4 = 4. Usa 'i' en el editor para el modo inserción
+help-start-5 = 5. Habla con la IA con Alt+3
+help-section-features = 🔧 Funciones principales:
+help-feature-tabs = • Edición con varias pestañas
+help-feature-chat = • Chat con IA integrado, con soporte de imágenes
+help-feature-vim = • Navegación de teclado estilo Vim
+help-feature-panels = • Paneles redimensionables
+help-feature-agentic = • Modo agente para operaciones con archivos
+help-section-interface = 🎮 Interfaz:
+help-interface-left = Izquierda: explorador de archivos + chat con IA
+help-interface-right = Derecha: editor de código con pestañas
+help-interface-bottom = Abajo: barra de estado con información del archivo
+help-section-tips = 💡 Consejos:
+help-tip-commands = • Ctrl+H muestra todos los comandos
+help-tip-mouse = • Se puede hacer clic con el ratón
+help-tip-agentic = • Ctrl+A permite a la IA trabajar con archivos
+help-tip-resize = • Ctrl+←→ cambia el ancho de la barra lateral
+help-close = Pulsa F1 o ? para cerrar la ayuda
@@ -1,10 +1,16 @@
-use super::{AgentAction, AgentExecutor, AgentResponse};
-use crate::api::GroqClient;
-use anyhow::Result;
+use super::{tools, AgentAction, AgentExecutor, AgentResponse};
+use crate::api::{GroqClient, GroqMessage};
+use anyhow::{anyhow, Result};
 use regex::Regex;
 use serde_json;
+use serde_json::Value;
 use std::path::PathBuf;
 
+/// Stands in for tool call ids in strict JSON mode, numbered from 1 in the order of the array
+pub const STRICT_JSON_ID: &str = "json-action-";
+/// Starts the user message that carries a strict JSON action's result
+pub const RESULT_PREFIX: &str = "[Result of action ";
+
 pub struct AgentActionParser;
 
 impl AgentActionParser {
@@ -22,6 +28,23 @@ impl AgentActionParser {
         actions
     }
 
+    /// Strict JSON mode: the reply must hold exactly one ```json block with an array of actions, each
+    /// checked against its tool's schema. An empty array means the model is done
+    pub fn parse_strict(response: &str) -> Result<Vec<AgentAction>> {
+        let block = Regex::new(r"(?s)```json\s*(.*?)\s*```")?;
+        let blocks: Vec<&str> = block.captures_iter(response).filter_map(|cap| cap.get(1)).map(|json| json.as_str()).collect();
+        let [json] = blocks.as_slice() else {
+            return Err(anyhow!("expected exactly one ```json block, found {}", blocks.len()));
+        };
+        let value: Value = serde_json::from_str(json).map_err(|e| anyhow!("the ```json block is not valid JSON: {}", e))?;
+        let items = value.as_array().ok_or_else(|| anyhow!("the ```json block must hold an array of actions"))?;
+        items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| tools::parse_json_action(item).map_err(|e| anyhow!("action {}: {}", index + 1, e)))
+            .collect()
+    }
+
     fn extract_json_actions(response: &str) -> Option<Vec<AgentAction>> {
         // Look for JSON blocks containing actions
         let json_regex = Regex::new(r"```json\s*(.*?)\s*```").ok()?;
@@ -126,6 +149,35 @@ impl AgentActionParser {
     }
 }
 
+/// System prompt of strict JSON mode, sent instead of tool definitions
+pub fn strict_json_prompt() -> String {
+    format!(
+        "Act by replying with exactly one ```json block holding an array of actions; text outside the block is shown to the user. \
+         Each action is an object with one key:\n{}\n\
+         Their results come back in the next message. Reply with an empty array [] once the task is done.",
+        tools::json_action_catalog()
+    )
+}
+
+/// Sent back when a strict JSON reply doesn't validate, so the model can correct itself
+pub fn strict_retry_prompt(error: &anyhow::Error) -> String {
+    format!(
+        "Your last reply could not be used: {}. Re-emit it as exactly one ```json block holding a valid array of actions.",
+        error
+    )
+}
+
+/// The result of an action for the conversation: a tool message answering the call, or in strict
+/// JSON mode, where there were no calls, a user message naming the action's number
+pub fn result_message(id: &str, response: &AgentResponse) -> GroqMessage {
+    match id.strip_prefix(STRICT_JSON_ID) {
+        Some(number) => {
+            GroqClient::create_text_message("user", &format!("{}{}] {}", RESULT_PREFIX, number, tools::result_content(response)))
+        }
+        None => GroqClient::create_tool_result_message(id, &tools::result_content(response)),
+    }
+}
+
 pub async fn process_agent_message(
     message: &str,
     executor: &mut dyn AgentExecutor,
@@ -200,4 +252,21 @@ mod tests {
             _ => panic!("Expected WriteFile action"),
         }
     }
+
+    #[test]
+    fn test_parse_strict_json() {
+        let reply = "Reading it first.\n```json\n[{\"ReadFile\": {\"path\": \"src/main.rs\"}}, {\"RunTests\": {}}]\n```";
+        let actions = AgentActionParser::parse_strict(reply).unwrap();
+        assert!(matches!(&actions[..], [AgentAction::ReadFile { .. }, AgentAction::RunTests { filter: None }]));
+        assert!(AgentActionParser::parse_strict("```json\n[]\n```").unwrap().is_empty());
+
+        let error = |reply: &str| AgentActionParser::parse_strict(reply).unwrap_err().to_string();
+        assert_eq!(error("I'll read src/main.rs"), "expected exactly one ```json block, found 0");
+        assert_eq!(error("```json\n{\"ReadFile\": {\"path\": \"a\"}}\n```"), "the ```json block must hold an array of actions");
+        assert_eq!(error("```json\n[{\"ReadFile\": {}}]\n```"), "action 1: ReadFile: missing `path`");
+
+        let result = result_message(&format!("{}2", STRICT_JSON_ID), &AgentResponse::success("Done".to_string(), None));
+        assert_eq!(result.role, "user");
+        assert!(result_message("call_1", &AgentResponse::success("Done".to_string(), None)).tool_call_id.is_some());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,78 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+pub const AIIGNORE_FILE: &str = ".aiignore";
+
+/// Project-level `.aiignore` rules (gitignore syntax) for files that must never reach the AI
+#[derive(Debug, Clone)]
+pub struct AiIgnore {
+    rules: Gitignore,
+}
+
+impl Default for AiIgnore {
+    fn default() -> Self {
+        Self { rules: Gitignore::empty() }
+    }
+}
+
+impl AiIgnore {
+    /// Load `<root>/.aiignore`; a missing file simply means nothing is ignored
+    pub fn load(root: &Path) -> Self {
+        let content = std::fs::read_to_string(root.join(AIIGNORE_FILE)).unwrap_or_default();
+        Self::parse(root, &content)
+    }
+
+    /// Rules from `content`; lines that aren't valid globs are skipped
+    pub fn parse(root: &Path, content: &str) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        for line in content.lines() {
+            let _ = builder.add_line(None, line);
+        }
+        Self { rules: builder.build().unwrap_or_else(|_| Gitignore::empty()) }
+    }
+
+    /// Whether `path` (absolute, or relative to the project root) is excluded from AI context;
+    /// `is_dir` tells whether it is a directory, for rules that end in `/`. Like gitignore, an
+    /// ignored directory hides everything below it. Paths outside the project never match
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.rules.is_empty() {
+            return false;
+        }
+        let relative = if path.is_absolute() {
+            match path.strip_prefix(self.rules.path()) {
+                Ok(relative) => relative,
+                Err(_) => return false,
+            }
+        } else {
+            path
+        };
+        if relative.as_os_str().is_empty() {
+            return false;
+        }
+        self.rules.matched_path_or_any_parents(relative, is_dir).is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aiignore_rules() {
+        let root = Path::new("/nonexistent/project");
+        let ignore = AiIgnore::parse(root, "# secrets\n.env\n*.pem\n/fixtures/\ngenerated/**/*.rs\n!generated/keep.rs\n");
+
+        assert!(ignore.is_ignored(&root.join(".env"), false));
+        assert!(ignore.is_ignored(&root.join("config/.env"), false));
+        assert!(ignore.is_ignored(Path::new("certs/server.pem"), false));
+        assert!(ignore.is_ignored(&root.join("fixtures"), true));
+        assert!(!ignore.is_ignored(&root.join("fixtures"), false));
+        assert!(ignore.is_ignored(&root.join("fixtures/data.json"), false));
+        assert!(!ignore.is_ignored(&root.join("src/fixtures"), true));
+        assert!(ignore.is_ignored(&root.join("generated/api.rs"), false));
+        assert!(ignore.is_ignored(&root.join("generated/v1/api.rs"), false));
+        assert!(!ignore.is_ignored(&root.join("generated/keep.rs"), false));
+        assert!(!ignore.is_ignored(&root.join("src/main.rs"), false));
+        assert!(!ignore.is_ignored(Path::new("/elsewhere/.env"), false));
+    }
+}
@@ -0,0 +1,69 @@
+/// Editor commands that hand the code around the cursor to the model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodeCommand {
+    Explain,
+    AddDocComments,
+    WriteTests,
+    Refactor,
+}
+
+/// The code a command is about: which file and lines, and what they hold
+pub struct CodeRegion<'a> {
+    pub path: &'a str,
+    pub first_line: usize, // 1-based, inclusive
+    pub last_line: usize,
+    pub language: &'a str,
+    pub code: &'a str,
+}
+
+impl CodeCommand {
+    pub fn label(self) -> &'static str {
+        match self {
+            CodeCommand::Explain => "Explain",
+            CodeCommand::AddDocComments => "Add doc comments to",
+            CodeCommand::WriteTests => "Write tests for",
+            CodeCommand::Refactor => "Refactor",
+        }
+    }
+
+    /// Whether the model changes files, as opposed to only answering in the chat
+    pub fn edits(self) -> bool {
+        self != CodeCommand::Explain
+    }
+
+    pub fn prompt(self, region: &CodeRegion) -> String {
+        let task = match self {
+            CodeCommand::Explain => "Explain what this code does and how, step by step. Point out anything surprising or likely to be a bug. Don't change any files.",
+            CodeCommand::AddDocComments => "Add doc comments to this code in the style the file already uses. Change nothing but comments.",
+            CodeCommand::WriteTests => "Write tests for this code, placed and written the way the project's existing tests are. Cover the edge cases, not just the happy path.",
+            CodeCommand::Refactor => "Refactor this code to be clearer and simpler without changing what it does.",
+        };
+        let mut prompt = format!(
+            "{}\n\n{}, lines {}-{} ({}):\n```\n{}\n```",
+            task,
+            region.path,
+            region.first_line,
+            region.last_line,
+            region.language,
+            region.code.trim_end()
+        );
+        if self.edits() {
+            prompt.push_str("\n\nMake the change with replace_in_file or apply_patch and leave the rest of the file as it is; the user reviews it as a diff before anything is written.");
+        }
+        prompt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_command_prompt() {
+        let region = CodeRegion { path: "src/lib.rs", first_line: 3, last_line: 5, language: "Rust", code: "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n" };
+        let prompt = CodeCommand::Refactor.prompt(&region);
+        assert!(prompt.contains("src/lib.rs, lines 3-5 (Rust):\n```\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n```"));
+        assert!(prompt.contains("replace_in_file"));
+        assert!(!CodeCommand::Explain.prompt(&region).contains("replace_in_file"));
+    }
+}
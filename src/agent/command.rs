@@ -0,0 +1,259 @@
+use super::AgentResponse;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How long and how loud an agent command may be
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CommandLimits {
+    pub timeout_secs: u64,     // The command is killed after this long
+    pub max_output_kb: usize,  // Captured stdout plus stderr; the rest is dropped
+}
+
+impl Default for CommandLimits {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 120,
+            max_output_kb: 64,
+        }
+    }
+}
+
+/// Shared with whoever runs the executor: output lines as they arrive, and a switch to kill the command
+#[derive(Debug, Clone, Default)]
+pub struct CommandMonitor {
+    lines: Arc<Mutex<Vec<String>>>,
+    kill: Arc<AtomicBool>,
+}
+
+impl CommandMonitor {
+    /// Lines printed since the last call
+    pub fn take_lines(&self) -> Vec<String> {
+        self.lines.lock().map(|mut lines| std::mem::take(&mut *lines)).unwrap_or_default()
+    }
+
+    /// Kill the running command; the next one starts normally
+    pub fn kill(&self) {
+        self.kill.store(true, Ordering::Relaxed);
+    }
+
+    fn push(&self, line: String) {
+        if let Ok(mut lines) = self.lines.lock() {
+            lines.push(line);
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+    pub timed_out: bool,
+    pub killed: bool,
+    pub truncated: bool,
+}
+
+impl CommandOutput {
+    pub fn into_response(self, command: &str, limits: &CommandLimits) -> AgentResponse {
+        let mut output = if self.stderr.is_empty() {
+            self.stdout
+        } else {
+            format!("STDOUT:\n{}\n\nSTDERR:\n{}", self.stdout, self.stderr)
+        };
+        if self.truncated {
+            output.push_str(&format!("\n[output truncated at {} KB]", limits.max_output_kb));
+        }
+        if self.timed_out {
+            AgentResponse::error(format!("Command timed out after {}s: {}", limits.timeout_secs, command), output)
+        } else if self.killed {
+            AgentResponse::error(format!("Command killed by the user: {}", command), output)
+        } else if self.success {
+            AgentResponse::success(format!("Command executed successfully: {}", command), Some(output))
+        } else {
+            AgentResponse::error(format!("Command failed: {}", command), output)
+        }
+    }
+}
+
+/// Run `command` through the shell in a process group of its own, so a timeout or a kill stops
+/// everything it started. Blocks the calling thread; stdout and stderr are read on two threads
+pub fn run(
+    command: &str,
+    working_dir: &Path,
+    limits: &CommandLimits,
+    monitor: Option<&CommandMonitor>,
+) -> std::io::Result<CommandOutput> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", command]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", command]);
+        cmd
+    };
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let mut child = cmd
+        .current_dir(working_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(monitor) = monitor {
+        monitor.kill.store(false, Ordering::Relaxed);
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    if let Some(stdout) = child.stdout.take() {
+        read_lines(stdout, Stream::Stdout, limits.max_output_kb * 1024, sender.clone());
+    }
+    if let Some(stderr) = child.stderr.take() {
+        read_lines(stderr, Stream::Stderr, limits.max_output_kb * 1024, sender);
+    }
+    let mut output = CommandOutput::default();
+    let mut budget = limits.max_output_kb * 1024;
+    let deadline = Instant::now() + Duration::from_secs(limits.timeout_secs);
+
+    loop {
+        match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok((stream, line)) => {
+                let captured = match stream {
+                    Stream::Stdout => &mut output.stdout,
+                    Stream::Stderr => &mut output.stderr,
+                };
+                capture(line, captured, &mut budget, &mut output.truncated, monitor);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break, // Both streams closed
+        }
+        if Instant::now() >= deadline {
+            output.timed_out = true;
+            break;
+        }
+        if monitor.is_some_and(|monitor| monitor.kill.load(Ordering::Relaxed)) {
+            output.killed = true;
+            break;
+        }
+    }
+
+    if output.timed_out || output.killed {
+        kill_group(&mut child);
+        let _ = child.wait();
+    } else {
+        output.success = child.wait()?.success();
+    }
+    Ok(output)
+}
+
+#[derive(Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Send each line of `reader` as it arrives, without its line ending; bytes that are not UTF-8
+/// are replaced rather than ending the stream, so the pipe is always drained. A line is held to
+/// `max_line` bytes: a longer one is sent cut off there and the rest of it is read and dropped
+fn read_lines<R: Read + Send + 'static>(reader: R, stream: Stream, max_line: usize, sender: mpsc::Sender<(Stream, String)>) {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        let mut cut = false; // The current line was already sent cut off
+        loop {
+            let chunk = match reader.fill_buf() {
+                Ok(chunk) if !chunk.is_empty() => chunk,
+                _ => break,
+            };
+            let end = chunk.iter().position(|&byte| byte == b'\n');
+            let read = end.map_or(chunk.len(), |end| end + 1);
+            if !cut {
+                let text = &chunk[..end.unwrap_or(chunk.len())];
+                line.extend_from_slice(&text[..text.len().min(max_line - line.len())]);
+            }
+            reader.consume(read);
+            let full = !cut && line.len() >= max_line;
+            if end.is_some() || full {
+                if !cut {
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                    if sender.send((stream, String::from_utf8_lossy(&line).into_owned())).is_err() {
+                        return;
+                    }
+                }
+                line.clear();
+                cut = full && end.is_none();
+            }
+        }
+        if !line.is_empty() {
+            let _ = sender.send((stream, String::from_utf8_lossy(&line).into_owned()));
+        }
+    });
+}
+
+/// Kill the shell and everything it started
+fn kill_group(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pid) = i32::try_from(child.id()) {
+        // SAFETY: signals the process group created for this child at spawn
+        unsafe {
+            libc::kill(-pid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+}
+
+/// Keep a line while the output budget lasts
+fn capture(line: String, captured: &mut String, budget: &mut usize, truncated: &mut bool, monitor: Option<&CommandMonitor>) {
+    if line.len() < *budget {
+        *budget -= line.len() + 1;
+        captured.push_str(&line);
+        captured.push('\n');
+        if let Some(monitor) = monitor {
+            monitor.push(line);
+        }
+    } else {
+        // Keep draining, or the command would block on a full pipe
+        *truncated = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command() {
+        let dir = std::env::temp_dir();
+        let limits = CommandLimits { timeout_secs: 5, max_output_kb: 1 };
+        let monitor = CommandMonitor::default();
+
+        let output = run("echo out; echo err >&2", &dir, &limits, Some(&monitor)).unwrap();
+        assert!(output.success);
+        assert_eq!((output.stdout.as_str(), output.stderr.as_str()), ("out\n", "err\n"));
+        assert_eq!(monitor.take_lines().len(), 2);
+
+        let output = run("yes | head -c 4000", &dir, &limits, None).unwrap();
+        assert!(output.truncated && output.stdout.len() <= 1024);
+
+        // One endless line is cut off instead of held in memory, and the pipe is still drained
+        let output = run("yes | tr -d '\\n' | head -c 3000000; echo; echo after", &dir, &limits, None).unwrap();
+        assert!(output.success && output.truncated && output.stdout.len() <= 1024);
+
+        let limits = CommandLimits { timeout_secs: 1, ..limits };
+        let response = run("sleep 10", &dir, &limits, None).unwrap().into_response("sleep 10", &limits);
+        assert_eq!(response.message, "Command timed out after 1s: sleep 10");
+
+        // Invalid UTF-8 is replaced, not the end of the output
+        let output = run("printf 'a\\377b\\n'; echo done", &dir, &limits, None).unwrap();
+        assert_eq!(output.stdout, "a\u{fffd}b\ndone\n");
+    }
+}
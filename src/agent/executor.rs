@@ -1,19 +1,50 @@
 use super::{AgentAction, AgentExecutor, AgentResponse, AgentCapabilities};
-use anyhow::Result;
+use super::aiignore::AiIgnore;
+use super::command::{self, CommandLimits, CommandMonitor};
+use super::git;
+use super::patch;
+use super::policy::AgentPolicy;
+use super::sandbox::Sandbox;
+use super::scratchpad::Scratchpad;
+use super::search;
+use super::test_runner;
+use super::turn_summary;
+use super::undo::UndoLog;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Reads of one batch running at the same time
+const MAX_CONCURRENT_READS: usize = 8;
 
 pub struct DefaultAgentExecutor {
     pub capabilities: AgentCapabilities,
     pub current_directory: PathBuf,
+    pub ai_ignore: AiIgnore,
+    pub undo_log: Option<UndoLog>, // Files are snapshotted here before they are overwritten or deleted
+    pub command_limits: CommandLimits,
+    pub command_monitor: Option<CommandMonitor>, // Receives command output as it is printed
+    pub policy: AgentPolicy, // The project's .i4z/agent.toml
+    pub test_command: Option<String>, // Run by RunTests; None detects it from the project
+    pub scratchpad: Option<Scratchpad>, // Where WriteNote and ReadNotes keep the agent's notes
+    changed: BTreeSet<PathBuf>, // Files the agent changed since its last commit, the only ones GitCommit stages
 }
 
 impl DefaultAgentExecutor {
     pub fn new(current_directory: PathBuf) -> Self {
         Self {
             capabilities: AgentCapabilities::default(),
+            ai_ignore: AiIgnore::load(&current_directory),
+            policy: AgentPolicy::load(&current_directory),
             current_directory,
+            undo_log: None,
+            command_limits: CommandLimits::default(),
+            command_monitor: None,
+            test_command: None,
+            scratchpad: None,
+            changed: BTreeSet::new(),
         }
     }
 
@@ -22,6 +53,126 @@ impl DefaultAgentExecutor {
         self
     }
 
+    pub fn with_command_limits(mut self, limits: CommandLimits) -> Self {
+        self.command_limits = limits;
+        self
+    }
+
+    pub fn with_command_monitor(mut self, monitor: CommandMonitor) -> Self {
+        self.command_monitor = Some(monitor);
+        self
+    }
+
+    pub fn with_test_command(mut self, test_command: Option<String>) -> Self {
+        self.test_command = test_command;
+        self
+    }
+
+    /// The configured test command, or the one the project's manifest suggests
+    fn test_command(&self) -> Option<String> {
+        self.test_command
+            .clone()
+            .or_else(|| test_runner::detect_command(&self.current_directory).map(str::to_string))
+    }
+
+    pub fn with_scratchpad(mut self, scratchpad: Scratchpad) -> Self {
+        self.scratchpad = Some(scratchpad);
+        self
+    }
+
+    pub fn with_undo_log(mut self, undo_log: UndoLog) -> Self {
+        self.undo_log = Some(undo_log);
+        self
+    }
+
+    /// The files an action is about to overwrite, create or delete, with a description for the undo log
+    fn undo_target(&self, action: &AgentAction) -> Option<(String, Vec<PathBuf>)> {
+        let (description, paths) = match action {
+            AgentAction::WriteFile { path, .. } => (format!("Write {}", path.display()), vec![path]),
+            AgentAction::ReplaceInFile { path, .. } | AgentAction::ApplyPatch { path, .. } | AgentAction::AppendToFile { path, .. } => {
+                (format!("Edit {}", path.display()), vec![path])
+            }
+            AgentAction::DeleteFile { path } => (format!("Delete {}", path.display()), vec![path]),
+            AgentAction::MoveFile { from, to } => (format!("Move {} to {}", from.display(), to.display()), vec![from, to]),
+            AgentAction::CopyFile { from, to } => (format!("Copy {} to {}", from.display(), to.display()), vec![to]),
+            _ => return None,
+        };
+        Some((description, paths.into_iter().map(|path| self.resolve_path(path)).collect()))
+    }
+
+    /// Resolved paths an action touches, each with whether the action changes it
+    fn touched_paths(&self, action: &AgentAction) -> Vec<(PathBuf, bool)> {
+        match action {
+            AgentAction::ReadFile { path } | AgentAction::ListDirectory { path } | AgentAction::GetFileInfo { path } => {
+                vec![(self.resolve_path(path), false)]
+            }
+            AgentAction::SearchFiles { directory, .. } => {
+                vec![(directory.as_ref().map_or_else(|| self.current_directory.clone(), |dir| self.resolve_path(dir)), false)]
+            }
+            AgentAction::WriteFile { path, .. }
+            | AgentAction::ReplaceInFile { path, .. }
+            | AgentAction::ApplyPatch { path, .. }
+            | AgentAction::AppendToFile { path, .. }
+            | AgentAction::DeleteFile { path }
+            | AgentAction::CreateDirectory { path } => vec![(self.resolve_path(path), true)],
+            AgentAction::MoveFile { from, to } => vec![(self.resolve_path(from), true), (self.resolve_path(to), true)],
+            AgentAction::CopyFile { from, to } => vec![(self.resolve_path(from), false), (self.resolve_path(to), true)],
+            AgentAction::ExecuteCommand { working_dir, .. } => {
+                working_dir.iter().map(|dir| (self.resolve_path(dir), false)).collect()
+            }
+            AgentAction::GitDiff { path, .. } => path.iter().map(|path| (self.resolve_path(path), false)).collect(),
+            AgentAction::GitCommit { .. } => self.changed.iter().map(|path| (self.current_directory.join(path), true)).collect(),
+            AgentAction::RunTests { .. }
+            | AgentAction::GitStatus {}
+            | AgentAction::WriteNote { .. }
+            | AgentAction::ReadNotes {} => Vec::new(),
+        }
+    }
+
+    /// `path` relative to the project root when inside it, as the policy's globs expect
+    fn relative_path(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.current_directory).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+    }
+
+    /// What a modifying action would do, without touching the filesystem; None for reads, which run as usual
+    fn simulate(&self, action: &AgentAction) -> Option<AgentResponse> {
+        let (message, data) = match action {
+            AgentAction::WriteFile { path, .. }
+            | AgentAction::ReplaceInFile { path, .. }
+            | AgentAction::ApplyPatch { path, .. }
+            | AgentAction::AppendToFile { path, .. } => (
+                format!("Dry run: would write {}", self.resolve_path(path).display()),
+                turn_summary::preview_diff(action, &self.current_directory).map(|(_, diff)| diff),
+            ),
+            AgentAction::DeleteFile { path } => (format!("Dry run: would delete {}", self.resolve_path(path).display()), None),
+            AgentAction::MoveFile { from, to } => (
+                format!("Dry run: would move {} to {}", self.resolve_path(from).display(), self.resolve_path(to).display()),
+                None,
+            ),
+            AgentAction::CopyFile { from, to } => (
+                format!("Dry run: would copy {} to {}", self.resolve_path(from).display(), self.resolve_path(to).display()),
+                None,
+            ),
+            AgentAction::CreateDirectory { path } => {
+                (format!("Dry run: would create directory {}", self.resolve_path(path).display()), None)
+            }
+            AgentAction::ExecuteCommand { command, working_dir } => {
+                let dir = working_dir.as_ref().map_or_else(|| self.current_directory.clone(), |dir| self.resolve_path(dir));
+                (format!("Dry run: would run `{}` in {}", command, dir.display()), None)
+            }
+            AgentAction::RunTests { filter } => {
+                let command = self.test_command().unwrap_or_else(|| "the tests".to_string());
+                (format!("Dry run: would run `{}`", test_runner::with_filter(&command, filter.as_deref()).unwrap_or(command)), None)
+            }
+            AgentAction::GitCommit { message } => {
+                let files: Vec<String> = self.changed.iter().map(|path| path.display().to_string()).collect();
+                (format!("Dry run: would commit the agent's changes as \"{}\"", message), Some(files.join("\n")))
+            }
+            _ => return None,
+        };
+        Some(AgentResponse::success(message, data))
+    }
+
     fn is_path_restricted(&self, path: &PathBuf) -> bool {
         for restricted in &self.capabilities.restricted_paths {
             if path.starts_with(restricted) {
@@ -31,6 +182,18 @@ impl DefaultAgentExecutor {
         false
     }
 
+    /// Whether the model may not see `path`, relative to the project root, in search results or diffs
+    fn is_hidden(&self, path: &Path) -> bool {
+        self.ai_ignore.is_ignored(path, self.current_directory.join(path).is_dir()) || self.policy.denies(path)
+    }
+
+    fn ignored_response(&self, path: &std::path::Path) -> AgentResponse {
+        AgentResponse::error(
+            format!("Path is excluded from AI context: {}", path.display()),
+            "Matched a rule in .aiignore".to_string(),
+        )
+    }
+
     fn resolve_path(&self, path: &PathBuf) -> PathBuf {
         if path.is_absolute() {
             path.clone()
@@ -38,20 +201,57 @@ impl DefaultAgentExecutor {
             self.current_directory.join(path)
         }
     }
-}
 
-impl AgentExecutor for DefaultAgentExecutor {
-    fn execute_action(&mut self, action: AgentAction) -> Result<AgentResponse> {
-        if !self.is_safe_action(&action) {
-            return Ok(AgentResponse::error(
+    /// Capabilities, sandbox and policy checks every action passes before it runs. Gives the paths it
+    /// touches relative to the project root, or the response refusing it
+    fn admit(&self, action: &AgentAction) -> Result<Vec<(PathBuf, bool)>, AgentResponse> {
+        if !self.is_safe_action(action) {
+            return Err(AgentResponse::error(
                 "Action not permitted".to_string(),
                 "This action is restricted by the current capabilities".to_string(),
             ));
         }
 
+        let paths = self.touched_paths(action);
+        if self.capabilities.sandbox {
+            let sandbox = Sandbox::new(&self.current_directory);
+            if let Some((path, _)) = paths.iter().find(|(path, _)| !sandbox.contains(path)) {
+                return Err(AgentResponse::error(
+                    format!("Path is outside the workspace: {}", path.display()),
+                    format!("The agent sandbox keeps paths inside {}", self.current_directory.display()),
+                ));
+            }
+        }
+
+        let paths: Vec<(PathBuf, bool)> = paths.iter().map(|(path, modifying)| (self.relative_path(path), *modifying)).collect();
+        // Git's config and hooks run programs, which would get around every other check
+        if let Some((path, _)) = paths.iter().find(|(path, modifying)| *modifying && path.components().any(|part| part.as_os_str() == ".git")) {
+            return Err(AgentResponse::error(
+                format!("Path is inside a git directory: {}", path.display()),
+                "The agent may not change anything under .git/".to_string(),
+            ));
+        }
+        if let Err(reason) = self.policy.check(action, &paths) {
+            return Err(AgentResponse::error("Blocked by the project's agent policy".to_string(), reason));
+        }
+        Ok(paths)
+    }
+
+    /// Run a read-only action. It needs no `&mut self`, so several can run at once
+    pub fn execute_read(&self, action: AgentAction) -> Result<AgentResponse> {
+        if let Err(refused) = self.admit(&action) {
+            return Ok(refused);
+        }
+        self.read(action)
+    }
+
+    fn read(&self, action: AgentAction) -> Result<AgentResponse> {
         match action {
             AgentAction::ReadFile { path } => {
                 let resolved_path = self.resolve_path(&path);
+                if self.ai_ignore.is_ignored(&resolved_path, resolved_path.is_dir()) {
+                    return Ok(self.ignored_response(&resolved_path));
+                }
                 match fs::read_to_string(&resolved_path) {
                     Ok(content) => Ok(AgentResponse::success(
                         format!("Successfully read file: {}", resolved_path.display()),
@@ -64,6 +264,112 @@ impl AgentExecutor for DefaultAgentExecutor {
                 }
             }
 
+            AgentAction::SearchFiles { pattern, directory, in_contents } => {
+                let search_dir = directory.unwrap_or_else(|| self.current_directory.clone());
+                let resolved_dir = self.resolve_path(&search_dir);
+                if !resolved_dir.is_dir() {
+                    return Ok(AgentResponse::error(
+                        format!("Failed to search: {}", resolved_dir.display()),
+                        "Not a directory".to_string(),
+                    ));
+                }
+
+                let results = search::search(&resolved_dir, &self.current_directory, &pattern, in_contents, &self.ai_ignore, |path| {
+                    self.policy.denies(path)
+                });
+                let mut message = format!("Found {} matches for pattern '{}'", results.matches.len(), pattern);
+                if results.truncated {
+                    message.push_str(&format!(" (stopped at {}; narrow the pattern or directory)", search::MAX_RESULTS));
+                }
+                Ok(AgentResponse::success(message, Some(results.matches.join("\n"))))
+            }
+
+            AgentAction::ListDirectory { path } => {
+                let resolved_path = self.resolve_path(&path);
+                match fs::read_dir(&resolved_path) {
+                    Ok(entries) => {
+                        let mut items = Vec::new();
+                        for entry in entries.flatten() {
+                            let path = entry.path();
+                            if self.is_hidden(&self.relative_path(&path)) {
+                                continue;
+                            }
+                            let file_type = if path.is_dir() { "DIR" } else { "FILE" };
+                            let name = path.file_name()
+                                .and_then(|n| n.to_str())
+                                .unwrap_or("Unknown");
+                            items.push(format!("{:<6} {}", file_type, name));
+                        }
+                        items.sort();
+
+                        Ok(AgentResponse::success(
+                            format!("Listed directory: {}", resolved_path.display()),
+                            Some(items.join("\n")),
+                        ))
+                    }
+                    Err(e) => Ok(AgentResponse::error(
+                        format!("Failed to list directory: {}", resolved_path.display()),
+                        e.to_string(),
+                    )),
+                }
+            }
+
+            AgentAction::GetFileInfo { path } => {
+                let resolved_path = self.resolve_path(&path);
+                if self.ai_ignore.is_ignored(&resolved_path, resolved_path.is_dir()) {
+                    return Ok(self.ignored_response(&resolved_path));
+                }
+                match fs::metadata(&resolved_path) {
+                    Ok(metadata) => {
+                        let file_type = if metadata.is_dir() {
+                            "Directory"
+                        } else if metadata.is_file() {
+                            "File"
+                        } else {
+                            "Other"
+                        };
+
+                        let size = if metadata.is_file() {
+                            format!("{} bytes", metadata.len())
+                        } else {
+                            "N/A".to_string()
+                        };
+
+                        let info = format!(
+                            "Path: {}\nType: {}\nSize: {}\nReadonly: {}",
+                            resolved_path.display(),
+                            file_type,
+                            size,
+                            metadata.permissions().readonly()
+                        );
+
+                        Ok(AgentResponse::success(
+                            format!("File info for: {}", resolved_path.display()),
+                            Some(info),
+                        ))
+                    }
+                    Err(e) => Ok(AgentResponse::error(
+                        format!("Failed to get file info: {}", resolved_path.display()),
+                        e.to_string(),
+                    )),
+                }
+            }
+
+            other => Ok(AgentResponse::error(
+                "Not a read-only action".to_string(),
+                format!("{:?} changes the project", other),
+            )),
+        }
+    }
+
+    /// Run an action that was admitted, with the paths `admit` gave for it, and snapshotted
+    fn perform(&mut self, action: AgentAction, paths: &[(PathBuf, bool)]) -> Result<AgentResponse> {
+        match action {
+            AgentAction::ReadFile { .. }
+            | AgentAction::SearchFiles { .. }
+            | AgentAction::ListDirectory { .. }
+            | AgentAction::GetFileInfo { .. } => self.read(action),
+
             AgentAction::WriteFile { path, content } => {
                 let resolved_path = self.resolve_path(&path);
                 
@@ -130,40 +436,8 @@ impl AgentExecutor for DefaultAgentExecutor {
 
             AgentAction::ExecuteCommand { command, working_dir } => {
                 let working_dir = working_dir.unwrap_or_else(|| self.current_directory.clone());
-                let mut cmd = if cfg!(target_os = "windows") {
-                    let mut cmd = Command::new("cmd");
-                    cmd.args(["/C", &command]);
-                    cmd
-                } else {
-                    let mut cmd = Command::new("sh");
-                    cmd.args(["-c", &command]);
-                    cmd
-                };
-
-                cmd.current_dir(&working_dir);
-
-                match cmd.output() {
-                    Ok(output) => {
-                        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-                        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                        let combined_output = if stderr.is_empty() {
-                            stdout
-                        } else {
-                            format!("STDOUT:\n{}\n\nSTDERR:\n{}", stdout, stderr)
-                        };
-
-                        if output.status.success() {
-                            Ok(AgentResponse::success(
-                                format!("Command executed successfully: {}", command),
-                                Some(combined_output),
-                            ))
-                        } else {
-                            Ok(AgentResponse::error(
-                                format!("Command failed: {}", command),
-                                combined_output,
-                            ))
-                        }
-                    }
+                match command::run(&command, &working_dir, &self.command_limits, self.command_monitor.as_ref()) {
+                    Ok(output) => Ok(output.into_response(&command, &self.command_limits)),
                     Err(e) => Ok(AgentResponse::error(
                         format!("Failed to execute command: {}", command),
                         e.to_string(),
@@ -171,28 +445,64 @@ impl AgentExecutor for DefaultAgentExecutor {
                 }
             }
 
-            AgentAction::SearchFiles { pattern, directory } => {
-                let search_dir = directory.unwrap_or_else(|| self.current_directory.clone());
-                let resolved_dir = self.resolve_path(&search_dir);
+            AgentAction::RunTests { filter } => {
+                let Some(test_command) = self.test_command() else {
+                    return Ok(AgentResponse::error(
+                        "No test command for this project".to_string(),
+                        "Set `test_command` in the config, or run the tests with execute_command".to_string(),
+                    ));
+                };
+                let command = match test_runner::with_filter(&test_command, filter.as_deref()) {
+                    Ok(command) => command,
+                    Err(reason) => return Ok(AgentResponse::error("Invalid test filter".to_string(), reason)),
+                };
+                match command::run(&command, &self.current_directory, &self.command_limits, self.command_monitor.as_ref()) {
+                    Ok(output) => Ok(test_runner::report(output, &command, &self.command_limits)),
+                    Err(e) => Ok(AgentResponse::error(format!("Failed to run tests: {}", command), e.to_string())),
+                }
+            }
 
-                let mut matches = Vec::new();
-                if let Ok(entries) = fs::read_dir(&resolved_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                            if filename.contains(&pattern) {
-                                matches.push(path.display().to_string());
-                            }
-                        }
-                    }
+            AgentAction::WriteNote { note } => {
+                let Some(scratchpad) = &self.scratchpad else {
+                    return Ok(AgentResponse::error("Failed to write note".to_string(), "No scratchpad in this session".to_string()));
+                };
+                match scratchpad.append(&note) {
+                    Ok(count) => Ok(AgentResponse::success(format!("Noted ({} notes)", count), None)),
+                    Err(e) => Ok(AgentResponse::error("Failed to write note".to_string(), e.to_string())),
                 }
+            }
 
-                Ok(AgentResponse::success(
-                    format!("Found {} matches for pattern '{}'", matches.len(), pattern),
-                    Some(matches.join("\n")),
-                ))
+            AgentAction::ReadNotes {} => {
+                let notes = self.scratchpad.as_ref().map(Scratchpad::recent).unwrap_or_default();
+                if notes.is_empty() {
+                    Ok(AgentResponse::success("No notes yet".to_string(), None))
+                } else {
+                    Ok(AgentResponse::success("Notes".to_string(), Some(notes)))
+                }
+            }
+
+            AgentAction::GitStatus {} => match git::status(&self.current_directory, |path| self.is_hidden(path)) {
+                Ok(status) => Ok(AgentResponse::success("Git status".to_string(), Some(status))),
+                Err(e) => Ok(AgentResponse::error("Failed to get git status".to_string(), e.to_string())),
+            },
+
+            AgentAction::GitDiff { path, staged } => {
+                let path = path.map(|path| self.relative_path(&self.resolve_path(&path)));
+                match git::diff(&self.current_directory, path.as_deref(), staged, |path| self.is_hidden(path)) {
+                    Ok(diff) if diff.is_empty() => Ok(AgentResponse::success("No changes".to_string(), None)),
+                    Ok(diff) => Ok(AgentResponse::success("Git diff".to_string(), Some(diff))),
+                    Err(e) => Ok(AgentResponse::error("Failed to get git diff".to_string(), e.to_string())),
+                }
             }
 
+            AgentAction::GitCommit { message } => match git::commit(&self.current_directory, &message, &paths.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>()) {
+                Ok(summary) => {
+                    self.changed.clear();
+                    Ok(AgentResponse::success(format!("Committed {}", summary), None))
+                }
+                Err(e) => Ok(AgentResponse::error("Failed to commit".to_string(), e.to_string())),
+            },
+
             AgentAction::ReplaceInFile { path, old, new } => {
                 let resolved_path = self.resolve_path(&path);
                 match fs::read_to_string(&resolved_path) {
@@ -216,71 +526,158 @@ impl AgentExecutor for DefaultAgentExecutor {
                 }
             }
 
-            AgentAction::ListDirectory { path } => {
+            AgentAction::ApplyPatch { path, unified_diff } => {
                 let resolved_path = self.resolve_path(&path);
-                match fs::read_dir(&resolved_path) {
-                    Ok(entries) => {
-                        let mut items = Vec::new();
-                        for entry in entries.flatten() {
-                            let path = entry.path();
-                            let file_type = if path.is_dir() { "DIR" } else { "FILE" };
-                            let name = path.file_name()
-                                .and_then(|n| n.to_str())
-                                .unwrap_or("Unknown");
-                            items.push(format!("{:<6} {}", file_type, name));
-                        }
-                        items.sort();
-
-                        Ok(AgentResponse::success(
-                            format!("Listed directory: {}", resolved_path.display()),
-                            Some(items.join("\n")),
+                let content = match fs::read_to_string(&resolved_path) {
+                    Ok(content) => content,
+                    Err(e) => {
+                        return Ok(AgentResponse::error(
+                            format!("Failed to read file: {}", resolved_path.display()),
+                            e.to_string(),
                         ))
                     }
+                };
+                // Nothing is written unless every hunk applies
+                match patch::apply(&content, &unified_diff) {
+                    Ok(patched) => match fs::write(&resolved_path, patched) {
+                        Ok(_) => Ok(AgentResponse::success(
+                            format!("Successfully patched: {}", resolved_path.display()),
+                            None,
+                        )),
+                        Err(e) => Ok(AgentResponse::error(
+                            format!("Failed to write file: {}", resolved_path.display()),
+                            e.to_string(),
+                        )),
+                    },
                     Err(e) => Ok(AgentResponse::error(
-                        format!("Failed to list directory: {}", resolved_path.display()),
+                        format!("Failed to patch file: {}", resolved_path.display()),
                         e.to_string(),
                     )),
                 }
             }
 
-            AgentAction::GetFileInfo { path } => {
-                let resolved_path = self.resolve_path(&path);
-                match fs::metadata(&resolved_path) {
-                    Ok(metadata) => {
-                        let file_type = if metadata.is_dir() {
-                            "Directory"
-                        } else if metadata.is_file() {
-                            "File"
-                        } else {
-                            "Other"
-                        };
-
-                        let size = if metadata.is_file() {
-                            format!("{} bytes", metadata.len())
-                        } else {
-                            "N/A".to_string()
-                        };
+            AgentAction::MoveFile { from, to } => {
+                let (from, to) = (self.resolve_path(&from), self.resolve_path(&to));
+                if let Some(ignored) = [&from, &to].into_iter().find(|path| self.ai_ignore.is_ignored(path, path.is_dir())) {
+                    return Ok(self.ignored_response(ignored));
+                }
+                if let Err(e) = check_destination(&to) {
+                    return Ok(AgentResponse::error(format!("Failed to move to: {}", to.display()), e.to_string()));
+                }
+                match fs::rename(&from, &to) {
+                    Ok(_) => Ok(AgentResponse::success(
+                        format!("Successfully moved {} to {}", from.display(), to.display()),
+                        None,
+                    )),
+                    Err(e) => Ok(AgentResponse::error(format!("Failed to move: {}", from.display()), e.to_string())),
+                }
+            }
 
-                        let info = format!(
-                            "Path: {}\nType: {}\nSize: {}\nReadonly: {}",
-                            resolved_path.display(),
-                            file_type,
-                            size,
-                            metadata.permissions().readonly()
-                        );
+            AgentAction::CopyFile { from, to } => {
+                let (from, to) = (self.resolve_path(&from), self.resolve_path(&to));
+                if let Some(ignored) = [&from, &to].into_iter().find(|path| self.ai_ignore.is_ignored(path, path.is_dir())) {
+                    return Ok(self.ignored_response(ignored));
+                }
+                if from.is_dir() {
+                    return Ok(AgentResponse::error(
+                        format!("Failed to copy: {}", from.display()),
+                        "Only files can be copied".to_string(),
+                    ));
+                }
+                if let Err(e) = check_destination(&to) {
+                    return Ok(AgentResponse::error(format!("Failed to copy to: {}", to.display()), e.to_string()));
+                }
+                match fs::copy(&from, &to) {
+                    Ok(_) => Ok(AgentResponse::success(
+                        format!("Successfully copied {} to {}", from.display(), to.display()),
+                        None,
+                    )),
+                    Err(e) => Ok(AgentResponse::error(format!("Failed to copy: {}", from.display()), e.to_string())),
+                }
+            }
 
-                        Ok(AgentResponse::success(
-                            format!("File info for: {}", resolved_path.display()),
-                            Some(info),
-                        ))
-                    }
+            AgentAction::AppendToFile { path, content } => {
+                let resolved_path = self.resolve_path(&path);
+                let appended = fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&resolved_path)
+                    .and_then(|mut file| file.write_all(content.as_bytes()));
+                match appended {
+                    Ok(_) => Ok(AgentResponse::success(
+                        format!("Successfully appended to: {}", resolved_path.display()),
+                        None,
+                    )),
                     Err(e) => Ok(AgentResponse::error(
-                        format!("Failed to get file info: {}", resolved_path.display()),
+                        format!("Failed to append to file: {}", resolved_path.display()),
                         e.to_string(),
                     )),
                 }
             }
+
+        }
+    }
+}
+
+impl AgentExecutor for DefaultAgentExecutor {
+    fn execute_action(&mut self, action: AgentAction) -> Result<AgentResponse> {
+        let paths = match self.admit(&action) {
+            Ok(paths) => paths,
+            Err(refused) => return Ok(refused),
+        };
+
+        if self.capabilities.dry_run {
+            if let Some(response) = self.simulate(&action) {
+                return Ok(response);
+            }
+        }
+
+        let committing = matches!(action, AgentAction::GitCommit { .. });
+        for (path, _) in paths.iter().filter(|(_, modifying)| *modifying && !committing) {
+            if let Err(reason) = self.policy.record_modification(path) {
+                return Ok(AgentResponse::error("Blocked by the project's agent policy".to_string(), reason));
+            }
+            self.changed.insert(path.clone());
+        }
+
+        if let Some((description, paths)) = self.undo_target(&action) {
+            if let Some(undo_log) = self.undo_log.as_mut() {
+                // Without a snapshot the change couldn't be taken back, so it doesn't happen
+                if let Err(e) = undo_log.record(description.clone(), &paths) {
+                    return Ok(AgentResponse::error(format!("Failed to snapshot for undo: {}", description), e.to_string()));
+                }
+            }
+        }
+
+        let response = self.perform(action, &paths);
+        if let Some(undo_log) = self.undo_log.as_mut() {
+            // Without the stamp undo just can't tell whether the files were changed since
+            let _ = undo_log.settle();
         }
+        response
+    }
+
+    /// Batches of nothing but reads run on several threads, so exploring a project with dozens of
+    /// reads doesn't wait on each one in turn
+    fn execute_batch(&mut self, actions: Vec<AgentAction>) -> Result<Vec<AgentResponse>> {
+        if actions.len() < 2 || !actions.iter().all(AgentAction::is_read_only) {
+            return actions.into_iter().map(|action| self.execute_action(action)).collect();
+        }
+        let executor = &*self;
+        let mut responses = Vec::with_capacity(actions.len());
+        for chunk in actions.chunks(MAX_CONCURRENT_READS) {
+            let results: Vec<Result<AgentResponse>> = std::thread::scope(|scope| {
+                let reads: Vec<_> = chunk.iter().map(|action| scope.spawn(move || executor.execute_read(action.clone()))).collect();
+                reads
+                    .into_iter()
+                    .map(|read| read.join().unwrap_or_else(|_| Err(anyhow!("Read panicked"))))
+                    .collect()
+            });
+            for result in results {
+                responses.push(result?);
+            }
+        }
+        Ok(responses)
     }
 
     fn is_safe_action(&self, action: &AgentAction) -> bool {
@@ -297,9 +694,12 @@ impl AgentExecutor for DefaultAgentExecutor {
             AgentAction::DeleteFile { path } => {
                 self.capabilities.can_modify_filesystem && !self.is_path_restricted(&self.resolve_path(path))
             }
-            AgentAction::ExecuteCommand { .. } => {
+            AgentAction::ExecuteCommand { .. } | AgentAction::RunTests { .. } => {
                 self.capabilities.can_execute_commands
             }
+            AgentAction::GitStatus {} | AgentAction::GitDiff { .. } => self.capabilities.can_use_git,
+            AgentAction::GitCommit { .. } => self.capabilities.can_use_git && self.capabilities.can_write_files,
+            AgentAction::WriteNote { .. } | AgentAction::ReadNotes {} => true,
             AgentAction::SearchFiles { directory, .. } => {
                 if let Some(dir) = directory {
                     !self.is_path_restricted(&self.resolve_path(dir))
@@ -307,9 +707,22 @@ impl AgentExecutor for DefaultAgentExecutor {
                     !self.is_path_restricted(&self.current_directory)
                 }
             }
-            AgentAction::ReplaceInFile { path, .. } => {
+            AgentAction::ReplaceInFile { path, .. }
+            | AgentAction::ApplyPatch { path, .. }
+            | AgentAction::AppendToFile { path, .. } => {
                 self.capabilities.can_write_files && !self.is_path_restricted(&self.resolve_path(path))
             }
+            AgentAction::MoveFile { from, to } => {
+                self.capabilities.can_modify_filesystem
+                    && !self.is_path_restricted(&self.resolve_path(from))
+                    && !self.is_path_restricted(&self.resolve_path(to))
+            }
+            AgentAction::CopyFile { from, to } => {
+                self.capabilities.can_read_files
+                    && self.capabilities.can_write_files
+                    && !self.is_path_restricted(&self.resolve_path(from))
+                    && !self.is_path_restricted(&self.resolve_path(to))
+            }
             AgentAction::ListDirectory { path } => {
                 self.capabilities.can_read_files && !self.is_path_restricted(&self.resolve_path(path))
             }
@@ -318,4 +731,15 @@ impl AgentExecutor for DefaultAgentExecutor {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Moves and copies never overwrite; missing parent directories are created
+fn check_destination(to: &Path) -> std::io::Result<()> {
+    if to.exists() {
+        return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, "Destination already exists"));
+    }
+    match to.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Diff lines sent to the model; the rest is left out with a note
+const MAX_DIFF_LINES: usize = 2000;
+/// Settings through which a repository could make git run programs of its own, turned off
+const SAFE_CONFIG: &[&str] = &["-c", "core.hooksPath=/dev/null", "-c", "core.fsmonitor=", "-c", "core.pager=cat"];
+
+/// Short status of the repository at `root`, with the branch on the first line, leaving out the
+/// paths `hidden` says the model may not see
+pub fn status(root: &Path, hidden: impl Fn(&Path) -> bool) -> Result<String> {
+    let status = git(root, &["status", "--short", "--branch"])?;
+    let kept: Vec<&str> = status
+        .lines()
+        .filter(|line| line.starts_with("##") || line.get(3..).is_none_or(|name| !hidden(Path::new(name.rsplit(" -> ").next().unwrap_or(name)))))
+        .collect();
+    Ok(kept.join("\n"))
+}
+
+/// Unstaged changes, or staged ones with `staged`, of the whole repository or one path, without
+/// the files `hidden` says the model may not see
+pub fn diff(root: &Path, path: Option<&Path>, staged: bool, hidden: impl Fn(&Path) -> bool) -> Result<String> {
+    let mut args = vec!["diff".to_string(), "--no-ext-diff".to_string(), "--no-textconv".to_string(), "--no-color".to_string()];
+    if staged {
+        args.push("--cached".to_string());
+    }
+    if let Some(path) = path {
+        args.push("--".to_string());
+        args.push(path.display().to_string());
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let diff = without_hidden(&git(root, &args)?, hidden);
+    let total = diff.lines().count();
+    if total <= MAX_DIFF_LINES {
+        return Ok(diff);
+    }
+    let mut kept: String = diff.lines().take(MAX_DIFF_LINES).collect::<Vec<_>>().join("\n");
+    kept.push_str(&format!("\n... ({} more lines; diff single paths to see them)", total - MAX_DIFF_LINES));
+    Ok(kept)
+}
+
+/// The file sections of `diff` whose file `hidden` doesn't rule out
+fn without_hidden(diff: &str, hidden: impl Fn(&Path) -> bool) -> String {
+    let mut kept = String::new();
+    let mut keep = true;
+    for line in diff.split_inclusive('\n') {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            let name = header.trim_end().rsplit_once(" b/").map_or(header.trim_end(), |(_, name)| name);
+            keep = !hidden(Path::new(name));
+        }
+        if keep {
+            kept.push_str(line);
+        }
+    }
+    kept
+}
+
+/// Stage the changes to `paths`, relative to `root`, and commit just those, leaving anything else
+/// in the working tree or the index alone; returns git's one-line summary
+pub fn commit(root: &Path, message: &str, paths: &[PathBuf]) -> Result<String> {
+    if message.trim().is_empty() {
+        return Err(anyhow!("The commit message is empty"));
+    }
+    // Files created and removed again are neither on disk nor known to git
+    let paths: Vec<String> = paths
+        .iter()
+        .filter(|path| root.join(path).exists() || git(root, &["ls-files", "--", &path.display().to_string()]).is_ok_and(|files| !files.is_empty()))
+        .map(|path| path.display().to_string())
+        .collect();
+    if paths.is_empty() {
+        return Err(anyhow!("Nothing to commit - the agent hasn't changed any files"));
+    }
+    let pathspec: Vec<&str> = paths.iter().map(String::as_str).collect();
+    git(root, &[&["add", "--all", "--"], pathspec.as_slice()].concat())?;
+    if git(root, &[&["diff", "--cached", "--quiet", "--"], pathspec.as_slice()].concat()).is_ok() {
+        return Err(anyhow!("Nothing to commit"));
+    }
+    let output = git(root, &[&["commit", "--no-verify", "--quiet", "--message", message, "--"], pathspec.as_slice()].concat())?;
+    let summary = git(root, &["log", "-1", "--format=%h %s"]).unwrap_or(output);
+    Ok(summary.trim().to_string())
+}
+
+/// Run git in `root`; a non-zero exit is an error carrying what git printed
+fn git(root: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(SAFE_CONFIG)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("Cannot run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_git_actions() {
+        let dir = std::env::temp_dir().join(format!("i4z-git-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        if git(&dir, &["init", "--quiet"]).is_err() {
+            return; // No git on this machine
+        }
+        git(&dir, &["config", "user.email", "agent@example.com"]).unwrap();
+        git(&dir, &["config", "user.name", "Agent"]).unwrap();
+
+        let none = |_: &Path| false;
+        fs::write(dir.join("notes.txt"), "one\n").unwrap();
+        fs::write(dir.join(".env"), "KEY=secret\n").unwrap();
+        assert!(status(&dir, none).unwrap().contains("?? notes.txt"));
+        assert!(!status(&dir, |path: &Path| path == Path::new(".env")).unwrap().contains(".env"));
+        let notes = [PathBuf::from("notes.txt")];
+        assert!(commit(&dir, "  ", &notes).is_err());
+        assert!(commit(&dir, "Add notes", &notes).unwrap().ends_with(" Add notes"));
+        assert!(commit(&dir, "Again", &notes).is_err());
+        assert!(commit(&dir, "Gone", &[PathBuf::from("never-there.txt")]).is_err());
+        assert!(status(&dir, none).unwrap().contains("?? .env")); // Not the agent's, so not committed
+
+        git(&dir, &["add", ".env"]).unwrap();
+        fs::write(dir.join("notes.txt"), "one\ntwo\n").unwrap();
+        assert!(diff(&dir, Some(Path::new("notes.txt")), false, none).unwrap().contains("+two"));
+        assert!(diff(&dir, None, true, none).unwrap().contains("secret"));
+        assert!(diff(&dir, None, true, |path: &Path| path == Path::new(".env")).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,28 @@
 pub mod actions;
+pub mod aiignore;
+pub mod code_commands;
+pub mod command;
 pub mod executor;
+pub mod git;
+pub mod orchestrator;
+pub mod patch;
+pub mod plan;
+pub mod policy;
+pub mod project_context;
+pub mod project_memory;
+pub mod references;
+pub mod retrieval;
+pub mod risk;
+pub mod sandbox;
+pub mod scratchpad;
+pub mod search;
+pub mod spend_guard;
+pub mod supervisor;
+pub mod task_queue;
+pub mod test_runner;
+pub mod tools;
+pub mod turn_summary;
+pub mod undo;
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
@@ -12,10 +35,43 @@ pub enum AgentAction {
     CreateDirectory { path: PathBuf },
     DeleteFile { path: PathBuf },
     ExecuteCommand { command: String, working_dir: Option<PathBuf> },
-    SearchFiles { pattern: String, directory: Option<PathBuf> },
+    SearchFiles {
+        pattern: String, // Regex
+        directory: Option<PathBuf>,
+        #[serde(default)]
+        in_contents: bool, // Match lines of files rather than file names
+    },
     ReplaceInFile { path: PathBuf, old: String, new: String },
+    ApplyPatch { path: PathBuf, unified_diff: String },
+    MoveFile { from: PathBuf, to: PathBuf },
+    CopyFile { from: PathBuf, to: PathBuf },
+    AppendToFile { path: PathBuf, content: String },
     ListDirectory { path: PathBuf },
     GetFileInfo { path: PathBuf },
+    RunTests {
+        #[serde(default)]
+        filter: Option<String>, // Only tests whose name matches
+    },
+    GitStatus {},
+    GitDiff {
+        #[serde(default)]
+        path: Option<PathBuf>, // The whole repository when missing
+        #[serde(default)]
+        staged: bool,
+    },
+    GitCommit { message: String }, // Stages every change first
+    WriteNote { note: String }, // To the session's scratchpad, not the project
+    ReadNotes {},
+}
+
+impl AgentAction {
+    /// Only looks at the project, so it can run alongside other reads
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            AgentAction::ReadFile { .. } | AgentAction::ListDirectory { .. } | AgentAction::GetFileInfo { .. } | AgentAction::SearchFiles { .. }
+        )
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +105,11 @@ impl AgentResponse {
 pub trait AgentExecutor {
     fn execute_action(&mut self, action: AgentAction) -> Result<AgentResponse>;
     fn is_safe_action(&self, action: &AgentAction) -> bool;
+
+    /// Run several actions, responses in the same order; executors may run read-only batches concurrently
+    fn execute_batch(&mut self, actions: Vec<AgentAction>) -> Result<Vec<AgentResponse>> {
+        actions.into_iter().map(|action| self.execute_action(action)).collect()
+    }
 }
 
 pub struct AgentCapabilities {
@@ -56,7 +117,10 @@ pub struct AgentCapabilities {
     pub can_write_files: bool,
     pub can_execute_commands: bool,
     pub can_modify_filesystem: bool,
+    pub can_use_git: bool, // Git status, diff and commit in the workspace repository
     pub restricted_paths: Vec<PathBuf>,
+    pub dry_run: bool, // Report what modifying actions would do instead of doing it
+    pub sandbox: bool, // Refuse paths that lead outside the workspace root, symlinks included
 }
 
 impl Default for AgentCapabilities {
@@ -66,12 +130,27 @@ impl Default for AgentCapabilities {
             can_write_files: true,
             can_execute_commands: false, // Disabled by default for safety
             can_modify_filesystem: true,
+            can_use_git: false,
             restricted_paths: vec![
                 PathBuf::from("/etc"),
                 PathBuf::from("/root"),
                 PathBuf::from("/sys"),
                 PathBuf::from("/proc"),
             ],
+            dry_run: false,
+            sandbox: true,
+        }
+    }
+}
+
+impl AgentCapabilities {
+    /// Reads and searches only: no writes, no filesystem changes, no commands
+    pub fn read_only() -> Self {
+        Self {
+            can_write_files: false,
+            can_execute_commands: false,
+            can_modify_filesystem: false,
+            ..Self::default()
         }
     }
 }
\ No newline at end of file
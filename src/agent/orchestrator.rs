@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+
+/// How far one agent run may go before it stops, even if the model wants to carry on
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoopBudget {
+    pub max_iterations: usize, // Model round trips per run
+    pub max_tokens: u32,       // Prompt and completion tokens per run, summed over its round trips
+}
+
+impl Default for LoopBudget {
+    fn default() -> Self {
+        Self {
+            max_iterations: 10,
+            max_tokens: 100_000,
+        }
+    }
+}
+
+/// Why a run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    Completed, // The model answered without calling a tool
+    IterationBudget,
+    TokenBudget,
+    Cancelled,
+    Rejected, // The user turned down the actions it wanted to take
+    Failed(String),
+}
+
+impl StopReason {
+    pub fn describe(&self, budget: &LoopBudget) -> String {
+        match self {
+            StopReason::Completed => "finished".to_string(),
+            StopReason::IterationBudget => format!("step budget of {} reached", budget.max_iterations),
+            StopReason::TokenBudget => format!("token budget of {} reached", budget.max_tokens),
+            StopReason::Cancelled => "cancelled".to_string(),
+            StopReason::Rejected => "actions rejected".to_string(),
+            StopReason::Failed(error) => format!("failed: {}", error),
+        }
+    }
+}
+
+/// One plan/act/observe run: the model is asked again after every batch of tool results until it
+/// answers in text or the budget runs out
+#[derive(Debug, Clone)]
+pub struct AgentRun {
+    pub budget: LoopBudget,
+    pub iterations: usize,
+    pub tokens: u32,
+}
+
+impl AgentRun {
+    pub fn new(budget: LoopBudget) -> Self {
+        Self { budget, iterations: 0, tokens: 0 }
+    }
+
+    /// Count a finished round trip to the model
+    pub fn record(&mut self, tokens: u32) {
+        self.iterations += 1;
+        self.tokens = self.tokens.saturating_add(tokens);
+    }
+
+    /// Why no further round trip may start, if a budget has run out
+    pub fn exhausted(&self) -> Option<StopReason> {
+        if self.iterations >= self.budget.max_iterations {
+            Some(StopReason::IterationBudget)
+        } else if self.tokens >= self.budget.max_tokens {
+            Some(StopReason::TokenBudget)
+        } else {
+            None
+        }
+    }
+
+    /// e.g. "step 3/10, 12.4k/100k tokens" for the round trip about to start
+    pub fn progress(&self) -> String {
+        format!(
+            "step {}/{}, {}/{} tokens",
+            self.iterations + 1,
+            self.budget.max_iterations,
+            compact(self.tokens),
+            compact(self.budget.max_tokens)
+        )
+    }
+}
+
+fn compact(tokens: u32) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        _ if tokens.is_multiple_of(1000) => format!("{}k", tokens / 1000),
+        _ => format!("{:.1}k", tokens as f64 / 1000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_run_budget() {
+        let mut run = AgentRun::new(LoopBudget { max_iterations: 3, max_tokens: 10_000 });
+        assert_eq!(run.progress(), "step 1/3, 0/10k tokens");
+        run.record(4_200);
+        assert_eq!(run.exhausted(), None);
+        assert_eq!(run.progress(), "step 2/3, 4.2k/10k tokens");
+        run.record(6_000);
+        assert_eq!(run.exhausted(), Some(StopReason::TokenBudget));
+
+        let mut run = AgentRun::new(LoopBudget { max_iterations: 2, max_tokens: 10_000 });
+        run.record(10);
+        run.record(10);
+        assert_eq!(run.exhausted(), Some(StopReason::IterationBudget));
+        assert_eq!(StopReason::IterationBudget.describe(&run.budget), "step budget of 2 reached");
+    }
+}
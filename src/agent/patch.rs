@@ -0,0 +1,198 @@
+use anyhow::{anyhow, Result};
+
+/// Context lines that may be dropped from either end of a hunk when it doesn't match as written
+const MAX_FUZZ: usize = 2;
+
+/// Whether a file line and a hunk line count as the same
+type Matcher = dyn Fn(&str, &str) -> bool;
+
+#[derive(Debug)]
+struct Hunk {
+    header: String,
+    old_start: usize, // 1-based, as in the header
+    lines: Vec<(char, String)>, // ' ' context, '-' removed, '+' added
+    no_newline: Option<char>, // Side a "\ No newline at end of file" marker was on: '-', '+' or ' ' for both
+}
+
+impl Hunk {
+    fn old(&self) -> Vec<&str> {
+        self.lines.iter().filter(|(kind, _)| *kind != '+').map(|(_, line)| line.as_str()).collect()
+    }
+
+    /// The hunk without up to `fuzz` context lines at its start and end
+    fn trimmed(&self, fuzz: usize) -> Option<Hunk> {
+        let leading = self.lines.iter().take_while(|(kind, _)| *kind == ' ').count().min(fuzz);
+        let trailing = self.lines.iter().rev().take_while(|(kind, _)| *kind == ' ').count().min(fuzz);
+        if leading + trailing >= self.lines.len() || (fuzz > 0 && leading == 0 && trailing == 0) {
+            return None;
+        }
+        Some(Hunk {
+            header: self.header.clone(),
+            old_start: self.old_start + leading,
+            lines: self.lines[leading..self.lines.len() - trailing].to_vec(),
+            no_newline: self.no_newline,
+        })
+    }
+}
+
+/// Apply a unified diff to `original` hunk by hunk. Hunks are looked for near the line their header
+/// names, first exactly, then ignoring whitespace, then with less surrounding context. The file keeps
+/// its CRLF line endings, and its final newline unless the diff says otherwise
+pub fn apply(original: &str, unified_diff: &str) -> Result<String> {
+    let hunks = parse(unified_diff)?;
+    let mut lines: Vec<String> = original.lines().map(str::to_string).collect();
+    let mut offset: isize = 0; // How far earlier hunks moved the lines below them
+    let mut from = 0; // Hunks apply in order and never overlap
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        // A hunk that only adds lines names the line it goes after
+        let anchor = if hunk.old().is_empty() { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+        let expected = (anchor as isize + offset).max(0) as usize;
+        let (position, hunk) = locate(&lines, hunk, expected, from).ok_or_else(|| {
+            anyhow!(
+                "Hunk {} of {} ({}) does not apply: its context was not found near line {}",
+                index + 1,
+                hunks.len(),
+                hunk.header,
+                expected + 1
+            )
+        })?;
+
+        let old_len = hunk.old().len();
+        let mut replacement = Vec::new();
+        let mut cursor = position;
+        for (kind, line) in &hunk.lines {
+            match kind {
+                // Keep the file's own text for context that only matched loosely
+                ' ' => {
+                    replacement.push(lines[cursor].clone());
+                    cursor += 1;
+                }
+                '-' => cursor += 1,
+                _ => replacement.push(line.clone()),
+            }
+        }
+        offset += replacement.len() as isize - old_len as isize;
+        from = position + replacement.len();
+        lines.splice(position..position + old_len, replacement);
+    }
+
+    let line_ending = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let final_newline = match hunks.iter().filter_map(|hunk| hunk.no_newline).next_back() {
+        Some('+' | ' ') => false,
+        Some(_) => true,
+        None => original.ends_with('\n') || original.is_empty(),
+    };
+    let mut patched = lines.join(line_ending);
+    if !lines.is_empty() && final_newline {
+        patched.push_str(line_ending);
+    }
+    Ok(patched)
+}
+
+fn parse(unified_diff: &str) -> Result<Vec<Hunk>> {
+    let mut hunks: Vec<Hunk> = Vec::new();
+    for line in unified_diff.lines() {
+        if line.starts_with("@@") {
+            let old_start = line
+                .trim_start_matches('@')
+                .split_whitespace()
+                .next()
+                .and_then(|range| range.strip_prefix('-'))
+                .and_then(|range| range.split(',').next())
+                .and_then(|start| start.parse().ok())
+                .ok_or_else(|| anyhow!("Malformed hunk header: {}", line))?;
+            hunks.push(Hunk { header: line.to_string(), old_start, lines: Vec::new(), no_newline: None });
+            continue;
+        }
+        let Some(hunk) = hunks.last_mut() else {
+            continue; // File headers and anything else before the first hunk
+        };
+        match line.chars().next() {
+            Some(kind @ (' ' | '-' | '+')) => hunk.lines.push((kind, line[1..].to_string())),
+            // "\ No newline at end of file", about the line before it
+            Some('\\') => hunk.no_newline = hunk.lines.last().map(|(kind, _)| *kind),
+            // Editors and models often strip the space off empty context lines
+            None => hunk.lines.push((' ', String::new())),
+            Some(_) => return Err(anyhow!("Unexpected line in hunk {}: {}", hunk.header, line)),
+        }
+    }
+    if hunks.is_empty() {
+        return Err(anyhow!("No hunks found in the patch"));
+    }
+    Ok(hunks)
+}
+
+/// Where the hunk applies, trying looser matches before giving up; returns the hunk as it matched
+fn locate(lines: &[String], hunk: &Hunk, expected: usize, from: usize) -> Option<(usize, Hunk)> {
+    let exact = |a: &str, b: &str| a == b;
+    let trailing = |a: &str, b: &str| a.trim_end() == b.trim_end();
+    let whitespace = |a: &str, b: &str| a.split_whitespace().eq(b.split_whitespace());
+    let matchers: [&Matcher; 3] = [&exact, &trailing, &whitespace];
+
+    for fuzz in 0..=MAX_FUZZ {
+        let Some(candidate) = hunk.trimmed(fuzz) else {
+            continue;
+        };
+        let expected = expected + (candidate.old_start - hunk.old_start);
+        for same in matchers {
+            if let Some(position) = find(lines, &candidate.old(), expected, from, same) {
+                return Some((position, candidate));
+            }
+        }
+    }
+    None
+}
+
+/// Position of `old` in `lines` at or after `from`, closest to `expected`
+fn find(lines: &[String], old: &[&str], expected: usize, from: usize, same: &Matcher) -> Option<usize> {
+    if old.is_empty() {
+        // Pure insertion: trust the header
+        return Some(expected.clamp(from, lines.len()));
+    }
+    let last = lines.len().checked_sub(old.len())?;
+    let matches = |position: usize| lines[position..position + old.len()].iter().zip(old).all(|(a, b)| same(a, b));
+    let expected = expected.clamp(from, last.max(from));
+    for distance in 0..=lines.len() {
+        let after = expected + distance;
+        if after <= last && matches(after) {
+            return Some(after);
+        }
+        match expected.checked_sub(distance) {
+            Some(before) if distance > 0 && before >= from && before <= last && matches(before) => return Some(before),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_patch() {
+        let original = "fn main() {\n    let x = 1;\n    println!(\"{}\", x);\n}\n\nfn helper() {\n    todo!()\n}\n";
+
+        // Line numbers are off by two and the context has lost its indentation
+        let patch = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -3,3 +3,3 @@\n fn main() {\n-let x = 1;\n+    let x = 2;\n     println!(\"{}\", x);\n@@ -8,3 +8,3 @@ fn helper\n fn helper() {\n-    todo!()\n+    42\n }\n";
+        assert_eq!(
+            apply(original, patch).unwrap(),
+            "fn main() {\n    let x = 2;\n    println!(\"{}\", x);\n}\n\nfn helper() {\n    42\n}\n"
+        );
+
+        // Only the outer context is stale, so it is dropped
+        let stale = "@@ -1,4 +1,4 @@\n fn start() {\n     let x = 1;\n-    println!(\"{}\", x);\n+    dbg!(x);\n }\n";
+        assert!(apply(original, stale).unwrap().contains("    dbg!(x);\n}\n\nfn helper"));
+
+        let error = apply(original, "@@ -2,1 +2,1 @@\n-    let y = 1;\n+    let y = 2;\n").unwrap_err();
+        assert_eq!(error.to_string(), "Hunk 1 of 1 (@@ -2,1 +2,1 @@) does not apply: its context was not found near line 2");
+        assert!(apply(original, "just some text").is_err());
+
+        // CRLF files stay CRLF, and the final newline follows the diff's markers
+        let crlf = "a\r\nb\r\n";
+        assert_eq!(apply(crlf, "@@ -1,2 +1,2 @@\n a\n-b\n+c\n").unwrap(), "a\r\nc\r\n");
+        assert_eq!(apply(crlf, "@@ -1,2 +1,2 @@\n a\n-b\n+c\n\\ No newline at end of file\n").unwrap(), "a\r\nc");
+        assert_eq!(apply("a\nb", "@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n").unwrap(), "a\nc\n");
+    }
+}
@@ -0,0 +1,55 @@
+use regex::Regex;
+
+/// Steps the agent proposes before it starts changing anything
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Plan {
+    pub steps: Vec<String>,
+}
+
+impl Plan {
+    /// The first numbered list of two or more steps in a reply ("1. ...", "2) ..."), if any
+    pub fn parse(response: &str) -> Option<Self> {
+        let step_regex = Regex::new(r"^\s*\d+[.)]\s+(.+)$").ok()?;
+        let mut steps = Vec::new();
+        for line in response.lines() {
+            match step_regex.captures(line).and_then(|cap| cap.get(1)) {
+                Some(step) => steps.push(step.as_str().trim().to_string()),
+                // A list ends at the first line that is not a step; blank lines may separate steps
+                None if steps.len() >= 2 && !line.trim().is_empty() => break,
+                None if !line.trim().is_empty() => steps.clear(),
+                None => {}
+            }
+        }
+        (steps.len() >= 2).then_some(Self { steps })
+    }
+
+    /// Message telling the model which steps the user agreed to
+    pub fn to_contract(&self) -> String {
+        let mut message = String::from(
+            "[Agreed plan] I reviewed your plan. Carry out exactly these steps, in this order, \
+             and ask before doing anything outside them:\n",
+        );
+        for (index, step) in self.steps.iter().enumerate() {
+            message.push_str(&format!("{}. {}\n", index + 1, step));
+        }
+        message.trim_end().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan() {
+        let reply = "Here is my plan:\n\n1. Read src/main.rs\n2) Add the flag\n\n3. Run the tests\n\nShall I go ahead?";
+        let plan = Plan::parse(reply).unwrap();
+        assert_eq!(plan.steps, ["Read src/main.rs", "Add the flag", "Run the tests"]);
+        assert!(plan.to_contract().ends_with("1. Read src/main.rs\n2. Add the flag\n3. Run the tests"));
+
+        // A single numbered line is not a plan, and a stray one doesn't start the list
+        assert_eq!(Plan::parse("1. Just one thing"), None);
+        let reply = "1. See the docs.\nIn short:\n1. Back up\n2. Migrate";
+        assert_eq!(Plan::parse(reply).unwrap().steps, ["Back up", "Migrate"]);
+    }
+}
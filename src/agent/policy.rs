@@ -0,0 +1,183 @@
+use super::AgentAction;
+use anyhow::{anyhow, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const POLICY_FILE: &str = ".i4z/agent.toml";
+
+/// `.i4z/agent.toml` as written
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PolicyFile {
+    allow_paths: Vec<String>,      // Globs the agent may modify; empty: anything
+    deny_paths: Vec<String>,       // Globs the agent may neither read nor modify
+    execute_commands: Option<bool>, // false turns commands off for this project
+    safe_commands: Vec<String>,    // Programs commands may start; empty: any
+    max_files_per_run: Option<usize>,
+}
+
+/// Per-project limits on the agent. They only narrow what the executor's capabilities allow, so a
+/// checked-out repository can't hand itself more rights than the user gave
+#[derive(Debug, Default)]
+pub struct AgentPolicy {
+    allow_paths: Option<GlobSet>,
+    deny_paths: Option<GlobSet>,
+    execute_commands: bool,
+    safe_commands: Vec<String>,
+    max_files_per_run: Option<usize>,
+    pub error: Option<String>, // The file exists but couldn't be used; every change is refused
+    modified: HashSet<PathBuf>, // Files changed in the current run
+}
+
+impl AgentPolicy {
+    /// Load `<root>/.i4z/agent.toml`; a missing file leaves everything to the capabilities. An invalid
+    /// one fails closed: reads still work, changes and commands don't
+    pub fn load(root: &Path) -> Self {
+        match fs::read_to_string(root.join(POLICY_FILE)) {
+            Ok(content) => Self::parse(&content).unwrap_or_else(|e| Self {
+                error: Some(format!("Invalid {}: {}", POLICY_FILE, e)),
+                ..Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self { execute_commands: true, ..Self::default() },
+            Err(e) => Self { error: Some(format!("Could not read {}: {}", POLICY_FILE, e)), ..Self::default() },
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        let file: PolicyFile = toml::from_str(content)?;
+        Ok(Self {
+            allow_paths: globs(&file.allow_paths)?,
+            deny_paths: globs(&file.deny_paths)?,
+            execute_commands: file.execute_commands.unwrap_or(true),
+            safe_commands: file.safe_commands,
+            max_files_per_run: file.max_files_per_run,
+            error: None,
+            modified: HashSet::new(),
+        })
+    }
+
+    /// Forget the files changed so far; called when a new agent run starts
+    pub fn begin_run(&mut self) {
+        self.modified.clear();
+    }
+
+    /// Why the policy refuses `action`, if it does. `paths` are the action's paths relative to the
+    /// project root, each with whether the action changes it
+    pub fn check(&self, action: &AgentAction, paths: &[(PathBuf, bool)]) -> Result<(), String> {
+        let is_command = matches!(action, AgentAction::ExecuteCommand { .. } | AgentAction::RunTests { .. });
+        let modifies = paths.iter().any(|(_, modifying)| *modifying) || matches!(action, AgentAction::GitCommit { .. });
+        if let Some(error) = self.error.as_ref().filter(|_| modifies || is_command) {
+            return Err(error.clone());
+        }
+        for (path, modifying) in paths {
+            if self.denies(path) {
+                return Err(format!("{} is in deny_paths", path.display()));
+            }
+            if *modifying && self.allow_paths.as_ref().is_some_and(|allow| !allow.is_match(path)) {
+                return Err(format!("{} is not in allow_paths", path.display()));
+            }
+        }
+        if is_command && !self.execute_commands {
+            return Err("execute_commands is off for this project".to_string());
+        }
+        // The test command comes from the user's config or the manifest, not from the model
+        if let AgentAction::ExecuteCommand { command, .. } = action {
+            if !self.safe_commands.is_empty() {
+                if let Some(program) = programs(command).into_iter().find(|program| !self.safe_commands.contains(program)) {
+                    return Err(format!("`{}` is not in safe_commands", program));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `path`, relative to the project root, matches deny_paths
+    pub fn denies(&self, path: &Path) -> bool {
+        self.deny_paths.as_ref().is_some_and(|deny| deny.is_match(path))
+    }
+
+    /// Count a file the current run is about to change, refusing once max_files_per_run is reached
+    pub fn record_modification(&mut self, path: &Path) -> Result<(), String> {
+        if self.modified.contains(path) {
+            return Ok(());
+        }
+        if let Some(max) = self.max_files_per_run.filter(|max| self.modified.len() >= *max) {
+            return Err(format!("max_files_per_run of {} reached", max));
+        }
+        self.modified.insert(path.to_path_buf());
+        Ok(())
+    }
+}
+
+fn globs(patterns: &[String]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        // `*` stays within a directory, `**` crosses them, as in .gitignore
+        let glob = GlobBuilder::new(pattern.trim_start_matches('/'))
+            .literal_separator(true)
+            .build()
+            .map_err(|e| anyhow!("bad glob `{}`: {}", pattern, e))?;
+        builder.add(glob);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Program each part of a shell command line starts, e.g. `cargo` and `grep` for `cargo test | grep ok`.
+/// Substitutions could start anything, so they count as a program of their own
+fn programs(command: &str) -> Vec<String> {
+    if command.contains("$(") || command.contains('`') {
+        return vec!["$(...)".to_string()];
+    }
+    command
+        .split(['|', ';', '&', '\n'])
+        .filter_map(|part| {
+            part.split_whitespace()
+                .find(|word| !word.contains('=')) // Skip `FOO=bar` environment assignments
+                .map(|program| program.rsplit('/').next().unwrap_or(program).to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agent_policy() {
+        let mut policy = AgentPolicy::parse(
+            r#"
+            allow_paths = ["src/**", "Cargo.toml"]
+            deny_paths = ["**/*.pem"]
+            safe_commands = ["cargo", "git"]
+            max_files_per_run = 1
+            "#,
+        )
+        .unwrap();
+        let read = AgentAction::ReadFile { path: "README.md".into() };
+        assert!(policy.check(&read, &[("README.md".into(), false)]).is_ok());
+        assert!(policy.check(&read, &[("certs/key.pem".into(), false)]).is_err());
+        let write = AgentAction::WriteFile { path: "README.md".into(), content: String::new() };
+        assert_eq!(policy.check(&write, &[("README.md".into(), true)]), Err("README.md is not in allow_paths".to_string()));
+        assert!(policy.check(&write, &[("src/ide/app.rs".into(), true)]).is_ok());
+
+        let command = |command: &str| AgentAction::ExecuteCommand { command: command.to_string(), working_dir: None };
+        assert!(policy.check(&command("RUST_LOG=1 cargo test && git status"), &[]).is_ok());
+        assert_eq!(policy.check(&command("cargo test | rm -rf /"), &[]), Err("`rm` is not in safe_commands".to_string()));
+        assert!(policy.check(&command("cargo $(rm -rf /)"), &[]).is_err());
+
+        assert!(policy.record_modification(Path::new("src/a.rs")).is_ok());
+        assert!(policy.record_modification(Path::new("src/a.rs")).is_ok());
+        assert!(policy.record_modification(Path::new("src/b.rs")).is_err());
+        policy.begin_run();
+        assert!(policy.record_modification(Path::new("src/b.rs")).is_ok());
+
+        // A typo must not silently lift the limits
+        assert!(AgentPolicy::parse("alow_paths = []").is_err());
+    }
+}
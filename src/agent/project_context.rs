@@ -0,0 +1,126 @@
+use super::aiignore::AiIgnore;
+use ignore::WalkBuilder;
+use serde_json::Value as Json;
+use std::fs;
+use std::path::Path;
+use toml::Value as Toml;
+
+/// First line of the system message, so it is only injected once per conversation
+pub const HEADER: &str = "Project context (gathered automatically):";
+/// README text included, in characters
+const README_CHARS: usize = 1500;
+
+/// A compact picture of the project for the model: file tree, manifest and README excerpt, so it
+/// doesn't have to spend its first steps listing directories
+pub fn summarize(root: &Path, max_entries: usize) -> String {
+    let mut sections = vec![HEADER.to_string()];
+    sections.push(format!("Files:\n{}", tree(root, max_entries)));
+    if let Some(cargo) = fs::read_to_string(root.join("Cargo.toml")).ok().and_then(|text| cargo_summary(&text)) {
+        sections.push(format!("Cargo.toml:\n{}", cargo));
+    }
+    if let Some(package) = fs::read_to_string(root.join("package.json")).ok().and_then(|text| package_summary(&text)) {
+        sections.push(format!("package.json:\n{}", package));
+    }
+    if let Some(readme) = readme_excerpt(root) {
+        sections.push(format!("README excerpt:\n{}", readme));
+    }
+    sections.join("\n\n")
+}
+
+/// Indented tree of the files the agent may see, hidden, .gitignored and .aiignored ones left out
+fn tree(root: &Path, max_entries: usize) -> String {
+    let ai_ignore = AiIgnore::load(root);
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| !ai_ignore.is_ignored(entry.path(), entry.file_type().is_some_and(|kind| kind.is_dir())))
+        .build();
+    let mut lines = Vec::new();
+    for entry in walker.flatten().filter(|entry| entry.depth() > 0) {
+        if lines.len() == max_entries {
+            lines.push("... (more entries not shown)".to_string());
+            break;
+        }
+        let name = entry.file_name().to_string_lossy();
+        let slash = if entry.file_type().is_some_and(|kind| kind.is_dir()) { "/" } else { "" };
+        lines.push(format!("{}{}{}", "  ".repeat(entry.depth() - 1), name, slash));
+    }
+    lines.join("\n")
+}
+
+fn cargo_summary(text: &str) -> Option<String> {
+    let manifest: Toml = toml::from_str(text).ok()?;
+    let mut lines = Vec::new();
+    if let Some(package) = manifest.get("package") {
+        let field = |key: &str| package.get(key).and_then(Toml::as_str).unwrap_or("?").to_string();
+        lines.push(format!("package {} {} (edition {})", field("name"), field("version"), field("edition")));
+        if let Some(description) = package.get("description").and_then(Toml::as_str) {
+            lines.push(format!("description: {}", description));
+        }
+    }
+    if let Some(members) = manifest.get("workspace").and_then(|workspace| workspace.get("members")).and_then(Toml::as_array) {
+        let members: Vec<&str> = members.iter().filter_map(Toml::as_str).collect();
+        lines.push(format!("workspace members: {}", members.join(", ")));
+    }
+    for section in ["dependencies", "dev-dependencies"] {
+        if let Some(table) = manifest.get(section).and_then(Toml::as_table).filter(|table| !table.is_empty()) {
+            lines.push(format!("{}: {}", section, table.keys().cloned().collect::<Vec<_>>().join(", ")));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+fn package_summary(text: &str) -> Option<String> {
+    let package: Json = serde_json::from_str(text).ok()?;
+    let field = |key: &str| package.get(key).and_then(Json::as_str).unwrap_or("?").to_string();
+    let mut lines = vec![format!("package {} {}", field("name"), field("version"))];
+    for section in ["scripts", "dependencies", "devDependencies"] {
+        if let Some(object) = package.get(section).and_then(Json::as_object).filter(|object| !object.is_empty()) {
+            lines.push(format!("{}: {}", section, object.keys().cloned().collect::<Vec<_>>().join(", ")));
+        }
+    }
+    Some(lines.join("\n"))
+}
+
+fn readme_excerpt(root: &Path) -> Option<String> {
+    let text = ["README.md", "README", "README.txt", "readme.md"]
+        .iter()
+        .find_map(|name| fs::read_to_string(root.join(name)).ok())?;
+    let mut excerpt: String = text.trim().chars().take(README_CHARS).collect();
+    if excerpt.len() < text.trim().len() {
+        excerpt.push_str("\n...");
+    }
+    Some(excerpt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_summary() {
+        let dir = std::env::temp_dir().join(format!("i4z-context-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("src/agent")).unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.join("src/main.rs"), "").unwrap();
+        fs::write(dir.join("src/agent/mod.rs"), "").unwrap();
+        fs::write(dir.join("target/junk"), "").unwrap();
+        fs::write(
+            dir.join("Cargo.toml"),
+            "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\nserde = \"1\"\ntokio = \"1\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("README.md"), "# Demo\n\nDoes things.\n").unwrap();
+
+        let summary = summarize(&dir, 100);
+        assert!(summary.starts_with(HEADER));
+        assert!(summary.contains("Files:\nCargo.toml\nREADME.md\nsrc/\n  agent/\n    mod.rs\n  main.rs"));
+        assert!(!summary.contains("target"));
+        assert!(summary.contains("package demo 0.1.0 (edition 2021)\ndependencies: serde, tokio"));
+        assert!(summary.ends_with("README excerpt:\n# Demo\n\nDoes things."));
+        assert!(summarize(&dir, 2).contains("README.md\n... (more entries not shown)"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use anyhow::{anyhow, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The project's memory, relative to its root: conventions and facts every conversation in it should know
+pub const MEMORY_FILE: &str = ".i4z/memory.md";
+/// Memory sent at most, in characters, so a long file can't crowd out the conversation
+const MEMORY_CHARS: usize = 8000;
+
+pub fn path(root: &Path) -> PathBuf {
+    root.join(MEMORY_FILE)
+}
+
+/// The memory as the system message sent with every request; None while there is none
+pub fn system_message(root: &Path) -> Option<String> {
+    let text = fs::read_to_string(path(root)).ok()?;
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+    let text = match text.char_indices().nth(MEMORY_CHARS) {
+        Some((end, _)) => format!("{}\n[… the rest of {} was left out]", &text[..end], MEMORY_FILE),
+        None => text.to_string(),
+    };
+    Some(format!("Project memory - conventions and facts to follow in this project:\n{}", text))
+}
+
+/// Add `note` to the memory as a list item, creating the file
+pub fn remember(root: &Path, note: &str) -> Result<()> {
+    let note = note.trim();
+    if note.is_empty() {
+        return Err(anyhow!("Nothing to remember"));
+    }
+    let path = path(root);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let existing = fs::read_to_string(&path).unwrap_or_default();
+    let separator = if existing.is_empty() || existing.ends_with('\n') { "" } else { "\n" };
+    let entry = format!("{}- {}\n", separator, note.replace('\n', "\n  "));
+    OpenOptions::new().create(true).append(true).open(&path)?.write_all(entry.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_memory() {
+        let root = std::env::temp_dir().join(format!("i4z-memory-{}", uuid::Uuid::new_v4()));
+        assert_eq!(system_message(&root), None);
+        assert!(remember(&root, "  ").is_err());
+
+        remember(&root, "Errors go through anyhow").unwrap();
+        fs::write(path(&root), fs::read_to_string(path(&root)).unwrap() + "Tests live next to the code").unwrap();
+        remember(&root, "No unwrap outside tests").unwrap();
+        assert_eq!(
+            system_message(&root).unwrap(),
+            "Project memory - conventions and facts to follow in this project:\n- Errors go through anyhow\nTests live next to the code\n- No unwrap outside tests"
+        );
+
+        fs::write(path(&root), "x".repeat(MEMORY_CHARS + 10)).unwrap();
+        assert!(system_message(&root).unwrap().ends_with("[… the rest of .i4z/memory.md was left out]"));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
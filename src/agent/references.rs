@@ -0,0 +1,307 @@
+use super::aiignore::AiIgnore;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MAX_FILES: usize = 5000;
+const MAX_INDEXED_BYTES: u64 = 512 * 1024; // Larger files are listed but not scanned for symbols
+const MAX_FILE_LINES: usize = 400; // Per `@file` reference sent to the model
+const MAX_SNIPPET_LINES: usize = 60; // Per `#symbol` reference
+
+// Directories that are never worth completing into
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "dist", "build", "__pycache__"];
+
+/// A definition found in a project file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: String, // "fn", "struct", "class", ...
+    pub path: PathBuf, // Relative to the project root
+    pub line: usize,   // 0-based
+}
+
+impl Symbol {
+    /// Reference inserted into chat input, e.g. `#src/api.rs::GroqClient`
+    pub fn reference(&self) -> String {
+        format!("#{}::{}", self.path.display(), self.name)
+    }
+}
+
+/// Project files and their symbols, for `@` / `#` completion in the chat input
+#[derive(Debug, Default)]
+pub struct ProjectIndex {
+    pub files: Vec<PathBuf>, // Relative to the project root
+    pub symbols: Vec<Symbol>,
+}
+
+impl ProjectIndex {
+    /// Walk the project, skipping hidden and build directories and anything in `.aiignore`
+    pub fn build(root: &Path, with_symbols: bool) -> Self {
+        let ai_ignore = AiIgnore::load(root);
+        let mut index = Self::default();
+        let mut pending = vec![root.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+            entries.sort();
+            for path in entries {
+                let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+                if name.starts_with('.') || ai_ignore.is_ignored(&path, path.is_dir()) {
+                    continue;
+                }
+                if path.is_dir() {
+                    if !SKIPPED_DIRS.contains(&name.as_str()) {
+                        pending.push(path);
+                    }
+                } else if index.files.len() < MAX_FILES {
+                    let relative = path.strip_prefix(root).unwrap_or(&path).to_path_buf();
+                    if with_symbols && fs::metadata(&path).is_ok_and(|meta| meta.len() <= MAX_INDEXED_BYTES) {
+                        if let Ok(content) = fs::read_to_string(&path) {
+                            index.symbols.extend(find_symbols(&content, &relative));
+                        }
+                    }
+                    index.files.push(relative);
+                }
+            }
+        }
+        index.files.sort();
+        index
+    }
+
+    pub fn complete_files(&self, query: &str, limit: usize) -> Vec<&PathBuf> {
+        let mut matches: Vec<(usize, &PathBuf)> = self
+            .files
+            .iter()
+            .filter_map(|path| fuzzy_score(&path.to_string_lossy(), query).map(|score| (score, path)))
+            .collect();
+        matches.sort_by_key(|(score, path)| (*score, path.as_os_str().len()));
+        matches.into_iter().take(limit).map(|(_, path)| path).collect()
+    }
+
+    pub fn complete_symbols(&self, query: &str, limit: usize) -> Vec<&Symbol> {
+        let mut matches: Vec<(usize, &Symbol)> = self
+            .symbols
+            .iter()
+            .filter_map(|symbol| fuzzy_score(&symbol.name, query).map(|score| (score, symbol)))
+            .collect();
+        matches.sort_by_key(|(score, symbol)| (*score, symbol.name.len()));
+        matches.into_iter().take(limit).map(|(_, symbol)| symbol).collect()
+    }
+}
+
+/// Lower is better: 0 for a prefix match, 1 for a substring, 2 for a scattered subsequence
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<usize> {
+    let candidate = candidate.to_lowercase();
+    let query = query.to_lowercase();
+    if candidate.starts_with(&query) || candidate.rsplit('/').next().is_some_and(|name| name.starts_with(&query)) {
+        return Some(0);
+    }
+    if candidate.contains(&query) {
+        return Some(1);
+    }
+    let mut chars = candidate.chars();
+    query.chars().all(|q| chars.any(|c| c == q)).then_some(2)
+}
+
+fn symbol_regex() -> Regex {
+    Regex::new(
+        r"^\s*(?:(?:pub(?:\([^)]*\))?|export|default|async|unsafe|static|public|private|protected|abstract)\s+)*(fn|struct|enum|trait|mod|type|const|class|def|function|interface)\s+([A-Za-z_][A-Za-z0-9_]*)",
+    )
+    .expect("valid symbol regex")
+}
+
+fn find_symbols(content: &str, path: &Path) -> Vec<Symbol> {
+    let regex = symbol_regex();
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let cap = regex.captures(text)?;
+            Some(Symbol { name: cap[2].to_string(), kind: cap[1].to_string(), path: path.to_path_buf(), line })
+        })
+        .collect()
+}
+
+/// Stands for the file open in the editor, unsaved edits included
+pub const OPEN_FILE_TOKEN: &str = "#file";
+
+/// Stands for the lines selected with `V` in the editor
+pub const SELECTION_TOKEN: &str = "#selection";
+
+/// Punctuation that may follow a reference in a sentence
+const TRAILING_PUNCTUATION: [char; 7] = [',', '.', ';', ':', ')', '?', '!'];
+
+/// Whether a chat message asks for the open file with `#file`
+pub fn mentions_open_file(message: &str) -> bool {
+    mentions(message, OPEN_FILE_TOKEN)
+}
+
+/// Whether a chat message asks for the selected lines with `#selection`
+pub fn mentions_selection(message: &str) -> bool {
+    mentions(message, SELECTION_TOKEN)
+}
+
+fn mentions(message: &str, token: &str) -> bool {
+    message.split_whitespace().any(|word| word.trim_end_matches(TRAILING_PUNCTUATION) == token)
+}
+
+/// The active tab, or the lines selected in it, as it is in the editor
+pub struct OpenFile<'a> {
+    pub path: &'a Path, // Relative to the root, or the tab's name when it has no file
+    pub text: &'a str,
+    pub lines: Option<(usize, usize)>, // First and last selected line, counting from 0; None for the whole file
+}
+
+impl OpenFile<'_> {
+    /// How the chat and the model refer to it, e.g. "src/lib.rs lines 3-7"
+    pub fn label(&self) -> String {
+        match self.lines {
+            Some((first, last)) => format!("{} lines {}-{}", self.path.display(), first + 1, last + 1),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    /// Whether it may go to the model; .aiignored files never do
+    pub fn is_attachable(&self, root: &Path) -> bool {
+        !AiIgnore::load(root).is_ignored(&root.join(self.path), false)
+    }
+}
+
+/// Append the contents behind every `@file` and `#file::symbol` reference in a chat message, and
+/// `open_file` when given and not .aiignored
+pub fn resolve_references(message: &str, root: &Path, open_file: Option<&OpenFile>) -> String {
+    let ai_ignore = AiIgnore::load(root);
+    let mut context = Vec::new();
+    let mut seen = Vec::new();
+
+    if let Some(open_file) = open_file.filter(|open_file| !ai_ignore.is_ignored(&root.join(open_file.path), false)) {
+        let place = if open_file.lines.is_some() { "selected" } else { "open" };
+        context.push(text_block(&format!("{} ({} in the editor)", open_file.label(), place), open_file.text));
+    }
+
+    for token in message.split_whitespace() {
+        let token = token.trim_end_matches(TRAILING_PUNCTUATION);
+        if seen.iter().any(|t| t == token) {
+            continue;
+        }
+        let block = if let Some(path) = token.strip_prefix('@') {
+            file_block(root, &ai_ignore, path)
+        } else if let Some((path, name)) = token.strip_prefix('#').and_then(|reference| reference.split_once("::")) {
+            symbol_block(root, &ai_ignore, path, name)
+        } else {
+            None
+        };
+        if let Some(block) = block {
+            seen.push(token.to_string());
+            context.push(block);
+        }
+    }
+
+    if context.is_empty() {
+        return message.to_string();
+    }
+    format!("{}\n\n[Referenced context]\n{}", message, context.join("\n"))
+}
+
+/// The file `path` names, unless it resolves outside `root`, through `..` or a symlink, or is .aiignored
+fn read_reference(root: &Path, ai_ignore: &AiIgnore, path: &str) -> Option<String> {
+    if path.is_empty() {
+        return None;
+    }
+    let full_path = root.join(path).canonicalize().ok()?;
+    let relative = full_path.strip_prefix(root.canonicalize().ok()?).ok()?;
+    if ai_ignore.is_ignored(&root.join(path), full_path.is_dir()) || ai_ignore.is_ignored(&root.join(relative), full_path.is_dir()) {
+        return None;
+    }
+    fs::read_to_string(full_path).ok()
+}
+
+fn file_block(root: &Path, ai_ignore: &AiIgnore, path: &str) -> Option<String> {
+    Some(text_block(path, &read_reference(root, ai_ignore, path)?))
+}
+
+fn text_block(label: &str, content: &str) -> String {
+    let total = content.lines().count();
+    let mut text: String = content.lines().take(MAX_FILE_LINES).collect::<Vec<_>>().join("\n");
+    if total > MAX_FILE_LINES {
+        text.push_str(&format!("\n... ({} more lines)", total - MAX_FILE_LINES));
+    }
+    format!("--- {} ---\n```\n{}\n```", label, text)
+}
+
+/// The definition of `name` in `path`, up to the end of its body
+fn symbol_block(root: &Path, ai_ignore: &AiIgnore, path: &str, name: &str) -> Option<String> {
+    let content = read_reference(root, ai_ignore, path)?;
+    let symbol = find_symbols(&content, Path::new(path)).into_iter().find(|symbol| symbol.name == name)?;
+    let lines: Vec<&str> = content.lines().collect();
+    let indent = lines[symbol.line].len() - lines[symbol.line].trim_start().len();
+
+    // The body ends at the first later line that is back at the definition's indentation
+    let mut end = symbol.line;
+    for (index, line) in lines.iter().enumerate().skip(symbol.line + 1).take(MAX_SNIPPET_LINES - 1) {
+        let line_indent = line.len() - line.trim_start().len();
+        if !line.trim().is_empty() && line_indent <= indent {
+            if line.trim_start().starts_with(['}', ')', ']']) {
+                end = index;
+            }
+            break;
+        }
+        end = index;
+    }
+    Some(format!(
+        "--- {} {} ({}:{}) ---\n```\n{}\n```",
+        symbol.kind,
+        name,
+        path,
+        symbol.line + 1,
+        lines[symbol.line..=end].join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        let root = std::env::temp_dir().join(format!("i4z-references-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("target")).unwrap();
+        fs::write(root.join("src/lib.rs"), "pub struct Parser {\n    depth: usize,\n}\n\nfn helper() {}\n").unwrap();
+        fs::write(root.join("target/junk.rs"), "fn junk() {}").unwrap();
+        fs::write(root.join("notes.md"), "hello").unwrap();
+
+        let index = ProjectIndex::build(&root, true);
+        assert_eq!(index.files, [PathBuf::from("notes.md"), PathBuf::from("src/lib.rs")]);
+        assert_eq!(index.complete_files("lib", 5), [&PathBuf::from("src/lib.rs")]);
+        let parser = index.complete_symbols("pars", 5)[0];
+        assert_eq!((parser.kind.as_str(), parser.line), ("struct", 0));
+        assert_eq!(parser.reference(), "#src/lib.rs::Parser");
+
+        let resolved = resolve_references("Why is #src/lib.rs::Parser slow? See @notes.md.", &root, None);
+        assert!(resolved.starts_with("Why is #src/lib.rs::Parser slow? See @notes.md.\n\n[Referenced context]\n"));
+        assert!(resolved.contains("--- struct Parser (src/lib.rs:1) ---\n```\npub struct Parser {\n    depth: usize,\n}\n```"));
+        assert!(resolved.contains("--- notes.md ---\n```\nhello\n```"));
+        assert_eq!(resolve_references("mail me @ home #1", &root, None), "mail me @ home #1");
+        let outside = format!("see @../{}/notes.md", root.file_name().unwrap().to_string_lossy());
+        assert!(resolve_references(&outside, &root, None).contains("[Referenced context]"));
+        assert_eq!(resolve_references("see @../notes.md", &root.join("src"), None), "see @../notes.md");
+
+        assert!(mentions_open_file("what does #file do?"));
+        assert!(!mentions_open_file("#files"));
+        let unsaved = OpenFile { path: Path::new("src/lib.rs"), text: "fn edited() {}", lines: None };
+        let resolved = resolve_references("explain #file", &root, Some(&unsaved));
+        assert!(resolved.ends_with("--- src/lib.rs (open in the editor) ---\n```\nfn edited() {}\n```"));
+        assert!(mentions_selection("why #selection?"));
+        let selection = OpenFile { lines: Some((2, 4)), ..unsaved };
+        let resolved = resolve_references("explain #selection", &root, Some(&selection));
+        assert!(resolved.ends_with("--- src/lib.rs lines 3-5 (selected in the editor) ---\n```\nfn edited() {}\n```"));
+        fs::write(root.join(".aiignore"), "src/\n").unwrap();
+        assert!(!selection.is_attachable(&root));
+        assert_eq!(resolve_references("explain #selection", &root, Some(&selection)), "explain #selection");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+use super::aiignore::AiIgnore;
+use super::policy::AgentPolicy;
+use super::undo::project_key;
+use crate::api::{GroqClient, GroqMessage};
+use crate::provider::LlmProvider;
+use anyhow::{anyhow, Result};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// First line of the system message with the retrieved chunks
+pub const HEADER: &str = "Relevant code from the workspace (retrieved automatically):";
+/// Chunks sent to the embeddings endpoint per request
+const BATCH_SIZE: usize = 64;
+/// Larger files are generated or data, not code worth retrieving
+const MAX_FILE_BYTES: u64 = 200_000;
+
+/// Retrieval of workspace code for chat and agent requests; off unless the provider has embeddings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetrievalSettings {
+    pub enabled: bool,
+    pub model: String,      // Embeddings model
+    pub top_k: usize,       // Chunks added to each request
+    pub chunk_lines: usize, // Lines per chunk
+    pub max_files: usize,   // Files indexed at most, in walk order
+}
+
+impl Default for RetrievalSettings {
+    fn default() -> Self {
+        Self { enabled: false, model: "text-embedding-3-small".to_string(), top_k: 5, chunk_lines: 60, max_files: 2000 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    pub path: PathBuf, // Relative to the project root
+    pub first_line: usize,
+    pub last_line: usize,
+    pub text: String,
+    vector: Vec<f32>,
+}
+
+/// Embedded chunks of a project's files, kept in the cache directory and brought up to date by
+/// re-embedding only the files that changed
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorIndex {
+    model: String,
+    files: HashMap<PathBuf, String>, // Relative path -> fingerprint of the content that was embedded
+    chunks: Vec<Arc<Chunk>>,         // Shared, so a copy of the index to update in the background is cheap
+}
+
+impl VectorIndex {
+    /// Where the index of the project at `root` is kept
+    pub fn path_for(root: &Path) -> Result<PathBuf> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("embeddings");
+        let key = project_key(root);
+        let _ = fs::remove_file(dir.join(format!("{}.json", key))); // Older versions kept it as JSON
+        Ok(dir.join(format!("{}.bin", key)))
+    }
+
+    /// The saved index, or an empty one when there is none or it was built with another model
+    pub fn load(path: &Path, model: &str) -> Self {
+        fs::read(path)
+            .ok()
+            .and_then(|bytes| bincode::deserialize::<VectorIndex>(&bytes).ok())
+            .filter(|index| index.model == model)
+            .unwrap_or_else(|| Self { model: model.to_string(), ..Self::default() })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, bincode::serialize(self)?)?;
+        Ok(())
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// The `k` chunks closest to `query`, best first
+    pub fn search(&self, query: &[f32], k: usize) -> Vec<&Chunk> {
+        let mut scored: Vec<(f32, &Chunk)> = self.chunks.iter().map(|chunk| (cosine(query, &chunk.vector), chunk.as_ref())).collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored.into_iter().take(k).map(|(_, chunk)| chunk).collect()
+    }
+
+    /// Re-embed the files under `root` that changed since the last update and forget removed ones.
+    /// Returns how many files were embedded, and the error a batch stopped at: the files embedded
+    /// before it are kept, the rest are tried again next time. Walking and reading run on blocking threads
+    pub async fn update(&mut self, root: &Path, llm: &dyn LlmProvider, settings: &RetrievalSettings) -> (usize, Result<()>) {
+        let (walk_root, max_files) = (root.to_path_buf(), settings.max_files);
+        let current = match tokio::task::spawn_blocking(move || workspace_files(&walk_root, max_files)).await {
+            Ok(current) => current,
+            Err(e) => return (0, Err(e.into())),
+        };
+        let changed: Vec<PathBuf> = current.iter().filter(|(path, print)| self.files.get(*path) != Some(print)).map(|(path, _)| path.clone()).collect();
+        self.chunks.retain(|chunk| current.get(&chunk.path).is_some_and(|print| self.files.get(&chunk.path) == Some(print)));
+        self.files.retain(|path, _| current.contains_key(path));
+
+        let (read_root, to_read, lines) = (root.to_path_buf(), changed.clone(), settings.chunk_lines);
+        let mut pending = match tokio::task::spawn_blocking(move || read_chunks(&read_root, &to_read, lines)).await {
+            Ok(pending) => pending,
+            Err(e) => return (0, Err(e.into())),
+        };
+        let mut embedded = 0;
+        let mut result = Ok(());
+        for batch in pending.chunks_mut(BATCH_SIZE) {
+            if let Err(e) = embed(llm, &settings.model, batch).await {
+                result = Err(e);
+                break;
+            }
+            embedded += batch.len();
+        }
+
+        let unfinished: HashSet<PathBuf> = pending[embedded..].iter().map(|chunk| chunk.path.clone()).collect();
+        self.chunks.extend(pending.into_iter().take(embedded).filter(|chunk| !unfinished.contains(&chunk.path)).map(Arc::new));
+        let finished: Vec<PathBuf> = changed.into_iter().filter(|path| !unfinished.contains(path)).collect();
+        for path in &finished {
+            self.files.insert(path.clone(), current[path].clone());
+        }
+        (finished.len(), result)
+    }
+}
+
+/// Fill in the vectors of `batch` with one embeddings request
+async fn embed(llm: &dyn LlmProvider, model: &str, batch: &mut [Chunk]) -> Result<()> {
+    let inputs: Vec<String> = batch.iter().map(|chunk| format!("{}\n{}", chunk.path.display(), chunk.text)).collect();
+    let vectors = llm.embed(model, &inputs).await?;
+    if vectors.len() != batch.len() {
+        return Err(anyhow!("Expected {} embeddings, got {}", batch.len(), vectors.len()));
+    }
+    for (chunk, vector) in batch.iter_mut().zip(vectors) {
+        chunk.vector = vector;
+    }
+    Ok(())
+}
+
+/// The chunks of `paths`, still without vectors; binary and unreadable files have none
+fn read_chunks(root: &Path, paths: &[PathBuf], lines: usize) -> Vec<Chunk> {
+    let mut pending = Vec::new();
+    for path in paths {
+        let Ok(text) = fs::read_to_string(root.join(path)) else {
+            continue;
+        };
+        for (first_line, last_line, text) in chunk(&text, lines) {
+            pending.push(Chunk { path: path.clone(), first_line, last_line, text, vector: Vec::new() });
+        }
+    }
+    pending
+}
+
+/// What to look up for a request: the user's message, in the workspace index
+pub struct Lookup {
+    pub query: String,
+    pub index: Arc<VectorIndex>,
+    pub settings: RetrievalSettings,
+}
+
+/// `messages` with the chunks most relevant to the lookup's query added just before the latest
+/// message. Retrieval only helps, so when the query can't be embedded the messages go as they are
+pub async fn with_context(llm: &dyn LlmProvider, lookup: Option<Lookup>, mut messages: Vec<GroqMessage>) -> Vec<GroqMessage> {
+    let Some(Lookup { query, index, settings }) = lookup.filter(|lookup| !lookup.index.chunks.is_empty()) else {
+        return messages;
+    };
+    if messages.is_empty() {
+        return messages;
+    }
+    let Ok(mut vectors) = llm.embed(&settings.model, &[query]).await else {
+        return messages;
+    };
+    let Some(query) = vectors.pop() else {
+        return messages;
+    };
+    let hits = index.search(&query, settings.top_k);
+    if let Some(context) = context_message(&hits) {
+        messages.insert(messages.len() - 1, GroqClient::create_text_message("system", &context));
+    }
+    messages
+}
+
+fn context_message(hits: &[&Chunk]) -> Option<String> {
+    if hits.is_empty() {
+        return None;
+    }
+    let chunks: Vec<String> = hits
+        .iter()
+        .map(|chunk| format!("{}:{}-{}\n```\n{}\n```", chunk.path.display(), chunk.first_line, chunk.last_line, chunk.text))
+        .collect();
+    Some(format!("{}\n\n{}", HEADER, chunks.join("\n\n")))
+}
+
+/// `text` in pieces of `lines` lines, each with its 1-based first and last line; blank pieces are skipped
+pub fn chunk(text: &str, lines: usize) -> Vec<(usize, usize, String)> {
+    let all: Vec<&str> = text.lines().collect();
+    all.chunks(lines.max(1))
+        .enumerate()
+        .filter(|(_, piece)| piece.iter().any(|line| !line.trim().is_empty()))
+        .map(|(index, piece)| {
+            let first = index * lines.max(1) + 1;
+            (first, first + piece.len() - 1, piece.join("\n"))
+        })
+        .collect()
+}
+
+/// Files the agent may see, neither .aiignored nor in the policy's deny_paths, with a fingerprint
+/// of their size and modification time
+fn workspace_files(root: &Path, max_files: usize) -> HashMap<PathBuf, String> {
+    let ai_ignore = AiIgnore::load(root);
+    let policy = AgentPolicy::load(root);
+    let walker = WalkBuilder::new(root)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| !ai_ignore.is_ignored(entry.path(), entry.file_type().is_some_and(|kind| kind.is_dir())))
+        .build();
+    walker
+        .flatten()
+        .filter(|entry| entry.file_type().is_some_and(|kind| kind.is_file()))
+        .filter_map(|entry| {
+            let meta = entry.metadata().ok().filter(|meta| meta.len() <= MAX_FILE_BYTES)?;
+            let modified = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+            let path = entry.path().strip_prefix(root).ok()?.to_path_buf();
+            (!policy.denies(&path)).then(|| (path, format!("{}-{}", meta.len(), modified)))
+        })
+        .take(max_files)
+        .collect()
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 { 0.0 } else { dot / norms }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_and_search() {
+        let text = "fn a() {}\n\n\n\nfn b() {}\n";
+        assert_eq!(chunk(text, 2), vec![(1, 2, "fn a() {}\n".to_string()), (5, 5, "fn b() {}".to_string())]);
+
+        let chunk = |path: &str, vector: Vec<f32>| Arc::new(Chunk { path: path.into(), first_line: 1, last_line: 1, text: String::new(), vector });
+        let index = VectorIndex {
+            model: "m".to_string(),
+            files: HashMap::new(),
+            chunks: vec![chunk("parser.rs", vec![1.0, 0.0]), chunk("lexer.rs", vec![0.6, 0.8]), chunk("empty.rs", vec![0.0, 0.0])],
+        };
+        let hits: Vec<String> = index.search(&[0.0, 1.0], 2).iter().map(|chunk| chunk.path.display().to_string()).collect();
+        assert_eq!(hits, vec!["lexer.rs", "parser.rs"]);
+        assert!(context_message(&index.search(&[1.0, 0.0], 1)).unwrap().starts_with(&format!("{}\n\nparser.rs:1-1", HEADER)));
+
+        let path = std::env::temp_dir().join(format!("i4z-index-{}.bin", uuid::Uuid::new_v4()));
+        index.save(&path).unwrap();
+        assert_eq!(VectorIndex::load(&path, "m").chunk_count(), 3);
+        assert_eq!(VectorIndex::load(&path, "other").chunk_count(), 0);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+/// How much damage a shell command can do, from least to most risky
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CommandRisk {
+    Safe,
+    ModifiesFiles,
+    Network,
+    Destructive,
+}
+
+impl CommandRisk {
+    pub fn label(self) -> &'static str {
+        match self {
+            CommandRisk::Safe => "✅ safe",
+            CommandRisk::ModifiesFiles => "📝 modifies files",
+            CommandRisk::Network => "🌐 network",
+            CommandRisk::Destructive => "💥 destructive",
+        }
+    }
+
+    /// Destructive commands must be confirmed by typing, not a single keypress
+    pub fn requires_typed_confirmation(self) -> bool {
+        self == CommandRisk::Destructive
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskAssessment {
+    pub risk: CommandRisk,
+    pub reason: String, // One-line explanation shown in the confirmation dialog
+}
+
+impl RiskAssessment {
+    fn new(risk: CommandRisk, reason: impl Into<String>) -> Self {
+        Self { risk, reason: reason.into() }
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{}: {}", self.risk.label(), self.reason)
+    }
+}
+
+/// Classify a shell command with local heuristics; the riskiest part of a pipeline wins. Commands
+/// in `$(…)`, backticks and subshells count as parts of their own
+pub fn classify_command(command: &str) -> RiskAssessment {
+    let normalized = command
+        .replace(">&", ">") // `2>&1` is a redirection, not a background job
+        .replace("&&", ";")
+        .replace("||", ";")
+        .replace("$(", ";")
+        .replace(['`', '(', ')', '&'], ";");
+
+    // Piping downloaded content into a shell runs unreviewed code
+    let piped_to_shell = normalized.split(';').any(|part| {
+        let stages: Vec<&str> = part.split('|').map(str::trim).collect();
+        stages.len() > 1
+            && stages[..stages.len() - 1].iter().any(|stage| starts_with_program(stage, &["curl", "wget"]))
+            && stages[1..].iter().any(|stage| starts_with_program(stage, &["sh", "bash", "zsh", "python", "python3"]))
+    });
+    if piped_to_shell {
+        return RiskAssessment::new(CommandRisk::Destructive, "executes a downloaded script");
+    }
+
+    normalized
+        .split([';', '|', '\n'])
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .map(classify_segment)
+        .max_by_key(|assessment| assessment.risk)
+        .unwrap_or_else(|| RiskAssessment::new(CommandRisk::Safe, "empty command"))
+}
+
+fn starts_with_program(segment: &str, programs: &[&str]) -> bool {
+    words(segment).first().is_some_and(|program| programs.contains(program))
+}
+
+/// Words of a segment without `sudo`, `env` and the like or leading `VAR=value` assignments; the
+/// program is named without its directory or quotes, so `/bin/rm` and `"rm"` are `rm`
+fn words(segment: &str) -> Vec<&str> {
+    let mut words: Vec<&str> = segment
+        .split_whitespace()
+        .skip_while(|word| matches!(*word, "sudo" | "env" | "nohup" | "exec" | "command" | "nice" | "time") || (word.contains('=') && !word.starts_with('-')))
+        .collect();
+    if let Some(program) = words.first_mut() {
+        let unquoted = program.trim_matches(['"', '\'', '\\']);
+        *program = unquoted.rsplit('/').next().unwrap_or(unquoted);
+    }
+    words
+}
+
+/// The script of `sh -c '…'` and the like, without its quotes
+fn inline_script(args: &[&str]) -> Option<String> {
+    let script = args.iter().position(|arg| *arg == "-c").map(|index| args[index + 1..].join(" "))?;
+    Some(script.trim().trim_matches(['"', '\'']).to_string())
+}
+
+fn classify_segment(segment: &str) -> RiskAssessment {
+    let words = words(segment);
+    let Some(&program) = words.first() else {
+        return RiskAssessment::new(CommandRisk::Safe, "empty command");
+    };
+    let args = &words[1..];
+    let has_flag = |flags: &[&str]| {
+        args.iter().any(|arg| {
+            flags.contains(arg)
+                // Combined short flags such as `-rf`
+                || (arg.starts_with('-') && !arg.starts_with("--") && flags.iter().any(|flag| {
+                    flag.len() == 2 && flag.starts_with('-') && !flag.starts_with("--") && arg.contains(&flag[1..])
+                }))
+        })
+    };
+    let subcommand = args.iter().find(|arg| !arg.starts_with('-')).copied().unwrap_or("");
+
+    let assessment = match program {
+        // Programs that run another command are as risky as that command
+        "eval" => classify_command(args.join(" ").trim_matches(['"', '\''])),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" if inline_script(args).is_some() => {
+            classify_command(&inline_script(args).unwrap_or_default())
+        }
+        // Which of the words is the command depends on xargs' options, so every candidate counts
+        "xargs" => (1..words.len())
+            .filter(|&index| !words[index].starts_with('-'))
+            .map(|index| classify_segment(&words[index..].join(" ")))
+            .max_by_key(|assessment| assessment.risk)
+            .unwrap_or_else(|| RiskAssessment::new(CommandRisk::Safe, "echoes its input")),
+        "rm" if has_flag(&["-r", "-R", "-f", "--recursive", "--force"]) => {
+            RiskAssessment::new(CommandRisk::Destructive, "recursively or forcibly deletes files")
+        }
+        "rm" | "rmdir" | "unlink" => RiskAssessment::new(CommandRisk::ModifiesFiles, "deletes files"),
+        "dd" | "mkfs" | "shred" | "wipefs" | "fdisk" | "parted" => {
+            RiskAssessment::new(CommandRisk::Destructive, "writes directly to disks or destroys data")
+        }
+        "shutdown" | "reboot" | "halt" | "poweroff" | "killall" | "pkill" => {
+            RiskAssessment::new(CommandRisk::Destructive, "stops the system or processes")
+        }
+        "chmod" | "chown" if has_flag(&["-R", "--recursive"]) => {
+            RiskAssessment::new(CommandRisk::Destructive, "recursively changes permissions")
+        }
+        "git" => match subcommand {
+            "reset" if has_flag(&["--hard"]) => RiskAssessment::new(CommandRisk::Destructive, "discards uncommitted changes"),
+            "clean" if has_flag(&["-f", "--force"]) => RiskAssessment::new(CommandRisk::Destructive, "deletes untracked files"),
+            "push" if has_flag(&["-f", "--force", "--force-with-lease"]) => {
+                RiskAssessment::new(CommandRisk::Destructive, "rewrites remote history")
+            }
+            "push" | "pull" | "fetch" | "clone" => RiskAssessment::new(CommandRisk::Network, "talks to a git remote"),
+            "status" | "log" | "diff" | "show" | "blame" | "branch" if args.len() <= 2 => {
+                RiskAssessment::new(CommandRisk::Safe, "read-only git query")
+            }
+            _ => RiskAssessment::new(CommandRisk::ModifiesFiles, "changes the git working tree or history"),
+        },
+        "curl" | "wget" | "ssh" | "scp" | "rsync" | "ftp" | "nc" | "telnet" => {
+            RiskAssessment::new(CommandRisk::Network, "makes network connections")
+        }
+        "npm" | "yarn" | "pnpm" | "pip" | "pip3" | "gem" | "apt" | "apt-get" | "brew" | "dnf" | "pacman"
+            if matches!(subcommand, "install" | "add" | "update" | "upgrade" | "i") =>
+        {
+            RiskAssessment::new(CommandRisk::Network, "downloads and installs packages")
+        }
+        "cargo" => match subcommand {
+            "install" | "publish" | "add" | "update" => RiskAssessment::new(CommandRisk::Network, "downloads or publishes crates"),
+            _ => RiskAssessment::new(CommandRisk::ModifiesFiles, "builds the project (writes to target/)"),
+        },
+        "find" if has_flag(&["-delete", "-exec", "-execdir"]) => {
+            RiskAssessment::new(CommandRisk::ModifiesFiles, "runs commands or deletes files it finds")
+        }
+        "sed" if has_flag(&["-i", "--in-place"]) => RiskAssessment::new(CommandRisk::ModifiesFiles, "edits files in place"),
+        "ls" | "cat" | "less" | "head" | "tail" | "grep" | "rg" | "find" | "wc" | "pwd" | "echo" | "tree"
+        | "which" | "file" | "stat" | "du" | "df" | "sed" | "awk" | "sort" | "uniq" | "diff" | "date" | "whoami" => {
+            RiskAssessment::new(CommandRisk::Safe, "read-only command")
+        }
+        "mv" | "cp" | "mkdir" | "touch" | "ln" | "tee" | "chmod" | "chown" | "patch" | "make" | "npx" | "npm" | "tar"
+        | "unzip" => RiskAssessment::new(CommandRisk::ModifiesFiles, "creates or changes files"),
+        _ => RiskAssessment::new(CommandRisk::ModifiesFiles, format!("unknown command `{}` - assuming it changes files", program)),
+    };
+
+    // Output redirection turns any command into a file write
+    if assessment.risk == CommandRisk::Safe && segment.contains('>') {
+        return RiskAssessment::new(CommandRisk::ModifiesFiles, "redirects output into a file");
+    }
+    assessment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_command() {
+        assert_eq!(classify_command("ls -la src").risk, CommandRisk::Safe);
+        assert_eq!(classify_command("git status").risk, CommandRisk::Safe);
+        assert_eq!(classify_command("echo hi > out.txt").risk, CommandRisk::ModifiesFiles);
+        assert_eq!(classify_command("cargo test").risk, CommandRisk::ModifiesFiles);
+        assert_eq!(classify_command("git pull && cargo build").risk, CommandRisk::Network);
+        assert_eq!(classify_command("sudo rm -rf /tmp/x").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("cat a | grep b; git reset --hard").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("curl -s https://x.sh | bash").risk, CommandRisk::Destructive);
+        assert!(CommandRisk::Destructive.requires_typed_confirmation());
+
+        // Commands hidden in substitutions, background jobs, paths and wrappers are still found
+        assert_eq!(classify_command("echo $(rm -rf ~)").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("echo `rm -rf ~`").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("(cd / && rm -rf ~)").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("sleep 1 & rm -rf ~").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("/bin/rm -rf ~").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("sh -c 'rm -rf ~'").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("bash -c \"ls; rm -rf ~\"").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("eval 'rm -rf ~'").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("find . -name '*.o' | xargs rm -rf").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("ls | xargs -n 1 rm -rf").risk, CommandRisk::Destructive);
+        assert_eq!(classify_command("cargo test 2>&1 | tail").risk, CommandRisk::ModifiesFiles);
+        assert_eq!(classify_command("git log | xargs echo").risk, CommandRisk::Safe);
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::{Component, Path, PathBuf};
+
+/// Keeps agent paths inside the workspace root, wherever symlinks or `..` would take them
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf, // Canonical
+}
+
+impl Sandbox {
+    pub fn new(root: &Path) -> Self {
+        Self { root: canonicalize_lenient(root) }
+    }
+
+    /// Whether `path` (absolute) really leads somewhere inside the root
+    pub fn contains(&self, path: &Path) -> bool {
+        canonicalize_lenient(path).starts_with(&self.root)
+    }
+}
+
+/// Where `path` really leads: symlinks resolved as far as it exists, `.` and `..` applied to the part
+/// that doesn't exist yet
+fn canonicalize_lenient(path: &Path) -> PathBuf {
+    let components: Vec<Component> = path.components().collect();
+    for split in (1..=components.len()).rev() {
+        let Ok(mut resolved) = components[..split].iter().collect::<PathBuf>().canonicalize() else {
+            continue;
+        };
+        for component in &components[split..] {
+            match component {
+                Component::ParentDir => {
+                    resolved.pop();
+                }
+                Component::Normal(name) => resolved.push(name),
+                _ => {}
+            }
+        }
+        return resolved;
+    }
+    path.to_path_buf()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_sandbox() {
+        let dir = std::env::temp_dir().join(format!("i4z-sandbox-{}", uuid::Uuid::new_v4()));
+        let root = dir.join("project");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(dir.join("outside")).unwrap();
+        let sandbox = Sandbox::new(&root);
+
+        assert!(sandbox.contains(&root.join("src/main.rs")));
+        assert!(sandbox.contains(&root.join("new/dir/../file.txt")));
+        assert!(!sandbox.contains(&root.join("src/../../outside/file.txt")));
+        assert!(!sandbox.contains(&root.join("missing/../../../etc/passwd")));
+        assert!(!sandbox.contains(Path::new("/etc/passwd")));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(dir.join("outside"), root.join("link")).unwrap();
+            assert!(!sandbox.contains(&root.join("link/file.txt")));
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+use super::undo::project_key;
+use anyhow::{anyhow, Result};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Notes sent with a request at most, in characters; the oldest are left out first
+const NOTES_CHARS: usize = 8000;
+/// Scratchpads of sessions not written to for this long are deleted
+const KEEP_FOR: Duration = Duration::from_secs(30 * 86400);
+
+/// Notes the agent keeps for itself during a session: decisions, findings and TODOs that must
+/// outlive the conversation being trimmed to fit the context window
+#[derive(Debug, Clone)]
+pub struct Scratchpad {
+    path: PathBuf,
+    content: Arc<Mutex<Option<String>>>, // The file as last read or written, shared by every clone
+}
+
+impl Scratchpad {
+    /// The scratchpad of one agent session in one project, in the cache directory
+    pub fn for_session(project_dir: &Path, session: &str) -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("notes");
+        remove_stale(&dir);
+        Ok(Self::at(dir.join(format!("{}-{}.md", project_key(project_dir), session))))
+    }
+
+    pub fn at(path: PathBuf) -> Self {
+        Self { path, content: Arc::default() }
+    }
+
+    /// Add a note as a list item stamped with the time; returns how many notes there are now
+    pub fn append(&self, note: &str) -> Result<usize> {
+        let note = note.trim();
+        if note.is_empty() {
+            return Err(anyhow!("The note is empty"));
+        }
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let entry = format!("- [{}] {}\n", chrono::Local::now().format("%H:%M"), note.replace('\n', "\n  "));
+        OpenOptions::new().create(true).append(true).open(&self.path)?.write_all(entry.as_bytes())?;
+        if let Ok(mut content) = self.content.lock() {
+            *content = None; // Read again, with whatever else was appended
+        }
+        Ok(self.count())
+    }
+
+    /// Every note so far, as written to the file; empty when there are none. Only the first call
+    /// after a note was added reads the file
+    pub fn read(&self) -> String {
+        let Ok(mut content) = self.content.lock() else {
+            return fs::read_to_string(&self.path).unwrap_or_default();
+        };
+        content.get_or_insert_with(|| fs::read_to_string(&self.path).unwrap_or_default()).clone()
+    }
+
+    /// The newest notes that fit the request budget, marked when older ones were left out
+    pub fn recent(&self) -> String {
+        let notes = self.read();
+        let total = notes.chars().count();
+        if total <= NOTES_CHARS {
+            return notes;
+        }
+        let start = notes.char_indices().nth(total - NOTES_CHARS).map_or(0, |(index, _)| index);
+        let start = notes[start..].find("\n- ").map_or(start, |line| start + line + 1); // At a whole note
+        format!("[… earlier notes left out]\n{}", &notes[start..])
+    }
+
+    pub fn count(&self) -> usize {
+        self.read().lines().filter(|line| line.starts_with("- ")).count()
+    }
+}
+
+/// Delete the scratchpads in `dir` that haven't been written to for a while
+fn remove_stale(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let age = entry.metadata().and_then(|meta| meta.modified()).ok().and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age > KEEP_FOR) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratchpad() {
+        let path = std::env::temp_dir().join(format!("i4z-notes-{}", uuid::Uuid::new_v4())).join("notes.md");
+        let scratchpad = Scratchpad::at(path.clone());
+        assert_eq!(scratchpad.read(), "");
+        assert!(scratchpad.append("  ").is_err());
+
+        assert_eq!(scratchpad.append("Parser lives in src/parse.rs").unwrap(), 1);
+        assert_eq!(scratchpad.append("TODO:\n- fix the lexer").unwrap(), 2);
+        let notes = scratchpad.read();
+        assert!(notes.lines().next().unwrap().ends_with("] Parser lives in src/parse.rs"));
+        assert!(notes.ends_with("] TODO:\n  - fix the lexer\n"));
+        assert_eq!(scratchpad.recent(), notes);
+
+        for _ in 0..200 {
+            scratchpad.append(&"x".repeat(100)).unwrap();
+        }
+        let recent = scratchpad.recent();
+        assert!(recent.starts_with("[… earlier notes left out]\n- [") && recent.chars().count() < NOTES_CHARS + 50);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
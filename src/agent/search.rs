@@ -0,0 +1,116 @@
+use super::aiignore::AiIgnore;
+use ignore::WalkBuilder;
+use regex::Regex;
+use std::fs;
+use std::path::Path;
+
+/// Matches reported per search; the model is told when there were more
+pub const MAX_RESULTS: usize = 200;
+/// Longer matching lines are cut, so one minified file can't flood the context
+const MAX_LINE_CHARS: usize = 200;
+/// Larger files are left out of content searches; they are generated or data, not code
+const MAX_SEARCHED_BYTES: u64 = 1024 * 1024;
+/// Bytes looked at for a NUL, which marks a binary file
+const SNIFF_BYTES: usize = 8000;
+
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    pub matches: Vec<String>, // "path" for name matches, "path:line: text" for content matches
+    pub truncated: bool,
+}
+
+/// Walk `directory` recursively, skipping hidden, .gitignored and .aiignored files and those `denied`
+/// says the agent may not read, given their path relative to `root`, and match `pattern` against file
+/// names, or against every line when `in_contents` is set; content searches skip large and binary
+/// files. An invalid regex is searched for literally
+pub fn search(
+    directory: &Path,
+    root: &Path,
+    pattern: &str,
+    in_contents: bool,
+    ai_ignore: &AiIgnore,
+    denied: impl Fn(&Path) -> bool,
+) -> SearchResults {
+    let regex = Regex::new(pattern).unwrap_or_else(|_| Regex::new(&regex::escape(pattern)).expect("escaped pattern"));
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).display().to_string();
+    let mut results = SearchResults::default();
+
+    let skip = ai_ignore.clone();
+    let walker = WalkBuilder::new(directory)
+        .require_git(false)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |entry| !skip.is_ignored(entry.path(), entry.file_type().is_some_and(|kind| kind.is_dir()))) // Don't descend into .aiignored directories
+        .build();
+    for entry in walker.flatten() {
+        let path = entry.path();
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) || denied(path.strip_prefix(root).unwrap_or(path)) {
+            continue;
+        }
+        if !in_contents {
+            if path.file_name().and_then(|name| name.to_str()).is_some_and(|name| regex.is_match(name)) {
+                results.matches.push(relative(path));
+            }
+        } else if let Some(content) = searchable_text(&entry) {
+            for (number, line) in content.lines().enumerate().filter(|(_, line)| regex.is_match(line)) {
+                let text: String = line.trim().chars().take(MAX_LINE_CHARS).collect();
+                results.matches.push(format!("{}:{}: {}", relative(path), number + 1, text));
+                if results.matches.len() > MAX_RESULTS {
+                    break;
+                }
+            }
+        }
+        if results.matches.len() > MAX_RESULTS {
+            results.matches.truncate(MAX_RESULTS);
+            results.truncated = true;
+            break;
+        }
+    }
+    results
+}
+
+/// The file's text, unless it is too large, binary or not UTF-8
+fn searchable_text(entry: &ignore::DirEntry) -> Option<String> {
+    entry.metadata().ok().filter(|meta| meta.len() <= MAX_SEARCHED_BYTES)?;
+    let bytes = fs::read(entry.path()).ok()?;
+    if bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0) {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let dir = std::env::temp_dir().join(format!("i4z-search-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+        fs::create_dir_all(dir.join("target")).unwrap();
+        fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        fs::write(dir.join(".aiignore"), "secrets.rs\n").unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {\n    run(1);\n}\n").unwrap();
+        fs::write(dir.join("src/nested/run.rs"), "pub fn run(n: u32) {}\n").unwrap();
+        fs::write(dir.join("src/secrets.rs"), "fn run() {}\n").unwrap();
+        fs::write(dir.join("target/run.rs"), "fn run() {}\n").unwrap();
+        fs::write(dir.join("src/run.bin"), b"run(\0\x01").unwrap();
+        fs::write(dir.join("src/bundle.js"), "run(1);\n".repeat(MAX_SEARCHED_BYTES as usize / 4)).unwrap();
+        let ai_ignore = AiIgnore::load(&dir);
+
+        let names = search(&dir, &dir, r"^run\.rs$", false, &ai_ignore, |_| false);
+        assert_eq!(names.matches, vec![format!("src{0}nested{0}run.rs", std::path::MAIN_SEPARATOR)]);
+
+        let lines = search(&dir.join("src"), &dir, r"run\(", true, &ai_ignore, |_| false);
+        assert_eq!(lines.matches.len(), 2);
+        assert!(lines.matches[0].ends_with("main.rs:2: run(1);"));
+        assert!(!lines.truncated);
+
+        // Not a valid regex, so it is taken literally
+        assert_eq!(search(&dir, &dir, "run(1", true, &ai_ignore, |_| false).matches.len(), 1);
+
+        let denied = |path: &Path| path.starts_with("src/nested");
+        assert_eq!(search(&dir, &dir, r"run\(", true, &ai_ignore, denied).matches.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const MINUTE: Duration = Duration::from_secs(60);
+
+/// Caps that keep a runaway agent loop from burning through the API quota; 0 turns a cap off
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SpendLimits {
+    pub max_requests_per_minute: usize,
+    pub max_requests_per_session: usize,
+    pub max_session_cost: f64,        // Dollars, estimated from the tokens used
+    pub cost_per_million_tokens: f64, // Price the estimate assumes, prompt and completion alike
+}
+
+impl Default for SpendLimits {
+    fn default() -> Self {
+        Self {
+            max_requests_per_minute: 30,
+            max_requests_per_session: 300,
+            max_session_cost: 0.0,
+            cost_per_million_tokens: 0.79,
+        }
+    }
+}
+
+/// Counts the requests and tokens of a session and says when the next request would go past a
+/// cap. The user can wave it through, which lets another cap's worth of requests or cost go by
+#[derive(Debug, Clone)]
+pub struct SpendGuard {
+    pub limits: SpendLimits,
+    recent: VecDeque<Instant>, // Requests sent in the last minute
+    requests: usize,
+    tokens: u64,
+    requests_allowed: usize,  // Session requests approved so far
+    cost_allowed: f64,        // Session cost approved so far
+    rate_waived_until: Option<Instant>,
+}
+
+impl SpendGuard {
+    pub fn new(limits: SpendLimits) -> Self {
+        Self {
+            limits,
+            recent: VecDeque::new(),
+            requests: 0,
+            tokens: 0,
+            requests_allowed: limits.max_requests_per_session,
+            cost_allowed: limits.max_session_cost,
+            rate_waived_until: None,
+        }
+    }
+
+    pub fn record_request(&mut self, now: Instant) {
+        self.requests += 1;
+        self.recent.push_back(now);
+    }
+
+    pub fn record_tokens(&mut self, tokens: u32) {
+        self.tokens += tokens as u64;
+    }
+
+    pub fn estimated_cost(&self) -> f64 {
+        self.tokens as f64 / 1_000_000.0 * self.limits.cost_per_million_tokens
+    }
+
+    /// Why the next request has to wait for the user, if it does
+    pub fn check(&mut self, now: Instant) -> Option<String> {
+        while self.recent.front().is_some_and(|sent| now.duration_since(*sent) >= MINUTE) {
+            self.recent.pop_front();
+        }
+        let per_minute = self.limits.max_requests_per_minute;
+        if per_minute > 0 && self.recent.len() >= per_minute && self.rate_waived_until.is_none_or(|until| now >= until) {
+            return Some(format!("{} requests in the last minute (limit {})", self.recent.len(), per_minute));
+        }
+        if self.limits.max_requests_per_session > 0 && self.requests >= self.requests_allowed {
+            return Some(format!("{} requests this session (limit {})", self.requests, self.requests_allowed));
+        }
+        let cost = self.estimated_cost();
+        if self.limits.max_session_cost > 0.0 && cost >= self.cost_allowed {
+            return Some(format!("an estimated ${:.2} spent this session (limit ${:.2})", cost, self.cost_allowed));
+        }
+        None
+    }
+
+    /// The user chose to go on: the rate cap is lifted for a minute, the session caps move up by their size
+    pub fn approve(&mut self, now: Instant) {
+        if self.limits.max_requests_per_minute > 0 && self.recent.len() >= self.limits.max_requests_per_minute {
+            self.rate_waived_until = Some(now + MINUTE);
+        }
+        while self.requests >= self.requests_allowed && self.limits.max_requests_per_session > 0 {
+            self.requests_allowed += self.limits.max_requests_per_session;
+        }
+        while self.estimated_cost() >= self.cost_allowed && self.limits.max_session_cost > 0.0 {
+            self.cost_allowed += self.limits.max_session_cost;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spend_guard() {
+        let limits = SpendLimits { max_requests_per_minute: 2, max_requests_per_session: 3, max_session_cost: 1.0, cost_per_million_tokens: 1.0 };
+        let mut guard = SpendGuard::new(limits);
+        let start = Instant::now();
+        guard.record_request(start);
+        guard.record_request(start);
+        assert_eq!(guard.check(start).as_deref(), Some("2 requests in the last minute (limit 2)"));
+        assert_eq!(guard.check(start + MINUTE), None);
+
+        guard.approve(start);
+        guard.record_request(start);
+        assert_eq!(guard.check(start).as_deref(), Some("3 requests this session (limit 3)"));
+        guard.approve(start);
+        assert_eq!(guard.check(start), None);
+
+        guard.record_tokens(1_500_000);
+        assert!(guard.check(start).is_some_and(|reason| reason.contains("$1.50")));
+        guard.approve(start);
+        assert_eq!(guard.check(start), None);
+    }
+}
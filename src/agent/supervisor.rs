@@ -0,0 +1,250 @@
+use super::risk::classify_command;
+use super::turn_summary::{self, FileChange, TurnSummary};
+use super::{AgentAction, AgentExecutor, AgentResponse};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutonomyLevel {
+    ConfirmEach, // Every modifying action needs approval
+    Supervised,  // Run freely, but pause at checkpoints for review
+    Autonomous,  // Never pause
+}
+
+impl AutonomyLevel {
+    pub fn next(self) -> Self {
+        match self {
+            AutonomyLevel::ConfirmEach => AutonomyLevel::Supervised,
+            AutonomyLevel::Supervised => AutonomyLevel::Autonomous,
+            AutonomyLevel::Autonomous => AutonomyLevel::ConfirmEach,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AutonomyLevel::ConfirmEach => "CONFIRM",
+            AutonomyLevel::Supervised => "SUPERVISED",
+            AutonomyLevel::Autonomous => "AUTONOMOUS",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutonomySettings {
+    pub level: AutonomyLevel,
+    pub checkpoint_every_actions: usize, // Supervised: pause after this many modifying actions
+    pub checkpoint_diff_lines: usize,    // Supervised: pause once this many lines changed
+}
+
+impl Default for AutonomySettings {
+    fn default() -> Self {
+        Self {
+            level: AutonomyLevel::ConfirmEach,
+            checkpoint_every_actions: 5,
+            checkpoint_diff_lines: 200,
+        }
+    }
+}
+
+/// Tracks agent progress since the last user review and decides when to pause
+#[derive(Debug, Clone)]
+pub struct Supervisor {
+    pub settings: AutonomySettings,
+    pub actions_since_checkpoint: usize,
+    pub diff_lines_since_checkpoint: usize,
+    pub turn: TurnSummary, // Everything executed since the turn started
+    pub review_every_change: bool, // Set for one agent run whose changes are reviewed whatever the level
+}
+
+/// Outcome of running a batch of actions under supervision
+pub struct SupervisedRun {
+    pub responses: Vec<AgentResponse>,
+    pub pending: Vec<AgentAction>, // Not yet executed because a checkpoint was reached
+}
+
+impl Supervisor {
+    pub fn new(settings: AutonomySettings) -> Self {
+        Self {
+            settings,
+            actions_since_checkpoint: 0,
+            diff_lines_since_checkpoint: 0,
+            turn: TurnSummary::default(),
+            review_every_change: false,
+        }
+    }
+
+    /// Whether the user has to review before `action` runs
+    pub fn needs_review(&self, action: &AgentAction) -> bool {
+        if !is_modifying(action) {
+            return false;
+        }
+        // Destructive commands are always reviewed, whatever the autonomy level
+        if let AgentAction::ExecuteCommand { command, .. } = action {
+            if classify_command(command).risk.requires_typed_confirmation() {
+                return true;
+            }
+        }
+        if self.review_every_change {
+            return true;
+        }
+        match self.settings.level {
+            AutonomyLevel::ConfirmEach => true,
+            AutonomyLevel::Supervised => {
+                self.actions_since_checkpoint >= self.settings.checkpoint_every_actions
+                    || self.diff_lines_since_checkpoint >= self.settings.checkpoint_diff_lines
+            }
+            AutonomyLevel::Autonomous => false,
+        }
+    }
+
+    pub fn record(&mut self, action: &AgentAction, diff_lines: usize) {
+        if is_modifying(action) {
+            self.actions_since_checkpoint += 1;
+            self.diff_lines_since_checkpoint += diff_lines;
+        }
+    }
+
+    /// The user reviewed the work so far
+    pub fn checkpoint(&mut self) {
+        self.actions_since_checkpoint = 0;
+        self.diff_lines_since_checkpoint = 0;
+    }
+
+    /// Hand over the summary of the finished turn and start a fresh one
+    pub fn take_turn_summary(&mut self) -> TurnSummary {
+        std::mem::take(&mut self.turn)
+    }
+
+    fn execute(&mut self, action: AgentAction, executor: &mut dyn AgentExecutor, root: &Path) -> Result<AgentResponse> {
+        let change = turn_summary::measure(&action, root);
+        self.record(&action, change.as_ref().map_or(0, FileChange::lines_changed));
+        let response = executor.execute_action(action.clone())?;
+        self.turn.record(&action, change, response.success);
+        Ok(response)
+    }
+
+    /// The user reviewed these actions: run them all, then start counting towards the next checkpoint
+    pub fn run_reviewed(&mut self, actions: Vec<AgentAction>, executor: &mut dyn AgentExecutor, root: &Path) -> Result<Vec<AgentResponse>> {
+        let responses = actions
+            .into_iter()
+            .map(|action| self.execute(action, executor, root))
+            .collect::<Result<Vec<_>>>()?;
+        self.checkpoint();
+        Ok(responses)
+    }
+
+    /// Run actions until one needs review; the rest are handed back as `pending`
+    pub fn run(&mut self, actions: Vec<AgentAction>, executor: &mut dyn AgentExecutor, root: &Path) -> Result<SupervisedRun> {
+        // Reads never need review, and neither the turn summary nor the checkpoints count them
+        if actions.iter().all(AgentAction::is_read_only) {
+            return Ok(SupervisedRun { responses: executor.execute_batch(actions)?, pending: Vec::new() });
+        }
+
+        let mut responses = Vec::new();
+        let mut actions = actions.into_iter();
+
+        while let Some(action) = actions.next() {
+            if self.needs_review(&action) {
+                let mut pending = vec![action];
+                pending.extend(actions);
+                return Ok(SupervisedRun { responses, pending });
+            }
+            responses.push(self.execute(action, executor, root)?);
+        }
+
+        Ok(SupervisedRun { responses, pending: Vec::new() })
+    }
+
+    /// The user approved a paused batch: run its first action, then continue until the next checkpoint
+    pub fn resume(&mut self, pending: Vec<AgentAction>, executor: &mut dyn AgentExecutor, root: &Path) -> Result<SupervisedRun> {
+        self.checkpoint();
+        let mut pending = pending.into_iter();
+        let mut responses = Vec::new();
+
+        if let Some(action) = pending.next() {
+            responses.push(self.execute(action, executor, root)?);
+        }
+
+        let mut run = self.run(pending.collect(), executor, root)?;
+        responses.append(&mut run.responses);
+        run.responses = responses;
+        Ok(run)
+    }
+}
+
+fn is_modifying(action: &AgentAction) -> bool {
+    !matches!(
+        action,
+        AgentAction::ReadFile { .. }
+            | AgentAction::ListDirectory { .. }
+            | AgentAction::SearchFiles { .. }
+            | AgentAction::GetFileInfo { .. }
+            | AgentAction::GitStatus {}
+            | AgentAction::GitDiff { .. }
+            | AgentAction::WriteNote { .. }
+            | AgentAction::ReadNotes {}
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn write(content: &str) -> AgentAction {
+        AgentAction::WriteFile { path: PathBuf::from("/nonexistent/file.txt"), content: content.to_string() }
+    }
+
+    #[test]
+    fn test_supervised_checkpoints() {
+        let mut supervisor = Supervisor::new(AutonomySettings {
+            level: AutonomyLevel::Supervised,
+            checkpoint_every_actions: 2,
+            checkpoint_diff_lines: 10,
+        });
+        let read = AgentAction::ReadFile { path: PathBuf::from("a.rs") };
+
+        assert!(!supervisor.needs_review(&write("x")));
+        supervisor.record(&write("x"), 1);
+        supervisor.record(&read, 0);
+        assert!(!supervisor.needs_review(&write("x")));
+        supervisor.record(&write("x"), 1);
+        assert!(supervisor.needs_review(&write("x"))); // Action threshold
+        assert!(!supervisor.needs_review(&read));
+
+        supervisor.checkpoint();
+        supervisor.record(&write("x"), 12);
+        assert!(supervisor.needs_review(&write("x"))); // Diff threshold
+
+        supervisor.checkpoint();
+        supervisor.settings.level = AutonomyLevel::ConfirmEach;
+        assert!(supervisor.needs_review(&write("x")));
+        supervisor.settings.level = AutonomyLevel::Autonomous;
+        supervisor.record(&write("x"), 1000);
+        assert!(!supervisor.needs_review(&write("x")));
+        let wipe = AgentAction::ExecuteCommand { command: "rm -rf build".to_string(), working_dir: None };
+        assert!(supervisor.needs_review(&wipe));
+    }
+
+    #[test]
+    fn test_concurrent_reads_keep_their_order() {
+        let dir = std::env::temp_dir().join(format!("i4z-reads-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let reads: Vec<AgentAction> = (0..20)
+            .map(|i| {
+                std::fs::write(dir.join(format!("{}.txt", i)), i.to_string()).unwrap();
+                AgentAction::ReadFile { path: PathBuf::from(format!("{}.txt", i)) }
+            })
+            .collect();
+        let mut executor = crate::agent::executor::DefaultAgentExecutor::new(dir.clone());
+        let mut supervisor = Supervisor::new(AutonomySettings::default());
+
+        let run = supervisor.run(reads, &mut executor, &dir).unwrap();
+        let contents: Vec<String> = run.responses.into_iter().map(|response| response.data.unwrap_or_default()).collect();
+        assert_eq!(contents, (0..20).map(|i| i.to_string()).collect::<Vec<_>>());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
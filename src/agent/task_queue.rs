@@ -0,0 +1,151 @@
+use super::orchestrator::StopReason;
+use crate::api::GroqMessage;
+
+/// Finished tasks kept for the Tasks panel; older ones are forgotten
+const MAX_FINISHED: usize = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskStatus {
+    Queued,
+    Running,
+    Finished(StopReason),
+}
+
+/// One agent run the user asked for: queued until the agent is free, then run on its own
+#[derive(Debug, Clone)]
+pub struct AgentTask {
+    pub id: usize,
+    pub title: String, // What the user wrote, as shown in the chat
+    pub status: TaskStatus,
+    pub progress: String, // Latest progress line while it runs
+    pub review_changes: bool, // Every change it makes goes through the approval panel
+    pub cancel_requested: bool, // Stop at the next step
+    message: Option<GroqMessage>, // Sent to the model when the task starts
+}
+
+/// Agent runs in the order they were asked for; one runs at a time, since they share the
+/// conversation and the workspace
+#[derive(Debug, Default)]
+pub struct TaskQueue {
+    tasks: Vec<AgentTask>,
+    next_id: usize,
+}
+
+impl TaskQueue {
+    pub fn push(&mut self, title: &str, message: GroqMessage, review_changes: bool) -> usize {
+        self.add(title, TaskStatus::Queued, Some(message), review_changes)
+    }
+
+    /// A task that starts right away, its message already sent
+    pub fn begin(&mut self, title: &str, review_changes: bool) -> usize {
+        self.add(title, TaskStatus::Running, None, review_changes)
+    }
+
+    fn add(&mut self, title: &str, status: TaskStatus, message: Option<GroqMessage>, review_changes: bool) -> usize {
+        self.next_id += 1;
+        self.tasks.push(AgentTask {
+            id: self.next_id,
+            title: title.to_string(),
+            status,
+            progress: String::new(),
+            review_changes,
+            cancel_requested: false,
+            message,
+        });
+        self.next_id
+    }
+
+    pub fn tasks(&self) -> &[AgentTask] {
+        &self.tasks
+    }
+
+    pub fn queued(&self) -> usize {
+        self.tasks.iter().filter(|task| task.status == TaskStatus::Queued).count()
+    }
+
+    pub fn running(&mut self) -> Option<&mut AgentTask> {
+        self.tasks.iter_mut().find(|task| task.status == TaskStatus::Running)
+    }
+
+    /// Mark the oldest queued task as running and hand over what to send for it
+    pub fn start_next(&mut self) -> Option<(AgentTask, GroqMessage)> {
+        let task = self.tasks.iter_mut().find(|task| task.status == TaskStatus::Queued && task.message.is_some())?;
+        let message = task.message.take()?;
+        task.status = TaskStatus::Running;
+        Some((task.clone(), message))
+    }
+
+    pub fn set_progress(&mut self, progress: &str) {
+        if let Some(task) = self.running() {
+            task.progress = progress.to_string();
+        }
+    }
+
+    pub fn finish_running(&mut self, reason: StopReason) {
+        if let Some(task) = self.running() {
+            task.status = TaskStatus::Finished(reason);
+        }
+        let is_finished = |task: &AgentTask| matches!(task.status, TaskStatus::Finished(_));
+        if self.tasks.iter().filter(|task| is_finished(task)).count() > MAX_FINISHED {
+            if let Some(oldest) = self.tasks.iter().position(is_finished) {
+                self.tasks.remove(oldest);
+            }
+        }
+    }
+
+    /// Cancel a task: a queued one never starts, a running one stops at its next step. Returns
+    /// whether it was the running one
+    pub fn cancel(&mut self, id: usize) -> bool {
+        let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) else {
+            return false;
+        };
+        match task.status {
+            TaskStatus::Queued => {
+                task.status = TaskStatus::Finished(StopReason::Cancelled);
+                task.message = None;
+                false
+            }
+            TaskStatus::Running => {
+                task.cancel_requested = true;
+                true
+            }
+            TaskStatus::Finished(_) => false,
+        }
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.tasks.retain(|task| !matches!(task.status, TaskStatus::Finished(_)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::GroqClient;
+
+    #[test]
+    fn test_task_queue() {
+        let mut queue = TaskQueue::default();
+        let first = queue.push("fix the build", GroqClient::create_text_message("user", "fix the build"), false);
+        let second = queue.push("add docs", GroqClient::create_text_message("user", "add docs"), false);
+        let third = queue.push("write tests", GroqClient::create_text_message("user", "write tests"), true);
+        assert_eq!(queue.queued(), 3);
+
+        let (task, _) = queue.start_next().unwrap();
+        assert_eq!(task.id, first);
+        queue.set_progress("step 1/10");
+        assert!(!queue.cancel(second));
+        assert!(queue.cancel(first));
+        assert!(queue.running().is_some_and(|task| task.cancel_requested));
+        queue.finish_running(StopReason::Cancelled);
+
+        // The cancelled one is skipped
+        let (task, _) = queue.start_next().unwrap();
+        assert_eq!((task.id, task.review_changes), (third, true));
+        queue.finish_running(StopReason::Completed);
+        assert!(queue.start_next().is_none());
+
+        queue.clear_finished();
+        assert!(queue.tasks().is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use super::command::{CommandLimits, CommandOutput};
+use super::AgentResponse;
+use std::path::Path;
+
+/// Lines of output kept per failing test
+const MAX_DETAIL_LINES: usize = 40;
+/// Lines of output kept when no failing test could be picked out, e.g. a build error
+const MAX_TAIL_LINES: usize = 60;
+
+/// The usual test command for the project at `root`, going by its manifest
+pub fn detect_command(root: &Path) -> Option<&'static str> {
+    [
+        ("Cargo.toml", "cargo test"),
+        ("package.json", "npm test"),
+        ("go.mod", "go test ./..."),
+        ("pyproject.toml", "pytest"),
+        ("pytest.ini", "pytest"),
+        ("setup.py", "pytest"),
+    ]
+    .into_iter()
+    .find(|(manifest, _)| root.join(manifest).exists())
+    .map(|(_, command)| command)
+}
+
+/// `command` narrowed to tests matching `filter`. The filter ends up in a shell command line, so
+/// only test-name characters are accepted
+pub fn with_filter(command: &str, filter: Option<&str>) -> Result<String, String> {
+    match filter.map(str::trim).filter(|filter| !filter.is_empty()) {
+        None => Ok(command.to_string()),
+        Some(filter) if filter.chars().all(|c| c.is_alphanumeric() || "_:./-".contains(c)) => {
+            Ok(format!("{} {}", command, filter))
+        }
+        Some(filter) => Err(format!("Test filter may only contain letters, digits and _:./-, got `{}`", filter)),
+    }
+}
+
+/// Failing tests in cargo, pytest or go test output, each with the output it printed
+pub fn failures(output: &str) -> Vec<(String, String)> {
+    let mut failures: Vec<(String, String)> = Vec::new();
+    for line in output.lines() {
+        let name = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" ... FAILED")) // cargo
+            .or_else(|| line.strip_prefix("FAILED ").map(|rest| rest.split(" - ").next().unwrap_or(rest))) // pytest
+            .or_else(|| line.strip_prefix("--- FAIL: ").and_then(|rest| rest.split_whitespace().next())); // go
+        if let Some(name) = name.filter(|name| !failures.iter().any(|(known, _)| known == name)) {
+            failures.push((name.to_string(), String::new()));
+        }
+    }
+
+    // cargo prints what each failing test wrote under `---- name stdout ----`
+    let mut current: Option<usize> = None;
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("---- ").and_then(|rest| rest.strip_suffix(" stdout ----")) {
+            current = failures.iter().position(|(known, _)| known == name);
+            continue;
+        }
+        if line.starts_with("---- ") || line == "failures:" {
+            current = None;
+        }
+        if let Some((_, details)) = current.and_then(|index| failures.get_mut(index)) {
+            if details.lines().count() < MAX_DETAIL_LINES {
+                details.push_str(line);
+                details.push('\n');
+            }
+        }
+    }
+    failures
+}
+
+/// What the model is told about a test run: a pass, or which tests failed and why
+pub fn report(output: CommandOutput, command: &str, limits: &CommandLimits) -> AgentResponse {
+    if output.timed_out || output.killed {
+        return output.into_response(command, limits);
+    }
+    let text = format!("{}\n{}", output.stdout, output.stderr);
+    if output.success {
+        let summary: Vec<&str> = text.lines().filter(|line| line.starts_with("test result:")).collect();
+        return AgentResponse::success(format!("Tests passed: `{}`", command), Some(summary.join("\n")));
+    }
+
+    let failures = failures(&text);
+    if failures.is_empty() {
+        // Nothing recognisable as a test failed, so it is most likely the build; its errors are at the end
+        let lines: Vec<&str> = text.lines().collect();
+        let tail = lines[lines.len().saturating_sub(MAX_TAIL_LINES)..].join("\n");
+        return AgentResponse::error(format!("Tests did not pass: `{}`", command), tail);
+    }
+    let names: Vec<&str> = failures.iter().map(|(name, _)| name.as_str()).collect();
+    let details: Vec<String> = failures
+        .iter()
+        .map(|(name, details)| format!("---- {} ----\n{}", name, details.trim_end()))
+        .collect();
+    AgentResponse::error(format!("{} test(s) failed: {}", failures.len(), names.join(", ")), details.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failures() {
+        let cargo = "running 3 tests\ntest parser::tests::ok ... ok\ntest parser::tests::empty ... FAILED\ntest lexer::tests::eof ... FAILED\n\nfailures:\n\n---- parser::tests::empty stdout ----\nthread 'parser::tests::empty' panicked at src/parser.rs:10:5:\nassertion failed: tokens.is_empty()\n\n---- lexer::tests::eof stdout ----\nthread 'lexer::tests::eof' panicked at src/lexer.rs:3:9\n\nfailures:\n    parser::tests::empty\n    lexer::tests::eof\n\ntest result: FAILED. 1 passed; 2 failed\n";
+        let found = failures(cargo);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].0, "parser::tests::empty");
+        assert!(found[0].1.contains("assertion failed: tokens.is_empty()"));
+        assert!(!found[0].1.contains("lexer"));
+
+        let output = CommandOutput { stdout: cargo.to_string(), ..CommandOutput::default() };
+        let response = report(output, "cargo test", &CommandLimits::default());
+        assert_eq!(response.message, "2 test(s) failed: parser::tests::empty, lexer::tests::eof");
+
+        assert_eq!(failures("FAILED tests/test_api.py::test_get - KeyError: 'id'")[0].0, "tests/test_api.py::test_get");
+        assert_eq!(with_filter("cargo test", Some("parser::tests")), Ok("cargo test parser::tests".to_string()));
+        assert!(with_filter("cargo test", Some("x; rm -rf /")).is_err());
+    }
+}
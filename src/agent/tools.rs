@@ -0,0 +1,205 @@
+use super::{AgentAction, AgentResponse};
+use crate::api::{FunctionDefinition, Tool, ToolCall};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+/// Tool name, the `AgentAction` variant it maps to, and what the model is told it does
+const TOOLS: [(&str, &str, &str); 19] = [
+    ("read_file", "ReadFile", "Read a file in the project"),
+    ("write_file", "WriteFile", "Create a file, or replace the whole content of an existing one"),
+    ("create_directory", "CreateDirectory", "Create a directory and any missing parents"),
+    ("delete_file", "DeleteFile", "Delete a file or directory"),
+    ("execute_command", "ExecuteCommand", "Run a shell command and return its output"),
+    ("search_files", "SearchFiles", "Search a directory tree (skipping .gitignored files) for file names, or with in_contents for lines, matching a regex"),
+    ("replace_in_file", "ReplaceInFile", "Replace every occurrence of `old` in a file with `new`"),
+    ("apply_patch", "ApplyPatch", "Edit a file with a unified diff; prefer this to rewriting whole files"),
+    ("append_to_file", "AppendToFile", "Add text to the end of a file, creating it if needed"),
+    ("move_file", "MoveFile", "Move or rename a file or directory; the destination must not exist"),
+    ("copy_file", "CopyFile", "Copy a file; the destination must not exist"),
+    ("list_directory", "ListDirectory", "List the entries of a directory"),
+    ("get_file_info", "GetFileInfo", "Size, type and modification time of a file"),
+    ("run_tests", "RunTests", "Run the project's tests and get the failing ones with their output; run again after fixing until they pass"),
+    ("git_status", "GitStatus", "Branch and changed files of the project's git repository"),
+    ("git_diff", "GitDiff", "Uncommitted changes as a unified diff, of the whole repository or one path"),
+    ("git_commit", "GitCommit", "Commit the files you changed since your last commit; other changes in the repository stay uncommitted"),
+    ("write_note", "WriteNote", "Add to your scratchpad for this session: decisions, findings, TODOs. Notes stay with you when old messages are trimmed"),
+    ("read_notes", "ReadNotes", "Read your scratchpad for this session"),
+];
+
+/// Every agent action, described as a function the model can call
+pub fn definitions() -> Vec<Tool> {
+    TOOLS
+        .iter()
+        .map(|(name, _, description)| Tool {
+            kind: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.to_string(),
+                description: description.to_string(),
+                parameters: parameters(name),
+            },
+        })
+        .collect()
+}
+
+/// JSON schema of a tool's arguments; paths are relative to the project root
+fn parameters(name: &str) -> Value {
+    let string = |description: &str| json!({"type": "string", "description": description});
+    let (properties, required) = match name {
+        "write_file" => (
+            json!({"path": string("File to write"), "content": string("Complete new content")}),
+            vec!["path", "content"],
+        ),
+        "execute_command" => (
+            json!({"command": string("Shell command"), "working_dir": string("Directory to run it in, defaults to the project root")}),
+            vec!["command"],
+        ),
+        "search_files" => (
+            json!({
+                "pattern": string("Regular expression"),
+                "directory": string("Where to search, defaults to the project root"),
+                "in_contents": {"type": "boolean", "description": "Match lines inside files and report them with line numbers, instead of file names"}
+            }),
+            vec!["pattern"],
+        ),
+        "replace_in_file" => (
+            json!({"path": string("File to edit"), "old": string("Exact text to replace"), "new": string("Replacement text")}),
+            vec!["path", "old", "new"],
+        ),
+        "append_to_file" => (
+            json!({"path": string("File to append to"), "content": string("Text to add, including any leading newline")}),
+            vec!["path", "content"],
+        ),
+        "move_file" | "copy_file" => (
+            json!({"from": string("Existing path"), "to": string("New path")}),
+            vec!["from", "to"],
+        ),
+        "apply_patch" => (
+            json!({"path": string("File to patch"), "unified_diff": string("Unified diff with @@ hunks and a few lines of context")}),
+            vec!["path", "unified_diff"],
+        ),
+        "git_status" | "read_notes" => (json!({}), vec![]),
+        "write_note" => (json!({"note": string("What to remember")}), vec!["note"]),
+        "git_diff" => (
+            json!({
+                "path": string("Only this file or directory"),
+                "staged": {"type": "boolean", "description": "Show staged changes instead of unstaged ones"}
+            }),
+            vec![],
+        ),
+        "git_commit" => (json!({"message": string("Commit message: a short summary line, then details if needed")}), vec!["message"]),
+        "run_tests" => (json!({"filter": string("Only run tests whose name contains this, e.g. a module path")}), vec![]),
+        _ => (json!({"path": string("Path relative to the project root")}), vec!["path"]),
+    };
+    json!({"type": "object", "properties": properties, "required": required})
+}
+
+/// Every action written the way strict JSON mode expects it, one per line with its description,
+/// e.g. `{"ReadFile": {"path": string}} - Read a file in the project`; `?` marks optional arguments
+pub fn json_action_catalog() -> String {
+    TOOLS
+        .iter()
+        .map(|(name, variant, description)| {
+            let schema = parameters(name);
+            let required: Vec<&str> = schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str).collect();
+            let arguments: Vec<String> = schema["properties"]
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(key, property)| {
+                    let optional = if required.contains(&key.as_str()) { "" } else { "?" };
+                    format!("\"{}\"{}: {}", key, optional, property["type"].as_str().unwrap_or("string"))
+                })
+                .collect();
+            format!("{{\"{}\": {{{}}}}} - {}", variant, arguments.join(", "), description)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Check a `{"Variant": {arguments}}` object from strict JSON mode against its tool's schema and
+/// build the action; errors say what to fix
+pub fn parse_json_action(value: &Value) -> Result<AgentAction> {
+    let Some((variant, arguments)) = value.as_object().filter(|object| object.len() == 1).and_then(|object| object.iter().next()) else {
+        return Err(anyhow!("expected an object with an action name as its only key, got {}", value));
+    };
+    let (name, _, _) = TOOLS
+        .iter()
+        .find(|(_, known, _)| known == variant)
+        .ok_or_else(|| anyhow!("unknown action `{}`", variant))?;
+    let schema = parameters(name);
+    let arguments = arguments.as_object().ok_or_else(|| anyhow!("{}: arguments must be an object", variant))?;
+    for required in schema["required"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+        if !arguments.contains_key(required) {
+            return Err(anyhow!("{}: missing `{}`", variant, required));
+        }
+    }
+    for (key, argument) in arguments {
+        let kind = schema["properties"][key]["type"]
+            .as_str()
+            .ok_or_else(|| anyhow!("{}: unknown argument `{}`", variant, key))?;
+        let valid = argument.is_null() || if kind == "boolean" { argument.is_boolean() } else { argument.is_string() };
+        if !valid {
+            return Err(anyhow!("{}: `{}` must be a {}", variant, key, kind));
+        }
+    }
+    serde_json::from_value(json!({ variant: arguments })).map_err(|e| anyhow!("{}: {}", variant, e))
+}
+
+/// Turn a tool call from the model back into the action it names
+pub fn parse_call(call: &ToolCall) -> Result<AgentAction> {
+    let (_, variant, _) = TOOLS
+        .iter()
+        .find(|(name, _, _)| *name == call.function.name)
+        .ok_or_else(|| anyhow!("Unknown tool: {}", call.function.name))?;
+    let arguments: Value = if call.function.arguments.trim().is_empty() {
+        json!({})
+    } else {
+        serde_json::from_str(&call.function.arguments)?
+    };
+    serde_json::from_value(json!({ *variant: arguments }))
+        .map_err(|e| anyhow!("Invalid arguments for {}: {}", call.function.name, e))
+}
+
+/// What goes back to the model as the result of a call
+pub fn result_content(response: &AgentResponse) -> String {
+    serde_json::to_string(response).unwrap_or_else(|_| response.message.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::FunctionCall;
+    use std::path::Path;
+
+    fn call(name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: "call_1".to_string(),
+            kind: "function".to_string(),
+            function: FunctionCall { name: name.to_string(), arguments: arguments.to_string() },
+        }
+    }
+
+    #[test]
+    fn test_tool_calls() {
+        assert_eq!(definitions().len(), 19);
+        assert!(matches!(parse_call(&call("git_status", "")).unwrap(), AgentAction::GitStatus {}));
+        assert_eq!(definitions()[6].function.parameters["required"], json!(["path", "old", "new"]));
+
+        let action = parse_call(&call("replace_in_file", r#"{"path": "src/main.rs", "old": "a", "new": "b"}"#)).unwrap();
+        assert!(matches!(action, AgentAction::ReplaceInFile { path, .. } if path == Path::new("src/main.rs")));
+        assert!(matches!(
+            parse_call(&call("execute_command", r#"{"command": "cargo test"}"#)).unwrap(),
+            AgentAction::ExecuteCommand { working_dir: None, .. }
+        ));
+        assert!(parse_call(&call("format_disk", "{}")).is_err());
+        assert!(parse_call(&call("write_file", r#"{"path": "a.txt"}"#)).is_err()); // Missing content
+
+        assert!(json_action_catalog().contains(r#"{"SearchFiles": {"directory"?: string, "in_contents"?: boolean, "pattern": string}}"#));
+        assert!(parse_json_action(&json!({"ReadFile": {"path": "src/main.rs"}})).is_ok());
+        let error = |value: Value| parse_json_action(&value).unwrap_err().to_string();
+        assert_eq!(error(json!({"WriteFile": {"path": "a.txt"}})), "WriteFile: missing `content`");
+        assert_eq!(error(json!({"ReadFile": {"path": "a", "mode": "r"}})), "ReadFile: unknown argument `mode`");
+        assert_eq!(error(json!({"SearchFiles": {"pattern": "x", "in_contents": "yes"}})), "SearchFiles: `in_contents` must be a boolean");
+        assert_eq!(error(json!({"FormatDisk": {}})), "unknown action `FormatDisk`");
+    }
+}
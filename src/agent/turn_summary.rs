@@ -0,0 +1,213 @@
+use super::{patch, AgentAction};
+use crate::diff::{diff_lines, unified_diff, DiffLine};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Deleted,
+}
+
+impl ChangeKind {
+    fn marker(self) -> char {
+        match self {
+            ChangeKind::Created => 'A',
+            ChangeKind::Modified => 'M',
+            ChangeKind::Deleted => 'D',
+        }
+    }
+}
+
+/// Effect of one action on one path, measured before the action runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileChange {
+    pub path: PathBuf, // Relative to the project root when inside it
+    pub kind: ChangeKind,
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl FileChange {
+    pub fn lines_changed(&self) -> usize {
+        self.added + self.removed
+    }
+}
+
+/// Measure what a file action is about to change; None for actions that leave files alone
+pub fn measure(action: &AgentAction, root: &Path) -> Option<FileChange> {
+    let resolve = |path: &Path| root.join(path);
+    let relative = |path: &Path| resolve(path).strip_prefix(root).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf());
+    let read = |path: &Path| fs::read_to_string(resolve(path)).ok();
+
+    let change = |path: &Path, kind, added, removed| FileChange { path: relative(path), kind, added, removed };
+    match action {
+        AgentAction::WriteFile { path, content } => Some(match read(path) {
+            Some(old) => {
+                let lines = diff_lines(&old, content);
+                let added = lines.iter().filter(|line| matches!(line, DiffLine::Insert(_))).count();
+                let removed = lines.iter().filter(|line| matches!(line, DiffLine::Delete(_))).count();
+                change(path, ChangeKind::Modified, added, removed)
+            }
+            None => change(path, ChangeKind::Created, content.lines().count(), 0),
+        }),
+        AgentAction::ReplaceInFile { path, old, new } => {
+            let occurrences = read(path)?.matches(old.as_str()).count();
+            Some(change(path, ChangeKind::Modified, occurrences * new.lines().count(), occurrences * old.lines().count()))
+        }
+        AgentAction::ApplyPatch { path, unified_diff } => {
+            let old = read(path)?;
+            let new = patch::apply(&old, unified_diff).ok()?;
+            let lines = diff_lines(&old, &new);
+            let added = lines.iter().filter(|line| matches!(line, DiffLine::Insert(_))).count();
+            let removed = lines.iter().filter(|line| matches!(line, DiffLine::Delete(_))).count();
+            Some(change(path, ChangeKind::Modified, added, removed))
+        }
+        AgentAction::AppendToFile { path, content } => Some(match read(path) {
+            Some(_) => change(path, ChangeKind::Modified, content.lines().count(), 0),
+            None => change(path, ChangeKind::Created, content.lines().count(), 0),
+        }),
+        // Moved content isn't changed, so only the new name counts
+        AgentAction::MoveFile { to, .. } => Some(change(to, ChangeKind::Created, 0, 0)),
+        AgentAction::CopyFile { from, to } => {
+            let added = read(from).map(|content| content.lines().count()).unwrap_or(0);
+            Some(change(to, ChangeKind::Created, added, 0))
+        }
+        AgentAction::DeleteFile { path } => {
+            let removed = read(path).map(|content| content.lines().count()).unwrap_or(0);
+            Some(change(path, ChangeKind::Deleted, 0, removed))
+        }
+        AgentAction::CreateDirectory { path } => Some(change(path, ChangeKind::Created, 0, 0)),
+        _ => None,
+    }
+}
+
+/// Unified diff a file-writing action is about to make, taken before it runs
+pub fn preview_diff(action: &AgentAction, root: &Path) -> Option<(String, String)> {
+    let (path, new) = match action {
+        AgentAction::WriteFile { path, content } => (path, content.clone()),
+        AgentAction::ReplaceInFile { path, old, new } => {
+            (path, fs::read_to_string(root.join(path)).ok()?.replace(old.as_str(), new))
+        }
+        AgentAction::ApplyPatch { path, unified_diff } => {
+            (path, patch::apply(&fs::read_to_string(root.join(path)).ok()?, unified_diff).ok()?)
+        }
+        AgentAction::AppendToFile { path, content } => {
+            (path, fs::read_to_string(root.join(path)).unwrap_or_default() + content)
+        }
+        AgentAction::CopyFile { from, to } => (to, fs::read_to_string(root.join(from)).ok()?),
+        _ => return None,
+    };
+    let old = fs::read_to_string(root.join(path)).unwrap_or_default();
+    let name = path.display().to_string();
+    Some((name.clone(), unified_diff(&old, &new, &format!("a/{}", name), &format!("b/{}", name), 3)))
+}
+
+/// What an agent turn did to the workspace, so the model's next turn starts from an accurate picture
+#[derive(Debug, Clone, Default)]
+pub struct TurnSummary {
+    files: BTreeMap<PathBuf, FileChange>,
+    commands: Vec<(String, bool)>, // Command and whether it succeeded
+}
+
+impl TurnSummary {
+    pub fn record(&mut self, action: &AgentAction, change: Option<FileChange>, succeeded: bool) {
+        match action {
+            AgentAction::ExecuteCommand { command, .. } => self.commands.push((command.clone(), succeeded)),
+            AgentAction::RunTests { filter } => {
+                self.commands.push((format!("run_tests {}", filter.as_deref().unwrap_or_default()).trim_end().to_string(), succeeded))
+            }
+            AgentAction::GitCommit { message } => self.commands.push((format!("git commit -m {:?}", message), succeeded)),
+            _ => {}
+        }
+        let Some(change) = change.filter(|_| succeeded) else {
+            return;
+        };
+
+        match self.files.remove(&change.path) {
+            // Created and deleted within the same turn: nothing left to report
+            Some(previous) if previous.kind == ChangeKind::Created && change.kind == ChangeKind::Deleted => {}
+            Some(previous) => {
+                let kind = if previous.kind == ChangeKind::Created { ChangeKind::Created } else { change.kind };
+                let merged = FileChange {
+                    kind,
+                    added: previous.added + change.added,
+                    removed: previous.removed + change.removed,
+                    ..change
+                };
+                self.files.insert(merged.path.clone(), merged);
+            }
+            None => {
+                self.files.insert(change.path.clone(), change);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.commands.is_empty()
+    }
+
+    /// Compact message appended to the conversation after the turn, or None if nothing happened
+    pub fn to_message(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let added: usize = self.files.values().map(|change| change.added).sum();
+        let removed: usize = self.files.values().map(|change| change.removed).sum();
+        let mut message = format!(
+            "[Workspace summary] Files changed: {} (+{} -{})\n",
+            self.files.len(),
+            added,
+            removed
+        );
+        for change in self.files.values() {
+            message.push_str(&format!(
+                "  {} {} (+{} -{})\n",
+                change.kind.marker(),
+                change.path.display(),
+                change.added,
+                change.removed
+            ));
+        }
+        if !self.commands.is_empty() {
+            message.push_str("Commands run:\n");
+            for (command, succeeded) in &self.commands {
+                let status = if *succeeded { "" } else { " (failed)" };
+                message.push_str(&format!("  $ {}{}\n", command, status));
+            }
+        }
+        Some(message.trim_end().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, kind: ChangeKind, added: usize, removed: usize) -> Option<FileChange> {
+        Some(FileChange { path: PathBuf::from(path), kind, added, removed })
+    }
+
+    #[test]
+    fn test_turn_summary_message() {
+        let write = AgentAction::WriteFile { path: PathBuf::from("a.rs"), content: String::new() };
+        let command = AgentAction::ExecuteCommand { command: "cargo test".to_string(), working_dir: None };
+        let mut summary = TurnSummary::default();
+        assert_eq!(summary.to_message(), None);
+
+        summary.record(&write, change("src/lib.rs", ChangeKind::Modified, 3, 1), true);
+        summary.record(&write, change("src/lib.rs", ChangeKind::Modified, 2, 0), true);
+        summary.record(&write, change("tmp.rs", ChangeKind::Created, 4, 0), true);
+        summary.record(&write, change("tmp.rs", ChangeKind::Deleted, 0, 4), true);
+        summary.record(&write, change("failed.rs", ChangeKind::Created, 9, 0), false);
+        summary.record(&command, None, false);
+
+        assert_eq!(
+            summary.to_message().unwrap(),
+            "[Workspace summary] Files changed: 1 (+5 -1)\n  M src/lib.rs (+5 -1)\nCommands run:\n  $ cargo test (failed)"
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Changes kept per project; older ones can no longer be undone
+const MAX_CHANGES: usize = 200;
+/// Bytes one change may snapshot; an action that would overwrite or delete more is refused
+const MAX_SNAPSHOT_BYTES: u64 = 32 * 1024 * 1024;
+/// Lines the log may grow to before it is rewritten with just the changes still kept
+const MAX_LOG_ENTRIES: usize = 4 * MAX_CHANGES;
+const LOG_FILE: &str = "log.jsonl";
+const BLOB_DIR: &str = "blobs";
+
+/// A file as it was before an agent action touched it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub blob: Option<String>, // File in the log's blob directory holding the old content; None: the file did not exist yet
+}
+
+/// What one agent action overwrote or deleted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Change {
+    pub id: String,
+    pub session: String,
+    pub description: String, // e.g. "Edit src/main.rs"
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub snapshots: Vec<Snapshot>,
+    #[serde(skip)]
+    after: Option<Vec<Option<u64>>>, // Hash of each snapshot's file once the action ran; None for a missing file
+}
+
+/// One line of the log; changes are appended, never rewritten in place
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Entry {
+    Change(Change),
+    Settled { id: String, after: Vec<Option<u64>> },
+    Undone { id: String },
+}
+
+/// Per-project journal of agent file changes, kept on disk so they can also be reverted from
+/// another process (`agent undo` after the IDE has closed)
+pub struct UndoLog {
+    dir: PathBuf,
+    session: String, // Agent session recorded with new changes
+    pub changes: Vec<Change>,
+    entries: usize,          // Lines in the log file
+    pending: Option<String>, // Change recorded whose action hasn't been stamped yet
+}
+
+impl UndoLog {
+    pub fn for_project(project_dir: &Path, session: &str) -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("undo");
+        let key = project_key(project_dir);
+        let _ = fs::remove_file(dir.join(format!("{}.json", key))); // The single-file log of older versions
+        Ok(Self::at(dir.join(key), session))
+    }
+
+    pub fn at(dir: PathBuf, session: &str) -> Self {
+        let mut log = Self { dir, session: session.to_string(), changes: Vec::new(), entries: 0, pending: None };
+        log.reload();
+        log
+    }
+
+    pub fn session(&self) -> &str {
+        &self.session
+    }
+
+    /// Session of the newest change, e.g. to revert the last agent run from the CLI
+    pub fn last_session(&self) -> Option<&str> {
+        self.changes.last().map(|change| change.session.as_str())
+    }
+
+    /// Snapshot `paths` (every file below them, for directories) before an action changes them
+    pub fn record(&mut self, description: String, paths: &[PathBuf]) -> Result<()> {
+        self.settle()?;
+        let mut files = Vec::new();
+        for path in paths {
+            collect_files(path, &mut files)?;
+        }
+        let size: u64 = files.iter().filter_map(|path| fs::metadata(path).ok()).map(|metadata| metadata.len()).sum();
+        if size > MAX_SNAPSHOT_BYTES {
+            bail!("{} MB to snapshot, over the {} MB undo keeps per change", size >> 20, MAX_SNAPSHOT_BYTES >> 20);
+        }
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let blobs = self.dir.join(BLOB_DIR);
+        let mut snapshots = Vec::new();
+        for path in files {
+            let blob = match fs::read(&path) {
+                Ok(bytes) => {
+                    let name = format!("{}-{}", id, snapshots.len());
+                    fs::create_dir_all(&blobs)?;
+                    fs::write(blobs.join(&name), bytes)?;
+                    Some(name)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            snapshots.push(Snapshot { path, blob });
+        }
+        let change = Change {
+            id: id.clone(),
+            session: self.session.clone(),
+            description,
+            timestamp: chrono::Local::now(),
+            snapshots,
+            after: None,
+        };
+        self.reload();
+        self.append(&Entry::Change(change.clone()))?;
+        self.changes.push(change);
+        self.pending = Some(id);
+        Ok(())
+    }
+
+    /// Note what the files of the change just recorded look like now that its action ran, so undo
+    /// can tell when someone changed them since
+    pub fn settle(&mut self) -> Result<()> {
+        let Some(id) = self.pending.take() else {
+            return Ok(());
+        };
+        let Some(change) = self.changes.iter_mut().find(|change| change.id == id) else {
+            return Ok(());
+        };
+        let after: Vec<Option<u64>> = change.snapshots.iter().map(|snapshot| fingerprint(&snapshot.path)).collect();
+        change.after = Some(after.clone());
+        self.append(&Entry::Settled { id, after })
+    }
+
+    /// Put back the files of the newest change
+    pub fn undo_last(&mut self) -> Result<Option<Change>> {
+        self.settle()?;
+        self.reload();
+        let Some(change) = self.changes.last().cloned() else {
+            return Ok(None);
+        };
+        self.undo(&change)?;
+        Ok(Some(change))
+    }
+
+    /// Revert every change of `session`, newest first. Each one is logged as undone as soon as its
+    /// files are back, so a failure part way keeps what was undone before it
+    pub fn undo_session(&mut self, session: &str) -> Result<Vec<Change>> {
+        self.settle()?;
+        self.reload();
+        let mut undone = Vec::new();
+        while let Some(change) = self.changes.iter().rev().find(|change| change.session == session).cloned() {
+            if let Err(e) = self.undo(&change) {
+                return Err(match undone.len() {
+                    0 => e,
+                    count => anyhow!("{} (after undoing {} newer change(s))", e, count),
+                });
+            }
+            undone.push(change);
+        }
+        Ok(undone)
+    }
+
+    fn undo(&mut self, change: &Change) -> Result<()> {
+        if let Some(after) = &change.after {
+            for (snapshot, after) in change.snapshots.iter().zip(after) {
+                if fingerprint(&snapshot.path) != *after {
+                    bail!("{} was changed after `{}` - not overwriting it", snapshot.path.display(), change.description);
+                }
+            }
+        }
+        let blobs = self.dir.join(BLOB_DIR);
+        for snapshot in change.snapshots.iter().rev() {
+            match &snapshot.blob {
+                Some(blob) => {
+                    let content = fs::read(blobs.join(blob)).map_err(|e| anyhow!("Snapshot of {} is gone: {}", snapshot.path.display(), e))?;
+                    if let Some(parent) = snapshot.path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&snapshot.path, content)?;
+                }
+                None if snapshot.path.is_file() => fs::remove_file(&snapshot.path)?,
+                None => {}
+            }
+        }
+        self.append(&Entry::Undone { id: change.id.clone() })?;
+        self.changes.retain(|kept| kept.id != change.id);
+        for blob in change.snapshots.iter().filter_map(|snapshot| snapshot.blob.as_ref()) {
+            let _ = fs::remove_file(blobs.join(blob));
+        }
+        Ok(())
+    }
+
+    /// Another process may have recorded or undone changes meanwhile
+    fn reload(&mut self) {
+        let log = fs::read_to_string(self.dir.join(LOG_FILE)).unwrap_or_default();
+        let mut changes: Vec<Change> = Vec::new();
+        self.entries = 0;
+        for entry in log.lines().filter_map(|line| serde_json::from_str::<Entry>(line).ok()) {
+            self.entries += 1;
+            match entry {
+                Entry::Change(change) => changes.push(change),
+                Entry::Settled { id, after } => {
+                    if let Some(change) = changes.iter_mut().find(|change| change.id == id) {
+                        change.after = Some(after);
+                    }
+                }
+                Entry::Undone { id } => changes.retain(|change| change.id != id),
+            }
+        }
+        changes.drain(..changes.len().saturating_sub(MAX_CHANGES));
+        self.changes = changes;
+    }
+
+    fn append(&mut self, entry: &Entry) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let line = format!("{}\n", serde_json::to_string(entry)?);
+        OpenOptions::new().create(true).append(true).open(self.dir.join(LOG_FILE))?.write_all(line.as_bytes())?;
+        self.entries += 1;
+        if self.entries > MAX_LOG_ENTRIES {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the log with only the changes still kept, and drop the blobs nothing refers to
+    fn compact(&mut self) -> Result<()> {
+        let mut log = String::new();
+        for change in &self.changes {
+            log.push_str(&serde_json::to_string(&Entry::Change(change.clone()))?);
+            log.push('\n');
+            if let Some(after) = &change.after {
+                log.push_str(&serde_json::to_string(&Entry::Settled { id: change.id.clone(), after: after.clone() })?);
+                log.push('\n');
+            }
+        }
+        // Write then rename, so a crash never leaves a half-written log behind
+        let temp = self.dir.join(format!("{}.tmp", LOG_FILE));
+        fs::write(&temp, &log)?;
+        fs::rename(&temp, self.dir.join(LOG_FILE))?;
+        self.entries = log.lines().count();
+
+        let kept: HashSet<&String> = self.changes.iter().flat_map(|change| &change.snapshots).filter_map(|snapshot| snapshot.blob.as_ref()).collect();
+        for entry in fs::read_dir(self.dir.join(BLOB_DIR))?.flatten() {
+            if !entry.file_name().to_str().is_some_and(|name| kept.contains(&name.to_string())) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names a project's files in the cache directory: a hash of its canonical path
+pub fn project_key(project_dir: &Path) -> String {
+    let project = project_dir.canonicalize().unwrap_or_else(|_| project_dir.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    project.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// `path`, or every file below it when it is a directory
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Hash of a file's content; None when it isn't there
+fn fingerprint(path: &Path) -> Option<u64> {
+    let content = fs::read(path).ok()?;
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_undo_log() {
+        let dir = std::env::temp_dir().join(format!("i4z-undo-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "fn main() {}\n").unwrap();
+        let log_path = dir.join("undo");
+
+        let mut log = UndoLog::at(log_path.clone(), "first");
+        log.record("Edit src/main.rs".to_string(), &[dir.join("src/main.rs")]).unwrap();
+        fs::write(dir.join("src/main.rs"), "changed\n").unwrap();
+        log.settle().unwrap();
+        log.record("Write notes.txt".to_string(), &[dir.join("notes.txt")]).unwrap();
+        fs::write(dir.join("notes.txt"), "new file\n").unwrap();
+        log.settle().unwrap();
+
+        // A later session deletes the whole directory
+        let mut later = UndoLog::at(log_path.clone(), "second");
+        later.record("Move src to lib".to_string(), &[dir.join("src"), dir.join("lib")]).unwrap();
+        fs::rename(dir.join("src"), dir.join("lib")).unwrap();
+        later.settle().unwrap();
+        assert_eq!(later.last_session(), Some("second"));
+
+        // Someone wrote a new src/main.rs since, so undo leaves it alone
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "edited\n").unwrap();
+        assert!(later.undo_last().is_err());
+        fs::remove_dir_all(dir.join("src")).unwrap();
+
+        assert_eq!(later.undo_last().unwrap().unwrap().description, "Move src to lib");
+        assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), "changed\n");
+
+        // The first log sees what the second one did, and reverts its own session
+        let undone = log.undo_session("first").unwrap();
+        assert_eq!(undone.len(), 2);
+        assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), "fn main() {}\n");
+        assert!(!dir.join("notes.txt").exists());
+        assert!(UndoLog::at(log_path.clone(), "third").changes.is_empty());
+        assert_eq!(fs::read_dir(log_path.join(BLOB_DIR)).unwrap().count(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
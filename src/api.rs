@@ -1,12 +1,23 @@
+use crate::api_error::ApiError;
+use crate::context_window;
+use crate::provider::ProviderKind;
+use crate::retry::{self, RetryPolicy, RetryStatus};
 use anyhow::{anyhow, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::OnceLock;
 use std::time::Duration;
+use tokio::sync::mpsc;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GroqMessage {
     pub role: String,
     pub content: MessageContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>, // Assistant: the tools it asked to call
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>, // Tool: the call this message answers
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +41,35 @@ pub struct ImageUrl {
     pub url: String,
 }
 
+/// A function the model may call instead of answering in text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    #[serde(rename = "type")]
+    pub kind: String, // Always "function"
+    pub function: FunctionDefinition,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value, // JSON schema of the arguments
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub function: FunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: String, // JSON object, as text
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct GroqRequest {
     pub model: String,
@@ -37,6 +77,8 @@ pub struct GroqRequest {
     pub temperature: f32,
     pub max_tokens: Option<u32>,
     pub stream: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<Tool>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -59,84 +101,455 @@ pub struct Choice {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ResponseMessage {
     pub role: String,
-    pub content: String,
+    #[serde(default)]
+    pub content: Option<String>, // Null when the model only calls tools
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ToolCall>>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+/// How requests are shaped for the connection: normal, or lean for flaky mobile/SSH links
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetworkProfile {
+    pub low_bandwidth: bool,
+    pub timeout: Duration,
+    pub max_tokens: u32,
+    pub recent_messages: Option<usize>, // Non-system messages kept in the request; None keeps all
+    pub max_message_chars: Option<usize>, // Longer messages keep only their tail
+    pub allow_images: bool,
+    pub retries: u32, // Retries after a timeout, each with a smaller payload
+}
+
+impl NetworkProfile {
+    pub fn normal() -> Self {
+        Self {
+            low_bandwidth: false,
+            timeout: Duration::from_secs(60),
+            max_tokens: 4096,
+            recent_messages: None,
+            max_message_chars: None,
+            allow_images: true,
+            retries: 0,
+        }
+    }
+
+    pub fn low_bandwidth() -> Self {
+        Self {
+            low_bandwidth: true,
+            timeout: Duration::from_secs(180),
+            max_tokens: 1024,
+            recent_messages: Some(6),
+            max_message_chars: Some(6000),
+            allow_images: false,
+            retries: 2,
+        }
+    }
+
+    pub fn for_mode(low_bandwidth: bool) -> Self {
+        if low_bandwidth { Self::low_bandwidth() } else { Self::normal() }
+    }
+
+    /// Next attempt after a timeout: half the history and half the answer length
+    pub(crate) fn smaller(self) -> Self {
+        Self {
+            max_tokens: (self.max_tokens / 2).max(256),
+            recent_messages: Some(self.recent_messages.unwrap_or(8).div_ceil(2).max(1)),
+            max_message_chars: Some(self.max_message_chars.unwrap_or(12_000) / 2),
+            ..self
+        }
+    }
+}
+
+/// Shrink a request to what the profile allows, keeping system messages and the latest turns
+pub fn trim_messages(messages: &[GroqMessage], profile: &NetworkProfile) -> Vec<GroqMessage> {
+    let non_system = messages.iter().filter(|msg| msg.role != "system").count();
+    let skip = profile.recent_messages.map_or(0, |keep| non_system.saturating_sub(keep));
+
+    let mut skipped = 0;
+    let mut kept_any = false;
+    messages
+        .iter()
+        .filter(|msg| {
+            if msg.role == "system" {
+                return true;
+            }
+            if skipped >= skip {
+                // Tool results can't be sent without the assistant turn that asked for them
+                if msg.role == "tool" && !kept_any {
+                    return false;
+                }
+                kept_any = true;
+                return true;
+            }
+            skipped += 1;
+            false
+        })
+        .map(|msg| {
+            let content = match &msg.content {
+                MessageContent::Text(text) => MessageContent::Text(truncate_head(text, profile.max_message_chars)),
+                MessageContent::MultiModal(parts) if !profile.allow_images => {
+                    let text: Vec<&str> = parts
+                        .iter()
+                        .filter_map(|part| match part {
+                            ContentPart::Text { text } => Some(text.as_str()),
+                            ContentPart::Image { .. } => None,
+                        })
+                        .collect();
+                    MessageContent::Text(truncate_head(&format!("{} [image omitted]", text.join("\n")), profile.max_message_chars))
+                }
+                other => other.clone(),
+            };
+            GroqMessage { content, ..msg.clone() }
+        })
+        .collect()
+}
+
+/// The messages of a request as the profile and the model's context window allow. Long sessions
+/// give up their oldest content instead of failing with a context length error
+pub(crate) fn fit_messages(
+    messages: &[GroqMessage],
+    model: &str,
+    tools: &[Tool],
+    profile: &NetworkProfile,
+    context_window: Option<usize>,
+) -> Vec<GroqMessage> {
+    let window = context_window.unwrap_or_else(|| context_window::context_window(model));
+    let budget = context_window::prompt_budget(window, profile.max_tokens, tools);
+    context_window::pack(&trim_messages(messages, profile), budget).messages
+}
+
+/// Tokens the answer may take with `model`, leaving the prompt at least half the context window
+pub(crate) fn answer_tokens(model: &str, profile: &NetworkProfile, context_window: Option<usize>) -> u32 {
+    let window = context_window.unwrap_or_else(|| context_window::context_window(model));
+    context_window::answer_tokens(window, profile.max_tokens)
+}
+
+/// Timeouts and connection reuse of API requests
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpSettings {
+    pub connect_timeout_secs: u64,
+    pub timeout_secs: u64,        // A whole answer that isn't streamed
+    pub stream_timeout_secs: u64, // A whole streamed answer; long agent replies take minutes
+    pub read_timeout_secs: u64,   // Longest silence while a streamed answer arrives
+    pub pool_idle_secs: u64,      // How long an idle connection is kept for the next request
+    pub pool_max_idle: usize,     // Idle connections kept per host; 0 opens a fresh one per request
+}
+
+impl Default for HttpSettings {
+    fn default() -> Self {
+        Self {
+            connect_timeout_secs: 10,
+            timeout_secs: 60,
+            stream_timeout_secs: 600,
+            read_timeout_secs: 60,
+            pool_idle_secs: 90,
+            pool_max_idle: 8,
+        }
+    }
+}
+
+/// How requests reach the API: through a proxy, trusting an extra root certificate, for
+/// corporate networks, and with which timeouts
+#[derive(Clone, Default)]
+pub struct Transport {
+    proxy: Option<reqwest::Proxy>, // None leaves it to HTTPS_PROXY and NO_PROXY in the environment
+    root_certificate: Option<reqwest::Certificate>,
+    pub http: HttpSettings,
+}
+
+impl Transport {
+    /// Checks the proxy URL and reads the certificate up front, so a typo fails on connecting
+    /// rather than on the first request
+    pub fn new(https_proxy: Option<&str>, no_proxy: Option<&str>, ca_cert: Option<&Path>) -> Result<Self> {
+        let proxy = match https_proxy {
+            Some(url) => {
+                let no_proxy = match no_proxy {
+                    Some(hosts) => reqwest::NoProxy::from_string(hosts),
+                    None => reqwest::NoProxy::from_env(),
+                };
+                let proxy = reqwest::Proxy::https(url).map_err(|e| anyhow!("Invalid https_proxy {}: {}", url, e))?;
+                Some(proxy.no_proxy(no_proxy))
+            }
+            None => None,
+        };
+        let root_certificate = match ca_cert {
+            Some(path) => {
+                let pem = std::fs::read(path).map_err(|e| anyhow!("Could not read ca_cert {}: {}", path.display(), e))?;
+                let certificate = reqwest::Certificate::from_pem(&pem).map_err(|e| anyhow!("Invalid ca_cert {}: {}", path.display(), e))?;
+                Some(certificate)
+            }
+            None => None,
+        };
+        Ok(Self { proxy, root_certificate, http: HttpSettings::default() })
+    }
+
+    pub fn with_http(mut self, http: HttpSettings) -> Self {
+        self.http = http;
+        self
+    }
+}
+
+/// HTTP client with the profile's timeout, going through `transport`
+pub(crate) fn http_client(profile: &NetworkProfile, transport: &Transport) -> Client {
+    let http = transport.http;
+    let mut builder = Client::builder()
+        .timeout(profile.timeout)
+        .connect_timeout(Duration::from_secs(http.connect_timeout_secs))
+        .pool_idle_timeout(Duration::from_secs(http.pool_idle_secs))
+        .pool_max_idle_per_host(http.pool_max_idle);
+    if let Some(proxy) = &transport.proxy {
+        builder = builder.proxy(proxy.clone());
+    }
+    if let Some(certificate) = &transport.root_certificate {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    if profile.low_bandwidth {
+        // Flaky links drop idle connections; open a fresh one per request instead of keeping them alive
+        builder = builder
+            .connect_timeout(Duration::from_secs(http.connect_timeout_secs.max(30)))
+            .pool_max_idle_per_host(0)
+            .tcp_keepalive(None);
+    }
+    builder.build().expect("Failed to create HTTP client")
+}
+
+/// Keep the last `max_chars` characters; the end of a long message is usually the question
+fn truncate_head(text: &str, max_chars: Option<usize>) -> String {
+    match max_chars {
+        Some(max) if text.chars().count() > max => {
+            let tail: String = text.chars().skip(text.chars().count() - max).collect();
+            format!("[…earlier part trimmed]\n{}", tail)
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[derive(Clone)]
 pub struct GroqClient {
-    client: Client,
+    client: OnceLock<Client>, // Built on the first request; TLS setup is slow and not needed to draw the UI
     api_key: String,
     base_url: String,
+    pub profile: NetworkProfile,
+    transport: Transport,
+    pub context_window: Option<usize>, // Overrides the model's known window, in tokens
+    pub provider: ProviderKind, // Whose chat-completions endpoint this talks to
+    pub retry: RetryPolicy,
+    pub retry_status: RetryStatus, // Shared by clones, so the UI can show what a request is waiting for
 }
 
 impl GroqClient {
-    pub fn new(api_key: String) -> Self {
-        let client = Client::builder()
-            .timeout(Duration::from_secs(60))
-            .build()
-            .expect("Failed to create HTTP client");
-
+    pub fn with_profile(api_key: String, profile: NetworkProfile) -> Self {
         Self {
-            client,
+            client: OnceLock::new(),
             api_key,
-            base_url: "https://api.groq.com/openai/v1".to_string(),
+            base_url: ProviderKind::Groq.base_url().to_string(),
+            profile,
+            transport: Transport::default(),
+            context_window: None,
+            provider: ProviderKind::Groq,
+            retry: RetryPolicy::default(),
+            retry_status: RetryStatus::default(),
         }
     }
 
-    pub async fn chat_completion(&self, request: GroqRequest) -> Result<GroqResponse> {
-        let url = format!("{}/chat/completions", self.base_url);
-        
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await?;
+    /// Talk to another provider with the same chat-completions protocol, e.g. OpenAI or Ollama
+    pub fn for_provider(mut self, provider: ProviderKind) -> Self {
+        self.base_url = provider.base_url().to_string();
+        self.provider = provider;
+        self
+    }
 
-        if !response.status().is_success() {
-            let error_text = response.text().await?;
-            return Err(anyhow!("Groq API error: {}", error_text));
+    pub fn with_context_window(mut self, context_window: Option<usize>) -> Self {
+        self.context_window = context_window;
+        self
+    }
+
+    /// Send requests to a self-hosted server with the same protocol instead, e.g. LiteLLM, vLLM or LM Studio
+    pub fn with_base_url(mut self, base_url: Option<String>) -> Self {
+        if let Some(base_url) = base_url {
+            self.base_url = base_url.trim_end_matches('/').to_string();
         }
+        self
+    }
 
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    fn http(&self) -> &Client {
+        self.client.get_or_init(|| http_client(&self.profile, &self.transport))
+    }
+
+    pub async fn chat_completion(&self, request: GroqRequest) -> Result<GroqResponse> {
+        let response = self.post(&request).await?;
         let groq_response: GroqResponse = response.json().await?;
         Ok(groq_response)
     }
 
+    /// Send a completion request, retrying rate limits and server errors; an error status that
+    /// isn't retried becomes an error with the body's text
+    async fn post(&self, request: &GroqRequest) -> Result<reqwest::Response> {
+        let url = format!("{}/chat/completions", self.base_url);
+        // Streamed answers come in over minutes, so they get their own, longer timeout
+        let timeout = if request.stream {
+            self.profile.timeout.max(Duration::from_secs(self.transport.http.stream_timeout_secs))
+        } else {
+            self.profile.timeout
+        };
+        retry::send(&self.retry, &self.retry_status, self.provider.label(), || {
+            self.authorized(self.http().post(&url))
+                .header("Content-Type", "application/json")
+                .timeout(timeout)
+                .json(request)
+        })
+        .await
+    }
+
+    /// Ids of the models the endpoint serves, sorted
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/models", self.base_url);
+        let response = retry::send(&self.retry, &self.retry_status, self.provider.label(), || self.authorized(self.http().get(&url))).await?;
+        Ok(model_ids(&response.json().await?))
+    }
+
+    /// Vectors for `inputs`, in order, from the OpenAI-style `/embeddings` endpoint
+    pub async fn embed(&self, model: &str, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/embeddings", self.base_url);
+        let request = serde_json::json!({ "model": model, "input": inputs });
+        let response = retry::send(&self.retry, &self.retry_status, self.provider.label(), || {
+            self.authorized(self.http().post(&url)).json(&request)
+        })
+        .await?;
+        embedding_vectors(&response.json().await?)
+    }
+
+    fn authorized(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        // Local servers often take no key, and some reject an empty bearer token
+        if self.api_key.is_empty() {
+            builder
+        } else {
+            builder.header("Authorization", format!("Bearer {}", self.api_key))
+        }
+    }
+
     pub async fn send_message(
         &self,
         model: &str,
         messages: Vec<GroqMessage>,
         temperature: f32,
     ) -> Result<String> {
-        let request = GroqRequest {
-            model: model.to_string(),
-            messages,
-            temperature,
-            max_tokens: Some(4096),
-            stream: false,
-        };
-
-        let response = self.chat_completion(request).await?;
-        
+        let response = self.complete(model, messages, temperature).await?;
         if let Some(choice) = response.choices.first() {
-            Ok(choice.message.content.clone())
+            Ok(choice.message.content.clone().unwrap_or_default())
         } else {
             Err(anyhow!("No response from Groq API"))
         }
     }
 
+    /// Like `send_message`, but returns the whole response including token usage
+    pub async fn complete(
+        &self,
+        model: &str,
+        messages: Vec<GroqMessage>,
+        temperature: f32,
+    ) -> Result<GroqResponse> {
+        self.complete_with_tools(model, messages, temperature, &[]).await
+    }
+
+    /// Like `complete`, offering `tools` the model may call instead of answering in text
+    pub async fn complete_with_tools(
+        &self,
+        model: &str,
+        messages: Vec<GroqMessage>,
+        temperature: f32,
+        tools: &[Tool],
+    ) -> Result<GroqResponse> {
+        let mut profile = self.profile;
+        let mut attempt = 0;
+        loop {
+            let request = self.request(model, &messages, temperature, tools, &profile, false);
+            match self.chat_completion(request).await {
+                Err(e) if attempt < profile.retries && is_timeout(&e) => {
+                    attempt += 1;
+                    profile = profile.smaller();
+                }
+                result => break result,
+            }
+        }
+    }
+
+    /// Like `complete_with_tools`, but streamed: answer text goes to `partial` as it arrives and the
+    /// whole response is put together at the end. Nothing is retried, as part of the answer may be shown already
+    pub async fn complete_streaming(
+        &self,
+        model: &str,
+        messages: Vec<GroqMessage>,
+        temperature: f32,
+        tools: &[Tool],
+        partial: mpsc::UnboundedSender<String>,
+    ) -> Result<GroqResponse> {
+        let request = self.request(model, &messages, temperature, tools, &self.profile, true);
+        let mut response = self.post(&request).await?;
+        let mut stream = StreamAssembler::default();
+        let silence = Duration::from_secs(self.transport.http.read_timeout_secs);
+        while let Some(bytes) = tokio::time::timeout(silence, response.chunk()).await.map_err(|_| ApiError::Network {
+            message: format!("no data for {}s while streaming", silence.as_secs()),
+            timeout: true,
+        })?? {
+            let text = stream.feed(&bytes)?;
+            if !text.is_empty() {
+                let _ = partial.send(text); // The receiver may have stopped listening; the answer is still wanted
+            }
+        }
+        Ok(stream.finish())
+    }
+
+    fn request(&self, model: &str, messages: &[GroqMessage], temperature: f32, tools: &[Tool], profile: &NetworkProfile, stream: bool) -> GroqRequest {
+        GroqRequest {
+            model: model.to_string(),
+            messages: fit_messages(messages, model, tools, profile, self.context_window),
+            temperature,
+            max_tokens: Some(answer_tokens(model, profile, self.context_window)),
+            stream,
+            tools: tools.to_vec(),
+        }
+    }
+
     pub fn create_text_message(role: &str, content: &str) -> GroqMessage {
         GroqMessage {
             role: role.to_string(),
             content: MessageContent::Text(content.to_string()),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+
+    /// The assistant turn that asked for `tool_calls`, kept so the results can follow it
+    pub fn create_tool_call_message(content: &str, tool_calls: Vec<ToolCall>) -> GroqMessage {
+        GroqMessage {
+            tool_calls: Some(tool_calls),
+            ..Self::create_text_message("assistant", content)
+        }
+    }
+
+    /// The outcome of one tool call, sent back to the model
+    pub fn create_tool_result_message(tool_call_id: &str, content: &str) -> GroqMessage {
+        GroqMessage {
+            tool_call_id: Some(tool_call_id.to_string()),
+            ..Self::create_text_message("tool", content)
         }
     }
 
@@ -153,6 +566,255 @@ impl GroqClient {
                     },
                 },
             ]),
+            tool_calls: None,
+            tool_call_id: None,
+        }
+    }
+}
+
+/// One `data:` event of a streamed completion
+#[derive(Debug, Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    created: u64,
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    #[serde(default)]
+    usage: Option<Usage>,
+    #[serde(default)]
+    x_groq: Option<GroqStreamInfo>, // Groq reports usage here, on the last chunk
+}
+
+#[derive(Debug, Deserialize)]
+struct GroqStreamInfo {
+    #[serde(default)]
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: StreamDelta,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct StreamDelta {
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tool_calls: Option<Vec<ToolCallDelta>>,
+}
+
+/// A piece of a tool call; the first piece of each call carries its id and name
+#[derive(Debug, Deserialize)]
+struct ToolCallDelta {
+    index: usize,
+    #[serde(default)]
+    id: Option<String>,
+    #[serde(default)]
+    function: Option<FunctionDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FunctionDelta {
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    arguments: Option<String>,
+}
+
+/// Puts a streamed completion back together from its server-sent events
+#[derive(Default)]
+struct StreamAssembler {
+    pending: Vec<u8>, // Start of a line whose end hasn't arrived yet
+    id: String,
+    model: String,
+    created: u64,
+    content: String,
+    tool_calls: Vec<ToolCall>,
+    finish_reason: String,
+    usage: Option<Usage>,
+}
+
+impl StreamAssembler {
+    /// Take in the next bytes of the stream; returns the answer text they completed
+    fn feed(&mut self, bytes: &[u8]) -> Result<String> {
+        self.pending.extend_from_slice(bytes);
+        let mut text = String::new();
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                continue; // Blank separators, comments and other fields
+            };
+            if data == "[DONE]" {
+                continue;
+            }
+            let value: serde_json::Value = serde_json::from_str(data)?;
+            if let Some(error) = value.get("error") {
+                return Err(anyhow!("Groq API error: {}", error));
+            }
+            let chunk: StreamChunk = serde_json::from_value(value)?;
+            text.push_str(&self.apply(chunk));
+        }
+        Ok(text)
+    }
+
+    fn apply(&mut self, chunk: StreamChunk) -> String {
+        if self.id.is_empty() {
+            (self.id, self.model, self.created) = (chunk.id, chunk.model, chunk.created);
+        }
+        if let Some(usage) = chunk.usage.or(chunk.x_groq.and_then(|info| info.usage)) {
+            self.usage = Some(usage);
+        }
+        let mut text = String::new();
+        for choice in chunk.choices {
+            if let Some(content) = choice.delta.content {
+                text.push_str(&content);
+            }
+            for delta in choice.delta.tool_calls.unwrap_or_default() {
+                if delta.index >= self.tool_calls.len() {
+                    self.tool_calls.resize_with(delta.index + 1, || ToolCall {
+                        id: String::new(),
+                        kind: "function".to_string(),
+                        function: FunctionCall { name: String::new(), arguments: String::new() },
+                    });
+                }
+                let call = &mut self.tool_calls[delta.index];
+                if let Some(id) = delta.id {
+                    call.id = id;
+                }
+                if let Some(function) = delta.function {
+                    call.function.name.push_str(&function.name.unwrap_or_default());
+                    call.function.arguments.push_str(&function.arguments.unwrap_or_default());
+                }
+            }
+            if let Some(reason) = choice.finish_reason {
+                self.finish_reason = reason;
+            }
+        }
+        self.content.push_str(&text);
+        text
+    }
+
+    fn finish(self) -> GroqResponse {
+        let message = ResponseMessage {
+            role: "assistant".to_string(),
+            content: (!self.content.is_empty()).then_some(self.content),
+            tool_calls: (!self.tool_calls.is_empty()).then_some(self.tool_calls),
+        };
+        GroqResponse {
+            id: self.id,
+            object: "chat.completion".to_string(),
+            created: self.created,
+            model: self.model,
+            choices: vec![Choice { index: 0, message, finish_reason: self.finish_reason }],
+            usage: self.usage.unwrap_or_default(),
+        }
+    }
+}
+
+/// Model ids in a `/models` answer, `{"data": [{"id": ...}, ...]}`, sorted
+pub(crate) fn model_ids(body: &serde_json::Value) -> Vec<String> {
+    let mut ids: Vec<String> = body["data"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|model| model["id"].as_str().map(str::to_string))
+        .collect();
+    ids.sort();
+    ids
+}
+
+/// The vectors of an `/embeddings` answer, in the order of the inputs
+pub(crate) fn embedding_vectors(body: &serde_json::Value) -> Result<Vec<Vec<f32>>> {
+    let data = body["data"].as_array().ok_or_else(|| anyhow!("Unexpected embeddings response"))?;
+    let mut indexed: Vec<(u64, Vec<f32>)> = data
+        .iter()
+        .map(|item| {
+            let vector = item["embedding"].as_array().into_iter().flatten().filter_map(|x| x.as_f64()).map(|x| x as f32).collect();
+            (item["index"].as_u64().unwrap_or(0), vector)
+        })
+        .collect();
+    indexed.sort_by_key(|(index, _)| *index);
+    Ok(indexed.into_iter().map(|(_, vector)| vector).collect())
+}
+
+pub(crate) fn is_timeout(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<ApiError>(), Some(ApiError::Network { timeout: true, .. }))
+        || error.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trim_messages_for_low_bandwidth() {
+        let mut messages = vec![GroqClient::create_text_message("system", "You are helpful")];
+        for i in 0..10 {
+            messages.push(GroqClient::create_text_message("user", &format!("question {}", i)));
         }
+        messages.push(GroqClient::create_image_message("user", "what is this?", "AAAA"));
+
+        let profile = NetworkProfile { max_message_chars: Some(30), ..NetworkProfile::low_bandwidth() };
+        let trimmed = trim_messages(&messages, &profile);
+        assert_eq!(trimmed.len(), 7); // System prompt + 6 most recent
+        assert_eq!(trimmed[0].role, "system");
+        assert!(matches!(&trimmed[1].content, MessageContent::Text(text) if text == "question 5"));
+        assert!(matches!(&trimmed[6].content, MessageContent::Text(text) if text == "what is this? [image omitted]"));
+
+        assert_eq!(truncate_head("abcdef", Some(3)), "[…earlier part trimmed]\ndef");
+        assert_eq!(trim_messages(&messages, &NetworkProfile::normal()).len(), messages.len());
+        assert_eq!(NetworkProfile::low_bandwidth().smaller().recent_messages, Some(3));
+    }
+
+    #[test]
+    fn test_stream_assembler() {
+        let mut stream = StreamAssembler::default();
+        let events = concat!(
+            "data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"delta\":{\"role\":\"assistant\",\"content\":\"Hel\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"delta\":{\"content\":\"lo\"}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_1\",\"function\":{\"name\":\"read_file\",\"arguments\":\"{\\\"pa\"}}]}}]}\n\n",
+            "data: {\"id\":\"c1\",\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"th\\\":\\\"a.rs\\\"}\"}}]},\"finish_reason\":\"tool_calls\"}],",
+            "\"x_groq\":{\"usage\":{\"prompt_tokens\":10,\"completion_tokens\":5,\"total_tokens\":15}}}\n\n",
+            "data: [DONE]\n\n",
+        );
+        // Split mid-line, as the network does
+        let (first, rest) = events.split_at(30);
+        assert_eq!(stream.feed(first.as_bytes()).unwrap(), "");
+        assert_eq!(stream.feed(rest.as_bytes()).unwrap(), "Hello");
+
+        let response = stream.finish();
+        let message = &response.choices[0].message;
+        assert_eq!(message.content.as_deref(), Some("Hello"));
+        let calls = message.tool_calls.as_ref().unwrap();
+        assert_eq!((calls[0].id.as_str(), calls[0].function.arguments.as_str()), ("call_1", "{\"path\":\"a.rs\"}"));
+        assert_eq!((response.usage.total_tokens, response.choices[0].finish_reason.as_str()), (15, "tool_calls"));
+
+        assert!(StreamAssembler::default().feed(b"data: {\"error\":{\"message\":\"rate limited\"}}\n").is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_transport_checks_settings() {
+        assert!(Transport::new(Some("http://proxy.corp:3128"), Some("localhost,.internal"), None).is_ok());
+        assert!(Transport::new(Some("not a url"), None, None).is_err());
+        assert!(Transport::new(None, None, Some(Path::new("/nonexistent/ca.pem"))).is_err());
+    }
+
+    #[test]
+    fn test_model_ids() {
+        let body = serde_json::json!({"object": "list", "data": [{"id": "mixtral-8x7b-32768"}, {"id": "gemma2-9b-it"}, {"object": "model"}]});
+        assert_eq!(model_ids(&body), vec!["gemma2-9b-it", "mixtral-8x7b-32768"]);
+        assert!(model_ids(&serde_json::json!({})).is_empty());
+
+        let body = serde_json::json!({"data": [{"index": 1, "embedding": [0.5]}, {"index": 0, "embedding": [0.25, 1.0]}]});
+        assert_eq!(embedding_vectors(&body).unwrap(), vec![vec![0.25, 1.0], vec![0.5]]);
+    }
+}
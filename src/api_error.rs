@@ -0,0 +1,99 @@
+use reqwest::StatusCode;
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+/// Why an API request failed, sorted into what the user can do about it. Carried inside
+/// `anyhow::Error`, so callers that care can `downcast_ref` it
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiError {
+    AuthFailed { provider: &'static str },
+    RateLimited { provider: &'static str, retry_after: Option<Duration> },
+    ContextTooLong,
+    ModelNotFound { model: Option<String> },
+    Network { message: String, timeout: bool },
+    ServerError { provider: &'static str, status: u16, message: String },
+    InvalidRequest { provider: &'static str, status: u16, message: String }, // Any other refusal
+}
+
+impl ApiError {
+    /// Sort an error answer by its status and the provider's error body
+    pub fn from_response(provider: &'static str, status: StatusCode, body: &str, retry_after: Option<Duration>) -> Self {
+        let json: Option<Value> = serde_json::from_str(body).ok();
+        let field = |key: &str| json.as_ref().and_then(|json| json["error"][key].as_str()).unwrap_or_default().to_string();
+        let message = Some(field("message")).filter(|message| !message.is_empty()).unwrap_or_else(|| body.trim().to_string());
+        let code = format!("{} {}", field("code"), field("type"));
+        let lower = message.to_lowercase();
+
+        if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN || code.contains("invalid_api_key") {
+            ApiError::AuthFailed { provider }
+        } else if status == StatusCode::TOO_MANY_REQUESTS {
+            ApiError::RateLimited { provider, retry_after }
+        } else if code.contains("context_length_exceeded")
+            || lower.contains("context length")
+            || lower.contains("context window")
+            || lower.contains("reduce the length")
+            || lower.contains("prompt is too long")
+        {
+            ApiError::ContextTooLong
+        } else if code.contains("model_not_found") || (lower.contains("model") && (lower.contains("does not exist") || lower.contains("not found"))) {
+            // "The model `llama-9` does not exist or you do not have access to it."
+            let model = message.split('`').nth(1).map(str::to_string);
+            ApiError::ModelNotFound { model }
+        } else if status.is_server_error() {
+            ApiError::ServerError { provider, status: status.as_u16(), message }
+        } else {
+            ApiError::InvalidRequest { provider, status: status.as_u16(), message }
+        }
+    }
+
+    pub fn from_network(error: &reqwest::Error) -> Self {
+        ApiError::Network { message: error.to_string(), timeout: error.is_timeout() }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::AuthFailed { provider } => write!(f, "{} rejected the API key - run `agent config --api-key YOUR_KEY`", provider),
+            ApiError::RateLimited { provider, retry_after: Some(wait) } => {
+                write!(f, "Rate limited by {} - try again in {}s", provider, wait.as_secs_f64().ceil())
+            }
+            ApiError::RateLimited { provider, retry_after: None } => write!(f, "Rate limited by {} - wait a moment and try again", provider),
+            ApiError::ContextTooLong => {
+                write!(f, "The conversation is too long for the model - clear the chat or set a smaller \"context_window\" in the config")
+            }
+            ApiError::ModelNotFound { model: Some(model) } => write!(f, "Model `{}` not found - run `agent config --model MODEL_NAME`", model),
+            ApiError::ModelNotFound { model: None } => write!(f, "Model not found - run `agent config --model MODEL_NAME`"),
+            ApiError::Network { message, timeout: true } => write!(f, "The API did not answer in time: {}", message),
+            ApiError::Network { message, .. } => write!(f, "Could not reach the API: {}", message),
+            ApiError::ServerError { provider, status, message } => write!(f, "{} server error ({}): {}", provider, status, message),
+            ApiError::InvalidRequest { provider, status, message } => write!(f, "{} refused the request ({}): {}", provider, status, message),
+        }
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_api_errors() {
+        let missing = r#"{"error":{"message":"The model `llama-9` does not exist or you do not have access to it.","type":"invalid_request_error","code":"model_not_found"}}"#;
+        assert_eq!(
+            ApiError::from_response("Groq", StatusCode::NOT_FOUND, missing, None),
+            ApiError::ModelNotFound { model: Some("llama-9".to_string()) }
+        );
+        let key = r#"{"error":{"message":"Invalid API Key","type":"invalid_request_error","code":"invalid_api_key"}}"#;
+        assert_eq!(ApiError::from_response("Groq", StatusCode::UNAUTHORIZED, key, None), ApiError::AuthFailed { provider: "Groq" });
+        let long = r#"{"error":{"message":"Please reduce the length of the messages or completion.","code":"context_length_exceeded"}}"#;
+        assert_eq!(ApiError::from_response("Groq", StatusCode::BAD_REQUEST, long, None), ApiError::ContextTooLong);
+
+        let overloaded = ApiError::from_response("Anthropic", StatusCode::SERVICE_UNAVAILABLE, "upstream overloaded", None);
+        assert_eq!(overloaded.to_string(), "Anthropic server error (503): upstream overloaded");
+        let limited = ApiError::from_response("Groq", StatusCode::TOO_MANY_REQUESTS, "{}", Some(Duration::from_millis(7500)));
+        assert_eq!(limited.to_string(), "Rate limited by Groq - try again in 8s");
+    }
+}
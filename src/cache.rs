@@ -0,0 +1,86 @@
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize, Deserialize)]
+struct Entry<T> {
+    version: String, // Crate version that wrote it; a new build invalidates everything
+    fingerprint: String,
+    saved_at: u64, // Unix seconds
+    value: T,
+}
+
+/// On-disk cache for data that is slow to fetch at startup, like the provider's model list
+pub struct WarmCache {
+    dir: PathBuf,
+}
+
+impl WarmCache {
+    pub fn new() -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("warm");
+        Ok(Self::at(dir))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+
+    /// The cached value, unless it is missing, unreadable, from another build, for another
+    /// `fingerprint` (e.g. a different endpoint) or older than `max_age`
+    pub fn load<T: DeserializeOwned>(&self, key: &str, fingerprint: &str, max_age: Duration) -> Option<T> {
+        let entry: Entry<T> = serde_json::from_str(&fs::read_to_string(self.path(key)).ok()?).ok()?;
+        let age = now_secs().saturating_sub(entry.saved_at);
+        let fresh = entry.version == env!("CARGO_PKG_VERSION")
+            && entry.fingerprint == fingerprint
+            && age <= max_age.as_secs();
+        fresh.then_some(entry.value)
+    }
+
+    pub fn store<T: Serialize>(&self, key: &str, fingerprint: &str, value: &T) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let entry = Entry {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            fingerprint: fingerprint.to_string(),
+            saved_at: now_secs(),
+            value,
+        };
+        // Write then rename, so a crash never leaves a half-written entry behind
+        let temp = self.dir.join(format!("{}.json.tmp", key));
+        fs::write(&temp, serde_json::to_vec(&entry)?)?;
+        fs::rename(temp, self.path(key))?;
+        Ok(())
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warm_cache() {
+        let dir = std::env::temp_dir().join(format!("i4z-warm-{}", uuid::Uuid::new_v4()));
+        let cache = WarmCache::at(dir.clone());
+        let day = Duration::from_secs(86400);
+        assert_eq!(cache.load::<Vec<String>>("models", "groq", day), None);
+
+        let models = vec!["llama-3.1-8b-instant".to_string()];
+        cache.store("models", "groq", &models).unwrap();
+        assert_eq!(cache.load("models", "groq", day), Some(models));
+        assert_eq!(cache.load::<Vec<String>>("models", "openai", day), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -4,20 +4,28 @@ use base64::{engine::general_purpose, Engine as _};
 use image::ImageFormat;
 use std::io::Cursor;
 
+/// Connects to the system clipboard on first use, so startup doesn't wait on (or fail without) a display server
+#[derive(Default)]
 pub struct ClipboardManager {
-    clipboard: Clipboard,
+    clipboard: Option<Clipboard>,
 }
 
 impl ClipboardManager {
-    pub fn new() -> Result<Self> {
-        let clipboard = Clipboard::new()
-            .map_err(|e| anyhow!("Failed to initialize clipboard: {}", e))?;
-        
-        Ok(Self { clipboard })
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn clipboard(&mut self) -> Result<&mut Clipboard> {
+        if self.clipboard.is_none() {
+            let clipboard = Clipboard::new()
+                .map_err(|e| anyhow!("Failed to initialize clipboard: {}", e))?;
+            self.clipboard = Some(clipboard);
+        }
+        Ok(self.clipboard.as_mut().expect("clipboard initialized above"))
     }
 
     pub async fn get_image_as_base64(&mut self) -> Result<String> {
-        let image_data = self.clipboard
+        let image_data = self.clipboard()?
             .get_image()
             .map_err(|e| anyhow!("Failed to get image from clipboard: {}", e))?;
 
@@ -47,17 +55,17 @@ impl ClipboardManager {
     }
 
     pub fn has_image(&mut self) -> bool {
-        self.clipboard.get_image().is_ok()
+        self.clipboard().is_ok_and(|clipboard| clipboard.get_image().is_ok())
     }
 
     pub async fn get_text(&mut self) -> Result<String> {
-        self.clipboard
+        self.clipboard()?
             .get_text()
             .map_err(|e| anyhow!("Failed to get text from clipboard: {}", e))
     }
 
     pub fn set_text(&mut self, text: &str) -> Result<()> {
-        self.clipboard
+        self.clipboard()?
             .set_text(text)
             .map_err(|e| anyhow!("Failed to set clipboard text: {}", e))
     }
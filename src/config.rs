@@ -1,57 +1,452 @@
-use anyhow::{anyhow, Result};
+use crate::agent::command::CommandLimits;
+use crate::agent::orchestrator::LoopBudget;
+use crate::agent::retrieval::RetrievalSettings;
+use crate::agent::spend_guard::SpendLimits;
+use crate::agent::supervisor::AutonomySettings;
+use crate::api::HttpSettings;
+use crate::conversation::HistorySettings;
+use crate::ide::gutter::GutterSettings;
+use crate::diff::DiffOptions;
+use crate::ide::explorer_filter::ExplorerSettings;
+use crate::ide::filetype::FiletypeSettings;
+use crate::ide::indent::IndentSettings;
+use crate::ide::save_hooks::SaveHooks;
+use crate::ide::theme::{CustomTheme, Theme};
+use crate::persona::Persona;
+use crate::postprocess::PostProcessSettings;
+use crate::profile::{self, Profile};
+use crate::provider::ProviderKind;
+use crate::retry::RetryPolicy;
+use crate::secrets::{self, KeyStore};
+use crate::workflow::WorkflowTemplate;
+use crate::config_layers::{self, PROJECT_FILE};
+use anyhow::{anyhow, Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Settings `provider::connect` reads; the client is rebuilt only when one of them changes
+const CONNECTION_KEYS: &[&str] = &[
+    "provider", "groq_api_key", "api_keys", "key_store", "profile", "profiles", "api_base_url", "https_proxy", "no_proxy",
+    "ca_cert", "http", "default_model", "max_tokens", "debug_api", "retry", "context_window", "low_bandwidth",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub groq_api_key: Option<String>,
+    #[serde(default)]
+    pub provider: ProviderKind, // Which service answers chat requests
+    #[serde(default)]
+    pub api_keys: HashMap<String, String>, // Provider id -> key, for providers other than Groq
+    #[serde(default)]
+    pub key_store: KeyStore, // "config" keeps keys set with `agent config` in this file; "keyring" in the OS keyring
+    #[serde(default)]
+    pub api_base_url: Option<String>, // Chat-completions server to use instead of the provider's, e.g. "http://localhost:4000/v1"
+    #[serde(default)]
+    pub https_proxy: Option<String>, // e.g. "http://proxy.corp:3128"; None uses HTTPS_PROXY from the environment
+    #[serde(default)]
+    pub no_proxy: Option<String>, // Comma-separated hosts that skip the proxy; None uses NO_PROXY
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>, // PEM root certificate to trust as well, for TLS-intercepting firewalls
+    #[serde(default)]
+    pub http: HttpSettings, // Timeouts and connection reuse of API requests
     pub default_model: String,
     pub temperature: f32,
     pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub debug_api: bool, // Log every chat request and response to api-debug.jsonl next to this file, keys redacted
+    #[serde(skip)]
+    pub debug_api_once: bool, // Set by --debug-api; logs this run only
+    #[serde(skip)]
+    pub overrides: toml::Table, // Project, environment and command-line settings, kept out of the global file
+    #[serde(skip)]
+    pub assignments: Vec<String>, // The command line's `--set` settings, applied again when the config is reloaded
+    #[serde(skip)]
+    pub project_ignored: Vec<String>, // Settings in the project's file that projects may not set
+    #[serde(default)]
+    pub retry: RetryPolicy, // Retries of rate-limited, overloaded or unreachable API requests
+    #[serde(default = "default_stream_responses")]
+    pub stream_responses: bool, // Show answers as they are written instead of all at once
+    #[serde(default)]
+    pub postprocess: PostProcessSettings, // Stop sequences and clean-up applied to answers before they are shown
+    #[serde(default)]
+    pub context_window: Option<usize>, // Tokens the model accepts; None uses the known size for the model
+    #[serde(default)]
+    pub history: HistorySettings, // Tokens of conversation kept between requests, and summarizing what gets too old
+    #[serde(default = "default_autosave_interval")]
+    pub autosave_interval_secs: u64, // 0 disables swap-file autosave
+    #[serde(default)]
+    pub answer_pipe_command: Option<String>, // e.g. "espeak" - receives the last answer as plain text on stdin
+    #[serde(default)]
+    pub autonomy: AutonomySettings,
+    #[serde(default)]
+    pub agent_loop: LoopBudget, // Steps and tokens one agent run may use before it stops
+    #[serde(default)]
+    pub spend_limits: SpendLimits, // Request and estimated cost caps at which the agent pauses for the user
+    #[serde(default)]
+    pub command_limits: CommandLimits, // Timeout and captured output size of agent commands
+    #[serde(default)]
+    pub test_command: Option<String>, // e.g. "cargo nextest run"; None picks one from the project's manifest
+    #[serde(default)]
+    pub strict_json_actions: bool, // The agent replies with a JSON block of actions instead of tool calls, for models without them
+    #[serde(default)]
+    pub retrieval: RetrievalSettings, // Embeddings index of the workspace, searched for code relevant to each request
+    #[serde(default = "default_project_context_entries")]
+    pub project_context_entries: usize, // Files listed in the project summary sent before the first agent request; 0 turns it off
+    #[serde(default = "default_agent_sandbox")]
+    pub agent_sandbox: bool, // Agent actions may only touch paths inside the workspace root
+    #[serde(default)]
+    pub dry_run: bool, // Agent actions report what they would change instead of changing it
+    #[serde(default)]
+    pub workflows: Vec<WorkflowTemplate>,
+    #[serde(default)]
+    pub persona: Option<String>, // Persona whose system prompt starts each conversation
+    #[serde(default)]
+    pub personas: Vec<Persona>, // Added to the built-in ones, replacing those of the same name
+    #[serde(default)]
+    pub profile: Option<String>, // Profile switched to last; its key is used while it is active
+    #[serde(default)]
+    pub profiles: Vec<Profile>, // e.g. "work" and "personal" accounts, or "fast" and "smart" models
+    #[serde(default)]
+    pub low_bandwidth: bool, // Smaller requests, longer timeouts and retries for flaky links
+    #[serde(default)]
+    pub indent: IndentSettings,
+    #[serde(default)]
+    pub ui_language: Option<String>, // e.g. "es"; defaults to $LANG
+    #[serde(default)]
+    pub response_language: Option<String>, // e.g. "Spanish" - added to the system prompt
+    #[serde(default)]
+    pub openers: HashMap<String, String>, // Extension -> external command, e.g. "pdf": "zathura"
+    #[serde(default)]
+    pub save_hooks: SaveHooks,
+    #[serde(default)]
+    pub filetypes: HashMap<String, FiletypeSettings>, // Keyed like `indent.filetypes`, overridden by `.editorconfig`
+    #[serde(default)]
+    pub ruler: Option<usize>, // Column of a vertical ruler, e.g. 100; a filetype's `max_line_length` wins
+    #[serde(default = "default_show_ruler")]
+    pub show_ruler: bool,
+    #[serde(default = "default_chat_height")]
+    pub chat_height: u16, // Lines of the chat panel at startup; Ctrl+Up/Down resize it for the session
+    #[serde(default)]
+    pub show_whitespace: bool, // Draw spaces, tabs and line ends as · → ¶
+    #[serde(default)]
+    pub diff: DiffOptions, // Starting algorithm and whitespace handling of the diff view
+    #[serde(default)]
+    pub theme: Theme, // "default", "light", "solarized", "high_contrast", "deuteranopia", "protanopia" or a name from `themes`
+    #[serde(default)]
+    pub themes: Vec<CustomTheme>, // The user's own themes: a name and colors for the roles, e.g. "accent": "#88c0d0"
+    #[serde(default)]
+    pub keymap: HashMap<String, String>, // Key chord -> event name, e.g. "ctrl+b": "ToggleSplit"; "none" unbinds
+    #[serde(default)]
+    pub gutter: GutterSettings,
+    #[serde(default)]
+    pub explorer: ExplorerSettings, // Ignore globs, .gitignore and hidden files of the file explorer
+    #[serde(default = "default_restore_session")]
+    pub restore_session: bool, // Reopen the last tabs of a directory when launched in it again
+    #[serde(default = "default_notification_history")]
+    pub notification_history: usize, // Notifications kept by the live panel
+    #[serde(default = "default_notification_archive")]
+    pub notification_archive: usize, // Notifications kept by the searchable history of a session
+}
+
+fn default_autosave_interval() -> u64 {
+    30
+}
+
+fn default_stream_responses() -> bool {
+    true
+}
+
+fn default_show_ruler() -> bool {
+    true
+}
+
+fn default_chat_height() -> u16 {
+    12
+}
+
+fn default_restore_session() -> bool {
+    true
+}
+
+fn default_project_context_entries() -> usize {
+    200
+}
+
+fn default_agent_sandbox() -> bool {
+    true
+}
+
+fn default_notification_history() -> usize {
+    10
+}
+
+fn default_notification_archive() -> usize {
+    1000
 }
 
 impl Config {
     pub fn load() -> Result<Self> {
+        Self::load_layered(&std::env::current_dir()?, &[])
+    }
+
+    /// The effective config: built-in defaults, then the global file, then the project's
+    /// `.i4z/config.toml` under `project_root`, limited to editor settings, then `I4Z_*` environment
+    /// variables, then the command line's `key=value` settings. Later layers win
+    pub fn load_layered(project_root: &Path, assignments: &[String]) -> Result<Self> {
         let config_path = Self::get_config_path()?;
-        
-        if config_path.exists() {
-            let content = fs::read_to_string(&config_path)?;
-            let config: Config = serde_json::from_str(&content)?;
-            Ok(config)
-        } else {
-            let default_config = Self::default();
-            default_config.save()?;
-            Ok(default_config)
+        let migrated = Self::migrate_json(&config_path)?;
+        let global = config_layers::read_table(&config_path)?;
+        let (mut overrides, project_ignored) = config_layers::project_layer(config_layers::read_table(&project_root.join(PROJECT_FILE))?);
+        config_layers::merge(&mut overrides, &config_layers::env_layer(std::env::vars()));
+        config_layers::merge(&mut overrides, &config_layers::assignments_layer(assignments)?);
+
+        let mut effective = toml::Table::try_from(Self::default())?;
+        config_layers::merge(&mut effective, &global);
+        config_layers::merge(&mut effective, &overrides);
+        let mut config: Config = effective.try_into().context("Invalid config")?;
+        config.overrides = overrides;
+        config.assignments = assignments.to_vec();
+        config.project_ignored = project_ignored;
+        if migrated || !config_path.exists() {
+            config.save()?;
         }
+        Ok(config)
+    }
+
+    /// Load the config again, for edits made to its files while the IDE runs
+    pub fn reload(&self) -> Result<Self> {
+        let mut config = Self::load_layered(&std::env::current_dir()?, &self.assignments)?;
+        config.debug_api_once = self.debug_api_once;
+        Ok(config)
+    }
+
+    /// When the global or the project config file last changed
+    pub fn modified_at() -> Option<std::time::SystemTime> {
+        let project_file = std::env::current_dir().ok()?.join(PROJECT_FILE);
+        [Self::get_config_path().ok()?, project_file]
+            .iter()
+            .filter_map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .max()
+    }
+
+    /// The setting at a dotted key such as `http.timeout_secs`; None when it isn't set
+    pub fn setting(&self, key: &str) -> Option<toml::Value> {
+        let table = toml::Table::try_from(self).ok()?;
+        config_layers::get_dotted(&table, key).cloned()
+    }
+
+    /// A copy with the setting at `key` changed, or cleared with None; fails when the value doesn't fit
+    pub fn with_setting(&self, key: &str, value: Option<toml::Value>) -> Result<Self> {
+        let mut table = toml::Table::try_from(self)?;
+        config_layers::set_dotted(&mut table, key, value);
+        let mut config: Config = table.try_into()?;
+        (config.debug_api_once, config.overrides, config.assignments) = (self.debug_api_once, self.overrides.clone(), self.assignments.clone());
+        Ok(config)
     }
 
+    /// Whether the project file, the environment or `--set` decides the setting at `key`
+    pub fn is_overridden(&self, key: &str) -> bool {
+        config_layers::get_dotted(&self.overrides, key).is_some()
+    }
+
+    /// Whether `other` has the same settings
+    pub fn same_settings(&self, other: &Config) -> bool {
+        toml::Value::try_from(self).ok() == toml::Value::try_from(other).ok()
+    }
+
+    /// Whether `other` builds the same model client: provider, endpoint, keys, proxy and HTTP settings
+    pub fn same_connection(&self, other: &Config) -> bool {
+        let (Ok(toml::Value::Table(mine)), Ok(toml::Value::Table(theirs))) = (toml::Value::try_from(self), toml::Value::try_from(other)) else {
+            return false;
+        };
+        self.debug_api_once == other.debug_api_once && CONNECTION_KEYS.iter().all(|key| mine.get(*key) == theirs.get(*key))
+    }
+
+    /// Write the settings to the global file; values from the project, the environment or the
+    /// command line stay out of it unless they were changed since
     pub fn save(&self) -> Result<()> {
         let config_path = Self::get_config_path()?;
-        
-        if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
-        
-        let content = serde_json::to_string_pretty(self)?;
-        fs::write(config_path, content)?;
-        
-        Ok(())
+        let mut value = toml::Value::try_from(self)?;
+        config_layers::round_floats(&mut value);
+        let toml::Value::Table(mut table) = value else {
+            return Err(anyhow!("Config is not a table"));
+        };
+        let global = config_layers::read_table(&config_path).unwrap_or_default();
+        config_layers::without_overrides(&mut table, &global, &self.overrides);
+        config_layers::write_preserving(&config_path, &table)
     }
 
     pub fn get_config_path() -> Result<PathBuf> {
         let home_dir = dirs::home_dir()
             .ok_or_else(|| anyhow!("Could not find home directory"))?;
         
-        Ok(home_dir.join(".config").join("rust-coding-agent").join("config.json"))
+        Ok(home_dir.join(".config").join("rust-coding-agent").join("config.toml"))
+    }
+
+    /// Turn the `config.json` of earlier versions into `config.toml`, keeping the old file as
+    /// `config.json.bak`; returns whether there was one to migrate
+    fn migrate_json(config_path: &Path) -> Result<bool> {
+        let json_path = config_path.with_file_name("config.json");
+        if config_path.exists() || !json_path.exists() {
+            return Ok(false);
+        }
+        let config: Config = serde_json::from_str(&fs::read_to_string(&json_path)?).context("Invalid config.json")?;
+        let mut value = toml::Value::try_from(&config)?;
+        config_layers::round_floats(&mut value);
+        let toml::Value::Table(table) = value else {
+            return Err(anyhow!("Config is not a table"));
+        };
+        config_layers::write_preserving(config_path, &table)?;
+        fs::rename(&json_path, json_path.with_extension("json.bak"))?;
+        Ok(true)
+    }
+
+    /// The effective config as TOML, keys redacted, for `agent config --show`
+    pub fn show(&self) -> Result<String> {
+        let mut value = toml::Value::try_from(self)?;
+        redact_keys(&mut value);
+        config_layers::round_floats(&mut value);
+        Ok(toml::to_string_pretty(&value)?)
     }
 
     pub fn get_groq_key(&self) -> Option<String> {
-        self.groq_api_key.clone()
+        self.api_key(ProviderKind::Groq)
     }
 
     pub fn set_groq_key(&mut self, key: String) -> Result<()> {
-        self.groq_api_key = Some(key);
+        self.set_api_key(ProviderKind::Groq, key)
+    }
+
+    /// The key for `kind`: the active profile's, else the one in this file, else the provider's
+    /// environment variable (e.g. `GROQ_API_KEY`), else the OS keyring's when keys are kept there
+    pub fn api_key(&self, kind: ProviderKind) -> Option<String> {
+        self.profile_key(kind)
+            .or_else(|| self.stored_key(kind))
+            .or_else(|| secrets::from_env(kind))
+            .or_else(|| self.keyring_key(kind.id()))
+    }
+
+    /// The active profile's own key for `kind`, from the profile or the keyring when keys are kept there
+    fn profile_key(&self, kind: ProviderKind) -> Option<String> {
+        let profile = self.active_profile().filter(|profile| profile.applies_to(kind))?;
+        profile.api_key.clone().or_else(|| self.keyring_key(&secrets::profile_account(&profile.name)))
+    }
+
+    fn keyring_key(&self, account: &str) -> Option<String> {
+        if self.key_store == KeyStore::Keyring { secrets::keyring_get(account).ok().flatten() } else { None }
+    }
+
+    /// The key for `kind` in this file; Groq's lives in `groq_api_key`, which older configs already have
+    fn stored_key(&self, kind: ProviderKind) -> Option<String> {
+        match kind {
+            ProviderKind::Groq => self.groq_api_key.clone(),
+            _ => self.api_keys.get(kind.id()).cloned(),
+        }
+    }
+
+    fn store_key(&mut self, kind: ProviderKind, key: Option<String>) {
+        match (kind, key) {
+            (ProviderKind::Groq, key) => self.groq_api_key = key,
+            (_, Some(key)) => {
+                self.api_keys.insert(kind.id().to_string(), key);
+            }
+            (_, None) => {
+                self.api_keys.remove(kind.id());
+            }
+        }
+    }
+
+    pub fn set_api_key(&mut self, kind: ProviderKind, key: String) -> Result<()> {
+        if self.key_store == KeyStore::Keyring {
+            secrets::keyring_set(kind.id(), Some(&key))?;
+            self.store_key(kind, None);
+        } else {
+            self.store_key(kind, Some(key));
+        }
+        self.save()
+    }
+
+    /// Keep keys in `store` from now on, moving the ones already set, profiles' own keys included;
+    /// returns the keyring accounts of the keys that moved. Keys leave their old place only once
+    /// they are safe in the new one
+    pub fn set_key_store(&mut self, store: KeyStore) -> Result<Vec<String>> {
+        let mut moved = Vec::new();
+        match store {
+            KeyStore::Keyring => {
+                for kind in ProviderKind::ALL {
+                    if let Some(key) = self.stored_key(kind) {
+                        secrets::keyring_set(kind.id(), Some(&key))?;
+                        self.store_key(kind, None);
+                        moved.push(kind.id().to_string());
+                    }
+                }
+                for profile in &mut self.profiles {
+                    if let Some(key) = &profile.api_key {
+                        let account = secrets::profile_account(&profile.name);
+                        secrets::keyring_set(&account, Some(key))?;
+                        profile.api_key = None;
+                        moved.push(account);
+                    }
+                }
+                self.key_store = store;
+                self.save()?;
+            }
+            KeyStore::Config => {
+                if self.key_store == KeyStore::Keyring {
+                    for kind in ProviderKind::ALL {
+                        if let Some(key) = secrets::keyring_get(kind.id())? {
+                            self.store_key(kind, Some(key));
+                            moved.push(kind.id().to_string());
+                        }
+                    }
+                    for profile in self.profiles.iter_mut().filter(|profile| profile.api_key.is_none()) {
+                        let account = secrets::profile_account(&profile.name);
+                        if let Some(key) = secrets::keyring_get(&account)? {
+                            profile.api_key = Some(key);
+                            moved.push(account);
+                        }
+                    }
+                }
+                self.key_store = store;
+                self.save()?;
+                for account in &moved {
+                    secrets::keyring_set(account, None)?;
+                }
+            }
+        }
+        Ok(moved)
+    }
+
+    pub fn active_profile(&self) -> Option<&Profile> {
+        profile::find(&self.profiles, self.profile.as_deref()?)
+    }
+
+    /// Switch to the profile called `name`: its provider, model and temperature become the
+    /// current ones and its key is used from now on
+    pub fn switch_profile(&mut self, name: &str) -> Result<Profile> {
+        let profile = profile::find(&self.profiles, name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No profile called '{}'", name))?;
+        if let Some(kind) = profile.provider.filter(|kind| *kind != self.provider) {
+            self.provider = kind;
+            self.default_model = kind.default_model().to_string();
+        }
+        if let Some(model) = &profile.model {
+            self.default_model = model.clone();
+        }
+        if let Some(temperature) = profile.temperature {
+            self.temperature = temperature;
+        }
+        self.profile = Some(profile.name.clone());
+        self.save()?;
+        Ok(profile)
+    }
+
+    /// Stop using the active profile's key; the settings it switched to stay
+    pub fn leave_profile(&mut self) -> Result<()> {
+        self.profile = None;
         self.save()
     }
 
@@ -73,6 +468,11 @@ impl Config {
         self.save()
     }
 
+    pub fn save_workflow(&mut self, template: WorkflowTemplate) -> Result<()> {
+        crate::workflow::upsert(&mut self.workflows, template);
+        self.save()
+    }
+
     pub fn get_max_tokens(&self) -> Option<u32> {
         self.max_tokens
     }
@@ -87,9 +487,86 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             groq_api_key: None,
+            provider: ProviderKind::Groq,
+            api_keys: HashMap::new(),
+            key_store: KeyStore::default(),
+            api_base_url: None,
+            https_proxy: None,
+            no_proxy: None,
+            ca_cert: None,
+            http: HttpSettings::default(),
+            debug_api: false,
+            debug_api_once: false,
+            overrides: toml::Table::new(),
+            assignments: Vec::new(),
+            project_ignored: Vec::new(),
+            retry: RetryPolicy::default(),
             default_model: "llama-3.1-70b-versatile".to_string(),
             temperature: 0.7,
             max_tokens: Some(4096),
+            stream_responses: default_stream_responses(),
+            postprocess: PostProcessSettings::default(),
+            context_window: None,
+            history: HistorySettings::default(),
+            autosave_interval_secs: default_autosave_interval(),
+            answer_pipe_command: None,
+            autonomy: AutonomySettings::default(),
+            agent_loop: LoopBudget::default(),
+            spend_limits: SpendLimits::default(),
+            command_limits: CommandLimits::default(),
+            test_command: None,
+            strict_json_actions: false,
+            retrieval: RetrievalSettings::default(),
+            project_context_entries: default_project_context_entries(),
+            agent_sandbox: true,
+            dry_run: false,
+            workflows: Vec::new(),
+            persona: None,
+            personas: Vec::new(),
+            profile: None,
+            profiles: Vec::new(),
+            low_bandwidth: false,
+            indent: IndentSettings::default(),
+            ui_language: None,
+            response_language: None,
+            openers: HashMap::new(),
+            save_hooks: SaveHooks::default(),
+            filetypes: HashMap::new(),
+            ruler: None,
+            show_ruler: default_show_ruler(),
+            chat_height: default_chat_height(),
+            show_whitespace: false,
+            diff: DiffOptions::default(),
+            theme: Theme::default(),
+            themes: Vec::new(),
+            keymap: HashMap::new(),
+            gutter: GutterSettings::default(),
+            explorer: ExplorerSettings::default(),
+            restore_session: default_restore_session(),
+            notification_history: default_notification_history(),
+            notification_archive: default_notification_archive(),
         }
     }
-}
\ No newline at end of file
+}
+/// Replace API keys, wherever they are in the config, with their last four characters
+fn redact_keys(value: &mut toml::Value) {
+    let redact = |key: &mut toml::Value| {
+        if let toml::Value::String(text) = key {
+            let tail: String = text.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+            *text = format!("…{}", tail);
+        }
+    };
+    match value {
+        toml::Value::Table(table) => {
+            for (name, value) in table.iter_mut() {
+                match (name.as_str(), value) {
+                    ("api_keys", toml::Value::Table(keys)) => keys.iter_mut().for_each(|(_, key)| redact(key)),
+                    (name, value) if name.ends_with("api_key") => redact(value),
+                    (_, value) => redact_keys(value),
+                }
+            }
+        }
+        toml::Value::Array(items) => items.iter_mut().for_each(redact_keys),
+        _ => {}
+    }
+}
@@ -0,0 +1,273 @@
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Settings of one project, layered over the global config
+pub const PROJECT_FILE: &str = ".i4z/config.toml";
+/// The settings a project's file may set: how the editor looks and edits. Anything that reaches
+/// the network, holds keys or runs commands comes only from the user's own layers, so a cloned
+/// repository can't send the API key elsewhere or run code on save
+pub const PROJECT_KEYS: &[&str] = &[
+    "indent",
+    "filetypes",
+    "ruler",
+    "show_ruler",
+    "show_whitespace",
+    "diff",
+    "theme",
+    "themes",
+    "gutter",
+    "explorer",
+    "chat_height",
+    "history",
+    "response_language",
+    "project_context_entries",
+];
+/// Environment variables starting with this set config keys; `__` separates nested keys,
+/// e.g. `I4Z_DEFAULT_MODEL=llama-3.1-8b-instant` or `I4Z_HTTP__TIMEOUT_SECS=120`
+pub const ENV_PREFIX: &str = "I4Z_";
+
+/// `base` with `layer` on top: tables are merged key by key, anything else is replaced
+pub fn merge(base: &mut Table, layer: &Table) {
+    for (key, value) in layer {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(base_table)), Value::Table(layer_table)) => merge(base_table, layer_table),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// The TOML file at `path` as a table; an empty one when there is no file
+pub fn read_table(path: &Path) -> Result<Table> {
+    match fs::read_to_string(path) {
+        Ok(text) => text.parse().with_context(|| format!("Invalid {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Table::new()),
+        Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+    }
+}
+
+/// `layer` from a project's file without the settings projects may not set, and the keys it had of those
+pub fn project_layer(mut layer: Table) -> (Table, Vec<String>) {
+    let ignored: Vec<String> = layer.keys().filter(|key| !PROJECT_KEYS.contains(&key.as_str())).cloned().collect();
+    for key in &ignored {
+        layer.remove(key);
+    }
+    (layer, ignored)
+}
+
+/// The settings in the environment variables `vars` that start with [`ENV_PREFIX`]
+pub fn env_layer(vars: impl IntoIterator<Item = (String, String)>) -> Table {
+    let mut layer = Table::new();
+    for (name, value) in vars {
+        let Some(key) = name.strip_prefix(ENV_PREFIX).filter(|key| !key.is_empty()) else {
+            continue;
+        };
+        let path: Vec<String> = key.to_lowercase().split("__").map(str::to_string).collect();
+        set(&mut layer, &path, parse_value(&value));
+    }
+    layer
+}
+
+/// The settings given as `key=value`, with `.` between nested keys, e.g. `http.timeout_secs=120`
+pub fn assignments_layer(assignments: &[String]) -> Result<Table> {
+    let mut layer = Table::new();
+    for assignment in assignments {
+        let (key, value) = assignment.split_once('=').ok_or_else(|| anyhow!("Expected KEY=VALUE, got `{}`", assignment))?;
+        set_dotted(&mut layer, key.trim(), Some(parse_value(value.trim())));
+    }
+    Ok(layer)
+}
+
+/// `text` read as a TOML value such as `0.2`, `true` or `["a", "b"]`; anything else is a string
+fn parse_value(text: &str) -> Value {
+    format!("value = {}", text)
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(text.to_string()))
+}
+
+/// The value at a dotted key such as `http.timeout_secs`
+pub fn get_dotted<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    let (parents, last) = match key.rsplit_once('.') {
+        Some((parents, last)) => (Some(parents), last),
+        None => (None, key),
+    };
+    let mut table = table;
+    for parent in parents.into_iter().flat_map(|parents| parents.split('.')) {
+        table = table.get(parent)?.as_table()?;
+    }
+    table.get(last)
+}
+
+/// Set the value at a dotted key, or remove it with None
+pub fn set_dotted(table: &mut Table, key: &str, value: Option<Value>) {
+    let path: Vec<String> = key.split('.').map(str::to_string).collect();
+    match value {
+        Some(value) => set(table, &path, value),
+        None => {
+            let (last, parents) = path.split_last().expect("split yields at least one key");
+            let mut table = table;
+            for parent in parents {
+                let Some(Value::Table(inner)) = table.get_mut(parent) else {
+                    return;
+                };
+                table = inner;
+            }
+            table.remove(last);
+        }
+    }
+}
+
+fn set(table: &mut Table, path: &[String], value: Value) {
+    let Some((last, parents)) = path.split_last() else {
+        return;
+    };
+    let mut table = table;
+    for key in parents {
+        let entry = table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        let Value::Table(inner) = entry else {
+            unreachable!()
+        };
+        table = inner;
+    }
+    table.insert(last.clone(), value);
+}
+
+/// Take the values the overrides supplied back out of `effective`, leaving them as the global file
+/// had them, so project and environment settings don't end up in the global file. Values changed
+/// since they were loaded stay, as the user set them
+pub fn without_overrides(effective: &mut Table, global: &Table, overrides: &Table) {
+    for (key, value) in overrides {
+        match (effective.get_mut(key), value, global.get(key)) {
+            (Some(Value::Table(effective_table)), Value::Table(override_table), global_value) => {
+                let empty = Table::new();
+                let global_table = global_value.and_then(Value::as_table).unwrap_or(&empty);
+                without_overrides(effective_table, global_table, override_table);
+            }
+            (Some(current), _, global_value) if current == value => match global_value {
+                Some(global_value) => *current = global_value.clone(),
+                None => {
+                    effective.remove(key);
+                }
+            },
+            _ => {}
+        }
+    }
+}
+
+/// Floats with the shortest digits that read back as the same `f32`, so a temperature of 0.7 isn't
+/// written as 0.699999988079071
+pub fn round_floats(value: &mut Value) {
+    match value {
+        Value::Float(float) => {
+            if let Ok(short) = (*float as f32).to_string().parse::<f64>() {
+                *float = short;
+            }
+        }
+        Value::Table(table) => table.iter_mut().for_each(|(_, value)| round_floats(value)),
+        Value::Array(items) => items.iter_mut().for_each(round_floats),
+        _ => {}
+    }
+}
+
+/// Write `table` to the TOML file at `path`, keeping the comments and layout of the entries that
+/// didn't change
+pub fn write_preserving(path: &Path, table: &Table) -> Result<()> {
+    let existing = fs::read_to_string(path).unwrap_or_default();
+    let fresh: toml_edit::DocumentMut = toml::to_string_pretty(table)?.parse()?;
+    let text = match existing.parse::<toml_edit::DocumentMut>() {
+        Ok(mut document) if !existing.trim().is_empty() => {
+            let old: Table = existing.parse().unwrap_or_default();
+            update(document.as_table_mut(), fresh.as_table(), &old, table);
+            document.to_string()
+        }
+        _ => fresh.to_string(),
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Bring `document` from the values `old` to `new`, taking changed entries from `fresh`
+fn update(document: &mut toml_edit::Table, fresh: &toml_edit::Table, old: &Table, new: &Table) {
+    document.retain(|key, _| new.contains_key(key));
+    for (key, value) in new {
+        if old.get(key) == Some(value) && document.contains_key(key) {
+            continue;
+        }
+        let Some(fresh_item) = fresh.get(key) else {
+            continue;
+        };
+        match (document.get_mut(key), old.get(key), value, fresh_item) {
+            (Some(toml_edit::Item::Table(table)), Some(Value::Table(old_table)), Value::Table(new_table), toml_edit::Item::Table(fresh_table)) => {
+                update(table, fresh_table, old_table, new_table);
+            }
+            (Some(item), ..) => {
+                let mut replacement = fresh_item.clone();
+                // Keep a comment at the end of the line
+                if let (Some(previous), Some(value)) = (item.as_value(), replacement.as_value_mut()) {
+                    *value.decor_mut() = previous.decor().clone();
+                }
+                *item = replacement;
+            }
+            (None, ..) => {
+                document.insert(key, fresh_item.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers() {
+        let mut effective: Table = "temperature = 0.7\ndefault_model = \"a\"\n[http]\ntimeout_secs = 60\nconnect_timeout_secs = 10".parse().unwrap();
+        let global = effective.clone();
+        let mut overrides: Table = "default_model = \"b\"".parse().unwrap();
+        let env = vec![("I4Z_HTTP__TIMEOUT_SECS".to_string(), "120".to_string()), ("AGENT_MODE".to_string(), "fast".to_string())];
+        merge(&mut overrides, &env_layer(env));
+        merge(&mut overrides, &assignments_layer(&["persona=concise".to_string()]).unwrap());
+        assert!(assignments_layer(&["persona".to_string()]).is_err());
+        merge(&mut effective, &overrides);
+        assert_eq!(effective["default_model"].as_str(), Some("b"));
+        assert_eq!(effective["http"]["timeout_secs"].as_integer(), Some(120));
+        assert_eq!(effective["http"]["connect_timeout_secs"].as_integer(), Some(10));
+
+        let project: Table = "api_base_url = \"http://evil\"\nruler = 100\n[save_hooks.formatters]\nrs = \"sh\"".parse().unwrap();
+        let (project, ignored) = project_layer(project);
+        assert_eq!(ignored, vec!["api_base_url", "save_hooks"]);
+        assert_eq!(project.keys().collect::<Vec<_>>(), vec!["ruler"]);
+
+        // Saving keeps the global values of what was overridden, unless the user changed it since
+        effective.insert("default_model".to_string(), Value::String("c".to_string()));
+        without_overrides(&mut effective, &global, &overrides);
+        assert_eq!(effective["default_model"].as_str(), Some("c"));
+        assert_eq!(effective["http"]["timeout_secs"].as_integer(), Some(60));
+        assert!(!effective.contains_key("persona"));
+
+        let path = std::env::temp_dir().join(format!("config-layers-{}.toml", std::process::id()));
+        fs::write(&path, "# My settings\ntemperature = 0.7 # warm\ndefault_model = \"a\"\n\n[http]\n# seconds\ntimeout_secs = 60\nconnect_timeout_secs = 10\n").unwrap();
+        let mut value = Value::Table(effective);
+        value["temperature"] = Value::Float(0.2f32 as f64);
+        round_floats(&mut value);
+        write_preserving(&path, value.as_table().unwrap()).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert_eq!(written, "# My settings\ntemperature = 0.2 # warm\ndefault_model = \"c\"\n\n[http]\n# seconds\ntimeout_secs = 60\nconnect_timeout_secs = 10\n");
+        fs::remove_file(&path).unwrap();
+
+        // Every setting can be written as TOML and read back
+        let defaults = toml::Table::try_from(crate::config::Config::default()).unwrap();
+        assert!(defaults.try_into::<crate::config::Config>().is_ok());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::api::{ContentPart, GroqMessage, MessageContent, Tool};
+
+/// Used for models this table doesn't know and no `context_window` is configured for
+const DEFAULT_CONTEXT_WINDOW: usize = 8192;
+/// Rough cost of an image in the prompt
+const IMAGE_TOKENS: usize = 1000;
+/// Role, separators and other framing the API adds around each message
+const MESSAGE_OVERHEAD: usize = 4;
+/// Lines an old tool result keeps once it is shortened
+const SHORTENED_LINES: usize = 20;
+/// System messages may take at most this share of the prompt budget together, so long project
+/// memory, notes or summaries leave room for the conversation
+const SYSTEM_SHARE: usize = 2;
+/// Tokens of the note that ends a cut-down system message, with room to spare
+const TRIMMED_NOTE_TOKENS: usize = 16;
+/// Marks where `@file` and `#file` references were appended to a user message
+const REFERENCED_CONTEXT: &str = "\n\n[Referenced context]\n";
+
+/// Tokens a model accepts, prompt and answer together
+pub fn context_window(model: &str) -> usize {
+    let model = model.to_lowercase();
+    if model.contains("claude") {
+        200_000
+    } else if model.contains("gpt-4o") || model.contains("llama-3.1") || model.contains("llama-3.2") || model.contains("llama-3.3") {
+        131_072
+    } else if model.contains("32768") || model.contains("mixtral") {
+        32_768
+    } else {
+        DEFAULT_CONTEXT_WINDOW
+    }
+}
+
+/// About four characters per token: close enough to stay clear of the limit without a tokenizer
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+pub fn message_tokens(message: &GroqMessage) -> usize {
+    let content = match &message.content {
+        MessageContent::Text(text) => estimate_tokens(text),
+        MessageContent::MultiModal(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => estimate_tokens(text),
+                ContentPart::Image { .. } => IMAGE_TOKENS,
+            })
+            .sum(),
+    };
+    let calls: usize = message
+        .tool_calls
+        .iter()
+        .flatten()
+        .map(|call| estimate_tokens(&call.function.name) + estimate_tokens(&call.function.arguments))
+        .sum();
+    content + calls + MESSAGE_OVERHEAD
+}
+
+/// Tokens the answer may take: `max_tokens`, but never more than half the window, so a large
+/// setting can't leave the prompt without room
+pub fn answer_tokens(window: usize, max_tokens: u32) -> u32 {
+    max_tokens.min(u32::try_from(window / 2).unwrap_or(u32::MAX))
+}
+
+/// Prompt tokens left once the answer and the tool definitions have their share
+pub fn prompt_budget(window: usize, max_answer_tokens: u32, tools: &[Tool]) -> usize {
+    let tools = serde_json::to_string(tools).map_or(0, |json| estimate_tokens(&json));
+    window.saturating_sub(answer_tokens(window, max_answer_tokens) as usize + tools)
+}
+
+#[derive(Debug, Default)]
+pub struct Packed {
+    pub messages: Vec<GroqMessage>,
+    pub shortened: usize, // Tool results and file references cut down
+    pub dropped: usize,   // Oldest messages left out altogether
+}
+
+/// Fit `messages` into `budget` tokens, giving up the oldest content first: tool results and
+/// referenced files of earlier turns are cut down, then whole turns are dropped. System messages
+/// and the latest message always stay, though the longest system messages are cut down once
+/// together they pass their share of the budget
+pub fn pack(messages: &[GroqMessage], budget: usize) -> Packed {
+    let mut packed = Packed { messages: messages.to_vec(), ..Packed::default() };
+    let total = |messages: &[GroqMessage]| messages.iter().map(message_tokens).sum::<usize>();
+    let Some(latest) = packed.messages.len().checked_sub(1) else {
+        return packed;
+    };
+
+    let system_budget = budget / SYSTEM_SHARE;
+    loop {
+        let system = packed.messages.iter().filter(|msg| msg.role == "system").map(message_tokens).sum::<usize>();
+        let longest = (0..latest).filter(|&index| packed.messages[index].role == "system").max_by_key(|&index| message_tokens(&packed.messages[index]));
+        let Some(index) = longest.filter(|_| system > system_budget) else {
+            break;
+        };
+        let MessageContent::Text(text) = &packed.messages[index].content else {
+            break;
+        };
+        let keep = message_tokens(&packed.messages[index]).saturating_sub(system - system_budget + TRIMMED_NOTE_TOKENS) * 4;
+        if keep >= text.chars().count() {
+            break;
+        }
+        let head: String = text.chars().take(keep).collect();
+        packed.messages[index].content = MessageContent::Text(format!("{}\n[… trimmed to fit the context window]", head));
+        packed.shortened += 1;
+    }
+
+    for index in 0..latest {
+        if total(&packed.messages) <= budget {
+            return packed;
+        }
+        if let Some(shorter) = shorten(&packed.messages[index]) {
+            packed.messages[index].content = MessageContent::Text(shorter);
+            packed.shortened += 1;
+        }
+    }
+
+    while total(&packed.messages) > budget {
+        let Some(oldest) = packed.messages.iter().position(|msg| msg.role != "system") else {
+            break;
+        };
+        if oldest + 1 >= packed.messages.len() {
+            break;
+        }
+        packed.messages.remove(oldest);
+        packed.dropped += 1;
+        // Tool results can't be sent without the assistant turn that asked for them
+        while packed.messages.get(oldest).is_some_and(|msg| msg.role == "tool") && oldest + 1 < packed.messages.len() {
+            packed.messages.remove(oldest);
+            packed.dropped += 1;
+        }
+    }
+
+    if packed.dropped > 0 {
+        let note = format!("[{} earlier messages were left out to fit the context window]", packed.dropped);
+        let position = packed.messages.iter().take_while(|msg| msg.role == "system").count();
+        packed.messages.insert(position, crate::api::GroqClient::create_text_message("system", &note));
+    }
+    packed
+}
+
+/// A shorter stand-in for a bulky old message, if it has anything to give up
+fn shorten(message: &GroqMessage) -> Option<String> {
+    let MessageContent::Text(text) = &message.content else {
+        return None;
+    };
+    match message.role.as_str() {
+        "tool" if text.lines().count() > SHORTENED_LINES => {
+            let head: Vec<&str> = text.lines().take(SHORTENED_LINES).collect();
+            Some(format!("{}\n[… {} more lines trimmed to fit the context window]", head.join("\n"), text.lines().count() - SHORTENED_LINES))
+        }
+        "user" => text
+            .split_once(REFERENCED_CONTEXT)
+            .map(|(question, _)| format!("{}\n\n[referenced files trimmed to fit the context window]", question)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::GroqClient;
+
+    #[test]
+    fn test_pack_context() {
+        let file = "line\n".repeat(400);
+        let mut messages = vec![
+            GroqClient::create_text_message("system", "You are helpful"),
+            GroqClient::create_text_message("user", &format!("explain{}{}", REFERENCED_CONTEXT, file)),
+            GroqClient::create_text_message("assistant", "It prints lines"),
+            GroqClient::create_tool_result_message("call_1", &file),
+            GroqClient::create_text_message("user", "and now?"),
+        ];
+        assert_eq!(pack(&messages, 10_000).messages.len(), 5);
+
+        let packed = pack(&messages, 200);
+        assert_eq!((packed.shortened, packed.dropped), (2, 0));
+        assert!(matches!(&packed.messages[1].content, MessageContent::Text(text) if text.starts_with("explain\n\n[referenced files trimmed")));
+
+        // Too little room even for the shortened turns: the oldest go, and the model is told
+        messages[2] = GroqClient::create_text_message("assistant", &"word ".repeat(400));
+        let packed = pack(&messages, 200);
+        assert_eq!(packed.messages.first().map(|msg| msg.role.as_str()), Some("system"));
+        assert!(matches!(&packed.messages[1].content, MessageContent::Text(text) if text.contains("left out to fit")));
+        assert!(matches!(&packed.messages.last().unwrap().content, MessageContent::Text(text) if text == "and now?"));
+        assert!(packed.messages.iter().map(message_tokens).sum::<usize>() <= 200);
+
+        // Long system messages give up their tail once together they pass half the budget
+        messages[0] = GroqClient::create_text_message("system", &"memory ".repeat(400));
+        messages.insert(1, GroqClient::create_text_message("system", "You are helpful"));
+        let packed = pack(&messages, 400);
+        assert!(message_tokens(&packed.messages[0]) + message_tokens(&packed.messages[1]) <= 200);
+        assert!(matches!(&packed.messages[0].content, MessageContent::Text(text) if text.ends_with("trimmed to fit the context window]")));
+        assert!(matches!(&packed.messages[1].content, MessageContent::Text(text) if text == "You are helpful"));
+        assert!(matches!(&packed.messages.last().unwrap().content, MessageContent::Text(text) if text == "and now?"));
+
+        assert_eq!(context_window("llama-3.1-8b-instant"), 131_072);
+        assert_eq!(prompt_budget(8192, 4096, &[]), 8192 - 4096 - 1);
+        assert_eq!(prompt_budget(8192, 1_000_000, &[]), 8192 - 4096 - 1);
+        assert_eq!(answer_tokens(200_000, 8192), 8192);
+    }
+}
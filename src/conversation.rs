@@ -1,51 +1,276 @@
-use crate::api::GroqMessage;
+use crate::api::{ContentPart, GroqClient, GroqMessage, MessageContent};
+use crate::context_window::message_tokens;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+/// Estimated tokens of history kept when no limit is configured
+const DEFAULT_MAX_TOKENS: usize = 24_000;
+/// Characters of one message that go into a summary request
+const SUMMARY_MESSAGE_CHARS: usize = 4000;
+/// Pinned messages may take at most this share of the history's tokens, so pinning a big
+/// file dump can't keep everything else out
+const PINNED_SHARE: usize = 2;
+/// Starts the system message that stands in for summarized turns
+pub const SUMMARY_HEADER: &str = "Summary of the earlier conversation";
+const SUMMARY_PROMPT: &str = "Summarize this conversation between a user and a coding assistant for the assistant to continue from. \
+Keep the user's goals and decisions, files and code discussed, changes made, commands run with their outcomes, and open questions. \
+Write terse bullet points and nothing else.";
+
+/// How much of the conversation is kept between requests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HistorySettings {
+    pub max_tokens: usize, // Estimated tokens of history kept; older turns are dropped first
+    pub summarize: bool,   // Have the model condense old turns into a summary before they would be dropped
+}
+
+impl Default for HistorySettings {
+    fn default() -> Self {
+        Self { max_tokens: DEFAULT_MAX_TOKENS, summarize: false }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Conversation {
     messages: Vec<GroqMessage>,
-    max_history: usize,
+    #[serde(default)]
+    sent_at: Vec<Option<DateTime<Utc>>>, // When each message was added; None for those from before this was kept
+    #[serde(default)]
+    pinned: Vec<DateTime<Utc>>, // When the pinned messages were added, which tells them apart
+    #[serde(skip, default = "default_max_tokens")]
+    max_tokens: usize, // Estimated tokens of history kept; set from the config by whoever holds it
+    #[serde(skip)]
+    revision: u64, // Bumped by every change, to tell when it needs saving again
+    #[serde(skip)]
+    trims: u64, // Bumped whenever messages are taken out, so a summary of old ones started earlier can tell it is stale
+}
+
+fn default_max_tokens() -> usize {
+    DEFAULT_MAX_TOKENS
 }
 
 impl Conversation {
     pub fn new() -> Self {
         Self {
             messages: Vec::new(),
-            max_history: 50, // Keep last 50 messages to manage context length
+            sent_at: Vec::new(),
+            pinned: Vec::new(),
+            max_tokens: DEFAULT_MAX_TOKENS,
+            revision: 0,
+            trims: 0,
         }
     }
 
     pub fn add_message(&mut self, message: GroqMessage) {
+        self.sync_times();
         self.messages.push(message);
-        
-        // Trim conversation if it gets too long
-        if self.messages.len() > self.max_history {
-            // Keep system message (if present) and remove oldest user/assistant messages
-            let system_msgs: Vec<GroqMessage> = self.messages
-                .iter()
-                .filter(|msg| msg.role == "system")
-                .cloned()
-                .collect();
-            
-            let other_msgs: Vec<GroqMessage> = self.messages
-                .iter()
-                .filter(|msg| msg.role != "system")
-                .rev()
-                .take(self.max_history - system_msgs.len())
-                .cloned()
-                .collect();
-            
-            self.messages = system_msgs;
-            self.messages.extend(other_msgs.into_iter().rev());
+        self.sent_at.push(Some(Utc::now()));
+        self.revision += 1;
+        self.trim();
+    }
+
+    /// Keep about `max_tokens` of history from now on, dropping the oldest turns if there is more
+    pub fn set_max_tokens(&mut self, max_tokens: usize) {
+        self.max_tokens = max_tokens;
+        self.trim();
+    }
+
+    /// Estimated tokens of all messages, as sent
+    pub fn tokens(&self) -> usize {
+        self.messages.iter().map(message_tokens).sum()
+    }
+
+    /// Drop the oldest messages after the system ones until the rest fits `max_tokens`; pinned
+    /// messages and the latest always stay, though pins past their budget lose it, oldest first
+    fn trim(&mut self) {
+        self.sync_times();
+        while self.pinned_tokens() > self.pin_budget() {
+            let Some(oldest) = self.pinned().first().copied() else {
+                break;
+            };
+            self.unpin(oldest);
+        }
+        let mut tokens = self.tokens();
+        let before = self.messages.len();
+        while tokens > self.max_tokens {
+            let Some(oldest) = self.oldest_unpinned() else {
+                break;
+            };
+            if oldest + 1 >= self.messages.len() {
+                break;
+            }
+            tokens -= message_tokens(&self.messages.remove(oldest));
+            self.sent_at.remove(oldest);
+            // Tool results whose assistant turn was trimmed away would be rejected by the API
+            while self.messages.get(oldest).is_some_and(|msg| msg.role == "tool") && oldest + 1 < self.messages.len() {
+                tokens -= message_tokens(&self.messages.remove(oldest));
+                self.sent_at.remove(oldest);
+            }
+        }
+        if self.messages.len() < before {
+            self.revision += 1;
+            self.trims += 1;
         }
     }
 
+    /// How many unpinned messages after the system ones are worth condensing into a summary: once
+    /// history passes three quarters of its limit, the oldest whole turns down to about half of it
+    pub fn summarizable(&self) -> Option<usize> {
+        let tokens = self.tokens();
+        if tokens <= self.max_tokens * 3 / 4 {
+            return None;
+        }
+        let others: Vec<&GroqMessage> = self.unpinned_history().collect();
+        let mut excess = tokens - self.max_tokens / 2;
+        let mut count = 0;
+        // Never the latest message, and never an assistant turn without its tool results
+        while count + 1 < others.len() && (excess > 0 || others[count].role == "tool") {
+            excess = excess.saturating_sub(message_tokens(others[count]));
+            count += 1;
+        }
+        (count > 0 && others[count].role != "tool").then_some(count)
+    }
+
+    /// The request for a summary of the first `count` unpinned messages after the system ones, building on any earlier summary
+    pub fn summary_request(&self, count: usize) -> Vec<GroqMessage> {
+        let mut transcript: Vec<String> = self
+            .messages
+            .iter()
+            .filter(|msg| msg.role == "system")
+            .filter_map(|msg| message_text(msg).strip_prefix(SUMMARY_HEADER).map(|summary| format!("Earlier summary{}", summary)))
+            .collect();
+        for message in self.unpinned_history().take(count) {
+            let mut text = message_text(message);
+            if let Some((end, _)) = text.char_indices().nth(SUMMARY_MESSAGE_CHARS) {
+                text = format!("{} [… cut]", &text[..end]);
+            }
+            for call in message.tool_calls.iter().flatten() {
+                text.push_str(&format!("\n[called {}({})]", call.function.name, call.function.arguments));
+            }
+            transcript.push(format!("{}: {}", message.role, text.trim()));
+        }
+        vec![
+            GroqClient::create_text_message("system", SUMMARY_PROMPT),
+            GroqClient::create_text_message("user", &transcript.join("\n\n")),
+        ]
+    }
+
+    /// Put `summary` in place of the first `count` unpinned messages after the system ones and of any earlier summary
+    pub fn replace_with_summary(&mut self, count: usize, summary: &str) {
+        self.remove_system_messages(SUMMARY_HEADER);
+        for _ in 0..count {
+            let Some(oldest) = self.oldest_unpinned() else {
+                break;
+            };
+            self.messages.remove(oldest);
+            self.sent_at.remove(oldest);
+        }
+        let position = self.messages.iter().take_while(|msg| msg.role == "system").count();
+        let summary = GroqClient::create_text_message("system", &format!("{}:\n{}", SUMMARY_HEADER, summary.trim()));
+        self.messages.insert(position, summary);
+        self.sent_at.insert(position, Some(Utc::now()));
+        self.revision += 1;
+        self.trims += 1;
+    }
+
+    /// The messages after the system ones that trimming and summaries may take out
+    fn unpinned_history(&self) -> impl Iterator<Item = &GroqMessage> {
+        self.messages.iter().enumerate().filter(|(index, msg)| msg.role != "system" && !self.is_pinned(*index)).map(|(_, msg)| msg)
+    }
+
+    fn oldest_unpinned(&self) -> Option<usize> {
+        (0..self.messages.len()).find(|&index| self.messages[index].role != "system" && !self.is_pinned(index))
+    }
+
+    /// Keep the question or answer at `index` in the history however long it gets; false for
+    /// messages that can't be pinned, such as tool calls and their results
+    pub fn pin(&mut self, index: usize) -> bool {
+        let Some((message, at)) = self.messages.get(index).zip(self.sent_at(index)) else {
+            return false;
+        };
+        if !matches!(message.role.as_str(), "user" | "assistant") || message.tool_calls.is_some() || self.pinned.contains(&at) {
+            return false;
+        }
+        if !self.fits_pin(index) {
+            return false;
+        }
+        self.pinned.push(at);
+        self.revision += 1;
+        true
+    }
+
+    /// Estimated tokens the pinned messages take
+    pub fn pinned_tokens(&self) -> usize {
+        self.pinned().into_iter().map(|index| message_tokens(&self.messages[index])).sum()
+    }
+
+    /// Estimated tokens the pinned messages may take together
+    pub fn pin_budget(&self) -> usize {
+        self.max_tokens / PINNED_SHARE
+    }
+
+    /// Whether pinning the message at `index` keeps the pinned ones within their budget
+    pub fn fits_pin(&self, index: usize) -> bool {
+        self.messages.get(index).is_some_and(|message| self.pinned_tokens() + message_tokens(message) <= self.pin_budget())
+    }
+
+    pub fn unpin(&mut self, index: usize) -> bool {
+        let Some(at) = self.sent_at(index).filter(|at| self.pinned.contains(at)) else {
+            return false;
+        };
+        self.pinned.retain(|pinned| *pinned != at);
+        self.revision += 1;
+        true
+    }
+
+    pub fn is_pinned(&self, index: usize) -> bool {
+        self.sent_at(index).is_some_and(|at| self.pinned.contains(&at))
+    }
+
+    /// Indices of the pinned messages, oldest first
+    pub fn pinned(&self) -> Vec<usize> {
+        (0..self.messages.len()).filter(|&index| self.is_pinned(index)).collect()
+    }
+
+    /// Changes whenever messages are trimmed, summarized or truncated away
+    pub fn trims(&self) -> u64 {
+        self.trims
+    }
+
+    /// When the message at `index` was added, if known
+    pub fn sent_at(&self, index: usize) -> Option<DateTime<Utc>> {
+        self.sent_at.get(index).copied().flatten()
+    }
+
+    /// Index of the message added at `at`, e.g. to find a question again after older messages were trimmed
+    pub fn position_sent_at(&self, at: DateTime<Utc>) -> Option<usize> {
+        self.sent_at.iter().rposition(|sent_at| *sent_at == Some(at))
+    }
+
+    /// Drop the message at `index` and everything after it, e.g. to send an earlier question again
+    pub fn truncate(&mut self, index: usize) {
+        self.sync_times();
+        self.messages.truncate(index);
+        self.sent_at.truncate(index);
+        self.pinned.retain(|at| self.sent_at.contains(&Some(*at)));
+        self.revision += 1;
+        self.trims += 1;
+    }
+
+    /// Give messages from a conversation saved before times were kept an unknown time
+    fn sync_times(&mut self) {
+        self.sent_at.resize(self.messages.len(), None);
+    }
+
     pub fn get_messages(&self) -> &Vec<GroqMessage> {
         &self.messages
     }
 
     pub fn clear(&mut self) {
         self.messages.clear();
+        self.sent_at.clear();
+        self.pinned.clear();
+        self.revision += 1;
     }
 
     pub fn message_count(&self) -> usize {
@@ -55,11 +280,16 @@ impl Conversation {
     pub fn add_system_message(&mut self, content: String) {
         let system_message = GroqMessage {
             role: "system".to_string(),
-            content: crate::api::MessageContent::Text(content),
+            content: MessageContent::Text(content),
+            tool_calls: None,
+            tool_call_id: None,
         };
         
         // Insert system message at the beginning
+        self.sync_times();
         self.messages.insert(0, system_message);
+        self.sent_at.insert(0, Some(Utc::now()));
+        self.revision += 1;
     }
 
     pub fn get_last_user_message(&self) -> Option<&GroqMessage> {
@@ -76,6 +306,42 @@ impl Conversation {
             .find(|msg| msg.role == "assistant")
     }
 
+    /// Whether a system message starts with `prefix`, e.g. to inject one only once
+    pub fn has_system_message(&self, prefix: &str) -> bool {
+        self.messages.iter().any(|msg| {
+            msg.role == "system" && matches!(&msg.content, MessageContent::Text(text) if text.starts_with(prefix))
+        })
+    }
+
+    /// Drop the system messages starting with `prefix`, e.g. to replace one
+    pub fn remove_system_messages(&mut self, prefix: &str) {
+        self.sync_times();
+        let keep: Vec<bool> = self.messages.iter().map(|msg| {
+            msg.role != "system" || !matches!(&msg.content, MessageContent::Text(text) if text.starts_with(prefix))
+        }).collect();
+        let mut kept = keep.iter();
+        self.messages.retain(|_| *kept.next().unwrap_or(&true));
+        let mut kept = keep.iter();
+        self.sent_at.retain(|_| *kept.next().unwrap_or(&true));
+        self.revision += 1;
+    }
+
+    /// Changes whenever the messages do
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Text of the first message with `role`, e.g. the system prompt
+    pub fn first_text_message(&self, role: &str) -> Option<&str> {
+        self.messages
+            .iter()
+            .filter(|msg| msg.role == role)
+            .find_map(|msg| match &msg.content {
+                MessageContent::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+    }
+
     pub fn export_to_json(&self) -> serde_json::Result<String> {
         serde_json::to_string_pretty(self)
     }
@@ -89,4 +355,92 @@ impl Default for Conversation {
     fn default() -> Self {
         Self::new()
     }
-}
\ No newline at end of file
+}
+
+/// A message's text, with `[image]` for its images
+pub fn message_text(message: &GroqMessage) -> String {
+    match &message.content {
+        MessageContent::Text(text) => text.clone(),
+        MessageContent::MultiModal(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => text.as_str(),
+                ContentPart::Image { .. } => "[image]",
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_is_trimmed_and_summarized_by_tokens() {
+        let mut conversation = Conversation::new();
+        conversation.add_system_message("You are helpful".to_string());
+        conversation.set_max_tokens(1000);
+        for turn in 0..4 {
+            conversation.add_message(GroqClient::create_text_message("user", &format!("question {}", turn)));
+            conversation.add_message(GroqClient::create_text_message("assistant", &"answer ".repeat(100)));
+        }
+        assert_eq!(conversation.message_count(), 9);
+        assert_eq!(conversation.summarizable(), Some(4));
+        assert!(matches!(&conversation.summary_request(4)[1].content, MessageContent::Text(text) if text.starts_with("user: question 0\n\nassistant: answer")));
+
+        conversation.replace_with_summary(4, "- asked two questions");
+        assert_eq!(conversation.first_text_message("user"), Some("question 2"));
+        assert!(conversation.has_system_message(SUMMARY_HEADER));
+        assert_eq!(conversation.trims(), 1);
+        assert_eq!(conversation.summarizable(), None);
+
+        // A big file dump pushes the oldest turns out, never the system messages or the latest message
+        conversation.add_message(GroqClient::create_text_message("user", &"x".repeat(8000)));
+        assert_eq!(conversation.message_count(), 3);
+        assert_eq!(conversation.get_messages().iter().filter(|msg| msg.role == "system").count(), 2);
+        assert_eq!(conversation.sent_at.len(), 3);
+        assert_eq!(conversation.trims(), 2);
+
+        // Resending the big message drops it and what came after
+        let at = conversation.sent_at(2).unwrap();
+        assert_eq!(conversation.position_sent_at(at), Some(2));
+        conversation.truncate(2);
+        assert_eq!((conversation.message_count(), conversation.position_sent_at(at)), (2, None));
+    }
+
+    #[test]
+    fn test_pinned_messages_outlive_trimming() {
+        let mut conversation = Conversation::new();
+        conversation.set_max_tokens(1000);
+        conversation.add_message(GroqClient::create_text_message("user", "Always use anyhow for errors"));
+        conversation.add_message(GroqClient::create_text_message("assistant", "Noted"));
+        assert!(conversation.pin(0));
+        assert!(!conversation.pin(0));
+        for turn in 0..4 {
+            conversation.add_message(GroqClient::create_text_message("user", &format!("question {}", turn)));
+            conversation.add_message(GroqClient::create_text_message("assistant", &"answer ".repeat(200)));
+        }
+        assert_eq!(conversation.first_text_message("user"), Some("Always use anyhow for errors"));
+        assert_eq!(conversation.pinned(), vec![0]);
+        assert!(matches!(&conversation.summary_request(1)[1].content, MessageContent::Text(text) if !text.contains("anyhow")));
+
+        conversation.replace_with_summary(1, "- asked a question");
+        assert!(conversation.is_pinned(1));
+        assert!(conversation.unpin(1));
+        assert_eq!(conversation.pinned(), Vec::<usize>::new());
+
+        assert!(conversation.pin(2));
+        conversation.truncate(2);
+        assert!(conversation.pinned.is_empty());
+
+        // A big dump can't be pinned, and lowering the limit unpins what no longer fits
+        conversation.add_message(GroqClient::create_text_message("user", &"x".repeat(2400)));
+        let dump = conversation.message_count() - 1;
+        assert!(!conversation.fits_pin(dump));
+        assert!(!conversation.pin(dump));
+        assert!(conversation.pin(1));
+        conversation.set_max_tokens(10);
+        assert!(conversation.pinned().is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use serde::{Deserialize, Serialize};
+use similar::{Algorithm, ChangeTag, DiffTag, TextDiff};
+use std::time::{Duration, Instant};
+
+/// Line-level diff used to compare a buffer with its on-disk version, agent edits and git revisions
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiffLine<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffAlgorithm {
+    Myers,
+    #[default]
+    Patience, // Anchors on unique lines, so moved blocks and braces line up more readably
+    Lcs,
+}
+
+impl DiffAlgorithm {
+    pub fn next(self) -> Self {
+        match self {
+            DiffAlgorithm::Myers => DiffAlgorithm::Patience,
+            DiffAlgorithm::Patience => DiffAlgorithm::Lcs,
+            DiffAlgorithm::Lcs => DiffAlgorithm::Myers,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            DiffAlgorithm::Myers => "myers",
+            DiffAlgorithm::Patience => "patience",
+            DiffAlgorithm::Lcs => "lcs",
+        }
+    }
+
+    fn algorithm(self) -> Algorithm {
+        match self {
+            DiffAlgorithm::Myers => Algorithm::Myers,
+            DiffAlgorithm::Patience => Algorithm::Patience,
+            DiffAlgorithm::Lcs => Algorithm::Lcs,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct DiffOptions {
+    #[serde(default)]
+    pub algorithm: DiffAlgorithm,
+    #[serde(default)]
+    pub ignore_whitespace: bool, // Lines differing only in spacing count as equal
+}
+
+/// A line split into runs of text, flagged `true` where it differs from the other version
+pub type Runs = Vec<(String, bool)>;
+
+/// Granularity of the highlighting inside a changed line
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Granularity {
+    Word,
+    Char,
+}
+
+// Past this, a diff gives up on finding the smallest result and settles for a coarser one
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+// Longer lines are highlighted as a whole rather than word by word
+const MAX_INLINE_LEN: usize = 2_000;
+
+pub fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    diff_lines_with(old, new, DiffOptions::default())
+}
+
+pub fn diff_lines_with<'a>(old: &'a str, new: &'a str, options: DiffOptions) -> Vec<DiffLine<'a>> {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    // Compare normalized keys, but report the original lines
+    let key = |line: &&'a str| -> String {
+        if options.ignore_whitespace {
+            line.split_whitespace().collect::<Vec<_>>().join(" ")
+        } else {
+            line.to_string()
+        }
+    };
+    let old_keys: Vec<String> = old_lines.iter().map(key).collect();
+    let new_keys: Vec<String> = new_lines.iter().map(key).collect();
+
+    let deadline = Instant::now() + DIFF_TIMEOUT;
+    let ops = similar::capture_diff_slices_deadline(options.algorithm.algorithm(), &old_keys, &new_keys, Some(deadline));
+    let mut result = Vec::new();
+    for op in ops {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            // The buffer's side of lines that only differ in spacing
+            DiffTag::Equal => result.extend(new_lines[new_range].iter().map(|line| DiffLine::Equal(line))),
+            DiffTag::Delete => result.extend(old_lines[old_range].iter().map(|line| DiffLine::Delete(line))),
+            DiffTag::Insert => result.extend(new_lines[new_range].iter().map(|line| DiffLine::Insert(line))),
+            // Deletions first, like `diff -u`
+            DiffTag::Replace => {
+                result.extend(old_lines[old_range].iter().map(|line| DiffLine::Delete(line)));
+                result.extend(new_lines[new_range].iter().map(|line| DiffLine::Insert(line)));
+            }
+        }
+    }
+    result
+}
+
+/// The old and new versions of a changed line, split where they differ
+pub fn inline_diff(old: &str, new: &str, granularity: Granularity) -> (Runs, Runs) {
+    if old.len() > MAX_INLINE_LEN || new.len() > MAX_INLINE_LEN {
+        return (vec![(old.to_string(), true)], vec![(new.to_string(), true)]);
+    }
+    let mut config = TextDiff::configure();
+    config.algorithm(Algorithm::Patience).timeout(DIFF_TIMEOUT);
+    let diff = match granularity {
+        Granularity::Word => config.diff_words(old, new),
+        Granularity::Char => config.diff_chars(old, new),
+    };
+
+    let (mut old_runs, mut new_runs) = (Runs::new(), Runs::new());
+    let push = |runs: &mut Runs, text: &str, changed: bool| match runs.last_mut() {
+        Some((run, was_changed)) if *was_changed == changed => run.push_str(text),
+        _ => runs.push((text.to_string(), changed)),
+    };
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Equal => {
+                push(&mut old_runs, change.value(), false);
+                push(&mut new_runs, change.value(), false);
+            }
+            ChangeTag::Delete => push(&mut old_runs, change.value(), true),
+            ChangeTag::Insert => push(&mut new_runs, change.value(), true),
+        }
+    }
+    (old_runs, new_runs)
+}
+
+/// Render a unified diff with `context` unchanged lines around each hunk
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str, context: usize) -> String {
+    unified_diff_with(old, new, old_label, new_label, context, DiffOptions::default())
+}
+
+pub fn unified_diff_with(old: &str, new: &str, old_label: &str, new_label: &str, context: usize, options: DiffOptions) -> String {
+    let lines = diff_lines_with(old, new, options);
+    let mut output = format!("--- {}\n+++ {}\n", old_label, new_label);
+
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, DiffLine::Equal(_)))
+        .map(|(index, _)| index)
+        .collect();
+    if changed.is_empty() {
+        return output;
+    }
+
+    // Group changes whose context windows overlap into hunks
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &index in &changed {
+        let start = index.saturating_sub(context);
+        let end = (index + context + 1).min(lines.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    for (start, end) in hunks {
+        // Line numbers (1-based) at which this hunk starts in each file
+        let old_start = lines[..start].iter().filter(|line| !matches!(line, DiffLine::Insert(_))).count() + 1;
+        let new_start = lines[..start].iter().filter(|line| !matches!(line, DiffLine::Delete(_))).count() + 1;
+        let old_count = lines[start..end].iter().filter(|line| !matches!(line, DiffLine::Insert(_))).count();
+        let new_count = lines[start..end].iter().filter(|line| !matches!(line, DiffLine::Delete(_))).count();
+
+        output.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_count, new_start, new_count));
+        for line in &lines[start..end] {
+            let (marker, text) = match line {
+                DiffLine::Equal(text) => (' ', text),
+                DiffLine::Delete(text) => ('-', text),
+                DiffLine::Insert(text) => ('+', text),
+            };
+            output.push(marker);
+            output.push_str(text);
+            output.push('\n');
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng";
+        let new = "a\nb\nC\nd\ne\nf\ng\nh";
+        assert_eq!(
+            unified_diff(old, new, "disk", "buffer", 1),
+            "--- disk\n+++ buffer\n@@ -2,3 +2,3 @@\n b\n-c\n+C\n d\n@@ -7,1 +7,2 @@\n g\n+h\n"
+        );
+        assert_eq!(unified_diff(old, old, "a", "b", 3), "--- a\n+++ b\n");
+
+        let spaced = DiffOptions { ignore_whitespace: true, ..DiffOptions::default() };
+        assert_eq!(unified_diff_with("if x {\n  y\n}", "if x  {\n    y\n}", "a", "b", 3, spaced), "--- a\n+++ b\n");
+
+        let (old_runs, new_runs) = inline_diff("let total = a + b;", "let sum = a + b;", Granularity::Word);
+        assert_eq!(old_runs, vec![("let ".to_string(), false), ("total".to_string(), true), (" = a + b;".to_string(), false)]);
+        assert_eq!(new_runs[1], ("sum".to_string(), true));
+        let (_, new_runs) = inline_diff("colour", "color", Granularity::Char);
+        assert_eq!(new_runs, vec![("color".to_string(), false)]);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::agent::actions::RESULT_PREFIX;
+use crate::conversation::message_text;
+use crate::sessions::SavedSession;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// File formats a conversation can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+
+    /// The format a file's extension asks for
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" | "htm" => Ok(ExportFormat::Html),
+            _ => Err(anyhow!("Unknown export format `{}` - use md or html", text)),
+        }
+    }
+}
+
+/// Where an export goes when no file is named: `conversation-<date>-<id>.<ext>` in `dir`
+pub fn default_path(dir: &Path, session: &SavedSession, format: ExportFormat) -> PathBuf {
+    let date = session.updated_at.with_timezone(&Local).format("%Y%m%d-%H%M");
+    let id = &session.id[..session.id.len().min(8)];
+    dir.join(format!("conversation-{}-{}.{}", date, id, format.extension()))
+}
+
+/// Write an export to `path`, making its directory if needed; an existing file is only replaced when `overwrite`
+pub fn write(path: &Path, document: &str, overwrite: bool) -> Result<()> {
+    if path.exists() && !overwrite {
+        return Err(anyhow!("{} already exists", path.display()));
+    }
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, document)?;
+    Ok(())
+}
+
+/// The session's conversation as a document: questions, answers, the agent's actions and their results
+pub fn render(session: &SavedSession, format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(session),
+        ExportFormat::Html => to_html(session),
+    }
+}
+
+/// One part of the conversation as exported
+enum Entry<'a> {
+    Question(String),
+    Answer(String),
+    Action { name: &'a str, arguments: String },
+    Result { name: &'a str, text: String },
+}
+
+/// The parts worth exporting with when they were added; system messages such as the system prompt are left out
+fn entries(session: &SavedSession) -> Vec<(Option<DateTime<Utc>>, Entry<'_>)> {
+    let messages = session.conversation.get_messages();
+    let mut names: HashMap<&str, &str> = HashMap::new(); // Tool call id -> tool name
+    let mut entries = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        let at = session.conversation.sent_at(index);
+        let text = message_text(message);
+        match message.role.as_str() {
+            "user" => match text.strip_prefix(RESULT_PREFIX).and_then(|rest| rest.split_once("] ")) {
+                Some((number, result)) => entries.push((at, Entry::Result { name: "", text: format!("Action {}: {}", number, result) })),
+                None => entries.push((at, Entry::Question(text))),
+            },
+            "assistant" => {
+                if !text.trim().is_empty() {
+                    entries.push((at, Entry::Answer(text)));
+                }
+                for call in message.tool_calls.iter().flatten() {
+                    names.insert(&call.id, &call.function.name);
+                    let arguments = serde_json::from_str::<serde_json::Value>(&call.function.arguments)
+                        .and_then(|value| serde_json::to_string_pretty(&value))
+                        .unwrap_or_else(|_| call.function.arguments.clone());
+                    entries.push((at, Entry::Action { name: &call.function.name, arguments }));
+                }
+            }
+            "tool" => {
+                let name = message.tool_call_id.as_deref().and_then(|id| names.get(id).copied()).unwrap_or("");
+                entries.push((at, Entry::Result { name, text }));
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn heading(session: &SavedSession) -> &str {
+    match (&session.name, session.title.is_empty()) {
+        (Some(name), _) => name,
+        (None, false) => &session.title,
+        (None, true) => "Conversation",
+    }
+}
+
+fn time(at: Option<DateTime<Utc>>) -> String {
+    at.map(|at| format!(" · {}", at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))).unwrap_or_default()
+}
+
+fn meta(session: &SavedSession) -> String {
+    format!(
+        "{} · started {} · exported {}",
+        session.project.display(),
+        session.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+        Local::now().format("%Y-%m-%d %H:%M")
+    )
+}
+
+fn action_label(name: &str) -> String {
+    if name.is_empty() { "Result".to_string() } else { format!("Result of {}", name) }
+}
+
+fn to_markdown(session: &SavedSession) -> String {
+    let mut out = format!("# {}\n\n*{}*\n", heading(session), meta(session));
+    for (at, entry) in entries(session) {
+        let section = match entry {
+            Entry::Question(text) => format!("### 🧑 You{}\n\n{}", time(at), text.trim()),
+            Entry::Answer(text) => format!("### 🤖 Assistant{}\n\n{}", time(at), text.trim()),
+            Entry::Action { name, arguments } => format!("**⚙️ Action `{}`**{}\n\n{}", name, time(at), fenced(&arguments, "json")),
+            Entry::Result { name, text } => format!("**{}**\n\n{}", action_label(name), fenced(text.trim(), "")),
+        };
+        out.push('\n');
+        out.push_str(&section);
+        out.push('\n');
+    }
+    out
+}
+
+/// `text` in a code fence longer than any run of backticks inside it
+fn fenced(text: &str, language: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, text, fence)
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:52rem;margin:2rem auto;padding:0 1rem;line-height:1.5;color:#222}\
+.meta{color:#777}section{border-left:4px solid #ccc;padding:.2rem 1rem;margin:1rem 0}\
+.user{border-color:#2e9e44}.assistant{border-color:#1f8fbf}.action,.result{border-color:#c9a227;font-size:.9em}\
+h3{margin:.4rem 0;font-size:1rem}time{color:#888;font-weight:normal}\
+pre{background:#f4f4f4;padding:.6rem;overflow-x:auto}code{background:#f4f4f4;padding:0 .2rem}";
+
+fn to_html(session: &SavedSession) -> String {
+    let mut body = String::new();
+    for (at, entry) in entries(session) {
+        let (class, title, content) = match entry {
+            Entry::Question(text) => ("user", "🧑 You".to_string(), markdown_to_html(&text)),
+            Entry::Answer(text) => ("assistant", "🤖 Assistant".to_string(), markdown_to_html(&text)),
+            Entry::Action { name, arguments } => ("action", format!("⚙️ Action <code>{}</code>", escape(name)), code_block(&arguments, "json")),
+            Entry::Result { name, text } => ("result", escape(&action_label(name)), code_block(text.trim(), "")),
+        };
+        let time = at.map(|at| format!(" <time>{}</time>", at.with_timezone(&Local).format("%Y-%m-%d %H:%M"))).unwrap_or_default();
+        body.push_str(&format!("<section class=\"{}\">\n<h3>{}{}</h3>\n{}</section>\n", class, title, time, content));
+    }
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"meta\">{}</p>\n{}</body>\n</html>\n",
+        STYLE,
+        escape(&meta(session)),
+        body,
+        title = escape(heading(session)),
+    )
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn code_block(code: &str, language: &str) -> String {
+    let class = if language.is_empty() { String::new() } else { format!(" class=\"language-{}\"", language) };
+    format!("<pre><code{}>{}</code></pre>\n", class, escape(code))
+}
+
+/// The Markdown the model writes as HTML: code fences, headings, paragraphs, inline code and bold
+fn markdown_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<String> = Vec::new();
+    let mut code: Option<(&str, Vec<&str>)> = None;
+    let flush = |paragraph: &mut Vec<String>, html: &mut String| {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", paragraph.join("<br>\n")));
+            paragraph.clear();
+        }
+    };
+    for line in text.lines() {
+        if let Some((language, lines)) = code.as_mut() {
+            if line.trim_start().starts_with("```") {
+                html.push_str(&code_block(&lines.join("\n"), language));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        if let Some(language) = line.trim_start().strip_prefix("```") {
+            flush(&mut paragraph, &mut html);
+            code = Some((language.trim(), Vec::new()));
+        } else if line.trim().is_empty() {
+            flush(&mut paragraph, &mut html);
+        } else if let Some(title) = line.trim_start().strip_prefix('#').map(|rest| rest.trim_start_matches('#')).filter(|rest| rest.starts_with(' ')) {
+            flush(&mut paragraph, &mut html);
+            html.push_str(&format!("<h4>{}</h4>\n", inline(title.trim())));
+        } else {
+            paragraph.push(inline(line));
+        }
+    }
+    flush(&mut paragraph, &mut html);
+    if let Some((language, lines)) = code {
+        html.push_str(&code_block(&lines.join("\n"), language)); // An answer cut off inside a code block
+    }
+    html
+}
+
+fn inline(text: &str) -> String {
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    static BOLD: OnceLock<Regex> = OnceLock::new();
+    let code = CODE.get_or_init(|| Regex::new(r"`([^`]+)`").expect("valid regex"));
+    let bold = BOLD.get_or_init(|| Regex::new(r"\*\*([^*]+)\*\*").expect("valid regex"));
+    let escaped = escape(text);
+    let with_code = code.replace_all(&escaped, "<code>$1</code>");
+    bold.replace_all(&with_code, "<strong>$1</strong>").into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::GroqClient;
+    use crate::conversation::Conversation;
+
+    #[test]
+    fn test_export_formats() {
+        let mut conversation = Conversation::new();
+        conversation.add_system_message("You are a coding assistant".to_string());
+        conversation.add_message(GroqClient::create_text_message("user", "Fix <main>"));
+        conversation.add_message(GroqClient::create_text_message("assistant", "Use **this**:\n\n```rust\nfn main() {}\n```"));
+        let mut session = SavedSession::new("0123456789".to_string(), PathBuf::from("/work/app"));
+        session.name = Some("debug CI".to_string());
+        session.conversation = conversation;
+
+        let markdown = render(&session, ExportFormat::Markdown);
+        assert!(markdown.starts_with("# debug CI\n\n*/work/app · started "));
+        assert!(markdown.contains("### 🧑 You · "));
+        assert!(markdown.contains("\n\nUse **this**:\n\n```rust\nfn main() {}\n```\n"));
+        assert!(!markdown.contains("coding assistant"));
+
+        let html = render(&session, ExportFormat::Html);
+        assert!(html.contains("<p>Fix &lt;main&gt;</p>"));
+        assert!(html.contains("<p>Use <strong>this</strong>:</p>\n<pre><code class=\"language-rust\">fn main() {}</code></pre>"));
+
+        assert_eq!(fenced("a ``` b", ""), "````\na ``` b\n````");
+        assert_eq!(ExportFormat::from_path(Path::new("notes/chat.HTML")), Some(ExportFormat::Html));
+        assert!(default_path(Path::new("/tmp"), &session, ExportFormat::Markdown).display().to_string().ends_with("-01234567.md"));
+
+        let file = std::env::temp_dir().join(format!("i4z-export-{}", uuid::Uuid::new_v4())).join("notes/chat.md");
+        write(&file, "first", false).unwrap();
+        assert!(write(&file, "second", false).is_err());
+        write(&file, "second", true).unwrap();
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "second");
+        std::fs::remove_dir_all(file.parent().unwrap().parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,356 @@
+//! Non-interactive `agent ask` / `agent run`, printing either plain text or newline-delimited
+//! JSON events for other tools to consume.
+
+use crate::agent::actions::{self, AgentActionParser, STRICT_JSON_ID};
+use crate::agent::orchestrator::{AgentRun, LoopBudget};
+use crate::agent::{project_context, project_memory};
+use crate::agent::spend_guard::{SpendGuard, SpendLimits};
+use crate::agent::supervisor::{AutonomySettings, Supervisor};
+use crate::agent::turn_summary::{self, FileChange};
+use crate::agent::{tools, AgentAction, AgentExecutor, AgentResponse};
+use crate::api::{GroqClient, GroqMessage, ResponseMessage, Tool};
+use crate::provider::LlmProvider;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::io::Write;
+use std::path::Path;
+use std::time::Instant;
+
+/// Bumped whenever an event changes shape; every JSON line carries it as `"v"`
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Process exit codes; 2 is left to clap for usage errors
+pub mod exit_code {
+    pub const OK: i32 = 0;
+    pub const ERROR: i32 = 1; // Config, network or API failure
+    pub const ACTIONS_FAILED: i32 = 3; // At least one action ran and failed
+    pub const REVIEW_REQUIRED: i32 = 4; // Stopped at an action that needs approval
+}
+
+const RUN_SYSTEM_PROMPT: &str = "You are a coding agent working in the user's project directory. \
+Use the tools to read and change files; paths are relative to the project root. \
+Explain briefly what you are doing, and answer without calling a tool once the task is done.";
+
+/// Which model answers, and how
+pub struct Model<'a> {
+    pub client: &'a dyn LlmProvider,
+    pub name: &'a str,
+    pub temperature: f32,
+    pub persona: Option<String>, // The configured persona's system message, sent first
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!("unknown output format '{}' (expected text or json)", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Message { role: String, content: String },
+    ToolCall { id: usize, action: AgentAction },
+    ToolResult { id: usize, #[serde(flatten)] response: AgentResponse },
+    Diff { id: usize, path: String, diff: String },
+    Usage { prompt_tokens: u32, completion_tokens: u32, total_tokens: u32 },
+    Error { message: String },
+    Done { outcome: &'static str, exit_code: i32 }, // Always the last event
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    v: u32,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Writes events in the chosen format
+pub struct Emitter<W: Write> {
+    format: OutputFormat,
+    out: W,
+}
+
+impl<W: Write> Emitter<W> {
+    pub fn new(format: OutputFormat, out: W) -> Self {
+        Self { format, out }
+    }
+
+    pub fn emit(&mut self, event: Event) -> Result<()> {
+        match self.format {
+            OutputFormat::Json => {
+                let line = serde_json::to_string(&Envelope { v: SCHEMA_VERSION, event: &event })?;
+                writeln!(self.out, "{}", line)?;
+            }
+            OutputFormat::Text => match &event {
+                Event::Message { content, .. } => writeln!(self.out, "{}", content)?,
+                Event::ToolCall { action, .. } => writeln!(self.out, "▶ {:?}", action)?,
+                Event::ToolResult { response, .. } => {
+                    let icon = if response.success { "✅" } else { "❌" };
+                    writeln!(self.out, "{} {}", icon, response.message)?;
+                    if let Some(error) = &response.error {
+                        writeln!(self.out, "   {}", error)?;
+                    }
+                }
+                Event::Diff { diff, .. } => write!(self.out, "{}", diff)?,
+                Event::Usage { prompt_tokens, completion_tokens, .. } => {
+                    eprintln!("[tokens: {} prompt, {} completion]", prompt_tokens, completion_tokens)
+                }
+                Event::Error { message } => eprintln!("❌ {}", message),
+                Event::Done { .. } => {}
+            },
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+
+    /// Emit the final event and hand back the exit code
+    pub fn finish(&mut self, exit_code: i32) -> i32 {
+        let outcome = match exit_code {
+            exit_code::OK => "ok",
+            exit_code::ACTIONS_FAILED => "actions_failed",
+            exit_code::REVIEW_REQUIRED => "review_required",
+            _ => "error",
+        };
+        let _ = self.emit(Event::Done { outcome, exit_code });
+        exit_code
+    }
+
+    /// Report an error that ended the run
+    pub fn fail(&mut self, error: anyhow::Error) -> i32 {
+        let _ = self.emit(Event::Error { message: error.to_string() });
+        self.finish(exit_code::ERROR)
+    }
+}
+
+/// One question, one answer; no actions are executed
+pub async fn ask<W: Write>(model: &Model<'_>, prompt: &str, root: &Path, emitter: &mut Emitter<W>) -> i32 {
+    let mut messages: Vec<GroqMessage> = model
+        .persona
+        .iter()
+        .cloned()
+        .chain(project_memory::system_message(root))
+        .map(|system| GroqClient::create_text_message("system", &system))
+        .collect();
+    messages.push(GroqClient::create_text_message("user", prompt));
+    match reply(model, messages, &[], emitter).await {
+        Ok(_) => emitter.finish(exit_code::OK),
+        Err(e) => emitter.fail(e),
+    }
+}
+
+/// How far `run` may go on its own
+pub struct RunSettings {
+    pub autonomy: AutonomySettings,
+    pub budget: LoopBudget,
+    pub context_entries: usize, // Files in the project summary sent up front; 0 for none
+    pub strict_json: bool, // Actions come from a JSON block in the answer instead of tool calls
+    pub spend: SpendLimits, // Nobody is there to wave the run past these, so it stops at them
+}
+
+/// Ask the model for a task and execute the actions it replies with, as far as the autonomy level allows
+pub async fn run<W: Write>(
+    model: &Model<'_>,
+    task: &str,
+    executor: &mut dyn AgentExecutor,
+    root: &Path,
+    settings: RunSettings,
+    emitter: &mut Emitter<W>,
+) -> i32 {
+    let mut messages = vec![GroqClient::create_text_message("system", RUN_SYSTEM_PROMPT)];
+    if let Some(persona) = &model.persona {
+        messages.push(GroqClient::create_text_message("system", persona));
+    }
+    let RunSettings { autonomy, budget, context_entries, strict_json, spend } = settings;
+    if let Some(memory) = project_memory::system_message(root) {
+        messages.push(GroqClient::create_text_message("system", &memory));
+    }
+    if strict_json {
+        messages.push(GroqClient::create_text_message("system", &actions::strict_json_prompt()));
+    }
+    if context_entries > 0 {
+        messages.push(GroqClient::create_text_message("system", &project_context::summarize(root, context_entries)));
+    }
+    messages.push(GroqClient::create_text_message("user", task));
+    let tools = if strict_json { Vec::new() } else { tools::definitions() };
+    let mut supervisor = Supervisor::new(autonomy);
+    let mut agent_run = AgentRun::new(budget);
+    let mut spend_guard = SpendGuard::new(spend);
+    let mut failed = false;
+    let mut id = 0;
+
+    // Each round the model either calls tools, whose results go back to it, or answers and is done
+    loop {
+        if let Some(reason) = agent_run.exhausted() {
+            return emitter.fail(anyhow!("Stopped without a final answer: {}", reason.describe(&budget)));
+        }
+        if let Some(reason) = spend_guard.check(Instant::now()) {
+            let _ = emitter.emit(Event::Error { message: format!("Stopped at {}; raise spend_limits to let it go on", reason) });
+            return emitter.finish(exit_code::REVIEW_REQUIRED);
+        }
+        spend_guard.record_request(Instant::now());
+        let (answer, tokens) = match reply(model, messages.clone(), &tools, emitter).await {
+            Ok(reply) => reply,
+            Err(e) => return emitter.fail(e),
+        };
+        agent_run.record(tokens);
+        spend_guard.record_tokens(tokens);
+        let content = answer.content.unwrap_or_default();
+        let calls: Vec<(String, Result<AgentAction>)> = if strict_json {
+            messages.push(GroqClient::create_text_message("assistant", &content));
+            match AgentActionParser::parse_strict(&content) {
+                Ok(actions) => actions
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, action)| (format!("{}{}", STRICT_JSON_ID, index + 1), Ok(action)))
+                    .collect(),
+                Err(e) => {
+                    // Ask again; the loop budget bounds how often
+                    let retry = actions::strict_retry_prompt(&e);
+                    if emitter.emit(Event::Message { role: "user".to_string(), content: retry.clone() }).is_err() {
+                        return exit_code::ERROR;
+                    }
+                    messages.push(GroqClient::create_text_message("user", &retry));
+                    continue;
+                }
+            }
+        } else {
+            let calls = answer.tool_calls.unwrap_or_default();
+            if !calls.is_empty() {
+                messages.push(GroqClient::create_tool_call_message(&content, calls.clone()));
+            }
+            calls.iter().map(|call| (call.id.clone(), tools::parse_call(call))).collect()
+        };
+        if calls.is_empty() {
+            return emitter.finish(if failed { exit_code::ACTIONS_FAILED } else { exit_code::OK });
+        }
+
+        for (call_id, action) in calls {
+            let response = match action {
+                Ok(action) => {
+                    if emitter.emit(Event::ToolCall { id, action: action.clone() }).is_err() {
+                        return exit_code::ERROR;
+                    }
+                    if supervisor.needs_review(&action) {
+                        let response = AgentResponse::error(
+                            "Not run: needs review".to_string(),
+                            "Raise the autonomy level (--autonomous) to let it run unattended".to_string(),
+                        );
+                        let _ = emitter.emit(Event::ToolResult { id, response });
+                        let _ = summarize_turn(&mut supervisor, &mut messages, emitter);
+                        return emitter.finish(exit_code::REVIEW_REQUIRED);
+                    }
+                    execute(action, id, executor, root, &mut supervisor, emitter)
+                }
+                Err(e) => {
+                    let response = AgentResponse::error("Invalid tool call".to_string(), e.to_string());
+                    let _ = emitter.emit(Event::ToolResult { id, response: response.clone() });
+                    response
+                }
+            };
+            failed |= !response.success;
+            messages.push(actions::result_message(&call_id, &response));
+            id += 1;
+        }
+        if summarize_turn(&mut supervisor, &mut messages, emitter).is_err() {
+            return exit_code::ERROR;
+        }
+    }
+}
+
+/// Tell the model, and whoever reads the output, what the turn's actions changed in the workspace
+fn summarize_turn<W: Write>(supervisor: &mut Supervisor, messages: &mut Vec<GroqMessage>, emitter: &mut Emitter<W>) -> Result<()> {
+    let Some(summary) = supervisor.take_turn_summary().to_message() else {
+        return Ok(());
+    };
+    emitter.emit(Event::Message { role: "user".to_string(), content: summary.clone() })?;
+    messages.push(GroqClient::create_text_message("user", &summary));
+    Ok(())
+}
+
+/// Run one action, reporting its result and, for file changes that went through, the diff
+fn execute<W: Write>(
+    action: AgentAction,
+    id: usize,
+    executor: &mut dyn AgentExecutor,
+    root: &Path,
+    supervisor: &mut Supervisor,
+    emitter: &mut Emitter<W>,
+) -> AgentResponse {
+    let change = turn_summary::measure(&action, root);
+    supervisor.record(&action, change.as_ref().map_or(0, FileChange::lines_changed));
+    let preview = turn_summary::preview_diff(&action, root);
+    let response = match executor.execute_action(action.clone()) {
+        Ok(response) => response,
+        Err(e) => AgentResponse::error("Action failed".to_string(), e.to_string()),
+    };
+    supervisor.turn.record(&action, change, response.success);
+    let _ = emitter.emit(Event::ToolResult { id, response: response.clone() });
+    if let (true, Some((path, diff))) = (response.success, preview) {
+        let _ = emitter.emit(Event::Diff { id, path, diff });
+    }
+    response
+}
+
+async fn reply<W: Write>(
+    model: &Model<'_>,
+    messages: Vec<GroqMessage>,
+    tools: &[Tool],
+    emitter: &mut Emitter<W>,
+) -> Result<(ResponseMessage, u32)> {
+    let response = model.client.chat(model.name, messages, model.temperature, tools).await?;
+    let message = response
+        .choices
+        .into_iter()
+        .next()
+        .map(|choice| choice.message)
+        .ok_or_else(|| anyhow!("No response from Groq API"))?;
+    if let Some(content) = message.content.as_ref().filter(|content| !content.is_empty()) {
+        emitter.emit(Event::Message { role: "assistant".to_string(), content: content.clone() })?;
+    }
+    let usage = response.usage;
+    emitter.emit(Event::Usage {
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        total_tokens: usage.total_tokens,
+    })?;
+    Ok((message, usage.total_tokens))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_events() {
+        let mut emitter = Emitter::new(OutputFormat::Json, Vec::new());
+        emitter.emit(Event::Message { role: "assistant".to_string(), content: "hi".to_string() }).unwrap();
+        let response = AgentResponse::success("Read a.rs".to_string(), None);
+        emitter.emit(Event::ToolResult { id: 0, response }).unwrap();
+        assert_eq!(emitter.finish(exit_code::ACTIONS_FAILED), 3);
+
+        let lines: Vec<serde_json::Value> = String::from_utf8(emitter.out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0], serde_json::json!({"v": 1, "type": "message", "role": "assistant", "content": "hi"}));
+        assert_eq!(lines[1]["type"], "tool_result");
+        assert_eq!(lines[1]["success"], true);
+        assert_eq!(lines[2], serde_json::json!({"v": 1, "type": "done", "outcome": "actions_failed", "exit_code": 3}));
+
+        assert_eq!("json".parse(), Ok(OutputFormat::Json));
+        assert!("yaml".parse::<OutputFormat>().is_err());
+    }
+}
@@ -0,0 +1,118 @@
+use fluent_bundle::{FluentArgs, FluentBundle, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+// UI catalogs compiled into the binary; en-US is the fallback for missing messages
+const LOCALES: &[(&str, &str)] = &[
+    ("en-US", include_str!("../locales/en-US.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+];
+
+/// Pick the catalog for a requested locale ("es_ES.UTF-8", "es-MX", "en"), matching on the language
+pub fn resolve_locale(requested: &str) -> &'static str {
+    let tag = requested.split('.').next().unwrap_or("").replace('_', "-");
+    let language = tag
+        .parse::<LanguageIdentifier>()
+        .map(|id| id.language.as_str().to_string())
+        .unwrap_or_default();
+    LOCALES
+        .iter()
+        .map(|(locale, _)| *locale)
+        .find(|locale| locale.split('-').next() == Some(language.as_str()))
+        .unwrap_or("en-US")
+}
+
+/// Locale from the config, else the environment ($LC_ALL, $LC_MESSAGES, $LANG)
+pub fn detect_locale(configured: Option<&str>) -> &'static str {
+    let from_env = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty());
+    resolve_locale(configured.map(str::to_string).or(from_env).as_deref().unwrap_or("en-US"))
+}
+
+pub struct Localizer {
+    bundle: FluentBundle<FluentResource>,
+    fallback: FluentBundle<FluentResource>,
+}
+
+impl Localizer {
+    pub fn new(locale: &str) -> Self {
+        Self {
+            bundle: bundle_for(resolve_locale(locale)),
+            fallback: bundle_for("en-US"),
+        }
+    }
+
+    /// Translated message, falling back to English and then to the message id itself
+    pub fn t(&self, id: &str) -> String {
+        self.format(id, None)
+    }
+
+    pub fn t_args(&self, id: &str, args: &[(&str, &str)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, FluentValue::from(*value));
+        }
+        self.format(id, Some(&fluent_args))
+    }
+
+    fn format(&self, id: &str, args: Option<&FluentArgs>) -> String {
+        [&self.bundle, &self.fallback]
+            .into_iter()
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                Some(bundle.format_pattern(pattern, args, &mut errors).into_owned())
+            })
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+fn bundle_for(locale: &'static str) -> FluentBundle<FluentResource> {
+    let source = LOCALES
+        .iter()
+        .find(|(name, _)| *name == locale)
+        .map(|(_, source)| *source)
+        .unwrap_or(LOCALES[0].1);
+    let language: LanguageIdentifier = locale.parse().unwrap_or_default();
+
+    let mut bundle = FluentBundle::new(vec![language]);
+    // Bidi isolation marks would show up as stray characters in the terminal
+    bundle.set_use_isolating(false);
+    let resource = FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, _)| resource);
+    let _ = bundle.add_resource(resource);
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localizer() {
+        assert_eq!(resolve_locale("es_ES.UTF-8"), "es");
+        assert_eq!(resolve_locale("es-MX"), "es");
+        assert_eq!(resolve_locale("fr_FR"), "en-US");
+        assert_eq!(resolve_locale("C"), "en-US");
+
+        let spanish = Localizer::new("es");
+        assert_eq!(spanish.t("status-mode-insert"), "INSERTAR");
+        assert_eq!(spanish.t_args("status-file-count", &[("count", "3")]), "(3 archivos)");
+        assert_eq!(spanish.t("no-such-message"), "no-such-message");
+        assert_eq!(Localizer::new("en-US").t("status-panel-chat"), "CHAT");
+
+        // Every catalog must define the same messages as the English one
+        let ids: Vec<&str> = LOCALES[0].1
+            .lines()
+            .filter_map(|line| line.split_once(" = ").map(|(id, _)| id))
+            .filter(|id| !id.starts_with('#'))
+            .collect();
+        for (locale, source) in LOCALES {
+            let bundle = bundle_for(locale);
+            assert!(FluentResource::try_new(source.to_string()).is_ok(), "{} has syntax errors", locale);
+            for id in &ids {
+                assert!(bundle.has_message(id), "{} is missing {}", locale, id);
+            }
+        }
+    }
+}
@@ -0,0 +1,88 @@
+/// Highest `max_tokens` the form accepts; providers cap answers well below this anyway
+const MAX_TOKENS_LIMIT: u32 = 1_000_000;
+/// ←/→ step of `max_tokens`
+const MAX_TOKENS_STEP: u32 = 256;
+
+/// Rows of the API settings form, top to bottom
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingsRow {
+    Model,
+    Persona,
+    Temperature,
+    MaxTokens,
+    DryRun,
+}
+
+impl SettingsRow {
+    pub const ALL: [SettingsRow; 5] =
+        [SettingsRow::Model, SettingsRow::Persona, SettingsRow::Temperature, SettingsRow::MaxTokens, SettingsRow::DryRun];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SettingsRow::Model => "Model",
+            SettingsRow::Persona => "Persona",
+            SettingsRow::Temperature => "Temperature",
+            SettingsRow::MaxTokens => "Max tokens",
+            SettingsRow::DryRun => "Dry run",
+        }
+    }
+}
+
+/// The persona `steps` away from `current` in `names`, passing through "none" between the last and the first
+pub fn step_persona(names: &[String], current: Option<&str>, steps: i32) -> Option<String> {
+    // Position 0 is "none", persona i is at i + 1
+    let slots = names.len() as i32 + 1;
+    let position = current
+        .and_then(|current| names.iter().position(|name| name.eq_ignore_ascii_case(current)))
+        .map_or(0, |index| index as i32 + 1);
+    let next = (position + steps).rem_euclid(slots);
+    (next > 0).then(|| names[next as usize - 1].clone())
+}
+
+/// `temperature` moved by `steps` tenths, kept within the API's 0-2
+pub fn step_temperature(temperature: f32, steps: i32) -> f32 {
+    (((temperature * 10.0).round() + steps as f32) / 10.0).clamp(0.0, 2.0)
+}
+
+/// `max_tokens` moved by `steps` of 256; going below the first step clears it, which leaves the
+/// answer length to the network profile
+pub fn step_max_tokens(max_tokens: Option<u32>, steps: i32) -> Option<u32> {
+    let current = max_tokens.unwrap_or(0) as i64;
+    let next = (current / MAX_TOKENS_STEP as i64 + steps as i64) * MAX_TOKENS_STEP as i64;
+    (next > 0).then(|| next.min(MAX_TOKENS_LIMIT as i64) as u32)
+}
+
+/// `max_tokens` as typed: a digit is appended, `None` (Backspace) drops the last one
+pub fn type_max_tokens(max_tokens: Option<u32>, digit: Option<u32>) -> Option<u32> {
+    let current = max_tokens.unwrap_or(0);
+    let next = match digit {
+        Some(digit) => current.saturating_mul(10).saturating_add(digit).min(MAX_TOKENS_LIMIT),
+        None => current / 10,
+    };
+    (next > 0).then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_steps() {
+        assert_eq!(step_temperature(0.7, 1), 0.8);
+        assert_eq!(step_temperature(0.05, -3), 0.0);
+        assert_eq!(step_temperature(1.95, 5), 2.0);
+
+        assert_eq!(step_max_tokens(Some(4096), 1), Some(4352));
+        assert_eq!(step_max_tokens(Some(600), -1), Some(256));
+        assert_eq!(step_max_tokens(Some(300), -1), None);
+        assert_eq!(step_max_tokens(None, 1), Some(256));
+
+        assert_eq!(type_max_tokens(Some(20), Some(4)), Some(204));
+        assert_eq!(type_max_tokens(Some(7), None), None);
+
+        let names = vec!["concise".to_string(), "rust-reviewer".to_string()];
+        assert_eq!(step_persona(&names, None, 1), Some("concise".to_string()));
+        assert_eq!(step_persona(&names, Some("rust-reviewer"), 1), None);
+        assert_eq!(step_persona(&names, None, -1), Some("rust-reviewer".to_string()));
+    }
+}
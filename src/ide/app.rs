@@ -1,10 +1,52 @@
-use crate::api::GroqClient;
+use crate::api::{GroqClient, GroqMessage, GroqResponse, ResponseMessage, Tool};
+use crate::agent::actions::{self, format_agent_responses, AgentActionParser, STRICT_JSON_ID};
+use crate::agent::command::CommandMonitor;
+use crate::agent::executor::DefaultAgentExecutor;
+use crate::agent::policy::AgentPolicy;
+use crate::agent::orchestrator::{AgentRun, StopReason};
+use crate::agent::plan::Plan;
+use crate::agent::project_context;
+use crate::agent::project_memory;
+use crate::agent::code_commands::{CodeCommand, CodeRegion};
+use crate::agent::references::{self, OpenFile, ProjectIndex};
+use crate::agent::retrieval::{self, Lookup, VectorIndex};
+use crate::agent::scratchpad::Scratchpad;
+use crate::agent::spend_guard::SpendGuard;
+use crate::agent::task_queue::TaskQueue;
+use crate::agent::supervisor::{AutonomyLevel, SupervisedRun, Supervisor};
+use crate::agent::tools;
+use crate::agent::undo::UndoLog;
+use crate::agent::{AgentAction, AgentCapabilities, AgentResponse};
 use crate::config::Config;
-use crate::conversation::Conversation;
+use crate::config_layers::PROJECT_FILE;
+use crate::conversation::{message_text, Conversation};
+use crate::export::{self, ExportFormat};
+use crate::sessions::{self, BranchRow, Checkpoint, Fork, SavedSession};
+use crate::i18n::{self, Localizer};
 use crate::clipboard::ClipboardManager;
-use crate::ide::{sidebar, editor, statusbar, events::IdeEvent};
+use crate::provider::{self, LlmProvider};
+use crate::persona;
+use crate::postprocess;
+use crate::workflow;
+use crate::ide::{sidebar, editor, statusbar, palette, emoji_picker, diff_view, marks, file_refs, notification_log, notes_view, tasks_view, plan_editor, approval, language_picker, model_picker, autosave, opener, events::IdeEvent};
+use crate::ide::api_settings::{self, SettingsRow};
+use crate::ide::explorer_filter::ExplorerFilter;
+use crate::ide::keymap::{Keymap, KeymapView};
+use crate::ide::settings_panel::{SettingKind, SettingsPanel};
+use crate::ide::requests::{RequestId, RequestKind, Requests};
+use crate::ide::session::WorkspaceSession;
+use crate::ide::session_browser::SessionBrowser;
+use crate::ide::branch_view::BranchView;
+use crate::ide::prompt_history::{PromptHistory, PromptSearch};
+use crate::ide::sidebar::chat::Chat;
+use crate::ide::threads::{ChatThread, Threads};
+use crate::ide::session_lock::{LockHolder, SessionLock};
+use crate::ide::sidebar::chat::MessageType;
+use crate::ide::sidebar::completion::{Completion, CompletionItem};
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[derive(Debug, Clone)]
@@ -14,7 +56,7 @@ pub struct NotificationMessage {
     pub notification_type: NotificationType,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum NotificationType {
     MouseHover,
     MouseClick,
@@ -23,6 +65,15 @@ pub enum NotificationType {
     Debug,
 }
 
+/// A summary of the conversation's oldest turns on its way; it applies only if those turns are
+/// still there when it arrives
+struct PendingSummary {
+    session: String, // Saved session it was started for
+    trims: u64,      // The conversation's trims at the start
+    count: usize,    // Messages after the system ones it condenses
+    task: tokio::task::JoinHandle<Result<GroqResponse>>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AppMode {
     Normal,
@@ -30,6 +81,76 @@ pub enum AppMode {
     Agentic,
 }
 
+/// What to do once the user answers the unsaved-changes prompt
+#[derive(Debug, Clone, PartialEq)]
+pub enum PendingClose {
+    Tab(u32),
+    Tabs(Vec<u32>), // From the tab menu's "Close Others" / "Close to the Right"
+    Quit,
+}
+
+impl PendingClose {
+    /// Whether closing would lose this tab's unsaved changes
+    fn covers(&self, tab_id: u32) -> bool {
+        match self {
+            PendingClose::Tab(id) => *id == tab_id,
+            PendingClose::Tabs(ids) => ids.contains(&tab_id),
+            PendingClose::Quit => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TabMenuItem {
+    Pin,
+    Close,
+    CloseOthers,
+    CloseToTheRight,
+    CopyPath,
+}
+
+impl TabMenuItem {
+    pub const ALL: [TabMenuItem; 5] = [
+        TabMenuItem::Pin,
+        TabMenuItem::Close,
+        TabMenuItem::CloseOthers,
+        TabMenuItem::CloseToTheRight,
+        TabMenuItem::CopyPath,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TabMenuItem::Pin => "Pin / Unpin",
+            TabMenuItem::Close => "Close",
+            TabMenuItem::CloseOthers => "Close Others",
+            TabMenuItem::CloseToTheRight => "Close to the Right",
+            TabMenuItem::CopyPath => "Copy Path",
+        }
+    }
+}
+
+/// Context menu opened by right-clicking a tab
+#[derive(Debug, Clone, Copy)]
+pub struct TabMenu {
+    pub tab_id: u32,
+    pub x: u16, // Where the click happened; the menu opens just below it
+    pub y: u16,
+    pub selected: usize,
+}
+
+/// Command output lines kept in the chat while agent actions run
+const LIVE_OUTPUT_LINES: usize = 12;
+
+/// Agent actions running off the UI thread, so command output can stream into the chat and Esc can
+/// kill a command; the executor and supervisor travel with them and come back when they are done
+struct ActionBatch {
+    ids: Vec<String>, // Tool calls the actions answer, in order
+    rejected: usize, // Turned down in the approval panel before the batch started
+    output: Vec<String>, // Latest command output, shown while the batch runs
+    message_id: RequestId, // The chat message showing it
+    task: tokio::task::JoinHandle<(DefaultAgentExecutor, Supervisor, Result<SupervisedRun>)>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum FocusedPanel {
     FileExplorer,
@@ -51,6 +172,9 @@ pub struct LayoutState {
     pub notification_area: ratatui::layout::Rect,  
     pub chat_area: ratatui::layout::Rect,
     pub editor_area: ratatui::layout::Rect,
+    pub inactive_pane_area: Option<ratatui::layout::Rect>, // Unfocused editor pane when split
+    pub language_label_area: ratatui::layout::Rect, // File type in the status bar; clicking it picks the language
+    pub tab_menu_area: ratatui::layout::Rect, // Items of the open tab context menu, one per row
 }
 
 impl Default for LayoutState {
@@ -69,6 +193,9 @@ impl Default for LayoutState {
             notification_area: Rect::new(0, 0, 0, 0),
             chat_area: Rect::new(0, 0, 0, 0),
             editor_area: Rect::new(0, 0, 0, 0),
+            inactive_pane_area: None,
+            language_label_area: Rect::new(0, 0, 0, 0),
+            tab_menu_area: Rect::new(0, 0, 0, 0),
         }
     }
 }
@@ -76,7 +203,7 @@ impl Default for LayoutState {
 pub struct IdeApp {
     // Core components
     pub config: Config,
-    pub groq_client: GroqClient,
+    pub llm: Arc<dyn LlmProvider>, // The configured provider's client
     pub conversation: Conversation,
     pub clipboard: ClipboardManager,
     
@@ -84,6 +211,53 @@ pub struct IdeApp {
     pub sidebar: sidebar::Sidebar,
     pub editor: editor::Editor,
     pub statusbar: statusbar::StatusBar,
+    pub command_palette: palette::CommandPalette,
+    pub emoji_picker: emoji_picker::EmojiPicker,
+    pub diff_view: diff_view::DiffView,
+    pub marks: marks::Marks,
+    pub mark_picker: marks::MarkPicker,
+    pub ref_picker: file_refs::RefPicker,
+    pub notification_archive: notification_log::NotificationArchive,
+    pub notification_history: notification_log::ArchiveView,
+    pub plan_editor: plan_editor::PlanEditor,
+    pub approval: approval::ApprovalPanel,
+    pub notes_view: notes_view::NotesView,
+    pub keymap: Keymap, // Built-in key bindings with the config's on top
+    pub keymap_view: KeymapView,
+    pub settings_panel: SettingsPanel,
+    pub session_browser: SessionBrowser,
+    pub branch_view: BranchView,
+    pub prompt_history: PromptHistory, // Prompts sent before, for Up/Down in the empty chat input
+    pub prompt_search: PromptSearch,
+    pub saved_session: SavedSession, // The conversation as kept in the sessions directory
+    saved_revision: u64, // Conversation revision last written there
+    pub threads: Threads, // Other chat threads, each with its own conversation
+    keymap_changed: bool, // The keymap was rebuilt and the event handler still has the old one
+    config_stamp: Option<std::time::SystemTime>, // When the config files last changed, to notice edits made outside
+    pub tasks_view: tasks_view::TasksView,
+    pub tasks: TaskQueue, // Agent runs asked for while another was under way
+    pub agent_notes: Option<Scratchpad>, // The agent's scratchpad for this session
+    agent_executor: DefaultAgentExecutor, // Runs agentic-mode actions in the workspace root
+    supervisor: Supervisor, // Decides which agentic-mode actions need the approval panel
+    pending_tool_calls: Vec<String>, // Tool call IDs of the actions in the approval panel, in order
+    agent_run: Option<AgentRun>, // From the user's message to the model's final answer
+    requests: Requests, // Answers the model is working on: the conversation's and background questions
+    side_answers: Vec<GroqMessage>, // Background questions and answers, added to the conversation once it is free
+    retry_notice: Option<String>, // The retry wait shown in place of the progress line
+    vector_index: Option<Arc<VectorIndex>>, // Embedded workspace chunks, once indexed
+    indexing: Option<tokio::task::JoinHandle<Result<(VectorIndex, usize)>>>, // Index update in the background
+    indexed_at: Option<std::time::Instant>,
+    summary: Option<PendingSummary>, // Old turns being condensed
+    retrieval_query: Option<String>, // Message to retrieve workspace code for in the next request
+    action_batch: Option<ActionBatch>, // Agent actions being run
+    command_monitor: CommandMonitor, // Output and kill switch of the agent command being run
+    spend_guard: SpendGuard, // Request and cost caps of this session
+    pub spend_pause: Option<String>, // Why the agent is waiting for the user to let it go on
+    pub language_picker: language_picker::LanguagePicker,
+    pub model_picker: model_picker::ModelPicker,
+    model_list: Option<tokio::task::JoinHandle<Result<Vec<String>>>>, // Models being fetched for the picker
+    formatting: Option<(u32, String, tokio::task::JoinHandle<Result<String>>)>, // Tab being saved, the text its formatter was given and the run
+    pub i18n: Localizer,
     
     // State management
     pub mode: AppMode,
@@ -93,13 +267,44 @@ pub struct IdeApp {
     pub show_help: bool,
     pub show_command_help: bool,
     pub show_api_config: bool,
+    pub api_config_row: SettingsRow, // Selected row of the API settings form
+    settings_changed: bool, // The config changed without being saved yet; the API settings form saves once it closes
+    pub pending_normal_key: Option<char>, // First key of a multi-key normal mode command
+    pub pending_count: Option<usize>, // Count typed before a normal mode motion, e.g. the 5 in `5j`
     
     // File operation dialogs
     pub show_create_file_dialog: bool,
     pub show_create_folder_dialog: bool,
     pub show_rename_dialog: bool,
+    pub show_save_workflow_dialog: bool,
     pub dialog_input: String,
     pub operation_target: Option<PathBuf>,
+    pub show_recovery_dialog: bool,
+    pub show_unsaved_dialog: bool,
+    pub show_external_change_dialog: bool,
+    pub external_change_tab: Option<u32>, // Tab whose file changed on disk under unsaved edits
+    pub show_long_line_dialog: bool,
+    pub long_line_tab: Option<u32>, // Just-opened tab with a very long (minified) line
+    last_disk_check: std::time::Instant,
+    pub pending_close: Option<PendingClose>,
+    pub tab_menu: Option<TabMenu>,
+    pub pending_recoveries: Vec<autosave::SwapFile>,
+    pub show_workflow_picker: bool,
+    pub workflow_selected: usize,
+    pub model_override: Option<String>, // Set by the running workflow template
+    pending_external_command: Option<String>, // Opener to run once the main loop has released the terminal
+    reference_index: Option<(usize, ProjectIndex)>, // Built when a chat `@`/`#` reference starts at this input offset
+    reference_indexing: Option<(usize, tokio::task::JoinHandle<ProjectIndex>)>, // The index being built for that offset
+    dismissed_reference: Option<usize>, // Input offset of a reference whose suggestions were closed with Esc
+    session_lock: SessionLock,
+    pub lock_holder: Option<LockHolder>, // Other instance running on this project
+    pub show_session_lock_dialog: bool,
+    pub read_only_session: bool, // Attached to a project another instance is editing
+    pub reader_mode: bool, // Started with --read-only: nothing in the project can be written
+    suspend_requested: bool,
+    
+    // Autosave
+    pub autosave: autosave::AutosaveManager,
     
     // Mouse tracking and notifications
     pub mouse_position: (u16, u16),
@@ -119,28 +324,120 @@ pub struct IdeApp {
 
 impl IdeApp {
     pub async fn new(config: Config) -> Result<Self> {
-        let api_key = config.get_groq_key()
-            .ok_or_else(|| anyhow::anyhow!("Groq API key not configured. Run: agent config --groq-key YOUR_KEY"))?;
-        
-        let groq_client = GroqClient::new(api_key);
-        let conversation = Conversation::new();
-        let clipboard = ClipboardManager::new()?;
+        let llm = provider::connect(&config)?;
+        let (keymap, keymap_problems) = Keymap::load(&config.keymap);
+        let mut conversation = Conversation::new();
+        conversation.set_max_tokens(config.history.max_tokens);
+        let clipboard = ClipboardManager::new();
         let session_id = Uuid::new_v4();
         let current_directory = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
         
         // Initialize components
-        let sidebar = sidebar::Sidebar::new(&current_directory)?;
-        let editor = editor::Editor::new();
+        let (explorer_filter, explorer_problems) = ExplorerFilter::new(&current_directory, &config.explorer);
+        let mut sidebar = sidebar::Sidebar::new(&current_directory, explorer_filter)?;
+        sidebar.chat.max_display_chars = config.postprocess.max_display_chars;
+        let mut editor = editor::Editor::new();
+        editor.gutter = config.gutter.clone();
+        (editor.ruler, editor.show_ruler, editor.show_whitespace) = (config.ruler, config.show_ruler, config.show_whitespace);
         let statusbar = statusbar::StatusBar::new();
+        let command_palette = palette::CommandPalette::new();
+        let emoji_picker = emoji_picker::EmojiPicker::new();
+        let i18n = Localizer::new(i18n::detect_locale(config.ui_language.as_deref()));
         
-        Ok(Self {
+        // Look for swap files left behind by a crashed session
+        let autosave = autosave::AutosaveManager::new(
+            std::time::Duration::from_secs(config.autosave_interval_secs)
+        )?;
+        let pending_recoveries = autosave.find_orphans();
+
+        // Commands go through the same review as every other change, so the executor may run them
+        let mut agent_executor = DefaultAgentExecutor::new(current_directory.clone())
+            .with_capabilities(AgentCapabilities {
+                can_execute_commands: true,
+                can_use_git: true,
+                dry_run: config.dry_run,
+                sandbox: config.agent_sandbox,
+                ..AgentCapabilities::default()
+            })
+            .with_command_limits(config.command_limits)
+            .with_test_command(config.test_command.clone());
+        let command_monitor = CommandMonitor::default();
+        agent_executor = agent_executor.with_command_monitor(command_monitor.clone());
+        let policy_error = agent_executor.policy.error.clone();
+        // Without a cache dir agent changes still run, they just can't be undone
+        if let Ok(undo_log) = UndoLog::for_project(&current_directory, &session_id.to_string()) {
+            agent_executor = agent_executor.with_undo_log(undo_log);
+        }
+        let spend_guard = SpendGuard::new(config.spend_limits);
+        let agent_notes = Scratchpad::for_session(&current_directory, &session_id.to_string()).ok();
+        if let Some(notes) = &agent_notes {
+            agent_executor = agent_executor.with_scratchpad(notes.clone());
+        }
+        let supervisor = Supervisor::new(config.autonomy.clone());
+
+        // Another instance on the same project would fight over files and agent runs
+        let mut session_lock = SessionLock::new(&current_directory, session_id)?;
+        let lock_holder = session_lock.try_acquire().unwrap_or(None);
+
+        // Without a writable cache dir the history still works, it just isn't kept on disk
+        let notification_archive = notification_log::NotificationArchive::new(session_id, config.notification_archive)
+            .unwrap_or_else(|_| notification_log::NotificationArchive::at(None, config.notification_archive));
+        
+        let mut app = Self {
             config,
-            groq_client,
+            llm,
             conversation,
             clipboard,
             sidebar,
             editor,
             statusbar,
+            command_palette,
+            emoji_picker,
+            diff_view: diff_view::DiffView::new(),
+            marks: marks::Marks::default(),
+            mark_picker: marks::MarkPicker::new(),
+            ref_picker: file_refs::RefPicker::new(),
+            notification_archive,
+            notification_history: notification_log::ArchiveView::default(),
+            plan_editor: plan_editor::PlanEditor::new(),
+            approval: approval::ApprovalPanel::default(),
+            notes_view: notes_view::NotesView::default(),
+            keymap,
+            keymap_view: KeymapView::default(),
+            settings_panel: SettingsPanel::default(),
+            session_browser: SessionBrowser::default(),
+            branch_view: BranchView::default(),
+            prompt_history: PromptHistory::path().map(PromptHistory::load).unwrap_or_default(),
+            prompt_search: PromptSearch::default(),
+            saved_session: SavedSession::new(session_id.to_string(), current_directory.clone()),
+            saved_revision: 0,
+            threads: Threads::default(),
+            keymap_changed: false,
+            config_stamp: Config::modified_at(),
+            tasks_view: tasks_view::TasksView::default(),
+            tasks: TaskQueue::default(),
+            agent_notes,
+            agent_executor,
+            supervisor,
+            pending_tool_calls: Vec::new(),
+            spend_guard,
+            spend_pause: None,
+            agent_run: None,
+            requests: Requests::default(),
+            side_answers: Vec::new(),
+            retry_notice: None,
+            vector_index: None,
+            indexing: None,
+            summary: None,
+            indexed_at: None,
+            retrieval_query: None,
+            action_batch: None,
+            command_monitor,
+            language_picker: language_picker::LanguagePicker::new(),
+            model_picker: model_picker::ModelPicker::default(),
+            model_list: None,
+            formatting: None,
+            i18n,
             mode: AppMode::Normal,
             focused_panel: FocusedPanel::FileExplorer,
             layout: LayoutState::default(),
@@ -148,11 +445,40 @@ impl IdeApp {
             show_help: false,
             show_command_help: false,
             show_api_config: false,
+            api_config_row: SettingsRow::Model,
+            settings_changed: false,
+            pending_normal_key: None,
+            pending_count: None,
             show_create_file_dialog: false,
             show_create_folder_dialog: false,
             show_rename_dialog: false,
+            show_save_workflow_dialog: false,
             dialog_input: String::new(),
             operation_target: None,
+            show_recovery_dialog: !pending_recoveries.is_empty(),
+            show_unsaved_dialog: false,
+            show_external_change_dialog: false,
+            external_change_tab: None,
+            show_long_line_dialog: false,
+            long_line_tab: None,
+            last_disk_check: std::time::Instant::now(),
+            pending_close: None,
+            tab_menu: None,
+            pending_recoveries,
+            show_workflow_picker: false,
+            workflow_selected: 0,
+            model_override: None,
+            pending_external_command: None,
+            reference_index: None,
+            reference_indexing: None,
+            dismissed_reference: None,
+            show_session_lock_dialog: lock_holder.is_some(),
+            session_lock,
+            lock_holder,
+            read_only_session: false,
+            reader_mode: false,
+            suspend_requested: false,
+            autosave,
             mouse_position: (0, 0),
             last_click_position: None,
             notifications: Vec::new(),
@@ -162,117 +488,2730 @@ impl IdeApp {
             drag_start_x: 0,
             session_id,
             current_directory,
-        })
+        };
+        app.diff_view.options = app.config.diff;
+        app.layout.chat_height = app.config.chat_height.clamp(app.layout.min_chat_height, 25);
+        if app.config.restore_session {
+            app.restore_session();
+        }
+        app.restore_conversation();
+        if let Some(error) = policy_error {
+            app.add_notification(format!("⚠️ {} - agent changes and commands are blocked", error), NotificationType::Info);
+        }
+        for key in app.config.project_ignored.clone() {
+            app.add_notification(format!("🔒 {}: ignored `{}` - a project may only set editor settings", PROJECT_FILE, key), NotificationType::Info);
+        }
+        for problem in keymap_problems {
+            app.add_notification(format!("⌨️ Keymap: {}", problem), NotificationType::Info);
+        }
+        for problem in explorer_problems {
+            app.add_notification(format!("📁 Explorer: {}", problem), NotificationType::Info);
+        }
+        for theme in app.config.themes.clone() {
+            for color in theme.palette.invalid_colors() {
+                app.add_notification(format!("🎨 Theme {}: unknown color {}", theme.name, color), NotificationType::Info);
+            }
+        }
+        app.check_theme();
+        Ok(app)
+    }
+
+    /// Reopen the tabs and sidebar width this directory had when the IDE last quit
+    fn restore_session(&mut self) {
+        let Some(session) = WorkspaceSession::path_for(&self.current_directory).ok().and_then(|path| WorkspaceSession::load(&path)) else {
+            return;
+        };
+        self.layout.sidebar_width = session.sidebar_width.clamp(self.layout.min_sidebar_width, self.layout.max_sidebar_width);
+        self.restore_threads(&session.threads, session.active_thread);
+        self.marks = session.marks.clone();
+        self.refresh_mark_signs();
+        let opened = session.restore(&mut self.editor);
+        if opened > 0 {
+            self.focus_panel(FocusedPanel::Editor);
+            self.add_notification(format!("🗂️ Restored {} tabs from the last session", opened), NotificationType::FileOperation);
+        }
+        if opened < session.tabs.len() {
+            self.add_notification(
+                format!("⚠️ {} files from the last session no longer exist", session.tabs.len() - opened),
+                NotificationType::FileOperation
+            );
+        }
+    }
+
+    /// The terminal went away (SSH drop, SIGTERM): keep unsaved buffers as swap files for the
+    /// recovery prompt, and save the conversation and open tabs for the next launch
+    pub fn save_on_hangup(&mut self) -> Result<()> {
+        self.autosave.write_swaps(&self.editor)?;
+        if self.conversation.message_count() > 0 {
+            self.persist_conversation()?;
+            let path = WorkspaceSession::conversation_path_for(&self.current_directory)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            self.saved_session.conversation = self.conversation.clone();
+            std::fs::write(path, serde_json::to_string(&self.saved_session)?)?;
+        }
+        self.save_session()
+    }
+
+    /// Bring back a conversation saved by `save_on_hangup`, carrying on with its saved session
+    fn restore_conversation(&mut self) {
+        let Ok(path) = WorkspaceSession::conversation_path_for(&self.current_directory) else {
+            return;
+        };
+        let Some(session) = std::fs::read_to_string(&path).ok().and_then(|json| serde_json::from_str::<SavedSession>(&json).ok()) else {
+            return;
+        };
+        let _ = std::fs::remove_file(path);
+        self.open_saved_session(session);
+        self.add_notification("💬 Restored the conversation from before the connection dropped".to_string(), NotificationType::Info);
+    }
+
+    /// Write the conversation to its saved session if it changed since the last time
+    pub fn persist_conversation(&mut self) -> Result<()> {
+        if self.conversation.revision() == self.saved_revision {
+            return Ok(());
+        }
+        // Also on failure, so a broken sessions directory isn't retried until the next message
+        self.saved_revision = self.conversation.revision();
+        self.saved_session.save(&SavedSession::dir()?, &self.conversation)?;
+        Ok(())
+    }
+
+    fn persist_conversation_or_notify(&mut self) {
+        if let Err(e) = self.persist_conversation() {
+            self.add_notification(format!("❌ Failed to save the conversation: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    /// Keep the current conversation and go on in a new session of the same thread, e.g. when the chat is cleared
+    fn start_new_session(&mut self) {
+        self.persist_conversation_or_notify();
+        let name = self.saved_session.name.take();
+        self.saved_session = SavedSession::new(Uuid::new_v4().to_string(), self.current_directory.clone());
+        self.saved_session.name = name;
+    }
+
+    /// Continue a saved conversation in this thread, or switch to the thread that has it open
+    pub fn resume_session(&mut self, session: SavedSession) {
+        if session.id == self.saved_session.id || self.threads_busy() {
+            return;
+        }
+        let open = self.threads.sessions(&self.saved_session).position(|open| open.id == session.id);
+        if let Some(index) = open {
+            self.switch_thread(index);
+            return;
+        }
+        self.persist_conversation_or_notify();
+        let (title, turns) = (session.title.clone(), session.turns());
+        self.model_override = None;
+        self.open_saved_session(session);
+        self.add_notification(format!("💬 Resumed \"{}\" ({} messages)", title, turns), NotificationType::Info);
+    }
+
+    fn open_saved_session(&mut self, session: SavedSession) {
+        let thread = self.thread_for(session);
+        self.load_thread(thread);
+    }
+
+    /// A thread showing the questions and answers of a saved conversation
+    fn thread_for(&self, session: SavedSession) -> ChatThread {
+        let mut chat = Chat::new();
+        chat.max_display_chars = self.config.postprocess.max_display_chars;
+        for (index, message) in session.conversation.get_messages().iter().enumerate() {
+            let crate::api::MessageContent::Text(text) = &message.content else {
+                continue;
+            };
+            match message.role.as_str() {
+                "user" => {
+                    chat.add_user_message(text);
+                    chat.link_last_user_message(session.conversation.sent_at(index));
+                }
+                "assistant" if !text.is_empty() => chat.add_ai_message(text),
+                _ => {}
+            }
+        }
+        let conversation = session.conversation.clone();
+        ChatThread { saved_revision: conversation.revision(), conversation, chat, session }
+    }
+
+    /// Take the active thread out of the chat panel and conversation, to park it
+    fn take_thread(&mut self) -> ChatThread {
+        let placeholder = SavedSession::new(String::new(), self.current_directory.clone());
+        ChatThread {
+            conversation: std::mem::take(&mut self.conversation),
+            chat: std::mem::take(&mut self.sidebar.chat),
+            session: std::mem::replace(&mut self.saved_session, placeholder),
+            saved_revision: self.saved_revision,
+        }
+    }
+
+    fn load_thread(&mut self, thread: ChatThread) {
+        self.conversation = thread.conversation;
+        self.conversation.set_max_tokens(self.config.history.max_tokens);
+        self.sidebar.chat = thread.chat;
+        self.saved_session = thread.session;
+        self.saved_revision = thread.saved_revision;
+    }
+
+    /// Reopen the chat threads of the last session; those whose conversation is gone are left out
+    fn restore_threads(&mut self, ids: &[String], active: usize) {
+        let Ok(dir) = SavedSession::dir() else {
+            return;
+        };
+        let mut sessions: Vec<SavedSession> = ids.iter().filter_map(|id| SavedSession::load(&dir, id).ok()).collect();
+        if sessions.len() < 2 {
+            return;
+        }
+        let active = ids.get(active).and_then(|id| sessions.iter().position(|session| &session.id == id)).unwrap_or(0);
+        let current = sessions.remove(active);
+        let parked = sessions.into_iter().map(|session| self.thread_for(session)).collect();
+        self.threads = Threads::with_parked(parked, active);
+        self.open_saved_session(current);
+    }
+
+    /// Whether an answer or agent run is under way, which switching threads would send to the wrong conversation
+    fn threads_busy(&mut self) -> bool {
+        let busy = !self.requests.ids().is_empty() || self.agent_run.is_some() || self.action_batch.is_some();
+        if busy {
+            self.add_notification("⏳ Wait for the current answer before switching conversations".to_string(), NotificationType::Info);
+        }
+        busy
+    }
+
+    fn switch_thread(&mut self, index: usize) {
+        if index == self.threads.active() || index >= self.threads.len() || self.threads_busy() {
+            return;
+        }
+        self.persist_conversation_or_notify();
+        let current = self.take_thread();
+        let next = self.threads.switch(index, current);
+        self.load_thread(next);
+    }
+
+    fn new_thread(&mut self, name: &str) {
+        let mut session = SavedSession::new(Uuid::new_v4().to_string(), self.current_directory.clone());
+        session.name = Some(name.to_string());
+        if self.open_new_thread(session) {
+            self.sidebar.chat.add_system_message(&format!("🧵 Thread '{}' started", name));
+        }
+    }
+
+    /// Park the active thread and continue in one with `session`'s conversation; returns whether it was opened
+    fn open_new_thread(&mut self, session: SavedSession) -> bool {
+        let name = session.thread_name().to_string();
+        if self.threads.position(&name, &self.saved_session).is_some() {
+            self.sidebar.chat.add_system_message(&format!("⚠️ There already is a thread called '{}'", name));
+            return false;
+        }
+        if self.threads_busy() {
+            return false;
+        }
+        self.persist_conversation_or_notify();
+        let current = self.take_thread();
+        self.threads.add(current);
+        let thread = self.thread_for(session);
+        self.load_thread(thread);
+        true
+    }
+
+    /// Write the conversation to its saved session now, e.g. after naming it
+    fn save_conversation_now(&mut self) {
+        self.saved_revision = self.conversation.revision();
+        if let Err(e) = SavedSession::dir().and_then(|dir| self.saved_session.save(&dir, &self.conversation)) {
+            self.add_notification(format!("❌ Failed to save the conversation: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    /// `/checkpoint [name]` keeps the conversation as it is now, to fork new threads from later
+    fn checkpoint_command(&mut self, name: &str) {
+        if self.conversation.first_text_message("user").is_none() {
+            self.sidebar.chat.add_system_message("⚠️ Nothing to checkpoint yet - ask something first");
+            return;
+        }
+        let name = match name.split_whitespace().collect::<Vec<_>>().join("-") {
+            name if name.is_empty() => format!("checkpoint-{}", self.saved_session.checkpoints.len() + 1),
+            name => name,
+        };
+        if self.saved_session.checkpoint(&name).is_some() {
+            self.sidebar.chat.add_system_message(&format!("⚠️ There already is a checkpoint called '{}'", name));
+            return;
+        }
+        let until = self.conversation.message_count().checked_sub(1).and_then(|last| self.conversation.sent_at(last));
+        if until.is_none() {
+            self.sidebar.chat.add_system_message("⚠️ This conversation is from an older version - send another message before making a checkpoint");
+            return;
+        }
+        self.saved_session.checkpoints.push(Checkpoint { name: name.clone(), created_at: chrono::Utc::now(), until, conversation: None });
+        self.save_conversation_now();
+        self.sidebar.chat.add_system_message(&format!("📍 Checkpoint '{}' saved - `/fork {}` continues from here in a new thread", name, name));
+    }
+
+    /// `/fork [checkpoint] [thread name]` continues from a checkpoint of this conversation, by
+    /// default the latest, in a new thread
+    fn fork_command(&mut self, argument: &str) {
+        let (checkpoint, name) = argument.split_once(' ').map_or((argument, ""), |(checkpoint, name)| (checkpoint, name.trim()));
+        let checkpoint = match (checkpoint, self.saved_session.checkpoints.last()) {
+            ("", Some(latest)) => latest.name.clone(),
+            ("", None) => {
+                self.sidebar.chat.add_system_message("⚠️ No checkpoints in this conversation - `/checkpoint <name>` makes one");
+                return;
+            }
+            (checkpoint, _) => checkpoint.to_string(),
+        };
+        let mut source = self.saved_session.clone();
+        source.conversation = self.conversation.clone();
+        self.fork_thread(&source, &checkpoint, name);
+    }
+
+    /// `/pin` keeps the latest answer, and `/pin question` the latest question, in the history
+    /// however long it gets
+    fn pin_command(&mut self, argument: &str) {
+        let (role, label) = match argument {
+            "" | "answer" => ("assistant", "answer"),
+            "question" => ("user", "question"),
+            _ => {
+                self.sidebar.chat.add_system_message("⚠️ `/pin` pins the latest answer and `/pin question` the latest question");
+                return;
+            }
+        };
+        let messages = self.conversation.get_messages();
+        let latest = (0..messages.len()).rev().find(|&index| {
+            let text = message_text(&messages[index]);
+            messages[index].role == role && messages[index].tool_calls.is_none() && !text.trim().is_empty() && !text.starts_with(actions::RESULT_PREFIX)
+        });
+        let Some(index) = latest else {
+            self.sidebar.chat.add_system_message(&format!("⚠️ No {} to pin yet", label));
+            return;
+        };
+        let title = sessions::title(&message_text(&messages[index]));
+        if self.conversation.is_pinned(index) {
+            self.sidebar.chat.add_system_message(&format!("📌 Already pinned: {}", title));
+        } else if !self.conversation.fits_pin(index) {
+            self.sidebar.chat.add_system_message(&format!(
+                "⚠️ Too big to pin: pinned messages may take about {} tokens, half the history - `/unpin` frees room",
+                self.conversation.pin_budget()
+            ));
+        } else if self.conversation.pin(index) {
+            self.sidebar.chat.add_system_message(&format!("📌 Pinned: {} - it stays in the history, `/pins` lists what is pinned", title));
+        } else {
+            self.sidebar.chat.add_system_message(&format!("⚠️ The latest {} is from before pins were kept and can't be pinned", label));
+        }
+    }
+
+    /// `/pins` lists the pinned messages, numbered for `/unpin <n>`
+    fn list_pins(&mut self) {
+        let pinned = self.conversation.pinned();
+        if pinned.is_empty() {
+            self.sidebar.chat.add_system_message("📌 Nothing is pinned - `/pin` pins the latest answer");
+            return;
+        }
+        let rows: Vec<String> = pinned
+            .iter()
+            .enumerate()
+            .map(|(number, &index)| {
+                let message = &self.conversation.get_messages()[index];
+                let who = if message.role == "user" { "You" } else { "AI" };
+                format!("  {}. {}: {}", number + 1, who, sessions::title(&message_text(message)))
+            })
+            .collect();
+        self.sidebar.chat.add_system_message(&format!("📌 Pinned messages (`/unpin <n>` or `/unpin all` lets them go):\n{}", rows.join("\n")));
+    }
+
+    /// `/unpin <n>` lets pinned message `n` of `/pins` be trimmed again, `/unpin all` every one
+    fn unpin_command(&mut self, argument: &str) {
+        let pinned = self.conversation.pinned();
+        let chosen: Vec<usize> = match argument {
+            "all" => pinned,
+            number => match number.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|n| pinned.get(n)) {
+                Some(&index) => vec![index],
+                None => {
+                    self.sidebar.chat.add_system_message("⚠️ `/unpin <n>` takes a number from `/pins`, or `all`");
+                    return;
+                }
+            },
+        };
+        for &index in &chosen {
+            self.conversation.unpin(index);
+        }
+        self.sidebar.chat.add_system_message(&format!("📌 Unpinned {} message{}", chosen.len(), if chosen.len() == 1 { "" } else { "s" }));
+    }
+
+    /// `/memory <note>` adds a note to the project's memory; `/memory` alone opens it in the editor
+    fn memory_command(&mut self, note: &str) {
+        if self.reader_mode {
+            self.sidebar.chat.add_system_message("⚠️ Read-only session - nothing can be written to the project");
+            return;
+        }
+        if !note.is_empty() {
+            match project_memory::remember(&self.current_directory, note) {
+                Ok(()) => self.sidebar.chat.add_system_message(&format!("🧠 Remembered in {} - it goes along with every request", project_memory::MEMORY_FILE)),
+                Err(e) => self.add_notification(format!("❌ Failed to update the project memory: {}", e), NotificationType::FileOperation),
+            }
+            return;
+        }
+        self.edit_project_memory();
+    }
+
+    /// Open the project's memory in the editor, creating it first
+    fn edit_project_memory(&mut self) {
+        let path = project_memory::path(&self.current_directory);
+        if !path.exists() && !self.reader_mode {
+            let created = path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| std::fs::write(&path, "# Project memory\n\nConventions and facts sent with every request in this project.\n\n"));
+            if let Err(e) = created {
+                self.add_notification(format!("❌ Failed to create {}: {}", project_memory::MEMORY_FILE, e), NotificationType::FileOperation);
+                return;
+            }
+        }
+        if let Err(e) = self.open_in_editor(path) {
+            self.add_notification(format!("❌ Failed to open {}: {}", project_memory::MEMORY_FILE, e), NotificationType::FileOperation);
+            return;
+        }
+        self.focus_panel(FocusedPanel::Editor);
+    }
+
+    /// Start a thread with the conversation of `source`'s checkpoint, named `name` or after the checkpoint
+    fn fork_thread(&mut self, source: &SavedSession, checkpoint: &str, name: &str) {
+        let Some(checkpoint) = source.checkpoint(checkpoint) else {
+            self.sidebar.chat.add_system_message(&format!("⚠️ No checkpoint called '{}' in this conversation", checkpoint));
+            return;
+        };
+        let name = match name {
+            "" => (1..)
+                .map(|n| if n == 1 { checkpoint.name.clone() } else { format!("{}-{}", checkpoint.name, n) })
+                .find(|name| self.threads.position(name, &self.saved_session).is_none())
+                .unwrap_or_default(),
+            name => name.to_string(),
+        };
+        let Some(conversation) = checkpoint.conversation_in(&source.conversation) else {
+            self.sidebar.chat.add_system_message(&format!("⚠️ The messages of checkpoint '{}' were trimmed from the history", checkpoint.name));
+            return;
+        };
+        let mut session = SavedSession::new(Uuid::new_v4().to_string(), self.current_directory.clone());
+        session.name = Some(name.clone());
+        session.conversation = conversation;
+        session.forked_from = Some(Fork { session: source.id.clone(), checkpoint: checkpoint.name.clone() });
+        if self.open_new_thread(session) {
+            self.save_conversation_now();
+            self.sidebar.chat.add_system_message(&format!("🌿 Thread '{}' forked from checkpoint '{}'", name, checkpoint.name));
+        }
+    }
+
+    /// Open the tree of conversations forked from this one's checkpoints, and those it was forked from
+    fn show_branches(&mut self) {
+        self.persist_conversation_or_notify();
+        match SavedSession::dir() {
+            Ok(dir) => {
+                let rows = sessions::branch_tree(&SavedSession::list(&dir), &self.saved_session.id);
+                self.branch_view.open(rows, &self.saved_session.id);
+            }
+            Err(e) => self.add_notification(format!("❌ Failed to list sessions: {}", e), NotificationType::FileOperation),
+        }
+    }
+
+    /// Continue a conversation picked in the branch tree, or fork a new thread from a checkpoint
+    fn open_branch(&mut self, row: BranchRow) {
+        let session = if row.session == self.saved_session.id {
+            Ok(self.saved_session.clone())
+        } else {
+            SavedSession::dir().and_then(|dir| SavedSession::load(&dir, &row.session))
+        };
+        match (session, row.checkpoint) {
+            (Ok(session), Some(checkpoint)) => self.fork_thread(&session, &checkpoint, ""),
+            (Ok(session), None) => self.resume_session(session),
+            (Err(e), _) => self.add_notification(format!("❌ Failed to open the conversation: {}", e), NotificationType::FileOperation),
+        }
+    }
+
+    fn close_thread(&mut self) {
+        if self.threads.len() < 2 {
+            self.sidebar.chat.add_system_message("🧵 This is the only thread");
+            return;
+        }
+        if self.threads_busy() {
+            return;
+        }
+        self.persist_conversation_or_notify();
+        let name = self.saved_session.thread_name().to_string();
+        if let Some(next) = self.threads.close_active() {
+            self.load_thread(next);
+        }
+        self.add_notification(format!("🧵 Closed thread '{}' - \"Browse Sessions\" reopens it", name), NotificationType::Info);
+    }
+
+    /// `/thread` lists the threads, `/thread <name>` switches to one, and `/thread new <name>`,
+    /// `/thread rename <name>` and `/thread close` manage them
+    fn thread_command(&mut self, argument: &str) {
+        let (command, rest) = argument.split_once(' ').map_or((argument, ""), |(command, rest)| (command, rest.trim()));
+        match (command, rest) {
+            ("", _) => {
+                let active = self.threads.active();
+                let names: Vec<String> = self
+                    .threads
+                    .names(&self.saved_session)
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| if index == active { format!("  ● {}", name) } else { format!("    {}", name) })
+                    .collect();
+                self.sidebar.chat.add_system_message(&format!(
+                    "🧵 Threads (`/thread <name>` switches, `/thread new <name>`, `/thread rename <name>`, `/thread close`):\n{}",
+                    names.join("\n")
+                ));
+            }
+            ("new", "") | ("rename", "") => self.sidebar.chat.add_system_message(&format!("⚠️ `/thread {} <name>` needs a name", command)),
+            ("new", name) => self.new_thread(name),
+            ("rename", name) => {
+                if self.threads.position(name, &self.saved_session).is_some() {
+                    self.sidebar.chat.add_system_message(&format!("⚠️ There already is a thread called '{}'", name));
+                    return;
+                }
+                self.saved_session.name = Some(name.to_string());
+                self.save_conversation_now();
+                self.sidebar.chat.add_system_message(&format!("🧵 Thread renamed to '{}'", name));
+            }
+            ("close", "") => self.close_thread(),
+            _ => match self.threads.position(argument, &self.saved_session) {
+                Some(index) => self.switch_thread(index),
+                None => self.sidebar.chat.add_system_message(&format!("⚠️ No thread called '{}' - `/thread new {}` starts one", argument, argument)),
+            },
+        }
+    }
+
+    /// `/export [md|html] [path] [--force]` writes the conversation as a document; the format defaults
+    /// to the path's extension, then Markdown, and the path to a dated file in the project. An
+    /// existing file is only replaced with `--force`
+    fn export_command(&mut self, argument: &str) {
+        if self.reader_mode {
+            self.sidebar.chat.add_system_message("⚠️ Read-only session - nothing can be written to the project");
+            return;
+        }
+        let force = argument.split_whitespace().any(|word| word == "--force");
+        let argument = argument.split_whitespace().filter(|word| *word != "--force").collect::<Vec<_>>().join(" ");
+        let argument = argument.as_str();
+        let (first, rest) = argument.split_once(' ').map_or((argument, ""), |(first, rest)| (first, rest.trim()));
+        let (format, path) = match first.parse::<ExportFormat>() {
+            Ok(format) => (Some(format), rest),
+            Err(_) => (None, argument),
+        };
+        let path = (!path.is_empty()).then(|| self.current_directory.join(path));
+        let format = format.or_else(|| path.as_deref().and_then(ExportFormat::from_path)).unwrap_or(ExportFormat::Markdown);
+        let mut session = self.saved_session.clone();
+        session.conversation = self.conversation.clone();
+        let path = path.unwrap_or_else(|| export::default_path(&self.current_directory, &session, format));
+        let shown = path.strip_prefix(&self.current_directory).unwrap_or(&path).to_path_buf();
+        if path.exists() && !force {
+            self.sidebar.chat.add_system_message(&format!("⚠️ {} already exists - add --force to replace it", shown.display()));
+            return;
+        }
+        match export::write(&path, &export::render(&session, format), force) {
+            Ok(()) => self.sidebar.chat.add_system_message(&format!("📤 Exported the conversation to {}", shown.display())),
+            Err(e) => self.add_notification(format!("❌ Failed to export the conversation: {}", e), NotificationType::FileOperation),
+        }
+    }
+
+    /// Remember the open tabs for the next launch in this directory
+    pub fn save_session(&self) -> Result<()> {
+        if !self.config.restore_session {
+            return Ok(());
+        }
+        let mut session = WorkspaceSession::capture(&self.editor, self.layout.sidebar_width);
+        session.marks = self.marks.clone();
+        if self.threads.len() > 1 {
+            session.threads = self.threads.sessions(&self.saved_session).map(|session| session.id.clone()).collect();
+            session.active_thread = self.threads.active();
+        }
+        session.save(&WorkspaceSession::path_for(&self.current_directory)?)
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.should_quit
+    }
+
+    pub fn quit(&mut self) {
+        self.should_quit = true;
+    }
+
+    /// Quit, asking first if any buffer has unsaved changes
+    pub fn request_quit(&mut self) {
+        if self.editor.tabs.iter().any(|tab| tab.is_modified) {
+            self.pending_close = Some(PendingClose::Quit);
+            self.show_unsaved_dialog = true;
+        } else {
+            self.quit();
+        }
+    }
+
+    /// Close a tab, asking first if it has unsaved changes. Returns whether it was closed right away
+    pub fn request_close_tab(&mut self, tab_id: u32) -> bool {
+        let is_modified = self.editor.tabs.iter().any(|tab| tab.id == tab_id && tab.is_modified);
+        if is_modified {
+            self.pending_close = Some(PendingClose::Tab(tab_id));
+            self.show_unsaved_dialog = true;
+            false
+        } else {
+            self.editor.close_tab_by_id(tab_id);
+            true
+        }
+    }
+
+    /// Close several tabs: clean ones right away, then one prompt for all the modified ones
+    fn request_close_tabs(&mut self, tab_ids: Vec<u32>) {
+        let (modified, clean): (Vec<u32>, Vec<u32>) = tab_ids
+            .into_iter()
+            .partition(|id| self.editor.tabs.iter().any(|tab| tab.id == *id && tab.is_modified));
+        for tab_id in clean {
+            self.editor.close_tab_by_id(tab_id);
+        }
+        if !modified.is_empty() {
+            self.pending_close = Some(PendingClose::Tabs(modified));
+            self.show_unsaved_dialog = true;
+        }
+    }
+
+    /// Names of the modified buffers the pending close would lose
+    pub fn unsaved_file_names(&self) -> Vec<String> {
+        self.editor.tabs
+            .iter()
+            .filter(|tab| tab.is_modified)
+            .filter(|tab| self.pending_close.as_ref().is_none_or(|pending| pending.covers(tab.id)))
+            .map(|tab| tab.file_name.clone())
+            .collect()
+    }
+
+    fn handle_unsaved_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar('s') | IdeEvent::Enter | IdeEvent::Select => self.resolve_pending_close(true),
+            IdeEvent::InsertChar('d') => self.resolve_pending_close(false),
+            IdeEvent::InsertChar('c') | IdeEvent::NormalMode => {
+                self.pending_close = None;
+                self.show_unsaved_dialog = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn resolve_pending_close(&mut self, save: bool) {
+        self.show_unsaved_dialog = false;
+        let Some(pending) = self.pending_close.take() else {
+            return;
+        };
+
+        if save {
+            let result: Result<()> = self.editor.tabs
+                .iter_mut()
+                .filter(|tab| tab.is_modified && pending.covers(tab.id))
+                .try_for_each(|tab| match tab.file_path {
+                    Some(_) => tab.save(&self.config.save_hooks).map(|_| ()),
+                    None => Err(anyhow::anyhow!("'{}' has no file path yet", tab.file_name)),
+                });
+            if let Err(e) = result {
+                self.add_notification(format!("❌ Save failed, nothing was closed: {}", e), NotificationType::FileOperation);
+                return;
+            }
+        }
+
+        match pending {
+            PendingClose::Tab(tab_id) => {
+                self.editor.close_tab_by_id(tab_id);
+                let message = if save { "💾 Saved and closed tab" } else { "🗑️ Discarded changes and closed tab" };
+                self.add_notification(message.to_string(), NotificationType::FileOperation);
+            }
+            PendingClose::Tabs(tab_ids) => {
+                let count = tab_ids.len();
+                for tab_id in tab_ids {
+                    self.editor.close_tab_by_id(tab_id);
+                }
+                let action = if save { "💾 Saved and closed" } else { "🗑️ Discarded changes and closed" };
+                self.add_notification(format!("{} {} tabs", action, count), NotificationType::FileOperation);
+            }
+            PendingClose::Quit => self.quit(),
+        }
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    pub fn toggle_command_help(&mut self) {
+        self.show_command_help = !self.show_command_help;
+    }
+
+    pub fn toggle_api_config(&mut self) {
+        if self.show_api_config {
+            self.close_api_config();
+        } else {
+            self.show_api_config = true;
+        }
+    }
+
+    /// Close the API settings form, saving what was changed in it and reconnecting with the new settings
+    fn close_api_config(&mut self) {
+        self.show_api_config = false;
+        if self.settings_changed {
+            self.save_settings();
+            self.reconnect_llm();
+        }
+    }
+
+    /// Save the config if something changed it since the last save
+    fn save_settings(&mut self) {
+        if !std::mem::take(&mut self.settings_changed) {
+            return;
+        }
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    /// Keys of the API settings form: ↑/↓ pick a row, ←/→ adjust it, digits type max tokens,
+    /// Enter opens the model picker or toggles dry run. Changes are saved once the form closes
+    fn handle_api_config_event(&mut self, event: IdeEvent) {
+        let row = SettingsRow::ALL.iter().position(|row| *row == self.api_config_row).unwrap_or(0);
+        match (event, self.api_config_row) {
+            (IdeEvent::NavigateUp, _) => self.api_config_row = SettingsRow::ALL[row.saturating_sub(1)],
+            (IdeEvent::NavigateDown, _) => self.api_config_row = SettingsRow::ALL[(row + 1).min(SettingsRow::ALL.len() - 1)],
+            (IdeEvent::InsertChar('m'), _) | (IdeEvent::Enter | IdeEvent::Select, SettingsRow::Model) => {
+                self.close_api_config();
+                self.show_model_picker(false);
+            }
+            (IdeEvent::InsertChar('d'), _) | (IdeEvent::Enter | IdeEvent::Select | IdeEvent::NavigateLeft | IdeEvent::NavigateRight, SettingsRow::DryRun) => {
+                self.toggle_dry_run()
+            }
+            (IdeEvent::NavigateLeft, SettingsRow::Persona) => self.step_persona(-1),
+            (IdeEvent::NavigateRight | IdeEvent::Enter | IdeEvent::Select, SettingsRow::Persona) => self.step_persona(1),
+            (IdeEvent::NavigateLeft, SettingsRow::Temperature) => self.set_temperature(api_settings::step_temperature(self.config.get_temperature(), -1)),
+            (IdeEvent::NavigateRight, SettingsRow::Temperature) => self.set_temperature(api_settings::step_temperature(self.config.get_temperature(), 1)),
+            (IdeEvent::NavigateLeft, SettingsRow::MaxTokens) => self.set_max_tokens(api_settings::step_max_tokens(self.config.get_max_tokens(), -1)),
+            (IdeEvent::NavigateRight, SettingsRow::MaxTokens) => self.set_max_tokens(api_settings::step_max_tokens(self.config.get_max_tokens(), 1)),
+            (IdeEvent::InsertChar(c), SettingsRow::MaxTokens) if c.is_ascii_digit() => {
+                self.set_max_tokens(api_settings::type_max_tokens(self.config.get_max_tokens(), c.to_digit(10)))
+            }
+            (IdeEvent::Backspace, SettingsRow::MaxTokens) => self.set_max_tokens(api_settings::type_max_tokens(self.config.get_max_tokens(), None)),
+            (IdeEvent::NormalMode | IdeEvent::ShowApiConfig | IdeEvent::InsertChar('q'), _) => self.close_api_config(),
+            (IdeEvent::Quit, _) => {
+                self.close_api_config();
+                self.request_quit();
+            }
+            _ => {}
+        }
+    }
+
+    fn step_persona(&mut self, steps: i32) {
+        let names: Vec<String> = persona::all(&self.config.personas).into_iter().map(|persona| persona.name).collect();
+        let next = api_settings::step_persona(&names, self.config.persona.as_deref(), steps);
+        self.set_persona(next);
+    }
+
+    /// Make `name` the persona of this and later conversations; its system prompt replaces the
+    /// previous persona's right away, and the config is saved by whoever changed it
+    fn set_persona(&mut self, name: Option<String>) {
+        self.config.persona = name;
+        self.settings_changed = true;
+        self.conversation.remove_system_messages(persona::HEADER);
+        self.apply_persona();
+    }
+
+    /// Start the conversation with the persona's system prompt, unless it already has one
+    fn apply_persona(&mut self) {
+        let Some(name) = self.config.persona.as_deref() else {
+            return;
+        };
+        if let Some(persona) = persona::find(&self.config.personas, name).filter(|_| !self.conversation.has_system_message(persona::HEADER)) {
+            self.conversation.add_system_message(persona::system_message(&persona));
+        }
+    }
+
+    /// `/persona` lists the personas, `/persona <name>` switches to one and `/persona off` goes back to none
+    fn persona_command(&mut self, argument: &str) {
+        let personas = persona::all(&self.config.personas);
+        match argument {
+            "" => {
+                let current = self.config.persona.as_deref().unwrap_or("none");
+                let names: Vec<String> = personas.iter().map(|persona| persona.name.clone()).collect();
+                self.sidebar.chat.add_system_message(&format!("🎭 Persona: {} (available: {}; `/persona off` for none)", current, names.join(", ")));
+            }
+            "off" | "none" => {
+                self.set_persona(None);
+                self.save_settings();
+                self.sidebar.chat.add_system_message("🎭 Persona off");
+            }
+            name => match personas.into_iter().find(|persona| persona.name.eq_ignore_ascii_case(name)) {
+                Some(persona) => {
+                    self.sidebar.chat.add_system_message(&format!("🎭 Persona: {}", persona.name));
+                    self.set_persona(Some(persona.name));
+                    self.save_settings();
+                }
+                None => self.sidebar.chat.add_system_message(&format!("⚠️ No persona called '{}' - `/persona` lists them", name)),
+            },
+        }
+    }
+
+    /// `/profile` lists the profiles, `/profile <name>` switches to one and `/profile off` stops using its key
+    fn profile_command(&mut self, argument: &str) {
+        match argument {
+            "" if self.config.profiles.is_empty() => {
+                self.sidebar.chat.add_system_message("👤 No profiles yet - add them under \"profiles\" in the config");
+            }
+            "" => {
+                let current = self.config.active_profile().map_or("none".to_string(), |profile| profile.name.clone());
+                let profiles: Vec<String> = self.config.profiles.iter().map(|profile| format!("  {}", profile.summary())).collect();
+                self.sidebar.chat.add_system_message(&format!("👤 Profile: {} (`/profile off` for none)\n{}", current, profiles.join("\n")));
+            }
+            "off" | "none" => {
+                if let Err(e) = self.config.leave_profile() {
+                    self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+                }
+                self.reconnect_llm();
+                self.sidebar.chat.add_system_message("👤 Profile off");
+            }
+            name => match self.config.switch_profile(name) {
+                Ok(profile) => {
+                    self.reconnect_llm();
+                    let message = format!("👤 Profile: {} ({}, {})", profile.name, self.config.provider.label(), self.config.get_model());
+                    self.sidebar.chat.add_system_message(&message);
+                }
+                Err(e) => self.sidebar.chat.add_system_message(&format!("⚠️ {} - `/profile` lists them", e)),
+            },
+        }
+    }
+
+    fn set_temperature(&mut self, temperature: f32) {
+        self.config.temperature = temperature.clamp(0.0, 2.0);
+        self.settings_changed = true;
+    }
+
+    fn set_max_tokens(&mut self, max_tokens: Option<u32>) {
+        self.config.max_tokens = max_tokens;
+        self.settings_changed = true;
+    }
+
+    /// Rebuild the provider client after a setting it was made with has changed
+    fn reconnect_llm(&mut self) {
+        if let Ok(llm) = provider::connect(&self.config) {
+            self.llm = llm;
+        }
+    }
+
+    pub fn toggle_dry_run(&mut self) {
+        self.config.dry_run = !self.config.dry_run;
+        self.agent_executor.capabilities.dry_run = self.config.dry_run;
+        let message = if self.config.dry_run {
+            "🧪 Dry run on: agent actions report what they would change without touching files"
+        } else {
+            "🧪 Dry run off: agent actions change files again"
+        };
+        self.add_notification(message.to_string(), NotificationType::Info);
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    /// Ask the agent to run the tests and fix what fails; the run's budget bounds how often it retries
+    fn run_tests_and_fix(&mut self) {
+        if self.mode != AppMode::Agentic {
+            self.toggle_agentic_mode();
+            if self.mode != AppMode::Agentic {
+                return;
+            }
+        }
+        let request = "Run the tests with run_tests. If any fail, fix the code and run them again, until they all pass.";
+        self.sidebar.chat.add_user_message(request);
+        self.run_agent_task(request, GroqClient::create_text_message("user", request), false);
+    }
+
+    /// Whether an agent run is under way, from the request until its final answer
+    fn agent_is_busy(&self) -> bool {
+        self.agent_run.is_some() || self.requests.conversation().is_some() || self.action_batch.is_some()
+    }
+
+    /// Start an agent run for `message`, or queue it as a task if the agent is busy with another
+    fn run_agent_task(&mut self, title: &str, message: GroqMessage, review_changes: bool) {
+        if self.agent_is_busy() {
+            let id = self.tasks.push(title, message, review_changes);
+            self.sidebar.chat.add_system_message(&format!("🗂️ Queued as task #{} - it starts once the agent is free", id));
+            return;
+        }
+        self.tasks.begin(title, review_changes);
+        self.start_agent_task(message, review_changes);
+    }
+
+    fn start_agent_task(&mut self, message: GroqMessage, review_changes: bool) {
+        self.supervisor.review_every_change = review_changes;
+        self.conversation.add_message(message);
+        self.request_ai_reply();
+    }
+
+    /// Called from the main loop: start the next queued task once the agent is free
+    pub fn poll_task_queue(&mut self) {
+        if self.agent_is_busy() {
+            return;
+        }
+        // A run that ended without saying how must not leave its task running, or the queue stalls
+        if self.tasks.running().is_some() {
+            self.tasks.finish_running(StopReason::Failed("the agent run ended early".to_string()));
+        }
+        let Some((task, message)) = self.tasks.start_next() else {
+            return;
+        };
+        // Once per task, so a refused switch isn't asked again on every loop
+        if self.mode != AppMode::Agentic {
+            self.toggle_agentic_mode();
+        }
+        if self.mode != AppMode::Agentic {
+            self.tasks.finish_running(StopReason::Failed("agentic mode is off".to_string()));
+            return;
+        }
+        self.sidebar.chat.add_system_message(&format!("🗂️ Starting task #{}", task.id));
+        self.start_agent_task(message, task.review_changes);
+    }
+
+    /// Cancel the task selected in the Tasks panel; a running one stops at its next step
+    fn cancel_selected_task(&mut self) {
+        let Some(task) = self.tasks.tasks().get(self.tasks_view.selected) else {
+            return;
+        };
+        let id = task.id;
+        if self.tasks.cancel(id) {
+            if self.requests.conversation().is_some() {
+                self.cancel_ai_request();
+            } else if self.action_batch.is_some() {
+                self.stop_agent_command();
+            }
+        }
+        self.add_notification(format!("⏹️ Cancelled task #{}", id), NotificationType::Info);
+    }
+
+    /// Ask the model about the function around the cursor. Commands that change code run as an
+    /// agent turn whose edits all go through the approval panel as diffs
+    fn run_code_command(&mut self, command: CodeCommand) {
+        let Some(tab) = self.editor.get_current_tab() else {
+            self.add_notification("ℹ️ Open a file first".to_string(), NotificationType::Info);
+            return;
+        };
+        let Some(path) = tab.file_path.clone() else {
+            self.add_notification("💾 Save the buffer to a file first".to_string(), NotificationType::Info);
+            return;
+        };
+        if command.edits() && tab.is_modified {
+            // The agent edits the file on disk, which doesn't have these changes yet
+            self.add_notification(format!("💾 Save {} first", tab.file_name), NotificationType::Info);
+            return;
+        }
+        let (first, last) = tab.code_region();
+        let code: Vec<String> = tab.buffer.lines_from(first).take(last - first + 1).collect();
+        let relative = path.strip_prefix(&self.current_directory).unwrap_or(&path).display().to_string();
+        let region = CodeRegion {
+            path: &relative,
+            first_line: first + 1,
+            last_line: last + 1,
+            language: tab.language().name,
+            code: &code.join("\n"),
+        };
+        let request = command.prompt(&region);
+        let title = format!("{} {}:{}-{}", command.label(), relative, first + 1, last + 1);
+
+        if !command.edits() {
+            // Only answers, so it needn't wait for the agent or the chat's answer
+            self.sidebar.chat.add_user_message(&title);
+            self.ask_in_background(title, request);
+            return;
+        }
+        if self.mode != AppMode::Agentic {
+            self.toggle_agentic_mode();
+            if self.mode != AppMode::Agentic {
+                return;
+            }
+        }
+        self.sidebar.chat.add_user_message(&title);
+        self.run_agent_task(&title, GroqClient::create_text_message("user", &request), true);
+    }
+
+    /// Ask the model a one-off question alongside the conversation; the answer appears under the
+    /// question in the chat and joins the conversation once its turn is over
+    fn ask_in_background(&mut self, title: String, prompt: String) {
+        if let Some(reason) = self.spend_guard.check(std::time::Instant::now()) {
+            self.add_notification(format!("⏸️ Not asked, {}", reason), NotificationType::Info);
+            return;
+        }
+        self.apply_persona();
+        // The conversation's instructions, without its turns, which may be waiting on tool results
+        let mut messages: Vec<GroqMessage> =
+            self.conversation.get_messages().iter().filter(|message| message.role == "system").cloned().collect();
+        messages.push(GroqClient::create_text_message("user", &prompt));
+        let messages = self.with_standing_instructions(messages);
+        let id = self.spawn_request(RequestKind::Background { title, prompt }, messages, Vec::new(), None);
+        self.sidebar.chat.set_request_message(id, MessageType::System, "🤖 Answering in the background... (Esc to cancel)");
+    }
+
+    pub fn set_mode(&mut self, mode: AppMode) {
+        self.mode = mode;
+    }
+
+    pub fn toggle_agentic_mode(&mut self) {
+        // Reader mode keeps agentic mode, its executor just can't write or run anything
+        if self.read_only_session && !self.reader_mode && self.mode != AppMode::Agentic {
+            self.add_notification("🔒 Agentic mode is disabled in a read-only session".to_string(), NotificationType::Info);
+            return;
+        }
+        self.mode = match self.mode {
+            AppMode::Agentic => AppMode::Normal,
+            _ => AppMode::Agentic,
+        };
+    }
+
+    pub fn cycle_autonomy_level(&mut self) {
+        self.config.autonomy.level = self.config.autonomy.level.next();
+        self.supervisor.settings = self.config.autonomy.clone();
+        let description = match self.config.autonomy.level {
+            AutonomyLevel::ConfirmEach => "every change needs approval".to_string(),
+            AutonomyLevel::Supervised => format!(
+                "review every {} actions or {} changed lines",
+                self.config.autonomy.checkpoint_every_actions,
+                self.config.autonomy.checkpoint_diff_lines
+            ),
+            AutonomyLevel::Autonomous => "no review pauses".to_string(),
+        };
+        self.add_notification(
+            format!("🤖 Agent autonomy: {} ({})", self.config.autonomy.level.label(), description),
+            NotificationType::Info
+        );
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    pub fn toggle_low_bandwidth(&mut self) {
+        self.config.low_bandwidth = !self.config.low_bandwidth;
+        self.reconnect_llm();
+        let message = if self.config.low_bandwidth {
+            "🐢 Low-bandwidth mode on: trimmed context, no images, shorter answers, longer timeouts"
+        } else {
+            "🚀 Low-bandwidth mode off"
+        };
+        self.add_notification(message.to_string(), NotificationType::Info);
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    pub fn cycle_line_numbers(&mut self) {
+        self.config.gutter.line_numbers = self.config.gutter.line_numbers.next();
+        self.editor.gutter = self.config.gutter.clone();
+        self.add_notification(
+            format!("🔢 Line numbers: {}", self.config.gutter.line_numbers.label()),
+            NotificationType::Info
+        );
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    pub fn toggle_hidden_files(&mut self) {
+        match self.sidebar.file_explorer.toggle_hidden() {
+            Ok(shown) => {
+                self.config.explorer.show_hidden = shown;
+                let message = if shown { "👁️ Hidden files shown" } else { "👁️ Hidden files hidden" };
+                self.add_notification(message.to_string(), NotificationType::Info);
+            }
+            Err(e) => self.add_notification(format!("❌ Failed to refresh file tree: {}", e), NotificationType::FileOperation),
+        }
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    fn handle_settings_event(&mut self, event: IdeEvent) {
+        if let Some(text) = self.settings_panel.editing.as_mut() {
+            match event {
+                IdeEvent::InsertChar(c) => text.push(c),
+                IdeEvent::Backspace => {
+                    text.pop();
+                }
+                IdeEvent::Enter | IdeEvent::Select => {
+                    let setting = self.settings_panel.selected_setting();
+                    let text = self.settings_panel.editing.take().unwrap_or_default();
+                    match setting.parse(&text) {
+                        Ok(value) => self.change_setting(setting.key, value),
+                        Err(e) => self.add_notification(format!("⚙️ {}: {}", setting.label, e), NotificationType::Info),
+                    }
+                }
+                IdeEvent::NormalMode => self.settings_panel.editing = None,
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return;
+        }
+        match event {
+            IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => self.settings_panel.move_selection(-1),
+            IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => self.settings_panel.move_selection(1),
+            IdeEvent::MouseScroll(delta) => self.settings_panel.move_selection(delta as isize),
+            IdeEvent::Enter | IdeEvent::Select => {
+                let setting = self.settings_panel.selected_setting();
+                let current = self.config.setting(setting.key);
+                match setting.kind {
+                    SettingKind::Number | SettingKind::Text | SettingKind::List => {
+                        self.settings_panel.editing = Some(setting.display(current.as_ref()));
+                    }
+                    _ => {
+                        let next = setting.next(current.as_ref(), &self.config);
+                        self.change_setting(setting.key, next);
+                    }
+                }
+            }
+            IdeEvent::NormalMode | IdeEvent::InsertChar('q') | IdeEvent::ShowSettings => self.settings_panel.close(),
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    /// Change one setting from the settings panel, apply it and save it
+    fn change_setting(&mut self, key: &str, value: Option<toml::Value>) {
+        let setting = self.settings_panel.selected_setting();
+        if self.config.is_overridden(key) {
+            self.add_notification(
+                format!("⚙️ {} is set by the project config, the environment or --set; change it there", setting.label),
+                NotificationType::Info
+            );
+            return;
+        }
+        match self.config.with_setting(key, value) {
+            Ok(config) => {
+                self.apply_config(config);
+                if let Err(e) = self.config.save() {
+                    self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+                }
+                // Our own write is not an outside edit to reload
+                self.config_stamp = Config::modified_at();
+            }
+            Err(e) => self.add_notification(format!("⚙️ {}: {}", setting.label, e), NotificationType::Info),
+        }
+    }
+
+    /// Switch to `config`, updating the parts of the IDE that copied a setting when they were built
+    /// Say so when the configured theme is neither a preset nor one of the user's themes
+    fn check_theme(&mut self) {
+        if !self.config.theme.is_known(&self.config.themes) {
+            let message = format!("🎨 Unknown theme `{}` - using the terminal's colors", self.config.theme.label());
+            self.add_notification(message, NotificationType::Info);
+        }
+    }
+
+    fn apply_config(&mut self, config: Config) {
+        let old = std::mem::replace(&mut self.config, config);
+        if old.theme != self.config.theme || old.themes != self.config.themes {
+            self.check_theme();
+        }
+        if old.keymap != self.config.keymap {
+            let (keymap, problems) = Keymap::load(&self.config.keymap);
+            self.keymap = keymap;
+            self.keymap_changed = true;
+            for problem in problems {
+                self.add_notification(format!("⌨️ Keymap: {}", problem), NotificationType::Info);
+            }
+        }
+        if old.explorer != self.config.explorer {
+            let (filter, problems) = ExplorerFilter::new(&self.current_directory, &self.config.explorer);
+            self.sidebar.file_explorer.filter = filter;
+            if let Err(e) = self.sidebar.file_explorer.refresh() {
+                self.add_notification(format!("❌ Failed to refresh file tree: {}", e), NotificationType::FileOperation);
+            }
+            for problem in problems {
+                self.add_notification(format!("📁 Explorer: {}", problem), NotificationType::Info);
+            }
+        }
+        if old.chat_height != self.config.chat_height {
+            self.layout.chat_height = self.config.chat_height.clamp(self.layout.min_chat_height, 25);
+        }
+        self.sidebar.chat.max_display_chars = self.config.postprocess.max_display_chars;
+        self.conversation.set_max_tokens(self.config.history.max_tokens);
+        self.editor.gutter = self.config.gutter.clone();
+        (self.editor.ruler, self.editor.show_ruler, self.editor.show_whitespace) =
+            (self.config.ruler, self.config.show_ruler, self.config.show_whitespace);
+        self.diff_view.options = self.config.diff;
+        self.editor.forget_file_settings();
+        if !self.reader_mode {
+            self.agent_executor.capabilities.dry_run = self.config.dry_run;
+            self.agent_executor.capabilities.sandbox = self.config.agent_sandbox;
+        }
+        if !old.same_connection(&self.config) {
+            self.reconnect_llm();
+        }
+    }
+
+    /// Reload the config when its global or project file was edited outside the IDE
+    fn check_config_changes(&mut self) {
+        let stamp = Config::modified_at();
+        if stamp == self.config_stamp {
+            return;
+        }
+        self.config_stamp = stamp;
+        match self.config.reload() {
+            Ok(config) if config.same_settings(&self.config) => {}
+            Ok(config) => {
+                self.apply_config(config);
+                self.add_notification("⚙️ Config reloaded".to_string(), NotificationType::Info);
+            }
+            Err(e) => self.add_notification(format!("⚙️ Config not reloaded: {:#}", e), NotificationType::Info),
+        }
+    }
+
+    fn show_sessions(&mut self) {
+        // Saved first, so the list has the current conversation as it is now
+        self.persist_conversation_or_notify();
+        match SavedSession::dir() {
+            Ok(dir) => self.session_browser.open(SavedSession::list(&dir)),
+            Err(e) => self.add_notification(format!("❌ Failed to list sessions: {}", e), NotificationType::FileOperation),
+        }
+    }
+
+    /// The rebuilt keymap, once, for the event handler
+    pub fn take_keymap_update(&mut self) -> Option<Keymap> {
+        std::mem::take(&mut self.keymap_changed).then(|| self.keymap.clone())
+    }
+
+    pub fn cycle_theme(&mut self) {
+        self.config.theme = self.config.theme.next(&self.config.themes);
+        self.add_notification(format!("🎨 Theme: {}", self.config.theme.label()), NotificationType::Info);
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    pub fn toggle_ruler(&mut self) {
+        self.config.show_ruler = !self.config.show_ruler;
+        self.editor.show_ruler = self.config.show_ruler;
+        let message = match (self.config.show_ruler, self.config.ruler) {
+            (false, _) => "📏 Ruler hidden".to_string(),
+            (true, Some(column)) => format!("📏 Ruler shown at column {} (or the file type's max_line_length)", column),
+            (true, None) => "📏 Ruler shown for file types with a max_line_length - set `ruler` in the config for all files".to_string(),
+        };
+        self.add_notification(message, NotificationType::Info);
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    pub fn toggle_whitespace(&mut self) {
+        self.config.show_whitespace = !self.config.show_whitespace;
+        self.editor.show_whitespace = self.config.show_whitespace;
+        let message = if self.config.show_whitespace { "· Visible whitespace on" } else { "· Visible whitespace off" };
+        self.add_notification(message.to_string(), NotificationType::Info);
+        if let Err(e) = self.config.save() {
+            self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    pub fn focus_panel(&mut self, panel: FocusedPanel) {
+        self.focused_panel = panel;
+    }
+
+    pub fn cycle_focus(&mut self) {
+        // Only include Notifications in cycling if they're visible
+        self.focused_panel = match self.focused_panel {
+            FocusedPanel::FileExplorer => {
+                // Enter a split editor at its first pane
+                if self.editor.is_second_pane_focused() {
+                    self.editor.focus_other_pane();
+                }
+                FocusedPanel::Editor
+            }
+            FocusedPanel::Editor if self.editor.split.is_some() && !self.editor.is_second_pane_focused() => {
+                self.editor.focus_other_pane();
+                FocusedPanel::Editor
+            }
+            FocusedPanel::Editor => {
+                if self.show_notifications && !self.notifications.is_empty() {
+                    FocusedPanel::Notifications
+                } else {
+                    FocusedPanel::Chat
+                }
+            },
+            FocusedPanel::Notifications => FocusedPanel::Chat,
+            FocusedPanel::Chat => FocusedPanel::FileExplorer,
+        };
+    }
+
+    pub fn resize_sidebar(&mut self, delta: i16) {
+        let new_width = (self.layout.sidebar_width as i16 + delta).max(self.layout.min_sidebar_width as i16);
+        self.layout.sidebar_width = (new_width as u16).min(self.layout.max_sidebar_width);
+    }
+
+    pub fn resize_chat(&mut self, delta: i16) {
+        let new_height = (self.layout.chat_height as i16 + delta).max(self.layout.min_chat_height as i16);
+        self.layout.chat_height = (new_height as u16).min(25); // Max 25 lines for chat
+    }
+
+    pub fn resize_notifications(&mut self, delta: i16) {
+        let new_height = (self.layout.notification_height as i16 + delta).max(self.layout.min_notification_height as i16);
+        self.layout.notification_height = (new_height as u16).min(15); // Max 15 lines for notifications
+    }
+
+    pub fn update_component_areas(&mut self, 
+        file_explorer_area: ratatui::layout::Rect,
+        notification_area: ratatui::layout::Rect,
+        chat_area: ratatui::layout::Rect,
+        editor_area: ratatui::layout::Rect
+    ) {
+        self.layout.file_explorer_area = file_explorer_area;
+        self.layout.notification_area = notification_area;
+        self.layout.chat_area = chat_area;
+        self.layout.editor_area = editor_area;
+    }
+
+    pub fn show_create_file_dialog(&mut self) {
+        self.show_create_file_dialog = true;
+        self.dialog_input.clear();
+    }
+
+    pub fn show_create_folder_dialog(&mut self) {
+        self.show_create_folder_dialog = true;
+        self.dialog_input.clear();
+    }
+
+    pub fn show_rename_dialog(&mut self, target_path: PathBuf) {
+        self.show_rename_dialog = true;
+        self.operation_target = Some(target_path.clone());
+        // Pre-populate with current filename
+        self.dialog_input = target_path.file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+            .to_string();
+    }
+
+    pub fn show_save_workflow_dialog(&mut self) {
+        self.show_save_workflow_dialog = true;
+        self.dialog_input.clear();
+    }
+
+    pub fn hide_all_dialogs(&mut self) {
+        self.show_create_file_dialog = false;
+        self.show_create_folder_dialog = false;
+        self.show_rename_dialog = false;
+        self.show_save_workflow_dialog = false;
+        self.dialog_input.clear();
+        self.operation_target = None;
+    }
+
+    pub fn has_active_dialog(&self) -> bool {
+        self.show_create_file_dialog || self.show_create_folder_dialog || self.show_rename_dialog || self.show_save_workflow_dialog
+    }
+
+    fn last_answer_plain_text(&self) -> Option<String> {
+        self.sidebar.chat
+            .last_ai_message()
+            .map(|msg| sidebar::chat::to_plain_text(msg.text()))
+    }
+
+    /// Open a file in a tab, warning when it could not be decoded cleanly
+    fn open_in_editor(&mut self, path: PathBuf) -> Result<()> {
+        let open_tabs = self.editor.tabs.len();
+        self.editor.open_file(path)?;
+        // Only ask about minified files when they are first opened, not when switching back to them
+        let long_line_tab = self.editor
+            .get_current_tab()
+            .filter(|tab| self.editor.tabs.len() > open_tabs && tab.long_line.is_some() && !tab.is_binary)
+            .map(|tab| tab.id);
+        if long_line_tab.is_some() {
+            self.long_line_tab = long_line_tab;
+            self.show_long_line_dialog = true;
+        }
+        let lossy = self.editor
+            .get_current_tab()
+            .filter(|tab| tab.lossy_decoded)
+            .map(|tab| (tab.file_name.clone(), tab.encoding.label()));
+        if let Some((file_name, encoding)) = lossy {
+            self.add_notification(
+                format!("⚠️ '{}' is not valid {} - invalid bytes were replaced and will be lost on save", file_name, encoding),
+                NotificationType::FileOperation
+            );
+        }
+        Ok(())
+    }
+
+    /// Queue the external opener configured for this file type, if any
+    fn queue_external_opener(&mut self, path: &std::path::Path) -> bool {
+        match opener::command_for(&self.config.openers, path) {
+            Some(command) => {
+                self.pending_external_command = Some(command);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn take_external_command(&mut self) -> Option<String> {
+        self.pending_external_command.take()
+    }
+
+    fn request_suspend(&mut self) {
+        if cfg!(unix) {
+            self.suspend_requested = true;
+        } else {
+            self.add_notification("⚠️ Suspending is only supported on Unix terminals".to_string(), NotificationType::Info);
+        }
+    }
+
+    #[cfg_attr(not(unix), allow(dead_code))]
+    pub fn take_suspend_request(&mut self) -> bool {
+        std::mem::take(&mut self.suspend_requested)
+    }
+
+    /// Periodic work driven by the main loop
+    pub fn tick(&mut self) {
+        self.editor.refresh_file_settings(&self.config.indent, &self.config.save_hooks, &self.config.filetypes);
+
+        if let Err(e) = self.autosave.tick(&self.editor) {
+            self.add_notification(format!("❌ Autosave failed: {}", e), NotificationType::FileOperation);
+        }
+
+        if self.last_disk_check.elapsed() >= std::time::Duration::from_secs(1) {
+            self.last_disk_check = std::time::Instant::now();
+            self.check_external_changes();
+            self.check_config_changes();
+            self.persist_conversation_or_notify();
+            if !self.read_only_session && self.session_lock.is_lost() {
+                self.session_lock.disown();
+                self.enter_read_only_session();
+                self.add_notification(
+                    "⚠️ Another instance took over this project - this window is now read-only".to_string(),
+                    NotificationType::FileOperation
+                );
+            }
+        }
+
+        if self.read_only_session {
+            // Covers tabs opened after attaching as well
+            for tab in self.editor.tabs.iter_mut() {
+                tab.read_only = true;
+            }
+        }
+    }
+
+    fn enter_read_only_session(&mut self) {
+        self.read_only_session = true;
+        if matches!(self.mode, AppMode::Insert | AppMode::Agentic) {
+            self.mode = AppMode::Normal;
+        }
+        for tab in self.editor.tabs.iter_mut() {
+            tab.read_only = true;
+        }
+    }
+
+    /// Browse without any write path: saves, explorer changes and agent writes or commands are
+    /// refused, and the project lock is left for a real editing session
+    pub fn enter_reader_mode(&mut self) {
+        self.reader_mode = true;
+        self.enter_read_only_session();
+        self.session_lock.release();
+        self.show_session_lock_dialog = false;
+        self.sidebar.file_explorer.read_only = true;
+        self.agent_executor.capabilities = AgentCapabilities::read_only();
+        self.add_notification(
+            "🔒 Reader mode - saving, file operations and agent writes or commands are disabled".to_string(),
+            NotificationType::Info
+        );
+    }
+
+    fn handle_session_lock_event(&mut self, event: IdeEvent) {
+        let pid = self.lock_holder.as_ref().map(|holder| holder.pid).unwrap_or_default();
+        match event {
+            IdeEvent::InsertChar('r') | IdeEvent::Enter | IdeEvent::Select | IdeEvent::NormalMode => {
+                self.enter_read_only_session();
+                self.add_notification(
+                    format!("👀 Attached read-only - PID {} is editing this project", pid),
+                    NotificationType::FileOperation
+                );
+            }
+            IdeEvent::InsertChar('t') => match self.session_lock.take_over() {
+                Ok(()) => self.add_notification(
+                    format!("🔓 Took over this project from PID {}, which is now read-only", pid),
+                    NotificationType::FileOperation
+                ),
+                Err(e) => {
+                    self.enter_read_only_session();
+                    self.add_notification(format!("❌ Takeover failed, attached read-only: {}", e), NotificationType::FileOperation);
+                }
+            },
+            IdeEvent::InsertChar('q') | IdeEvent::Quit => self.quit(),
+            _ => return,
+        }
+        self.show_session_lock_dialog = false;
+    }
+
+    /// Reload clean buffers whose file changed on disk; flag modified ones and ask the user
+    fn check_external_changes(&mut self) {
+        let mut reloaded = Vec::new();
+        let mut conflicts = Vec::new();
+
+        for tab in self.editor.tabs.iter_mut() {
+            if tab.changed_on_disk || !tab.has_changed_on_disk() {
+                continue;
+            }
+            if tab.is_modified {
+                tab.changed_on_disk = true;
+                conflicts.push(tab.id);
+            } else {
+                match tab.reload() {
+                    Ok(()) => reloaded.push(tab.file_name.clone()),
+                    Err(_) => tab.keep_buffer_over_disk(), // Deleted or unreadable - keep what we have
+                }
+            }
+        }
+
+        for name in reloaded {
+            self.add_notification(format!("🔄 Reloaded '{}' (changed on disk)", name), NotificationType::FileOperation);
+        }
+        if let Some(&tab_id) = conflicts.first() {
+            if !self.show_external_change_dialog {
+                self.show_external_change_prompt(tab_id);
+            }
+        }
+    }
+
+    fn show_external_change_prompt(&mut self, tab_id: u32) {
+        self.external_change_tab = Some(tab_id);
+        self.show_external_change_dialog = true;
+    }
+
+    fn handle_external_change_event(&mut self, event: IdeEvent) {
+        let Some(tab_id) = self.external_change_tab else {
+            self.show_external_change_dialog = false;
+            return;
+        };
+        let Some(tab) = self.editor.tabs.iter_mut().find(|tab| tab.id == tab_id) else {
+            self.show_external_change_dialog = false;
+            return;
+        };
+        let file_name = tab.file_name.clone();
+
+        match event {
+            IdeEvent::InsertChar('r') => {
+                match tab.reload() {
+                    Ok(()) => self.add_notification(format!("🔄 Reloaded '{}' from disk", file_name), NotificationType::FileOperation),
+                    Err(e) => self.add_notification(format!("❌ Reload failed: {}", e), NotificationType::FileOperation),
+                }
+            }
+            IdeEvent::InsertChar('k') => {
+                tab.keep_buffer_over_disk();
+                self.add_notification(format!("✋ Keeping your version of '{}' - Ctrl+S will overwrite the disk copy", file_name), NotificationType::FileOperation);
+            }
+            IdeEvent::InsertChar('d') => {
+                let disk = tab.file_path.as_ref().and_then(|path| std::fs::read_to_string(path).ok()).unwrap_or_default();
+                let diff = crate::diff::unified_diff(&disk, &tab.buffer.to_string(), "on disk", "buffer", 3);
+                self.editor.open_tab(editor::EditorTab::recovered(None, format!("{} (diff)", file_name), &diff));
+                if let Some(diff_tab) = self.editor.get_current_tab_mut() {
+                    diff_tab.is_modified = false;
+                    diff_tab.read_only = true;
+                }
+                self.add_notification("🔍 Opened diff - resolve with Ctrl+S on the original tab".to_string(), NotificationType::FileOperation);
+            }
+            IdeEvent::NormalMode => {}
+            _ => return,
+        }
+
+        self.show_external_change_dialog = false;
+        self.external_change_tab = None;
+    }
+
+    fn handle_long_line_event(&mut self, event: IdeEvent) {
+        let tab = self.long_line_tab.and_then(|id| self.editor.tabs.iter_mut().find(|tab| tab.id == id));
+        let Some(tab) = tab else {
+            self.show_long_line_dialog = false;
+            return;
+        };
+        let file_name = tab.file_name.clone();
+
+        match event {
+            IdeEvent::InsertChar('p') => {
+                let unit = tab.settings.indent_unit();
+                match tab.pretty_print(&self.config.save_hooks, &unit) {
+                    Ok(()) => self.add_notification(
+                        format!("✨ Pretty-printed '{}' - Ctrl+S to save it that way", file_name),
+                        NotificationType::FileOperation
+                    ),
+                    Err(e) => self.add_notification(format!("❌ Cannot pretty-print '{}': {}", file_name, e), NotificationType::FileOperation),
+                }
+            }
+            IdeEvent::InsertChar('e') => {
+                tab.allow_editing();
+                self.add_notification(format!("✏️ Editing '{}' as-is", file_name), NotificationType::FileOperation);
+            }
+            IdeEvent::InsertChar('r') | IdeEvent::NormalMode => self.add_notification(
+                format!("📏 '{}' is a read-only view - move with 0, $, w and counts like 50l", file_name),
+                NotificationType::FileOperation
+            ),
+            _ => return,
+        }
+
+        self.show_long_line_dialog = false;
+        self.long_line_tab = None;
+    }
+
+    /// The agent hit a request or cost cap: go on past it, or stop the run
+    fn handle_spend_pause_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar('c') | IdeEvent::Enter | IdeEvent::Select => {
+                self.spend_pause = None;
+                self.spend_guard.approve(std::time::Instant::now());
+                self.request_ai_reply();
+            }
+            IdeEvent::InsertChar('s') | IdeEvent::NormalMode => {
+                if let Some(reason) = self.spend_pause.take() {
+                    self.sidebar.chat.add_system_message(&format!("🛑 Agent stopped at {}", reason));
+                }
+                self.finish_agent_run(StopReason::Cancelled);
+            }
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    /// Reformat the current file, e.g. to make minified JSON readable
+    fn pretty_print_current(&mut self) {
+        let Some(tab) = self.editor.get_current_tab_mut() else {
+            return;
+        };
+        let unit = tab.settings.indent_unit();
+        let file_name = tab.file_name.clone();
+        match tab.pretty_print(&self.config.save_hooks, &unit).map(|()| tab.buffer.len_lines()) {
+            Ok(lines) => self.add_notification(
+                format!("✨ Pretty-printed '{}' ({} lines)", file_name, lines),
+                NotificationType::FileOperation
+            ),
+            Err(e) => self.add_notification(format!("❌ Cannot pretty-print '{}': {}", file_name, e), NotificationType::FileOperation),
+        }
+    }
+
+    /// Capture the current setup (system prompt, open files, model, autonomy, prompt) under `name`
+    fn save_workflow(&mut self, name: String) {
+        let pinned_files = self.editor.tabs
+            .iter()
+            .filter_map(|tab| tab.file_path.as_deref())
+            .map(|path| workflow::relative_to(path, &self.current_directory))
+            .collect();
+        let input = self.sidebar.chat.input.trim();
+        let opening_prompt = if input.is_empty() {
+            self.conversation.first_text_message("user").unwrap_or_default().to_string()
+        } else {
+            input.to_string()
+        };
+
+        let template = workflow::WorkflowTemplate {
+            name: name.clone(),
+            system_prompt: self.conversation.first_text_message("system").map(str::to_string),
+            pinned_files,
+            model: Some(self.model().to_string()),
+            autonomy: Some(self.config.autonomy.level),
+            opening_prompt,
+        };
+        match self.config.save_workflow(template) {
+            Ok(()) => self.add_notification(format!("💾 Saved workflow '{}'", name), NotificationType::Info),
+            Err(e) => self.add_notification(format!("❌ Failed to save workflow: {}", e), NotificationType::FileOperation),
+        }
+    }
+
+    fn handle_workflow_picker_event(&mut self, event: IdeEvent) {
+        let count = self.config.workflows.len();
+        match event {
+            IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => {
+                self.workflow_selected = self.workflow_selected.checked_sub(1).unwrap_or(count.saturating_sub(1));
+            }
+            IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => {
+                self.workflow_selected = (self.workflow_selected + 1) % count.max(1);
+            }
+            IdeEvent::Enter | IdeEvent::Select => {
+                self.show_workflow_picker = false;
+                if let Some(template) = self.config.workflows.get(self.workflow_selected).cloned() {
+                    self.start_workflow(template);
+                }
+            }
+            IdeEvent::NormalMode | IdeEvent::StartWorkflow => self.show_workflow_picker = false,
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    /// Start a fresh conversation from a template, leaving its opening prompt in the chat input
+    fn start_workflow(&mut self, template: workflow::WorkflowTemplate) {
+        self.start_new_session();
+        self.sidebar.chat.clear();
+        self.conversation.clear();
+        self.model_override = template.model.clone();
+        if let Some(level) = template.autonomy {
+            self.config.autonomy.level = level;
+        }
+
+        // Pinned files are opened and handed to the model, minus anything in .aiignore
+        let ai_ignore = crate::agent::aiignore::AiIgnore::load(&self.current_directory);
+        let mut pinned = Vec::new();
+        for path in &template.pinned_files {
+            let full_path = self.current_directory.join(path);
+            if ai_ignore.is_ignored(&full_path, full_path.is_dir()) {
+                continue;
+            }
+            match std::fs::read_to_string(&full_path) {
+                Ok(content) => {
+                    pinned.push((path.clone(), content));
+                    if let Err(e) = self.open_in_editor(full_path) {
+                        self.add_notification(format!("⚠️ Could not open {}: {}", path.display(), e), NotificationType::FileOperation);
+                    }
+                }
+                Err(e) => self.add_notification(
+                    format!("⚠️ Pinned file {} skipped: {}", path.display(), e),
+                    NotificationType::FileOperation
+                ),
+            }
+        }
+
+        // Pinned context goes in first so the system prompt ends up at the very top
+        if let Some(context) = workflow::pinned_context(&pinned) {
+            self.conversation.add_system_message(context);
+        }
+        if let Some(system_prompt) = &template.system_prompt {
+            self.conversation.add_system_message(system_prompt.clone());
+        }
+
+        self.sidebar.chat.add_system_message(&format!(
+            "📋 Workflow '{}' started ({} pinned files, model {})",
+            template.name,
+            pinned.len(),
+            self.model()
+        ));
+        self.sidebar.chat.insert_str(&template.opening_prompt);
+        self.focus_panel(FocusedPanel::Chat);
+    }
+
+    /// Model for the next request: the running workflow's, else the configured default
+    fn model(&self) -> &str {
+        self.model_override.as_deref().unwrap_or_else(|| self.config.get_model())
+    }
+
+    fn handle_recovery_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar('y') | IdeEvent::InsertChar('r') | IdeEvent::Enter | IdeEvent::Select => {
+                let swaps = std::mem::take(&mut self.pending_recoveries);
+                for swap in &swaps {
+                    self.editor.open_tab(editor::EditorTab::recovered(
+                        swap.original_path.clone(),
+                        swap.file_name.clone(),
+                        &swap.content,
+                    ));
+                    self.autosave.discard(swap);
+                }
+                self.add_notification(
+                    format!("♻️ Recovered {} unsaved buffer(s) - save them to keep the changes", swaps.len()),
+                    NotificationType::FileOperation
+                );
+                self.focus_panel(FocusedPanel::Editor);
+                self.show_recovery_dialog = false;
+            }
+            IdeEvent::InsertChar('n') | IdeEvent::InsertChar('d') => {
+                for swap in std::mem::take(&mut self.pending_recoveries) {
+                    self.autosave.discard(&swap);
+                }
+                self.add_notification("🗑️ Discarded recovery files".to_string(), NotificationType::FileOperation);
+                self.show_recovery_dialog = false;
+            }
+            IdeEvent::NormalMode => {
+                // Keep the swap files for the next start
+                self.pending_recoveries.clear();
+                self.show_recovery_dialog = false;
+            }
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    /// Whether typed characters should go to a text field rather than act as navigation keys
+    /// The focused panel, for key bindings limited to one
+    pub fn key_context(&self) -> Option<&'static str> {
+        match self.focused_panel {
+            FocusedPanel::Chat => Some("chat"),
+            FocusedPanel::Editor => Some("editor"),
+            FocusedPanel::FileExplorer => Some("explorer"),
+            _ => None,
+        }
+    }
+
+    pub fn is_capturing_text(&self) -> bool {
+        self.command_palette.is_open
+            || self.emoji_picker.is_open
+            || self.language_picker.is_open
+            || self.model_picker.is_open
+            || self.diff_view.is_open
+            || self.mark_picker.is_open
+            || self.ref_picker.is_open
+            || self.notification_history.is_open
+            || self.notes_view.is_open
+            || self.keymap_view.is_open
+            || self.settings_panel.is_open
+            || self.session_browser.is_open
+            || self.branch_view.is_open
+            || self.prompt_search.is_open
+            || self.tasks_view.is_open
+            || self.plan_editor.is_open
+            || self.approval.is_open
+            || self.show_api_config
+            || self.show_session_lock_dialog
+            || self.show_recovery_dialog
+            || self.show_unsaved_dialog
+            || self.show_external_change_dialog
+            || self.show_long_line_dialog
+            || self.spend_pause.is_some()
+            || self.has_active_dialog()
+            || self.focused_panel == FocusedPanel::Chat
+            || (self.focused_panel == FocusedPanel::Editor && self.mode == AppMode::Insert)
+    }
+
+    async fn handle_palette_event(&mut self, event: IdeEvent) -> Result<()> {
+        match event {
+            IdeEvent::InsertChar(c) => self.command_palette.add_char(c),
+            IdeEvent::Backspace => self.command_palette.backspace(),
+            IdeEvent::NavigateUp => self.command_palette.select_previous(),
+            IdeEvent::NavigateDown => self.command_palette.select_next(),
+            IdeEvent::NormalMode | IdeEvent::ToggleCommandPalette => self.command_palette.close(),
+            IdeEvent::Quit => self.request_quit(),
+            IdeEvent::Enter | IdeEvent::Select => {
+                let selection = self.command_palette.get_selection();
+                self.command_palette.close();
+                match selection {
+                    Some(palette::PaletteSelection::Calculation(result)) => self.insert_or_copy(&result, "🧮"),
+                    Some(palette::PaletteSelection::Command(event)) => {
+                        Box::pin(self.dispatch_event(event)).await?;
+                    }
+                    None => {}
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_diff_view_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar('j') | IdeEvent::NavigateDown => self.diff_view.scroll_by(1),
+            IdeEvent::InsertChar('k') | IdeEvent::NavigateUp => self.diff_view.scroll_by(-1),
+            IdeEvent::InsertChar('d') => self.diff_view.scroll_by(self.diff_view.half_page()),
+            IdeEvent::InsertChar('u') => self.diff_view.scroll_by(-self.diff_view.half_page()),
+            IdeEvent::MouseScroll(delta) => self.diff_view.scroll_by(delta as isize * 3),
+            IdeEvent::InsertChar('n') => self.diff_view.next_change(),
+            IdeEvent::InsertChar('s') => self.diff_view.toggle_layout(),
+            IdeEvent::InsertChar('w') => self.diff_view.toggle_ignore_whitespace(),
+            IdeEvent::InsertChar('a') => self.diff_view.cycle_algorithm(),
+            IdeEvent::InsertChar('q') | IdeEvent::NormalMode => self.diff_view.close(),
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    fn handle_notification_history_event(&mut self, event: IdeEvent) -> Result<()> {
+        let history = &mut self.notification_history;
+        match event {
+            IdeEvent::InsertChar(c) => history.add_char(c),
+            IdeEvent::Backspace => history.backspace(),
+            IdeEvent::Tab => history.next_filter(),
+            IdeEvent::NavigateUp => history.select_previous(),
+            IdeEvent::NavigateDown => {
+                let matches = self.notification_archive.search(&history.query, history.kind()).len();
+                history.select_next(matches);
+            }
+            IdeEvent::Enter | IdeEvent::Select => {
+                let message = self.notification_archive
+                    .search(&history.query, history.kind())
+                    .get(history.selected)
+                    .map(|entry| entry.message.clone());
+                if let Some(message) = message {
+                    if self.open_first_file_ref(&message)? {
+                        self.notification_history.close();
+                    }
+                }
+            }
+            IdeEvent::NormalMode | IdeEvent::ShowNotificationHistory => history.close(),
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_ref_picker_event(&mut self, event: IdeEvent) -> Result<()> {
+        match event {
+            IdeEvent::InsertChar('k') | IdeEvent::NavigateUp => self.ref_picker.select_previous(),
+            IdeEvent::InsertChar('j') | IdeEvent::NavigateDown => self.ref_picker.select_next(),
+            IdeEvent::Enter | IdeEvent::Select => {
+                let selected = self.ref_picker.get_selected().cloned();
+                self.ref_picker.close();
+                if let Some(file_ref) = selected {
+                    self.open_file_ref(&file_ref)?;
+                }
+            }
+            IdeEvent::InsertChar('q') | IdeEvent::NormalMode | IdeEvent::ShowFileRefs => self.ref_picker.close(),
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_mark_picker_event(&mut self, event: IdeEvent) -> Result<()> {
+        match event {
+            IdeEvent::InsertChar('k') | IdeEvent::NavigateUp => self.mark_picker.select_previous(),
+            IdeEvent::InsertChar('j') | IdeEvent::NavigateDown => self.mark_picker.select_next(),
+            IdeEvent::InsertChar('d') | IdeEvent::DeleteFile(_) => {
+                if let Some(name) = self.mark_picker.get_selected() {
+                    self.marks.remove(name);
+                    self.mark_picker.remove_selected();
+                    self.refresh_mark_signs();
+                }
+            }
+            IdeEvent::Enter | IdeEvent::Select => {
+                let selected = self.mark_picker.get_selected();
+                self.mark_picker.close();
+                if let Some(name) = selected {
+                    self.jump_to_mark(name)?;
+                }
+            }
+            IdeEvent::InsertChar('q') | IdeEvent::NormalMode | IdeEvent::ShowMarks => self.mark_picker.close(),
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn handle_plan_editor_event(&mut self, event: IdeEvent) -> Result<()> {
+        if self.plan_editor.is_editing() {
+            match event {
+                IdeEvent::InsertChar(c) => self.plan_editor.add_char(c),
+                IdeEvent::Backspace => self.plan_editor.backspace(),
+                IdeEvent::Enter | IdeEvent::Select => self.plan_editor.finish_edit(),
+                IdeEvent::NormalMode => self.plan_editor.cancel_edit(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match event {
+            IdeEvent::InsertChar('k') | IdeEvent::NavigateUp => self.plan_editor.select_previous(),
+            IdeEvent::InsertChar('j') | IdeEvent::NavigateDown => self.plan_editor.select_next(),
+            IdeEvent::InsertChar('K') => self.plan_editor.move_up(),
+            IdeEvent::InsertChar('J') => self.plan_editor.move_down(),
+            IdeEvent::InsertChar('e') | IdeEvent::Enter | IdeEvent::Select => self.plan_editor.start_edit(),
+            IdeEvent::InsertChar('o') => self.plan_editor.insert_below(),
+            IdeEvent::InsertChar('d') | IdeEvent::DeleteFile(_) => self.plan_editor.delete(),
+            IdeEvent::InsertChar('y') | IdeEvent::SendMessage => self.approve_plan(),
+            IdeEvent::InsertChar('q') | IdeEvent::NormalMode => {
+                self.plan_editor.close();
+                self.sidebar.chat.add_system_message("📋 Plan rejected - nothing was sent to the agent");
+            }
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_approval_event(&mut self, event: IdeEvent) {
+        if self.approval.is_confirming() {
+            match event {
+                IdeEvent::InsertChar(c) => self.approval.type_confirmation(c),
+                IdeEvent::Backspace => self.approval.confirmation_backspace(),
+                IdeEvent::Enter | IdeEvent::Select if !self.approval.submit_confirmation() => {
+                    self.add_notification("⚠️ Not approved - type `yes` or the whole command".to_string(), NotificationType::Info);
+                }
+                IdeEvent::NormalMode => self.approval.cancel_confirmation(),
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return;
+        }
+        match event {
+            IdeEvent::InsertChar('k') | IdeEvent::NavigateUp => self.approval.select_previous(),
+            IdeEvent::InsertChar('j') | IdeEvent::NavigateDown => self.approval.select_next(),
+            IdeEvent::InsertChar('K') => self.approval.scroll_preview(-1),
+            IdeEvent::InsertChar('J') => self.approval.scroll_preview(1),
+            IdeEvent::MouseScroll(delta) => self.approval.scroll_preview(delta as isize * 3),
+            IdeEvent::InsertChar(' ') => self.approval.toggle_selected(),
+            IdeEvent::InsertChar('a') => {
+                self.approval.approve_all();
+                self.run_approved_actions();
+            }
+            IdeEvent::Enter | IdeEvent::Select | IdeEvent::InsertChar('y') => self.run_approved_actions(),
+            IdeEvent::InsertChar('q') | IdeEvent::InsertChar('r') | IdeEvent::NormalMode => {
+                let decisions = self.approval.take_decisions();
+                for id in std::mem::take(&mut self.pending_tool_calls) {
+                    self.answer_tool_call(&id, &rejected_response());
+                }
+                self.sidebar.chat.add_system_message(&format!("🚫 Rejected {} agent action(s) - nothing was run", decisions.len()));
+                self.finish_agent_run(StopReason::Rejected);
+            }
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    /// Run the actions ticked in the approval panel, post their results in the chat and let the
+    /// model carry on with them
+    fn run_approved_actions(&mut self) {
+        let ids = std::mem::take(&mut self.pending_tool_calls);
+        let mut approved = Vec::new();
+        let mut approved_ids = Vec::new();
+        let mut rejected = 0;
+        for ((action, is_approved), id) in self.approval.take_decisions().into_iter().zip(ids) {
+            if is_approved {
+                approved.push(action);
+                approved_ids.push(id);
+            } else {
+                self.answer_tool_call(&id, &rejected_response());
+                rejected += 1;
+            }
+        }
+        if approved.is_empty() {
+            self.sidebar.chat.add_system_message("🚫 No agent actions approved - nothing was run");
+            self.finish_agent_run(StopReason::Rejected);
+            return;
+        }
+        self.start_action_batch(approved_ids, approved, true, rejected);
+    }
+
+    /// Run the tool calls from an agentic-mode answer as far as the autonomy level allows; the
+    /// first one that needs review and everything after it go to the approval panel. Returns
+    /// whether every call already has its result, so the model can go on
+    fn run_agent_actions(&mut self, calls: Vec<(String, AgentAction)>) -> bool {
+        if calls.is_empty() {
+            return true;
+        }
+        let (ids, actions): (Vec<String>, Vec<AgentAction>) = calls.into_iter().unzip();
+        self.start_action_batch(ids, actions, false, 0);
+        false
+    }
+
+    /// Hand the executor and supervisor to a blocking task that runs `actions`; `poll_agent_actions`
+    /// takes them back. Reviewed actions were approved already and skip the checkpoints
+    fn start_action_batch(&mut self, ids: Vec<String>, actions: Vec<AgentAction>, reviewed: bool, rejected: usize) {
+        let root = self.current_directory.clone();
+        let mut executor = std::mem::replace(&mut self.agent_executor, DefaultAgentExecutor::new(root.clone()));
+        let mut supervisor = std::mem::replace(&mut self.supervisor, Supervisor::new(self.config.autonomy.clone()));
+        let task = tokio::task::spawn_blocking(move || {
+            // A panicking action must not take the executor and supervisor with it
+            let run = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                if reviewed {
+                    supervisor
+                        .run_reviewed(actions, &mut executor, &root)
+                        .map(|responses| SupervisedRun { responses, pending: Vec::new() })
+                } else {
+                    supervisor.run(actions, &mut executor, &root)
+                }
+            }))
+            .unwrap_or_else(|_| Err(anyhow::anyhow!("an agent action panicked")));
+            (executor, supervisor, run)
+        });
+        let message_id = self.requests.new_id();
+        self.sidebar.chat.set_request_message(message_id, MessageType::System, "⚡ Running agent actions... (Esc stops a command)");
+        self.tasks.set_progress(&format!("running {} action(s)", ids.len()));
+        self.action_batch = Some(ActionBatch { ids, rejected, output: Vec::new(), message_id, task });
+    }
+
+    /// Called from the main loop: show command output as it arrives and, once the batch is done,
+    /// answer its tool calls and let the model go on, or open the approval panel at a checkpoint
+    pub async fn poll_agent_actions(&mut self) {
+        let Some(batch) = self.action_batch.as_mut() else {
+            return;
+        };
+        if !batch.task.is_finished() {
+            let lines = self.command_monitor.take_lines();
+            if !lines.is_empty() {
+                batch.output.extend(lines);
+                let excess = batch.output.len().saturating_sub(LIVE_OUTPUT_LINES);
+                batch.output.drain(..excess);
+                let live = format!("⚡ Running agent actions... (Esc stops a command)\n{}", batch.output.join("\n"));
+                self.sidebar.chat.set_request_message(batch.message_id, MessageType::System, &live);
+            }
+            return;
+        }
+        let Some(batch) = self.action_batch.take() else {
+            return;
+        };
+        self.command_monitor.take_lines();
+        self.sidebar.chat.remove_request_message(batch.message_id); // Live output
+        let result = match batch.task.await {
+            Ok((executor, supervisor, run)) => {
+                self.agent_executor = executor;
+                self.supervisor = supervisor;
+                // Settings changed while the batch ran apply from now on
+                self.agent_executor.capabilities.dry_run = self.config.dry_run;
+                self.supervisor.settings = self.config.autonomy.clone();
+                run
+            }
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(run) => {
+                for (id, response) in batch.ids.iter().zip(&run.responses) {
+                    self.answer_tool_call(id, response);
+                }
+                let executed = run.responses.len();
+                if executed > 0 || batch.rejected > 0 {
+                    self.report_agent_run(Ok(run.responses), batch.rejected);
+                }
+                if run.pending.is_empty() {
+                    self.deliver_turn_summary();
+                    self.request_ai_reply();
+                    return;
+                }
+                self.pending_tool_calls = batch.ids[executed..].to_vec();
+                self.tasks.set_progress("waiting for approval");
+                self.approval.open(run.pending, &self.current_directory);
+            }
+            Err(e) => {
+                self.fail_tool_calls(&batch.ids, &e);
+                self.finish_agent_run(StopReason::Failed(e.to_string()));
+                self.report_agent_run(Err(e), batch.rejected);
+            }
+        }
+    }
+
+    /// Kill the agent command that is running, if any; the rest of its batch still runs
+    pub fn stop_agent_command(&mut self) {
+        if self.action_batch.is_some() {
+            self.command_monitor.kill();
+        }
+    }
+
+    /// Send the result of a tool call back to the model with the next request
+    fn answer_tool_call(&mut self, id: &str, response: &AgentResponse) {
+        self.conversation.add_message(actions::result_message(id, response));
+    }
+
+    /// What the turn's actions changed in the workspace, in the chat and for the model's next turn
+    fn deliver_turn_summary(&mut self) {
+        if let Some(summary) = self.supervisor.take_turn_summary().to_message() {
+            self.sidebar.chat.add_system_message(&format!("📋 {}", summary));
+            self.conversation.add_message(GroqClient::create_text_message("user", &summary));
+        }
+    }
+
+    /// Every call still needs an answer, or the next request is refused
+    fn fail_tool_calls(&mut self, ids: &[String], error: &anyhow::Error) {
+        let response = AgentResponse::error("Action failed".to_string(), error.to_string());
+        for id in ids {
+            self.answer_tool_call(id, &response);
+        }
+    }
+
+    /// Post what the agent did in the chat and pick up the files it created or removed
+    fn report_agent_run(&mut self, result: Result<Vec<AgentResponse>>, rejected: usize) {
+        let mut report = match result {
+            Ok(responses) => format_agent_responses(&responses),
+            Err(e) => format!("❌ Agent actions failed: {}\n", e),
+        };
+        if rejected > 0 {
+            report.push_str(&format!("🚫 {} action(s) rejected and not run\n", rejected));
+        }
+        self.sidebar.chat.add_system_message(&report);
+        if let Err(e) = self.sidebar.file_explorer.refresh() {
+            self.add_notification(format!("❌ Failed to refresh file explorer: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    /// Put back what agent actions overwrote or deleted: the newest change, or every change of this session
+    fn undo_agent_changes(&mut self, whole_session: bool) {
+        if self.read_only_session {
+            self.sidebar.chat.add_system_message("🔒 Agent changes can't be undone in a read-only session");
+            return;
+        }
+        if self.action_batch.is_some() {
+            self.sidebar.chat.add_system_message("⏳ Agent actions are still running - undo once they are done");
+            return;
+        }
+        let Some(undo_log) = self.agent_executor.undo_log.as_mut() else {
+            self.sidebar.chat.add_system_message("❌ Agent undo is unavailable - no cache directory to keep snapshots in");
+            return;
+        };
+        let undone = if whole_session {
+            let session = undo_log.session().to_string();
+            undo_log.undo_session(&session)
+        } else {
+            undo_log.undo_last().map(|change| change.into_iter().collect())
+        };
+        let message = match undone {
+            Ok(changes) if changes.is_empty() => "ℹ️ No agent changes to undo".to_string(),
+            Ok(changes) => {
+                let descriptions: Vec<String> = changes.iter().map(|change| format!("  {}", change.description)).collect();
+                format!("↩️ Undid {} agent change(s):\n{}", changes.len(), descriptions.join("\n"))
+            }
+            Err(e) => format!("❌ Undo failed: {}", e),
+        };
+        self.sidebar.chat.add_system_message(&message);
+        self.check_external_changes(); // Open buffers show the files as they are back now
+        if let Err(e) = self.sidebar.file_explorer.refresh() {
+            self.add_notification(format!("❌ Failed to refresh file explorer: {}", e), NotificationType::FileOperation);
+        }
+    }
+
+    /// Send the reviewed plan back to the model as the contract for the rest of the run
+    fn approve_plan(&mut self) {
+        let plan = self.plan_editor.plan();
+        self.plan_editor.close();
+        if plan.steps.is_empty() {
+            self.sidebar.chat.add_system_message("📋 Plan rejected - nothing was sent to the agent");
+            return;
+        }
+        self.sidebar.chat.add_system_message(&format!("📋 Plan agreed ({} steps)", plan.steps.len()));
+        self.conversation.add_message(GroqClient::create_text_message("user", &plan.to_contract()));
+        self.request_ai_reply();
+    }
+
+    /// Remember the cursor position of the current file under `name`
+    fn set_mark(&mut self, name: char) {
+        let Some(tab) = self.editor.get_current_tab() else {
+            return;
+        };
+        let Some(path) = tab.file_path.clone() else {
+            self.add_notification("⚠️ Save the file before setting a mark".to_string(), NotificationType::Info);
+            return;
+        };
+        let line = tab.cursor_line;
+        self.marks.set(name, marks::Mark { path, line, col: tab.cursor_col });
+        self.refresh_mark_signs();
+        self.add_notification(format!("🔖 Mark '{}' set at line {}", name, line + 1), NotificationType::Info);
+    }
+
+    fn handle_tab_menu_event(&mut self, event: IdeEvent) {
+        let Some(menu) = &mut self.tab_menu else {
+            return;
+        };
+        let area = self.layout.tab_menu_area;
+        let item_at = |x: u16, y: u16| {
+            area.contains(ratatui::layout::Position { x, y }).then(|| (y - area.y) as usize)
+        };
+        match event {
+            IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => menu.selected = menu.selected.saturating_sub(1),
+            IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => {
+                menu.selected = (menu.selected + 1).min(TabMenuItem::ALL.len() - 1);
+            }
+            IdeEvent::MouseMove(x, y) => {
+                if let Some(index) = item_at(x, y) {
+                    menu.selected = index;
+                }
+            }
+            IdeEvent::Enter | IdeEvent::Select => {
+                let (tab_id, item) = (menu.tab_id, TabMenuItem::ALL[menu.selected]);
+                self.tab_menu = None;
+                self.run_tab_menu_item(tab_id, item);
+            }
+            IdeEvent::MouseClick(x, y) => {
+                let tab_id = menu.tab_id;
+                self.tab_menu = None;
+                if let Some(item) = item_at(x, y).and_then(|index| TabMenuItem::ALL.get(index)) {
+                    self.run_tab_menu_item(tab_id, *item);
+                }
+            }
+            IdeEvent::NormalMode | IdeEvent::MouseRightClick(..) | IdeEvent::InsertChar('q') => self.tab_menu = None,
+            IdeEvent::Quit => self.request_quit(),
+            _ => {}
+        }
+    }
+
+    fn run_tab_menu_item(&mut self, tab_id: u32, item: TabMenuItem) {
+        let Some(index) = self.editor.tabs.iter().position(|tab| tab.id == tab_id) else {
+            return;
+        };
+        // Pinned tabs are only closed one at a time, on purpose
+        match item {
+            TabMenuItem::Pin => self.toggle_pin(index),
+            TabMenuItem::Close => {
+                self.request_close_tab(tab_id);
+            }
+            TabMenuItem::CloseOthers => {
+                let others = self.editor.tabs.iter().filter(|tab| tab.id != tab_id && !tab.pinned).map(|tab| tab.id).collect();
+                self.request_close_tabs(others);
+            }
+            TabMenuItem::CloseToTheRight => {
+                let right = self.editor.tabs[index + 1..].iter().filter(|tab| !tab.pinned).map(|tab| tab.id).collect();
+                self.request_close_tabs(right);
+            }
+            TabMenuItem::CopyPath => match self.editor.tabs[index].file_path.clone() {
+                Some(path) => match self.clipboard.set_text(&path.to_string_lossy()) {
+                    Ok(()) => self.add_notification(format!("📋 Copied {}", path.display()), NotificationType::Info),
+                    Err(e) => self.add_notification(format!("❌ {}", e), NotificationType::FileOperation),
+                },
+                None => self.add_notification("⚠️ This tab has not been saved yet".to_string(), NotificationType::Info),
+            },
+        }
+    }
+
+    fn toggle_pin(&mut self, index: usize) {
+        let name = self.editor.tabs.get(index).map(|tab| tab.file_name.clone()).unwrap_or_default();
+        match self.editor.toggle_pin(index) {
+            Some(true) => self.add_notification(format!("📌 Pinned {}", name), NotificationType::Info),
+            Some(false) => self.add_notification(format!("📌 Unpinned {}", name), NotificationType::Info),
+            None => {}
+        }
+    }
+
+    fn show_language_picker(&mut self) {
+        match self.editor.get_current_tab() {
+            Some(tab) => {
+                let detected = tab.detected_language().name;
+                self.language_picker.open(detected);
+            }
+            None => self.add_notification("⚠️ Open a file to set its language".to_string(), NotificationType::Info),
+        }
+    }
+
+    fn handle_language_picker_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar(c) => self.language_picker.add_char(c),
+            IdeEvent::Backspace => self.language_picker.backspace(),
+            IdeEvent::NavigateUp => self.language_picker.select_previous(),
+            IdeEvent::NavigateDown => self.language_picker.select_next(),
+            IdeEvent::NormalMode | IdeEvent::SetLanguage => self.language_picker.close(),
+            IdeEvent::Quit => self.request_quit(),
+            IdeEvent::Enter | IdeEvent::Select => {
+                let selected = self.language_picker.get_selected();
+                self.language_picker.close();
+                let (Some(choice), Some(tab)) = (selected, self.editor.get_current_tab_mut()) else {
+                    return;
+                };
+                tab.language_override = choice.map(|language| language.id);
+                let message = format!("🏷️ '{}' is now {}", tab.file_name, tab.language().name);
+                self.add_notification(message, NotificationType::Info);
+            }
+            _ => {}
+        }
+    }
+
+    /// Open the model picker and fetch the provider's models, from the cache unless `refresh`
+    fn show_model_picker(&mut self, refresh: bool) {
+        if !self.model_picker.is_open {
+            self.model_picker.open(self.config.get_model());
+        }
+        let llm = self.llm.clone();
+        let endpoint = provider::endpoint(&self.config);
+        if let Some(previous) = self.model_list.take() {
+            previous.abort();
+        }
+        self.model_list = Some(tokio::spawn(async move { provider::available_models(llm.as_ref(), &endpoint, refresh).await }));
+    }
+
+    /// Bring the workspace's embeddings index up to date in the background, at most once a minute
+    fn refresh_vector_index(&mut self) {
+        let recent = self.indexed_at.is_some_and(|at| at.elapsed() < std::time::Duration::from_secs(60));
+        if !self.config.retrieval.enabled || self.indexing.is_some() || recent {
+            return;
+        }
+        self.indexed_at = Some(std::time::Instant::now());
+        let llm = self.llm.clone();
+        let root = self.current_directory.clone();
+        let settings = self.config.retrieval.clone();
+        let index = self.vector_index.clone();
+        self.indexing = Some(tokio::spawn(async move {
+            let path = VectorIndex::path_for(&root)?;
+            let mut index = match index {
+                Some(index) => Arc::unwrap_or_clone(index), // Copies the file list; the chunks are shared
+                None => {
+                    let (path, model) = (path.clone(), settings.model.clone());
+                    tokio::task::spawn_blocking(move || VectorIndex::load(&path, &model)).await?
+                }
+            };
+            let (embedded, result) = index.update(&root, llm.as_ref(), &settings).await;
+            if embedded > 0 {
+                // Also when a batch failed, so the files embedded before it needn't be again
+                index = tokio::task::spawn_blocking(move || index.save(&path).map(|_| index)).await??;
+            }
+            result?;
+            Ok((index, embedded))
+        }));
+    }
+
+    /// Have the model condense the oldest turns once the conversation nears its token limit, so
+    /// they are summarized rather than dropped
+    fn summarize_history(&mut self) {
+        if !self.config.history.summarize || self.summary.is_some() {
+            return;
+        }
+        let Some(count) = self.conversation.summarizable() else {
+            return;
+        };
+        let messages = self.conversation.summary_request(count);
+        let (client, model) = (self.llm.clone(), self.model().to_string());
+        self.spend_guard.record_request(std::time::Instant::now());
+        let task = tokio::spawn(async move { client.chat(&model, messages, 0.2, &[]).await });
+        self.summary = Some(PendingSummary { session: self.saved_session.id.clone(), trims: self.conversation.trims(), count, task });
+    }
+
+    /// Called from the main loop: put a finished summary in place of the turns it condenses
+    pub async fn poll_history_summary(&mut self) {
+        if !self.summary.as_ref().is_some_and(|summary| summary.task.is_finished()) {
+            return;
+        }
+        let Some(summary) = self.summary.take() else {
+            return;
+        };
+        let text = match summary.task.await {
+            Ok(Ok(response)) => {
+                self.spend_guard.record_tokens(response.usage.total_tokens);
+                response.choices.into_iter().next().and_then(|choice| choice.message.content)
+            }
+            Ok(Err(e)) => {
+                self.add_notification(format!("⚠️ Could not summarize the earlier conversation: {}", e), NotificationType::Info);
+                None
+            }
+            Err(_) => None,
+        };
+        // The chat was cleared, switched or trimmed meanwhile
+        let stale = summary.session != self.saved_session.id || summary.trims != self.conversation.trims();
+        if let Some(text) = text.filter(|text| !text.trim().is_empty() && !stale) {
+            self.conversation.replace_with_summary(summary.count, &text);
+            self.sidebar.chat.add_system_message(&format!("🗜️ Summarized {} earlier messages to keep the conversation within its limit", summary.count));
+        }
     }
 
-    pub fn should_quit(&self) -> bool {
-        self.should_quit
+    /// Called from the main loop: start indexing once retrieval is on, and take the index when it is done
+    pub async fn poll_vector_index(&mut self) {
+        if self.indexed_at.is_none() {
+            self.refresh_vector_index();
+        }
+        if !self.indexing.as_ref().is_some_and(|indexing| indexing.is_finished()) {
+            return;
+        }
+        let Some(indexing) = self.indexing.take() else {
+            return;
+        };
+        match indexing.await {
+            Ok(Ok((index, embedded))) => {
+                if embedded > 0 {
+                    let message = format!("🔎 Indexed {} file(s) for code retrieval ({} chunks in all)", embedded, index.chunk_count());
+                    self.add_notification(message, NotificationType::Info);
+                }
+                self.vector_index = Some(Arc::new(index));
+            }
+            Ok(Err(e)) => {
+                // Most likely the provider has no embeddings; don't try again every minute
+                self.config.retrieval.enabled = false;
+                self.add_notification(format!("⚠️ Code retrieval off for this session: {}", e), NotificationType::Info);
+            }
+            Err(_) => {}
+        }
     }
 
-    pub fn quit(&mut self) {
-        self.should_quit = true;
+    /// Called from the main loop: fill the model picker once the list has arrived
+    pub async fn poll_model_list(&mut self) {
+        if !self.model_list.as_ref().is_some_and(|request| request.is_finished()) {
+            return;
+        }
+        let Some(request) = self.model_list.take() else {
+            return;
+        };
+        match request.await {
+            Ok(Ok(models)) => self.model_picker.set_models(models),
+            Ok(Err(e)) => self.model_picker.status = Some(format!("❌ {}", e)),
+            Err(_) => {}
+        }
     }
 
-    pub fn toggle_help(&mut self) {
-        self.show_help = !self.show_help;
+    /// Called from the main loop: write the file once its formatter has finished, unless the
+    /// buffer was edited meanwhile
+    pub async fn poll_formatting(&mut self) {
+        if !self.formatting.as_ref().is_some_and(|(_, _, run)| run.is_finished()) {
+            return;
+        }
+        let Some((tab_id, text, run)) = self.formatting.take() else {
+            return;
+        };
+        let formatted = run.await.unwrap_or_else(|e| Err(anyhow::anyhow!("{}", e)));
+        let Some(tab) = self.editor.tabs.iter_mut().find(|tab| tab.id == tab_id) else {
+            return;
+        };
+        if tab.format_job(&self.config.save_hooks).is_none_or(|(_, current)| current != text) {
+            self.add_notification(
+                "⚠️ Not saved - the file was edited while formatting; save again".to_string(),
+                NotificationType::FileOperation
+            );
+            return;
+        }
+        let saved = tab.write(Some(formatted));
+        self.report_save(saved);
     }
 
-    pub fn toggle_command_help(&mut self) {
-        self.show_command_help = !self.show_command_help;
+    fn report_save(&mut self, saved: Result<Option<String>>) {
+        match saved {
+            Ok(None) => self.add_notification("💾 File saved successfully".to_string(), NotificationType::FileOperation),
+            Ok(Some(formatter_error)) => self.add_notification(
+                format!("⚠️ File saved unformatted - formatter failed: {}", formatter_error),
+                NotificationType::FileOperation
+            ),
+            Err(e) => self.add_notification(format!("❌ Save failed: {}", e), NotificationType::FileOperation),
+        }
     }
 
-    pub fn toggle_api_config(&mut self) {
-        self.show_api_config = !self.show_api_config;
+    fn handle_prompt_search_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar(c) => self.prompt_search.add_char(c),
+            IdeEvent::Backspace => self.prompt_search.backspace(),
+            IdeEvent::NavigateUp => self.prompt_search.select_previous(),
+            IdeEvent::NavigateDown => self.prompt_search.select_next(&self.prompt_history),
+            IdeEvent::MouseScroll(delta) if delta < 0 => self.prompt_search.select_previous(),
+            IdeEvent::MouseScroll(_) => self.prompt_search.select_next(&self.prompt_history),
+            IdeEvent::NormalMode | IdeEvent::SearchPromptHistory => self.prompt_search.close(),
+            IdeEvent::Quit => self.request_quit(),
+            IdeEvent::Enter | IdeEvent::Select => {
+                let selected = self.prompt_search.get_selected(&self.prompt_history);
+                self.prompt_search.close();
+                if let Some(prompt) = selected {
+                    self.sidebar.chat.set_input(&prompt);
+                    self.focus_panel(FocusedPanel::Chat);
+                }
+            }
+            _ => {}
+        }
     }
 
-    pub fn set_mode(&mut self, mode: AppMode) {
-        self.mode = mode;
+    /// Up and Down in the empty chat input step through the prompts sent before, like a shell's
+    /// history; otherwise they scroll the chat
+    fn recall_prompt(&mut self, older: bool) {
+        let chat = &mut self.sidebar.chat;
+        let prompt = match (chat.editing.is_some(), older) {
+            (true, _) => None,
+            (false, true) => self.prompt_history.older(&chat.input),
+            (false, false) => self.prompt_history.newer(&chat.input),
+        };
+        match prompt.map(str::to_string) {
+            Some(prompt) => chat.set_input(&prompt),
+            None if older => chat.scroll_up(),
+            None => chat.scroll_down(),
+        }
     }
 
-    pub fn toggle_agentic_mode(&mut self) {
-        self.mode = match self.mode {
-            AppMode::Agentic => AppMode::Normal,
-            _ => AppMode::Agentic,
+    fn handle_model_picker_event(&mut self, event: IdeEvent) {
+        match event {
+            IdeEvent::InsertChar(c) => self.model_picker.add_char(c),
+            IdeEvent::Backspace => self.model_picker.backspace(),
+            IdeEvent::NavigateUp => self.model_picker.select_previous(),
+            IdeEvent::NavigateDown => self.model_picker.select_next(),
+            IdeEvent::Tab => {
+                self.model_picker.status = Some("⏳ Loading models…".to_string());
+                self.show_model_picker(true);
+            }
+            IdeEvent::NormalMode | IdeEvent::SelectModel => self.model_picker.close(),
+            IdeEvent::Quit => self.request_quit(),
+            IdeEvent::Enter | IdeEvent::Select => {
+                let selected = self.model_picker.get_selected();
+                self.model_picker.close();
+                let Some(model) = selected else {
+                    return;
+                };
+                match self.config.set_model(model.clone()) {
+                    Ok(()) => self.add_notification(format!("🤖 Default model is now {}", model), NotificationType::Info),
+                    Err(e) => self.add_notification(format!("❌ Failed to save config: {}", e), NotificationType::FileOperation),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn toggle_comment(&mut self) {
+        let Some(tab) = self.editor.get_current_tab_mut() else {
+            return;
         };
+        if !tab.toggle_comment() {
+            let message = format!("⚠️ {} has no line comments - set the file's language from the status bar", tab.language().name);
+            self.add_notification(message, NotificationType::Info);
+        }
     }
 
-    pub fn focus_panel(&mut self, panel: FocusedPanel) {
-        self.focused_panel = panel;
+    /// Show each mark's letter in the gutter next to its line
+    fn refresh_mark_signs(&mut self) {
+        self.editor.signs = self.marks.iter().map(|(name, mark)| (mark.path.clone(), mark.line, name)).collect();
     }
 
-    pub fn cycle_focus(&mut self) {
-        // Only include Notifications in cycling if they're visible
-        self.focused_panel = match self.focused_panel {
-            FocusedPanel::FileExplorer => FocusedPanel::Editor,
-            FocusedPanel::Editor => {
-                if self.show_notifications && !self.notifications.is_empty() {
-                    FocusedPanel::Notifications
-                } else {
-                    FocusedPanel::Chat
-                }
-            },
-            FocusedPanel::Notifications => FocusedPanel::Chat,
-            FocusedPanel::Chat => FocusedPanel::FileExplorer,
+    /// Open the marked file if needed and put the cursor back on the mark
+    fn jump_to_mark(&mut self, name: char) -> Result<()> {
+        let Some(mark) = self.marks.get(name).cloned() else {
+            self.add_notification(format!("⚠️ Mark '{}' is not set", name), NotificationType::Info);
+            return Ok(());
         };
+        if let Err(e) = self.open_in_editor(mark.path.clone()) {
+            self.add_notification(format!("⚠️ Cannot jump to mark '{}': {}", name, e), NotificationType::FileOperation);
+            return Ok(());
+        }
+        self.editor.move_cursor_to(mark.line, mark.col);
+        self.focus_panel(FocusedPanel::Editor);
+        Ok(())
     }
 
-    pub fn resize_sidebar(&mut self, delta: i16) {
-        let new_width = (self.layout.sidebar_width as i16 + delta).max(self.layout.min_sidebar_width as i16);
-        self.layout.sidebar_width = (new_width as u16).min(self.layout.max_sidebar_width);
+    fn show_marks(&mut self) {
+        let entries = self
+            .marks
+            .iter()
+            .map(|(name, mark)| {
+                let path = mark.path.strip_prefix(&self.current_directory).unwrap_or(&mark.path);
+                marks::MarkEntry {
+                    name,
+                    location: format!("{}:{}:{}", path.display(), mark.line + 1, mark.col + 1),
+                    preview: self.line_text(&mark.path, mark.line).unwrap_or_default(),
+                }
+            })
+            .collect();
+        self.mark_picker.open(entries);
     }
 
-    pub fn resize_chat(&mut self, delta: i16) {
-        let new_height = (self.layout.chat_height as i16 + delta).max(self.layout.min_chat_height as i16);
-        self.layout.chat_height = (new_height as u16).min(25); // Max 25 lines for chat
+    /// List the `path:line` references of the chat and notifications that point at existing files, newest first
+    fn show_file_refs(&mut self) {
+        let chat = self.sidebar.chat.messages.iter().rev().map(|msg| (msg.text(), "chat"));
+        let notifications = self.notifications.iter().rev().map(|n| (n.message.as_str(), "notification"));
+        let mut entries: Vec<file_refs::RefEntry> = Vec::new();
+        for (text, source) in chat.chain(notifications) {
+            for file_ref in file_refs::find(text) {
+                if file_ref.resolve(&self.current_directory).is_some()
+                    && !entries.iter().any(|entry| entry.file_ref == file_ref)
+                {
+                    entries.push(file_refs::RefEntry { file_ref, source });
+                }
+            }
+        }
+        self.ref_picker.open(entries);
     }
 
-    pub fn resize_notifications(&mut self, delta: i16) {
-        let new_height = (self.layout.notification_height as i16 + delta).max(self.layout.min_notification_height as i16);
-        self.layout.notification_height = (new_height as u16).min(15); // Max 15 lines for notifications
+    /// Open the first reference in `text` that names an existing file; false if there is none
+    fn open_first_file_ref(&mut self, text: &str) -> Result<bool> {
+        let found = file_refs::find(text)
+            .into_iter()
+            .find(|file_ref| file_ref.resolve(&self.current_directory).is_some());
+        match found {
+            Some(file_ref) => self.open_file_ref(&file_ref).map(|()| true),
+            None => Ok(false),
+        }
     }
 
-    pub fn update_component_areas(&mut self, 
-        file_explorer_area: ratatui::layout::Rect,
-        notification_area: ratatui::layout::Rect,
-        chat_area: ratatui::layout::Rect,
-        editor_area: ratatui::layout::Rect
-    ) {
-        self.layout.file_explorer_area = file_explorer_area;
-        self.layout.notification_area = notification_area;
-        self.layout.chat_area = chat_area;
-        self.layout.editor_area = editor_area;
+    /// Open a `path:line[:col]` reference with the cursor on that spot
+    fn open_file_ref(&mut self, file_ref: &file_refs::FileRef) -> Result<()> {
+        let Some(path) = file_ref.resolve(&self.current_directory) else {
+            self.add_notification(format!("⚠️ File not found: {}", file_ref.path), NotificationType::FileOperation);
+            return Ok(());
+        };
+        if let Err(e) = self.open_in_editor(path) {
+            self.add_notification(format!("❌ Cannot open {}: {}", file_ref.label(), e), NotificationType::FileOperation);
+            return Ok(());
+        }
+        self.editor.move_cursor_to(file_ref.line - 1, file_ref.col.unwrap_or(1) - 1);
+        self.focus_panel(FocusedPanel::Editor);
+        Ok(())
     }
 
-    pub fn show_create_file_dialog(&mut self) {
-        self.show_create_file_dialog = true;
-        self.dialog_input.clear();
+    /// A line of a file, from its open buffer if there is one, else from disk
+    fn line_text(&self, path: &std::path::Path, line: usize) -> Option<String> {
+        match self.editor.tabs.iter().find(|tab| tab.file_path.as_deref() == Some(path)) {
+            Some(tab) => tab.buffer.line(line),
+            None => std::fs::read_to_string(path).ok()?.lines().nth(line).map(str::to_string),
+        }
     }
 
-    pub fn show_create_folder_dialog(&mut self) {
-        self.show_create_folder_dialog = true;
-        self.dialog_input.clear();
+    /// Compare the current buffer with its saved file in the diff overlay
+    fn show_diff(&mut self) {
+        let Some(tab) = self.editor.get_current_tab() else {
+            self.add_notification("⚠️ No file open to diff".to_string(), NotificationType::Info);
+            return;
+        };
+        match tab.disk_text() {
+            Ok(disk) => {
+                let title = format!("{}: saved ↔ buffer", tab.file_name);
+                let buffer = tab.buffer.to_string();
+                self.open_diff(title, &disk, &buffer, "✅ No changes against the saved file");
+            }
+            Err(e) => self.add_notification(format!("⚠️ Cannot diff: {}", e), NotificationType::FileOperation),
+        }
     }
 
-    pub fn show_rename_dialog(&mut self, target_path: PathBuf) {
-        self.show_rename_dialog = true;
-        self.operation_target = Some(target_path.clone());
-        // Pre-populate with current filename
-        self.dialog_input = target_path.file_name()
-            .and_then(|name| name.to_str())
-            .unwrap_or("")
-            .to_string();
+    /// Compare the current buffer with the file as it was last committed
+    fn show_git_diff(&mut self) {
+        let Some(tab) = self.editor.get_current_tab() else {
+            self.add_notification("⚠️ No file open to diff".to_string(), NotificationType::Info);
+            return;
+        };
+        let Some(path) = tab.file_path.clone() else {
+            self.add_notification("⚠️ Save the file before comparing it with git".to_string(), NotificationType::Info);
+            return;
+        };
+        let dir = path.parent().map(PathBuf::from).unwrap_or_else(|| self.current_directory.clone());
+        let output = std::process::Command::new("git")
+            .arg("-C")
+            .arg(&dir)
+            .arg("show")
+            .arg(format!("HEAD:./{}", tab.file_name))
+            .output();
+        match output {
+            Ok(output) if output.status.success() => {
+                let title = format!("{}: HEAD ↔ buffer", tab.file_name);
+                let buffer = tab.buffer.to_string();
+                self.open_diff(title, &String::from_utf8_lossy(&output.stdout), &buffer, "✅ No changes since the last commit");
+            }
+            Ok(output) => self.add_notification(
+                format!("⚠️ Cannot diff against git: {}", String::from_utf8_lossy(&output.stderr).trim()),
+                NotificationType::FileOperation
+            ),
+            Err(e) => self.add_notification(format!("⚠️ Cannot run git: {}", e), NotificationType::FileOperation),
+        }
     }
 
-    pub fn hide_all_dialogs(&mut self) {
-        self.show_create_file_dialog = false;
-        self.show_create_folder_dialog = false;
-        self.show_rename_dialog = false;
-        self.dialog_input.clear();
-        self.operation_target = None;
+    /// Compare the tabs shown in the two editor panes
+    fn compare_panes(&mut self) {
+        let Some(other) = self.editor.other_pane_tab_index() else {
+            self.add_notification("⚠️ Split the editor (Ctrl+\\) with a different file in each pane to compare them".to_string(), NotificationType::Info);
+            return;
+        };
+        let (Some(left), Some(right)) = (self.editor.tabs.get(other), self.editor.get_current_tab()) else {
+            return;
+        };
+        let title = format!("{} ↔ {}", left.file_name, right.file_name);
+        let (old, new) = (left.buffer.to_string(), right.buffer.to_string());
+        self.open_diff(title, &old, &new, "✅ The two files are identical");
     }
 
-    pub fn has_active_dialog(&self) -> bool {
-        self.show_create_file_dialog || self.show_create_folder_dialog || self.show_rename_dialog
+    fn open_diff(&mut self, title: String, old: &str, new: &str, unchanged_message: &str) {
+        if self.diff_view.open(title, old, new) == 0 {
+            self.diff_view.close();
+            self.add_notification(unchanged_message.to_string(), NotificationType::FileOperation);
+        }
+    }
+
+    async fn handle_emoji_picker_event(&mut self, event: IdeEvent) -> Result<()> {
+        match event {
+            IdeEvent::InsertChar(c) => self.emoji_picker.add_char(c),
+            IdeEvent::Backspace => self.emoji_picker.backspace(),
+            IdeEvent::NavigateUp => self.emoji_picker.select_previous(),
+            IdeEvent::NavigateDown => self.emoji_picker.select_next(),
+            IdeEvent::NormalMode | IdeEvent::ToggleEmojiPicker => self.emoji_picker.close(),
+            IdeEvent::Quit => self.request_quit(),
+            IdeEvent::Enter | IdeEvent::Select => {
+                if let Some(symbol) = self.emoji_picker.get_selected() {
+                    self.insert_or_copy(symbol, "😀");
+                }
+                self.emoji_picker.close();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Insert text at the cursor of the focused editor or chat input, falling back to the clipboard
+    fn insert_or_copy(&mut self, text: &str, icon: &str) {
+        match self.focused_panel {
+            FocusedPanel::Editor if self.editor.has_open_files() => {
+                for c in text.chars() {
+                    self.editor.insert_char(c);
+                }
+                self.add_notification(format!("{} Inserted {}", icon, text), NotificationType::Info);
+            }
+            FocusedPanel::Chat => {
+                self.sidebar.chat.insert_str(text);
+                self.add_notification(format!("{} Inserted {}", icon, text), NotificationType::Info);
+            }
+            _ => match self.clipboard.set_text(text) {
+                Ok(()) => self.add_notification(format!("{} Copied {} to clipboard", icon, text), NotificationType::Info),
+                Err(e) => self.add_notification(format!("❌ {}", e), NotificationType::FileOperation),
+            },
+        }
     }
 
     pub fn add_notification(&mut self, message: String, notification_type: NotificationType) {
@@ -282,11 +3221,16 @@ impl IdeApp {
             notification_type,
         };
         
+        // Hover events fire on every mouse move and would bury everything else
+        if notification.notification_type != NotificationType::MouseHover {
+            self.notification_archive.push(&notification.message, notification.notification_type);
+        }
+
         self.notifications.push(notification);
         self.show_notifications = true;
         
-        // Keep only the last 10 notifications to prevent memory buildup
-        if self.notifications.len() > 10 {
+        // The live panel keeps only the latest few; the rest are in the history overlay
+        if self.notifications.len() > self.config.notification_history.max(1) {
             self.notifications.remove(0);
         }
     }
@@ -406,59 +3350,36 @@ impl IdeApp {
             return (false, 0, 0);
         }
 
-        // Tab area is inside the editor border now
-        let main_area_start_x = self.layout.sidebar_width + 1; // +1 for editor's left border
-        let tab_y = 1; // Row 1 is the tab row inside the editor border (0-based, so 1 = inside top border)
+        // The focused pane's tab row, inside the editor border, as last drawn
+        let area = self.editor.tab_bar_area;
+        let result = x >= area.x && x < area.right() && y == area.y;
 
-        // Tab area is specifically at row 2 inside the editor border
-        let result = x >= main_area_start_x && y == tab_y;
-        
-        (result, main_area_start_x, tab_y)
+        (result, area.x, area.y)
     }
 
-    fn get_tab_click_info(&self, x: u16, y: u16) -> Option<(usize, bool)> {
-        use crate::ide::layout;
-        use ratatui::layout::Rect;
-
-        let (is_in_tab_area, expected_x, expected_y) = self.is_click_in_tab_area(x, y);
+    /// Part of the tab bar under the mouse
+    fn get_tab_bar_item(&self, x: u16, y: u16) -> Option<editor::TabBarItem> {
+        let (is_in_tab_area, tab_x, _) = self.is_click_in_tab_area(x, y);
         if !is_in_tab_area {
             return None;
         }
-
-        // Create a rect representing the editor area (function will add +1 for tab position inside border)
-        let editor_area = Rect::new(self.layout.sidebar_width, 0, 200, 20); // Editor area starts after sidebar at y=0
-        layout::get_tab_click_info(self, x, y, editor_area)
+        self.editor.tab_bar(self.editor.tab_bar_area.width).item_at(x - tab_x)
     }
 
-    fn get_tab_index_from_x(&self, x: u16) -> Option<usize> {
-        let tabs = self.editor.get_tab_info();
-        if tabs.is_empty() {
-            return None;
+    /// Tab index under the mouse (`usize::MAX` for the new-tab button) and whether it is the close button
+    fn get_tab_click_info(&self, x: u16, y: u16) -> Option<(usize, bool)> {
+        match self.get_tab_bar_item(x, y)? {
+            editor::TabBarItem::Tab(index) => Some((index, false)),
+            editor::TabBarItem::NewTab => Some((usize::MAX, false)),
+            editor::TabBarItem::ScrollLeft | editor::TabBarItem::ScrollRight => None,
         }
+    }
 
-        let mut current_x = self.layout.sidebar_width;
-        for (i, tab) in tabs.iter().enumerate() {
-            let is_modified = tab.is_modified;
-            let modified_indicator = if is_modified { "●" } else { "" };
-            let close_button = " ✕";
-            let tab_text = format!(" {} {}{}{} ",
-                crate::ide::layout::get_file_icon(&tab.file_name),
-                tab.file_name,
-                modified_indicator,
-                close_button
-            );
-
-            let tab_width = tab_text.len() as u16;
-            let tab_end_x = current_x + tab_width;
-
-            if x >= current_x && x < tab_end_x {
-                return Some(i);
-            }
-
-            current_x = tab_end_x + 1; // +1 for separator "│"
+    fn get_tab_index_from_x(&self, x: u16) -> Option<usize> {
+        match self.get_tab_bar_item(x, self.editor.tab_bar_area.y)? {
+            editor::TabBarItem::Tab(index) => Some(index),
+            _ => None,
         }
-
-        None
     }
 
     fn is_folder_expanded(&self, target_path: &std::path::Path) -> bool {
@@ -480,7 +3401,7 @@ impl IdeApp {
                         format!("📄 File '{}' created successfully", self.dialog_input),
                         NotificationType::FileOperation
                     );
-                    self.editor.open_file(file_path)?;
+                    self.open_in_editor(file_path)?;
                     self.focus_panel(FocusedPanel::Editor);
                 }
                 Err(e) => {
@@ -508,7 +3429,9 @@ impl IdeApp {
         } else if self.show_rename_dialog {
             if let Some(old_path) = &self.operation_target.clone() {
                 match self.sidebar.file_explorer.rename_file(old_path, &self.dialog_input) {
-                    Ok(_) => {
+                    Ok(new_path) => {
+                        self.marks.rename_path(old_path, &new_path);
+                        self.refresh_mark_signs();
                         self.add_notification(
                             format!("✏️ Renamed to '{}'", self.dialog_input),
                             NotificationType::FileOperation
@@ -522,21 +3445,312 @@ impl IdeApp {
                     }
                 }
             }
+        } else if self.show_save_workflow_dialog {
+            let name = self.dialog_input.trim().to_string();
+            self.save_workflow(name);
+        }
+
+        self.hide_all_dialogs();
+        Ok(())
+    }
+
+    pub async fn handle_event(&mut self, event: IdeEvent) -> Result<()> {
+        let before = self.editor.get_current_tab().map(|tab| (tab.id, tab.buffer.len_lines(), tab.cursor_line));
+        let result = self.dispatch_event(event).await;
+        self.shift_marks(before);
+        result
+    }
+
+    /// Marks below an edit that added or removed lines move with their lines
+    fn shift_marks(&mut self, before: Option<(u32, usize, usize)>) {
+        let (Some((tab_id, lines, cursor_line)), Some(tab)) = (before, self.editor.get_current_tab()) else {
+            return;
+        };
+        let (Some(path), true) = (&tab.file_path, tab.id == tab_id && tab.buffer.len_lines() != lines) else {
+            return;
+        };
+        let delta = tab.buffer.len_lines() as isize - lines as isize;
+        if self.marks.shift_lines(path, cursor_line.min(tab.cursor_line), delta) {
+            self.refresh_mark_signs();
+        }
+    }
+
+    async fn dispatch_event(&mut self, event: IdeEvent) -> Result<()> {
+        if self.show_session_lock_dialog {
+            self.handle_session_lock_event(event);
+            return Ok(());
+        }
+
+        if self.show_recovery_dialog {
+            self.handle_recovery_event(event);
+            return Ok(());
+        }
+
+        if self.show_unsaved_dialog {
+            self.handle_unsaved_event(event);
+            return Ok(());
+        }
+
+        if self.show_external_change_dialog {
+            self.handle_external_change_event(event);
+            return Ok(());
+        }
+
+        if self.show_long_line_dialog {
+            self.handle_long_line_event(event);
+            return Ok(());
+        }
+
+        if self.spend_pause.is_some() {
+            self.handle_spend_pause_event(event);
+            return Ok(());
+        }
+
+        if self.show_workflow_picker {
+            self.handle_workflow_picker_event(event);
+            return Ok(());
+        }
+
+        if self.command_palette.is_open {
+            return self.handle_palette_event(event).await;
+        }
+
+        if self.diff_view.is_open {
+            self.handle_diff_view_event(event);
+            return Ok(());
+        }
+
+        if self.plan_editor.is_open {
+            return self.handle_plan_editor_event(event).await;
+        }
+
+        if self.approval.is_open {
+            self.handle_approval_event(event);
+            return Ok(());
+        }
+
+        if self.model_picker.is_open {
+            self.handle_model_picker_event(event);
+            return Ok(());
+        }
+
+        if self.language_picker.is_open {
+            self.handle_language_picker_event(event);
+            return Ok(());
+        }
+
+        if self.tab_menu.is_some() {
+            self.handle_tab_menu_event(event);
+            return Ok(());
+        }
+
+        if self.mark_picker.is_open {
+            return self.handle_mark_picker_event(event);
+        }
+
+        if self.ref_picker.is_open {
+            return self.handle_ref_picker_event(event);
+        }
+
+        if self.notification_history.is_open {
+            return self.handle_notification_history_event(event);
+        }
+
+        if self.tasks_view.is_open {
+            let count = self.tasks.tasks().len();
+            match event {
+                IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => self.tasks_view.select(-1, count),
+                IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => self.tasks_view.select(1, count),
+                IdeEvent::InsertChar('x') => self.cancel_selected_task(),
+                IdeEvent::InsertChar('c') => self.tasks.clear_finished(),
+                IdeEvent::NormalMode | IdeEvent::InsertChar('q') | IdeEvent::ShowAgentTasks => self.tasks_view.close(),
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if self.notes_view.is_open {
+            match event {
+                IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => self.notes_view.scroll(-1),
+                IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => self.notes_view.scroll(1),
+                IdeEvent::MouseScroll(delta) => self.notes_view.scroll(delta as isize * 3),
+                IdeEvent::NormalMode | IdeEvent::InsertChar('q') | IdeEvent::ShowAgentNotes => self.notes_view.close(),
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if self.keymap_view.is_open {
+            match event {
+                IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => self.keymap_view.scroll(-1),
+                IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => self.keymap_view.scroll(1),
+                IdeEvent::MouseScroll(delta) => self.keymap_view.scroll(delta as isize * 3),
+                IdeEvent::NormalMode | IdeEvent::InsertChar('q') | IdeEvent::ShowKeymap => self.keymap_view.close(),
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if self.settings_panel.is_open {
+            self.handle_settings_event(event);
+            return Ok(());
+        }
+
+        if self.session_browser.is_open {
+            match event {
+                IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => self.session_browser.move_selection(-1),
+                IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => self.session_browser.move_selection(1),
+                IdeEvent::MouseScroll(delta) => self.session_browser.move_selection(delta as isize),
+                IdeEvent::Enter | IdeEvent::Select => {
+                    if let Some(session) = self.session_browser.take_selected() {
+                        self.resume_session(session);
+                    }
+                }
+                IdeEvent::NormalMode | IdeEvent::InsertChar('q') | IdeEvent::ShowSessions => self.session_browser.close(),
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if self.branch_view.is_open {
+            match event {
+                IdeEvent::NavigateUp | IdeEvent::InsertChar('k') => self.branch_view.move_selection(-1),
+                IdeEvent::NavigateDown | IdeEvent::InsertChar('j') => self.branch_view.move_selection(1),
+                IdeEvent::MouseScroll(delta) => self.branch_view.move_selection(delta as isize),
+                IdeEvent::Enter | IdeEvent::Select => {
+                    if let Some(row) = self.branch_view.take_selected() {
+                        self.open_branch(row);
+                    }
+                }
+                IdeEvent::NormalMode | IdeEvent::InsertChar('q') | IdeEvent::ShowBranches => self.branch_view.close(),
+                IdeEvent::Quit => self.request_quit(),
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        if self.prompt_search.is_open {
+            self.handle_prompt_search_event(event);
+            return Ok(());
+        }
+
+        if self.emoji_picker.is_open {
+            return self.handle_emoji_picker_event(event).await;
+        }
+
+        if self.show_api_config {
+            self.handle_api_config_event(event);
+            return Ok(());
+        }
+
+        // Esc stops the agent while it waits for the model, or else the background questions
+        if (self.requests.conversation().is_some() || self.requests.has_background()) && matches!(event, IdeEvent::NormalMode) {
+            self.cancel_ai_request();
+            return Ok(());
         }
 
-        self.hide_all_dialogs();
-        Ok(())
-    }
+        // ... and kills the command it is running
+        if self.action_batch.is_some() && matches!(event, IdeEvent::NormalMode) {
+            self.stop_agent_command();
+            self.add_notification("⏹️ Stopping the agent's command".to_string(), NotificationType::Info);
+            return Ok(());
+        }
+
+        if self.focused_panel == FocusedPanel::Chat && !self.has_active_dialog() && self.handle_chat_completion_event(&event) {
+            return Ok(());
+        }
 
-    pub async fn handle_event(&mut self, event: IdeEvent) -> Result<()> {
         match event {
-            IdeEvent::Quit => self.quit(),
+            IdeEvent::Quit => self.request_quit(),
+            IdeEvent::ToggleCommandPalette => self.command_palette.open(),
+            IdeEvent::ToggleEmojiPicker => self.emoji_picker.open(),
             
             IdeEvent::ToggleHelp => self.toggle_help(),
             IdeEvent::ToggleCommandHelp => self.toggle_command_help(),
+            IdeEvent::ShowKeymap => self.keymap_view.open(),
+            IdeEvent::ShowSettings => self.settings_panel.open(),
+            IdeEvent::ShowSessions => self.show_sessions(),
+            IdeEvent::ShowBranches => self.show_branches(),
+            IdeEvent::NewCheckpoint => {
+                self.sidebar.chat.insert_str("/checkpoint ");
+                self.focus_panel(FocusedPanel::Chat);
+            }
+            IdeEvent::PinLastAnswer => self.pin_command(""),
+            IdeEvent::EditProjectMemory => self.edit_project_memory(),
+            IdeEvent::NextThread => self.switch_thread(self.threads.step(1)),
+            IdeEvent::PreviousThread => self.switch_thread(self.threads.step(-1)),
+            IdeEvent::NewThread => {
+                self.sidebar.chat.insert_str("/thread new ");
+                self.focus_panel(FocusedPanel::Chat);
+            }
+            IdeEvent::SearchPromptHistory => {
+                self.prompt_search.open();
+                self.focus_panel(FocusedPanel::Chat);
+            }
+            IdeEvent::EditPreviousMessage => {
+                if self.sidebar.chat.edit_previous() {
+                    self.focus_panel(FocusedPanel::Chat);
+                } else {
+                    self.add_notification("✏️ No earlier message to edit in this conversation".to_string(), NotificationType::Info);
+                }
+            }
             IdeEvent::ShowApiConfig => self.toggle_api_config(),
             IdeEvent::ToggleAgenticMode => self.toggle_agentic_mode(),
+            IdeEvent::UndoAgentChange => self.undo_agent_changes(false),
+            IdeEvent::UndoAgentSession => self.undo_agent_changes(true),
+            IdeEvent::CycleAutonomyLevel => self.cycle_autonomy_level(),
+            IdeEvent::ToggleLowBandwidth => self.toggle_low_bandwidth(),
+            IdeEvent::ToggleDryRun => self.toggle_dry_run(),
+            IdeEvent::RunTestsAndFix => self.run_tests_and_fix(),
+            IdeEvent::CodeCommand(command) => self.run_code_command(command),
+            IdeEvent::CycleLineNumbers => self.cycle_line_numbers(),
+            IdeEvent::ToggleRuler => self.toggle_ruler(),
+            IdeEvent::CycleTheme => self.cycle_theme(),
+            IdeEvent::ToggleWhitespace => self.toggle_whitespace(),
+            IdeEvent::PrettyPrint => self.pretty_print_current(),
+            IdeEvent::SetLanguage => self.show_language_picker(),
+            IdeEvent::ToggleComment => {
+                if self.focused_panel == FocusedPanel::Editor {
+                    self.toggle_comment();
+                }
+            }
             IdeEvent::ClearNotifications => self.clear_notifications(),
+            IdeEvent::Suspend => self.request_suspend(),
+            IdeEvent::ShowDiff => self.show_diff(),
+            IdeEvent::ShowGitDiff => self.show_git_diff(),
+            IdeEvent::ComparePanes => self.compare_panes(),
+            IdeEvent::ShowMarks => self.show_marks(),
+            IdeEvent::ShowFileRefs => self.show_file_refs(),
+            IdeEvent::TogglePinTab => self.toggle_pin(self.editor.active_tab),
+            IdeEvent::ShowNotificationHistory => self.notification_history.open(),
+            IdeEvent::ShowAgentNotes => self.notes_view.open(),
+            IdeEvent::ShowAgentTasks => self.tasks_view.open(),
+            IdeEvent::SelectModel => self.show_model_picker(false),
+            IdeEvent::MoveLineUp | IdeEvent::MoveLineDown | IdeEvent::DuplicateLine | IdeEvent::JoinLines => {
+                if self.focused_panel == FocusedPanel::Editor {
+                    match event {
+                        IdeEvent::MoveLineUp => self.editor.move_line(true),
+                        IdeEvent::MoveLineDown => self.editor.move_line(false),
+                        IdeEvent::DuplicateLine => self.editor.duplicate_line(),
+                        _ => self.editor.join_lines(),
+                    }
+                }
+            }
+            IdeEvent::ToggleSplit => {
+                self.editor.toggle_split();
+                if self.editor.split.is_none() && !self.editor.has_open_files() {
+                    self.add_notification("⚠️ Open a file before splitting the editor".to_string(), NotificationType::Info);
+                }
+            }
+            IdeEvent::FocusOtherPane => {
+                if self.editor.focus_other_pane() {
+                    self.focus_panel(FocusedPanel::Editor);
+                }
+            }
             
             IdeEvent::FocusFileExplorer => self.focus_panel(FocusedPanel::FileExplorer),
             IdeEvent::FocusEditor => self.focus_panel(FocusedPanel::Editor),
@@ -544,10 +3758,22 @@ impl IdeApp {
             IdeEvent::FocusNotifications => self.focus_panel(FocusedPanel::Notifications),
             IdeEvent::CycleFocus => self.cycle_focus(),
             
-            IdeEvent::InsertMode => self.set_mode(AppMode::Insert),
+            IdeEvent::InsertMode => {
+                let read_only = self.focused_panel == FocusedPanel::Editor
+                    && self.editor.get_current_tab().is_some_and(|tab| tab.read_only);
+                if read_only {
+                    self.add_notification("🔒 This file is read-only".to_string(), NotificationType::FileOperation);
+                } else {
+                    self.set_mode(AppMode::Insert);
+                }
+            }
             IdeEvent::NormalMode => {
                 if self.has_active_dialog() {
                     self.hide_all_dialogs();
+                } else if self.focused_panel == FocusedPanel::Chat && self.sidebar.chat.editing.is_some() {
+                    self.sidebar.chat.cancel_edit();
+                } else if self.focused_panel == FocusedPanel::Editor && self.editor.has_selection() {
+                    self.editor.clear_selection();
                 } else {
                     self.set_mode(AppMode::Normal);
                 }
@@ -562,15 +3788,33 @@ impl IdeApp {
             
             // File operations
             IdeEvent::OpenFile(path) => {
-                self.editor.open_file(path)?;
-                self.focus_panel(FocusedPanel::Editor);
+                if !self.queue_external_opener(&path) {
+                    self.open_in_editor(path)?;
+                    self.focus_panel(FocusedPanel::Editor);
+                }
             }
             
             IdeEvent::SaveFile => {
-                if let Err(e) = self.editor.save_current_file() {
-                    self.add_notification(format!("❌ Save failed: {}", e), NotificationType::FileOperation);
+                // Never silently overwrite a file that changed on disk since it was opened
+                let conflict = self.editor
+                    .get_current_tab()
+                    .filter(|tab| tab.changed_on_disk || tab.has_changed_on_disk())
+                    .map(|tab| tab.id);
+                if let Some(tab_id) = conflict {
+                    self.show_external_change_prompt(tab_id);
+                } else if self.formatting.is_some() {
+                    self.add_notification("⏳ Still formatting the last save".to_string(), NotificationType::Info);
+                } else if let Some((tab_id, (command, text))) = self.editor
+                    .get_current_tab()
+                    .and_then(|tab| Some((tab.id, tab.format_job(&self.config.save_hooks)?)))
+                {
+                    // The formatter may be slow; it runs in the background and poll_formatting writes the file
+                    let input = text.clone();
+                    let run = tokio::task::spawn_blocking(move || crate::ide::save_hooks::run_formatter(&command, &input));
+                    self.formatting = Some((tab_id, text, run));
                 } else {
-                    self.add_notification("💾 File saved successfully".to_string(), NotificationType::FileOperation);
+                    let saved = self.editor.save_current_file(&self.config.save_hooks);
+                    self.report_save(saved);
                 }
             }
             
@@ -644,7 +3888,9 @@ impl IdeApp {
             }
             
             IdeEvent::CloseFile => {
-                self.editor.close_current_file();
+                if let Some(tab_id) = self.editor.get_current_tab().map(|tab| tab.id) {
+                    self.request_close_tab(tab_id);
+                }
             }
             
             // Navigation
@@ -652,7 +3898,7 @@ impl IdeApp {
                 match self.focused_panel {
                     FocusedPanel::FileExplorer => self.sidebar.file_explorer.navigate_up(),
                     FocusedPanel::Editor => self.editor.move_cursor_up(),
-                    FocusedPanel::Chat => self.sidebar.chat.scroll_up(),
+                    FocusedPanel::Chat => self.recall_prompt(true),
                     FocusedPanel::Notifications => self.sidebar.notifications.scroll_up(),
                 }
             }
@@ -661,20 +3907,30 @@ impl IdeApp {
                 match self.focused_panel {
                     FocusedPanel::FileExplorer => self.sidebar.file_explorer.navigate_down(),
                     FocusedPanel::Editor => self.editor.move_cursor_down(),
-                    FocusedPanel::Chat => self.sidebar.chat.scroll_down(),
+                    FocusedPanel::Chat => self.recall_prompt(false),
                     FocusedPanel::Notifications => self.sidebar.notifications.scroll_down(self.notifications.len()),
                 }
             }
             
             IdeEvent::NavigateLeft => {
-                if self.focused_panel == FocusedPanel::Editor {
-                    self.editor.move_cursor_left();
+                match self.focused_panel {
+                    FocusedPanel::Editor => self.editor.move_cursor_left(),
+                    FocusedPanel::Chat => {
+                        self.sidebar.chat.move_input_cursor_left();
+                        self.update_chat_completion();
+                    }
+                    _ => {}
                 }
             }
             
             IdeEvent::NavigateRight => {
-                if self.focused_panel == FocusedPanel::Editor {
-                    self.editor.move_cursor_right();
+                match self.focused_panel {
+                    FocusedPanel::Editor => self.editor.move_cursor_right(),
+                    FocusedPanel::Chat => {
+                        self.sidebar.chat.move_input_cursor_right();
+                        self.update_chat_completion();
+                    }
+                    _ => {}
                 }
             }
             
@@ -683,8 +3939,10 @@ impl IdeApp {
                     FocusedPanel::FileExplorer => {
                         if let Some(path) = self.sidebar.file_explorer.get_selected() {
                             if path.is_file() {
-                                self.editor.open_file(path)?;
-                                self.focus_panel(FocusedPanel::Editor);
+                                if !self.queue_external_opener(&path) {
+                                    self.open_in_editor(path)?;
+                                    self.focus_panel(FocusedPanel::Editor);
+                                }
                             } else {
                                 self.sidebar.file_explorer.toggle_expand();
                             }
@@ -706,16 +3964,49 @@ impl IdeApp {
                         }
                         (FocusedPanel::Chat, _) => {
                             self.sidebar.chat.add_char(c);
+                            self.update_chat_completion();
                         }
                         _ => {
                             // In normal mode, certain characters have special meaning
                             if self.focused_panel == FocusedPanel::Editor && self.mode == AppMode::Normal {
-                                match c {
-                                    'i' => self.set_mode(AppMode::Insert),
-                                    'h' => self.editor.move_cursor_left(),
-                                    'j' => self.editor.move_cursor_down(),
-                                    'k' => self.editor.move_cursor_up(),
-                                    'l' => self.editor.move_cursor_right(),
+                                // Multi-key commands (e.g. `gg`) wait for their second key
+                                let pending = self.pending_normal_key.take();
+                                // A count (`5j`) repeats the motion after it; a leading 0 is still "line start"
+                                if pending.is_none() && c.is_ascii_digit() && (c != '0' || self.pending_count.is_some()) {
+                                    let digit = c.to_digit(10).unwrap_or(0) as usize;
+                                    self.pending_count = Some((self.pending_count.unwrap_or(0) * 10 + digit).min(9999));
+                                    return Ok(());
+                                }
+                                let count = self.pending_count.take().unwrap_or(1);
+                                match (pending, c) {
+                                    (Some('g'), 'g') => self.editor.move_buffer_start(),
+                                    (Some('z'), 'a') => self.editor.toggle_fold(),
+                                    (Some('z'), 'R') => self.editor.unfold_all(),
+                                    (Some('>'), '>') => self.editor.indent_line(),
+                                    (Some('<'), '<') => self.editor.dedent_line(),
+                                    // With lines selected `>` and `<` act at once, like Vim's visual mode
+                                    (_, '>') if self.editor.has_selection() => self.editor.indent_line(),
+                                    (_, '<') if self.editor.has_selection() => self.editor.dedent_line(),
+                                    (_, 'V') => self.editor.toggle_line_selection(),
+                                    (Some('m'), name) if marks::Marks::is_valid_name(name) => self.set_mark(name),
+                                    (Some('\''), name) if marks::Marks::is_valid_name(name) => self.jump_to_mark(name)?,
+                                    (_, 'g') | (_, 'z') | (_, '>') | (_, '<') | (_, 'm') | (_, '\'') => self.pending_normal_key = Some(c),
+                                    (_, 'i') => {
+                                        self.editor.clear_selection();
+                                        self.set_mode(AppMode::Insert);
+                                    }
+                                    (_, 'h') => (0..count).for_each(|_| self.editor.move_cursor_left()),
+                                    (_, 'j') => (0..count).for_each(|_| self.editor.move_cursor_down()),
+                                    (_, 'k') => (0..count).for_each(|_| self.editor.move_cursor_up()),
+                                    (_, 'l') => (0..count).for_each(|_| self.editor.move_cursor_right()),
+                                    (_, 'w') => (0..count).for_each(|_| self.editor.move_word_forward()),
+                                    (_, 'b') => (0..count).for_each(|_| self.editor.move_word_backward()),
+                                    (_, 'e') => (0..count).for_each(|_| self.editor.move_word_end()),
+                                    (_, '0') => self.editor.move_line_start(),
+                                    (_, '$') => self.editor.move_line_end(),
+                                    (_, 'G') => self.editor.move_buffer_end(),
+                                    (_, '%') => self.editor.jump_to_matching_bracket(),
+                                    (_, 'J') => self.editor.join_lines(),
                                     _ => {} // Ignore other characters in normal mode
                                 }
                             }
@@ -734,6 +4025,7 @@ impl IdeApp {
                         }
                         FocusedPanel::Chat => {
                             self.sidebar.chat.backspace();
+                            self.update_chat_completion();
                         }
                         _ => {}
                     }
@@ -749,14 +4041,27 @@ impl IdeApp {
                             self.editor.insert_newline();
                         }
                         FocusedPanel::Chat => {
-                            self.send_chat_message(false).await?;
+                            self.send_chat_message(false, false).await?;
+                        }
+                        FocusedPanel::Notifications => {
+                            // Notifications are listed newest first
+                            let selected = self.sidebar.notifications.list_state.selected().unwrap_or(0);
+                            let index = self.notifications.len().checked_sub(selected + 1);
+                            let text = index.and_then(|i| self.notifications.get(i)).map(|n| n.message.clone());
+                            if let Some(text) = text {
+                                if !self.open_first_file_ref(&text)? {
+                                    self.add_notification("⚠️ No file:line reference in this notification".to_string(), NotificationType::Info);
+                                }
+                            }
                         }
                         FocusedPanel::FileExplorer => {
                             // Open file or toggle folder
                             if let Some(path) = self.sidebar.file_explorer.get_selected() {
                                 if path.is_file() {
-                                    self.editor.open_file(path)?;
-                                    self.focus_panel(FocusedPanel::Editor);
+                                    if !self.queue_external_opener(&path) {
+                                        self.open_in_editor(path)?;
+                                        self.focus_panel(FocusedPanel::Editor);
+                                    }
                                 } else {
                                     self.sidebar.file_explorer.toggle_expand();
                                 }
@@ -767,6 +4072,14 @@ impl IdeApp {
                 }
             }
             
+            IdeEvent::SoftNewline => {
+                match self.focused_panel {
+                    FocusedPanel::Chat => self.sidebar.chat.insert_newline(),
+                    FocusedPanel::Editor if self.mode == AppMode::Insert => self.editor.insert_newline(),
+                    _ => {}
+                }
+            }
+            
             // Mouse events
             IdeEvent::MouseMove(x, y) => {
                 self.update_mouse_position(x, y);
@@ -820,6 +4133,25 @@ impl IdeApp {
                 }
             }
 
+            IdeEvent::MouseRightClick(x, y) => {
+                let tab = self.get_tab_click_info(x, y)
+                    .filter(|(index, _)| *index != usize::MAX)
+                    .and_then(|(index, _)| self.editor.get_tab_id_at_index(index).map(|id| (index, id)));
+                if let Some((index, tab_id)) = tab {
+                    self.editor.switch_to_tab(index);
+                    self.tab_menu = Some(TabMenu { tab_id, x, y: y + 1, selected: 0 });
+                }
+            }
+
+            IdeEvent::MouseMiddleClick(x, y) => {
+                let tab_id = self.get_tab_click_info(x, y)
+                    .filter(|(index, _)| *index != usize::MAX)
+                    .and_then(|(index, _)| self.editor.get_tab_id_at_index(index));
+                if let Some(tab_id) = tab_id {
+                    self.request_close_tab(tab_id);
+                }
+            }
+
             IdeEvent::MouseRelease(_x, _y) => {
                 // End tab dragging
                 if self.is_dragging_tab {
@@ -853,6 +4185,11 @@ impl IdeApp {
                     self.layout.notification_area.x, self.layout.notification_area.y
                 ));
 
+                if self.layout.language_label_area.contains(ratatui::layout::Position { x, y }) {
+                    self.show_language_picker();
+                    return Ok(());
+                }
+
                 // First check if click is in tab area
                 let (is_in_tab_area, expected_x, expected_y) = self.is_click_in_tab_area(x, y);
                 self.add_debug_notification(format!(
@@ -862,6 +4199,15 @@ impl IdeApp {
                 
                 if is_in_tab_area {
                     self.add_debug_notification(format!("Click detected in tab area at ({}, {})", x, y));
+                    let scroll = match self.get_tab_bar_item(x, y) {
+                        Some(editor::TabBarItem::ScrollLeft) => -1,
+                        Some(editor::TabBarItem::ScrollRight) => 1,
+                        _ => 0,
+                    };
+                    if scroll != 0 {
+                        self.editor.scroll_tabs(scroll);
+                        return Ok(());
+                    }
                     if let Some((tab_index, is_close_button)) = self.get_tab_click_info(x, y) {
                         // Add debug notification for tab clicks
                         self.add_debug_notification(format!("Tab click: index={}, is_close={}", tab_index, is_close_button));
@@ -874,11 +4220,12 @@ impl IdeApp {
                             
                             // Close the tab
                             if let Some(tab_id) = self.editor.get_tab_id_at_index(tab_index) {
-                                self.editor.close_tab_by_id(tab_id);
-                                self.add_notification(
-                                    format!("{} tab close button clicked", file_name),
-                                    NotificationType::MouseClick
-                                );
+                                if self.request_close_tab(tab_id) {
+                                    self.add_notification(
+                                        format!("{} tab close button clicked", file_name),
+                                        NotificationType::MouseClick
+                                    );
+                                }
                             } else {
                                 self.add_notification(
                                     format!("❌ Failed to get tab ID for index {}", tab_index),
@@ -941,9 +4288,9 @@ impl IdeApp {
                                     format!("📁 Folder '{}' {}", file_name, action),
                                     NotificationType::FileOperation
                                 );
-                            } else {
+                            } else if !self.queue_external_opener(&path) {
                                 // Open file in editor
-                                if let Err(e) = self.editor.open_file(path.clone()) {
+                                if let Err(e) = self.open_in_editor(path.clone()) {
                                     self.add_notification(
                                         format!("❌ Failed to open file '{}': {}", file_name, e),
                                         NotificationType::FileOperation
@@ -966,6 +4313,18 @@ impl IdeApp {
                         match context.as_str() {
                             "AI Chat" => {
                                 self.focus_panel(FocusedPanel::Chat);
+
+                                // Clicking a collapsed answer shows all of it
+                                if self.sidebar.chat.expand_at(self.layout.chat_area, y) {
+                                    return Ok(());
+                                }
+                                // Clicking a message with a file:line reference opens it
+                                let text = self.sidebar.chat.message_at(self.layout.chat_area, y).map(|msg| msg.text().to_string());
+                                if let Some(text) = text {
+                                    if self.open_first_file_ref(&text)? {
+                                        return Ok(());
+                                    }
+                                }
                                 
                                 // Enhanced chat area click detection
                                 let area = self.layout.chat_area;
@@ -979,6 +4338,9 @@ impl IdeApp {
                             }
                             "Editor" => {
                                 self.focus_panel(FocusedPanel::Editor);
+                                if self.layout.inactive_pane_area.is_some_and(|pane| self.point_in_rect(x, y, pane)) {
+                                    self.editor.focus_other_pane();
+                                }
                                 self.add_notification("Focused Editor".to_string(), NotificationType::Info);
                             }
                             "Notifications" => {
@@ -986,6 +4348,10 @@ impl IdeApp {
                                 
                                 // Check if clicked on a specific notification
                                 if let Some(notification_index) = self.get_clicked_notification_item(x, y) {
+                                    let text = self.notifications.get(notification_index).map(|n| n.message.clone());
+                                    if self.open_first_file_ref(&text.unwrap_or_default())? {
+                                        return Ok(());
+                                    }
                                     if let Some(notification) = self.notifications.get(notification_index) {
                                         self.add_notification(
                                             format!("📋 Clicked on notification: {}", notification.message),
@@ -1003,13 +4369,19 @@ impl IdeApp {
             }
             
             IdeEvent::MouseScroll(delta) => {
+                // The wheel over the tab bar scrolls the tabs sideways
+                if self.is_click_in_tab_area(self.mouse_position.0, self.mouse_position.1).0 {
+                    self.editor.scroll_tabs(delta.signum() as isize);
+                    return Ok(());
+                }
+
                 // Handle mouse scrolling based on context
                 let context = self.get_mouse_context(self.mouse_position.0, self.mouse_position.1);
                 match context.as_str() {
                     "Editor" => {
                         // Get current tab info for debugging
                         let tab_info = if let Some(tab) = self.editor.get_current_tab() {
-                            format!("Lines: {}, Scroll: {}", tab.lines.len(), tab.scroll_offset)
+                            format!("Lines: {}, Scroll: {}", tab.buffer.len_lines(), tab.scroll_offset)
                         } else {
                             "No tab open".to_string()
                         };
@@ -1055,33 +4427,95 @@ impl IdeApp {
             }
             
             IdeEvent::Tab => {
-                if self.focused_panel == FocusedPanel::Editor && self.mode == AppMode::Insert {
-                    self.editor.insert_char('\t');
+                if self.focused_panel == FocusedPanel::Editor && (self.mode == AppMode::Insert || self.editor.has_selection()) {
+                    self.editor.smart_tab();
+                } else {
+                    // Text fields capture Tab, so it still has to move focus everywhere else
+                    self.cycle_focus();
+                }
+            }
+            
+            IdeEvent::BackTab => {
+                if self.focused_panel == FocusedPanel::Editor {
+                    self.editor.dedent_line();
                 }
             }
             
             // Chat operations
             IdeEvent::SendMessage => {
                 if self.focused_panel == FocusedPanel::Chat {
-                    self.send_chat_message(false).await?;
+                    self.send_chat_message(false, false).await?;
                 }
             }
             
             IdeEvent::SendMessageWithImage => {
                 if self.focused_panel == FocusedPanel::Chat {
-                    self.send_chat_message(true).await?;
+                    self.send_chat_message(true, false).await?;
                 }
             }
+
+            // Also from the palette, so not only with the chat focused
+            IdeEvent::SendMessageWithFile => {
+                self.send_chat_message(false, true).await?;
+            }
             
             IdeEvent::ClearChat => {
+                self.start_new_session();
                 self.sidebar.chat.clear();
                 self.conversation.clear();
+                self.model_override = None;
+            }
+            
+            IdeEvent::CopyLastAnswer => {
+                match self.last_answer_plain_text() {
+                    Some(text) => match self.clipboard.set_text(&text) {
+                        Ok(()) => self.add_notification(
+                            format!("📋 Copied last answer as plain text ({} chars)", text.chars().count()),
+                            NotificationType::Info
+                        ),
+                        Err(e) => self.add_notification(format!("❌ Clipboard error: {}", e), NotificationType::FileOperation),
+                    },
+                    None => self.add_notification("⚠️ No AI answer to copy yet".to_string(), NotificationType::Info),
+                }
+            }
+            
+            IdeEvent::SaveWorkflow => self.show_save_workflow_dialog(),
+            
+            IdeEvent::StartWorkflow => {
+                if self.config.workflows.is_empty() {
+                    self.add_notification(
+                        "⚠️ No workflows yet - set up a conversation and use \"Save Conversation as Workflow\"".to_string(),
+                        NotificationType::Info
+                    );
+                } else {
+                    self.workflow_selected = 0;
+                    self.show_workflow_picker = true;
+                }
+            }
+            
+            IdeEvent::PipeLastAnswer => {
+                let Some(command) = self.config.answer_pipe_command.clone() else {
+                    self.add_notification(
+                        "⚠️ Set answer_pipe_command in the config to pipe answers (e.g. \"espeak\")".to_string(),
+                        NotificationType::Info
+                    );
+                    return Ok(());
+                };
+                match self.last_answer_plain_text() {
+                    Some(text) => match pipe_to_command(&command, &text) {
+                        Ok(()) => self.add_notification(format!("🔊 Sent last answer to `{}`", command), NotificationType::Info),
+                        Err(e) => self.add_notification(format!("❌ Pipe command failed: {}", e), NotificationType::FileOperation),
+                    },
+                    None => self.add_notification("⚠️ No AI answer to send yet".to_string(), NotificationType::Info),
+                }
             }
             
             // File tree operations
             IdeEvent::RefreshFileTree => {
                 self.sidebar.file_explorer.refresh()?;
             }
+
+            IdeEvent::ToggleHiddenFiles => self.toggle_hidden_files(),
             
             IdeEvent::ToggleFileExpand => {
                 if self.focused_panel == FocusedPanel::FileExplorer {
@@ -1091,8 +4525,9 @@ impl IdeApp {
 
             // Tab management events
             IdeEvent::CloseTab(tab_id) => {
-                self.editor.close_tab_by_id(tab_id);
-                self.add_notification("Tab closed".to_string(), NotificationType::FileOperation);
+                if self.request_close_tab(tab_id) {
+                    self.add_notification("Tab closed".to_string(), NotificationType::FileOperation);
+                }
             }
 
             IdeEvent::SwitchToTab(index) => {
@@ -1152,16 +4587,137 @@ impl IdeApp {
         Ok(())
     }
 
-    async fn send_chat_message(&mut self, include_image: bool) -> Result<()> {
-        let message = self.sidebar.chat.get_input_and_clear();
-        if message.trim().is_empty() {
+    async fn send_chat_message(&mut self, include_image: bool, include_file: bool) -> Result<()> {
+        // In agentic mode a message sent while the agent works is queued as a task
+        let agentic = self.mode == AppMode::Agentic;
+        if self.requests.conversation().is_some() && !agentic {
+            self.add_notification("⏳ Still waiting for the last answer - Esc cancels it".to_string(), NotificationType::Info);
             return Ok(());
         }
+        if self.action_batch.is_some() && !agentic {
+            self.add_notification("⏳ Agent actions are still running - Esc stops a command".to_string(), NotificationType::Info);
+            return Ok(());
+        }
+        if self.sidebar.chat.editing.is_some() && self.agent_is_busy() {
+            self.add_notification("⏳ Wait for the agent to finish before resending an earlier message".to_string(), NotificationType::Info);
+            return Ok(());
+        }
+        let editing = self.sidebar.chat.editing.take().map(|(index, _)| index);
+        let message = self.sidebar.chat.get_input_and_clear();
+        if let Err(e) = self.prompt_history.record(&message) {
+            self.add_notification(format!("⚠️ Failed to save the prompt history: {}", e), NotificationType::FileOperation);
+        }
+        match message.trim() {
+            "" => return Ok(()),
+            "/undo" => {
+                self.undo_agent_changes(false);
+                return Ok(());
+            }
+            "/undo session" => {
+                self.undo_agent_changes(true);
+                return Ok(());
+            }
+            command if command == "/persona" || command.starts_with("/persona ") => {
+                self.persona_command(command["/persona".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/profile" || command.starts_with("/profile ") => {
+                self.profile_command(command["/profile".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/thread" || command.starts_with("/thread ") => {
+                self.thread_command(command["/thread".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/checkpoint" || command.starts_with("/checkpoint ") => {
+                self.checkpoint_command(command["/checkpoint".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/fork" || command.starts_with("/fork ") => {
+                self.fork_command(command["/fork".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/pin" || command.starts_with("/pin ") => {
+                self.pin_command(command["/pin".len()..].trim());
+                return Ok(());
+            }
+            "/pins" => {
+                self.list_pins();
+                return Ok(());
+            }
+            command if command == "/unpin" || command.starts_with("/unpin ") => {
+                self.unpin_command(command["/unpin".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/memory" || command.starts_with("/memory ") => {
+                self.memory_command(command["/memory".len()..].trim());
+                return Ok(());
+            }
+            command if command == "/export" || command.starts_with("/export ") => {
+                self.export_command(command["/export".len()..].trim());
+                return Ok(());
+            }
+            _ => {}
+        }
+        if let Some(index) = editing {
+            if !self.rewind_chat(index) {
+                self.sidebar.chat.insert_str(&message);
+                return Ok(());
+            }
+        }
 
         // Add user message to chat
         self.sidebar.chat.add_user_message(&message);
+        let title = message.clone();
+        self.retrieval_query = Some(message.clone());
+        self.refresh_vector_index();
+        self.reference_index = None;
+        // The active tab, or the lines selected in it, goes along as it is in the editor, unsaved edits included
+        let wants_selection = references::mentions_selection(&message) || (include_file && self.editor.has_selection());
+        let open_file = if include_file || wants_selection || references::mentions_open_file(&message) {
+            match self.editor.get_current_tab() {
+                Some(tab) => {
+                    let name = match &tab.file_path {
+                        Some(path) => path.strip_prefix(&self.current_directory).unwrap_or(path).to_path_buf(),
+                        None => PathBuf::from(&tab.file_name),
+                    };
+                    let lines: Vec<String> = tab.buffer.lines().collect();
+                    let (text, selected) = if wants_selection && tab.selection_anchor.is_some() {
+                        let (first, last) = tab.selected_lines();
+                        (lines[first..=last].join("\n"), Some((first, last)))
+                    } else {
+                        (lines.join("\n"), None)
+                    };
+                    Some((name, text, selected))
+                }
+                None => {
+                    self.sidebar.chat.add_system_message("⚠️ No file is open to attach");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let open_file = open_file.as_ref().map(|(name, text, lines)| OpenFile { path: name, text, lines: *lines });
+        match &open_file {
+            Some(open_file) if !open_file.is_attachable(&self.current_directory) => {
+                self.sidebar.chat.add_system_message(&format!("⚠️ {} is .aiignored and was not attached", open_file.label()));
+            }
+            Some(open_file) => {
+                if wants_selection && open_file.lines.is_none() {
+                    self.sidebar.chat.add_system_message("⚠️ Nothing is selected (`V` in the editor selects lines) - attaching the whole file");
+                }
+                self.sidebar.chat.add_system_message(&format!("📎 {} attached", open_file.label()));
+            }
+            None => {}
+        }
+        // The model gets the files and snippets behind `@`/`#` references; the chat keeps the short form
+        let message = references::resolve_references(&message, &self.current_directory, open_file.as_ref());
 
-        let groq_message = if include_image {
+        let groq_message = if include_image && !self.llm.profile().allow_images {
+            self.sidebar.chat.add_system_message("⚠️ Images are not sent in low-bandwidth mode");
+            crate::api::GroqClient::create_text_message("user", &message)
+        } else if include_image {
             match self.clipboard.get_image_as_base64().await {
                 Ok(image_data) => {
                     self.sidebar.chat.add_system_message("📷 Image included");
@@ -1176,37 +4732,435 @@ impl IdeApp {
             crate::api::GroqClient::create_text_message("user", &message)
         };
 
-        self.conversation.add_message(groq_message);
+        let revision = self.conversation.revision();
+        if agentic {
+            self.run_agent_task(&title, groq_message, false);
+        } else {
+            self.conversation.add_message(groq_message);
+            self.request_ai_reply();
+        }
+        // Unless it was queued as a task, the question is in the conversation now and can be edited later
+        if self.conversation.revision() != revision {
+            let question = self.conversation.get_messages().iter().rposition(|msg| msg.role == "user");
+            self.sidebar.chat.link_last_user_message(question.and_then(|index| self.conversation.sent_at(index)));
+        }
+        Ok(())
+    }
+
+    /// Drop the chat's question at `index` and everything after it from the chat and the
+    /// conversation, to send it again edited; false if it is no longer in the conversation
+    fn rewind_chat(&mut self, index: usize) -> bool {
+        let sent_at = self.sidebar.chat.messages.get(index).and_then(|msg| msg.sent_at);
+        let Some(position) = sent_at.and_then(|at| self.conversation.position_sent_at(at)) else {
+            self.add_notification("⚠️ That message was trimmed from the conversation, so it can't be resent from there".to_string(), NotificationType::Info);
+            return false;
+        };
+        let later = self.conversation.message_count() - position - 1;
+        self.conversation.truncate(position);
+        self.sidebar.chat.truncate(index);
+        self.sidebar.chat.add_system_message(&format!("✏️ Resending an earlier message - the {} messages after it were dropped", later));
+        true
+    }
+
+    /// Keys for the open `@`/`#` suggestion popup; returns whether the event was used
+    fn handle_chat_completion_event(&mut self, event: &IdeEvent) -> bool {
+        let Some(completion) = self.sidebar.chat.completion.as_mut() else {
+            return false;
+        };
+        match event {
+            IdeEvent::NavigateUp => completion.select_previous(),
+            IdeEvent::NavigateDown => completion.select_next(),
+            IdeEvent::Tab | IdeEvent::Enter => self.sidebar.chat.accept_completion(),
+            IdeEvent::NormalMode => {
+                self.dismissed_reference = Some(completion.start);
+                self.sidebar.chat.completion = None;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Suggest files after `@` and symbols after `#` for the word at the chat cursor
+    fn update_chat_completion(&mut self) {
+        let Some((start, trigger, query)) = self.sidebar.chat.completion_query().map(|(s, t, q)| (s, t, q.to_string())) else {
+            self.sidebar.chat.completion = None;
+            self.reference_index = None;
+            self.reference_indexing = None;
+            self.dismissed_reference = None;
+            return;
+        };
+        if self.dismissed_reference == Some(start) {
+            return;
+        }
+        // Index once per reference, so files and symbols created meanwhile show up next time. The walk
+        // runs on a blocking thread; `poll_reference_index` shows the suggestions once it is done
+        let Some((_, index)) = self.reference_index.as_ref().filter(|(indexed_at, _)| *indexed_at == start) else {
+            if self.reference_indexing.as_ref().is_none_or(|(indexed_at, _)| *indexed_at != start) {
+                let root = self.current_directory.clone();
+                let task = tokio::task::spawn_blocking(move || ProjectIndex::build(&root, trigger == '#'));
+                self.reference_indexing = Some((start, task));
+            }
+            return;
+        };
+
+        let items: Vec<CompletionItem> = if trigger == '@' {
+            index
+                .complete_files(&query, 8)
+                .into_iter()
+                .map(|path| CompletionItem {
+                    label: path.display().to_string(),
+                    detail: String::new(),
+                    insert: format!("@{}", path.display()),
+                })
+                .collect()
+        } else {
+            index
+                .complete_symbols(&query, 8)
+                .into_iter()
+                .map(|symbol| CompletionItem {
+                    label: symbol.name.clone(),
+                    detail: format!("{} {}:{}", symbol.kind, symbol.path.display(), symbol.line + 1),
+                    insert: symbol.reference(),
+                })
+                .collect()
+        };
+        self.sidebar.chat.completion = (!items.is_empty()).then_some(Completion { items, selected: 0, start });
+    }
+
+    /// Called from the main loop: take the `@`/`#` index once it is built and suggest from it
+    pub async fn poll_reference_index(&mut self) {
+        if !self.reference_indexing.as_ref().is_some_and(|(_, task)| task.is_finished()) {
+            return;
+        }
+        let Some((start, task)) = self.reference_indexing.take() else {
+            return;
+        };
+        if let Ok(index) = task.await {
+            self.reference_index = Some((start, index));
+            self.update_chat_completion();
+        }
+    }
+
+    /// Ask the model to answer the conversation so far; `poll_ai_reply` picks the answer up, so
+    /// the UI keeps drawing and Esc can cancel while it is on its way
+    fn request_ai_reply(&mut self) {
+        let budget = self.config.agent_loop;
+        if self.agent_run.is_none() {
+            // Read the policy afresh, so edits to it apply from this run on and its file count starts over
+            let policy = AgentPolicy::load(&self.current_directory);
+            if let Some(error) = policy.error.as_ref().filter(|error| self.agent_executor.policy.error.as_ref() != Some(*error)) {
+                self.add_notification(format!("⚠️ {} - agent changes and commands are blocked", error), NotificationType::Info);
+            }
+            self.agent_executor.policy = policy;
+        }
+        let run = self.agent_run.get_or_insert_with(|| AgentRun::new(budget));
+        if let Some(reason) = run.exhausted() {
+            self.finish_agent_run(reason);
+            return;
+        }
+        if self.tasks.running().is_some_and(|task| task.cancel_requested) {
+            self.finish_agent_run(StopReason::Cancelled);
+            return;
+        }
+        if let Some(reason) = self.spend_guard.check(std::time::Instant::now()) {
+            self.tasks.set_progress(&format!("paused at {}", reason));
+            self.spend_pause = Some(reason);
+            return;
+        }
+        let progress = if self.mode == AppMode::Agentic {
+            self.tasks.set_progress(&run.progress());
+            format!("🤖 Agent working - {} (Esc to cancel)", run.progress())
+        } else {
+            "🤖 AI is typing... (Esc to cancel)".to_string()
+        };
+
+        self.apply_persona();
+
+        // The first agentic request brings the project's layout along, and it stays for the rest of the conversation
+        let entries = self.config.project_context_entries;
+        if self.mode == AppMode::Agentic && entries > 0 && !self.conversation.has_system_message(project_context::HEADER) {
+            self.conversation.add_system_message(project_context::summarize(&self.current_directory, entries));
+        }
+
+        let mut messages = self.with_standing_instructions(self.conversation.get_messages().clone());
+        // Sent with every request, so the agent's notes outlast trimmed history
+        let notes = self.agent_notes.as_ref().map(Scratchpad::recent).unwrap_or_default();
+        if self.mode == AppMode::Agentic && !notes.is_empty() {
+            messages.insert(0, GroqClient::create_text_message("system", &format!("Your notes so far (write_note adds to them):\n{}", notes)));
+        }
+        let strict_json = self.mode == AppMode::Agentic && self.config.strict_json_actions;
+        if strict_json {
+            messages.insert(0, GroqClient::create_text_message("system", &actions::strict_json_prompt()));
+        }
+        let tools = if self.mode == AppMode::Agentic && !strict_json { tools::definitions() } else { Vec::new() };
+        let lookup = self
+            .retrieval_query
+            .take()
+            .zip(self.vector_index.clone())
+            .filter(|_| self.config.retrieval.enabled)
+            .map(|(query, index)| Lookup { query, index, settings: self.config.retrieval.clone() });
+        self.retry_notice = None;
+        let id = self.spawn_request(RequestKind::Conversation, messages, tools, lookup);
+        self.sidebar.chat.set_request_message(id, MessageType::System, &progress);
+    }
+
+    /// `messages` with the configured response language and the project's memory; sent with every
+    /// request rather than stored, so changes to either apply immediately
+    fn with_standing_instructions(&self, mut messages: Vec<GroqMessage>) -> Vec<GroqMessage> {
+        if let Some(memory) = project_memory::system_message(&self.current_directory) {
+            messages.insert(0, GroqClient::create_text_message("system", &memory));
+        }
+        if let Some(language) = &self.config.response_language {
+            let instruction = format!("Always respond in {}, whatever language the user writes in.", language);
+            messages.insert(0, GroqClient::create_text_message("system", &instruction));
+        }
+        messages
+    }
 
-        // Show typing indicator
-        self.sidebar.chat.add_system_message("🤖 AI is typing...");
+    /// Send `messages` to the model in the background; `poll_ai_reply` matches the answer back by the returned ID
+    fn spawn_request(&mut self, kind: RequestKind, messages: Vec<GroqMessage>, tools: Vec<Tool>, lookup: Option<Lookup>) -> RequestId {
+        let model = self.model().to_string();
+        let client = self.llm.clone();
+        let temperature = self.config.get_temperature();
+        self.spend_guard.record_request(std::time::Instant::now());
+        // Low-bandwidth mode keeps whole responses, which it can retry with a smaller payload
+        if self.config.stream_responses && !client.profile().low_bandwidth {
+            let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+            let handle = tokio::spawn(async move {
+                let messages = retrieval::with_context(client.as_ref(), lookup, messages).await;
+                client.chat_streaming(&model, messages, temperature, &tools, sender).await
+            });
+            self.requests.start(kind, handle, Some(receiver))
+        } else {
+            let handle = tokio::spawn(async move {
+                let messages = retrieval::with_context(client.as_ref(), lookup, messages).await;
+                client.chat(&model, messages, temperature, &tools).await
+            });
+            self.requests.start(kind, handle, None)
+        }
+    }
 
-        // Get AI response
-        match self.get_ai_response().await {
-            Ok(response) => {
-                self.sidebar.chat.remove_last_message(); // Remove typing indicator
-                self.sidebar.chat.add_ai_message(&response);
-                self.conversation.add_message(crate::api::GroqClient::create_text_message("assistant", &response));
+    /// Called from the main loop: show the model's answer once it has arrived, run the tools it
+    /// called and, when all their results are in, ask it to go on
+    pub async fn poll_ai_reply(&mut self) {
+        self.show_retry_notice();
+        self.show_streamed_text();
+        self.add_side_answers();
+        let Some(request) = self.requests.take_finished() else {
+            return;
+        };
+        let (id, kind) = (request.id, request.kind.clone());
+        let response = request.response().await;
+        if let RequestKind::Background { title, prompt } = kind {
+            self.finish_background_request(id, &title, prompt, response);
+            return;
+        }
+        self.sidebar.chat.remove_request_message(id); // Progress line, or the answer so far
+        let message = response.and_then(|response| {
+            if let Some(run) = self.agent_run.as_mut() {
+                run.record(response.usage.total_tokens);
+            }
+            self.spend_guard.record_tokens(response.usage.total_tokens);
+            response
+                .choices
+                .into_iter()
+                .next()
+                .map(|choice| choice.message)
+                .ok_or_else(|| anyhow::anyhow!("No response from Groq API"))
+        });
+        match message {
+            Ok(message) => {
+                if self.handle_ai_message(message) {
+                    self.request_ai_reply();
+                }
+                self.summarize_history();
             }
             Err(e) => {
-                self.sidebar.chat.remove_last_message(); // Remove typing indicator
                 self.sidebar.chat.add_system_message(&format!("❌ Error: {}", e));
+                self.agent_run = None;
+                self.supervisor.review_every_change = false;
+                self.tasks.finish_running(StopReason::Failed(e.to_string()));
             }
         }
+    }
 
-        Ok(())
+    /// Show a background question's answer in place of its progress line, and keep the exchange
+    /// for the conversation
+    fn finish_background_request(&mut self, id: RequestId, title: &str, prompt: String, response: Result<GroqResponse>) {
+        let answer = response.and_then(|response| {
+            self.spend_guard.record_tokens(response.usage.total_tokens);
+            response
+                .choices
+                .into_iter()
+                .next()
+                .and_then(|choice| choice.message.content)
+                .ok_or_else(|| anyhow::anyhow!("No response from Groq API"))
+        });
+        let answer = answer.map(|answer| self.postprocess(&answer));
+        match answer {
+            Ok(answer) => {
+                self.sidebar.chat.set_request_message(id, MessageType::Assistant, &answer);
+                self.side_answers.push(GroqClient::create_text_message("user", &prompt));
+                self.side_answers.push(GroqClient::create_text_message("assistant", &answer));
+            }
+            Err(e) => self.sidebar.chat.set_request_message(id, MessageType::System, &format!("❌ {}: {}", title, e)),
+        }
+        self.sidebar.chat.finish_request_message(id);
     }
 
-    async fn get_ai_response(&self) -> Result<String> {
-        let messages = self.conversation.get_messages().clone();
-        let model = self.config.get_model();
-        
-        self.groq_client
-            .send_message(model, messages, 0.7)
-            .await
+    /// Add the background exchanges to the conversation once it isn't in the middle of a turn,
+    /// which would separate tool calls from their results
+    fn add_side_answers(&mut self) {
+        if self.side_answers.is_empty() || self.agent_is_busy() || !self.pending_tool_calls.is_empty() {
+            return;
+        }
+        for message in std::mem::take(&mut self.side_answers) {
+            self.conversation.add_message(message);
+        }
+    }
+
+    /// Show the text streamed since in place of each request's progress line, or its answer so far
+    fn show_streamed_text(&mut self) {
+        let pipeline = postprocess::Pipeline::from_settings(&self.config.postprocess);
+        for request in self.requests.receive_streamed() {
+            let text = pipeline.process(&request.streamed_text);
+            self.sidebar.chat.set_request_message(request.id, MessageType::Assistant, &format!("{}▌", text));
+        }
+    }
+
+    /// An answer as it is shown and kept: stop sequences applied, reasoning and extra blank lines removed
+    fn postprocess(&self, text: &str) -> String {
+        postprocess::Pipeline::from_settings(&self.config.postprocess).process(text)
+    }
+
+    /// Replace the conversation's progress line with what a rate-limited or failing request is waiting for
+    fn show_retry_notice(&mut self) {
+        let Some(request) = self.requests.conversation() else {
+            return;
+        };
+        let status = self.llm.retry_status();
+        if status.is_none() || status == self.retry_notice || !request.streamed_text.is_empty() {
+            return;
+        }
+        let message = format!("⏳ {} (Esc to cancel)", status.as_deref().unwrap_or_default());
+        self.sidebar.chat.set_request_message(request.id, MessageType::System, &message);
+        self.retry_notice = status;
+    }
+
+    /// Stop waiting for the model; the conversation keeps everything up to the last tool results.
+    /// With no conversation request in flight, the background questions are stopped instead
+    fn cancel_ai_request(&mut self) {
+        match self.requests.conversation().map(|request| request.id) {
+            Some(id) => {
+                self.cancel_request(id);
+                self.finish_agent_run(StopReason::Cancelled);
+            }
+            None => {
+                for id in self.requests.ids() {
+                    self.cancel_request(id);
+                }
+            }
+        }
+    }
+
+    fn cancel_request(&mut self, id: RequestId) {
+        let Some(request) = self.requests.cancel(id) else {
+            return;
+        };
+        if request.streamed_text.is_empty() {
+            self.sidebar.chat.remove_request_message(id); // Progress line
+        } else {
+            // What arrived stays readable, though the model never hears of it
+            self.sidebar.chat.set_request_message(id, MessageType::Assistant, &format!("{} [cancelled]", request.streamed_text));
+            self.sidebar.chat.finish_request_message(id);
+        }
+    }
+
+    /// Post how the run ended, unless it was a plain one-shot answer
+    fn finish_agent_run(&mut self, reason: StopReason) {
+        self.supervisor.review_every_change = false;
+        // Changes of a turn cut short never reach the model, but still show what happened
+        if let Some(summary) = self.supervisor.take_turn_summary().to_message() {
+            self.sidebar.chat.add_system_message(&format!("📋 {}", summary));
+        }
+        self.tasks.finish_running(reason.clone());
+        let Some(run) = self.agent_run.take() else {
+            return;
+        };
+        if reason == StopReason::Completed && run.iterations <= 1 {
+            return;
+        }
+        let icon = if reason == StopReason::Completed { "✅" } else { "⏹️" };
+        self.sidebar.chat.add_system_message(&format!(
+            "{} Agent {} after {} step(s), {} tokens",
+            icon,
+            reason.describe(&run.budget),
+            run.iterations,
+            run.tokens
+        ));
+    }
+
+    /// Show one answer from the model; returns whether it called tools whose results are waiting for it
+    fn handle_ai_message(&mut self, message: ResponseMessage) -> bool {
+        let content = self.postprocess(&message.content.unwrap_or_default());
+        let calls = message.tool_calls.unwrap_or_default();
+        if !content.is_empty() {
+            self.sidebar.chat.add_ai_message(&content);
+        }
+        if calls.is_empty() && self.mode == AppMode::Agentic && self.config.strict_json_actions {
+            return self.handle_strict_json_reply(content);
+        }
+        if calls.is_empty() {
+            self.conversation.add_message(GroqClient::create_text_message("assistant", &content));
+            self.finish_agent_run(StopReason::Completed);
+            // A proposed plan is reviewed before the agent starts on it
+            if self.mode == AppMode::Agentic {
+                if let Some(plan) = Plan::parse(&content) {
+                    self.plan_editor.open(plan);
+                }
+            }
+            return false;
+        }
+
+        self.conversation.add_message(GroqClient::create_tool_call_message(&content, calls.clone()));
+        let mut runnable = Vec::new();
+        for call in calls {
+            match tools::parse_call(&call) {
+                Ok(action) => runnable.push((call.id, action)),
+                Err(e) => self.answer_tool_call(&call.id, &AgentResponse::error("Invalid tool call".to_string(), e.to_string())),
+            }
+        }
+        self.run_agent_actions(runnable)
+    }
+
+    /// Strict JSON mode: run the actions in the answer's JSON block, or ask the model to re-emit a
+    /// block that doesn't validate. Returns whether the model should be asked again right away
+    fn handle_strict_json_reply(&mut self, content: String) -> bool {
+        self.conversation.add_message(GroqClient::create_text_message("assistant", &content));
+        match AgentActionParser::parse_strict(&content) {
+            Ok(actions) if actions.is_empty() => {
+                self.finish_agent_run(StopReason::Completed);
+                false
+            }
+            Ok(actions) => {
+                let calls = actions
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, action)| (format!("{}{}", STRICT_JSON_ID, index + 1), action))
+                    .collect();
+                self.run_agent_actions(calls)
+            }
+            Err(e) => {
+                // The run's budget bounds the retries
+                self.sidebar.chat.add_system_message(&format!("⚠️ Invalid action JSON ({}) - asking the model to re-emit it", e));
+                self.conversation.add_message(GroqClient::create_text_message("user", &actions::strict_retry_prompt(&e)));
+                true
+            }
+        }
     }
 
     pub fn get_status_info(&self) -> statusbar::StatusInfo {
+        let (encoding, line_ending) = self.editor.get_file_format();
         statusbar::StatusInfo {
             mode: self.mode,
             focused_panel: self.focused_panel,
@@ -1214,6 +5168,41 @@ impl IdeApp {
             cursor_position: self.editor.get_cursor_position(),
             is_modified: self.editor.is_current_file_modified(),
             total_files: self.editor.get_tab_count(),
+            autonomy: self.config.autonomy.level,
+            encoding,
+            line_ending,
+            read_only_badge: self.editor.get_read_only_badge(),
+            language: self.editor.get_language_name(),
+            low_bandwidth: self.config.low_bandwidth,
         }
     }
-}
\ No newline at end of file
+}
+/// What the model is told about a tool call the user turned down in the approval panel
+fn rejected_response() -> AgentResponse {
+    AgentResponse::error("Not run".to_string(), "The user rejected this action".to_string())
+}
+
+/// Run `command` through the shell with `text` on stdin, without waiting for it to finish
+fn pipe_to_command(command: &str, text: &str) -> Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null()) // Output would corrupt the TUI
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow::anyhow!("Failed to open stdin"))?;
+    let text = text.to_string();
+    // Tools like TTS can take a while; feed and reap them off the UI thread
+    std::thread::spawn(move || {
+        let _ = stdin.write_all(text.as_bytes());
+        drop(stdin);
+        let _ = child.wait();
+    });
+
+    Ok(())
+}
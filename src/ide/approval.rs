@@ -0,0 +1,331 @@
+use crate::agent::git;
+use crate::agent::risk::classify_command;
+use crate::agent::turn_summary;
+use crate::agent::AgentAction;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::fs;
+use std::path::Path;
+
+/// An action the agent proposed, with what it would do rendered before anything runs
+pub struct PendingAction {
+    pub action: AgentAction,
+    pub approved: bool,
+    pub preview: Vec<String>,
+    pub destructive: bool, // Approved only by typing `yes` or the command itself
+}
+
+/// Confirmation panel for the actions parsed from an agentic-mode answer
+#[derive(Default)]
+pub struct ApprovalPanel {
+    pub is_open: bool,
+    pub selected: usize,
+    entries: Vec<PendingAction>,
+    preview_scroll: usize,
+    confirmation: Option<String>, // Typed so far to approve the selected destructive command
+}
+
+impl ApprovalPanel {
+    /// Everything starts approved except destructive commands, which have to be confirmed by typing
+    pub fn open(&mut self, actions: Vec<AgentAction>, root: &Path) {
+        self.entries = actions
+            .into_iter()
+            .map(|action| {
+                let destructive = matches!(&action, AgentAction::ExecuteCommand { command, .. }
+                    if classify_command(command).risk.requires_typed_confirmation());
+                PendingAction { preview: preview(&action, root), approved: !destructive, destructive, action }
+            })
+            .collect();
+        self.confirmation = None;
+        self.selected = 0;
+        self.preview_scroll = 0;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.entries.clear();
+        self.confirmation = None;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+        self.preview_scroll = 0;
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+            self.preview_scroll = 0;
+        }
+    }
+
+    pub fn scroll_preview(&mut self, delta: isize) {
+        let lines = self.entries.get(self.selected).map_or(0, |entry| entry.preview.len());
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(delta).min(lines.saturating_sub(1));
+    }
+
+    /// Tick or untick the selected action; ticking a destructive command asks for it to be typed first
+    pub fn toggle_selected(&mut self) {
+        if let Some(entry) = self.entries.get_mut(self.selected) {
+            if entry.destructive && !entry.approved {
+                self.confirmation = Some(String::new());
+            } else {
+                entry.approved = !entry.approved;
+            }
+        }
+    }
+
+    /// Tick every action but the destructive commands, which are only approved one by one
+    pub fn approve_all(&mut self) {
+        for entry in self.entries.iter_mut().filter(|entry| !entry.destructive) {
+            entry.approved = true;
+        }
+    }
+
+    pub fn is_confirming(&self) -> bool {
+        self.confirmation.is_some()
+    }
+
+    pub fn type_confirmation(&mut self, c: char) {
+        if let Some(text) = self.confirmation.as_mut() {
+            text.push(c);
+        }
+    }
+
+    pub fn confirmation_backspace(&mut self) {
+        if let Some(text) = self.confirmation.as_mut() {
+            text.pop();
+        }
+    }
+
+    pub fn cancel_confirmation(&mut self) {
+        self.confirmation = None;
+    }
+
+    /// Approve the selected destructive command if `yes` or the command was typed; returns whether it was
+    pub fn submit_confirmation(&mut self) -> bool {
+        let Some(typed) = self.confirmation.take() else {
+            return false;
+        };
+        let Some(entry) = self.entries.get_mut(self.selected) else {
+            return false;
+        };
+        let typed = typed.trim();
+        let confirmed = typed.eq_ignore_ascii_case("yes")
+            || matches!(&entry.action, AgentAction::ExecuteCommand { command, .. } if command.trim() == typed);
+        entry.approved |= confirmed;
+        confirmed
+    }
+
+    /// Close the panel, handing back every action in its original order with whether it was approved
+    pub fn take_decisions(&mut self) -> Vec<(AgentAction, bool)> {
+        let entries = std::mem::take(&mut self.entries);
+        self.close();
+        entries.into_iter().map(|entry| (entry.action, entry.approved)).collect()
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let approved = self.entries.iter().filter(|entry| entry.approved).count();
+        let block = Block::default()
+            .title(format!(
+                " 🤖 Approve Agent Actions ({} of {}) - Space: toggle  Enter: run approved  a: run all but destructive  J/K: scroll preview  Esc: reject ",
+                approved,
+                self.entries.len()
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(inner);
+
+        let selected_style = Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD);
+        let list: Vec<Line> = self
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let check = if entry.approved { "[x]" } else { "[ ]" };
+                let style = if index == self.selected {
+                    selected_style
+                } else if entry.approved {
+                    Style::default().fg(Color::White)
+                } else {
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT)
+                };
+                Line::from(Span::styled(format!(" {} {} ", check, describe(&entry.action)), style))
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(list), columns[0]);
+
+        let preview_area = match &self.confirmation {
+            Some(typed) => {
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(1), Constraint::Length(3)])
+                    .split(columns[1]);
+                let prompt = Paragraph::new(Line::from(Span::styled(
+                    format!("{}_", typed),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                )))
+                .block(Block::default()
+                    .title(" ⚠️ Destructive - type `yes` or the command to approve (Enter: confirm, Esc: cancel) ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Red)));
+                frame.render_widget(prompt, rows[1]);
+                rows[0]
+            }
+            None => columns[1],
+        };
+
+        let preview: Vec<Line> = self
+            .entries
+            .get(self.selected)
+            .map(|entry| {
+                entry.preview.iter().skip(self.preview_scroll).map(|line| {
+                    let style = if line.starts_with("+++") || line.starts_with("---") {
+                        Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+                    } else if line.starts_with('+') {
+                        Style::default().fg(Color::Green)
+                    } else if line.starts_with('-') {
+                        Style::default().fg(Color::Red)
+                    } else if line.starts_with("@@") {
+                        Style::default().fg(Color::Cyan)
+                    } else {
+                        Style::default().fg(Color::Gray)
+                    };
+                    Line::from(Span::styled(line.clone(), style))
+                }).collect()
+            })
+            .unwrap_or_default();
+        frame.render_widget(
+            Paragraph::new(preview).block(Block::default().borders(Borders::LEFT).border_style(Style::default().fg(Color::DarkGray))),
+            preview_area,
+        );
+    }
+}
+
+/// One-line summary of an action for the list
+pub fn describe(action: &AgentAction) -> String {
+    match action {
+        AgentAction::ReadFile { path } => format!("📖 Read {}", path.display()),
+        AgentAction::WriteFile { path, .. } => format!("✏️ Write {}", path.display()),
+        AgentAction::CreateDirectory { path } => format!("📁 Create {}", path.display()),
+        AgentAction::DeleteFile { path } => format!("🗑️ Delete {}", path.display()),
+        AgentAction::ExecuteCommand { command, .. } => format!("⚡ Run `{}`", command),
+        AgentAction::SearchFiles { pattern, in_contents: true, .. } => format!("🔍 Search files for /{}/", pattern),
+        AgentAction::SearchFiles { pattern, .. } => format!("🔍 Search for {}", pattern),
+        AgentAction::ReplaceInFile { path, .. } => format!("🔁 Edit {}", path.display()),
+        AgentAction::ApplyPatch { path, .. } => format!("🩹 Patch {}", path.display()),
+        AgentAction::AppendToFile { path, .. } => format!("➕ Append to {}", path.display()),
+        AgentAction::MoveFile { from, to } => format!("🚚 Move {} → {}", from.display(), to.display()),
+        AgentAction::CopyFile { from, to } => format!("📄 Copy {} → {}", from.display(), to.display()),
+        AgentAction::ListDirectory { path } => format!("📂 List {}", path.display()),
+        AgentAction::GetFileInfo { path } => format!("ℹ️ Inspect {}", path.display()),
+        AgentAction::RunTests { filter: Some(filter) } => format!("🧪 Run tests matching {}", filter),
+        AgentAction::RunTests { filter: None } => "🧪 Run tests".to_string(),
+        AgentAction::GitStatus {} => "🌿 Git status".to_string(),
+        AgentAction::GitDiff { path: Some(path), .. } => format!("🌿 Git diff of {}", path.display()),
+        AgentAction::GitDiff { path: None, .. } => "🌿 Git diff".to_string(),
+        AgentAction::GitCommit { message } => format!("🌿 Commit \"{}\"", message.lines().next().unwrap_or_default()),
+        AgentAction::WriteNote { note } => format!("📝 Note: {}", note.lines().next().unwrap_or_default()),
+        AgentAction::ReadNotes {} => "📝 Read notes".to_string(),
+    }
+}
+
+/// What the action would change: a diff for file edits, the command and its risk for commands
+fn preview(action: &AgentAction, root: &Path) -> Vec<String> {
+    if let Some((_, diff)) = turn_summary::preview_diff(action, root) {
+        return diff.lines().map(str::to_string).collect();
+    }
+    match action {
+        AgentAction::DeleteFile { path } => {
+            let content = fs::read_to_string(root.join(path)).unwrap_or_default();
+            let mut lines = vec![format!("--- {} (deleted, {} lines)", path.display(), content.lines().count())];
+            lines.extend(content.lines().map(|line| format!("-{}", line)));
+            lines
+        }
+        AgentAction::ExecuteCommand { command, working_dir } => {
+            let dir = working_dir.as_deref().unwrap_or(Path::new("."));
+            vec![
+                format!("$ {}", command),
+                format!("in {}", dir.display()),
+                String::new(),
+                classify_command(command).summary(),
+            ]
+        }
+        AgentAction::CreateDirectory { path } => vec![format!("New directory {}", path.display())],
+        AgentAction::MoveFile { from, to } => vec![format!("{} moves to {}", from.display(), to.display())],
+        AgentAction::RunTests { .. } => vec!["Runs the project's test command, which runs project code".to_string()],
+        AgentAction::GitCommit { message } => {
+            let mut lines: Vec<String> = message.lines().map(str::to_string).collect();
+            lines.push(String::new());
+            lines.push("Stages and commits only the files the agent changed since its last commit, of:".to_string());
+            match git::status(root, |_| false) {
+                Ok(status) => lines.extend(status.lines().skip(1).map(str::to_string)), // First line is the branch
+                Err(e) => lines.push(e.to_string()),
+            }
+            lines
+        }
+        _ => vec!["Read-only: nothing in the project changes".to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_approval_panel() {
+        let root = std::env::temp_dir().join(format!("i4z-approval-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("notes.txt"), "old line\n").unwrap();
+
+        let mut panel = ApprovalPanel::default();
+        panel.open(
+            vec![
+                AgentAction::WriteFile { path: PathBuf::from("notes.txt"), content: "new line\n".to_string() },
+                AgentAction::ExecuteCommand { command: "rm -rf build".to_string(), working_dir: None },
+                AgentAction::ReadFile { path: PathBuf::from("notes.txt") },
+            ],
+            &root,
+        );
+        assert!(panel.entries[0].preview.contains(&"+new line".to_string()));
+        assert!(!panel.entries[1].approved); // Destructive commands start unticked
+
+        panel.approve_all();
+        assert!(!panel.entries[1].approved);
+        panel.select_next();
+        panel.toggle_selected();
+        assert!(panel.is_confirming() && !panel.entries[1].approved);
+        "y".chars().for_each(|c| panel.type_confirmation(c));
+        assert!(!panel.submit_confirmation());
+        panel.toggle_selected();
+        "rm -rf build".chars().for_each(|c| panel.type_confirmation(c));
+        assert!(panel.submit_confirmation());
+        assert!(panel.entries[1].approved);
+
+        panel.toggle_selected(); // Unticking needs no typing
+        panel.select_next();
+        panel.toggle_selected();
+        let decisions = panel.take_decisions();
+        let approved: Vec<bool> = decisions.iter().map(|(_, approved)| *approved).collect();
+        assert_eq!(approved, [true, false, false]);
+        assert!(matches!(decisions[0].0, AgentAction::WriteFile { .. }));
+        assert!(!panel.is_open);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+use crate::ide::editor::{Editor, EditorTab};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapFile {
+    pub original_path: Option<PathBuf>,
+    pub file_name: String,
+    pub pid: u32,
+    pub saved_at: chrono::DateTime<chrono::Local>,
+    pub content: String,
+    #[serde(skip)]
+    pub swap_path: PathBuf,
+}
+
+pub struct AutosaveManager {
+    pub swap_dir: PathBuf,
+    pub interval: Duration,
+    last_run: Instant,
+    written: HashSet<PathBuf>, // Swap files owned by this session
+}
+
+impl AutosaveManager {
+    pub fn new(interval: Duration) -> Result<Self> {
+        let swap_dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("swap");
+
+        Ok(Self {
+            swap_dir,
+            interval,
+            last_run: Instant::now(),
+            written: HashSet::new(),
+        })
+    }
+
+    fn swap_path_for(&self, tab: &EditorTab) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        match &tab.file_path {
+            Some(path) => path.hash(&mut hasher),
+            None => format!("untitled-{}", tab.id).hash(&mut hasher),
+        }
+        self.swap_dir.join(format!("{:016x}.swp", hasher.finish()))
+    }
+
+    /// Write swap files for modified buffers once the autosave interval has elapsed
+    pub fn tick(&mut self, editor: &Editor) -> Result<()> {
+        if self.interval.is_zero() || self.last_run.elapsed() < self.interval {
+            return Ok(());
+        }
+        self.last_run = Instant::now();
+        self.write_swaps(editor)
+    }
+
+    pub fn write_swaps(&mut self, editor: &Editor) -> Result<()> {
+        fs::create_dir_all(&self.swap_dir)?;
+
+        let mut current = HashSet::new();
+        for tab in editor.tabs.iter().filter(|tab| tab.is_modified) {
+            let swap_path = self.swap_path_for(tab);
+            let swap = SwapFile {
+                original_path: tab.file_path.clone(),
+                file_name: tab.file_name.clone(),
+                pid: std::process::id(),
+                saved_at: chrono::Local::now(),
+                content: tab.buffer.to_string(),
+                swap_path: swap_path.clone(),
+            };
+            fs::write(&swap_path, serde_json::to_string(&swap)?)?;
+            current.insert(swap_path);
+        }
+
+        // Buffers that were saved or closed no longer need their swap file
+        for stale in self.written.difference(&current) {
+            let _ = fs::remove_file(stale);
+        }
+        self.written = current;
+
+        Ok(())
+    }
+
+    /// Remove every swap file written by this session (clean shutdown)
+    pub fn cleanup(&mut self) {
+        for path in self.written.drain() {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// Swap files left behind by sessions that are no longer running
+    pub fn find_orphans(&self) -> Vec<SwapFile> {
+        let Ok(entries) = fs::read_dir(&self.swap_dir) else {
+            return Vec::new();
+        };
+
+        let mut orphans: Vec<SwapFile> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "swp"))
+            .filter_map(|path| {
+                let content = fs::read_to_string(&path).ok()?;
+                let mut swap: SwapFile = serde_json::from_str(&content).ok()?;
+                swap.swap_path = path;
+                Some(swap)
+            })
+            .filter(|swap| swap.pid != std::process::id() && !is_process_alive(swap.pid))
+            .collect();
+
+        orphans.sort_by_key(|swap| std::cmp::Reverse(swap.saved_at));
+        orphans
+    }
+
+    pub fn discard(&self, swap: &SwapFile) {
+        let _ = fs::remove_file(&swap.swap_path);
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn is_process_alive(pid: u32) -> bool {
+    PathBuf::from(format!("/proc/{}", pid)).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn is_process_alive(_pid: u32) -> bool {
+    // Without a cheap liveness check, treat every foreign swap file as orphaned
+    false
+}
@@ -0,0 +1,72 @@
+use crate::sessions::BranchRow;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+/// Overlay with the tree of conversations forked from checkpoints, to open a branch or fork another
+#[derive(Default)]
+pub struct BranchView {
+    pub is_open: bool,
+    rows: Vec<BranchRow>,
+    state: ListState,
+}
+
+impl BranchView {
+    /// Show `rows` with the row of conversation `current` selected
+    pub fn open(&mut self, rows: Vec<BranchRow>, current: &str) {
+        let selected = rows.iter().position(|row| row.session == current && row.checkpoint.is_none());
+        self.state.select(selected.or((!rows.is_empty()).then_some(0)));
+        self.rows = rows;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.rows.clear();
+        self.is_open = false;
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if let Some(selected) = self.state.selected() {
+            self.state.select(Some(selected.saturating_add_signed(delta).min(self.rows.len() - 1)));
+        }
+    }
+
+    /// Take the selected row and close
+    pub fn take_selected(&mut self) -> Option<BranchRow> {
+        let selected = self.state.selected().filter(|&index| index < self.rows.len())?;
+        let row = self.rows.swap_remove(selected);
+        self.close();
+        Some(row)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, current_id: &str) {
+        frame.render_widget(Clear, area);
+        let items: Vec<ListItem> = if self.rows.is_empty() {
+            vec![ListItem::new(Span::styled(" No checkpoints yet - `/checkpoint <name>` makes one", Style::default().fg(Color::DarkGray)))]
+        } else {
+            self.rows
+                .iter()
+                .map(|row| {
+                    let current = row.session == current_id && row.checkpoint.is_none();
+                    let style = match (current, &row.checkpoint) {
+                        (true, _) => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                        (false, Some(_)) => Style::default().fg(Color::Yellow),
+                        (false, None) => Style::default().fg(Color::Gray),
+                    };
+                    ListItem::new(Line::from(Span::styled(format!(" {}", row.text), style)))
+                })
+                .collect()
+        };
+        let list = List::new(items)
+            .block(Block::default()
+                .title(" 🌿 Branches (Enter: open a conversation or fork a ◆ checkpoint, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)))
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD));
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
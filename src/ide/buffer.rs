@@ -0,0 +1,194 @@
+use ropey::Rope;
+use std::fmt;
+
+/// Rope-backed text of an editor tab, addressed by (line, byte column) like the rest of the editor.
+/// Lines are separated by '\n' only; line endings on disk are handled by `encoding`.
+#[derive(Debug, Clone, Default)]
+pub struct TextBuffer {
+    rope: Rope,
+}
+
+impl TextBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_text(text: &str) -> Self {
+        Self { rope: Rope::from_str(text) }
+    }
+
+    pub fn from_lines(lines: &[String]) -> Self {
+        Self::from_text(&lines.join("\n"))
+    }
+
+    pub fn len_lines(&self) -> usize {
+        self.rope.len_lines()
+    }
+
+    /// Content of a line without its line break
+    pub fn line(&self, index: usize) -> Option<String> {
+        if index >= self.len_lines() {
+            return None;
+        }
+        let mut text = String::from(self.rope.line(index));
+        if text.ends_with('\n') {
+            text.pop();
+        }
+        Some(text)
+    }
+
+    /// Length of a line in bytes, without its line break
+    pub fn line_len(&self, index: usize) -> usize {
+        if index >= self.len_lines() {
+            return 0;
+        }
+        let line = self.rope.line(index);
+        let len = line.len_bytes();
+        if len > 0 && line.byte(len - 1) == b'\n' {
+            len - 1
+        } else {
+            len
+        }
+    }
+
+    /// Lines from `start` onwards, without touching the ones before it
+    pub fn lines_from(&self, start: usize) -> impl Iterator<Item = String> + '_ {
+        let start = start.min(self.len_lines());
+        self.rope.lines_at(start).map(|line| {
+            let mut text = String::from(line);
+            if text.ends_with('\n') {
+                text.pop();
+            }
+            text
+        })
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines_from(0)
+    }
+
+    /// Character at a (line, byte column) position, or None at the end of the line
+    pub fn char_at(&self, line: usize, col: usize) -> Option<char> {
+        if col >= self.line_len(line) {
+            return None;
+        }
+        let byte = self.rope.line_to_byte(line) + col;
+        Some(self.rope.char(self.rope.byte_to_char(byte)))
+    }
+
+    /// Byte column of the character before `col` on a line
+    pub fn prev_col(&self, line: usize, col: usize) -> usize {
+        if col == 0 || line >= self.len_lines() {
+            return 0;
+        }
+        let line_start = self.rope.line_to_byte(line);
+        let index = self.rope.byte_to_char(line_start + col.min(self.line_len(line)));
+        self.rope.char_to_byte(index.saturating_sub(1)).max(line_start) - line_start
+    }
+
+    /// Byte column moved back to the start of the character it falls in
+    pub fn floor_col(&self, line: usize, col: usize) -> usize {
+        if line >= self.len_lines() {
+            return 0;
+        }
+        self.rope.char_to_byte(self.char_index(line, col)) - self.rope.line_to_byte(line)
+    }
+
+    /// Number of characters before a byte column
+    pub fn char_col(&self, line: usize, col: usize) -> usize {
+        if line >= self.len_lines() {
+            return 0;
+        }
+        self.char_index(line, col) - self.rope.line_to_char(line)
+    }
+
+    /// Up to `max_chars` characters of a line from character `first` on, with the byte column
+    /// they start at. Only that part is copied out of the rope, however long the line is
+    pub fn line_window(&self, line: usize, first: usize, max_chars: usize) -> (usize, String) {
+        if line >= self.len_lines() {
+            return (0, String::new());
+        }
+        let end = self.char_index(line, self.line_len(line));
+        let from = (self.rope.line_to_char(line) + first).min(end);
+        let to = (from + max_chars).min(end);
+        (self.rope.char_to_byte(from) - self.rope.line_to_byte(line), String::from(self.rope.slice(from..to)))
+    }
+
+    fn char_index(&self, line: usize, col: usize) -> usize {
+        let line = line.min(self.len_lines() - 1);
+        let byte = self.rope.line_to_byte(line) + col.min(self.line_len(line));
+        self.rope.byte_to_char(byte)
+    }
+
+    /// Insert text (which may contain newlines) at a (line, byte column) position
+    pub fn insert(&mut self, line: usize, col: usize, text: &str) {
+        let index = self.char_index(line, col);
+        self.rope.insert(index, text);
+    }
+
+    /// Remove the bytes `start..end` of a line
+    pub fn remove(&mut self, line: usize, start: usize, end: usize) {
+        let from = self.char_index(line, start);
+        let to = self.char_index(line, end);
+        self.rope.remove(from..to);
+    }
+
+    /// Replace the content of a line, keeping its line break
+    pub fn replace_line(&mut self, line: usize, text: &str) {
+        self.remove(line, 0, self.line_len(line));
+        self.insert(line, 0, text);
+    }
+
+    /// Split a line in two at a byte column
+    pub fn split_line(&mut self, line: usize, col: usize) {
+        self.insert(line, col, "\n");
+    }
+
+    /// Join a line with the one after it
+    pub fn join_with_next(&mut self, line: usize) {
+        if line + 1 < self.len_lines() {
+            let end = self.rope.line_to_char(line + 1);
+            self.rope.remove(end - 1..end);
+        }
+    }
+}
+
+impl fmt::Display for TextBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.rope.chunks() {
+            f.write_str(chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_editing() {
+        let mut buffer = TextBuffer::from_text("héllo\nworld");
+        assert_eq!(buffer.len_lines(), 2);
+        assert_eq!(buffer.line_len(0), 6);
+
+        buffer.insert(0, 6, "!");
+        buffer.split_line(1, 2);
+        assert_eq!(buffer.to_string(), "héllo!\nwo\nrld");
+
+        buffer.join_with_next(1);
+        buffer.remove(0, 1, 3);
+        assert_eq!(buffer.to_string(), "hllo!\nworld");
+
+        assert_eq!(buffer.lines().collect::<Vec<_>>(), vec!["hllo!", "world"]);
+        assert_eq!(buffer.char_at(1, 1), Some('o'));
+        assert_eq!(TextBuffer::from_text("é!").prev_col(0, 2), 0);
+        assert_eq!(TextBuffer::new().len_lines(), 1);
+
+        let buffer = TextBuffer::from_text("aé{xyz}\nb");
+        assert_eq!(buffer.floor_col(0, 2), 1);
+        assert_eq!(buffer.char_col(0, 4), 3);
+        assert_eq!(buffer.line_window(0, 2, 3), (3, "{xy".to_string()));
+        assert_eq!(buffer.line_window(0, 50, 3), (8, String::new()));
+    }
+}
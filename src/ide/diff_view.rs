@@ -0,0 +1,328 @@
+use crate::diff::{self, DiffLine, DiffOptions, Granularity, Runs};
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChangeKind {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// One diff line with its 1-based line number in the saved file and in the buffer
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub kind: ChangeKind,
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub text: String,
+    pub runs: Runs, // Text split where it differs from the line it replaced or was replaced by, if paired
+}
+
+/// Read-only overlay comparing a buffer with its saved file
+pub struct DiffView {
+    pub is_open: bool,
+    pub side_by_side: bool,
+    pub options: DiffOptions,
+    title: String,
+    old: String,
+    new: String,
+    entries: Vec<DiffEntry>,
+    scroll: usize,
+    page: usize, // Rows visible at the last draw, for half-page scrolling
+    change: Option<usize>, // Index of the change block last jumped to
+}
+
+impl Default for DiffView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DiffView {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            side_by_side: true,
+            options: DiffOptions::default(),
+            title: String::new(),
+            old: String::new(),
+            new: String::new(),
+            entries: Vec::new(),
+            scroll: 0,
+            page: 20,
+            change: None,
+        }
+    }
+
+    /// Open on `old` (saved) vs `new` (buffer); returns the number of changed lines
+    pub fn open(&mut self, title: String, old: &str, new: &str) -> usize {
+        self.title = title;
+        self.old = old.to_string();
+        self.new = new.to_string();
+        self.is_open = true;
+        self.refresh()
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.entries.clear();
+        self.old.clear();
+        self.new.clear();
+    }
+
+    pub fn toggle_ignore_whitespace(&mut self) {
+        self.options.ignore_whitespace = !self.options.ignore_whitespace;
+        self.refresh();
+    }
+
+    pub fn cycle_algorithm(&mut self) {
+        self.options.algorithm = self.options.algorithm.next();
+        self.refresh();
+    }
+
+    /// Diff again with the current options; returns the number of changed lines
+    fn refresh(&mut self) -> usize {
+        self.entries = entries(&self.old, &self.new, self.options);
+        self.scroll = 0;
+        self.change = None;
+        self.next_change();
+        self.entries.iter().filter(|entry| entry.kind != ChangeKind::Equal).count()
+    }
+
+    pub fn toggle_layout(&mut self) {
+        self.side_by_side = !self.side_by_side;
+        self.scroll = 0;
+        self.change = None;
+        self.next_change();
+    }
+
+    pub fn scroll_by(&mut self, delta: isize) {
+        let max = self.row_count().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(max);
+    }
+
+    pub fn half_page(&self) -> isize {
+        (self.page / 2).max(1) as isize
+    }
+
+    /// Scroll to the next block of changes (wrapping around), leaving a little context above it
+    pub fn next_change(&mut self) {
+        let rows = self.rows();
+        let is_change = |row: &(Option<&DiffEntry>, Option<&DiffEntry>)| {
+            [row.0, row.1].iter().flatten().any(|entry| entry.kind != ChangeKind::Equal)
+        };
+        let starts: Vec<usize> = (0..rows.len())
+            .filter(|&i| is_change(&rows[i]) && (i == 0 || !is_change(&rows[i - 1])))
+            .collect();
+        if starts.is_empty() {
+            return;
+        }
+        let next = self.change.map_or(0, |current| (current + 1) % starts.len());
+        self.change = Some(next);
+        self.scroll = starts[next].saturating_sub(2);
+    }
+
+    fn row_count(&self) -> usize {
+        self.rows().len()
+    }
+
+    /// Rows as displayed: one entry per row in unified mode, paired old/new columns side by side
+    fn rows(&self) -> Vec<(Option<&DiffEntry>, Option<&DiffEntry>)> {
+        if self.side_by_side {
+            side_by_side_rows(&self.entries)
+        } else {
+            self.entries.iter().map(|entry| (Some(entry), None)).collect()
+        }
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let layout = if self.side_by_side { "side by side" } else { "unified" };
+        let whitespace = if self.options.ignore_whitespace { ", ignoring whitespace" } else { "" };
+        let block = Block::default()
+            .title(format!(
+                " 🔍 {} ({}, {}{}) - j/k: scroll  n: next change  s: layout  w: whitespace  a: algorithm  Esc: close ",
+                self.title, layout, self.options.algorithm.label(), whitespace
+            ))
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        self.page = inner.height as usize;
+
+        let rows = self.rows();
+        let visible = rows.iter().skip(self.scroll).take(inner.height as usize);
+
+        if self.side_by_side {
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(inner);
+            let (left, right): (Vec<Line>, Vec<Line>) = visible
+                .map(|(old, new)| (side_line(*old, true), side_line(*new, false)))
+                .unzip();
+            frame.render_widget(Paragraph::new(left), columns[0]);
+            frame.render_widget(Paragraph::new(right), columns[1]);
+        } else {
+            let lines: Vec<Line> = visible.filter_map(|(entry, _)| entry.map(unified_line)).collect();
+            frame.render_widget(Paragraph::new(lines), inner);
+        }
+    }
+}
+
+fn entries(old: &str, new: &str, options: DiffOptions) -> Vec<DiffEntry> {
+    let (mut old_line, mut new_line) = (0, 0);
+    let mut entries: Vec<DiffEntry> = diff::diff_lines_with(old, new, options)
+        .into_iter()
+        .map(|line| match line {
+            DiffLine::Equal(text) => {
+                old_line += 1;
+                new_line += 1;
+                DiffEntry { kind: ChangeKind::Equal, old_line: Some(old_line), new_line: Some(new_line), text: text.to_string(), runs: Vec::new() }
+            }
+            DiffLine::Delete(text) => {
+                old_line += 1;
+                DiffEntry { kind: ChangeKind::Delete, old_line: Some(old_line), new_line: None, text: text.to_string(), runs: Vec::new() }
+            }
+            DiffLine::Insert(text) => {
+                new_line += 1;
+                DiffEntry { kind: ChangeKind::Insert, old_line: None, new_line: Some(new_line), text: text.to_string(), runs: Vec::new() }
+            }
+        })
+        .collect();
+
+    // Highlight what changed inside lines that were edited rather than added or removed
+    let mut i = 0;
+    while i < entries.len() {
+        let deletes = entries[i..].iter().take_while(|entry| entry.kind == ChangeKind::Delete).count();
+        let inserts = entries[i + deletes..].iter().take_while(|entry| entry.kind == ChangeKind::Insert).count();
+        for row in 0..deletes.min(inserts) {
+            let (old_runs, new_runs) = diff::inline_diff(&entries[i + row].text, &entries[i + deletes + row].text, Granularity::Word);
+            entries[i + row].runs = old_runs;
+            entries[i + deletes + row].runs = new_runs;
+        }
+        i += (deletes + inserts).max(1);
+    }
+    entries
+}
+
+/// Pair each run of deletions with the insertions that follow it, so edited lines sit side by side
+fn side_by_side_rows(entries: &[DiffEntry]) -> Vec<(Option<&DiffEntry>, Option<&DiffEntry>)> {
+    let mut rows = Vec::new();
+    let mut i = 0;
+    while i < entries.len() {
+        if entries[i].kind == ChangeKind::Equal {
+            rows.push((Some(&entries[i]), Some(&entries[i])));
+            i += 1;
+            continue;
+        }
+        let deletes = entries[i..].iter().take_while(|entry| entry.kind == ChangeKind::Delete).count();
+        let inserts = entries[i + deletes..].iter().take_while(|entry| entry.kind == ChangeKind::Insert).count();
+        for row in 0..deletes.max(inserts) {
+            let old = (row < deletes).then(|| &entries[i + row]);
+            let new = (row < inserts).then(|| &entries[i + deletes + row]);
+            rows.push((old, new));
+        }
+        i += deletes + inserts;
+    }
+    rows
+}
+
+fn change_style(kind: ChangeKind) -> Style {
+    match kind {
+        ChangeKind::Equal => Style::default().fg(Color::Gray),
+        ChangeKind::Delete => Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ChangeKind::Insert => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+    }
+}
+
+/// The entry's text, with the parts that changed within the line in reverse video
+fn text_spans(entry: &DiffEntry, marker: &str) -> Vec<Span<'static>> {
+    let style = change_style(entry.kind);
+    if entry.runs.is_empty() {
+        return vec![Span::styled(format!("{}{}", marker, entry.text), style)];
+    }
+    let mut spans = vec![Span::styled(marker.to_string(), style)];
+    for (text, changed) in &entry.runs {
+        let style = if *changed { style.add_modifier(Modifier::REVERSED) } else { style.remove_modifier(Modifier::BOLD) };
+        spans.push(Span::styled(text.clone(), style));
+    }
+    spans
+}
+
+fn side_line(entry: Option<&DiffEntry>, old_side: bool) -> Line<'static> {
+    let Some(entry) = entry else {
+        return Line::from(Span::styled("     ╱", Style::default().fg(Color::DarkGray)));
+    };
+    let number = if old_side { entry.old_line } else { entry.new_line };
+    let mut spans = vec![Span::styled(format!("{:4} │ ", number.unwrap_or_default()), Style::default().fg(Color::DarkGray))];
+    let marker = match entry.kind {
+        ChangeKind::Equal => " ",
+        ChangeKind::Delete => "-",
+        ChangeKind::Insert => "+",
+    };
+    spans.extend(text_spans(entry, marker));
+    Line::from(spans)
+}
+
+fn unified_line(entry: &DiffEntry) -> Line<'static> {
+    let marker = match entry.kind {
+        ChangeKind::Equal => ' ',
+        ChangeKind::Delete => '-',
+        ChangeKind::Insert => '+',
+    };
+    let number = |line: Option<usize>| line.map(|n| format!("{:4}", n)).unwrap_or_else(|| "    ".to_string());
+    let mut spans = vec![Span::styled(format!("{} {} │ ", number(entry.old_line), number(entry.new_line)), Style::default().fg(Color::DarkGray))];
+    spans.extend(text_spans(entry, &marker.to_string()));
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_side_by_side_rows() {
+        let entries = entries("a\nb\nc\nd", "a\nB\nC2\nextra\nd", DiffOptions::default());
+        let rows: Vec<(Option<&str>, Option<&str>)> = side_by_side_rows(&entries)
+            .into_iter()
+            .map(|(old, new)| (old.map(|e| e.text.as_str()), new.map(|e| e.text.as_str())))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (Some("a"), Some("a")),
+                (Some("b"), Some("B")),
+                (Some("c"), Some("C2")),
+                (None, Some("extra")),
+                (Some("d"), Some("d")),
+            ]
+        );
+        assert_eq!(entries.last().map(|e| (e.old_line, e.new_line)), Some((Some(4), Some(5))));
+        assert_eq!(entries[1].runs, vec![("b".to_string(), true)]);
+        assert!(entries[5].runs.is_empty()); // "extra" was added, not edited
+
+        let mut view = DiffView::new();
+        assert_eq!(view.open("t".to_string(), "a\nb\nc\nd", "a\nB\nC2\nextra\nd"), 5);
+        assert_eq!(view.scroll, 0);
+        assert_eq!(view.open("t".to_string(), "same", "same"), 0);
+
+        let old: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut new = old.clone();
+        new[5] = "five".to_string();
+        new[15] = "fifteen".to_string();
+        view.open("t".to_string(), &old.join("\n"), &new.join("\n"));
+        assert_eq!(view.scroll, 3);
+        view.next_change();
+        assert_eq!(view.scroll, 13);
+        view.next_change();
+        assert_eq!(view.scroll, 3);
+    }
+}
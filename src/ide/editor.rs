@@ -1,4 +1,15 @@
 use crate::ide::app::AppMode;
+use crate::ide::buffer::TextBuffer;
+use crate::ide::encoding::{self, LineEnding, TextEncoding};
+use crate::ide::filetype::{FileSettings, FiletypeSettings};
+use crate::ide::hexview;
+use crate::ide::gutter::GutterSettings;
+use crate::ide::indent::{self, IndentSettings};
+use crate::ide::language::{self, Language};
+use crate::ide::long_lines;
+use crate::ide::save_hooks::{self, SaveHooks};
+use crate::ide::theme;
+use crate::ide::whitespace;
 use anyhow::Result;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect, Alignment},
@@ -7,21 +18,45 @@ use ratatui::{
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::PathBuf, time::SystemTime};
+
+// Characters searched for a matching bracket before giving up
+const MAX_BRACKET_SCAN: usize = 100_000;
 
 #[derive(Debug, Clone)]
 pub struct EditorTab {
     pub file_path: Option<PathBuf>,
     pub file_name: String,
-    pub content: String,
-    pub lines: Vec<String>,
+    pub buffer: TextBuffer,
     pub cursor_line: usize,
     pub cursor_col: usize,
     pub scroll_offset: usize,
+    pub scroll_col: usize, // First character shown on each line, for horizontal scrolling
     pub is_modified: bool,
+    pub folds: Vec<(usize, usize)>, // Folded (header line, last hidden line) ranges, sorted
+    pub selection_anchor: Option<usize>, // Line a `V` line selection started on; it runs to the cursor's line
+    pub disk_mtime: Option<SystemTime>, // Modification time of the file when it was last loaded or saved
+    pub changed_on_disk: bool, // Modified externally while the buffer had unsaved edits
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    pub trailing_newline: bool,
+    pub lossy_decoded: bool, // File was not valid in its encoding; invalid bytes were replaced
+    pub read_only: bool, // Editing and saving are disabled
+    pub is_binary: bool, // Buffer holds a hex preview, not the file's content
+    pub long_line: Option<usize>, // Bytes in the longest line, when it is too long to edit comfortably
+    pub language_override: Option<&'static str>, // Language id picked by the user over the detected one
+    pub pinned: bool, // Kept leftmost in the tab bar and skipped by "Close Others" / "Close to the Right"
+    pub settings: FileSettings, // Indentation, ruler and save cleanup for this file
+    settings_for: Option<(String, Option<PathBuf>)>, // Settings name and path `settings` was resolved for
     pub id: u32, // Unique identifier for tab management
 }
 
+impl Default for EditorTab {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl EditorTab {
     pub fn new() -> Self {
         use std::time::{SystemTime, UNIX_EPOCH};
@@ -33,23 +68,33 @@ impl EditorTab {
         Self {
             file_path: None,
             file_name: "Untitled".to_string(),
-            content: String::new(),
-            lines: vec![String::new()],
+            buffer: TextBuffer::new(),
             cursor_line: 0,
             cursor_col: 0,
             scroll_offset: 0,
+            scroll_col: 0,
             is_modified: false,
+            folds: Vec::new(),
+            selection_anchor: None,
+            disk_mtime: None,
+            changed_on_disk: false,
+            encoding: TextEncoding::Utf8,
+            line_ending: LineEnding::Lf,
+            trailing_newline: true,
+            lossy_decoded: false,
+            read_only: false,
+            is_binary: false,
+            long_line: None,
+            language_override: None,
+            pinned: false,
+            settings: FileSettings::default(),
+            settings_for: None,
             id,
         }
     }
 
     pub fn from_file(path: PathBuf) -> Result<Self> {
-        let content = fs::read_to_string(&path)?;
-        let lines: Vec<String> = if content.is_empty() {
-            vec![String::new()]
-        } else {
-            content.lines().map(|s| s.to_string()).collect()
-        };
+        let (buffer, decoded, is_binary) = read_file(&path)?;
 
         let file_name = path.file_name()
             .and_then(|name| name.to_str())
@@ -62,113 +107,739 @@ impl EditorTab {
             .unwrap()
             .as_nanos() as u32;
 
+        let disk_mtime = file_mtime(&path);
+        let long_line = long_lines::longest_line(&decoded.lines);
+        // Minified files open as a read-only view until the user picks pretty-printing or editing
+        let read_only = is_binary || long_line.is_some() || is_write_protected(&path);
+
         Ok(Self {
             file_path: Some(path),
             file_name,
-            content,
-            lines,
+            buffer,
             cursor_line: 0,
             cursor_col: 0,
             scroll_offset: 0,
+            scroll_col: 0,
             is_modified: false,
+            folds: Vec::new(),
+            selection_anchor: None,
+            disk_mtime,
+            changed_on_disk: false,
+            encoding: decoded.encoding,
+            line_ending: decoded.line_ending,
+            trailing_newline: decoded.trailing_newline,
+            lossy_decoded: decoded.lossy,
+            read_only,
+            is_binary,
+            long_line,
+            language_override: None,
+            pinned: false,
+            settings: FileSettings::default(),
+            settings_for: None,
             id,
         })
     }
 
-    pub fn save(&mut self) -> Result<()> {
-        if let Some(path) = &self.file_path {
-            self.content = self.lines.join("\n");
-            fs::write(path, &self.content)?;
-            self.is_modified = false;
+    /// Tab restored from an autosave swap file; it starts out modified so it can be saved back
+    pub fn recovered(file_path: Option<PathBuf>, file_name: String, content: &str) -> Self {
+        let mut tab = Self::new();
+        tab.buffer = TextBuffer::from_text(content);
+        tab.file_path = file_path;
+        tab.file_name = file_name;
+        tab.is_modified = true;
+        tab
+    }
+
+    /// Run the save hooks and write the file. A failing formatter doesn't block the save:
+    /// the file is written unformatted and the formatter's error is returned
+    pub fn save(&mut self, hooks: &SaveHooks) -> Result<Option<String>> {
+        let formatted = self.format_job(hooks).map(|(command, text)| save_hooks::run_formatter(&command, &text));
+        self.write(formatted)
+    }
+
+    /// The formatter command for the file and the cleaned-up text to pipe through it, when it has one;
+    /// the caller may run it elsewhere and hand the result to `write`
+    pub fn format_job(&self, hooks: &SaveHooks) -> Option<(String, String)> {
+        let path = self.file_path.as_ref().filter(|_| !self.read_only)?;
+        let command = hooks.formatter_for_key(&language::settings_key(&self.settings_name()), path)?;
+        let (lines, trailing_newline) = self.cleaned_lines();
+        let mut text = lines.join("\n");
+        if trailing_newline {
+            text.push('\n');
+        }
+        Some((command, text))
+    }
+
+    /// Write the cleaned-up buffer, or `formatted`, what its formatter printed, when that worked.
+    /// Returns the formatter's error when the file was written unformatted
+    pub fn write(&mut self, formatted: Option<Result<String>>) -> Result<Option<String>> {
+        if self.read_only {
+            anyhow::bail!("'{}' is read-only", self.file_name);
         }
+        let Some(path) = self.file_path.clone() else {
+            return Ok(None);
+        };
+
+        let (mut lines, mut trailing_newline) = self.cleaned_lines();
+        let mut formatter_error = None;
+        match formatted {
+            Some(Ok(formatted)) => {
+                trailing_newline = formatted.ends_with('\n');
+                lines = formatted
+                    .strip_suffix('\n')
+                    .unwrap_or(&formatted)
+                    .split('\n')
+                    .map(|line| line.trim_end_matches('\r').to_string())
+                    .collect();
+            }
+            Some(Err(e)) => formatter_error = Some(e.to_string()),
+            None => {}
+        }
+        if self.buffer.lines().ne(lines.iter().cloned()) {
+            self.replace_lines(&lines);
+        }
+        self.trailing_newline = trailing_newline;
+
+        // Write back with the encoding, BOM and line endings the file was loaded with
+        fs::write(&path, encoding::encode(&lines, self.encoding, self.line_ending, self.trailing_newline))?;
+        self.is_modified = false;
+        self.disk_mtime = file_mtime(&path);
+        self.changed_on_disk = false;
+        Ok(formatter_error)
+    }
+
+    /// The buffer's lines with trailing whitespace and final newlines handled as its settings say
+    fn cleaned_lines(&self) -> (Vec<String>, bool) {
+        let mut lines: Vec<String> = self.buffer.lines().collect();
+        let trailing_newline = save_hooks::clean_lines(
+            &mut lines,
+            self.trailing_newline,
+            self.settings.trim_trailing_whitespace,
+            self.settings.ensure_final_newline,
+        );
+        (lines, trailing_newline)
+    }
+
+    /// Swap in rewritten content, keeping the cursor in bounds
+    fn replace_lines(&mut self, lines: &[String]) {
+        if lines.len() != self.buffer.len_lines() {
+            self.folds.clear();
+        }
+        self.buffer = TextBuffer::from_lines(lines);
+        self.cursor_line = self.cursor_line.min(self.buffer.len_lines() - 1);
+        self.adjust_cursor_col();
+    }
+
+    /// Whether the file was modified on disk since it was loaded or last saved
+    pub fn has_changed_on_disk(&self) -> bool {
+        match (&self.file_path, self.disk_mtime) {
+            (Some(path), Some(known)) => file_mtime(path).is_some_and(|current| current != known),
+            _ => false,
+        }
+    }
+
+    /// The saved file's content, decoded the same way the buffer was
+    pub fn disk_text(&self) -> Result<String> {
+        let path = self.file_path.as_ref().ok_or_else(|| anyhow::anyhow!("'{}' has not been saved yet", self.file_name))?;
+        Ok(read_file(path)?.0.to_string())
+    }
+
+    /// Replace the buffer with the current on-disk content
+    pub fn reload(&mut self) -> Result<()> {
+        let Some(path) = self.file_path.clone() else {
+            return Ok(());
+        };
+        let (buffer, decoded, is_binary) = read_file(&path)?;
+        self.buffer = buffer;
+        self.is_binary = is_binary;
+        self.read_only = is_binary || is_write_protected(&path);
+        self.encoding = decoded.encoding;
+        self.line_ending = decoded.line_ending;
+        self.trailing_newline = decoded.trailing_newline;
+        self.lossy_decoded = decoded.lossy;
+        self.long_line = long_lines::longest_line(&decoded.lines);
+        self.folds.clear();
+        self.cursor_line = self.cursor_line.min(self.buffer.len_lines() - 1);
+        self.adjust_cursor_col();
+        self.is_modified = false;
+        self.disk_mtime = file_mtime(&path);
+        self.changed_on_disk = false;
         Ok(())
     }
 
-    pub fn insert_char(&mut self, c: char) {
-        if self.cursor_line < self.lines.len() {
-            let line = &mut self.lines[self.cursor_line];
-            if self.cursor_col <= line.len() {
-                line.insert(self.cursor_col, c);
-                self.cursor_col += 1;
-                self.is_modified = true;
+    /// The language picked for this tab, or the detected one
+    pub fn language(&self) -> &'static Language {
+        self.language_override.and_then(language::by_id).unwrap_or_else(|| self.detected_language())
+    }
+
+    /// Language from the file name and `#!` line, ignoring any override
+    pub fn detected_language(&self) -> &'static Language {
+        language::detect(&self.file_name, Some(&self.buffer.line_window(0, 0, 200).1))
+    }
+
+    /// Name that per-filetype settings (indentation, formatters) are looked up under
+    pub fn settings_name(&self) -> String {
+        match self.language_override.and_then(language::by_id) {
+            Some(language) => language.settings_key().to_string(),
+            None => self.file_name.clone(),
+        }
+    }
+
+    /// Look the file's settings up again if its name, path or language changed since the last time
+    pub fn refresh_settings(&mut self, indent: &IndentSettings, hooks: &SaveHooks, filetypes: &HashMap<String, FiletypeSettings>) {
+        let key = (self.settings_name(), self.file_path.clone());
+        if self.settings_for.as_ref() != Some(&key) {
+            self.settings = FileSettings::resolve(&key.0, key.1.as_deref(), indent, hooks, filetypes);
+            self.settings_for = Some(key);
+        }
+    }
+
+    /// Comment out the cursor line with the language's line comment, or uncomment it.
+    /// Returns false when the language has no line comments
+    pub fn toggle_comment(&mut self) -> bool {
+        let Some(token) = self.language().line_comment else {
+            return false;
+        };
+        if self.read_only {
+            return true;
+        }
+        let text = self.buffer.line(self.cursor_line).unwrap_or_default();
+        let indent = text.len() - text.trim_start().len();
+        let rest = &text[indent..];
+        if let Some(uncommented) = rest.strip_prefix(token) {
+            let removed = token.len() + usize::from(uncommented.starts_with(' '));
+            self.buffer.remove(self.cursor_line, indent, indent + removed);
+            if self.cursor_col > indent {
+                self.cursor_col = self.cursor_col.saturating_sub(removed).max(indent);
             }
+            self.is_modified = true;
+        } else if !rest.is_empty() {
+            let inserted = format!("{} ", token);
+            self.buffer.insert(self.cursor_line, indent, &inserted);
+            if self.cursor_col >= indent {
+                self.cursor_col += inserted.len();
+            }
+            self.is_modified = true;
+        }
+        true
+    }
+
+    /// Lift the long-line read-only view (binary and write-protected files stay read-only)
+    pub fn allow_editing(&mut self) {
+        self.read_only = self.is_binary || self.file_path.as_deref().is_some_and(is_write_protected);
+    }
+
+    /// Reformat the buffer (see `long_lines::pretty_print`); the result is unsaved and editable
+    pub fn pretty_print(&mut self, hooks: &SaveHooks, indent: &str) -> Result<()> {
+        if self.is_binary {
+            anyhow::bail!("'{}' is a binary file", self.file_name);
+        }
+        let path = self.file_path.clone().unwrap_or_else(|| PathBuf::from(&self.file_name));
+        let key = language::settings_key(&self.settings_name());
+        let formatted = long_lines::pretty_print(&self.buffer.to_string(), &key, &path, hooks, indent)?;
+        let lines: Vec<String> = formatted.strip_suffix('\n').unwrap_or(&formatted).split('\n').map(str::to_string).collect();
+        self.replace_lines(&lines);
+        self.long_line = long_lines::longest_line(&lines);
+        (self.cursor_line, self.cursor_col, self.scroll_offset, self.scroll_col) = (0, 0, 0, 0);
+        self.is_modified = true;
+        self.allow_editing();
+        Ok(())
+    }
+
+    /// Keep the buffer as-is and accept that saving will overwrite the external change
+    pub fn keep_buffer_over_disk(&mut self) {
+        if let Some(path) = &self.file_path {
+            self.disk_mtime = file_mtime(path);
+        }
+        self.changed_on_disk = false;
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        if self.read_only {
+            return;
+        }
+        if self.cursor_line < self.buffer.len_lines() && self.cursor_col <= self.buffer.line_len(self.cursor_line) {
+            self.buffer.insert(self.cursor_line, self.cursor_col, c.encode_utf8(&mut [0; 4]));
+            self.cursor_col += c.len_utf8();
+            self.is_modified = true;
         }
     }
 
     pub fn insert_newline(&mut self) {
-        if self.cursor_line < self.lines.len() {
-            let current_line = self.lines[self.cursor_line].clone();
-            let (left, right) = current_line.split_at(self.cursor_col);
-            
-            self.lines[self.cursor_line] = left.to_string();
-            self.lines.insert(self.cursor_line + 1, right.to_string());
+        if self.read_only {
+            return;
+        }
+        if self.cursor_line < self.buffer.len_lines() {
+            self.buffer.split_line(self.cursor_line, self.cursor_col);
             
             self.cursor_line += 1;
             self.cursor_col = 0;
             self.is_modified = true;
+            // Line numbers shifted, so fold ranges are no longer valid
+            self.folds.clear();
         }
     }
 
     pub fn backspace(&mut self) {
+        if self.read_only {
+            return;
+        }
         if self.cursor_col > 0 {
             // Delete character before cursor
-            if self.cursor_line < self.lines.len() {
-                let line = &mut self.lines[self.cursor_line];
-                if self.cursor_col <= line.len() {
-                    line.remove(self.cursor_col - 1);
-                    self.cursor_col -= 1;
-                    self.is_modified = true;
-                }
+            if self.cursor_line < self.buffer.len_lines() {
+                let prev_col = self.buffer.prev_col(self.cursor_line, self.cursor_col);
+                self.buffer.remove(self.cursor_line, prev_col, self.cursor_col);
+                self.cursor_col = prev_col;
+                self.is_modified = true;
             }
         } else if self.cursor_line > 0 {
             // Join with previous line
-            let current_line = self.lines.remove(self.cursor_line);
             self.cursor_line -= 1;
-            self.cursor_col = self.lines[self.cursor_line].len();
-            self.lines[self.cursor_line].push_str(&current_line);
+            self.cursor_col = self.buffer.line_len(self.cursor_line);
+            self.buffer.join_with_next(self.cursor_line);
+            self.is_modified = true;
+            self.folds.clear();
+        }
+    }
+
+    /// Indent lines `first..=last` by one level; the cursor stays on the same character
+    pub fn indent_lines(&mut self, first: usize, last: usize, unit: &str) {
+        if self.read_only {
+            return;
+        }
+        for line in first..=last.min(self.buffer.len_lines() - 1) {
+            self.buffer.insert(line, 0, unit);
+            if line == self.cursor_line {
+                self.cursor_col += unit.len();
+            }
             self.is_modified = true;
         }
     }
 
+    /// Remove one level of indentation (a tab or up to `tab_width` spaces) from lines `first..=last`
+    pub fn dedent_lines(&mut self, first: usize, last: usize, tab_width: usize) {
+        if self.read_only {
+            return;
+        }
+        for line in first..=last.min(self.buffer.len_lines() - 1) {
+            let width = self.buffer.line(line).map_or(0, |text| indent::dedent_width(&text, tab_width));
+            if width == 0 {
+                continue;
+            }
+            self.buffer.remove(line, 0, width);
+            if line == self.cursor_line {
+                self.cursor_col = self.cursor_col.saturating_sub(width);
+            }
+            self.is_modified = true;
+        }
+    }
+
+    /// First and last line of the `V` selection, or the cursor's line when nothing is selected
+    pub fn selected_lines(&self) -> (usize, usize) {
+        let last_line = self.buffer.len_lines().saturating_sub(1);
+        let anchor = self.selection_anchor.unwrap_or(self.cursor_line).min(last_line);
+        (anchor.min(self.cursor_line), anchor.max(self.cursor_line))
+    }
+
+    /// Tab: indent the selection, or the line from its leading whitespace, else pad to the next tab stop
+    pub fn smart_tab(&mut self, unit: &str, tab_width: usize) {
+        if self.selection_anchor.is_some() || self.cursor_col <= self.first_non_blank(self.cursor_line) {
+            let (first, last) = self.selected_lines();
+            self.indent_lines(first, last, unit);
+        } else if unit == "\t" {
+            self.insert_char('\t');
+        } else {
+            // cursor_col is a byte offset; tab stops count characters
+            let column = self.buffer.line(self.cursor_line)
+                .map_or(0, |line| line.char_indices().take_while(|(i, _)| *i < self.cursor_col).count());
+            for _ in 0..tab_width - column % tab_width {
+                self.insert_char(' ');
+            }
+        }
+    }
+
+    /// Swap the cursor's line with the one above (`up`) or below it; the cursor moves along
+    pub fn move_line(&mut self, up: bool) {
+        if self.read_only {
+            return;
+        }
+        let other = if up {
+            match self.cursor_line.checked_sub(1) {
+                Some(line) => line,
+                None => return,
+            }
+        } else if self.cursor_line + 1 < self.buffer.len_lines() {
+            self.cursor_line + 1
+        } else {
+            return;
+        };
+        let (Some(current), Some(neighbour)) = (self.buffer.line(self.cursor_line), self.buffer.line(other)) else {
+            return;
+        };
+        self.buffer.replace_line(self.cursor_line, &neighbour);
+        self.buffer.replace_line(other, &current);
+        self.cursor_line = other;
+        self.is_modified = true;
+        self.folds.clear();
+    }
+
+    /// Copy the cursor's line below itself and move onto the copy
+    pub fn duplicate_line(&mut self) {
+        if self.read_only {
+            return;
+        }
+        let Some(text) = self.buffer.line(self.cursor_line) else {
+            return;
+        };
+        self.buffer.insert(self.cursor_line, self.buffer.line_len(self.cursor_line), &format!("\n{}", text));
+        self.cursor_line += 1;
+        self.is_modified = true;
+        self.folds.clear();
+    }
+
+    /// Vim `J`: append the next line to this one, separated by a single space
+    pub fn join_lines(&mut self) {
+        if self.read_only || self.cursor_line + 1 >= self.buffer.len_lines() {
+            return;
+        }
+        let line = self.cursor_line;
+        let current = self.buffer.line(line).unwrap_or_default();
+        let next = self.buffer.line(line + 1).unwrap_or_default();
+        let next = next.trim_start();
+        let kept = current.trim_end();
+        let separator = if kept.is_empty() || next.is_empty() || next.starts_with(')') { "" } else { " " };
+
+        let joined = format!("{}{}{}", kept, separator, next);
+        self.buffer.join_with_next(line);
+        self.buffer.replace_line(line, &joined);
+        self.cursor_col = kept.len();
+        self.is_modified = true;
+        self.folds.clear();
+    }
+
     pub fn move_cursor_up(&mut self) {
         if self.cursor_line > 0 {
             self.cursor_line -= 1;
+            // Step over a folded region onto its header
+            if let Some(&(start, _)) = self.folds.iter().find(|(start, end)| self.cursor_line > *start && self.cursor_line <= *end) {
+                self.cursor_line = start;
+            }
             self.adjust_cursor_col();
         }
     }
 
     pub fn move_cursor_down(&mut self) {
-        if self.cursor_line < self.lines.len().saturating_sub(1) {
-            self.cursor_line += 1;
+        let next_line = match self.folds.iter().find(|(start, _)| *start == self.cursor_line) {
+            Some(&(_, end)) => end + 1,
+            None => self.cursor_line + 1,
+        };
+        if next_line < self.buffer.len_lines() {
+            self.cursor_line = next_line;
             self.adjust_cursor_col();
         }
     }
 
+    /// Indentation-based foldable range starting at `line`: the following lines indented deeper
+    pub fn fold_range_at(&self, line: usize) -> Option<(usize, usize)> {
+        let header = self.buffer.line(line)?;
+        if header.trim().is_empty() {
+            return None;
+        }
+        let indent = indent_width(&header);
+
+        let mut end = line;
+        for (i, text) in self.buffer.lines_from(line + 1).enumerate().map(|(i, text)| (i + line + 1, text)) {
+            if text.trim().is_empty() {
+                continue;
+            }
+            if indent_width(&text) <= indent {
+                // A closing brace at the header's indentation belongs to the block
+                if indent_width(&text) == indent && text.trim_start().starts_with(['}', ')', ']']) {
+                    end = i;
+                }
+                break;
+            }
+            end = i;
+        }
+
+        (end > line).then_some((line, end))
+    }
+
+    /// Fold the block at the cursor (or the block enclosing it), or unfold it if already folded
+    pub fn toggle_fold(&mut self) {
+        if let Some(index) = self.folds.iter().position(|(start, end)| self.cursor_line >= *start && self.cursor_line <= *end) {
+            self.folds.remove(index);
+            return;
+        }
+
+        if let Some((start, end)) = self.blocks_around_cursor().into_iter().next() {
+            // Nested folds inside the new one are absorbed
+            self.folds.retain(|(s, e)| !(*s >= start && *e <= end));
+            self.folds.push((start, end));
+            self.folds.sort_unstable();
+            self.cursor_line = start;
+            self.adjust_cursor_col();
+        }
+    }
+
+    /// Foldable blocks containing the cursor line, innermost first
+    fn blocks_around_cursor(&self) -> Vec<(usize, usize)> {
+        (0..=self.cursor_line)
+            .rev()
+            .filter_map(|line| self.fold_range_at(line))
+            .filter(|(_, end)| *end >= self.cursor_line)
+            .collect()
+    }
+
+    /// Lines of the function around the cursor, or else of its innermost block, or else just the cursor line
+    pub fn code_region(&self) -> (usize, usize) {
+        let blocks = self.blocks_around_cursor();
+        let is_function = |line: usize| {
+            self.buffer.line(line).is_some_and(|header| {
+                header.split(|c: char| !c.is_alphanumeric()).any(|word| FUNCTION_KEYWORDS.contains(&word))
+            })
+        };
+        blocks
+            .iter()
+            .find(|(start, _)| is_function(*start))
+            .or(blocks.first())
+            .copied()
+            .unwrap_or((self.cursor_line, self.cursor_line))
+    }
+
+    pub fn unfold_all(&mut self) {
+        self.folds.clear();
+    }
+
+    /// Open any fold hiding the cursor line (after jumps like `G` or `%`)
+    pub fn reveal_cursor(&mut self) {
+        let line = self.cursor_line;
+        self.folds.retain(|(start, end)| !(line > *start && line <= *end));
+    }
+
+    /// Rows between two lines as displayed, counting a folded block as one row
+    pub fn visible_distance(&self, a: usize, b: usize) -> usize {
+        let (from, to) = (a.min(b), a.max(b));
+        if self.folds.is_empty() {
+            return to - from;
+        }
+        (from + 1..=to).filter(|line| !self.is_line_hidden(*line)).count()
+    }
+
+    pub fn is_line_hidden(&self, line: usize) -> bool {
+        self.folds.iter().any(|(start, end)| line > *start && line <= *end)
+    }
+
     pub fn move_cursor_left(&mut self) {
         if self.cursor_col > 0 {
-            self.cursor_col -= 1;
+            self.cursor_col = self.buffer.prev_col(self.cursor_line, self.cursor_col);
         } else if self.cursor_line > 0 {
             self.cursor_line -= 1;
-            self.cursor_col = self.lines.get(self.cursor_line)
-                .map(|line| line.len())
-                .unwrap_or(0);
+            self.cursor_col = self.buffer.line_len(self.cursor_line);
         }
     }
 
     pub fn move_cursor_right(&mut self) {
-        if let Some(line) = self.lines.get(self.cursor_line) {
-            if self.cursor_col < line.len() {
-                self.cursor_col += 1;
-            } else if self.cursor_line < self.lines.len().saturating_sub(1) {
-                self.cursor_line += 1;
-                self.cursor_col = 0;
+        if let Some(c) = self.buffer.char_at(self.cursor_line, self.cursor_col) {
+            self.cursor_col += c.len_utf8();
+        } else if self.cursor_line < self.buffer.len_lines().saturating_sub(1) {
+            self.cursor_line += 1;
+            self.cursor_col = 0;
+        }
+    }
+
+    pub fn move_word_forward(&mut self) {
+        let start = (self.cursor_line, self.cursor_col);
+        let mut pos = start;
+        let class = char_class(self.char_at(pos));
+
+        // Skip the rest of the current word (or punctuation run)
+        if class != CharClass::Whitespace {
+            while let Some(next) = self.next_pos(pos) {
+                pos = next;
+                if char_class(self.char_at(pos)) != class {
+                    break;
+                }
             }
         }
+
+        // Skip whitespace, including line breaks
+        while char_class(self.char_at(pos)) == CharClass::Whitespace {
+            match self.next_pos(pos) {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+
+        (self.cursor_line, self.cursor_col) = pos;
+    }
+
+    pub fn move_word_backward(&mut self) {
+        let mut pos = match self.prev_pos((self.cursor_line, self.cursor_col)) {
+            Some(prev) => prev,
+            None => return,
+        };
+
+        // Skip whitespace, including line breaks
+        while char_class(self.char_at(pos)) == CharClass::Whitespace {
+            match self.prev_pos(pos) {
+                Some(prev) => pos = prev,
+                None => break,
+            }
+        }
+
+        // Walk back to the start of the word
+        let class = char_class(self.char_at(pos));
+        while let Some(prev) = self.prev_pos(pos) {
+            if char_class(self.char_at(prev)) != class {
+                break;
+            }
+            pos = prev;
+        }
+
+        (self.cursor_line, self.cursor_col) = pos;
+    }
+
+    pub fn move_word_end(&mut self) {
+        let mut pos = match self.next_pos((self.cursor_line, self.cursor_col)) {
+            Some(next) => next,
+            None => return,
+        };
+
+        // Skip whitespace, including line breaks
+        while char_class(self.char_at(pos)) == CharClass::Whitespace {
+            match self.next_pos(pos) {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+
+        // Walk forward to the last character of the word
+        let class = char_class(self.char_at(pos));
+        while let Some(next) = self.next_pos(pos) {
+            if next.0 != pos.0 || char_class(self.char_at(next)) != class {
+                break;
+            }
+            pos = next;
+        }
+
+        (self.cursor_line, self.cursor_col) = pos;
+    }
+
+    pub fn move_line_start(&mut self) {
+        self.cursor_col = 0;
+    }
+
+    pub fn move_line_end(&mut self) {
+        // Land on the last character, like vim's `$`
+        let len = self.buffer.line_len(self.cursor_line);
+        self.cursor_col = self.buffer.prev_col(self.cursor_line, len);
+    }
+
+    /// Put the cursor at a position, clamped to the buffer, opening any fold around it
+    pub fn move_to(&mut self, line: usize, col: usize) {
+        self.cursor_line = line.min(self.buffer.len_lines().saturating_sub(1));
+        self.cursor_col = col;
+        self.adjust_cursor_col();
+        self.reveal_cursor();
+    }
+
+    pub fn move_buffer_start(&mut self) {
+        self.cursor_line = 0;
+        self.cursor_col = self.first_non_blank(0);
+    }
+
+    pub fn move_buffer_end(&mut self) {
+        self.cursor_line = self.buffer.len_lines().saturating_sub(1);
+        self.cursor_col = self.first_non_blank(self.cursor_line);
+    }
+
+    fn first_non_blank(&self, line_index: usize) -> usize {
+        self.buffer.line(line_index)
+            .and_then(|line| line.char_indices().find(|(_, c)| !c.is_whitespace()).map(|(i, _)| i))
+            .unwrap_or(0)
+    }
+
+    /// Character under a (line, byte column) position; line ends read as '\n'
+    fn char_at(&self, (line, col): (usize, usize)) -> char {
+        self.buffer.char_at(line, col).unwrap_or('\n')
+    }
+
+    fn next_pos(&self, (line, col): (usize, usize)) -> Option<(usize, usize)> {
+        if let Some(c) = self.buffer.char_at(line, col) {
+            Some((line, col + c.len_utf8()))
+        } else if line + 1 < self.buffer.len_lines() {
+            Some((line + 1, 0))
+        } else {
+            None
+        }
+    }
+
+    fn prev_pos(&self, (line, col): (usize, usize)) -> Option<(usize, usize)> {
+        if col > 0 {
+            Some((line, self.buffer.prev_col(line, col)))
+        } else if line > 0 {
+            Some((line - 1, self.buffer.line_len(line - 1)))
+        } else {
+            None
+        }
+    }
+
+    /// Position of the bracket matching the one under the cursor, if any
+    pub fn find_matching_bracket(&self) -> Option<(usize, usize)> {
+        let cursor = (self.cursor_line, self.cursor_col);
+        let current = self.char_at(cursor);
+        let (open, close, forward) = match current {
+            '(' => ('(', ')', true),
+            '[' => ('[', ']', true),
+            '{' => ('{', '}', true),
+            ')' => ('(', ')', false),
+            ']' => ('[', ']', false),
+            '}' => ('{', '}', false),
+            _ => return None,
+        };
+
+        let mut depth = 0usize;
+        let mut pos = cursor;
+        // Bounded so a stray bracket in a huge file does not stall every frame
+        for _ in 0..MAX_BRACKET_SCAN {
+            let c = self.char_at(pos);
+            if c == open || c == close {
+                let opens_scope = (c == open) == forward;
+                if opens_scope {
+                    depth += 1;
+                } else {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(pos);
+                    }
+                }
+            }
+
+            pos = if forward { self.next_pos(pos)? } else { self.prev_pos(pos)? };
+        }
+        None
+    }
+
+    pub fn jump_to_matching_bracket(&mut self) {
+        if let Some((line, col)) = self.find_matching_bracket() {
+            self.cursor_line = line;
+            self.cursor_col = col;
+        }
     }
 
     fn adjust_cursor_col(&mut self) {
-        if let Some(line) = self.lines.get(self.cursor_line) {
-            self.cursor_col = self.cursor_col.min(line.len());
+        // Through the rope, so very long lines are never copied out
+        self.cursor_col = self.buffer.floor_col(self.cursor_line, self.cursor_col);
+    }
+
+    /// Scroll sideways so the cursor stays within `width` columns
+    pub fn ensure_cursor_col_visible(&mut self, width: usize) {
+        let col = self.buffer.char_col(self.cursor_line, self.cursor_col);
+        if col < self.scroll_col {
+            self.scroll_col = col;
+        } else if width > 0 && col >= self.scroll_col + width {
+            self.scroll_col = col + 1 - width;
         }
     }
 
@@ -182,15 +853,97 @@ impl EditorTab {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Whitespace,
+    Word,
+    Punctuation,
+}
+
+/// Words that start a function definition in the languages the editor knows
+const FUNCTION_KEYWORDS: &[&str] = &["fn", "def", "function", "func", "fun"];
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Whitespace
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct TabInfo {
     pub file_name: String,
     pub is_modified: bool,
+    pub is_pinned: bool,
+}
+
+/// Something drawn in the tab bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TabBarItem {
+    Tab(usize),
+    ScrollLeft,  // `‹`, shown when tabs are hidden on the left
+    ScrollRight, // `›`, shown when tabs are hidden on the right
+    NewTab,
+}
+
+/// Tab bar laid out for a given width: each item with its column offset and width
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabBar {
+    pub items: Vec<(TabBarItem, u16, u16)>,
+}
+
+impl TabBar {
+    pub fn item_at(&self, offset: u16) -> Option<TabBarItem> {
+        self.items.iter().find(|(_, x, width)| offset >= *x && offset < x + width).map(|(item, _, _)| *item)
+    }
+
+    fn shows(&self, item: TabBarItem) -> bool {
+        self.items.iter().any(|(shown, _, _)| *shown == item)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitDirection {
+    Vertical,   // Panes side by side
+    Horizontal, // Panes stacked
+}
+
+/// Two editor panes; the focused pane always shows `active_tab`, so editing works unchanged
+#[derive(Debug, Clone, Copy)]
+pub struct Split {
+    pub direction: SplitDirection,
+    other_tab_id: u32, // Tab shown in the unfocused pane
+    second_focused: bool, // Right / bottom pane has focus
 }
 
 pub struct Editor {
     pub tabs: Vec<EditorTab>,
     pub active_tab: usize,
+    pub split: Option<Split>,
+    pub gutter: GutterSettings,
+    pub signs: Vec<(PathBuf, usize, char)>, // Shown in the gutter's sign column: file, line, symbol
+    pub ruler: Option<usize>, // Ruler column for files without a `max_line_length`
+    pub show_ruler: bool,
+    pub show_whitespace: bool,
+    pub tab_scroll: usize, // First unpinned tab shown when the tabs don't fit
+    pub tab_bar_area: Rect, // Where the focused pane's tab bar was last drawn, for mouse clicks
+    revealed_tab: Option<u32>, // Active tab the tab bar last scrolled to
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Editor {
@@ -198,9 +951,70 @@ impl Editor {
         Self {
             tabs: Vec::new(),
             active_tab: 0,
+            split: None,
+            gutter: GutterSettings::default(),
+            signs: Vec::new(),
+            ruler: None,
+            show_ruler: true,
+            show_whitespace: false,
+            tab_scroll: 0,
+            tab_bar_area: Rect::default(),
+            revealed_tab: None,
         }
     }
 
+    /// Cycle the layout: single pane -> side by side -> stacked -> single pane
+    pub fn toggle_split(&mut self) {
+        self.split = match self.split {
+            None if self.tabs.is_empty() => None,
+            None => {
+                // Show a different tab in the new pane when there is one
+                let other = (self.active_tab + 1) % self.tabs.len();
+                Some(Split {
+                    direction: SplitDirection::Vertical,
+                    other_tab_id: self.tabs[other].id,
+                    second_focused: false,
+                })
+            }
+            Some(split) if split.direction == SplitDirection::Vertical => {
+                Some(Split { direction: SplitDirection::Horizontal, ..split })
+            }
+            Some(_) => None,
+        };
+    }
+
+    /// Move focus to the other pane; false when the editor isn't split
+    pub fn focus_other_pane(&mut self) -> bool {
+        let Some(split) = self.split.as_mut() else {
+            return false;
+        };
+        let current_id = self.tabs.get(self.active_tab).map(|tab| tab.id);
+        if let Some(index) = self.tabs.iter().position(|tab| tab.id == split.other_tab_id) {
+            self.active_tab = index;
+        }
+        if let Some(id) = current_id {
+            split.other_tab_id = id;
+        }
+        split.second_focused = !split.second_focused;
+        true
+    }
+
+    pub fn is_second_pane_focused(&self) -> bool {
+        self.split.is_some_and(|split| split.second_focused)
+    }
+
+    /// Tab index shown in the unfocused pane; falls back to the active tab if its tab was closed
+    fn other_pane_tab(&self) -> usize {
+        self.split
+            .and_then(|split| self.tabs.iter().position(|tab| tab.id == split.other_tab_id))
+            .unwrap_or(self.active_tab)
+    }
+
+    /// Tab in the unfocused pane, when the editor is split and it shows a different tab
+    pub fn other_pane_tab_index(&self) -> Option<usize> {
+        Some(self.other_pane_tab()).filter(|&index| self.split.is_some() && index != self.active_tab)
+    }
+
     pub fn has_open_files(&self) -> bool {
         !self.tabs.is_empty()
     }
@@ -215,6 +1029,11 @@ impl Editor {
         self.active_tab = self.tabs.len() - 1;
     }
 
+    pub fn open_tab(&mut self, tab: EditorTab) {
+        self.tabs.push(tab);
+        self.active_tab = self.tabs.len() - 1;
+    }
+
     pub fn open_file(&mut self, path: PathBuf) -> Result<()> {
         // Check if file is already open
         for (index, tab) in self.tabs.iter().enumerate() {
@@ -310,11 +1129,12 @@ impl Editor {
         }
     }
 
-    pub fn save_current_file(&mut self) -> Result<()> {
-        if let Some(tab) = self.tabs.get_mut(self.active_tab) {
-            tab.save()?;
+    /// Save the active tab; returns the formatter error if formatting was skipped
+    pub fn save_current_file(&mut self, hooks: &SaveHooks) -> Result<Option<String>> {
+        match self.tabs.get_mut(self.active_tab) {
+            Some(tab) => tab.save(hooks),
+            None => Ok(None),
         }
-        Ok(())
     }
 
     pub fn get_current_tab(&self) -> Option<&EditorTab> {
@@ -349,6 +1169,84 @@ impl Editor {
         }
     }
 
+    pub fn smart_tab(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            let unit = tab.settings.indent_unit();
+            tab.smart_tab(&unit, tab.settings.tab_width);
+        }
+    }
+
+    /// Indent the selected lines, or the cursor's line
+    pub fn indent_line(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            let unit = tab.settings.indent_unit();
+            let (first, last) = tab.selected_lines();
+            tab.indent_lines(first, last, &unit);
+        }
+    }
+
+    /// Dedent the selected lines, or the cursor's line
+    pub fn dedent_line(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            let (first, last) = tab.selected_lines();
+            tab.dedent_lines(first, last, tab.settings.tab_width);
+        }
+    }
+
+    /// `V`: start selecting whole lines from the cursor's, or stop
+    pub fn toggle_line_selection(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.selection_anchor = match tab.selection_anchor {
+                Some(_) => None,
+                None => Some(tab.cursor_line),
+            };
+        }
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.get_current_tab().is_some_and(|tab| tab.selection_anchor.is_some())
+    }
+
+    pub fn clear_selection(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.selection_anchor = None;
+        }
+    }
+
+    /// Apply the config's indentation, save and per-filetype settings to tabs that need them
+    pub fn refresh_file_settings(&mut self, indent: &IndentSettings, hooks: &SaveHooks, filetypes: &HashMap<String, FiletypeSettings>) {
+        for tab in self.tabs.iter_mut() {
+            tab.refresh_settings(indent, hooks, filetypes);
+        }
+    }
+
+    /// Make every tab look its settings up again, after the config changed
+    pub fn forget_file_settings(&mut self) {
+        for tab in self.tabs.iter_mut() {
+            tab.settings_for = None;
+        }
+    }
+
+    pub fn move_line(&mut self, up: bool) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_line(up);
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn duplicate_line(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.duplicate_line();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn join_lines(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.join_lines();
+        }
+    }
+
     pub fn move_cursor_up(&mut self) {
         if let Some(tab) = self.get_current_tab_mut() {
             tab.move_cursor_up();
@@ -381,13 +1279,184 @@ impl Editor {
         }
     }
 
+    pub fn move_word_forward(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_word_forward();
+            tab.reveal_cursor();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn move_word_backward(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_word_backward();
+            tab.reveal_cursor();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn move_word_end(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_word_end();
+            tab.reveal_cursor();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn move_line_start(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_line_start();
+        }
+    }
+
+    pub fn move_line_end(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_line_end();
+        }
+    }
+
+    pub fn move_buffer_start(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_buffer_start();
+            tab.reveal_cursor();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn move_buffer_end(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_buffer_end();
+            tab.reveal_cursor();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn move_cursor_to(&mut self, line: usize, col: usize) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.move_to(line, col);
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn jump_to_matching_bracket(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.jump_to_matching_bracket();
+            tab.reveal_cursor();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn toggle_fold(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.toggle_fold();
+            tab.ensure_cursor_visible(20);
+        }
+    }
+
+    pub fn unfold_all(&mut self) {
+        if let Some(tab) = self.get_current_tab_mut() {
+            tab.unfold_all();
+        }
+    }
+
     pub fn get_tab_info(&self) -> Vec<TabInfo> {
         self.tabs.iter().map(|tab| TabInfo {
             file_name: tab.file_name.clone(),
             is_modified: tab.is_modified,
+            is_pinned: tab.pinned,
         }).collect()
     }
 
+    fn pinned_count(&self) -> usize {
+        self.tabs.iter().take_while(|tab| tab.pinned).count()
+    }
+
+    /// Pin or unpin a tab, moving it to the end of the pinned group or the start of the rest
+    pub fn toggle_pin(&mut self, index: usize) -> Option<bool> {
+        let pinned = !self.tabs.get(index)?.pinned;
+        let target = if pinned { self.pinned_count() } else { self.pinned_count() - 1 };
+        self.tabs[index].pinned = pinned;
+        self.reorder_tabs(index, target);
+        Some(pinned)
+    }
+
+    fn tab_label(tab: &TabInfo) -> String {
+        let pin = if tab.is_pinned { "📌" } else { "" };
+        let modified_indicator = if tab.is_modified { "●" } else { "" };
+        format!(" {}{}{} ", pin, tab.file_name, modified_indicator)
+    }
+
+    /// Lay the tabs out in `width` columns. Pinned tabs always show; when the rest don't fit they
+    /// scroll, starting at `tab_scroll`, between `‹` and `›` and the new-tab button is dropped
+    pub fn tab_bar(&self, width: u16) -> TabBar {
+        let widths: Vec<u16> = self.get_tab_info().iter().map(|tab| Line::from(Self::tab_label(tab)).width() as u16).collect();
+        let separators = widths.len().saturating_sub(1) as u16;
+        let mut items = Vec::new();
+        let mut x = 0;
+
+        if widths.iter().sum::<u16>() + separators + 3 <= width {
+            for (index, tab_width) in widths.iter().enumerate() {
+                items.push((TabBarItem::Tab(index), x, *tab_width));
+                x += tab_width + 1;
+            }
+            items.push((TabBarItem::NewTab, x.saturating_sub(1), 3));
+            return TabBar { items };
+        }
+
+        let pinned = self.pinned_count();
+        for (index, tab_width) in widths.iter().enumerate().take(pinned) {
+            items.push((TabBarItem::Tab(index), x, *tab_width));
+            x += tab_width + 1;
+        }
+        let first = self.first_scrolled_tab(&widths, width);
+        if first > pinned {
+            items.push((TabBarItem::ScrollLeft, x, 1));
+        }
+        x += 2;
+        let right_edge = width.saturating_sub(1);
+        for (index, tab_width) in widths.iter().enumerate().skip(first) {
+            if x + tab_width > right_edge {
+                items.push((TabBarItem::ScrollRight, right_edge, 1));
+                break;
+            }
+            items.push((TabBarItem::Tab(index), x, *tab_width));
+            x += tab_width + 1;
+        }
+        TabBar { items }
+    }
+
+    /// `tab_scroll` kept in range, without leaving room unused at the end of the bar
+    fn first_scrolled_tab(&self, widths: &[u16], width: u16) -> usize {
+        let pinned = self.pinned_count();
+        let pinned_width: u16 = widths[..pinned].iter().map(|w| w + 1).sum();
+        // Room between the `‹ ` and ` ›` columns
+        let room = width.saturating_sub(pinned_width + 3);
+        let fits = |first: usize| widths[first..].iter().map(|w| w + 1).sum::<u16>().saturating_sub(1) <= room;
+        let mut first = self.tab_scroll.clamp(pinned, widths.len().saturating_sub(1).max(pinned));
+        while first > pinned && fits(first - 1) {
+            first -= 1;
+        }
+        first
+    }
+
+    /// Scroll the tab bar by `delta` tabs
+    pub fn scroll_tabs(&mut self, delta: isize) {
+        let pinned = self.pinned_count();
+        let last = self.tabs.len().saturating_sub(1).max(pinned);
+        self.tab_scroll = self.tab_scroll.max(pinned).saturating_add_signed(delta).clamp(pinned, last);
+    }
+
+    /// Scroll the tab bar just far enough to show the tab at `index`
+    fn reveal_tab(&mut self, index: usize, width: u16) {
+        if index < self.pinned_count() {
+            return;
+        }
+        self.tab_scroll = self.tab_scroll.min(index);
+        while self.tab_scroll < index && !self.tab_bar(width).shows(TabBarItem::Tab(index)) {
+            self.tab_scroll += 1;
+        }
+    }
+
     pub fn get_active_tab_index(&self) -> usize {
         self.active_tab
     }
@@ -402,6 +1471,29 @@ impl Editor {
             .unwrap_or((0, 0))
     }
 
+    /// Encoding and line-ending labels for the status bar
+    pub fn get_file_format(&self) -> (String, &'static str) {
+        match self.get_current_tab() {
+            Some(tab) if tab.lossy_decoded => (format!("{} (lossy)", tab.encoding.label()), tab.line_ending.label()),
+            Some(tab) => (tab.encoding.label().to_string(), tab.line_ending.label()),
+            None => (TextEncoding::Utf8.label().to_string(), LineEnding::Lf.label()),
+        }
+    }
+
+    pub fn get_language_name(&self) -> Option<&'static str> {
+        self.get_current_tab().map(|tab| tab.language().name)
+    }
+
+    /// Status-bar badge for tabs that cannot be edited
+    pub fn get_read_only_badge(&self) -> Option<&'static str> {
+        match self.get_current_tab() {
+            Some(tab) if tab.is_binary => Some("BINARY"),
+            Some(tab) if tab.read_only && tab.long_line.is_some() => Some("LONG LINES"),
+            Some(tab) if tab.read_only => Some("READ-ONLY"),
+            _ => None,
+        }
+    }
+
     pub fn is_current_file_modified(&self) -> bool {
         self.get_current_tab()
             .map(|tab| tab.is_modified)
@@ -422,8 +1514,8 @@ impl Editor {
             let estimated_visible_lines = 15; // Conservative estimate 
             
             // Allow scrolling if we have more lines than visible and haven't reached the end
-            if tab.lines.len() > estimated_visible_lines {
-                let max_scroll = tab.lines.len().saturating_sub(estimated_visible_lines);
+            if tab.buffer.len_lines() > estimated_visible_lines {
+                let max_scroll = tab.buffer.len_lines().saturating_sub(estimated_visible_lines);
                 if tab.scroll_offset < max_scroll {
                     tab.scroll_offset += 1;
                 }
@@ -441,8 +1533,8 @@ impl Editor {
 
     pub fn scroll_down_by_visible(&mut self, visible_lines: usize) {
         if let Some(tab) = self.get_current_tab_mut() {
-            if tab.lines.len() > visible_lines {
-                let max_scroll = tab.lines.len().saturating_sub(visible_lines);
+            if tab.buffer.len_lines() > visible_lines {
+                let max_scroll = tab.buffer.len_lines().saturating_sub(visible_lines);
                 if tab.scroll_offset < max_scroll {
                     tab.scroll_offset += 1;
                 }
@@ -450,17 +1542,39 @@ impl Editor {
         }
     }
 
-    pub fn draw(&mut self, frame: &mut Frame, area: Rect, is_focused: bool, mode: AppMode) {
+    /// Draw the editor; returns the area of the unfocused pane when split
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, is_focused: bool, mode: AppMode) -> Option<Rect> {
         // If we have open files, draw tabs and editor content within a single border
-        if self.has_open_files() {
-            self.draw_with_tabs(frame, area, is_focused, mode);
-        } else {
+        if !self.has_open_files() {
             // No files open, draw welcome message
             self.draw_welcome(frame, area, is_focused, mode);
+            return None;
         }
+
+        let Some(split) = self.split else {
+            self.draw_with_tabs(frame, area, self.get_active_tab_index(), is_focused, mode);
+            return None;
+        };
+        let direction = match split.direction {
+            SplitDirection::Vertical => Direction::Horizontal,
+            SplitDirection::Horizontal => Direction::Vertical,
+        };
+        let panes = Layout::default()
+            .direction(direction)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+        let (focused_area, other_area) = if split.second_focused {
+            (panes[1], panes[0])
+        } else {
+            (panes[0], panes[1])
+        };
+        let other_tab = self.other_pane_tab();
+        self.draw_with_tabs(frame, other_area, other_tab, false, mode);
+        self.draw_with_tabs(frame, focused_area, self.active_tab, is_focused, mode);
+        Some(other_area)
     }
 
-    fn draw_with_tabs(&mut self, frame: &mut Frame, area: Rect, is_focused: bool, mode: AppMode) {
+    fn draw_with_tabs(&mut self, frame: &mut Frame, area: Rect, tab_index: usize, is_focused: bool, mode: AppMode) {
         let border_style = if is_focused {
             match mode {
                 AppMode::Insert => Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
@@ -475,6 +1589,7 @@ impl Editor {
         let editor_block = Block::default()
             .title(" 📝 Editor ")
             .borders(Borders::ALL)
+            .border_type(theme::border_type(is_focused))
             .border_style(border_style);
 
         // Get the inner area of the block (inside the border)
@@ -493,25 +1608,61 @@ impl Editor {
         frame.render_widget(editor_block, area);
 
         // Draw tabs inside the border
-        self.draw_tabs_internal(frame, chunks[0], is_focused, mode);
+        self.draw_tabs_internal(frame, chunks[0], tab_index, is_focused, mode);
 
         // Draw editor content inside the border
-        self.draw_content_internal(frame, chunks[1], is_focused, mode);
+        self.draw_content_internal(frame, chunks[1], tab_index, is_focused, mode);
     }
 
-    fn draw_tabs_internal(&self, frame: &mut Frame, area: Rect, is_focused: bool, _mode: AppMode) {
+    fn draw_tabs_internal(&mut self, frame: &mut Frame, area: Rect, active_tab: usize, is_focused: bool, _mode: AppMode) {
         let tabs = self.get_tab_info();
-        let active_tab = self.get_active_tab_index();
 
         if tabs.is_empty() {
             return;
         }
 
+        // Only the focused pane (which shows the active tab) scrolls the bar and takes clicks
+        if active_tab == self.active_tab {
+            let active_id = self.tabs.get(active_tab).map(|tab| tab.id);
+            if self.revealed_tab != active_id {
+                self.reveal_tab(active_tab, area.width);
+                self.revealed_tab = active_id;
+            }
+            self.tab_bar_area = area;
+        }
+
         let mut tab_spans = Vec::new();
-        
-        for (i, tab) in tabs.iter().enumerate() {
+        let mut column = 0;
+        let mut after_tab = false;
+
+        for (item, x, width) in self.tab_bar(area.width).items {
+            // Tabs next to each other get a separator, other gaps are left blank
+            let gap = x.saturating_sub(column) as usize;
+            let is_tab = matches!(item, TabBarItem::Tab(_));
+            if gap == 1 && is_tab && after_tab {
+                tab_spans.push(Span::raw("│"));
+            } else if gap > 0 {
+                tab_spans.push(Span::raw(" ".repeat(gap)));
+            }
+            column = x + width;
+            after_tab = is_tab;
+
+            let (i, tab) = match item {
+                TabBarItem::Tab(i) => (i, &tabs[i]),
+                TabBarItem::ScrollLeft => {
+                    tab_spans.push(Span::styled("‹", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+                    continue;
+                }
+                TabBarItem::ScrollRight => {
+                    tab_spans.push(Span::styled("›", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+                    continue;
+                }
+                TabBarItem::NewTab => {
+                    tab_spans.push(Span::styled(" + ", Style::default().fg(Color::Gray)));
+                    continue;
+                }
+            };
             let is_active = i == active_tab;
-            let is_modified = tab.is_modified;
 
             // Tab styling - simpler since we're inside the border
             let (bg_color, fg_color) = if is_active && is_focused {
@@ -527,61 +1678,113 @@ impl Editor {
                 style = style.add_modifier(Modifier::BOLD);
             }
 
-            // Tab content
-            let modified_indicator = if is_modified { "●" } else { "" };
-            let tab_text = format!(" {}{} ", tab.file_name, modified_indicator);
-
-            tab_spans.push(Span::styled(tab_text, style));
-
-            // Tab separator
-            if i < tabs.len() - 1 {
-                tab_spans.push(Span::raw("│"));
-            }
+            tab_spans.push(Span::styled(Self::tab_label(tab), style));
         }
 
-        // Add new tab button
-        tab_spans.push(Span::styled(" + ", Style::default().fg(Color::Gray)));
-
         let tabs_line = Line::from(tab_spans);
         let tabs_paragraph = Paragraph::new(tabs_line);
 
         frame.render_widget(tabs_paragraph, area);
     }
 
-    fn draw_content_internal(&mut self, frame: &mut Frame, area: Rect, is_focused: bool, _mode: AppMode) {
-        if let Some(tab) = self.get_current_tab_mut() {
+    fn draw_content_internal(&mut self, frame: &mut Frame, area: Rect, tab_index: usize, is_focused: bool, _mode: AppMode) {
+        let (gutter, signs) = (&self.gutter, &self.signs);
+        let (ruler, show_ruler, show_whitespace) = (self.ruler, self.show_ruler, self.show_whitespace);
+        if let Some(tab) = self.tabs.get_mut(tab_index) {
             // Calculate visible lines (no need to account for borders here)
             let visible_lines = area.height as usize;
-
-            // Create editor content with line numbers
+            let number_width = gutter.number_width(tab.buffer.len_lines());
+            let text_width = (area.width as usize).saturating_sub(gutter.width(number_width));
+            tab.ensure_cursor_col_visible(text_width);
+            let tab_signs: Vec<(usize, char)> = signs
+                .iter()
+                .filter(|(path, _, _)| tab.file_path.as_ref() == Some(path))
+                .map(|(_, line, sign)| (*line, *sign))
+                .collect();
+
+            // Highlight the bracket pair around the cursor
+            let bracket_pair = tab.find_matching_bracket()
+                .map(|matching| [(tab.cursor_line, tab.cursor_col), matching]);
+            let bracket_style = Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+            // Create editor content with line numbers, skipping lines hidden by folds
             let mut content_lines = Vec::new();
             let start_line = tab.scroll_offset;
-            let end_line = (start_line + visible_lines).min(tab.lines.len());
-
-            for (i, line) in tab.lines[start_line..end_line].iter().enumerate() {
-                let line_number = start_line + i + 1;
-                let is_cursor_line = start_line + i == tab.cursor_line;
+            // Only the visible window is pulled out of the rope, sideways too, so a
+            // multi-megabyte minified line costs no more to draw than a short one
+            let rows: Vec<usize> = (start_line..tab.buffer.len_lines())
+                .filter(|line_index| !tab.is_line_hidden(*line_index))
+                .take(visible_lines)
+                .collect();
+
+            for line_index in rows {
+                let (offset, line) = tab.buffer.line_window(line_index, tab.scroll_col, text_width);
+                let is_cursor_line = line_index == tab.cursor_line;
+                let (first_selected, last_selected) = tab.selected_lines();
+                let is_selected = tab.selection_anchor.is_some() && (first_selected..=last_selected).contains(&line_index);
                 
-                let line_style = if is_cursor_line && is_focused {
+                let line_style = if is_selected {
+                    Style::default().bg(Color::Blue)
+                } else if is_cursor_line && is_focused {
                     Style::default().bg(Color::DarkGray)
                 } else {
                     Style::default()
                 };
 
-                // Add line number and content
-                let line_content = if line.is_empty() {
-                    format!("{:3} │ ", line_number)
-                } else {
-                    format!("{:3} │ {}", line_number, line)
-                };
+                // Add line number and content, splitting out highlighted brackets
+                let sign = tab_signs.iter().find(|(line, _)| *line == line_index).map(|(_, sign)| *sign);
+                let distance = tab.visible_distance(line_index, tab.cursor_line);
+                let mut spans = Vec::new();
+                let mut highlights: Vec<usize> = bracket_pair.iter()
+                    .flatten()
+                    .filter(|(l, c)| *l == line_index && *c >= offset && *c < offset + line.len())
+                    .map(|(_, c)| *c - offset)
+                    .collect();
+                highlights.sort_unstable();
+
+                let mut last = 0;
+                for col in highlights {
+                    let width = line[col..].chars().next().map(|c| c.len_utf8()).unwrap_or(1);
+                    spans.push(Span::styled(line[last..col].to_string(), line_style));
+                    spans.push(Span::styled(line[col..col + width].to_string(), bracket_style));
+                    last = col + width;
+                }
+                spans.push(Span::styled(line[last..].to_string(), line_style));
+                if show_whitespace {
+                    spans = with_visible_whitespace(spans);
+                    if offset + line.len() == tab.buffer.line_len(line_index) {
+                        spans.push(Span::styled(whitespace::LINE_END.to_string(), line_style.fg(Color::DarkGray)));
+                    }
+                }
+                spans.insert(0, Span::styled(gutter.label(line_index, distance, number_width, sign), line_style));
+
+                if let Some((_, end)) = tab.folds.iter().find(|(start, _)| *start == line_index) {
+                    spans.push(Span::styled(
+                        format!(" ⋯ {} lines", end - line_index),
+                        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+                    ));
+                }
 
-                content_lines.push(Line::from(Span::styled(line_content, line_style)));
+                content_lines.push(Line::from(spans));
             }
 
             let editor_content = Paragraph::new(content_lines)
                 .style(Style::default().fg(Color::White));
 
             frame.render_widget(editor_content, area);
+
+            // Ruler at the file's maximum line length (or the global column), when it is scrolled into view
+            let ruler = tab.settings.max_line_length.or(ruler).filter(|_| show_ruler);
+            if let Some(column) = ruler.and_then(|column| column.checked_sub(tab.scroll_col)) {
+                if column < text_width {
+                    let x = area.x + (gutter.width(number_width) + column) as u16;
+                    for y in area.top()..area.bottom() {
+                        if let Some(cell) = frame.buffer_mut().cell_mut((x, y)) {
+                            cell.set_bg(Color::DarkGray); // The theme's border color
+                        }
+                    }
+                }
+            }
         }
     }
 
@@ -605,12 +1808,25 @@ impl Editor {
             .block(Block::default()
                 .title(" 📝 Editor ")
                 .borders(Borders::ALL)
+                .border_type(theme::border_type(is_focused))
                 .border_style(border_style));
 
         frame.render_widget(welcome, area);
     }
 }
 
+/// Spans with their spaces and tabs swapped for dimmed markers
+fn with_visible_whitespace(spans: Vec<Span<'static>>) -> Vec<Span<'static>> {
+    let mut marked = Vec::new();
+    for span in spans {
+        for (text, is_marker) in whitespace::mark(&span.content) {
+            let style = if is_marker { span.style.fg(Color::DarkGray) } else { span.style };
+            marked.push(Span::styled(text, style));
+        }
+    }
+    marked
+}
+
 fn get_file_icon(filename: &str) -> &'static str {
     let extension = std::path::Path::new(filename)
         .extension()
@@ -629,4 +1845,237 @@ fn get_file_icon(filename: &str) -> &'static str {
         "toml" | "yaml" | "yml" => "⚙️",
         _ => "📄",
     }
-}
\ No newline at end of file
+}
+
+fn file_mtime(path: &std::path::Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Load a file into a buffer; binary files get a hex preview and the decode result is left empty
+fn read_file(path: &std::path::Path) -> Result<(TextBuffer, encoding::DecodedText, bool)> {
+    let bytes = fs::read(path)?;
+    if hexview::is_binary(&bytes) {
+        return Ok((TextBuffer::from_text(&hexview::hex_dump(&bytes)), encoding::decode(&[]), true));
+    }
+    let decoded = encoding::decode(&bytes);
+    Ok((TextBuffer::from_lines(&decoded.lines), decoded, false))
+}
+
+/// Whether we lack permission to write the file (opening for append never truncates it)
+fn is_write_protected(path: &std::path::Path) -> bool {
+    fs::OpenOptions::new().append(true).open(path).is_err()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tab_with(text: &str) -> EditorTab {
+        let mut tab = EditorTab::new();
+        tab.buffer = TextBuffer::from_text(text);
+        tab
+    }
+
+    #[test]
+    fn test_split_panes() {
+        let mut editor = Editor::new();
+        editor.open_tab(tab_with("test"));
+        editor.open_tab(tab_with("impl"));
+        editor.switch_to_tab(0);
+
+        editor.toggle_split();
+        assert_eq!(editor.split.map(|split| split.direction), Some(SplitDirection::Vertical));
+        assert_eq!(editor.other_pane_tab(), 1);
+
+        assert!(editor.focus_other_pane());
+        assert!(editor.is_second_pane_focused());
+        assert_eq!((editor.active_tab, editor.other_pane_tab()), (1, 0));
+
+        // Closing the other pane's tab leaves both panes on the remaining one
+        editor.close_tab_by_index(0);
+        assert_eq!((editor.active_tab, editor.other_pane_tab()), (0, 0));
+
+        editor.toggle_split();
+        assert_eq!(editor.split.map(|split| split.direction), Some(SplitDirection::Horizontal));
+        editor.toggle_split();
+        assert!(editor.split.is_none());
+        assert!(!editor.focus_other_pane());
+    }
+
+    #[test]
+    fn test_pinned_tabs_and_tab_bar() {
+        let mut editor = Editor::new();
+        for name in ["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"] {
+            let mut tab = tab_with("");
+            tab.file_name = name.to_string();
+            editor.open_tab(tab);
+        }
+        assert_eq!(editor.toggle_pin(3), Some(true));
+        assert_eq!(editor.tabs[0].file_name, "d.rs");
+        assert_eq!(editor.active_tab, 4);
+        assert_eq!(editor.tab_bar(100).items.last(), Some(&(TabBarItem::NewTab, 36, 3)));
+
+        // " 📌d.rs " stays put; the rest scroll between `‹` and `›`
+        use TabBarItem::*;
+        assert_eq!(editor.tab_bar(24).items, vec![(Tab(0), 0, 8), (Tab(1), 11, 6), (ScrollRight, 23, 1)]);
+        editor.reveal_tab(4, 24);
+        let bar = editor.tab_bar(24);
+        assert_eq!(bar.items, vec![(Tab(0), 0, 8), (ScrollLeft, 9, 1), (Tab(4), 11, 6)]);
+        assert_eq!(bar.item_at(12), Some(Tab(4)));
+        editor.scroll_tabs(-10);
+        assert_eq!(editor.tab_scroll, 1);
+
+        assert_eq!(editor.toggle_pin(0), Some(false));
+        assert!(editor.tabs.iter().all(|tab| !tab.pinned));
+    }
+
+    #[test]
+    fn test_word_motions() {
+        let mut tab = tab_with("let foo = bar(1);\n    baz");
+
+        tab.move_word_forward();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 4));
+        tab.move_word_end();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 6));
+        tab.move_word_forward();
+        tab.move_word_forward();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 10));
+        tab.move_word_forward();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 13));
+
+        tab.cursor_col = 16;
+        tab.move_word_forward();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (1, 4));
+        tab.move_word_backward();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 15));
+    }
+
+    #[test]
+    fn test_matching_bracket() {
+        let mut tab = tab_with("fn main() {\n    foo(bar[0]);\n}");
+
+        tab.cursor_col = 10;
+        assert_eq!(tab.find_matching_bracket(), Some((2, 0)));
+        tab.jump_to_matching_bracket();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (2, 0));
+        tab.jump_to_matching_bracket();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 10));
+
+        tab.cursor_line = 1;
+        tab.cursor_col = 14;
+        assert_eq!(tab.find_matching_bracket(), Some((1, 7)));
+        tab.cursor_col = 0;
+        assert_eq!(tab.find_matching_bracket(), None);
+    }
+
+    #[test]
+    fn test_indent_and_dedent() {
+        let mut tab = tab_with("fn a() {\nx();\n}");
+        tab.cursor_line = 1;
+        tab.smart_tab("    ", 4);
+        assert_eq!(tab.buffer.line(1).as_deref(), Some("    x();"));
+        assert_eq!(tab.cursor_col, 4);
+
+        tab.cursor_col = 6;
+        tab.smart_tab("    ", 4);
+        assert_eq!(tab.buffer.line(1).as_deref(), Some("    x(  );"));
+
+        tab.indent_lines(0, 2, "\t");
+        tab.dedent_lines(0, 2, 4);
+        tab.dedent_lines(1, 1, 4);
+        assert_eq!(tab.buffer.to_string(), "fn a() {\nx(  );\n}");
+        assert_eq!(tab.cursor_col, 4);
+
+        // Tab stops count characters, not bytes
+        let mut tab = tab_with("é;\nb\nc");
+        tab.cursor_col = "é".len();
+        tab.smart_tab("    ", 4);
+        assert_eq!(tab.buffer.line(0).as_deref(), Some("é   ;"));
+
+        // A line selection is indented as a whole, wherever the cursor is on its line
+        tab.selection_anchor = Some(2);
+        tab.cursor_line = 1;
+        tab.cursor_col = 1;
+        tab.smart_tab("  ", 2);
+        assert_eq!(tab.buffer.to_string(), "é   ;\n  b\n  c");
+    }
+
+    #[test]
+    fn test_line_operations() {
+        let mut tab = tab_with("one\ntwo\nthree");
+        tab.move_line(false);
+        assert_eq!(tab.buffer.to_string(), "two\none\nthree");
+        assert_eq!(tab.cursor_line, 1);
+        tab.move_line(false);
+        tab.move_line(false); // Already the last line
+        assert_eq!(tab.buffer.to_string(), "two\nthree\none");
+
+        tab.duplicate_line();
+        assert_eq!(tab.buffer.to_string(), "two\nthree\none\none");
+        assert_eq!(tab.cursor_line, 3);
+
+        let mut tab = tab_with("call(  \n    arg\n)\n");
+        tab.join_lines();
+        tab.join_lines();
+        assert_eq!(tab.buffer.line(0).as_deref(), Some("call( arg)"));
+        assert_eq!(tab.cursor_col, 9);
+    }
+
+    #[test]
+    fn test_folding() {
+        let mut tab = tab_with("fn a() {\n    one();\n    two();\n}\nfn b() {}");
+
+        assert_eq!(tab.fold_range_at(0), Some((0, 3)));
+        tab.cursor_line = 2;
+        tab.toggle_fold();
+        assert_eq!(tab.folds, vec![(0, 3)]);
+        assert_eq!(tab.cursor_line, 0);
+        assert!(tab.is_line_hidden(3));
+
+        tab.move_cursor_down();
+        assert_eq!(tab.cursor_line, 4);
+        tab.move_cursor_up();
+        assert_eq!(tab.cursor_line, 0);
+
+        tab.toggle_fold();
+        assert!(tab.folds.is_empty());
+
+        let tab = EditorTab { cursor_line: 3, ..tab_with("impl A {\n    fn a() {\n        if x {\n            one();\n        }\n    }\n}") };
+        assert_eq!(tab.code_region(), (1, 5));
+    }
+
+    #[test]
+    fn test_line_and_buffer_motions() {
+        let mut tab = tab_with("  first\nsecond\n    last");
+
+        tab.move_buffer_end();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (2, 4));
+        tab.move_line_end();
+        assert_eq!(tab.cursor_col, 7);
+        tab.move_line_start();
+        assert_eq!(tab.cursor_col, 0);
+        tab.move_buffer_start();
+        assert_eq!((tab.cursor_line, tab.cursor_col), (0, 2));
+    }
+
+    #[test]
+    fn test_language_override() {
+        let mut tab = tab_with("#!/bin/sh\n  echo hi");
+        tab.file_name = "deploy".to_string();
+        assert_eq!(tab.language().id, "shell");
+        assert_eq!(tab.settings_name(), "deploy");
+
+        tab.cursor_line = 1;
+        assert!(tab.toggle_comment());
+        assert_eq!(tab.buffer.line(1).as_deref(), Some("  # echo hi"));
+        assert!(tab.toggle_comment());
+        assert_eq!(tab.buffer.line(1).as_deref(), Some("  echo hi"));
+
+        tab.language_override = Some("lua");
+        assert_eq!(tab.settings_name(), "lua");
+        tab.toggle_comment();
+        assert_eq!(tab.buffer.line(1).as_deref(), Some("  -- echo hi"));
+        tab.language_override = Some("json");
+        assert!(!tab.toggle_comment());
+    }
+}
@@ -0,0 +1,201 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+// (symbol, searchable name) - gitmoji-style entries first, then common unicode symbols
+const SYMBOLS: &[(&str, &str)] = &[
+    ("✨", "sparkles new feature"),
+    ("🐛", "bug fix"),
+    ("🔥", "fire remove code"),
+    ("📝", "memo docs documentation"),
+    ("🚀", "rocket deploy release"),
+    ("♻️", "recycle refactor"),
+    ("✅", "check mark tests passing"),
+    ("🚧", "construction work in progress"),
+    ("⚡", "zap performance"),
+    ("🎨", "art format structure"),
+    ("🔒", "lock security"),
+    ("⬆️", "arrow up upgrade dependencies"),
+    ("⬇️", "arrow down downgrade dependencies"),
+    ("🔧", "wrench config"),
+    ("🚑", "ambulance hotfix"),
+    ("💄", "lipstick ui style"),
+    ("🎉", "tada party initial commit"),
+    ("🔖", "bookmark version tag"),
+    ("🚨", "rotating light lint warnings"),
+    ("💚", "green heart ci fix"),
+    ("📦", "package build"),
+    ("👷", "construction worker ci"),
+    ("🗑️", "wastebasket deprecate"),
+    ("🔀", "twisted arrows merge"),
+    ("⏪", "rewind revert"),
+    ("🌐", "globe i18n localization"),
+    ("💡", "bulb idea comment"),
+    ("🏷️", "label types"),
+    ("🧪", "test tube experiment"),
+    ("🦀", "crab rust"),
+    ("🐍", "snake python"),
+    ("👍", "thumbs up"),
+    ("👎", "thumbs down"),
+    ("👀", "eyes review"),
+    ("🙏", "pray thanks"),
+    ("😀", "grinning smile"),
+    ("😅", "sweat smile"),
+    ("🤔", "thinking"),
+    ("❤️", "heart love"),
+    ("⭐", "star"),
+    ("⚠️", "warning"),
+    ("❌", "cross mark error"),
+    ("❓", "question"),
+    ("📌", "pushpin pin"),
+    ("🔗", "link"),
+    ("📊", "chart stats"),
+    ("→", "arrow right"),
+    ("←", "arrow left"),
+    ("↑", "arrow up"),
+    ("↓", "arrow down"),
+    ("⇒", "double arrow implies"),
+    ("↔", "arrow left right"),
+    ("•", "bullet"),
+    ("…", "ellipsis"),
+    ("—", "em dash"),
+    ("–", "en dash"),
+    ("×", "times multiply"),
+    ("÷", "divide"),
+    ("±", "plus minus"),
+    ("≈", "approximately"),
+    ("≠", "not equal"),
+    ("≤", "less or equal"),
+    ("≥", "greater or equal"),
+    ("∞", "infinity"),
+    ("√", "square root"),
+    ("∑", "sum sigma"),
+    ("π", "pi"),
+    ("λ", "lambda"),
+    ("µ", "micro mu"),
+    ("Δ", "delta"),
+    ("°", "degree"),
+    ("©", "copyright"),
+    ("®", "registered"),
+    ("™", "trademark"),
+    ("€", "euro"),
+    ("£", "pound"),
+    ("¥", "yen"),
+    ("✓", "check"),
+    ("✗", "ballot x"),
+    ("★", "black star"),
+    ("─", "box horizontal"),
+    ("│", "box vertical"),
+    ("┌", "box corner top left"),
+    ("└", "box corner bottom left"),
+    ("├", "box tee"),
+];
+
+pub struct EmojiPicker {
+    pub is_open: bool,
+    pub query: String,
+    pub selected: usize,
+}
+
+impl Default for EmojiPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EmojiPicker {
+    pub fn new() -> Self {
+        Self {
+            is_open: false,
+            query: String::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn matches(&self) -> Vec<(&'static str, &'static str)> {
+        let words: Vec<String> = self.query.split_whitespace().map(|w| w.to_lowercase()).collect();
+        SYMBOLS
+            .iter()
+            .filter(|(_, name)| words.iter().all(|word| name.contains(word.as_str())))
+            .copied()
+            .collect()
+    }
+
+    pub fn get_selected(&self) -> Option<&'static str> {
+        self.matches().get(self.selected).map(|(symbol, _)| *symbol)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                format!("> {}_", self.query),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            )),
+            Line::from(""),
+        ];
+
+        // Keep the selection inside the visible window
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let matches = self.matches();
+        let first = self.selected.saturating_sub(visible_rows.saturating_sub(1));
+
+        for (i, (symbol, name)) in matches.iter().enumerate().skip(first).take(visible_rows) {
+            let style = if i == self.selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            lines.push(Line::from(Span::styled(format!(" {}  {} ", symbol, name), style)));
+        }
+
+        if matches.is_empty() {
+            lines.push(Line::from(Span::styled(" No matching symbols", Style::default().fg(Color::DarkGray))));
+        }
+
+        let picker = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 😀 Insert Symbol (Enter: insert, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(picker, area);
+    }
+}
@@ -0,0 +1,141 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le, // Only recognised with a BOM
+    Utf16Be,
+}
+
+impl TextEncoding {
+    pub fn label(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf8Bom => "UTF-8 BOM",
+            TextEncoding::Utf16Le => "UTF-16 LE",
+            TextEncoding::Utf16Be => "UTF-16 BE",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+        }
+    }
+}
+
+/// File content split into lines, plus what is needed to write it back unchanged
+#[derive(Debug, Clone)]
+pub struct DecodedText {
+    pub lines: Vec<String>,
+    pub encoding: TextEncoding,
+    pub line_ending: LineEnding,
+    pub trailing_newline: bool,
+    pub lossy: bool, // Invalid bytes were replaced with U+FFFD
+}
+
+pub fn decode(bytes: &[u8]) -> DecodedText {
+    let (text, encoding, lossy) = if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        let text = String::from_utf8_lossy(rest);
+        let lossy = matches!(text, std::borrow::Cow::Owned(_));
+        (text.into_owned(), TextEncoding::Utf8Bom, lossy)
+    } else if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        decode_utf16(rest, u16::from_le_bytes, TextEncoding::Utf16Le)
+    } else if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        decode_utf16(rest, u16::from_be_bytes, TextEncoding::Utf16Be)
+    } else {
+        let text = String::from_utf8_lossy(bytes);
+        let lossy = matches!(text, std::borrow::Cow::Owned(_));
+        (text.into_owned(), TextEncoding::Utf8, lossy)
+    };
+
+    // Majority vote, so a single stray line ending does not flip the whole file
+    let crlf = text.matches("\r\n").count();
+    let lf = text.matches('\n').count() - crlf;
+    let line_ending = if crlf > lf { LineEnding::CrLf } else { LineEnding::Lf };
+
+    let trailing_newline = text.ends_with('\n');
+    let body = text.strip_suffix('\n').unwrap_or(&text);
+    let lines = body
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+        .collect();
+
+    DecodedText {
+        lines,
+        encoding,
+        line_ending,
+        trailing_newline,
+        lossy,
+    }
+}
+
+fn decode_utf16(bytes: &[u8], to_unit: fn([u8; 2]) -> u16, encoding: TextEncoding) -> (String, TextEncoding, bool) {
+    let chunks = bytes.chunks_exact(2);
+    let odd_length = !chunks.remainder().is_empty();
+    let units: Vec<u16> = chunks.map(|pair| to_unit([pair[0], pair[1]])).collect();
+    let lossy = odd_length || char::decode_utf16(units.iter().copied()).any(|c| c.is_err());
+    (String::from_utf16_lossy(&units), encoding, lossy)
+}
+
+pub fn encode(lines: &[String], encoding: TextEncoding, line_ending: LineEnding, trailing_newline: bool) -> Vec<u8> {
+    let mut text = lines.join(line_ending.as_str());
+    if trailing_newline {
+        text.push_str(line_ending.as_str());
+    }
+
+    match encoding {
+        TextEncoding::Utf8 => text.into_bytes(),
+        TextEncoding::Utf8Bom => [&[0xEF, 0xBB, 0xBF][..], text.as_bytes()].concat(),
+        TextEncoding::Utf16Le => {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        }
+        TextEncoding::Utf16Be => {
+            let mut bytes = vec![0xFE, 0xFF];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+            bytes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_preserves_format() {
+        for bytes in [
+            b"fn main() {}\r\n    x\r\n".to_vec(),
+            b"\xEF\xBB\xBFa\nb".to_vec(),
+            vec![0xFF, 0xFE, b'h', 0, b'i', 0, b'\n', 0],
+        ] {
+            let decoded = decode(&bytes);
+            assert!(!decoded.lossy);
+            assert_eq!(encode(&decoded.lines, decoded.encoding, decoded.line_ending, decoded.trailing_newline), bytes);
+        }
+
+        let crlf = decode(b"a\r\nb\r\n");
+        assert_eq!((crlf.line_ending, crlf.lines.clone()), (LineEnding::CrLf, vec!["a".to_string(), "b".to_string()]));
+
+        let latin1 = decode(b"caf\xE9");
+        assert!(latin1.lossy);
+        assert_eq!(latin1.lines, vec!["caf\u{FFFD}".to_string()]);
+    }
+}
@@ -2,6 +2,9 @@ use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent,
 use std::path::PathBuf;
 use std::time::Duration;
 use anyhow::Result;
+use tokio::sync::Notify;
+use crate::agent::code_commands::CodeCommand;
+use crate::ide::keymap::Keymap;
 
 #[derive(Debug, Clone)]
 pub enum IdeEvent {
@@ -9,9 +12,50 @@ pub enum IdeEvent {
     Quit,
     ToggleHelp,
     ToggleCommandHelp,  // Ctrl+H
+    ShowKeymap,  // The effective key bindings, config overrides included
+    ShowSettings,  // Every setting by category, changed in place
+    ShowSessions,  // Saved conversations, to resume one
+    ShowBranches,  // Tree of the conversations forked from checkpoints
+    NewCheckpoint,  // Starts `/checkpoint ` in the chat input
+    PinLastAnswer,  // Keeps the latest answer out of history trimming
+    EditProjectMemory,  // .i4z/memory.md, sent with every request
+    NextThread,  // Chat threads, in the order of the chat header
+    PreviousThread,
+    NewThread,  // Starts `/thread new ` in the chat input
+    EditPreviousMessage,  // An earlier question into the chat input, to resend from there
+    SearchPromptHistory,  // Fuzzy search over past prompts; Ctrl+R while in the chat
     ToggleAgenticMode,
+    UndoAgentChange,  // Restore what the agent's newest file change overwrote or deleted
+    UndoAgentSession,  // Revert every agent file change made in this session
+    CycleAutonomyLevel,
+    ToggleLowBandwidth,
+    ToggleDryRun,  // Agent actions only report what they would change
+    RunTestsAndFix,  // Agent run that repeats run_tests and fixes until they pass
+    ShowAgentNotes,  // The agent's scratchpad for this session
+    ShowAgentTasks,  // Queued, running and finished agent runs
+    CodeCommand(CodeCommand),  // Explain, document, test or refactor the function at the cursor
     ShowApiConfig,
+    SelectModel,  // Pick the default model from the provider's list
     ClearNotifications,  // Ctrl+K
+    ToggleCommandPalette,  // Ctrl+P
+    ToggleEmojiPicker,  // Ctrl+E
+    Suspend,  // Ctrl+Z
+    ToggleSplit,  // Ctrl+\
+    FocusOtherPane,
+    ShowDiff,  // Buffer vs saved file
+    ShowGitDiff,  // Buffer vs the last commit
+    ComparePanes,  // Tabs of the two split panes
+    ShowMarks,  // Bookmark picker
+    ShowFileRefs,  // file:line references found in chat and notifications
+    TogglePinTab,
+    ShowNotificationHistory,  // Searchable archive of this session's notifications
+    CycleLineNumbers,  // Absolute -> relative -> hybrid -> off
+    ToggleRuler,
+    CycleTheme,  // Default -> high contrast -> deuteranopia -> protanopia
+    ToggleWhitespace,  // Draw spaces, tabs and line ends
+    PrettyPrint,  // Reformat minified JSON, or run the file's save-hook formatter
+    SetLanguage,  // Language picker for the current tab
+    ToggleComment,  // Ctrl+/
     
     // Panel focus
     FocusFileExplorer,
@@ -42,6 +86,12 @@ pub enum IdeEvent {
     DeleteFile(PathBuf),
     RenameFile(PathBuf),
     
+    // Line editing
+    MoveLineUp,  // Alt+Up
+    MoveLineDown,  // Alt+Down
+    DuplicateLine,  // Ctrl+Shift+D
+    JoinLines,  // J (normal mode)
+    
     // Navigation
     NavigateUp,
     NavigateDown,
@@ -54,19 +104,29 @@ pub enum IdeEvent {
     Backspace,
     Delete,
     Enter,
+    SoftNewline, // Shift+Enter in text inputs
     Tab,
+    BackTab, // Shift+Tab
     
     // Chat operations
     SendMessage,
     SendMessageWithImage,
+    SendMessageWithFile, // Ctrl+Shift+Enter - attach the active tab's text
     ClearChat,
+    CopyLastAnswer, // Ctrl+Y - last AI answer as plain text
+    PipeLastAnswer, // Send last AI answer to `answer_pipe_command`
+    SaveWorkflow,   // Save the conversation setup as a named workflow template
+    StartWorkflow,  // Pick a workflow template and start a fresh conversation from it
     
     // File tree operations
     RefreshFileTree,
+    ToggleHiddenFiles, // Show or hide dotfiles in the file explorer
     ToggleFileExpand,
     
     // Mouse events
     MouseClick(u16, u16),
+    MouseRightClick(u16, u16),
+    MouseMiddleClick(u16, u16),
     MouseMove(u16, u16),
     MouseRelease(u16, u16),
     MouseScroll(i8),
@@ -84,17 +144,35 @@ pub enum IdeEvent {
 
 pub struct EventHandler {
     pub timeout: Duration,
+    pub capture_text: bool, // Route printable keys to text input instead of navigation
+    pub keymap: Keymap, // Ctrl and Alt chords and function keys
+    pub context: Option<&'static str>, // Focused panel, for bindings limited to one (see `keymap::CONTEXTS`)
+}
+
+impl Default for EventHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EventHandler {
     pub fn new() -> Self {
         Self {
             timeout: Duration::from_millis(100),
+            capture_text: false,
+            keymap: Keymap::load(&Default::default()).0,
+            context: None,
         }
     }
 
-    pub fn poll_event(&self) -> Result<Option<IdeEvent>> {
-        if event::poll(self.timeout)? {
+    /// Wait up to the timeout for input; a notification on `wake` (e.g. from a signal) ends the wait at once
+    pub async fn poll_event(&self, wake: &Notify) -> Result<Option<IdeEvent>> {
+        let timeout = self.timeout;
+        let ready = tokio::select! {
+            ready = tokio::task::spawn_blocking(move || event::poll(timeout)) => ready??,
+            _ = wake.notified() => false,
+        };
+        if ready {
             match event::read()? {
                 Event::Key(key) => Ok(self.handle_key_event(key)),
                 Event::Mouse(mouse) => Ok(self.handle_mouse_event(mouse)),
@@ -107,77 +185,29 @@ impl EventHandler {
     }
 
     fn handle_key_event(&self, key: KeyEvent) -> Option<IdeEvent> {
+        if let Some(event) = self.keymap.event_for(key, self.context) {
+            return Some(event);
+        }
         match key.modifiers {
-            m if m.contains(KeyModifiers::CONTROL) && m.contains(KeyModifiers::SHIFT) => {
-                self.handle_ctrl_shift_key(key.code)
-            }
-            KeyModifiers::CONTROL => self.handle_ctrl_key(key.code),
-            KeyModifiers::ALT => self.handle_alt_key(key.code),
+            // Unbound chords do nothing rather than type
+            m if m.contains(KeyModifiers::CONTROL) && m.contains(KeyModifiers::SHIFT) => None,
+            KeyModifiers::CONTROL | KeyModifiers::ALT => None,
             _ => self.handle_normal_key(key),
         }
     }
 
-    fn handle_ctrl_shift_key(&self, key_code: KeyCode) -> Option<IdeEvent> {
-        match key_code {
-            KeyCode::Tab => Some(IdeEvent::PreviousTab),
-            KeyCode::Up => Some(IdeEvent::ResizeNotificationsShrink),
-            KeyCode::Down => Some(IdeEvent::ResizeNotificationsExpand),
-            _ => None,
-        }
-    }
-
-    fn handle_ctrl_key(&self, key_code: KeyCode) -> Option<IdeEvent> {
-        match key_code {
-            // Application control
-            KeyCode::Char('q') | KeyCode::Char('c') => Some(IdeEvent::Quit),
-            KeyCode::Char('h') => Some(IdeEvent::ToggleCommandHelp),  // Ctrl+H help
-            
-            // File operations
-            KeyCode::Char('s') => Some(IdeEvent::SaveFile),
-            KeyCode::Char('n') => Some(IdeEvent::NewFile),
-            KeyCode::Char('w') => Some(IdeEvent::CloseFile),
-            KeyCode::Char('o') => Some(IdeEvent::FocusFileExplorer),
-            KeyCode::Char('d') => Some(IdeEvent::NewFolder),  // Create directory
-            
-            // Chat operations
-            KeyCode::Char('l') => Some(IdeEvent::ClearChat),
-            KeyCode::Enter => Some(IdeEvent::SendMessage),
-            KeyCode::Char('i') => Some(IdeEvent::SendMessageWithImage),
-            
-            // Mode toggles
-            KeyCode::Char('a') => Some(IdeEvent::ToggleAgenticMode),
-            KeyCode::Char(',') => Some(IdeEvent::ShowApiConfig),  // Settings
-            KeyCode::Char('k') => Some(IdeEvent::ClearNotifications),  // Clear notifications
-            
-            // Layout resizing
-            KeyCode::Right => Some(IdeEvent::ResizeSidebarExpand),
-            KeyCode::Left => Some(IdeEvent::ResizeSidebarShrink),
-            KeyCode::Up => Some(IdeEvent::ResizeChatShrink),
-            KeyCode::Down => Some(IdeEvent::ResizeChatExpand),
-            
-            // File tree
-            KeyCode::Char('r') => Some(IdeEvent::RefreshFileTree),
-
-            // Tab management
-            KeyCode::Tab => Some(IdeEvent::NextTab),
-            KeyCode::Char('t') => Some(IdeEvent::NewFile), // Ctrl+T for new tab
-
-            _ => None,
-        }
-    }
-
-    fn handle_alt_key(&self, key_code: KeyCode) -> Option<IdeEvent> {
-        match key_code {
-            // Panel focus shortcuts (Alt + number)
-            KeyCode::Char('1') => Some(IdeEvent::FocusFileExplorer),
-            KeyCode::Char('2') => Some(IdeEvent::FocusEditor),
-            KeyCode::Char('3') => Some(IdeEvent::FocusChat),
-            KeyCode::Char('4') => Some(IdeEvent::FocusNotifications),
-            _ => None,
+    fn handle_normal_key(&self, key: KeyEvent) -> Option<IdeEvent> {
+        // While typing into a text field, letters like h/j/k/l must not become navigation
+        if self.capture_text {
+            match key.code {
+                KeyCode::Char(c) => return Some(IdeEvent::InsertChar(c)),
+                KeyCode::Enter if key.modifiers.contains(KeyModifiers::SHIFT) => return Some(IdeEvent::SoftNewline),
+                KeyCode::Enter => return Some(IdeEvent::Enter),
+                KeyCode::Tab => return Some(IdeEvent::Tab),
+                _ => {}
+            }
         }
-    }
 
-    fn handle_normal_key(&self, key: KeyEvent) -> Option<IdeEvent> {
         match key.code {
             // Help
             KeyCode::F(1) | KeyCode::Char('?') => Some(IdeEvent::ToggleHelp),
@@ -202,6 +232,7 @@ impl EventHandler {
             
             // Panel cycling
             KeyCode::Tab => Some(IdeEvent::CycleFocus),
+            KeyCode::BackTab => Some(IdeEvent::BackTab),
             
             // Text input (only in insert mode or chat)
             KeyCode::Char(c) => Some(IdeEvent::InsertChar(c)),
@@ -216,6 +247,12 @@ impl EventHandler {
             MouseEventKind::Down(MouseButton::Left) => {
                 Some(IdeEvent::MouseClick(mouse.column, mouse.row))
             }
+            MouseEventKind::Down(MouseButton::Right) => {
+                Some(IdeEvent::MouseRightClick(mouse.column, mouse.row))
+            }
+            MouseEventKind::Down(MouseButton::Middle) => {
+                Some(IdeEvent::MouseMiddleClick(mouse.column, mouse.row))
+            }
             MouseEventKind::Up(MouseButton::Left) => {
                 Some(IdeEvent::MouseRelease(mouse.column, mouse.row))
             }
@@ -0,0 +1,123 @@
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Which entries the file explorer lists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExplorerSettings {
+    pub ignore: Vec<String>,  // Globs of names or workspace-relative paths to leave out, e.g. "*.log" or "docs/build"
+    pub show_hidden: bool,    // List dotfiles too; Alt+H switches
+    pub use_gitignore: bool,  // Leave out what .gitignore, .ignore and git's exclude files leave out
+}
+
+impl Default for ExplorerSettings {
+    fn default() -> Self {
+        Self { ignore: vec![".git".to_string(), "target".to_string(), "node_modules".to_string()], show_hidden: false, use_gitignore: true }
+    }
+}
+
+/// The explorer's rules for one workspace
+#[derive(Debug, Clone)]
+pub struct ExplorerFilter {
+    root: PathBuf,
+    ignore: GlobSet,
+    pub show_hidden: bool,
+    use_gitignore: bool,
+}
+
+impl ExplorerFilter {
+    /// The filter for the workspace at `root`, and a note for each glob that can't be read
+    pub fn new(root: &Path, settings: &ExplorerSettings) -> (Self, Vec<String>) {
+        let mut builder = GlobSetBuilder::new();
+        let mut problems = Vec::new();
+        for pattern in &settings.ignore {
+            // `*` stays within a directory, `**` crosses them, as in .gitignore
+            match GlobBuilder::new(pattern.trim_start_matches('/').trim_end_matches('/')).literal_separator(true).build() {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => problems.push(format!("bad glob `{}`: {}", pattern, e)),
+            }
+        }
+        let ignore = builder.build().unwrap_or_else(|_| GlobSet::empty());
+        let filter = Self { root: root.to_path_buf(), ignore, show_hidden: settings.show_hidden, use_gitignore: settings.use_gitignore };
+        (filter, problems)
+    }
+
+    /// Whether one of the ignore globs matches the entry's name or its path in the workspace
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let name_matches = path.file_name().is_some_and(|name| self.ignore.is_match(name));
+        name_matches || path.strip_prefix(&self.root).is_ok_and(|relative| self.ignore.is_match(relative))
+    }
+
+    /// The entries to list in `root` and every directory under it, by directory: directories
+    /// first, then files, both by name. It is one walk, so each ignore file is read only once
+    pub fn entries(&self, root: &Path) -> HashMap<PathBuf, Vec<PathBuf>> {
+        let filter = self.clone();
+        let walker = WalkBuilder::new(root)
+            .hidden(!self.show_hidden)
+            .git_ignore(self.use_gitignore)
+            .git_global(self.use_gitignore)
+            .git_exclude(self.use_gitignore)
+            .ignore(self.use_gitignore)
+            .parents(self.use_gitignore)
+            .require_git(false)
+            .filter_entry(move |entry| entry.depth() == 0 || !filter.is_ignored(entry.path()))
+            .build();
+        let mut listed: HashMap<PathBuf, Vec<(bool, PathBuf)>> = HashMap::new();
+        for entry in walker.flatten().filter(|entry| entry.depth() > 0) {
+            let Some(parent) = entry.path().parent().map(Path::to_path_buf) else {
+                continue;
+            };
+            let is_dir = entry.file_type().is_some_and(|kind| kind.is_dir());
+            listed.entry(parent).or_default().push((is_dir, entry.into_path()));
+        }
+        listed
+            .into_iter()
+            .map(|(dir, mut entries)| {
+                entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.file_name().cmp(&b.1.file_name())));
+                (dir, entries.into_iter().map(|(_, path)| path).collect())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_explorer_entries() {
+        let root = std::env::temp_dir().join(format!("explorer-filter-{}", std::process::id()));
+        for dir in ["src", "target", "docs/build", ".github"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["main.rs", "debug.log", ".env", "notes.txt"] {
+            fs::write(root.join(file), "").unwrap();
+        }
+        fs::write(root.join(".gitignore"), "notes.txt\n").unwrap();
+        let names = |filter: &ExplorerFilter, dir: &Path| -> Vec<String> {
+            let entries = filter.entries(&root);
+            entries.get(dir).into_iter().flatten().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+        };
+
+        let mut settings = ExplorerSettings::default();
+        settings.ignore.extend(["*.log".to_string(), "/docs/build/".to_string(), "[".to_string()]);
+        let (mut filter, problems) = ExplorerFilter::new(&root, &settings);
+        assert_eq!(problems.len(), 1);
+        assert_eq!(names(&filter, &root), vec!["docs", "src", "main.rs"]);
+        assert!(names(&filter, &root.join("docs")).is_empty());
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        assert_eq!(names(&filter, &root.join("src")), vec!["lib.rs"]);
+
+        filter.show_hidden = true;
+        assert_eq!(names(&filter, &root), vec![".github", "docs", "src", ".env", ".gitignore", "main.rs"]);
+        let (filter, _) = ExplorerFilter::new(&root, &ExplorerSettings { use_gitignore: false, ..ExplorerSettings::default() });
+        assert_eq!(names(&filter, &root), vec!["docs", "src", "debug.log", "main.rs", "notes.txt"]);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::path::{Path, PathBuf};
+
+/// A `path:line[:col]` reference found in compiler output or an AI answer (1-based line and column)
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileRef {
+    pub path: String,
+    pub line: usize,
+    pub col: Option<usize>,
+}
+
+impl FileRef {
+    /// The file on disk, relative paths taken from `root`
+    pub fn resolve(&self, root: &Path) -> Option<PathBuf> {
+        let path = Path::new(&self.path);
+        let path = if path.is_absolute() { path.to_path_buf() } else { root.join(path) };
+        path.is_file().then_some(path)
+    }
+
+    pub fn label(&self) -> String {
+        match self.col {
+            Some(col) => format!("{}:{}:{}", self.path, self.line, col),
+            None => format!("{}:{}", self.path, self.line),
+        }
+    }
+}
+
+/// Every `path:line` and `path:line:col` in `text`, in order of appearance
+pub fn find(text: &str) -> Vec<FileRef> {
+    text.split(|c: char| c.is_whitespace() || "()[]<>{}`'\",;|".contains(c))
+        .filter_map(parse)
+        .collect()
+}
+
+fn parse(word: &str) -> Option<FileRef> {
+    let word = word.trim_end_matches(['.', ':']);
+    let mut parts = word.split(':');
+    let path = parts.next()?;
+    let line = parts.next()?.parse().ok().filter(|line| *line > 0)?;
+    let col = match parts.next() {
+        Some(col) => Some(col.parse().ok().filter(|col| *col > 0)?),
+        None => None,
+    };
+    // Something that looks like a file: has an extension or a directory, and is not a URL or a time
+    let looks_like_path = (path.contains('.') || path.contains('/'))
+        && !path.starts_with("//")
+        && path.chars().any(|c| c.is_alphabetic());
+    (parts.next().is_none() && looks_like_path).then(|| FileRef { path: path.to_string(), line, col })
+}
+
+/// Row shown in the reference picker
+pub struct RefEntry {
+    pub file_ref: FileRef,
+    pub source: &'static str, // Where it was found, e.g. "chat"
+}
+
+/// Overlay listing the file references in recent chat messages and notifications
+pub struct RefPicker {
+    pub is_open: bool,
+    pub selected: usize,
+    entries: Vec<RefEntry>,
+}
+
+impl Default for RefPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RefPicker {
+    pub fn new() -> Self {
+        Self { is_open: false, selected: 0, entries: Vec::new() }
+    }
+
+    pub fn open(&mut self, entries: Vec<RefEntry>) {
+        self.entries = entries;
+        self.selected = 0;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.entries.clear();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn get_selected(&self) -> Option<&FileRef> {
+        self.entries.get(self.selected).map(|entry| &entry.file_ref)
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let selected_style = Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD);
+        let visible_rows = area.height.saturating_sub(2) as usize;
+        let first = self.selected.saturating_sub(visible_rows.saturating_sub(1));
+        let mut lines = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate().skip(first).take(visible_rows) {
+            let style = if index == self.selected { selected_style } else { Style::default().fg(Color::Gray) };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {} ", entry.file_ref.label()), style),
+                Span::styled(format!(" {}", entry.source), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                " No file:line references in the chat or notifications",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+
+        let picker = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 🔗 File References (Enter: open, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(picker, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_file_refs() {
+        let at = |path: &str, line, col| FileRef { path: path.to_string(), line, col };
+        assert_eq!(find("error[E0308]: mismatched types\n  --> src/main.rs:42:7"), vec![at("src/main.rs", 42, Some(7))]);
+        assert_eq!(find("See (`lib.rs:10`) and Makefile:3."), vec![at("lib.rs", 10, None)]);
+        assert_eq!(find("see /tmp/x/build.log:5:"), vec![at("/tmp/x/build.log", 5, None)]);
+        assert!(find("at 12:30:00, http://localhost:8080 and 1.5:2 and a.rs:0").is_empty());
+
+        let root = std::env::temp_dir();
+        assert_eq!(at("no-such-file.rs", 1, None).resolve(&root), None);
+        assert_eq!(at("src/lib.rs", 4, Some(2)).label(), "src/lib.rs:4:2");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::ide::indent::{IndentSettings, IndentStyle};
+use crate::ide::language;
+use crate::ide::save_hooks::SaveHooks;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// One entry of the config's `filetypes` table (or what `.editorconfig` says about a file);
+/// unset fields leave the global `indent` / `save_hooks` settings in place
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FiletypeSettings {
+    #[serde(default)]
+    pub tab_width: Option<usize>,
+    #[serde(default)]
+    pub indent_style: Option<IndentStyle>,
+    #[serde(default)]
+    pub max_line_length: Option<usize>, // Column of the ruler
+    #[serde(default)]
+    pub trim_trailing_whitespace: Option<bool>,
+    #[serde(default)]
+    pub ensure_final_newline: Option<bool>,
+}
+
+impl FiletypeSettings {
+    /// `other`'s fields where set, ours otherwise
+    fn overridden_by(self, other: FiletypeSettings) -> Self {
+        Self {
+            tab_width: other.tab_width.or(self.tab_width),
+            indent_style: other.indent_style.or(self.indent_style),
+            max_line_length: other.max_line_length.or(self.max_line_length),
+            trim_trailing_whitespace: other.trim_trailing_whitespace.or(self.trim_trailing_whitespace),
+            ensure_final_newline: other.ensure_final_newline.or(self.ensure_final_newline),
+        }
+    }
+}
+
+/// Editor settings in effect for one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSettings {
+    pub tab_width: usize,
+    pub indent_style: IndentStyle,
+    pub max_line_length: Option<usize>,
+    pub trim_trailing_whitespace: bool,
+    pub ensure_final_newline: bool,
+}
+
+impl Default for FileSettings {
+    fn default() -> Self {
+        Self::resolve("", None, &IndentSettings::default(), &SaveHooks::default(), &HashMap::new())
+    }
+}
+
+impl FileSettings {
+    /// Global settings, then the `filetypes` entry for the file's settings key, then `.editorconfig`
+    pub fn resolve(
+        settings_name: &str,
+        path: Option<&Path>,
+        indent: &IndentSettings,
+        hooks: &SaveHooks,
+        filetypes: &HashMap<String, FiletypeSettings>,
+    ) -> Self {
+        let key = language::settings_key(settings_name);
+        let global = FiletypeSettings {
+            tab_width: Some(indent.tab_width),
+            indent_style: Some(indent.style_for(settings_name)),
+            max_line_length: None,
+            trim_trailing_whitespace: Some(hooks.trim_trailing_whitespace),
+            ensure_final_newline: Some(hooks.ensure_final_newline),
+        };
+        let mut settings = global.overridden_by(filetypes.get(&key).cloned().unwrap_or_default());
+        if let Some(path) = path {
+            settings = settings.overridden_by(editorconfig_for(path));
+        }
+        Self {
+            tab_width: settings.tab_width.unwrap_or(indent.tab_width).max(1),
+            indent_style: settings.indent_style.unwrap_or(indent.style),
+            max_line_length: settings.max_line_length,
+            trim_trailing_whitespace: settings.trim_trailing_whitespace.unwrap_or(false),
+            ensure_final_newline: settings.ensure_final_newline.unwrap_or(false),
+        }
+    }
+
+    /// Text inserted by one level of indentation
+    pub fn indent_unit(&self) -> String {
+        match self.indent_style {
+            IndentStyle::Spaces => " ".repeat(self.tab_width),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// What the `.editorconfig` files above `path` say about it; nearer files win, up to one with `root = true`
+pub fn editorconfig_for(path: &Path) -> FiletypeSettings {
+    let mut found = Vec::new();
+    for dir in path.ancestors().skip(1) {
+        let Ok(text) = fs::read_to_string(dir.join(".editorconfig")) else {
+            continue;
+        };
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let (is_root, settings) = parse_editorconfig(&text, &relative.to_string_lossy().replace('\\', "/"));
+        found.push(settings);
+        if is_root {
+            break;
+        }
+    }
+    found.into_iter().rev().fold(FiletypeSettings::default(), FiletypeSettings::overridden_by)
+}
+
+/// Whether the file says `root = true`, and the properties of its sections matching `relative_path`
+fn parse_editorconfig(text: &str, relative_path: &str) -> (bool, FiletypeSettings) {
+    let mut is_root = false;
+    let mut section_matches: Option<bool> = None; // None before the first section
+    let mut settings = FiletypeSettings::default();
+    let mut indent_size = None;
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(pattern) = line.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+            section_matches = Some(glob_matches(pattern, relative_path));
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let (key, value) = (key.trim().to_lowercase(), value.trim().to_lowercase());
+        match section_matches {
+            None if key == "root" => is_root = value == "true",
+            Some(true) => match key.as_str() {
+                "indent_style" => settings.indent_style = match value.as_str() {
+                    "space" => Some(IndentStyle::Spaces),
+                    "tab" => Some(IndentStyle::Tabs),
+                    _ => settings.indent_style,
+                },
+                "indent_size" => indent_size = value.parse().ok().or(indent_size),
+                "tab_width" => settings.tab_width = value.parse().ok().or(settings.tab_width),
+                "max_line_length" => settings.max_line_length = value.parse().ok(),
+                "trim_trailing_whitespace" => settings.trim_trailing_whitespace = value.parse().ok(),
+                "insert_final_newline" => settings.ensure_final_newline = value.parse().ok(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    // `indent_size` is what decides the width of an indentation level here
+    settings.tab_width = indent_size.or(settings.tab_width);
+    (is_root, settings)
+}
+
+/// EditorConfig glob: `*` stays within a directory, `**` crosses them, `?` is one character and
+/// `{a,b}` lists alternatives. Patterns without a `/` match the file name in any directory
+fn glob_matches(pattern: &str, relative_path: &str) -> bool {
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let mut regex = String::from(if anchored { "^" } else { "^(?:.*/)?" });
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                regex.push_str(".*");
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '{' => regex.push_str("(?:"),
+            '}' => regex.push(')'),
+            ',' => regex.push('|'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).is_ok_and(|regex| regex.is_match(relative_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filetype_settings() {
+        assert!(glob_matches("*.{js,ts}", "web/app.ts"));
+        assert!(glob_matches("Makefile", "tools/Makefile"));
+        assert!(glob_matches("src/**.rs", "src/ide/app.rs"));
+        assert!(!glob_matches("/*.rs", "src/main.rs"));
+
+        let dir = std::env::temp_dir().join(format!("i4z-editorconfig-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("web")).unwrap();
+        fs::write(dir.join(".editorconfig"), "root = true\n\n[*]\nindent_style = space\nindent_size = 4\n\n[*.md]\ntrim_trailing_whitespace = false\n").unwrap();
+        fs::write(dir.join("web/.editorconfig"), "[*.ts]\nindent_size = 2\nmax_line_length = 100\n").unwrap();
+
+        let filetypes = HashMap::from([
+            ("ts".to_string(), FiletypeSettings { indent_style: Some(IndentStyle::Tabs), max_line_length: Some(80), ..Default::default() }),
+            ("py".to_string(), FiletypeSettings { max_line_length: Some(88), ..Default::default() }),
+        ]);
+        let resolve = |name: &str, path: Option<&Path>| {
+            FileSettings::resolve(name, path, &IndentSettings::default(), &SaveHooks::default(), &filetypes)
+        };
+
+        // `.editorconfig` beats the config's table, which beats the global settings
+        let ts = resolve("app.ts", Some(&dir.join("web/app.ts")));
+        assert_eq!((ts.indent_unit(), ts.max_line_length), ("  ".to_string(), Some(100)));
+        assert!(!resolve("notes.md", Some(&dir.join("notes.md"))).trim_trailing_whitespace);
+        assert_eq!(resolve("tool.py", None).max_line_length, Some(88));
+        assert_eq!(resolve("main.go", None).indent_unit(), "\t");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineNumbers {
+    #[default]
+    Absolute,
+    Relative, // Distance from the cursor line, for count motions like `5j`
+    Hybrid,   // Relative, but the cursor line shows its absolute number
+    Off,
+}
+
+impl LineNumbers {
+    pub fn next(self) -> Self {
+        match self {
+            LineNumbers::Absolute => LineNumbers::Relative,
+            LineNumbers::Relative => LineNumbers::Hybrid,
+            LineNumbers::Hybrid => LineNumbers::Off,
+            LineNumbers::Off => LineNumbers::Absolute,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LineNumbers::Absolute => "absolute",
+            LineNumbers::Relative => "relative",
+            LineNumbers::Hybrid => "hybrid",
+            LineNumbers::Off => "off",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GutterSettings {
+    #[serde(default)]
+    pub line_numbers: LineNumbers,
+    #[serde(default = "default_true")]
+    pub sign_column: bool, // One-character column left of the numbers for marks and diagnostics
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for GutterSettings {
+    fn default() -> Self {
+        Self { line_numbers: LineNumbers::Absolute, sign_column: true }
+    }
+}
+
+impl GutterSettings {
+    /// Width of the number column for a buffer of `total_lines` lines (at least 3, like before)
+    pub fn number_width(&self, total_lines: usize) -> usize {
+        match self.line_numbers {
+            LineNumbers::Off => 0,
+            _ => total_lines.to_string().len().max(3),
+        }
+    }
+
+    /// Columns taken by the gutter, separator included
+    pub fn width(&self, number_width: usize) -> usize {
+        self.label(0, 0, number_width, None).chars().count()
+    }
+
+    /// Gutter text for a line, `distance` rows away from the cursor line, including the separator
+    pub fn label(&self, line_index: usize, distance: usize, width: usize, sign: Option<char>) -> String {
+        let sign = if self.sign_column { sign.unwrap_or(' ').to_string() } else { String::new() };
+        let number = match self.line_numbers {
+            LineNumbers::Off => return if sign.is_empty() { String::new() } else { format!("{} ", sign) },
+            LineNumbers::Absolute => format!("{:>width$}", line_index + 1),
+            LineNumbers::Relative => format!("{:>width$}", distance),
+            // Vim puts the cursor line's number on the left so it stands out
+            LineNumbers::Hybrid if distance == 0 => format!("{:<width$}", line_index + 1),
+            LineNumbers::Hybrid => format!("{:>width$}", distance),
+        };
+        format!("{}{} │ ", sign, number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gutter_labels() {
+        let mut gutter = GutterSettings::default();
+        let width = gutter.number_width(1200);
+        assert_eq!(width, 4);
+        assert_eq!(gutter.label(41, 3, width, None), "   42 │ ");
+        assert_eq!(gutter.label(41, 3, width, Some('a')), "a  42 │ ");
+
+        gutter.sign_column = false;
+        gutter.line_numbers = LineNumbers::Relative;
+        assert_eq!(gutter.label(41, 3, 3, None), "  3 │ ");
+        gutter.line_numbers = LineNumbers::Hybrid;
+        assert_eq!(gutter.label(41, 0, 3, None), "42  │ ");
+        assert_eq!(gutter.label(45, 4, 3, None), "  4 │ ");
+
+        gutter.line_numbers = LineNumbers::Off;
+        assert_eq!(gutter.number_width(10), 0);
+        assert_eq!(gutter.label(0, 0, 0, None), "");
+        assert_eq!(LineNumbers::Off.next(), LineNumbers::Absolute);
+    }
+}
@@ -0,0 +1,55 @@
+// Only the start of a binary file is rendered; the rest is summarised in a footer line
+pub const MAX_PREVIEW_BYTES: usize = 64 * 1024;
+
+// Same heuristic as git: a NUL byte near the start means binary
+const SNIFF_BYTES: usize = 8000;
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    // UTF-16 text is full of NULs but carries a BOM that the decoder understands
+    if bytes.starts_with(&[0xFF, 0xFE]) || bytes.starts_with(&[0xFE, 0xFF]) {
+        return false;
+    }
+    bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0)
+}
+
+/// `hexdump -C` style preview: offset, 16 hex bytes and their printable ASCII
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let shown = &bytes[..bytes.len().min(MAX_PREVIEW_BYTES)];
+    let mut output = String::new();
+
+    for (index, row) in shown.chunks(16).enumerate() {
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let (left, right) = hex.split_at(hex.len().min(8));
+        let ascii: String = row
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        output.push_str(&format!("{:08x}  {:<23}  {:<23}  |{}|\n", index * 16, left.join(" "), right.join(" "), ascii));
+    }
+
+    if shown.len() < bytes.len() {
+        output.push_str(&format!("… {} more bytes not shown", bytes.len() - shown.len()));
+    } else {
+        output.push_str(&format!("{:08x}", bytes.len()));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_detection_and_dump() {
+        assert!(is_binary(b"\x7fELF\x02\x01\x00\x00"));
+        assert!(!is_binary(b"fn main() {}\n"));
+        assert!(!is_binary(&[0xFF, 0xFE, b'h', 0]));
+
+        assert_eq!(
+            hex_dump(b"Hello, binary\x00\x01\x02!"),
+            "00000000  48 65 6c 6c 6f 2c 20 62  69 6e 61 72 79 00 01 02  |Hello, binary...|\n\
+             00000010  21                                                |!|\n\
+             00000011"
+        );
+    }
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndentStyle {
+    Spaces,
+    Tabs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndentSettings {
+    pub tab_width: usize,
+    pub style: IndentStyle,
+    #[serde(default)]
+    pub filetypes: HashMap<String, IndentStyle>, // Keyed by extension, or file name for files like Makefile
+}
+
+impl Default for IndentSettings {
+    fn default() -> Self {
+        let filetypes = [("go", IndentStyle::Tabs), ("makefile", IndentStyle::Tabs), ("mk", IndentStyle::Tabs)]
+            .into_iter()
+            .map(|(key, style)| (key.to_string(), style))
+            .collect();
+        Self {
+            tab_width: 4,
+            style: IndentStyle::Spaces,
+            filetypes,
+        }
+    }
+}
+
+impl IndentSettings {
+    pub fn style_for(&self, file_name: &str) -> IndentStyle {
+        let key = super::language::settings_key(file_name);
+        self.filetypes.get(&key).copied().unwrap_or(self.style)
+    }
+
+    /// Text inserted by one level of indentation
+    pub fn unit_for(&self, file_name: &str) -> String {
+        match self.style_for(file_name) {
+            IndentStyle::Spaces => " ".repeat(self.tab_width.max(1)),
+            IndentStyle::Tabs => "\t".to_string(),
+        }
+    }
+}
+
+/// Bytes to strip from the start of `line` to remove one level of indentation
+pub fn dedent_width(line: &str, tab_width: usize) -> usize {
+    if line.starts_with('\t') {
+        return 1;
+    }
+    line.bytes().take(tab_width.max(1)).take_while(|&byte| byte == b' ').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_indent_settings() {
+        let settings = IndentSettings::default();
+        assert_eq!(settings.unit_for("main.rs"), "    ");
+        assert_eq!(settings.unit_for("main.go"), "\t");
+        assert_eq!(settings.unit_for("Makefile"), "\t");
+
+        assert_eq!(dedent_width("\t\tx", 4), 1);
+        assert_eq!(dedent_width("      x", 4), 4);
+        assert_eq!(dedent_width("  x", 4), 2);
+        assert_eq!(dedent_width("x", 4), 0);
+    }
+}
@@ -0,0 +1,466 @@
+use crate::agent::code_commands::CodeCommand;
+use crate::ide::events::IdeEvent;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Events a key chord can be bound to, by the name used in the `keymap` config section
+const EVENTS: &[(&str, IdeEvent)] = &[
+    ("Quit", IdeEvent::Quit),
+    ("ToggleHelp", IdeEvent::ToggleHelp),
+    ("ToggleCommandHelp", IdeEvent::ToggleCommandHelp),
+    ("ShowKeymap", IdeEvent::ShowKeymap),
+    ("ShowSettings", IdeEvent::ShowSettings),
+    ("ShowSessions", IdeEvent::ShowSessions),
+    ("ShowBranches", IdeEvent::ShowBranches),
+    ("NewCheckpoint", IdeEvent::NewCheckpoint),
+    ("PinLastAnswer", IdeEvent::PinLastAnswer),
+    ("EditProjectMemory", IdeEvent::EditProjectMemory),
+    ("NextThread", IdeEvent::NextThread),
+    ("PreviousThread", IdeEvent::PreviousThread),
+    ("NewThread", IdeEvent::NewThread),
+    ("EditPreviousMessage", IdeEvent::EditPreviousMessage),
+    ("SearchPromptHistory", IdeEvent::SearchPromptHistory),
+    ("ToggleAgenticMode", IdeEvent::ToggleAgenticMode),
+    ("UndoAgentChange", IdeEvent::UndoAgentChange),
+    ("UndoAgentSession", IdeEvent::UndoAgentSession),
+    ("CycleAutonomyLevel", IdeEvent::CycleAutonomyLevel),
+    ("ToggleLowBandwidth", IdeEvent::ToggleLowBandwidth),
+    ("ToggleDryRun", IdeEvent::ToggleDryRun),
+    ("RunTestsAndFix", IdeEvent::RunTestsAndFix),
+    ("ShowAgentNotes", IdeEvent::ShowAgentNotes),
+    ("ShowAgentTasks", IdeEvent::ShowAgentTasks),
+    ("ExplainCode", IdeEvent::CodeCommand(CodeCommand::Explain)),
+    ("AddDocComments", IdeEvent::CodeCommand(CodeCommand::AddDocComments)),
+    ("WriteTests", IdeEvent::CodeCommand(CodeCommand::WriteTests)),
+    ("RefactorCode", IdeEvent::CodeCommand(CodeCommand::Refactor)),
+    ("ShowApiConfig", IdeEvent::ShowApiConfig),
+    ("SelectModel", IdeEvent::SelectModel),
+    ("ClearNotifications", IdeEvent::ClearNotifications),
+    ("ToggleCommandPalette", IdeEvent::ToggleCommandPalette),
+    ("ToggleEmojiPicker", IdeEvent::ToggleEmojiPicker),
+    ("Suspend", IdeEvent::Suspend),
+    ("ToggleSplit", IdeEvent::ToggleSplit),
+    ("FocusOtherPane", IdeEvent::FocusOtherPane),
+    ("ShowDiff", IdeEvent::ShowDiff),
+    ("ShowGitDiff", IdeEvent::ShowGitDiff),
+    ("ComparePanes", IdeEvent::ComparePanes),
+    ("ShowMarks", IdeEvent::ShowMarks),
+    ("ShowFileRefs", IdeEvent::ShowFileRefs),
+    ("TogglePinTab", IdeEvent::TogglePinTab),
+    ("ShowNotificationHistory", IdeEvent::ShowNotificationHistory),
+    ("CycleLineNumbers", IdeEvent::CycleLineNumbers),
+    ("ToggleRuler", IdeEvent::ToggleRuler),
+    ("CycleTheme", IdeEvent::CycleTheme),
+    ("ToggleWhitespace", IdeEvent::ToggleWhitespace),
+    ("PrettyPrint", IdeEvent::PrettyPrint),
+    ("SetLanguage", IdeEvent::SetLanguage),
+    ("ToggleComment", IdeEvent::ToggleComment),
+    ("FocusFileExplorer", IdeEvent::FocusFileExplorer),
+    ("FocusEditor", IdeEvent::FocusEditor),
+    ("FocusChat", IdeEvent::FocusChat),
+    ("FocusNotifications", IdeEvent::FocusNotifications),
+    ("CycleFocus", IdeEvent::CycleFocus),
+    ("ResizeSidebarExpand", IdeEvent::ResizeSidebarExpand),
+    ("ResizeSidebarShrink", IdeEvent::ResizeSidebarShrink),
+    ("ResizeChatExpand", IdeEvent::ResizeChatExpand),
+    ("ResizeChatShrink", IdeEvent::ResizeChatShrink),
+    ("ResizeNotificationsExpand", IdeEvent::ResizeNotificationsExpand),
+    ("ResizeNotificationsShrink", IdeEvent::ResizeNotificationsShrink),
+    ("SaveFile", IdeEvent::SaveFile),
+    ("SaveAsFile", IdeEvent::SaveAsFile),
+    ("NewFile", IdeEvent::NewFile),
+    ("NewFolder", IdeEvent::NewFolder),
+    ("CloseFile", IdeEvent::CloseFile),
+    ("MoveLineUp", IdeEvent::MoveLineUp),
+    ("MoveLineDown", IdeEvent::MoveLineDown),
+    ("DuplicateLine", IdeEvent::DuplicateLine),
+    ("JoinLines", IdeEvent::JoinLines),
+    ("SendMessage", IdeEvent::SendMessage),
+    ("SendMessageWithImage", IdeEvent::SendMessageWithImage),
+    ("SendMessageWithFile", IdeEvent::SendMessageWithFile),
+    ("ClearChat", IdeEvent::ClearChat),
+    ("CopyLastAnswer", IdeEvent::CopyLastAnswer),
+    ("PipeLastAnswer", IdeEvent::PipeLastAnswer),
+    ("SaveWorkflow", IdeEvent::SaveWorkflow),
+    ("StartWorkflow", IdeEvent::StartWorkflow),
+    ("RefreshFileTree", IdeEvent::RefreshFileTree),
+    ("ToggleHiddenFiles", IdeEvent::ToggleHiddenFiles),
+    ("NextTab", IdeEvent::NextTab),
+    ("PreviousTab", IdeEvent::PreviousTab),
+];
+
+/// Built-in bindings; plain keys such as Esc, Enter and the arrows are handled by `EventHandler`
+const DEFAULTS: &[(&str, &str)] = &[
+    ("ctrl+q", "Quit"),
+    ("ctrl+c", "Quit"),
+    ("ctrl+h", "ToggleCommandHelp"),
+    ("ctrl+s", "SaveFile"),
+    ("ctrl+n", "NewFile"),
+    ("ctrl+t", "NewFile"),
+    ("ctrl+w", "CloseFile"),
+    ("ctrl+o", "FocusFileExplorer"),
+    ("ctrl+d", "NewFolder"),
+    ("ctrl+l", "ClearChat"),
+    ("ctrl+enter", "SendMessage"),
+    ("ctrl+i", "SendMessageWithImage"),
+    ("ctrl+y", "CopyLastAnswer"),
+    ("ctrl+a", "ToggleAgenticMode"),
+    ("ctrl+,", "ShowApiConfig"),
+    ("ctrl+p", "ToggleCommandPalette"),
+    ("ctrl+e", "ToggleEmojiPicker"),
+    ("ctrl+k", "ClearNotifications"),
+    ("ctrl+z", "Suspend"),
+    // Terminals send Ctrl+\ as the same byte as Ctrl+4, and Ctrl+/ as Ctrl+7
+    ("ctrl+\\", "ToggleSplit"),
+    ("ctrl+4", "ToggleSplit"),
+    ("ctrl+/", "ToggleComment"),
+    ("ctrl+7", "ToggleComment"),
+    ("ctrl+right", "ResizeSidebarExpand"),
+    ("ctrl+left", "ResizeSidebarShrink"),
+    ("ctrl+up", "ResizeChatShrink"),
+    ("ctrl+down", "ResizeChatExpand"),
+    ("ctrl+r", "RefreshFileTree"),
+    // Searches past prompts while typing one, as in a shell
+    ("chat:ctrl+r", "SearchPromptHistory"),
+    ("ctrl+tab", "NextTab"),
+    ("ctrl+shift+tab", "PreviousTab"),
+    ("ctrl+shift+enter", "SendMessageWithFile"),
+    ("ctrl+shift+d", "DuplicateLine"),
+    ("ctrl+shift+up", "ResizeNotificationsShrink"),
+    ("ctrl+shift+down", "ResizeNotificationsExpand"),
+    ("alt+1", "FocusFileExplorer"),
+    ("alt+2", "FocusEditor"),
+    ("alt+3", "FocusChat"),
+    ("alt+4", "FocusNotifications"),
+    ("alt+up", "MoveLineUp"),
+    ("alt+down", "MoveLineDown"),
+    ("alt+h", "ToggleHiddenFiles"),
+    ("alt+.", "NextThread"),
+    ("alt+,", "PreviousThread"),
+    ("alt+e", "EditPreviousMessage"),
+];
+
+/// Panels a binding can be limited to, written before the chord as in `chat:ctrl+r`; such a
+/// binding wins over one for everywhere while that panel has the focus
+pub const CONTEXTS: &[&str] = &["chat", "editor", "explorer"];
+
+/// `chat:ctrl+r` as the context and the chord text
+fn split_context(text: &str) -> Result<(Option<&'static str>, &str), String> {
+    match text.trim().split_once(':') {
+        Some((context, chord)) => match CONTEXTS.iter().find(|known| known.eq_ignore_ascii_case(context.trim())) {
+            Some(context) => Ok((Some(*context), chord)),
+            None => Err(format!("unknown context `{}`", context.trim())),
+        },
+        None => Ok((None, text)),
+    }
+}
+
+/// A key with the Ctrl, Alt and Shift modifiers held
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Chord {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+enum Key {
+    Char(char), // Lowercase
+    F(u8),
+    Named(&'static str),
+}
+
+/// Keys written by name, with the crossterm key each stands for
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("esc", KeyCode::Esc),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("insert", KeyCode::Insert),
+];
+
+impl Chord {
+    /// `ctrl+shift+d`, `alt+up` or `f5`, in any case
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim().to_lowercase();
+        // The key itself may be `+`, as in `ctrl++`
+        let (modifiers, key) = match text.strip_suffix("++") {
+            Some(modifiers) => (modifiers, "+"),
+            None => text.rsplit_once('+').unwrap_or(("", text.as_str())),
+        };
+        let mut chord = Chord { ctrl: false, alt: false, shift: false, key: Key::Char(' ') };
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier {
+                "ctrl" | "control" => chord.ctrl = true,
+                "alt" | "meta" => chord.alt = true,
+                "shift" => chord.shift = true,
+                other => return Err(format!("unknown modifier `{}`", other)),
+            }
+        }
+        let function_key = key.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()).filter(|n| (1..=24).contains(n));
+        let named = NAMED_KEYS.iter().find(|(name, _)| *name == key);
+        let mut chars = key.chars();
+        chord.key = match (chars.next(), chars.next(), function_key, named) {
+            _ if key == "space" => Key::Char(' '),
+            (_, _, Some(n), _) => Key::F(n),
+            (_, _, _, Some((name, _))) => Key::Named(name),
+            (Some(c), None, _, _) => Key::Char(c),
+            _ => return Err(format!("unknown key `{}`", key)),
+        };
+        Ok(chord)
+    }
+
+    /// The chord of a key press. Shift only counts for keys it doesn't already change, or with Ctrl or Alt
+    pub fn from_key(key: KeyEvent) -> Option<Self> {
+        let (ctrl, alt) = (key.modifiers.contains(KeyModifiers::CONTROL), key.modifiers.contains(KeyModifiers::ALT));
+        let mut shift = key.modifiers.contains(KeyModifiers::SHIFT);
+        let key = match key.code {
+            KeyCode::Char(c) => {
+                shift &= ctrl || alt;
+                Key::Char(c.to_ascii_lowercase())
+            }
+            KeyCode::BackTab => {
+                shift = true;
+                Key::Named("tab")
+            }
+            KeyCode::F(n) => Key::F(n),
+            code => Key::Named(NAMED_KEYS.iter().find(|(_, named)| *named == code)?.0),
+        };
+        Some(Chord { ctrl, alt, shift, key })
+    }
+
+    /// Whether binding it leaves typing and the fixed keys alone: it needs Ctrl or Alt, unless it is a function key
+    fn bindable(&self) -> bool {
+        self.ctrl || self.alt || matches!(self.key, Key::F(_))
+    }
+}
+
+impl Binding {
+    /// The chord with its context, e.g. `Ctrl+R (chat)`
+    pub fn shown_chord(&self) -> String {
+        match self.context {
+            Some(context) => format!("{} ({})", self.chord, context),
+            None => self.chord.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.ctrl, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+")] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        match self.key {
+            Key::Char(' ') => write!(f, "Space"),
+            Key::Char(c) => write!(f, "{}", c.to_ascii_uppercase()),
+            Key::F(n) => write!(f, "F{}", n),
+            Key::Named(name) => {
+                let mut chars = name.chars();
+                let first = chars.next().map(|c| c.to_ascii_uppercase()).unwrap_or_default();
+                write!(f, "{}{}", first, chars.as_str())
+            }
+        }
+    }
+}
+
+/// The event called `name`, ignoring case
+pub fn event_named(name: &str) -> Option<IdeEvent> {
+    EVENTS.iter().find(|(event, _)| event.eq_ignore_ascii_case(name)).map(|(_, event)| event.clone())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub chord: Chord,
+    pub context: Option<&'static str>, // Only while this panel has the focus
+    pub event: &'static str,
+    pub custom: bool, // From the config rather than built in
+}
+
+/// Key chords and the events they send: the built-in bindings with the config's on top
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    bindings: Vec<Binding>,
+}
+
+impl Keymap {
+    /// The effective keymap for the config's `keymap` section (chord -> event name, or "none" to
+    /// unbind), and what was wrong with it or overrode a built-in binding
+    pub fn load(custom: &HashMap<String, String>) -> (Self, Vec<String>) {
+        let mut bindings: Vec<Binding> = DEFAULTS
+            .iter()
+            .filter_map(|(text, event)| {
+                let event = EVENTS.iter().find(|(name, _)| name == event)?.0;
+                let (context, chord) = split_context(text).ok()?;
+                Some(Binding { chord: Chord::parse(chord).ok()?, context, event, custom: false })
+            })
+            .collect();
+        let mut problems = Vec::new();
+        let mut entries: Vec<(&String, &String)> = custom.iter().collect();
+        entries.sort();
+        for (text, name) in entries {
+            let parsed = split_context(text).and_then(|(context, chord)| Ok((context, Chord::parse(chord)?)));
+            let (context, chord) = match parsed {
+                Ok((context, chord)) if chord.bindable() => (context, chord),
+                Ok(_) => {
+                    problems.push(format!("`{}`: only Ctrl or Alt chords and function keys can be bound", text));
+                    continue;
+                }
+                Err(e) => {
+                    problems.push(format!("`{}`: {}", text, e));
+                    continue;
+                }
+            };
+            let event = match name.trim() {
+                "" | "none" => None,
+                name => match EVENTS.iter().find(|(event, _)| event.eq_ignore_ascii_case(name)) {
+                    Some((event, _)) => Some(*event),
+                    None => {
+                        problems.push(format!("`{}`: unknown event `{}`", text, name));
+                        continue;
+                    }
+                },
+            };
+            if let Some(existing) = bindings.iter().position(|binding| binding.chord == chord && binding.context == context) {
+                let replaced = bindings.remove(existing);
+                let shown = replaced.shown_chord();
+                if replaced.custom {
+                    problems.push(format!("{} is bound twice; `{}` wins over {}", shown, text, replaced.event));
+                } else if event != Some(replaced.event) {
+                    problems.push(format!("{} now sends {} instead of {}", shown, event.unwrap_or("nothing"), replaced.event));
+                }
+            }
+            if let Some(event) = event {
+                bindings.push(Binding { chord, context, event, custom: true });
+            }
+        }
+        bindings.sort_by_key(|binding| (binding.event, binding.chord, binding.context));
+        (Self { bindings }, problems)
+    }
+
+    /// The event a key press is bound to in `context`, the focused panel's, or everywhere
+    pub fn event_for(&self, key: KeyEvent, context: Option<&str>) -> Option<IdeEvent> {
+        let chord = Chord::from_key(key)?;
+        let bound = |wanted: Option<&str>| self.bindings.iter().find(|binding| binding.chord == chord && binding.context == wanted);
+        let binding = context.and_then(|context| bound(Some(context))).or_else(|| bound(None))?;
+        event_named(binding.event)
+    }
+
+    /// The bindings, sorted by event name
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+}
+
+/// Overlay listing the effective key bindings
+#[derive(Default)]
+pub struct KeymapView {
+    pub is_open: bool,
+    scroll: usize,
+}
+
+impl KeymapView {
+    pub fn open(&mut self) {
+        self.scroll = 0;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    pub fn scroll(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, keymap: &Keymap) {
+        frame.render_widget(Clear, area);
+        let mut lines: Vec<Line> = keymap
+            .bindings()
+            .iter()
+            .map(|binding| {
+                let style = if binding.custom { Style::default().fg(Color::Yellow) } else { Style::default().fg(Color::Gray) };
+                let origin = if binding.custom { "  (config)" } else { "" };
+                Line::from(vec![
+                    Span::styled(format!(" {:<25}", binding.shown_chord()), style.add_modifier(Modifier::BOLD)),
+                    Span::styled(format!("{}{}", binding.event, origin), style),
+                ])
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            " Esc, Enter, Tab, the arrows, F1 (help) and F2 (rename) are fixed",
+            Style::default().fg(Color::DarkGray),
+        )));
+        self.scroll = self.scroll.min(lines.len().saturating_sub(1));
+
+        let view = Paragraph::new(lines)
+            .scroll((self.scroll as u16, 0))
+            .block(Block::default()
+                .title(" ⌨️ Keymap (↑/↓: scroll, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+        frame.render_widget(view, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keymap_load() {
+        let (keymap, problems) = Keymap::load(&HashMap::new());
+        assert!(problems.is_empty());
+        assert_eq!(keymap.bindings().len(), DEFAULTS.len());
+
+        let ctrl_shift_d = KeyEvent::new(KeyCode::Char('D'), KeyModifiers::CONTROL | KeyModifiers::SHIFT);
+        assert!(matches!(keymap.event_for(ctrl_shift_d, None), Some(IdeEvent::DuplicateLine)));
+        assert!(keymap.event_for(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE), None).is_none());
+        let ctrl_r = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL);
+        assert!(matches!(keymap.event_for(ctrl_r, Some("chat")), Some(IdeEvent::SearchPromptHistory)));
+        assert!(matches!(keymap.event_for(ctrl_r, Some("editor")), Some(IdeEvent::RefreshFileTree)));
+
+        let custom: HashMap<String, String> = [
+            ("Ctrl+B", "togglesplit"),
+            ("ctrl+s", "none"),
+            ("f5", "RunTestsAndFix"),
+            ("x", "Quit"),
+            ("ctrl+s+", "Quit"),
+            ("alt+9", "Fly"),
+            ("editor:ctrl+r", "ToggleSplit"),
+            ("tabs:ctrl+r", "Quit"),
+        ]
+        .into_iter()
+        .map(|(chord, event)| (chord.to_string(), event.to_string()))
+        .collect();
+        let (keymap, problems) = Keymap::load(&custom);
+        assert_eq!(problems.len(), 5, "{:?}", problems);
+        assert!(problems.iter().any(|problem| problem == "Ctrl+S now sends nothing instead of SaveFile"));
+        assert!(matches!(keymap.event_for(KeyEvent::new(KeyCode::Char('b'), KeyModifiers::CONTROL), None), Some(IdeEvent::ToggleSplit)));
+        assert!(keymap.event_for(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL), None).is_none());
+        assert!(matches!(keymap.event_for(KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE), None), Some(IdeEvent::RunTestsAndFix)));
+        assert!(matches!(keymap.event_for(ctrl_r, Some("editor")), Some(IdeEvent::ToggleSplit)));
+        assert!(matches!(keymap.event_for(ctrl_r, Some("explorer")), Some(IdeEvent::RefreshFileTree)));
+        assert_eq!(Chord::parse("ctrl+shift+tab").unwrap().to_string(), "Ctrl+Shift+Tab");
+    }
+}
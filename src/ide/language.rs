@@ -0,0 +1,114 @@
+use std::path::Path;
+
+/// A file type the editor knows about
+#[derive(Debug, PartialEq)]
+pub struct Language {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub icon: &'static str,
+    pub extensions: &'static [&'static str],
+    pub file_names: &'static [&'static str], // Lowercase names of extension-less files, e.g. "dockerfile"
+    pub interpreters: &'static [&'static str], // Matched against a `#!` line
+    pub line_comment: Option<&'static str>,
+}
+
+const fn language(
+    id: &'static str,
+    name: &'static str,
+    icon: &'static str,
+    extensions: &'static [&'static str],
+    file_names: &'static [&'static str],
+    interpreters: &'static [&'static str],
+    line_comment: Option<&'static str>,
+) -> Language {
+    Language { id, name, icon, extensions, file_names, interpreters, line_comment }
+}
+
+pub const PLAIN_TEXT: Language = language("plaintext", "Plain Text", "📃", &["txt"], &[], &[], None);
+
+pub const LANGUAGES: &[Language] = &[
+    language("rust", "Rust", "🦀", &["rs"], &[], &[], Some("//")),
+    language("python", "Python", "🐍", &["py", "pyw", "pyi"], &[], &["python", "python3"], Some("#")),
+    language("javascript", "JavaScript", "📜", &["js", "mjs", "cjs", "jsx"], &[], &["node"], Some("//")),
+    language("typescript", "TypeScript", "📜", &["ts", "tsx", "mts"], &[], &["deno", "ts-node"], Some("//")),
+    language("json", "JSON", "📋", &["json", "geojson", "map"], &[], &[], None),
+    language("toml", "TOML", "⚙️", &["toml"], &["cargo.lock"], &[], Some("#")),
+    language("yaml", "YAML", "⚙️", &["yaml", "yml"], &[], &[], Some("#")),
+    language("markdown", "Markdown", "📄", &["md", "markdown"], &[], &[], None),
+    language("html", "HTML", "🌐", &["html", "htm"], &[], &[], None),
+    language("css", "CSS", "🎨", &["css", "scss"], &[], &[], None),
+    language("shell", "Shell Script", "🐚", &["sh", "bash", "zsh"], &[".bashrc", ".zshrc", ".profile"], &["sh", "bash", "zsh", "dash"], Some("#")),
+    language("dockerfile", "Dockerfile", "🐳", &["dockerfile"], &["dockerfile", "containerfile"], &[], Some("#")),
+    language("makefile", "Makefile", "🛠️", &["mk"], &["makefile", "gnumakefile"], &["make"], Some("#")),
+    language("go", "Go", "🐹", &["go"], &[], &[], Some("//")),
+    language("c", "C", "🔧", &["c", "h"], &[], &[], Some("//")),
+    language("cpp", "C++", "🔧", &["cpp", "cc", "cxx", "hpp", "hh"], &[], &[], Some("//")),
+    language("java", "Java", "☕", &["java"], &[], &[], Some("//")),
+    language("ruby", "Ruby", "💎", &["rb"], &["gemfile", "rakefile"], &["ruby"], Some("#")),
+    language("lua", "Lua", "🌙", &["lua"], &[], &["lua"], Some("--")),
+    language("sql", "SQL", "🗃️", &["sql"], &[], &[], Some("--")),
+    PLAIN_TEXT,
+];
+
+impl Language {
+    /// Name under which per-filetype settings (indent styles, formatters) are looked up
+    pub fn settings_key(&self) -> &'static str {
+        self.extensions.first().or(self.file_names.first()).copied().unwrap_or(self.id)
+    }
+}
+
+pub fn by_id(id: &str) -> Option<&'static Language> {
+    LANGUAGES.iter().find(|language| language.id == id)
+}
+
+/// Language from a file's extension or name, then its `#!` line; plain text otherwise
+pub fn detect(file_name: &str, first_line: Option<&str>) -> &'static Language {
+    let key = settings_key(file_name);
+    let name = file_name.to_lowercase();
+    let by_name = LANGUAGES.iter().find(|language| {
+        language.extensions.contains(&key.as_str()) || language.file_names.contains(&name.as_str())
+    });
+    by_name
+        .or_else(|| first_line.and_then(interpreter).and_then(|program| {
+            LANGUAGES.iter().find(|language| language.interpreters.contains(&program))
+        }))
+        .unwrap_or(&PLAIN_TEXT)
+}
+
+/// Per-filetype settings key of a file: its extension, or its whole name when it has none (lowercase)
+pub fn settings_key(file_name: &str) -> String {
+    let path = Path::new(file_name);
+    path.extension()
+        .or_else(|| path.file_name())
+        .and_then(|key| key.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Program named by a `#!` line, looking through `/usr/bin/env`
+fn interpreter(line: &str) -> Option<&str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    Some(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect("main.rs", None).id, "rust");
+        assert_eq!(detect("Dockerfile", None).id, "dockerfile");
+        assert_eq!(detect("deploy", Some("#!/usr/bin/env -S python3 -u")).id, "python");
+        assert_eq!(detect("deploy", Some("#!/bin/bash")).id, "shell");
+        assert_eq!(detect("notes", Some("hello")), &PLAIN_TEXT);
+
+        assert_eq!(by_id("dockerfile").map(Language::settings_key), Some("dockerfile"));
+        assert_eq!(by_id("python").map(Language::settings_key), Some("py"));
+        assert_eq!(settings_key("Makefile"), "makefile");
+    }
+}
@@ -0,0 +1,124 @@
+use crate::ide::language::{Language, LANGUAGES};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Filterable list for setting the current tab's language by hand
+pub struct LanguagePicker {
+    pub is_open: bool,
+    pub query: String,
+    pub selected: usize,
+    detected: &'static str, // Name shown on the "auto-detect" row
+}
+
+impl Default for LanguagePicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LanguagePicker {
+    pub fn new() -> Self {
+        Self { is_open: false, query: String::new(), selected: 0, detected: "" }
+    }
+
+    pub fn open(&mut self, detected: &'static str) {
+        self.is_open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.detected = detected;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches().len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Matching rows; `None` is the "auto-detect" row, listed while the query is empty
+    pub fn matches(&self) -> Vec<Option<&'static Language>> {
+        let query = self.query.to_lowercase();
+        let auto = query.is_empty().then_some(None);
+        auto.into_iter()
+            .chain(
+                LANGUAGES
+                    .iter()
+                    .filter(|language| {
+                        language.name.to_lowercase().contains(&query)
+                            || language.extensions.iter().any(|ext| ext.starts_with(query.as_str()))
+                    })
+                    .map(Some),
+            )
+            .collect()
+    }
+
+    pub fn get_selected(&self) -> Option<Option<&'static Language>> {
+        self.matches().get(self.selected).copied()
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                format!("> {}_", self.query),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            )),
+            Line::from(""),
+        ];
+
+        // Keep the selection inside the visible window
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let matches = self.matches();
+        let first = self.selected.saturating_sub(visible_rows.saturating_sub(1));
+
+        for (i, language) in matches.iter().enumerate().skip(first).take(visible_rows) {
+            let style = if i == self.selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            let text = match language {
+                Some(language) => format!(" {}  {}  ({}) ", language.icon, language.name, language.extensions.join(", ")),
+                None => format!(" 🔍  Auto-detect ({}) ", self.detected),
+            };
+            lines.push(Line::from(Span::styled(text, style)));
+        }
+
+        if matches.is_empty() {
+            lines.push(Line::from(Span::styled(" No matching languages", Style::default().fg(Color::DarkGray))));
+        }
+
+        let picker = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 🏷️ Language of This File (Enter: set, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(picker, area);
+    }
+}
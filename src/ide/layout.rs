@@ -1,9 +1,13 @@
-use crate::ide::app::{IdeApp, FocusedPanel};
+use crate::config::Config;
+use crate::i18n::Localizer;
+use crate::ide::api_settings::SettingsRow;
+use crate::ide::app::{IdeApp, FocusedPanel, PendingClose, TabMenuItem};
+use crate::ide::{language, long_lines};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect, Alignment},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Clear, Paragraph},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
     Frame,
 };
 
@@ -12,17 +16,170 @@ pub fn draw_ide(frame: &mut Frame, app: &mut IdeApp) {
 
     // Check for overlays first
     if app.show_command_help {
-        draw_command_help_overlay(frame, size);
+        draw_command_help_overlay(frame, size, &app.i18n);
         return;
     }
 
     if app.show_api_config {
-        draw_api_config_overlay(frame, size);
+        draw_api_config_overlay(frame, size, &app.config, app.api_config_row);
         return;
     }
 
     if app.show_help {
-        draw_help_overlay(frame, size);
+        draw_help_overlay(frame, size, &app.i18n);
+        return;
+    }
+
+    if app.show_session_lock_dialog {
+        draw_main_ide_layout(frame, app, size);
+        draw_session_lock_dialog(frame, app, size);
+        return;
+    }
+
+    if app.show_recovery_dialog {
+        draw_main_ide_layout(frame, app, size);
+        draw_recovery_dialog(frame, app, size);
+        return;
+    }
+
+    if app.show_external_change_dialog {
+        draw_main_ide_layout(frame, app, size);
+        draw_external_change_dialog(frame, app, size);
+        return;
+    }
+
+    if app.show_long_line_dialog {
+        draw_main_ide_layout(frame, app, size);
+        draw_long_line_dialog(frame, app, size);
+        return;
+    }
+
+    if app.spend_pause.is_some() {
+        draw_main_ide_layout(frame, app, size);
+        draw_spend_pause_dialog(frame, app, size);
+        return;
+    }
+
+    if app.show_unsaved_dialog {
+        draw_main_ide_layout(frame, app, size);
+        draw_unsaved_dialog(frame, app, size);
+        return;
+    }
+
+    if app.show_workflow_picker {
+        draw_main_ide_layout(frame, app, size);
+        draw_workflow_picker(frame, app, size);
+        return;
+    }
+
+    if app.diff_view.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.diff_view.draw(frame, centered_rect(90, 85, size));
+        return;
+    }
+
+    if app.plan_editor.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.plan_editor.draw(frame, centered_rect(70, 60, size));
+        return;
+    }
+
+    if app.approval.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.approval.draw(frame, centered_rect(90, 80, size));
+        return;
+    }
+
+    if app.mark_picker.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.mark_picker.draw(frame, centered_rect(60, 50, size));
+        return;
+    }
+
+    if app.notification_history.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.notification_history.draw(frame, centered_rect(80, 70, size), &app.notification_archive);
+        return;
+    }
+
+    if app.tasks_view.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.tasks_view.draw(frame, centered_rect(70, 60, size), app.tasks.tasks(), &app.config.agent_loop);
+        return;
+    }
+
+    if app.keymap_view.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.keymap_view.draw(frame, centered_rect(60, 70, size), &app.keymap);
+        return;
+    }
+
+    if app.session_browser.is_open {
+        draw_main_ide_layout(frame, app, size);
+        let current = app.saved_session.id.clone();
+        app.session_browser.draw(frame, centered_rect(80, 60, size), &current);
+        return;
+    }
+
+    if app.branch_view.is_open {
+        draw_main_ide_layout(frame, app, size);
+        let current = app.saved_session.id.clone();
+        app.branch_view.draw(frame, centered_rect(80, 60, size), &current);
+        return;
+    }
+
+    if app.prompt_search.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.prompt_search.draw(frame, centered_rect(80, 60, size), &app.prompt_history);
+        return;
+    }
+
+    if app.settings_panel.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.settings_panel.draw(frame, centered_rect(70, 80, size), &app.config);
+        return;
+    }
+
+    if app.notes_view.is_open {
+        draw_main_ide_layout(frame, app, size);
+        let notes = app.agent_notes.as_ref().map(|notes| notes.read()).unwrap_or_default();
+        app.notes_view.draw(frame, centered_rect(70, 70, size), &notes);
+        return;
+    }
+
+    if app.ref_picker.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.ref_picker.draw(frame, centered_rect(60, 50, size));
+        return;
+    }
+
+    if let Some(menu) = app.tab_menu {
+        draw_main_ide_layout(frame, app, size);
+        app.layout.tab_menu_area = draw_tab_menu(frame, menu.x, menu.y, menu.selected, size);
+        return;
+    }
+
+    if app.model_picker.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.model_picker.draw(frame, centered_rect(50, 60, size));
+        return;
+    }
+
+    if app.language_picker.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.language_picker.draw(frame, centered_rect(50, 60, size));
+        return;
+    }
+
+    if app.emoji_picker.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.emoji_picker.draw(frame, centered_rect(50, 60, size));
+        return;
+    }
+
+    if app.command_palette.is_open {
+        draw_main_ide_layout(frame, app, size);
+        app.command_palette.draw(frame, centered_rect(60, 50, size));
         return;
     }
 
@@ -76,7 +233,9 @@ fn draw_sidebar(frame: &mut Frame, app: &mut IdeApp, area: Rect) {
         app.sidebar.chat.draw(
             frame, 
             sidebar_chunks[4], 
-            app.focused_panel == FocusedPanel::Chat
+            app.focused_panel == FocusedPanel::Chat,
+            &app.threads.names(&app.saved_session),
+            app.threads.active()
         );
 
         // Update component areas for mouse coordinate mapping (with notifications)
@@ -111,7 +270,9 @@ fn draw_sidebar(frame: &mut Frame, app: &mut IdeApp, area: Rect) {
         app.sidebar.chat.draw(
             frame, 
             sidebar_chunks[2], 
-            app.focused_panel == FocusedPanel::Chat
+            app.focused_panel == FocusedPanel::Chat,
+            &app.threads.names(&app.saved_session),
+            app.threads.active()
         );
 
         // Update component areas for mouse coordinate mapping (without notifications)
@@ -142,12 +303,12 @@ fn draw_main_area(frame: &mut Frame, app: &mut IdeApp, area: Rect) {
     
     // Draw status bar
     let status_info = app.get_status_info();
-    app.statusbar.draw(frame, main_chunks[1], &status_info);
+    app.layout.language_label_area = app.statusbar.draw(frame, main_chunks[1], &status_info, &app.i18n);
 }
 
 fn draw_editor_area(frame: &mut Frame, app: &mut IdeApp, area: Rect) {
     // Editor now handles tabs internally, so just give it the full area
-    app.editor.draw(
+    app.layout.inactive_pane_area = app.editor.draw(
         frame, 
         area, 
         app.focused_panel == FocusedPanel::Editor,
@@ -156,51 +317,72 @@ fn draw_editor_area(frame: &mut Frame, app: &mut IdeApp, area: Rect) {
 }
 
 
-fn draw_command_help_overlay(frame: &mut Frame, area: Rect) {
+fn draw_command_help_overlay(frame: &mut Frame, area: Rect, i18n: &Localizer) {
     // Clear the background
     frame.render_widget(Clear, area);
 
+    let heading = |id: &str| Line::from(Span::styled(i18n.t(id), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+    let entry = |key: &str, id: &str| Line::from(format!("  {:<11} - {}", key, i18n.t(id)));
+
     let help_text = vec![
-        Line::from(Span::styled("⌨️  Command Reference - Ctrl+H", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(i18n.t("commands-title"), Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
         Line::from(""),
-        Line::from(Span::styled("🔧 File Operations:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  Ctrl+N      - New file"),
-        Line::from("  Ctrl+S      - Save file"),
-        Line::from("  Ctrl+W      - Close file"),
-        Line::from("  Ctrl+O      - Focus file explorer"),
-        Line::from("  Ctrl+D      - New folder"),
-        Line::from("  F2          - Rename (selected file)"),
-        Line::from("  Delete      - Delete (selected file)"),
+        heading("commands-section-files"),
+        entry("Ctrl+N", "commands-new-file"),
+        entry("Ctrl+S", "commands-save-file"),
+        entry("Ctrl+W", "commands-close-file"),
+        entry("Ctrl+O", "commands-focus-explorer"),
+        entry("Ctrl+D", "commands-new-folder"),
+        entry("F2", "commands-rename"),
+        entry("Delete", "commands-delete"),
         Line::from(""),
-        Line::from(Span::styled("📝 Editor:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  i           - Insert mode"),
-        Line::from("  Esc         - Normal mode"),
-        Line::from("  h/j/k/l     - Move cursor (normal mode)"),
-        Line::from("  ↑/↓/←/→     - Move cursor"),
+        heading("commands-section-editor"),
+        entry("i", "commands-insert-mode"),
+        entry("Esc", "commands-normal-mode"),
+        entry("h/j/k/l", "commands-move-normal"),
+        entry("w/b/e", "commands-word-motions"),
+        entry("0/$", "commands-line-motions"),
+        entry("gg/G", "commands-buffer-motions"),
+        entry("%", "commands-matching-bracket"),
+        entry("m{a-z} / '{a-z}", "commands-marks"),
+        entry("za / zR", "commands-folds"),
+        entry(">> / <<", "commands-indent"),
+        entry("Alt+↑/↓", "commands-move-line"),
+        entry("Ctrl+Shift+D / J", "commands-duplicate-join"),
+        entry("5j / 3w", "commands-count"),
+        entry("Ctrl+/", "commands-toggle-comment"),
+        entry("↑/↓/←/→", "commands-move"),
         Line::from(""),
-        Line::from(Span::styled("💬 AI Chat:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  Ctrl+Enter  - Send message"),
-        Line::from("  Ctrl+I      - Send with image"),
-        Line::from("  Ctrl+L      - Clear chat"),
-        Line::from("  Ctrl+K      - Clear notifications"),
+        heading("commands-section-chat"),
+        entry("Ctrl+Enter", "commands-send"),
+        entry("Shift+Enter", "commands-soft-newline"),
+        entry("Ctrl+I", "commands-send-image"),
+        entry("Ctrl+Shift+Enter", "commands-send-file"),
+        entry("Ctrl+L", "commands-clear-chat"),
+        entry("Ctrl+Y", "commands-copy-answer"),
+        entry("Ctrl+K", "commands-clear-notifications"),
         Line::from(""),
-        Line::from(Span::styled("🔄 Navigation:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  Tab         - Cycle panels"),
-        Line::from("  Alt+1/2/3   - Direct panel access"),
-        Line::from("  Space       - Toggle folder (file explorer)"),
+        heading("commands-section-navigation"),
+        entry("Tab", "commands-cycle-panels"),
+        entry("Alt+1/2/3", "commands-panel-access"),
+        entry("Ctrl+\\", "commands-split"),
+        entry("Space", "commands-toggle-folder"),
         Line::from(""),
-        Line::from(Span::styled("⚙️  System:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  Ctrl+A      - Toggle agentic mode"),
-        Line::from("  Ctrl+,      - API configuration"),
-        Line::from("  Ctrl+Q      - Quit"),
-        Line::from("  F1 / ?      - General help"),
+        heading("commands-section-system"),
+        entry("Ctrl+P", "commands-palette"),
+        entry("Ctrl+E", "commands-emoji"),
+        entry("Ctrl+A", "commands-agentic"),
+        entry("Ctrl+,", "commands-api-config"),
+        entry("Ctrl+Z", "commands-suspend"),
+        entry("Ctrl+Q", "commands-quit"),
+        entry("F1 / ?", "commands-general-help"),
         Line::from(""),
-        Line::from(Span::styled("Press Ctrl+H to close this help", Style::default().fg(Color::Gray))),
+        Line::from(Span::styled(i18n.t("commands-close"), Style::default().fg(Color::Gray))),
     ];
 
     let help_paragraph = Paragraph::new(help_text)
         .block(Block::default()
-            .title(" ⌨️  Commands ")
+            .title(format!(" {} ", i18n.t("commands-block-title")))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Cyan)))
         .alignment(Alignment::Left);
@@ -209,37 +391,43 @@ fn draw_command_help_overlay(frame: &mut Frame, area: Rect) {
     frame.render_widget(help_paragraph, help_area);
 }
 
-fn draw_api_config_overlay(frame: &mut Frame, area: Rect) {
+fn draw_api_config_overlay(frame: &mut Frame, area: Rect, config: &Config, selected: SettingsRow) {
     // Clear the background
     frame.render_widget(Clear, area);
 
-    let config_text = vec![
+    let mut config_text = vec![
         Line::from(Span::styled("⚙️  AI API Configuration", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
         Line::from(""),
-        Line::from(Span::styled("🔑 Current Configuration:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  API Provider: Groq"),
-        Line::from("  Model: llama-3.1-70b-versatile"),
-        Line::from("  Status: ✅ Connected"),
+        Line::from(format!("  API Provider: {}", config.provider.label())),
         Line::from(""),
-        Line::from(Span::styled("🔧 Available Models:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  • llama-3.1-70b-versatile (Current)"),
-        Line::from("  • llama-3.1-8b-instant"),
-        Line::from("  • mixtral-8x7b-32768"),
-        Line::from("  • gemma-7b-it"),
-        Line::from("  • gemma-9b-it"),
-        Line::from(""),
-        Line::from(Span::styled("⚡ Commands:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  Use terminal to configure:"),
-        Line::from("  ./agent config --groq-key YOUR_KEY"),
-        Line::from("  ./agent config --model MODEL_NAME"),
+    ];
+    for row in SettingsRow::ALL {
+        let value = match row {
+            SettingsRow::Model => format!("{}  (Enter: pick)", config.get_model()),
+            SettingsRow::Persona => format!("◀ {} ▶", config.persona.as_deref().unwrap_or("none")),
+            SettingsRow::Temperature => format!("◀ {:.1} ▶", config.get_temperature()),
+            SettingsRow::MaxTokens => match config.get_max_tokens() {
+                Some(max_tokens) => format!("◀ {} ▶", max_tokens),
+                None => "◀ network profile default ▶".to_string(),
+            },
+            SettingsRow::DryRun if config.dry_run => "ON (agent actions only report what they would change)".to_string(),
+            SettingsRow::DryRun => "OFF".to_string(),
+        };
+        let style = if row == selected {
+            Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        config_text.push(Line::from(Span::styled(format!("  {:<12} {}", row.label(), value), style)));
+    }
+    config_text.extend([
         Line::from(""),
-        Line::from(Span::styled("💡 Tips:", Style::default().fg(Color::Green))),
-        Line::from("  • 70b model: Best for coding tasks"),
-        Line::from("  • 8b model: Faster responses"),
-        Line::from("  • Mixtral: Great for complex reasoning"),
+        Line::from(Span::styled("⚡ From the terminal:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from("  ./agent config --provider groq|openai|anthropic|ollama"),
+        Line::from("  ./agent config --api-key YOUR_KEY"),
         Line::from(""),
-        Line::from(Span::styled("Press Ctrl+, to close", Style::default().fg(Color::Gray))),
-    ];
+        Line::from(Span::styled("↑/↓ select, ←/→ adjust, digits type max tokens; changes are saved. Esc or Ctrl+, to close", Style::default().fg(Color::Gray))),
+    ]);
 
     let config_paragraph = Paragraph::new(config_text)
         .block(Block::default()
@@ -252,44 +440,47 @@ fn draw_api_config_overlay(frame: &mut Frame, area: Rect) {
     frame.render_widget(config_paragraph, config_area);
 }
 
-fn draw_help_overlay(frame: &mut Frame, area: Rect) {
+fn draw_help_overlay(frame: &mut Frame, area: Rect, i18n: &Localizer) {
     // Clear the background
     frame.render_widget(Clear, area);
 
+    let heading = |id: &str, color: Color| Line::from(Span::styled(i18n.t(id), Style::default().fg(color).add_modifier(Modifier::BOLD)));
+    let item = |id: &str| Line::from(format!("  {}", i18n.t(id)));
+
     let help_text = vec![
-        Line::from(Span::styled("🦀 Rust Coding Agent - IDE Help", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+        heading("help-title", Color::Cyan),
         Line::from(""),
-        Line::from(Span::styled("🎯 Getting Started:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  1. Use Alt+1 to focus file explorer"),
-        Line::from("  2. Navigate with ↑/↓ or j/k keys"),
-        Line::from("  3. Press Enter to open files"),
-        Line::from("  4. Use 'i' in editor for insert mode"),
-        Line::from("  5. Chat with AI using Alt+3"),
+        heading("help-section-start", Color::Yellow),
+        item("help-start-1"),
+        item("help-start-2"),
+        item("help-start-3"),
+        item("help-start-4"),
+        item("help-start-5"),
         Line::from(""),
-        Line::from(Span::styled("🔧 Main Features:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  • Multi-tab file editing"),
-        Line::from("  • Integrated AI chat with image support"),
-        Line::from("  • Vim-like keyboard navigation"),
-        Line::from("  • Resizable panels"),
-        Line::from("  • Agentic mode for file operations"),
+        heading("help-section-features", Color::Yellow),
+        item("help-feature-tabs"),
+        item("help-feature-chat"),
+        item("help-feature-vim"),
+        item("help-feature-panels"),
+        item("help-feature-agentic"),
         Line::from(""),
-        Line::from(Span::styled("🎮 Interface:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
-        Line::from("  Left: File explorer + AI chat"),
-        Line::from("  Right: Code editor with tabs"),
-        Line::from("  Bottom: Status bar with file info"),
+        heading("help-section-interface", Color::Yellow),
+        item("help-interface-left"),
+        item("help-interface-right"),
+        item("help-interface-bottom"),
         Line::from(""),
-        Line::from(Span::styled("💡 Pro Tips:", Style::default().fg(Color::Green))),
-        Line::from("  • Use Ctrl+H for detailed commands"),
-        Line::from("  • Mouse support for clicking"),
-        Line::from("  • Ctrl+A enables AI file operations"),
-        Line::from("  • Ctrl+←→ to resize sidebar"),
+        Line::from(Span::styled(i18n.t("help-section-tips"), Style::default().fg(Color::Green))),
+        item("help-tip-commands"),
+        item("help-tip-mouse"),
+        item("help-tip-agentic"),
+        item("help-tip-resize"),
         Line::from(""),
-        Line::from(Span::styled("Press F1 or ? to close help", Style::default().fg(Color::Gray))),
+        Line::from(Span::styled(i18n.t("help-close"), Style::default().fg(Color::Gray))),
     ];
 
     let help_paragraph = Paragraph::new(help_text)
         .block(Block::default()
-            .title(" ❓ Help ")
+            .title(format!(" {} ", i18n.t("help-block-title")))
             .borders(Borders::ALL)
             .border_style(Style::default().fg(Color::Cyan)))
         .alignment(Alignment::Left);
@@ -318,97 +509,6 @@ pub fn get_file_icon(filename: &str) -> &'static str {
     }
 }
 
-pub fn get_tab_click_info(app: &crate::ide::app::IdeApp, x: u16, y: u16, area: Rect) -> Option<(usize, bool)> {
-    let tabs = app.editor.get_tab_info();
-    if tabs.is_empty() {
-        return None;
-    }
-
-    // Tabs are now inside the editor border, so adjust for the border
-    let tab_area_y = area.y + 1; // +1 for top border
-    let tab_area_x = area.x + 1; // +1 for left border
-    let tab_area_width = area.width.saturating_sub(2); // -2 for left and right borders
-    
-    // Debug the tab area calculation in layout function
-    // Note: Can't add notifications from here, but this helps us understand the calculation
-    
-    // Allow clicks within the tab area (which is now inside the editor border)
-    if y != tab_area_y || x < tab_area_x || x >= tab_area_x + tab_area_width {
-        return None;
-    }
-
-    let mouse_x = x;
-    let mouse_y = y;
-    let is_hovering_tabs = mouse_y == tab_area_y && mouse_x >= tab_area_x && mouse_x < tab_area_x + tab_area_width;
-
-    // Use the same logic as draw_tabs to calculate positions
-    let mut tab_spans_lengths = Vec::new();
-    
-    for (i, tab) in tabs.iter().enumerate() {
-        let is_modified = tab.is_modified;
-
-        // Calculate tab position - tabs start at the inner area (inside border)
-        let tab_start_x = tab_area_x + tab_spans_lengths.iter().sum::<u16>();
-        
-        // Calculate the actual tab content to get precise width (same as in draw_tabs)
-        let modified_indicator = if is_modified { "●" } else { "" };
-        let base_tab_text = format!(" {} {}{} ",
-            get_file_icon(&tab.file_name),
-            tab.file_name,
-            modified_indicator
-        );
-        let base_tab_width = base_tab_text.len() as u16;
-        let base_tab_end_x = tab_start_x + base_tab_width;
-        
-        // Check if mouse is hovering over this specific tab (including close button area)
-        let is_hovering_this_tab = is_hovering_tabs && mouse_x >= tab_start_x && mouse_x < base_tab_end_x + 3; // +3 for close button
-        let show_close_button = is_hovering_this_tab;
-
-        // Calculate complete tab content with close button
-        let close_button = if show_close_button { " ✕" } else { "" };
-        let tab_text = format!(" {} {}{}{} ",
-            get_file_icon(&tab.file_name),
-            tab.file_name,
-            modified_indicator,
-            close_button
-        );
-
-        let tab_width = tab_text.len() as u16;
-        let tab_end_x = tab_start_x + tab_width;
-
-        if x >= tab_start_x && x < tab_end_x {
-            // Check if click is on close button (only if it's visible)
-            if show_close_button {
-                let close_button_start = base_tab_end_x; // Close button starts after base content
-                let close_button_end = close_button_start + 3; // " ✕ " is 3 characters
-                let is_close_button = x >= close_button_start && x < close_button_end;
-                
-                // Debug info is now handled through notifications in the calling code
-                
-                return Some((i, is_close_button));
-            } else {
-                return Some((i, false)); // No close button visible, so not a close click
-            }
-        }
-
-        // Add this tab's width to the running total (like the spans in draw_tabs)
-        tab_spans_lengths.push(tab_width);
-        if i < tabs.len() - 1 {
-            tab_spans_lengths.push(1); // +1 for separator "│"
-        }
-    }
-
-    // Check for new tab button
-    let new_tab_text = " + ";
-    let new_tab_start = area.x + tab_spans_lengths.iter().sum::<u16>();
-    let new_tab_end = new_tab_start + new_tab_text.len() as u16;
-    if x >= new_tab_start && x < new_tab_end {
-        return Some((usize::MAX, false)); // Special value for new tab
-    }
-
-    None
-}
-
 fn draw_horizontal_separator(frame: &mut Frame, area: Rect, separator_char: &str, color: Color) {
     let separator_text = separator_char.repeat(area.width as usize);
     let separator = Paragraph::new(separator_text)
@@ -464,6 +564,8 @@ fn draw_dialog_overlay(frame: &mut Frame, app: &IdeApp, area: Rect) {
         ("📁 Create New Folder", "Enter folder name:", &app.dialog_input)
     } else if app.show_rename_dialog {
         ("✏️ Rename", "Enter new name:", &app.dialog_input)
+    } else if app.show_save_workflow_dialog {
+        ("💾 Save Workflow", "Workflow name (saves system prompt, open files, model, autonomy and prompt):", &app.dialog_input)
     } else {
         return;
     };
@@ -491,4 +593,265 @@ fn draw_dialog_overlay(frame: &mut Frame, app: &IdeApp, area: Rect) {
     // Center the dialog
     let dialog_area = centered_rect(50, 25, area);
     frame.render_widget(dialog, dialog_area);
-}
\ No newline at end of file
+}
+fn draw_session_lock_dialog(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(60, 35, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let holder = match &app.lock_holder {
+        Some(holder) => format!("PID {}, running since {}", holder.pid, holder.started_at.format("%Y-%m-%d %H:%M")),
+        None => "another process".to_string(),
+    };
+    let dialog_text = vec![
+        Line::from(Span::styled("🔐 This project is already open in another instance", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(format!("  {}", holder)),
+        Line::from(""),
+        Line::from("Editing from both would overwrite each other's saves and agent changes."),
+        Line::from(""),
+        Line::from(Span::styled(
+            "r/Enter: attach read-only   t: take over (the other becomes read-only)   q: quit",
+            Style::default().fg(Color::Gray)
+        )),
+    ];
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: false })
+        .block(Block::default()
+            .title(" 🔐 Project In Use ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
+
+fn draw_recovery_dialog(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(60, 40, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let mut dialog_text = vec![
+        Line::from(Span::styled("♻️ Unsaved changes from a previous session were found", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+
+    for swap in &app.pending_recoveries {
+        let location = swap.original_path
+            .as_ref()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| swap.file_name.clone());
+        dialog_text.push(Line::from(format!(
+            "  • {} ({})",
+            location,
+            swap.saved_at.format("%Y-%m-%d %H:%M")
+        )));
+    }
+
+    dialog_text.push(Line::from(""));
+    dialog_text.push(Line::from(Span::styled(
+        "y/Enter: recover into new tabs   n: discard   Esc: decide later",
+        Style::default().fg(Color::Gray)
+    )));
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .block(Block::default()
+            .title(" ♻️ Recover Files ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
+
+fn draw_unsaved_dialog(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(50, 30, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let title = match app.pending_close {
+        Some(PendingClose::Quit) => " ⚠️ Quit with Unsaved Changes ",
+        _ => " ⚠️ Close Unsaved Tab ",
+    };
+
+    let mut dialog_text = vec![
+        Line::from(Span::styled("These buffers have unsaved changes:", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+    for name in app.unsaved_file_names() {
+        dialog_text.push(Line::from(format!("  • {}", name)));
+    }
+    dialog_text.push(Line::from(""));
+    dialog_text.push(Line::from(Span::styled(
+        "s/Enter: Save   d: Discard   c/Esc: Cancel",
+        Style::default().fg(Color::Gray)
+    )));
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .block(Block::default()
+            .title(title)
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
+
+fn draw_external_change_dialog(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(50, 25, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let file_name = app.external_change_tab
+        .and_then(|id| app.editor.tabs.iter().find(|tab| tab.id == id))
+        .map(|tab| tab.file_name.clone())
+        .unwrap_or_default();
+
+    let dialog_text = vec![
+        Line::from(Span::styled(format!("'{}' was changed on disk", file_name), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from("Your buffer has unsaved edits."),
+        Line::from(""),
+        Line::from(Span::styled(
+            "r: Reload   k: Keep mine   d: Diff   Esc: Decide later",
+            Style::default().fg(Color::Gray)
+        )),
+    ];
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .block(Block::default()
+            .title(" 🔄 File Changed on Disk ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
+
+/// Small menu just below the right-clicked tab; returns the rows of its items
+fn draw_tab_menu(frame: &mut Frame, x: u16, y: u16, selected: usize, area: Rect) -> Rect {
+    let width = TabMenuItem::ALL.iter().map(|item| item.label().len() as u16).max().unwrap_or(0) + 4;
+    let height = TabMenuItem::ALL.len() as u16 + 2;
+    // Keep the whole menu on screen
+    let menu_area = Rect::new(
+        x.min(area.width.saturating_sub(width)),
+        y.min(area.height.saturating_sub(height)),
+        width.min(area.width),
+        height.min(area.height),
+    );
+    frame.render_widget(Clear, menu_area);
+
+    let lines: Vec<Line> = TabMenuItem::ALL
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let style = if index == selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            Line::from(Span::styled(format!(" {:<width$} ", item.label(), width = width as usize - 4), style))
+        })
+        .collect();
+
+    let menu = Paragraph::new(lines)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+    frame.render_widget(menu, menu_area);
+
+    Rect::new(menu_area.x + 1, menu_area.y + 1, menu_area.width.saturating_sub(2), menu_area.height.saturating_sub(2))
+}
+
+fn draw_long_line_dialog(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(50, 25, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let tab = app.long_line_tab.and_then(|id| app.editor.tabs.iter().find(|tab| tab.id == id));
+    let file_name = tab.map(|tab| tab.file_name.clone()).unwrap_or_default();
+    let longest = tab.and_then(|tab| tab.long_line).unwrap_or(0);
+    let can_pretty_print = tab.is_some_and(|tab| {
+        let key = language::settings_key(&tab.settings_name());
+        tab.file_path.as_deref().is_some_and(|path| long_lines::can_pretty_print(&key, path, &app.config.save_hooks))
+    });
+
+    let choices = if can_pretty_print {
+        "p: Pretty-print   r: Read-only view   e: Edit anyway"
+    } else {
+        "r: Read-only view   e: Edit anyway"
+    };
+    let dialog_text = vec![
+        Line::from(Span::styled(format!("'{}' looks minified", file_name), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(format!("Its longest line is {}.", long_lines::describe_len(longest))),
+        Line::from(""),
+        Line::from(Span::styled(choices, Style::default().fg(Color::Gray))),
+    ];
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .block(Block::default()
+            .title(" 📏 Very Long Lines ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
+
+fn draw_spend_pause_dialog(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(50, 25, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let reason = app.spend_pause.as_deref().unwrap_or_default();
+    let dialog_text = vec![
+        Line::from(Span::styled("The agent is paused", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))),
+        Line::from(format!("It has reached {}.", reason)),
+        Line::from(""),
+        Line::from(Span::styled("c: Continue   s: Stop", Style::default().fg(Color::Gray))),
+    ];
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .wrap(Wrap { trim: true })
+        .block(Block::default()
+            .title(" 💸 Spend Limit ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
+
+fn draw_workflow_picker(frame: &mut Frame, app: &IdeApp, area: Rect) {
+    let dialog_area = centered_rect(50, 40, area);
+    frame.render_widget(Clear, dialog_area);
+
+    let mut dialog_text = vec![
+        Line::from(Span::styled("Start a conversation from a template:", Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))),
+        Line::from(""),
+    ];
+    for (index, template) in app.config.workflows.iter().enumerate() {
+        let style = if index == app.workflow_selected {
+            Style::default().fg(Color::Black).bg(Color::Cyan)
+        } else {
+            Style::default()
+        };
+        let details = format!(
+            "  {} pinned · {}",
+            template.pinned_files.len(),
+            template.model.as_deref().unwrap_or("default model")
+        );
+        dialog_text.push(Line::from(vec![
+            Span::styled(format!(" {} ", template.name), style),
+            Span::styled(details, Style::default().fg(Color::Gray)),
+        ]));
+    }
+    dialog_text.push(Line::from(""));
+    dialog_text.push(Line::from(Span::styled(
+        "↑/↓: Select   Enter: Start   Esc: Cancel",
+        Style::default().fg(Color::Gray)
+    )));
+
+    let dialog = Paragraph::new(dialog_text)
+        .alignment(Alignment::Left)
+        .block(Block::default()
+            .title(" 📋 Workflows ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow)));
+
+    frame.render_widget(dialog, dialog_area);
+}
@@ -0,0 +1,123 @@
+use crate::ide::save_hooks::{self, SaveHooks};
+use anyhow::{bail, Result};
+use std::path::Path;
+
+// Lines longer than this (minified JS, JSON dumps) get the long-line prompt on open
+pub const LONG_LINE_BYTES: usize = 10_000;
+
+/// Length of the longest line, if it is long enough to need special handling
+pub fn longest_line<'a>(lines: impl IntoIterator<Item = &'a String>) -> Option<usize> {
+    lines.into_iter().map(String::len).max().filter(|len| *len > LONG_LINE_BYTES)
+}
+
+/// Human-readable size of a line, e.g. "2.1 MB"
+pub fn describe_len(bytes: usize) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b => format!("{} KB", b / 1024),
+    }
+}
+
+/// Whether `pretty_print` has a way to reformat a file with this settings key (see `language::settings_key`)
+pub fn can_pretty_print(key: &str, path: &Path, hooks: &SaveHooks) -> bool {
+    is_json(key) || hooks.formatter_for_key(key, path).is_some()
+}
+
+/// Reformat a file: JSON is reindented in place, anything else goes through its save-hook formatter
+pub fn pretty_print(text: &str, key: &str, path: &Path, hooks: &SaveHooks, indent: &str) -> Result<String> {
+    if is_json(key) {
+        return Ok(pretty_json(text, indent));
+    }
+    match hooks.formatter_for_key(key, path) {
+        Some(command) => save_hooks::run_formatter(&command, text),
+        None => bail!(
+            "no pretty-printer for this file type - add a formatter for it under `save_hooks.formatters`"
+        ),
+    }
+}
+
+fn is_json(key: &str) -> bool {
+    matches!(key, "json" | "geojson" | "map")
+}
+
+/// One value per line with `indent` per nesting level. Strings and key order are kept as-is,
+/// so this also works on JSON that doesn't parse
+pub fn pretty_json(text: &str, indent: &str) -> String {
+    let mut output = String::with_capacity(text.len() * 2);
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut chars = text.chars().peekable();
+
+    let newline = |output: &mut String, depth: usize| {
+        output.push('\n');
+        output.push_str(&indent.repeat(depth));
+    };
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            output.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                output.push(c);
+            }
+            '{' | '[' => {
+                output.push(c);
+                // Keep empty objects and arrays on one line
+                while chars.peek().is_some_and(|next| next.is_whitespace()) {
+                    chars.next();
+                }
+                if chars.peek().is_some_and(|next| matches!(next, '}' | ']')) {
+                    output.push(chars.next().unwrap_or(c));
+                } else {
+                    depth += 1;
+                    newline(&mut output, depth);
+                }
+            }
+            '}' | ']' => {
+                depth = depth.saturating_sub(1);
+                newline(&mut output, depth);
+                output.push(c);
+            }
+            ',' => {
+                output.push(c);
+                newline(&mut output, depth);
+            }
+            ':' => output.push_str(": "),
+            c if c.is_whitespace() => {}
+            c => output.push(c),
+        }
+    }
+    output.push('\n');
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_lines() {
+        let short = vec!["a".repeat(80)];
+        assert_eq!(longest_line(&short), None);
+        let long = vec!["b".repeat(LONG_LINE_BYTES + 1), String::new()];
+        assert_eq!(longest_line(&long), Some(LONG_LINE_BYTES + 1));
+        assert_eq!(describe_len(3 * 1024 * 1024 / 2), "1.5 MB");
+
+        let minified = r#"{"b":[1,2,{}],"a":"x, {y}: \"z\"","c":[ ]}"#;
+        assert_eq!(
+            pretty_json(minified, "  "),
+            "{\n  \"b\": [\n    1,\n    2,\n    {}\n  ],\n  \"a\": \"x, {y}: \\\"z\\\"\",\n  \"c\": []\n}\n"
+        );
+        assert!(pretty_print("x", "js", Path::new("app.min.js"), &SaveHooks::default(), "  ").is_err());
+    }
+}
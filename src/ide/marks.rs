@@ -0,0 +1,189 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// A named cursor position in a file (0-based line and column)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mark {
+    pub path: PathBuf,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Vim-style marks `a`-`z`, shared by all files and saved with the workspace session
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Marks {
+    marks: BTreeMap<char, Mark>,
+}
+
+impl Marks {
+    pub fn is_valid_name(name: char) -> bool {
+        name.is_ascii_lowercase()
+    }
+
+    pub fn set(&mut self, name: char, mark: Mark) -> bool {
+        if !Self::is_valid_name(name) {
+            return false;
+        }
+        self.marks.insert(name, mark);
+        true
+    }
+
+    pub fn get(&self, name: char) -> Option<&Mark> {
+        self.marks.get(&name)
+    }
+
+    pub fn remove(&mut self, name: char) {
+        self.marks.remove(&name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (char, &Mark)> {
+        self.marks.iter().map(|(name, mark)| (*name, mark))
+    }
+
+    /// Move the marks of `path` below line `after` by `delta` lines, after lines were inserted or
+    /// removed there; marks on removed lines land on `after`. Returns whether any moved
+    pub fn shift_lines(&mut self, path: &Path, after: usize, delta: isize) -> bool {
+        let mut moved = false;
+        for mark in self.marks.values_mut().filter(|mark| mark.path == path && mark.line > after) {
+            mark.line = mark.line.saturating_add_signed(delta).max(after);
+            moved = true;
+        }
+        moved
+    }
+
+    /// Keep marks pointing at a file after it is renamed or moved
+    pub fn rename_path(&mut self, from: &Path, to: &Path) {
+        for mark in self.marks.values_mut() {
+            if let Ok(rest) = mark.path.strip_prefix(from) {
+                mark.path = to.join(rest);
+            }
+        }
+    }
+}
+
+/// Row shown in the bookmark picker
+pub struct MarkEntry {
+    pub name: char,
+    pub location: String, // Path relative to the project, with line and column
+    pub preview: String,  // Text of the marked line
+}
+
+/// Overlay listing every mark; Enter jumps, d deletes
+pub struct MarkPicker {
+    pub is_open: bool,
+    pub selected: usize,
+    entries: Vec<MarkEntry>,
+}
+
+impl Default for MarkPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MarkPicker {
+    pub fn new() -> Self {
+        Self { is_open: false, selected: 0, entries: Vec::new() }
+    }
+
+    pub fn open(&mut self, entries: Vec<MarkEntry>) {
+        self.entries = entries;
+        self.selected = 0;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.entries.clear();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn get_selected(&self) -> Option<char> {
+        self.entries.get(self.selected).map(|entry| entry.name)
+    }
+
+    /// Drop the selected row after its mark was deleted
+    pub fn remove_selected(&mut self) {
+        if self.selected < self.entries.len() {
+            self.entries.remove(self.selected);
+            self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let selected_style = Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let style = if index == self.selected { selected_style } else { Style::default().fg(Color::Gray) };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {}  {} ", entry.name, entry.location), style),
+                Span::styled(format!(" {}", entry.preview.trim()), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                " No bookmarks - press m and a letter in the editor to set one",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+
+        let picker = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 🔖 Bookmarks (Enter: jump, d: delete, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(picker, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_marks() {
+        let mut marks = Marks::default();
+        let at = |path: &str, line| Mark { path: PathBuf::from(path), line, col: 0 };
+
+        assert!(marks.set('b', at("/p/src/lib.rs", 4)));
+        assert!(marks.set('a', at("/p/src/main.rs", 10)));
+        assert!(!marks.set('A', at("/p/x.rs", 0)));
+        assert!(marks.set('a', at("/p/src/main.rs", 12))); // Setting again moves the mark
+        assert_eq!(marks.iter().map(|(name, mark)| (name, mark.line)).collect::<Vec<_>>(), vec![('a', 12), ('b', 4)]);
+
+        marks.rename_path(Path::new("/p/src"), Path::new("/p/core"));
+        assert_eq!(marks.get('b').map(|mark| mark.path.clone()), Some(PathBuf::from("/p/core/lib.rs")));
+
+        assert!(marks.shift_lines(Path::new("/p/core/main.rs"), 3, 2));
+        assert!(!marks.shift_lines(Path::new("/p/core/lib.rs"), 4, 2)); // Only lines below the edit move
+        assert!(marks.shift_lines(Path::new("/p/core/main.rs"), 10, -5)); // Its line was removed
+        assert_eq!(marks.get('a').map(|mark| mark.line), Some(10));
+
+        let saved: Marks = serde_json::from_str(&serde_json::to_string(&marks).unwrap()).unwrap();
+        assert_eq!(saved, marks);
+
+        marks.remove('a');
+        assert!(marks.get('a').is_none());
+    }
+}
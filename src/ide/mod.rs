@@ -1,74 +1,85 @@
+// Text helpers shared with the agent engine and config; available without the terminal UI
+pub mod explorer_filter;
+pub mod filetype;
+pub mod gutter;
+pub mod indent;
+pub mod language;
+pub mod long_lines;
+pub mod opener;
+pub mod save_hooks;
+pub mod theme;
+pub mod whitespace;
+
+#[cfg(feature = "tui")]
 pub mod app;
+#[cfg(feature = "tui")]
 pub mod layout;
+#[cfg(feature = "tui")]
 pub mod sidebar;
+#[cfg(feature = "tui")]
 pub mod editor;
+#[cfg(feature = "tui")]
 pub mod statusbar;
+#[cfg(feature = "tui")]
 pub mod events;
+#[cfg(feature = "tui")]
+pub mod palette;
+#[cfg(feature = "tui")]
+pub mod emoji_picker;
+#[cfg(feature = "tui")]
+pub mod autosave;
+#[cfg(feature = "tui")]
+pub mod buffer;
+#[cfg(feature = "tui")]
+pub mod diff_view;
+#[cfg(feature = "tui")]
+pub mod encoding;
+#[cfg(feature = "tui")]
+pub mod hexview;
+#[cfg(feature = "tui")]
+pub mod session_lock;
+#[cfg(feature = "tui")]
+pub mod marks;
+#[cfg(feature = "tui")]
+pub mod plan_editor;
+#[cfg(feature = "tui")]
+pub mod approval;
+#[cfg(feature = "tui")]
+pub mod language_picker;
+#[cfg(feature = "tui")]
+pub mod model_picker;
+#[cfg(feature = "tui")]
+pub mod file_refs;
+#[cfg(feature = "tui")]
+pub mod session;
+#[cfg(feature = "tui")]
+pub mod session_browser;
+#[cfg(feature = "tui")]
+pub mod branch_view;
+#[cfg(feature = "tui")]
+pub mod prompt_history;
+#[cfg(feature = "tui")]
+pub mod threads;
+#[cfg(feature = "tui")]
+pub mod notification_log;
+#[cfg(feature = "tui")]
+pub mod notes_view;
+#[cfg(feature = "tui")]
+pub mod tasks_view;
+#[cfg(feature = "tui")]
+pub mod api_settings;
+#[cfg(feature = "tui")]
+pub mod keymap;
+#[cfg(feature = "tui")]
+pub mod settings_panel;
+#[cfg(feature = "tui")]
+pub mod requests;
+#[cfg(feature = "tui")]
+mod run;
 
+#[cfg(feature = "tui")]
 pub use app::{IdeApp, NotificationType};
+#[cfg(feature = "tui")]
 pub use events::EventHandler;
-
-use anyhow::Result;
-use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
-use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
-use crate::config::Config;
-
-pub async fn run_ide(config: Config) -> Result<()> {
-    let app = IdeApp::new(config).await?;
-    run_ide_with_app(app).await
-}
-
-pub async fn run_ide_with_app(mut app: IdeApp) -> Result<()> {
-    // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    let mut event_handler = EventHandler::new();
-
-    // Run the main loop
-    let result = run_ide_loop(&mut terminal, &mut app, &mut event_handler).await;
-
-    // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
-
-    result
-}
-
-async fn run_ide_loop(
-    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
-    app: &mut IdeApp,
-    event_handler: &mut EventHandler,
-) -> Result<()> {
-    loop {
-        // Draw the UI
-        terminal.draw(|frame| {
-            layout::draw_ide(frame, app);
-        })?;
-
-        // Handle events
-        if let Some(event) = event_handler.poll_event()? {
-            app.handle_event(event).await?;
-        }
-
-        // Check if we should quit
-        if app.should_quit() {
-            break;
-        }
-    }
-
-    Ok(())
-}
\ No newline at end of file
+#[cfg(feature = "tui")]
+pub use run::{run_ide, run_ide_with_app};
@@ -0,0 +1,117 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Filterable list of the models the provider serves, for picking the default model
+#[derive(Default)]
+pub struct ModelPicker {
+    pub is_open: bool,
+    pub query: String,
+    pub selected: usize,
+    models: Vec<String>,
+    current: String,
+    pub status: Option<String>, // "Loading…" or why the list couldn't be fetched
+}
+
+impl ModelPicker {
+    pub fn open(&mut self, current: &str) {
+        self.is_open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.current = current.to_string();
+        self.status = Some("⏳ Loading models…".to_string());
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    /// Show the fetched list, with the selection on the current model
+    pub fn set_models(&mut self, models: Vec<String>) {
+        self.status = models.is_empty().then(|| "The provider listed no models".to_string());
+        self.models = models;
+        self.selected = self.matches().iter().position(|model| *model == self.current).unwrap_or(0);
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches().len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn matches(&self) -> Vec<&str> {
+        let query = self.query.to_lowercase();
+        self.models
+            .iter()
+            .filter(|model| model.to_lowercase().contains(&query))
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn get_selected(&self) -> Option<String> {
+        self.matches().get(self.selected).map(|model| model.to_string())
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                format!("> {}_", self.query),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            )),
+            Line::from(""),
+        ];
+
+        if let Some(status) = &self.status {
+            lines.push(Line::from(Span::styled(format!(" {}", status), Style::default().fg(Color::Yellow))));
+        }
+
+        // Keep the selection inside the visible window
+        let visible_rows = area.height.saturating_sub(5) as usize;
+        let matches = self.matches();
+        let first = self.selected.saturating_sub(visible_rows.saturating_sub(1));
+
+        for (i, model) in matches.iter().enumerate().skip(first).take(visible_rows) {
+            let style = if i == self.selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            let current = if *model == self.current { "  (current)" } else { "" };
+            lines.push(Line::from(Span::styled(format!(" {}{} ", model, current), style)));
+        }
+
+        if matches.is_empty() && !self.models.is_empty() {
+            lines.push(Line::from(Span::styled(" No matching models", Style::default().fg(Color::DarkGray))));
+        }
+
+        let picker = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 🤖 Model (Enter: use, Tab: refresh, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(picker, area);
+    }
+}
@@ -0,0 +1,51 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Read-only overlay showing the agent's scratchpad for this session
+#[derive(Default)]
+pub struct NotesView {
+    pub is_open: bool,
+    scroll: usize,
+}
+
+impl NotesView {
+    pub fn open(&mut self) {
+        self.scroll = 0;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    pub fn scroll(&mut self, delta: isize) {
+        self.scroll = self.scroll.saturating_add_signed(delta);
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, notes: &str) {
+        frame.render_widget(Clear, area);
+        let lines: Vec<Line> = if notes.is_empty() {
+            vec![Line::from(Span::styled(
+                " The agent hasn't written any notes in this session",
+                Style::default().fg(Color::DarkGray),
+            ))]
+        } else {
+            notes.lines().map(|line| Line::from(line.to_string())).collect()
+        };
+        self.scroll = self.scroll.min(lines.len().saturating_sub(1));
+
+        let view = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll as u16, 0))
+            .block(Block::default()
+                .title(" 📝 Agent Notes (↑/↓: scroll, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+        frame.render_widget(view, area);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::ide::app::NotificationType;
+use crate::ide::sidebar::notifications::type_style;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Buffered entries reach the file at least this often
+const FLUSH_EVERY: Duration = Duration::from_secs(1);
+/// Archives of older sessions are deleted after this long
+const KEEP_FOR: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedNotification {
+    pub message: String,
+    pub kind: NotificationType,
+    pub at: DateTime<Local>,
+}
+
+/// Every notification of the session, beyond the few the live panel keeps, appended to a
+/// per-session file as JSON lines so it survives a crash. The file is rewritten with the kept
+/// entries once it holds twice the limit
+pub struct NotificationArchive {
+    entries: Vec<ArchivedNotification>,
+    limit: usize, // Oldest entries are dropped past this many
+    file: Option<PathBuf>,
+    writer: Option<BufWriter<File>>, // Open append handle to `file`
+    written: usize, // Lines in `file`
+    flushed_at: Instant,
+}
+
+impl NotificationArchive {
+    pub fn new(session: uuid::Uuid, limit: usize) -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("notifications");
+        fs::create_dir_all(&dir)?;
+        remove_stale(&dir);
+        Ok(Self::at(Some(dir.join(format!("{}.jsonl", session))), limit))
+    }
+
+    /// Archive kept in memory only, or in `file`
+    pub fn at(file: Option<PathBuf>, limit: usize) -> Self {
+        Self { entries: Vec::new(), limit, file, writer: None, written: 0, flushed_at: Instant::now() }
+    }
+
+    pub fn push(&mut self, message: &str, kind: NotificationType) {
+        let entry = ArchivedNotification { message: message.to_string(), kind, at: Local::now() };
+        // Best effort: a full disk must not break notifications
+        let _ = self.write(&entry);
+        self.entries.push(entry);
+        if self.entries.len() > self.limit {
+            let excess = self.entries.len() - self.limit;
+            self.entries.drain(..excess);
+        }
+    }
+
+    fn write(&mut self, entry: &ArchivedNotification) -> Result<()> {
+        let Some(path) = &self.file else {
+            return Ok(());
+        };
+        if self.written >= self.limit.max(1) * 2 {
+            // Keep the file from growing without bound: start over with what is still kept
+            self.writer = None;
+            let mut writer = BufWriter::new(File::create(path)?);
+            for kept in &self.entries {
+                writeln!(writer, "{}", serde_json::to_string(kept)?)?;
+            }
+            self.written = self.entries.len();
+            self.writer = Some(writer);
+        }
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => self.writer.insert(BufWriter::new(fs::OpenOptions::new().create(true).append(true).open(path)?)),
+        };
+        writeln!(writer, "{}", serde_json::to_string(entry)?)?;
+        self.written += 1;
+        self.flush_due();
+        Ok(())
+    }
+
+    /// Write out entries buffered for over `FLUSH_EVERY`; called from the main loop too, and
+    /// dropping the archive writes out the rest
+    pub fn flush_due(&mut self) {
+        let Some(writer) = self.writer.as_mut().filter(|writer| !writer.buffer().is_empty()) else {
+            return;
+        };
+        if self.flushed_at.elapsed() >= FLUSH_EVERY {
+            let _ = writer.flush();
+            self.flushed_at = Instant::now();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Newest first, of type `kind` if given, containing `query` (case-insensitive)
+    pub fn search(&self, query: &str, kind: Option<NotificationType>) -> Vec<&ArchivedNotification> {
+        let query = query.to_lowercase();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| kind.is_none_or(|kind| entry.kind == kind))
+            .filter(|entry| entry.message.to_lowercase().contains(&query))
+            .collect()
+    }
+}
+
+/// Type filters offered by the archive view, in the order Tab cycles through them
+const FILTERS: [Option<NotificationType>; 5] = [
+    None,
+    Some(NotificationType::FileOperation),
+    Some(NotificationType::Info),
+    Some(NotificationType::MouseClick),
+    Some(NotificationType::Debug),
+];
+
+/// Overlay for searching the archive: type to search, Tab to filter by type
+#[derive(Default)]
+pub struct ArchiveView {
+    pub is_open: bool,
+    pub query: String,
+    pub selected: usize,
+    filter: usize, // Index into FILTERS
+}
+
+impl ArchiveView {
+    pub fn open(&mut self) {
+        *self = Self { is_open: true, ..Self::default() };
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    pub fn kind(&self) -> Option<NotificationType> {
+        FILTERS[self.filter]
+    }
+
+    pub fn next_filter(&mut self) {
+        self.filter = (self.filter + 1) % FILTERS.len();
+        self.selected = 0;
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self, matches: usize) {
+        if self.selected + 1 < matches {
+            self.selected += 1;
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, archive: &NotificationArchive) {
+        frame.render_widget(Clear, area);
+
+        let filter = match self.kind() {
+            Some(kind) => format!("{} {:?}", type_style(&kind).0, kind),
+            None => "All types".to_string(),
+        };
+        let matches = archive.search(&self.query, self.kind());
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled(format!("> {}_", self.query), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+                Span::styled(format!("   [{}]  {} of {}", filter, matches.len(), archive.len()), Style::default().fg(Color::DarkGray)),
+            ]),
+            Line::from(""),
+        ];
+
+        // Keep the selection inside the visible window
+        let visible_rows = area.height.saturating_sub(4) as usize;
+        let first = self.selected.saturating_sub(visible_rows.saturating_sub(1));
+        for (index, entry) in matches.iter().enumerate().skip(first).take(visible_rows) {
+            let (icon, color) = type_style(&entry.kind);
+            let message_style = if index == self.selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {} ", entry.at.format("%H:%M:%S")), Style::default().fg(Color::DarkGray)),
+                Span::styled(format!("{} ", icon), Style::default().fg(color)),
+                Span::styled(entry.message.clone(), message_style),
+            ]));
+        }
+        if matches.is_empty() {
+            lines.push(Line::from(Span::styled(" No matching notifications", Style::default().fg(Color::DarkGray))));
+        }
+
+        let view = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 📜 Notification History (type: search, Tab: filter, Enter: open file:line, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(view, area);
+    }
+}
+
+/// Delete the archives of sessions that ended long ago
+fn remove_stale(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let age = entry.metadata().and_then(|meta| meta.modified()).ok().and_then(|modified| modified.elapsed().ok());
+        if age.is_some_and(|age| age > KEEP_FOR) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notification_archive() {
+        let mut archive = NotificationArchive::at(None, 3);
+        archive.push("📄 File 'a.rs' created", NotificationType::FileOperation);
+        archive.push("❌ Autosave failed", NotificationType::Info);
+        archive.push("📄 File 'b.rs' created", NotificationType::FileOperation);
+        archive.push("Focused Editor", NotificationType::Info);
+        assert_eq!(archive.len(), 3);
+
+        let messages = |query, kind| archive.search(query, kind).iter().map(|e| e.message.clone()).collect::<Vec<_>>();
+        assert_eq!(messages("FILE", Some(NotificationType::FileOperation)), vec!["📄 File 'b.rs' created"]);
+        assert_eq!(messages("", Some(NotificationType::Info)), vec!["Focused Editor", "❌ Autosave failed"]);
+        assert!(messages("a.rs", None).is_empty());
+
+        // The file is cut back to the kept entries once it holds twice as many
+        let path = std::env::temp_dir().join(format!("i4z-notifications-{}.jsonl", uuid::Uuid::new_v4()));
+        let mut archive = NotificationArchive::at(Some(path.clone()), 2);
+        for index in 0..5 {
+            archive.push(&format!("note {}", index), NotificationType::Info);
+        }
+        drop(archive);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,44 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Shell command line that opens `path` with the opener configured for its extension.
+/// `{}` in the opener is replaced by the quoted path; otherwise the path is appended.
+pub fn command_for(openers: &HashMap<String, String>, path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let opener = openers
+        .iter()
+        .find(|(ext, _)| ext.trim_start_matches('.').to_lowercase() == extension)
+        .map(|(_, command)| command.trim())
+        .filter(|command| !command.is_empty())?;
+
+    let quoted = shell_quote(&path.to_string_lossy());
+    if opener.contains("{}") {
+        Some(opener.replace("{}", &quoted))
+    } else {
+        Some(format!("{} {}", opener, quoted))
+    }
+}
+
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_for() {
+        let openers: HashMap<String, String> = [("pdf", "zathura"), (".PNG", "feh -F {}"), ("txt", " ")]
+            .into_iter()
+            .map(|(ext, command)| (ext.to_string(), command.to_string()))
+            .collect();
+
+        assert_eq!(command_for(&openers, Path::new("docs/spec.pdf")).as_deref(), Some("zathura 'docs/spec.pdf'"));
+        assert_eq!(command_for(&openers, Path::new("a b.png")).as_deref(), Some("feh -F 'a b.png'"));
+        assert_eq!(command_for(&openers, Path::new("it's.pdf")).as_deref(), Some(r"zathura 'it'\''s.pdf'"));
+        assert_eq!(command_for(&openers, Path::new("notes.txt")), None);
+        assert_eq!(command_for(&openers, Path::new("main.rs")), None);
+        assert_eq!(command_for(&openers, Path::new("Makefile")), None);
+    }
+}
@@ -0,0 +1,226 @@
+pub mod calculator;
+
+use crate::agent::code_commands::CodeCommand;
+use crate::ide::events::IdeEvent;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+pub struct PaletteCommand {
+    pub name: &'static str,
+    pub shortcut: &'static str,
+    pub event: IdeEvent,
+}
+
+pub enum PaletteSelection {
+    Calculation(String),
+    Command(IdeEvent),
+}
+
+pub struct CommandPalette {
+    pub is_open: bool,
+    pub input: String,
+    pub selected: usize,
+    commands: Vec<PaletteCommand>,
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        let command = |name, shortcut, event| PaletteCommand { name, shortcut, event };
+
+        Self {
+            is_open: false,
+            input: String::new(),
+            selected: 0,
+            commands: vec![
+                command("Save File", "Ctrl+S", IdeEvent::SaveFile),
+                command("Show Diff Against Saved File", "", IdeEvent::ShowDiff),
+                command("Show Diff Against Git HEAD", "", IdeEvent::ShowGitDiff),
+                command("Compare Files in Split Panes", "", IdeEvent::ComparePanes),
+                command("List Bookmarks", "", IdeEvent::ShowMarks),
+                command("Open File Reference from Chat / Notifications", "", IdeEvent::ShowFileRefs),
+                command("Send Chat Message with Current File", "Ctrl+Shift+Enter", IdeEvent::SendMessageWithFile),
+                command("Pin / Unpin Current Tab", "", IdeEvent::TogglePinTab),
+                command("Show Notification History", "", IdeEvent::ShowNotificationHistory),
+                command("Pretty-Print File", "", IdeEvent::PrettyPrint),
+                command("Set Language of Current File", "", IdeEvent::SetLanguage),
+                command("Toggle Line Comment", "Ctrl+/", IdeEvent::ToggleComment),
+                command("New File", "Ctrl+N", IdeEvent::NewFile),
+                command("Close File", "Ctrl+W", IdeEvent::CloseFile),
+                command("New Folder", "Ctrl+D", IdeEvent::NewFolder),
+                command("Refresh File Tree", "Ctrl+R", IdeEvent::RefreshFileTree),
+                command("Toggle Hidden Files", "Alt+H", IdeEvent::ToggleHiddenFiles),
+                command("Toggle Agentic Mode", "Ctrl+A", IdeEvent::ToggleAgenticMode),
+                command("Undo Last Agent Change", "", IdeEvent::UndoAgentChange),
+                command("Undo All Agent Changes of This Session", "", IdeEvent::UndoAgentSession),
+                command("Cycle Agent Autonomy (Confirm / Supervised / Autonomous)", "", IdeEvent::CycleAutonomyLevel),
+                command("Toggle Low-Bandwidth Mode", "", IdeEvent::ToggleLowBandwidth),
+                command("Toggle Agent Dry Run", "", IdeEvent::ToggleDryRun),
+                command("Run Tests and Fix", "", IdeEvent::RunTestsAndFix),
+                command("Show Agent Notes", "", IdeEvent::ShowAgentNotes),
+                command("Show Agent Tasks", "", IdeEvent::ShowAgentTasks),
+                command("Explain Code at Cursor", "", IdeEvent::CodeCommand(CodeCommand::Explain)),
+                command("Add Doc Comments", "", IdeEvent::CodeCommand(CodeCommand::AddDocComments)),
+                command("Write Tests for This Function", "", IdeEvent::CodeCommand(CodeCommand::WriteTests)),
+                command("Refactor Code at Cursor", "", IdeEvent::CodeCommand(CodeCommand::Refactor)),
+                command("Cycle Line Numbers (Absolute / Relative / Hybrid / Off)", "", IdeEvent::CycleLineNumbers),
+                command("Toggle Ruler", "", IdeEvent::ToggleRuler),
+                command("Cycle Theme (Dark / Light / Solarized / High Contrast / Deuteranopia / Protanopia / yours)", "", IdeEvent::CycleTheme),
+                command("Toggle Visible Whitespace (· → ¶)", "", IdeEvent::ToggleWhitespace),
+                command("Move Line Up", "Alt+↑", IdeEvent::MoveLineUp),
+                command("Move Line Down", "Alt+↓", IdeEvent::MoveLineDown),
+                command("Duplicate Line", "Ctrl+Shift+D", IdeEvent::DuplicateLine),
+                command("Join Lines", "J", IdeEvent::JoinLines),
+                command("Split Editor (Side by Side / Stacked / Off)", "Ctrl+\\", IdeEvent::ToggleSplit),
+                command("Focus Other Editor Pane", "", IdeEvent::FocusOtherPane),
+                command("Insert Emoji / Symbol", "Ctrl+E", IdeEvent::ToggleEmojiPicker),
+                command("Clear Chat", "Ctrl+L", IdeEvent::ClearChat),
+                command("Copy Last Answer as Plain Text", "Ctrl+Y", IdeEvent::CopyLastAnswer),
+                command("Send Last Answer to Pipe Command", "", IdeEvent::PipeLastAnswer),
+                command("Start Workflow…", "", IdeEvent::StartWorkflow),
+                command("Save Conversation as Workflow", "", IdeEvent::SaveWorkflow),
+                command("Clear Notifications", "Ctrl+K", IdeEvent::ClearNotifications),
+                command("API Configuration", "Ctrl+,", IdeEvent::ShowApiConfig),
+                command("Settings", "", IdeEvent::ShowSettings),
+                command("Browse Sessions", "", IdeEvent::ShowSessions),
+                command("Checkpoint Conversation…", "", IdeEvent::NewCheckpoint),
+                command("Show Conversation Branches", "", IdeEvent::ShowBranches),
+                command("Pin Last Answer", "", IdeEvent::PinLastAnswer),
+                command("Search Prompt History", "Ctrl+R in chat", IdeEvent::SearchPromptHistory),
+                command("Edit Project Memory", "", IdeEvent::EditProjectMemory),
+                command("New Chat Thread…", "", IdeEvent::NewThread),
+                command("Next Chat Thread", "Alt+.", IdeEvent::NextThread),
+                command("Previous Chat Thread", "Alt+,", IdeEvent::PreviousThread),
+                command("Edit and Resend Previous Message", "Alt+E", IdeEvent::EditPreviousMessage),
+                command("Select Model", "", IdeEvent::SelectModel),
+                command("Command Reference", "Ctrl+H", IdeEvent::ToggleCommandHelp),
+                command("Show Keymap", "", IdeEvent::ShowKeymap),
+                command("Help", "F1", IdeEvent::ToggleHelp),
+                command("Suspend to Shell", "Ctrl+Z", IdeEvent::Suspend),
+                command("Quit", "Ctrl+Q", IdeEvent::Quit),
+            ],
+        }
+    }
+
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.input.clear();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.input.clear();
+        self.selected = 0;
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        self.input.push(c);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.input.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.item_count() {
+            self.selected += 1;
+        }
+    }
+
+    /// Result of evaluating the input as a calculator expression, if it is one
+    pub fn calculation(&self) -> Option<String> {
+        calculator::evaluate(&self.input).ok()
+    }
+
+    pub fn filtered_commands(&self) -> Vec<&PaletteCommand> {
+        let query = self.input.to_lowercase();
+        self.commands
+            .iter()
+            .filter(|command| fuzzy_match(&command.name.to_lowercase(), &query))
+            .collect()
+    }
+
+    fn item_count(&self) -> usize {
+        self.calculation().iter().count() + self.filtered_commands().len()
+    }
+
+    pub fn get_selection(&self) -> Option<PaletteSelection> {
+        let mut index = self.selected;
+        if let Some(result) = self.calculation() {
+            if index == 0 {
+                return Some(PaletteSelection::Calculation(result));
+            }
+            index -= 1;
+        }
+        self.filtered_commands()
+            .get(index)
+            .map(|command| PaletteSelection::Command(command.event.clone()))
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let selected_style = Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD);
+        let mut lines = vec![
+            Line::from(Span::styled(
+                format!("> {}_", self.input),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            )),
+            Line::from(""),
+        ];
+
+        let mut index = 0;
+        if let Some(result) = self.calculation() {
+            let style = if self.selected == index { selected_style } else { Style::default().fg(Color::Green) };
+            lines.push(Line::from(Span::styled(format!(" 🧮 = {} ", result), style)));
+            index += 1;
+        }
+
+        for command in self.filtered_commands() {
+            let style = if self.selected == index { selected_style } else { Style::default().fg(Color::Gray) };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {} ", command.name), style),
+                Span::styled(format!(" {}", command.shortcut), Style::default().fg(Color::DarkGray)),
+            ]));
+            index += 1;
+        }
+
+        if index == 0 {
+            lines.push(Line::from(Span::styled(" No matching commands", Style::default().fg(Color::DarkGray))));
+        }
+
+        let palette = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 🔎 Command Palette (Enter: run/insert, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+
+        frame.render_widget(palette, area);
+    }
+}
+
+/// Case-insensitive subsequence match, so "svf" finds "Save File"
+fn fuzzy_match(candidate: &str, query: &str) -> bool {
+    let mut candidate_chars = candidate.chars();
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|q| candidate_chars.any(|c| c == q))
+}
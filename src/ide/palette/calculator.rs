@@ -0,0 +1,347 @@
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Dimension {
+    Time,
+    Data,
+    Length,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Quantity {
+    value: f64, // Always stored in the dimension's base unit
+    dimension: Option<Dimension>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+}
+
+/// Evaluate a palette expression such as `0xff + 12`, `2^10 in hex` or `3 days in hours`
+pub fn evaluate(input: &str) -> Result<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err(anyhow!("Empty expression"));
+    }
+
+    let (expression, target) = split_target(input);
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser { tokens, pos: 0 };
+    let quantity = parser.parse_expression()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(anyhow!("Unexpected input after expression"));
+    }
+
+    match target {
+        Some(target) => format_in_target(quantity, target),
+        None => Ok(format_quantity(quantity)),
+    }
+}
+
+/// Split `<expr> in <target>` / `<expr> to <target>` into its two halves
+fn split_target(input: &str) -> (&str, Option<&str>) {
+    for keyword in [" in ", " to "] {
+        if let Some(index) = input.rfind(keyword) {
+            let target = input[index + keyword.len()..].trim();
+            if !target.is_empty() && !target.contains(' ') {
+                return (&input[..index], Some(target));
+            }
+        }
+    }
+    (input, None)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '0' && i + 1 < chars.len() && matches!(chars[i + 1], 'x' | 'X' | 'b' | 'B' | 'o' | 'O') {
+            let radix = match chars[i + 1].to_ascii_lowercase() {
+                'x' => 16,
+                'b' => 2,
+                _ => 8,
+            };
+            let start = i + 2;
+            let mut end = start;
+            while end < chars.len() && (chars[end].is_digit(radix) || chars[end] == '_') {
+                end += 1;
+            }
+            let digits: String = chars[start..end].iter().filter(|c| **c != '_').collect();
+            let value = i64::from_str_radix(&digits, radix)
+                .map_err(|_| anyhow!("Invalid radix literal"))?;
+            tokens.push(Token::Number(value as f64));
+            i = end;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().filter(|c| **c != '_').collect();
+            let value = text.parse::<f64>().map_err(|_| anyhow!("Invalid number: {}", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_alphabetic() {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/%^".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else {
+            return Err(anyhow!("Unexpected character '{}'", c));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_expression(&mut self) -> Result<Quantity> {
+        let mut left = self.parse_term()?;
+        while let Some(Token::Op(op)) = self.peek().cloned() {
+            if op != '+' && op != '-' {
+                break;
+            }
+            self.pos += 1;
+            let right = self.parse_term()?;
+            let dimension = same_dimension(left, right)?;
+            let value = if op == '+' { left.value + right.value } else { left.value - right.value };
+            left = Quantity { value, dimension };
+        }
+        Ok(left)
+    }
+
+    fn parse_term(&mut self) -> Result<Quantity> {
+        let mut left = self.parse_power()?;
+        while let Some(Token::Op(op)) = self.peek().cloned() {
+            if op != '*' && op != '/' && op != '%' {
+                break;
+            }
+            self.pos += 1;
+            let right = self.parse_power()?;
+            left = match op {
+                '*' => {
+                    if left.dimension.is_some() && right.dimension.is_some() {
+                        return Err(anyhow!("Cannot multiply two quantities with units"));
+                    }
+                    Quantity { value: left.value * right.value, dimension: left.dimension.or(right.dimension) }
+                }
+                '/' => {
+                    if right.value == 0.0 {
+                        return Err(anyhow!("Division by zero"));
+                    }
+                    let dimension = match (left.dimension, right.dimension) {
+                        (Some(a), Some(b)) if a == b => None,
+                        (dimension, None) => dimension,
+                        _ => return Err(anyhow!("Incompatible units")),
+                    };
+                    Quantity { value: left.value / right.value, dimension }
+                }
+                _ => {
+                    if right.value == 0.0 {
+                        return Err(anyhow!("Division by zero"));
+                    }
+                    let dimension = same_dimension(left, right)?;
+                    Quantity { value: left.value % right.value, dimension }
+                }
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_power(&mut self) -> Result<Quantity> {
+        let base = self.parse_unary()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.parse_power()?;
+            if base.dimension.is_some() || exponent.dimension.is_some() {
+                return Err(anyhow!("Exponents only work on plain numbers"));
+            }
+            return Ok(Quantity { value: base.value.powf(exponent.value), dimension: None });
+        }
+        Ok(base)
+    }
+
+    fn parse_unary(&mut self) -> Result<Quantity> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                let inner = self.parse_unary()?;
+                Ok(Quantity { value: -inner.value, ..inner })
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.parse_unary()
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    fn parse_primary(&mut self) -> Result<Quantity> {
+        let quantity = match self.next() {
+            Some(Token::Number(value)) => Quantity { value, dimension: None },
+            Some(Token::LParen) => {
+                let inner = self.parse_expression()?;
+                if self.next() != Some(Token::RParen) {
+                    return Err(anyhow!("Missing closing parenthesis"));
+                }
+                inner
+            }
+            Some(token) => return Err(anyhow!("Unexpected token {:?}", token)),
+            None => return Err(anyhow!("Unexpected end of expression")),
+        };
+
+        // An identifier directly after a value is a unit suffix (`3 days`)
+        if let Some(Token::Ident(name)) = self.peek().cloned() {
+            if quantity.dimension.is_some() {
+                return Err(anyhow!("Value already has a unit"));
+            }
+            let (dimension, factor) = lookup_unit(&name)
+                .ok_or_else(|| anyhow!("Unknown unit '{}'", name))?;
+            self.pos += 1;
+            return Ok(Quantity { value: quantity.value * factor, dimension: Some(dimension) });
+        }
+
+        Ok(quantity)
+    }
+}
+
+fn same_dimension(left: Quantity, right: Quantity) -> Result<Option<Dimension>> {
+    match (left.dimension, right.dimension) {
+        (a, b) if a == b => Ok(a),
+        (Some(dimension), None) | (None, Some(dimension)) => Ok(Some(dimension)),
+        _ => Err(anyhow!("Incompatible units")),
+    }
+}
+
+fn lookup_unit(name: &str) -> Option<(Dimension, f64)> {
+    let unit = match name.to_lowercase().as_str() {
+        "ms" | "millisecond" | "milliseconds" => (Dimension::Time, 0.001),
+        "s" | "sec" | "secs" | "second" | "seconds" => (Dimension::Time, 1.0),
+        "min" | "mins" | "minute" | "minutes" => (Dimension::Time, 60.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => (Dimension::Time, 3600.0),
+        "d" | "day" | "days" => (Dimension::Time, 86_400.0),
+        "w" | "week" | "weeks" => (Dimension::Time, 604_800.0),
+        "b" | "byte" | "bytes" => (Dimension::Data, 1.0),
+        "kb" | "kib" => (Dimension::Data, 1024.0),
+        "mb" | "mib" => (Dimension::Data, 1024.0 * 1024.0),
+        "gb" | "gib" => (Dimension::Data, 1024.0 * 1024.0 * 1024.0),
+        "tb" | "tib" => (Dimension::Data, 1024.0 * 1024.0 * 1024.0 * 1024.0),
+        "mm" => (Dimension::Length, 0.001),
+        "cm" => (Dimension::Length, 0.01),
+        "m" | "meter" | "meters" => (Dimension::Length, 1.0),
+        "km" => (Dimension::Length, 1000.0),
+        "ft" | "feet" => (Dimension::Length, 0.3048),
+        "mi" | "mile" | "miles" => (Dimension::Length, 1609.344),
+        _ => return None,
+    };
+    Some(unit)
+}
+
+fn base_unit_name(dimension: Dimension) -> &'static str {
+    match dimension {
+        Dimension::Time => "seconds",
+        Dimension::Data => "bytes",
+        Dimension::Length => "meters",
+    }
+}
+
+fn format_quantity(quantity: Quantity) -> String {
+    match quantity.dimension {
+        Some(dimension) => format!("{} {}", format_number(quantity.value), base_unit_name(dimension)),
+        None => format_number(quantity.value),
+    }
+}
+
+fn format_in_target(quantity: Quantity, target: &str) -> Result<String> {
+    let radix = match target.to_lowercase().as_str() {
+        "hex" => Some(16),
+        "bin" | "binary" => Some(2),
+        "oct" | "octal" => Some(8),
+        "dec" | "decimal" => Some(10),
+        _ => None,
+    };
+
+    if let Some(radix) = radix {
+        if quantity.value.fract() != 0.0 || quantity.value < 0.0 {
+            return Err(anyhow!("Radix conversion needs a non-negative integer"));
+        }
+        let value = quantity.value as u64;
+        return Ok(match radix {
+            16 => format!("0x{:x}", value),
+            2 => format!("0b{:b}", value),
+            8 => format!("0o{:o}", value),
+            _ => value.to_string(),
+        });
+    }
+
+    let (dimension, factor) = lookup_unit(target)
+        .ok_or_else(|| anyhow!("Unknown unit '{}'", target))?;
+    if quantity.dimension != Some(dimension) {
+        return Err(anyhow!("Cannot convert to '{}'", target));
+    }
+    Ok(format!("{} {}", format_number(quantity.value / factor), target))
+}
+
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        let text = format!("{:.10}", value);
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic_and_radix() {
+        assert_eq!(evaluate("0xff + 12").unwrap(), "267");
+        assert_eq!(evaluate("2 * (3 + 4) - 1").unwrap(), "13");
+        assert_eq!(evaluate("2^10 in hex").unwrap(), "0x400");
+        assert_eq!(evaluate("0b1010 in dec").unwrap(), "10");
+        assert_eq!(evaluate("7 / 2").unwrap(), "3.5");
+    }
+
+    #[test]
+    fn test_unit_conversion() {
+        assert_eq!(evaluate("3 days in hours").unwrap(), "72 hours");
+        assert_eq!(evaluate("1.5 gb to mb").unwrap(), "1536 mb");
+        assert!(evaluate("3 days in mb").is_err());
+        assert!(evaluate("save file").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use crate::agent::plan::Plan;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Wrap},
+    Frame,
+};
+
+/// Rebase-style list for reviewing the agent's plan before it runs
+pub struct PlanEditor {
+    pub is_open: bool,
+    pub selected: usize,
+    steps: Vec<String>,
+    editing: Option<String>, // Text of the selected step while it is being edited
+}
+
+impl Default for PlanEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlanEditor {
+    pub fn new() -> Self {
+        Self { is_open: false, selected: 0, steps: Vec::new(), editing: None }
+    }
+
+    pub fn open(&mut self, plan: Plan) {
+        self.steps = plan.steps;
+        self.selected = 0;
+        self.editing = None;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.steps.clear();
+        self.editing = None;
+    }
+
+    /// The plan as the user left it
+    pub fn plan(&self) -> Plan {
+        Plan { steps: self.steps.clone() }
+    }
+
+    pub fn is_editing(&self) -> bool {
+        self.editing.is_some()
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.steps.len() {
+            self.selected += 1;
+        }
+    }
+
+    /// Swap the selected step with the one above it
+    pub fn move_up(&mut self) {
+        if self.selected > 0 {
+            self.steps.swap(self.selected, self.selected - 1);
+            self.selected -= 1;
+        }
+    }
+
+    pub fn move_down(&mut self) {
+        if self.selected + 1 < self.steps.len() {
+            self.steps.swap(self.selected, self.selected + 1);
+            self.selected += 1;
+        }
+    }
+
+    pub fn delete(&mut self) {
+        if self.selected < self.steps.len() {
+            self.steps.remove(self.selected);
+            self.selected = self.selected.min(self.steps.len().saturating_sub(1));
+        }
+    }
+
+    pub fn start_edit(&mut self) {
+        self.editing = self.steps.get(self.selected).cloned();
+    }
+
+    /// Add an empty step below the selected one and start editing it
+    pub fn insert_below(&mut self) {
+        let index = if self.steps.is_empty() { 0 } else { self.selected + 1 };
+        self.steps.insert(index, String::new());
+        self.selected = index;
+        self.editing = Some(String::new());
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        if let Some(text) = &mut self.editing {
+            text.push(c);
+        }
+    }
+
+    pub fn backspace(&mut self) {
+        if let Some(text) = &mut self.editing {
+            text.pop();
+        }
+    }
+
+    /// Keep the edited text; a step left empty is removed
+    pub fn finish_edit(&mut self) {
+        let Some(text) = self.editing.take() else {
+            return;
+        };
+        if text.trim().is_empty() {
+            self.delete();
+        } else if let Some(step) = self.steps.get_mut(self.selected) {
+            *step = text.trim().to_string();
+        }
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = None;
+        // Drop a step that was just added and never filled in
+        if self.steps.get(self.selected).is_some_and(|step| step.is_empty()) {
+            self.delete();
+        }
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect) {
+        frame.render_widget(Clear, area);
+
+        let selected_style = Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD);
+        let mut lines = Vec::new();
+        for (index, step) in self.steps.iter().enumerate() {
+            let line = match &self.editing {
+                Some(text) if index == self.selected => Line::from(Span::styled(
+                    format!(" {:2}. {}_", index + 1, text),
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+                )),
+                _ => {
+                    let style = if index == self.selected { selected_style } else { Style::default().fg(Color::Gray) };
+                    Line::from(Span::styled(format!(" {:2}. {} ", index + 1, step), style))
+                }
+            };
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            lines.push(Line::from(Span::styled(
+                " All steps removed - press o to add one, or Esc to reject the plan",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        lines.push(Line::from(""));
+        let help = if self.is_editing() {
+            " Enter: keep  Esc: cancel edit"
+        } else {
+            " j/k: select  J/K: move  e: edit  o: add  d: delete  y: approve  Esc: reject"
+        };
+        lines.push(Line::from(Span::styled(help, Style::default().fg(Color::DarkGray))));
+
+        let editor = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default()
+                .title(format!(" 📋 Agent Plan ({} steps) ", self.steps.len()))
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Magenta)));
+
+        frame.render_widget(editor, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_editor() {
+        let mut editor = PlanEditor::new();
+        editor.open(Plan { steps: vec!["a".into(), "b".into(), "c".into()] });
+
+        editor.select_next();
+        editor.move_up();
+        assert_eq!(editor.plan().steps, ["b", "a", "c"]);
+        assert_eq!(editor.selected, 0);
+
+        editor.select_next();
+        editor.start_edit();
+        editor.backspace();
+        "A2".chars().for_each(|c| editor.add_char(c));
+        editor.finish_edit();
+        editor.select_next();
+        editor.delete();
+        assert_eq!(editor.plan().steps, ["b", "A2"]);
+
+        editor.insert_below();
+        editor.cancel_edit();
+        assert_eq!(editor.plan().steps, ["b", "A2"]);
+    }
+}
@@ -0,0 +1,255 @@
+use crate::agent::references::fuzzy_score;
+use anyhow::{anyhow, Result};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Prompts kept at most; the oldest go first
+const MAX_PROMPTS: usize = 500;
+/// Matches the search overlay lists
+const MAX_MATCHES: usize = 50;
+
+/// Prompts sent from the chat input, oldest first, shared by every project and window like a
+/// shell's history; Up and Down in the empty input step through them
+#[derive(Default)]
+pub struct PromptHistory {
+    prompts: Vec<String>,
+    recalled: Option<usize>, // The prompt in the input while stepping through the history
+    path: Option<PathBuf>,   // Where it is kept; None keeps it in memory only
+}
+
+impl PromptHistory {
+    pub fn path() -> Result<PathBuf> {
+        let dir = dirs::data_local_dir().ok_or_else(|| anyhow!("Could not find data directory"))?;
+        Ok(dir.join("i4z_ide").join("prompt_history.jsonl"))
+    }
+
+    /// The history kept at `path`, one JSON string per line; an unreadable file is an empty history
+    pub fn load(path: PathBuf) -> Self {
+        Self { prompts: read(&path), recalled: None, path: Some(path) }
+    }
+
+    /// Add a sent prompt, unless it repeats the latest one, and stop stepping through the history
+    pub fn record(&mut self, prompt: &str) -> Result<()> {
+        self.recalled = None;
+        let prompt = prompt.trim();
+        if prompt.is_empty() || self.prompts.last().is_some_and(|last| last == prompt) {
+            return Ok(());
+        }
+        self.prompts.push(prompt.to_string());
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.prompts.len() > MAX_PROMPTS {
+            // Other windows may have appended since this one loaded the file; keep theirs too
+            let mut prompts = read(path);
+            prompts.push(prompt.to_string());
+            prompts.drain(..prompts.len().saturating_sub(MAX_PROMPTS));
+            write_all(path, &prompts)?;
+            self.prompts = prompts;
+            return Ok(());
+        }
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let line = format!("{}\n", serde_json::to_string(prompt)?);
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    /// Whether `input` is empty or still the recalled prompt, so Up and Down step through the history
+    fn recalling(&mut self, input: &str) -> bool {
+        if self.recalled.is_some_and(|index| self.prompts[index] != input) {
+            self.recalled = None;
+        }
+        self.recalled.is_some() || input.is_empty()
+    }
+
+    /// The prompt before the recalled one, or the latest; None when Up should do what it does elsewhere
+    pub fn older(&mut self, input: &str) -> Option<&str> {
+        if !self.recalling(input) || self.prompts.is_empty() {
+            return None;
+        }
+        let index = self.recalled.map_or(self.prompts.len() - 1, |index| index.saturating_sub(1));
+        self.recalled = Some(index);
+        Some(&self.prompts[index])
+    }
+
+    /// The prompt after the recalled one, or an empty input past the latest; None when not recalling
+    pub fn newer(&mut self, input: &str) -> Option<&str> {
+        if !self.recalling(input) {
+            return None;
+        }
+        let index = self.recalled?;
+        self.recalled = (index + 1 < self.prompts.len()).then_some(index + 1);
+        Some(self.recalled.map_or("", |index| &self.prompts[index]))
+    }
+
+    /// Prompts matching `query`, best first and the most recent among equals
+    pub fn search(&self, query: &str) -> Vec<&str> {
+        let query = query.trim();
+        let mut matches: Vec<(usize, usize, &str)> = self
+            .prompts
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(index, prompt)| fuzzy_score(prompt, query).map(|score| (score, self.prompts.len() - index, prompt.as_str())))
+            .collect();
+        matches.sort();
+        let mut seen = HashSet::new();
+        matches.into_iter().map(|(_, _, prompt)| prompt).filter(|prompt| seen.insert(*prompt)).take(MAX_MATCHES).collect()
+    }
+}
+
+/// The latest `MAX_PROMPTS` prompts kept at `path`
+fn read(path: &Path) -> Vec<String> {
+    let mut prompts: Vec<String> = fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    prompts.drain(..prompts.len().saturating_sub(MAX_PROMPTS));
+    prompts
+}
+
+fn write_all(path: &Path, prompts: &[String]) -> Result<()> {
+    let mut text = String::new();
+    for prompt in prompts {
+        text.push_str(&serde_json::to_string(prompt)?);
+        text.push('\n');
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Ctrl+R in the chat: past prompts filtered as you type, to put one back into the input
+#[derive(Default)]
+pub struct PromptSearch {
+    pub is_open: bool,
+    pub query: String,
+    pub selected: usize,
+}
+
+impl PromptSearch {
+    pub fn open(&mut self) {
+        self.is_open = true;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.query.clear();
+        self.selected = 0;
+    }
+
+    pub fn add_char(&mut self, c: char) {
+        self.query.push(c);
+        self.selected = 0;
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.selected = 0;
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self, history: &PromptHistory) {
+        if self.selected + 1 < history.search(&self.query).len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn get_selected(&self, history: &PromptHistory) -> Option<String> {
+        history.search(&self.query).get(self.selected).map(|prompt| prompt.to_string())
+    }
+
+    pub fn draw(&self, frame: &mut Frame, area: Rect, history: &PromptHistory) {
+        frame.render_widget(Clear, area);
+
+        let mut lines = vec![
+            Line::from(Span::styled(
+                format!("> {}_", self.query),
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            )),
+            Line::from(""),
+        ];
+        let matches = history.search(&self.query);
+        let width = area.width.saturating_sub(4) as usize;
+        let visible = area.height.saturating_sub(4) as usize;
+        let first = self.selected.saturating_sub(visible.saturating_sub(1));
+        for (index, prompt) in matches.iter().enumerate().skip(first) {
+            let style = if index == self.selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            let line: String = prompt.split_whitespace().collect::<Vec<_>>().join(" ").chars().take(width).collect();
+            lines.push(Line::from(Span::styled(format!(" {} ", line), style)));
+        }
+        if matches.is_empty() {
+            lines.push(Line::from(Span::styled(" No matching prompts", Style::default().fg(Color::DarkGray))));
+        }
+
+        let search = Paragraph::new(lines)
+            .block(Block::default()
+                .title(" 🕘 Prompt History (Enter: put in the chat input, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+        frame.render_widget(search, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_history_recall_and_search() {
+        let path = std::env::temp_dir().join(format!("i4z-prompts-{}.jsonl", uuid::Uuid::new_v4()));
+        let mut history = PromptHistory::load(path.clone());
+        history.record("explain the parser").unwrap();
+        history.record("fix the failing test\nin ci").unwrap();
+        history.record("fix the failing test\nin ci").unwrap();
+        history.record("/export html").unwrap();
+
+        let mut history = PromptHistory::load(path.clone());
+        assert_eq!(history.older("draft"), None);
+        assert_eq!(history.older(""), Some("/export html"));
+        assert_eq!(history.older("/export html"), Some("fix the failing test\nin ci"));
+        assert_eq!(history.older("fix the failing test\nin ci"), Some("explain the parser"));
+        assert_eq!(history.older("explain the parser"), Some("explain the parser"));
+        assert_eq!(history.newer("explain the parser"), Some("fix the failing test\nin ci"));
+        assert_eq!(history.newer("fix the failing test\nin ci"), Some("/export html"));
+        assert_eq!(history.newer("/export html"), Some(""));
+        assert_eq!(history.newer(""), None);
+
+        assert_eq!(history.search("fix"), vec!["fix the failing test\nin ci"]);
+        assert_eq!(history.search("the"), vec!["fix the failing test\nin ci", "explain the parser"]);
+        assert_eq!(history.search("").first(), Some(&"/export html"));
+
+        // Another window's prompts survive this one cutting the file down to the limit
+        let mut other = PromptHistory::load(path.clone());
+        for index in 0..MAX_PROMPTS - 3 {
+            history.record(&format!("prompt {}", index)).unwrap();
+        }
+        other.record("from the other window").unwrap();
+        history.record("one too many").unwrap();
+        let kept = PromptHistory::load(path.clone());
+        assert_eq!(kept.prompts.len(), MAX_PROMPTS);
+        assert!(kept.prompts.iter().any(|prompt| prompt == "from the other window"));
+        assert_eq!(kept.prompts.last().map(String::as_str), Some("one too many"));
+        fs::remove_file(path).unwrap();
+    }
+}
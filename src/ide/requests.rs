@@ -0,0 +1,131 @@
+use crate::api::GroqResponse;
+use anyhow::Result;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+
+/// Identifies one outbound chat request, so its text and answer land in the chat message that asked
+pub type RequestId = u64;
+
+/// Where a request's answer goes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestKind {
+    Conversation, // The chat or agent turn; at most one at a time, as each answer builds on the last
+    Background { title: String, prompt: String }, // A one-off question such as Explain, answered alongside
+}
+
+/// A request on its way to the model
+pub struct ChatRequest {
+    pub id: RequestId,
+    pub kind: RequestKind,
+    handle: JoinHandle<Result<GroqResponse>>,
+    stream: Option<UnboundedReceiver<String>>, // Its text as it arrives, when streaming
+    pub streamed_text: String, // What has arrived of it so far
+}
+
+/// The chat requests in flight
+#[derive(Default)]
+pub struct Requests {
+    next_id: RequestId,
+    in_flight: Vec<ChatRequest>,
+}
+
+impl Requests {
+    /// A fresh ID, also for chat messages that stand for other work in progress
+    pub fn new_id(&mut self) -> RequestId {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn start(&mut self, kind: RequestKind, handle: JoinHandle<Result<GroqResponse>>, stream: Option<UnboundedReceiver<String>>) -> RequestId {
+        let id = self.new_id();
+        self.in_flight.push(ChatRequest { id, kind, handle, stream, streamed_text: String::new() });
+        id
+    }
+
+    /// The conversation's request, if one is in flight
+    pub fn conversation(&self) -> Option<&ChatRequest> {
+        self.in_flight.iter().find(|request| request.kind == RequestKind::Conversation)
+    }
+
+    pub fn has_background(&self) -> bool {
+        self.in_flight.iter().any(|request| request.kind != RequestKind::Conversation)
+    }
+
+    /// Take the text that arrived since the last call; returns the requests whose text grew
+    pub fn receive_streamed(&mut self) -> Vec<&ChatRequest> {
+        let mut grown = Vec::new();
+        for (index, request) in self.in_flight.iter_mut().enumerate() {
+            let Some(stream) = request.stream.as_mut() else {
+                continue;
+            };
+            let mut arrived = false;
+            while let Ok(text) = stream.try_recv() {
+                request.streamed_text.push_str(&text);
+                arrived = true;
+            }
+            if arrived {
+                grown.push(index);
+            }
+        }
+        grown.into_iter().map(|index| &self.in_flight[index]).collect()
+    }
+
+    /// Take a request whose answer has arrived, oldest first
+    pub fn take_finished(&mut self) -> Option<ChatRequest> {
+        let index = self.in_flight.iter().position(|request| request.handle.is_finished())?;
+        Some(self.in_flight.remove(index))
+    }
+
+    /// Stop the request and take it, for what it streamed so far
+    pub fn cancel(&mut self, id: RequestId) -> Option<ChatRequest> {
+        let index = self.in_flight.iter().position(|request| request.id == id)?;
+        let request = self.in_flight.remove(index);
+        request.handle.abort();
+        Some(request)
+    }
+
+    pub fn ids(&self) -> Vec<RequestId> {
+        self.in_flight.iter().map(|request| request.id).collect()
+    }
+}
+
+impl ChatRequest {
+    /// Wait for the answer; only called once the request has finished
+    pub async fn response(self) -> Result<GroqResponse> {
+        match self.handle.await {
+            Ok(response) => response,
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_are_matched_by_id() {
+        let mut requests = Requests::default();
+        let (sender, receiver) = tokio::sync::mpsc::unbounded_channel();
+        let chat = requests.start(RequestKind::Conversation, tokio::spawn(std::future::pending()), Some(receiver));
+        let background = RequestKind::Background { title: "Explain".to_string(), prompt: String::new() };
+        let explain = requests.start(background, tokio::spawn(async { Err(anyhow::anyhow!("offline")) }), None);
+        assert_ne!(chat, explain);
+        assert_eq!(requests.conversation().map(|request| request.id), Some(chat));
+
+        sender.send("Hel".to_string()).unwrap();
+        sender.send("lo".to_string()).unwrap();
+        let grown: Vec<(RequestId, String)> = requests.receive_streamed().iter().map(|r| (r.id, r.streamed_text.clone())).collect();
+        assert_eq!(grown, vec![(chat, "Hello".to_string())]);
+
+        while !requests.in_flight.iter().any(|request| request.id == explain && request.handle.is_finished()) {
+            tokio::task::yield_now().await;
+        }
+        let finished = requests.take_finished().unwrap();
+        assert_eq!(finished.id, explain);
+        assert!(finished.response().await.is_err());
+        assert!(!requests.has_background());
+        assert_eq!(requests.cancel(chat).map(|request| request.streamed_text), Some("Hello".to_string()));
+        assert!(requests.ids().is_empty());
+    }
+}
@@ -0,0 +1,190 @@
+use crate::config::Config;
+use crate::ide::{layout, EventHandler, IdeApp, NotificationType};
+use anyhow::Result;
+use crossterm::{
+    event::{DisableMouseCapture, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::Notify;
+#[cfg(unix)]
+use tokio::signal::unix::SignalKind;
+
+pub async fn run_ide(config: Config) -> Result<()> {
+    let app = IdeApp::new(config).await?;
+    run_ide_with_app(app).await
+}
+
+pub async fn run_ide_with_app(mut app: IdeApp) -> Result<()> {
+    // Setup terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    let mut event_handler = EventHandler::new();
+    event_handler.keymap = app.keymap.clone();
+
+    // A dropped SSH connection (SIGHUP) or SIGTERM ends the loop; unsaved work is kept for recovery.
+    // `wake` cuts short the wait for input, so the loop notices at once
+    let hangup = Arc::new(AtomicBool::new(false));
+    let wake = Arc::new(Notify::new());
+    #[cfg(unix)]
+    for kind in [SignalKind::hangup(), SignalKind::terminate()] {
+        let mut signal = tokio::signal::unix::signal(kind)?;
+        let (hangup, wake) = (hangup.clone(), wake.clone());
+        tokio::spawn(async move {
+            if signal.recv().await.is_some() {
+                hangup.store(true, Ordering::Relaxed);
+                wake.notify_one();
+            }
+        });
+    }
+
+    // Run the main loop
+    let result = run_ide_loop(&mut terminal, &mut app, &mut event_handler, &hangup, &wake).await;
+
+    if hangup.load(Ordering::Relaxed) {
+        // The loop may have failed only because the terminal went away; save what we can and
+        // leave quietly, since there may be no terminal left to restore or report to
+        let saved = app.save_on_hangup();
+        let _ = suspend_terminal(&mut terminal);
+        return saved;
+    }
+
+    // Swap files are only kept around when the session ended abnormally
+    let saved = match result {
+        Ok(()) => {
+            app.autosave.cleanup();
+            let persisted = app.persist_conversation();
+            app.save_session().and(persisted)
+        }
+        Err(_) => Ok(()),
+    };
+
+    // Restore terminal
+    suspend_terminal(&mut terminal)?;
+
+    // Reported once the terminal is back, so the message stays visible
+    if let Err(e) = saved {
+        eprintln!("⚠️ Failed to save the session: {}", e);
+    }
+
+    result
+}
+
+async fn run_ide_loop(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    app: &mut IdeApp,
+    event_handler: &mut EventHandler,
+    hangup: &AtomicBool,
+    wake: &Notify,
+) -> Result<()> {
+    // A SIGTSTP from outside (e.g. `kill -TSTP`) should suspend just like Ctrl+Z
+    #[cfg(unix)]
+    let stop_signal = {
+        let flag = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGTSTP, flag.clone())?;
+        flag
+    };
+
+    while !hangup.load(Ordering::Relaxed) {
+        // Draw the UI
+        terminal.draw(|frame| {
+            layout::draw_ide(frame, app);
+            app.config.theme.apply(frame.buffer_mut(), &app.config.themes);
+        })?;
+
+        // Handle events
+        event_handler.capture_text = app.is_capturing_text();
+        event_handler.context = app.key_context();
+        if let Some(event) = event_handler.poll_event(wake).await? {
+            app.handle_event(event).await?;
+        }
+        app.poll_ai_reply().await;
+        app.poll_agent_actions().await;
+        app.poll_task_queue();
+        app.poll_model_list().await;
+        app.poll_formatting().await;
+        app.poll_reference_index().await;
+        app.poll_vector_index().await;
+        app.poll_history_summary().await;
+        app.notification_archive.flush_due();
+        if let Some(keymap) = app.take_keymap_update() {
+            event_handler.keymap = keymap;
+        }
+
+        #[cfg(unix)]
+        if app.take_suspend_request() | stop_signal.swap(false, Ordering::Relaxed) {
+            suspend_to_shell(terminal)?;
+        }
+
+        // External openers need the real terminal, so hand it over while they run
+        if let Some(command) = app.take_external_command() {
+            match run_external(terminal, &command) {
+                Ok(status) if status.success() => {}
+                Ok(status) => app.add_notification(
+                    format!("⚠️ `{}` exited with {}", command, status),
+                    NotificationType::FileOperation
+                ),
+                Err(e) => app.add_notification(
+                    format!("❌ Failed to run `{}`: {}", command, e),
+                    NotificationType::FileOperation
+                ),
+            }
+        }
+
+        // Periodic background work (autosave)
+        app.tick();
+
+        // Check if we should quit
+        if app.should_quit() {
+            app.stop_agent_command();
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Give the terminal back to the shell: cooked mode, main screen, no mouse reporting
+fn suspend_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen, DisableMouseCapture)?;
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+/// Take the terminal back and force a full redraw, since whatever ran meanwhile drew over it
+fn resume_terminal(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableMouseCapture)?;
+    terminal.clear()?;
+    Ok(())
+}
+
+/// Stop the process until the shell's `fg`, with the terminal restored for the shell meanwhile
+#[cfg(unix)]
+fn suspend_to_shell(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
+    suspend_terminal(terminal)?;
+    println!("Suspended - type `fg` to return to the IDE");
+    // Our SIGTSTP handler replaced the default stop, so stop explicitly; this returns on SIGCONT
+    signal_hook::low_level::emulate_default_handler(signal_hook::consts::SIGTSTP)?;
+    resume_terminal(terminal)
+}
+
+/// Run a shell command in the foreground with the terminal released, restoring it afterwards
+fn run_external(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    command: &str,
+) -> Result<std::process::ExitStatus> {
+    suspend_terminal(terminal)?;
+    let status = std::process::Command::new("sh").arg("-c").arg(command).status();
+    resume_terminal(terminal)?;
+    Ok(status?)
+}
\ No newline at end of file
@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A formatter still running after this long is killed and the file saved unformatted
+const FORMATTER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Steps applied to a buffer right before it is written to disk
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SaveHooks {
+    #[serde(default)]
+    pub trim_trailing_whitespace: bool,
+    #[serde(default)]
+    pub ensure_final_newline: bool, // Exactly one newline at the end of the file
+    #[serde(default)]
+    pub formatters: HashMap<String, String>, // Extension -> command filtering stdin to stdout, e.g. "rs": "rustfmt"
+}
+
+impl SaveHooks {
+    /// Run the built-in steps on the buffer's lines; returns the new trailing-newline flag
+    pub fn clean(&self, lines: &mut Vec<String>, trailing_newline: bool) -> bool {
+        clean_lines(lines, trailing_newline, self.trim_trailing_whitespace, self.ensure_final_newline)
+    }
+
+    /// Formatter command for the file, with `{}` replaced by its quoted path
+    pub fn formatter_for(&self, path: &Path) -> Option<String> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        self.formatter_for_key(&extension, path)
+    }
+
+    /// Like `formatter_for`, for a file whose type was set by hand (see `language::Language::settings_key`)
+    pub fn formatter_for_key(&self, key: &str, path: &Path) -> Option<String> {
+        let command = self.formatters.get(key)?.trim();
+        if command.is_empty() {
+            return None;
+        }
+        Some(command.replace("{}", &super::opener::shell_quote(&path.to_string_lossy())))
+    }
+}
+
+/// `SaveHooks::clean` with the two steps chosen per file (see `filetype::FileSettings`)
+pub fn clean_lines(lines: &mut Vec<String>, trailing_newline: bool, trim_trailing_whitespace: bool, ensure_final_newline: bool) -> bool {
+    if trim_trailing_whitespace {
+        for line in lines.iter_mut() {
+            line.truncate(line.trim_end().len());
+        }
+    }
+    if !ensure_final_newline {
+        return trailing_newline;
+    }
+    while lines.len() > 1 && lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    // An empty file stays empty rather than becoming a lone newline
+    lines.iter().any(|line| !line.is_empty())
+}
+
+/// Pipe `text` through a formatter command and return what it printed; one that takes longer
+/// than `FORMATTER_TIMEOUT` is killed
+pub fn run_formatter(command: &str, text: &str) -> Result<String> {
+    use std::io::{Read, Write};
+    use std::process::{Command, Stdio};
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin and drain the output on other threads, so a large file can't deadlock on a full pipe
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("Failed to open stdin"))?;
+    let input = text.to_string();
+    std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        std::thread::spawn(move || {
+            let mut output = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut output);
+            }
+            output
+        })
+    };
+    let stdout = drain(child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+    let stderr = drain(child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>));
+
+    let deadline = Instant::now() + FORMATTER_TIMEOUT;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(anyhow!("`{}` took longer than {}s", command, FORMATTER_TIMEOUT.as_secs()));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let stdout = stdout.join().map_err(|_| anyhow!("Failed to read the formatter's output"))?;
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        let reason = stderr.lines().next().unwrap_or("").trim();
+        return Err(anyhow!("`{}` exited with {} {}", command, status, reason));
+    }
+    Ok(String::from_utf8(stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_hooks() {
+        let hooks = SaveHooks { trim_trailing_whitespace: true, ensure_final_newline: true, ..SaveHooks::default() };
+        let mut lines: Vec<String> = ["fn main() {  ", "\tok();\t", "}", "", ""].iter().map(|s| s.to_string()).collect();
+        assert!(hooks.clean(&mut lines, false));
+        assert_eq!(lines, ["fn main() {", "\tok();", "}"]);
+
+        let keep = SaveHooks::default();
+        let mut lines = vec!["x ".to_string(), String::new()];
+        assert!(!keep.clean(&mut lines, false));
+        assert_eq!(lines, ["x ", ""]);
+
+        let mut empty = vec![String::new()];
+        assert!(!hooks.clean(&mut empty, false));
+
+        let mut with_formatter = SaveHooks::default();
+        with_formatter.formatters.insert("js".to_string(), "prettier --stdin-filepath {}".to_string());
+        assert_eq!(
+            with_formatter.formatter_for(Path::new("src/app.js")).as_deref(),
+            Some("prettier --stdin-filepath 'src/app.js'")
+        );
+        assert_eq!(with_formatter.formatter_for(Path::new("main.rs")), None);
+
+        assert_eq!(run_formatter("tr a-z A-Z", "abc\n").unwrap(), "ABC\n");
+        assert!(run_formatter("exit 3", "abc").is_err());
+    }
+}
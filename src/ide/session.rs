@@ -0,0 +1,157 @@
+use crate::ide::editor::Editor;
+use crate::ide::language;
+use crate::ide::marks::Marks;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTab {
+    pub path: PathBuf,
+    pub cursor_line: usize,
+    pub cursor_col: usize,
+    pub scroll_offset: usize,
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub language: Option<String>, // Language id picked by hand, if any
+}
+
+/// Open tabs and layout of a project, saved on quit and reopened on the next launch in that directory
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkspaceSession {
+    pub tabs: Vec<SessionTab>,
+    pub active_tab: usize,
+    pub sidebar_width: u16,
+    #[serde(default)]
+    pub threads: Vec<String>, // Saved session of each chat thread, in order
+    #[serde(default)]
+    pub active_thread: usize,
+    #[serde(default)]
+    pub marks: Marks,
+}
+
+impl WorkspaceSession {
+    /// Session file for a project directory (one per directory, under the cache dir)
+    pub fn path_for(project_dir: &Path) -> Result<PathBuf> {
+        project_file(project_dir, "json")
+    }
+
+    /// Where the chat is kept when the terminal hangs up, until the next launch picks it up
+    pub fn conversation_path_for(project_dir: &Path) -> Result<PathBuf> {
+        project_file(project_dir, "conversation.json")
+    }
+
+    /// Tabs backed by a file; untitled buffers have nothing to reopen
+    pub fn capture(editor: &Editor, sidebar_width: u16) -> Self {
+        let mut active_tab = 0;
+        let mut tabs = Vec::new();
+        for (index, tab) in editor.tabs.iter().enumerate() {
+            let Some(path) = &tab.file_path else {
+                continue;
+            };
+            if index == editor.active_tab {
+                active_tab = tabs.len();
+            }
+            tabs.push(SessionTab {
+                path: path.clone(),
+                cursor_line: tab.cursor_line,
+                cursor_col: tab.cursor_col,
+                scroll_offset: tab.scroll_offset,
+                pinned: tab.pinned,
+                language: tab.language_override.map(str::to_string),
+            });
+        }
+        Self { tabs, active_tab, sidebar_width, threads: Vec::new(), active_thread: 0, marks: Marks::default() }
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reopen the saved tabs in `editor`; returns how many could be opened (deleted files are skipped)
+    pub fn restore(&self, editor: &mut Editor) -> usize {
+        let mut active_tab = None;
+        let mut opened = 0;
+        for (index, saved) in self.tabs.iter().enumerate() {
+            if editor.open_file(saved.path.clone()).is_err() {
+                continue;
+            }
+            opened += 1;
+            if index == self.active_tab {
+                active_tab = Some(editor.active_tab);
+            }
+            let Some(tab) = editor.get_current_tab_mut() else {
+                continue;
+            };
+            tab.language_override = saved.language.as_deref().and_then(language::by_id).map(|language| language.id);
+            tab.move_to(saved.cursor_line, saved.cursor_col);
+            tab.scroll_offset = saved.scroll_offset.min(tab.cursor_line);
+            tab.pinned = saved.pinned;
+        }
+        if let Some(index) = active_tab {
+            editor.switch_to_tab(index);
+        }
+        opened
+    }
+}
+
+fn project_file(project_dir: &Path, extension: &str) -> Result<PathBuf> {
+    let project = project_dir.canonicalize().unwrap_or_else(|_| project_dir.to_path_buf());
+    let mut hasher = DefaultHasher::new();
+    project.hash(&mut hasher);
+    Ok(dirs::cache_dir()
+        .ok_or_else(|| anyhow!("Could not find cache directory"))?
+        .join("i4z_ide")
+        .join("sessions")
+        .join(format!("{:016x}.{}", hasher.finish(), extension)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = std::env::temp_dir().join(format!("i4z-session-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let (main, lib) = (dir.join("main.rs"), dir.join("lib.rs"));
+        fs::write(&main, "fn main() {\n    run();\n}\n").unwrap();
+        fs::write(&lib, "pub fn run() {}\n").unwrap();
+
+        let mut editor = Editor::new();
+        editor.open_file(main.clone()).unwrap();
+        editor.move_cursor_to(1, 4);
+        editor.open_file(lib.clone()).unwrap();
+        editor.tabs[1].language_override = Some("python");
+        editor.toggle_pin(1);
+        editor.new_file(); // Untitled, not saved in the session
+        editor.switch_to_tab(1);
+
+        let session = WorkspaceSession::capture(&editor, 42);
+        let file = dir.join("session.json");
+        session.save(&file).unwrap();
+        let loaded = WorkspaceSession::load(&file).unwrap();
+        assert_eq!(loaded, session);
+        assert_eq!(loaded.tabs.len(), 2);
+
+        fs::remove_file(&lib).unwrap();
+        let mut restored = Editor::new();
+        assert_eq!(loaded.restore(&mut restored), 1);
+        assert_eq!(restored.get_cursor_position(), (2, 5));
+        assert_eq!(restored.active_tab, 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+use crate::sessions::SavedSession;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+/// Overlay listing saved conversations, most recent first, to reopen one
+#[derive(Default)]
+pub struct SessionBrowser {
+    pub is_open: bool,
+    sessions: Vec<SavedSession>,
+    state: ListState,
+}
+
+impl SessionBrowser {
+    pub fn open(&mut self, sessions: Vec<SavedSession>) {
+        self.state.select((!sessions.is_empty()).then_some(0));
+        self.sessions = sessions;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.sessions.clear();
+        self.is_open = false;
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if let Some(selected) = self.state.selected() {
+            self.state.select(Some(selected.saturating_add_signed(delta).min(self.sessions.len() - 1)));
+        }
+    }
+
+    /// Take the selected session and close
+    pub fn take_selected(&mut self) -> Option<SavedSession> {
+        let selected = self.state.selected().filter(|&index| index < self.sessions.len())?;
+        let session = self.sessions.swap_remove(selected);
+        self.close();
+        Some(session)
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, current_id: &str) {
+        frame.render_widget(Clear, area);
+        let items: Vec<ListItem> = if self.sessions.is_empty() {
+            vec![ListItem::new(Span::styled(" No saved conversations yet", Style::default().fg(Color::DarkGray)))]
+        } else {
+            self.sessions
+                .iter()
+                .map(|session| {
+                    let marker = if session.id == current_id { "● " } else { "  " };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(marker, Style::default().fg(Color::Green)),
+                        Span::styled(session.summary(), Style::default().fg(Color::Gray)),
+                    ]))
+                })
+                .collect()
+        };
+        let list = List::new(items)
+            .block(Block::default()
+                .title(" 💬 Sessions (↑/↓: select, Enter: resume, Esc: close) ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)))
+            .highlight_style(Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD));
+        frame.render_stateful_widget(list, area, &mut self.state);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::ide::autosave::is_process_alive;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub session: String, // Distinguishes instances even when a PID is reused
+    pub started_at: chrono::DateTime<chrono::Local>,
+}
+
+/// One editing instance per project directory, recorded in a lockfile with the owner's PID
+pub struct SessionLock {
+    path: PathBuf,
+    session: String,
+    owned: bool,
+}
+
+impl SessionLock {
+    pub fn new(project_dir: &Path, session: Uuid) -> Result<Self> {
+        let project = project_dir.canonicalize().unwrap_or_else(|_| project_dir.to_path_buf());
+        let mut hasher = DefaultHasher::new();
+        project.hash(&mut hasher);
+        let lock_dir = dirs::cache_dir()
+            .ok_or_else(|| anyhow!("Could not find cache directory"))?
+            .join("i4z_ide")
+            .join("locks");
+        Ok(Self::at(lock_dir.join(format!("{:016x}.lock", hasher.finish())), session.to_string()))
+    }
+
+    fn at(path: PathBuf, session: String) -> Self {
+        Self { path, session, owned: false }
+    }
+
+    /// Take the lock unless a running instance holds it; returns that instance otherwise
+    pub fn try_acquire(&mut self) -> Result<Option<LockHolder>> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // The lock appears with its holder already written, so no instance ever reads it empty
+        let written = self.write_temp()?;
+        let linked = fs::hard_link(&written, &self.path);
+        let _ = fs::remove_file(&written);
+        match linked {
+            Ok(()) => {
+                self.owned = true;
+                Ok(None)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => match self.read_holder() {
+                Some(holder) if holder.session != self.session && is_process_alive(holder.pid) => Ok(Some(holder)),
+                // Left behind by a crashed instance (or unreadable): reclaim it
+                _ => self.take_over().map(|()| None),
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Claim the lock from another instance, which drops to read-only when it notices
+    pub fn take_over(&mut self) -> Result<()> {
+        let written = self.write_temp()?;
+        if let Err(e) = fs::rename(&written, &self.path) {
+            let _ = fs::remove_file(&written);
+            return Err(e.into());
+        }
+        self.owned = true;
+        Ok(())
+    }
+
+    /// Our holder record in a file of its own next to the lock, to move into place in one step
+    fn write_temp(&self) -> Result<PathBuf> {
+        let path = self.path.with_extension(format!("{}.tmp", self.session));
+        let mut file = fs::File::create(&path)?;
+        file.write_all(serde_json::to_string(&self.holder())?.as_bytes())?;
+        file.sync_all()?;
+        Ok(path)
+    }
+
+    /// Whether another instance has taken over the lock since we acquired it
+    pub fn is_lost(&self) -> bool {
+        self.owned && self.read_holder().is_none_or(|holder| holder.session != self.session)
+    }
+
+    /// Give the lock up without deleting it, e.g. after another instance took over
+    pub fn disown(&mut self) {
+        self.owned = false;
+    }
+
+    /// Delete the lock if we hold it, leaving the project free for another instance
+    pub fn release(&mut self) {
+        if self.owned && !self.is_lost() {
+            let _ = fs::remove_file(&self.path);
+        }
+        self.owned = false;
+    }
+
+    fn holder(&self) -> LockHolder {
+        LockHolder {
+            pid: std::process::id(),
+            session: self.session.clone(),
+            started_at: chrono::Local::now(),
+        }
+    }
+
+    fn read_holder(&self) -> Option<LockHolder> {
+        serde_json::from_str(&fs::read_to_string(&self.path).ok()?).ok()
+    }
+}
+
+impl Drop for SessionLock {
+    fn drop(&mut self) {
+        if self.owned && !self.is_lost() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_lock() {
+        let path = std::env::temp_dir().join(format!("i4z-lock-test-{}.lock", Uuid::new_v4()));
+
+        let mut first = SessionLock::at(path.clone(), "first".to_string());
+        assert!(first.try_acquire().unwrap().is_none());
+        assert!(!first.is_lost());
+
+        let mut second = SessionLock::at(path.clone(), "second".to_string());
+        let holder = second.try_acquire().unwrap().expect("lock is held by the first instance");
+        assert_eq!(holder.session, first.session);
+
+        second.take_over().unwrap();
+        assert!(first.is_lost());
+        first.disown();
+        drop(first);
+        assert!(path.exists());
+
+        drop(second);
+        assert!(!path.exists());
+        // The holder records written on the way are gone too
+        assert!(!path.with_extension("first.tmp").exists() && !path.with_extension("second.tmp").exists());
+    }
+}
@@ -0,0 +1,235 @@
+use crate::config::Config;
+use crate::config_layers::get_dotted;
+use anyhow::{anyhow, Result};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use toml::Value;
+
+/// How a setting is edited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SettingKind {
+    Toggle,                          // Enter flips it
+    Choice(&'static [&'static str]), // Enter moves to the next value
+    Theme,                           // Enter moves to the next built-in or user theme
+    Number,
+    Text, // Cleared by leaving it empty
+    List, // Comma-separated
+}
+
+/// One row of the settings panel
+#[derive(Debug, Clone, Copy)]
+pub struct Setting {
+    pub category: &'static str,
+    pub key: &'static str, // Dotted config key, as with `--set`
+    pub label: &'static str,
+    pub kind: SettingKind,
+}
+
+const fn setting(category: &'static str, key: &'static str, label: &'static str, kind: SettingKind) -> Setting {
+    Setting { category, key, label, kind }
+}
+
+/// The settings the panel lists, grouped by category in display order
+pub const SETTINGS: &[Setting] = &[
+    setting("Model", "provider", "Provider", SettingKind::Choice(&["groq", "open_ai", "anthropic", "ollama"])),
+    setting("Model", "default_model", "Model", SettingKind::Text),
+    setting("Model", "temperature", "Temperature", SettingKind::Number),
+    setting("Model", "max_tokens", "Max tokens", SettingKind::Number),
+    setting("Model", "stream_responses", "Stream answers", SettingKind::Toggle),
+    setting("Model", "history.max_tokens", "History tokens", SettingKind::Number),
+    setting("Model", "history.summarize", "Summarize old turns", SettingKind::Toggle),
+    setting("Model", "api_base_url", "API base URL", SettingKind::Text),
+    setting("Appearance", "theme", "Theme", SettingKind::Theme),
+    setting("Appearance", "chat_height", "Chat height", SettingKind::Number),
+    setting("Appearance", "gutter.line_numbers", "Line numbers", SettingKind::Choice(&["absolute", "relative", "hybrid", "off"])),
+    setting("Appearance", "show_ruler", "Show ruler", SettingKind::Toggle),
+    setting("Appearance", "ruler", "Ruler column", SettingKind::Number),
+    setting("Appearance", "show_whitespace", "Show whitespace", SettingKind::Toggle),
+    setting("Explorer", "explorer.ignore", "Ignore globs", SettingKind::List),
+    setting("Explorer", "explorer.show_hidden", "Show hidden files", SettingKind::Toggle),
+    setting("Explorer", "explorer.use_gitignore", "Use .gitignore", SettingKind::Toggle),
+    setting("Editor", "indent.tab_width", "Tab width", SettingKind::Number),
+    setting("Editor", "indent.style", "Indent with", SettingKind::Choice(&["spaces", "tabs"])),
+    setting("Editor", "save_hooks.trim_trailing_whitespace", "Trim trailing whitespace", SettingKind::Toggle),
+    setting("Editor", "save_hooks.ensure_final_newline", "Ensure final newline", SettingKind::Toggle),
+    setting("Editor", "autosave_interval_secs", "Autosave every (secs)", SettingKind::Number),
+    setting("Editor", "restore_session", "Restore last tabs", SettingKind::Toggle),
+    setting("Agent", "dry_run", "Dry run", SettingKind::Toggle),
+    setting("Agent", "agent_sandbox", "Stay in workspace", SettingKind::Toggle),
+    setting("Agent", "strict_json_actions", "JSON actions", SettingKind::Toggle),
+    setting("Agent", "low_bandwidth", "Low bandwidth", SettingKind::Toggle),
+    setting("Agent", "response_language", "Answer in", SettingKind::Text),
+    setting("Network", "http.timeout_secs", "Timeout (secs)", SettingKind::Number),
+    setting("Network", "http.connect_timeout_secs", "Connect timeout (secs)", SettingKind::Number),
+    setting("Network", "https_proxy", "HTTPS proxy", SettingKind::Text),
+];
+
+impl Setting {
+    /// The current value as shown in the panel and as the starting text of an edit
+    pub fn display(&self, value: Option<&Value>) -> String {
+        match value {
+            None => String::new(),
+            Some(Value::String(text)) => text.clone(),
+            Some(Value::Boolean(on)) => if *on { "on" } else { "off" }.to_string(),
+            Some(Value::Float(number)) => format!("{}", (*number * 100.0).round() / 100.0),
+            Some(Value::Array(items)) => items.iter().map(|item| self.display(Some(item))).collect::<Vec<_>>().join(", "),
+            Some(value) => value.to_string(),
+        }
+    }
+
+    /// The value typed for a Number, Text or List setting; None clears it
+    pub fn parse(&self, input: &str) -> Result<Option<Value>> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        let value = match self.kind {
+            SettingKind::Number => match input.parse::<i64>() {
+                Ok(number) => Value::Integer(number),
+                Err(_) => Value::Float(input.parse::<f64>().map_err(|_| anyhow!("`{}` is not a number", input))?),
+            },
+            SettingKind::List => Value::Array(
+                input.split(',').map(str::trim).filter(|item| !item.is_empty()).map(|item| Value::String(item.to_string())).collect(),
+            ),
+            _ => Value::String(input.to_string()),
+        };
+        Ok(Some(value))
+    }
+
+    /// The value after `current` for settings changed with Enter; None for those that are typed
+    pub fn next(&self, current: Option<&Value>, config: &Config) -> Option<Value> {
+        match self.kind {
+            SettingKind::Toggle => Some(Value::Boolean(!current.and_then(Value::as_bool).unwrap_or(false))),
+            SettingKind::Choice(choices) => {
+                let index = choices.iter().position(|choice| Some(*choice) == current.and_then(Value::as_str));
+                let next = index.map_or(0, |index| (index + 1) % choices.len());
+                Some(Value::String(choices[next].to_string()))
+            }
+            SettingKind::Theme => Some(Value::String(config.theme.next(&config.themes).into())),
+            SettingKind::Number | SettingKind::Text | SettingKind::List => None,
+        }
+    }
+}
+
+/// Overlay listing the settings by category, changed in place and applied at once
+#[derive(Default)]
+pub struct SettingsPanel {
+    pub is_open: bool,
+    pub selected: usize,
+    pub editing: Option<String>, // Text typed for the selected setting
+    scroll: usize,
+}
+
+impl SettingsPanel {
+    pub fn open(&mut self) {
+        self.editing = None;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.editing = None;
+        self.is_open = false;
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        self.selected = self.selected.saturating_add_signed(delta).min(SETTINGS.len() - 1);
+    }
+
+    pub fn selected_setting(&self) -> &'static Setting {
+        &SETTINGS[self.selected]
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, config: &Config) {
+        frame.render_widget(Clear, area);
+        let mut lines = Vec::new();
+        let mut selected_line = 0;
+        let values = toml::Table::try_from(config).unwrap_or_default();
+        for (index, setting) in SETTINGS.iter().enumerate() {
+            if index == 0 || SETTINGS[index - 1].category != setting.category {
+                if index > 0 {
+                    lines.push(Line::from(""));
+                }
+                lines.push(Line::from(Span::styled(
+                    format!(" {}", setting.category),
+                    Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                )));
+            }
+            let overridden = config.is_overridden(setting.key);
+            let current = setting.display(get_dotted(&values, setting.key));
+            let value = match (&self.editing, index == self.selected) {
+                (Some(text), true) => format!("{}▏", text),
+                _ if overridden => format!("{}  (project, environment or --set)", current),
+                _ => current,
+            };
+            let style = if index == self.selected {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else if overridden {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            if index == self.selected {
+                selected_line = lines.len();
+            }
+            lines.push(Line::from(vec![
+                Span::styled(format!("   {:<26}", setting.label), style.add_modifier(Modifier::BOLD)),
+                Span::styled(value, style),
+            ]));
+        }
+
+        // Keep the selection in view
+        let height = area.height.saturating_sub(2) as usize;
+        if selected_line < self.scroll {
+            self.scroll = selected_line.saturating_sub(1);
+        } else if selected_line >= self.scroll + height {
+            self.scroll = selected_line + 1 - height;
+        }
+
+        let title = if self.editing.is_some() {
+            " ⚙️ Settings (Enter: apply, empty clears, Esc: cancel) "
+        } else {
+            " ⚙️ Settings (↑/↓: select, Enter: change, Esc: close) "
+        };
+        let view = Paragraph::new(lines)
+            .scroll((self.scroll as u16, 0))
+            .block(Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)));
+        frame.render_widget(view, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_edit_config() {
+        let config = Config::default();
+        for setting in SETTINGS {
+            let value = config.setting(setting.key);
+            if let Some(next) = setting.next(value.as_ref(), &config) {
+                assert!(config.with_setting(setting.key, Some(next)).is_ok(), "{}", setting.key);
+            }
+        }
+
+        let ignore = SETTINGS.iter().find(|setting| setting.key == "explorer.ignore").unwrap();
+        let changed = config.with_setting(ignore.key, ignore.parse("target, *.log").unwrap()).unwrap();
+        assert_eq!(changed.explorer.ignore, vec!["target", "*.log"]);
+        assert_eq!(ignore.display(changed.setting(ignore.key).as_ref()), "target, *.log");
+
+        let temperature = SETTINGS.iter().find(|setting| setting.key == "temperature").unwrap();
+        assert_eq!(config.with_setting(temperature.key, temperature.parse("1").unwrap()).unwrap().temperature, 1.0);
+        assert!(temperature.parse("warm").is_err());
+        assert!(config.with_setting("chat_height", Some(Value::String("tall".to_string()))).is_err());
+
+        let proxy = config.with_setting("https_proxy", Some(Value::String("http://proxy:3128".to_string()))).unwrap();
+        assert_eq!(proxy.with_setting("https_proxy", None).unwrap().https_proxy, None);
+    }
+}
@@ -1,7 +1,9 @@
 pub mod file_explorer;
 pub mod chat;
+pub mod completion;
 pub mod notifications;
 
+use crate::ide::explorer_filter::ExplorerFilter;
 use anyhow::Result;
 use std::path::Path;
 
@@ -12,8 +14,8 @@ pub struct Sidebar {
 }
 
 impl Sidebar {
-    pub fn new(root_path: &Path) -> Result<Self> {
-        let file_explorer = file_explorer::FileExplorer::new(root_path)?;
+    pub fn new(root_path: &Path, filter: ExplorerFilter) -> Result<Self> {
+        let file_explorer = file_explorer::FileExplorer::new(root_path, filter)?;
         let chat = chat::Chat::new();
         let notifications = notifications::NotificationPanel::new();
         
@@ -5,7 +5,10 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
     Frame,
 };
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, Utc};
+use super::completion::Completion;
+use crate::ide::theme;
+use crate::postprocess;
 
 #[derive(Debug, Clone)]
 pub enum MessageType {
@@ -19,6 +22,9 @@ pub struct ChatMessage {
     pub message_type: MessageType,
     pub content: String,
     pub timestamp: DateTime<Local>,
+    pub request_id: Option<u64>, // The request whose progress or answer so far this shows
+    pub full_content: Option<String>, // The whole answer while `content` shows only its start
+    pub sent_at: Option<DateTime<Utc>>, // When a question went into the conversation, to find it there again for editing
 }
 
 impl ChatMessage {
@@ -27,22 +33,20 @@ impl ChatMessage {
             message_type,
             content,
             timestamp: Local::now(),
+            request_id: None,
+            full_content: None,
+            sent_at: None,
         }
     }
 
     pub fn to_list_item(&self) -> ListItem {
-        let (prefix, style) = match self.message_type {
-            MessageType::User => ("🧑", Style::default().fg(Color::Green)),
-            MessageType::Assistant => ("🤖", Style::default().fg(Color::Cyan)),
-            MessageType::System => ("ℹ️", Style::default().fg(Color::Yellow)),
+        let style = match self.message_type {
+            MessageType::User => Style::default().fg(Color::Green),
+            MessageType::Assistant => Style::default().fg(Color::Cyan),
+            MessageType::System => Style::default().fg(Color::Yellow),
         };
 
-        let time_str = self.timestamp.format("%H:%M").to_string();
-        let display_text = format!("{} [{}] {}", prefix, time_str, self.content);
-        
-        // Wrap long messages
-        let wrapped_lines = wrap_text(&display_text, 25); // Approximate width for sidebar
-        let lines: Vec<Line> = wrapped_lines
+        let lines: Vec<Line> = self.wrapped_lines()
             .into_iter()
             .enumerate()
             .map(|(i, line)| {
@@ -57,13 +61,49 @@ impl ChatMessage {
 
         ListItem::new(lines)
     }
+
+    /// Show only the start of a long message until it is clicked
+    fn collapse(&mut self, max_chars: usize) {
+        if let Some(head) = postprocess::preview(&self.content, max_chars) {
+            let hidden = self.content.chars().count() - head.chars().count();
+            let head = format!("{}\n… [{} more characters - click to show all]", head, hidden);
+            self.full_content = Some(std::mem::replace(&mut self.content, head));
+        }
+    }
+
+    /// The whole message, also while it is collapsed
+    pub fn text(&self) -> &str {
+        self.full_content.as_deref().unwrap_or(&self.content)
+    }
+
+    fn wrapped_lines(&self) -> Vec<String> {
+        let prefix = match self.message_type {
+            MessageType::User => "🧑",
+            MessageType::Assistant => "🤖",
+            MessageType::System => "ℹ️",
+        };
+        let time_str = self.timestamp.format("%H:%M").to_string();
+        let display_text = format!("{} [{}] {}", prefix, time_str, self.content);
+        // Wrap long messages
+        wrap_text(&display_text, 25) // Approximate width for sidebar
+    }
 }
 
 pub struct Chat {
     pub messages: Vec<ChatMessage>,
     pub input: String,
+    pub input_cursor: usize, // Byte offset of the cursor in `input`
     pub scroll_offset: usize,
     pub list_state: ListState,
+    pub completion: Option<Completion>, // `@file` / `#symbol` suggestions for the word being typed
+    pub max_display_chars: usize, // Longer answers are collapsed; 0 shows them whole
+    pub editing: Option<(usize, String)>, // Earlier question being edited in the input, and the draft it replaced
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Chat {
@@ -76,8 +116,12 @@ impl Chat {
                 ChatMessage::new(MessageType::System, "Welcome! Ask me anything about your code.".to_string())
             ],
             input: String::new(),
+            input_cursor: 0,
             scroll_offset: 0,
             list_state,
+            completion: None,
+            max_display_chars: 0,
+            editing: None,
         }
     }
 
@@ -87,7 +131,9 @@ impl Chat {
     }
 
     pub fn add_ai_message(&mut self, content: &str) {
-        self.messages.push(ChatMessage::new(MessageType::Assistant, content.to_string()));
+        let mut message = ChatMessage::new(MessageType::Assistant, content.to_string());
+        message.collapse(self.max_display_chars);
+        self.messages.push(message);
         self.scroll_to_bottom();
     }
 
@@ -96,11 +142,130 @@ impl Chat {
         self.scroll_to_bottom();
     }
 
+    /// Tie the latest question to its message in the conversation, which makes it editable
+    pub fn link_last_user_message(&mut self, sent_at: Option<DateTime<Utc>>) {
+        if let Some(message) = self.messages.iter_mut().rev().find(|msg| matches!(msg.message_type, MessageType::User)) {
+            message.sent_at = sent_at;
+        }
+    }
+
+    /// Put the editable question before the one being edited, or the latest, into the input;
+    /// returns whether there was one
+    pub fn edit_previous(&mut self) -> bool {
+        let end = self.editing.as_ref().map_or(self.messages.len(), |(index, _)| *index);
+        let Some(index) = self.messages[..end].iter().rposition(|msg| msg.sent_at.is_some()) else {
+            return false;
+        };
+        let draft = match self.editing.take() {
+            Some((_, draft)) => draft,
+            None => std::mem::take(&mut self.input),
+        };
+        self.input = self.messages[index].text().to_string();
+        self.input_cursor = self.input.len();
+        self.completion = None;
+        self.editing = Some((index, draft));
+        true
+    }
+
+    /// Stop editing an earlier question and bring back what was typed before
+    pub fn cancel_edit(&mut self) {
+        if let Some((_, draft)) = self.editing.take() {
+            self.input_cursor = draft.len();
+            self.input = draft;
+            self.completion = None;
+        }
+    }
+
+    /// Drop the message at `index` and everything after it
+    pub fn truncate(&mut self, index: usize) {
+        self.messages.truncate(index);
+        self.scroll_to_bottom();
+    }
+
+    pub fn last_ai_message(&self) -> Option<&ChatMessage> {
+        self.messages
+            .iter()
+            .rev()
+            .find(|msg| matches!(msg.message_type, MessageType::Assistant))
+    }
+
+    /// Message drawn at screen row `y` when the chat was drawn in `area`
+    pub fn message_at(&self, area: Rect, y: u16) -> Option<&ChatMessage> {
+        self.index_at(area, y).map(|index| &self.messages[index])
+    }
+
+    /// Show the whole of the collapsed message at screen row `y`; returns whether there was one
+    pub fn expand_at(&mut self, area: Rect, y: u16) -> bool {
+        let Some(message) = self.index_at(area, y).map(|index| &mut self.messages[index]) else {
+            return false;
+        };
+        match message.full_content.take() {
+            Some(full) => {
+                message.content = full;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn index_at(&self, area: Rect, y: u16) -> Option<usize> {
+        // Rows inside the message list's border, above the input box
+        let list_bottom = area.bottom().saturating_sub(self.input_height() + 1);
+        if y <= area.y || y >= list_bottom {
+            return None;
+        }
+        let row = (y - area.y - 1) as usize;
+        let mut first_row = 0;
+        // Same order and limit as `draw_messages`
+        let newest_first = self.messages.iter().enumerate().rev().take(20);
+        newest_first
+            .into_iter()
+            .find(|(_, msg)| {
+                first_row += msg.wrapped_lines().len();
+                row < first_row
+            })
+            .map(|(index, _)| index)
+    }
+
     pub fn remove_last_message(&mut self) {
         self.messages.pop();
     }
 
+    /// Show `content` in the message of request `id`, in place, or as a new message if it has none yet
+    pub fn set_request_message(&mut self, id: u64, message_type: MessageType, content: &str) {
+        match self.messages.iter_mut().find(|msg| msg.request_id == Some(id)) {
+            Some(msg) => {
+                msg.message_type = message_type;
+                msg.content = content.to_string();
+            }
+            None => {
+                let mut message = ChatMessage::new(message_type, content.to_string());
+                message.request_id = Some(id);
+                self.messages.push(message);
+                self.scroll_to_bottom();
+            }
+        }
+    }
+
+    /// Remove the message of request `id`; returns whether there was one
+    pub fn remove_request_message(&mut self, id: u64) -> bool {
+        let count = self.messages.len();
+        self.messages.retain(|msg| msg.request_id != Some(id));
+        self.messages.len() != count
+    }
+
+    /// Keep the message of request `id` as an ordinary one, so later updates don't touch it
+    pub fn finish_request_message(&mut self, id: u64) {
+        if let Some(msg) = self.messages.iter_mut().find(|msg| msg.request_id == Some(id)) {
+            msg.request_id = None;
+            if matches!(msg.message_type, MessageType::Assistant) {
+                msg.collapse(self.max_display_chars);
+            }
+        }
+    }
+
     pub fn clear(&mut self) {
+        self.editing = None;
         self.messages.clear();
         self.messages.push(ChatMessage::new(MessageType::System, "Chat cleared.".to_string()));
         self.scroll_offset = 0;
@@ -123,34 +288,149 @@ impl Chat {
     }
 
     pub fn add_char(&mut self, c: char) {
-        self.input.push(c);
+        let before = &self.input[..self.input_cursor];
+        let next = self.input[self.input_cursor..].chars().next();
+
+        // Typing a closer that is already there just steps over it
+        if matches!(c, ')' | ']' | '}' | '"' | '\'' | '`') && next == Some(c) {
+            self.input_cursor += c.len_utf8();
+            return;
+        }
+
+        // A third backtick opens a fenced code block
+        if c == '`' && before.ends_with("``") {
+            self.insert_str("`\n");
+            let cursor = self.input_cursor;
+            self.insert_str("\n```");
+            self.input_cursor = cursor;
+            return;
+        }
+
+        let closer = match c {
+            '(' => Some(')'),
+            '[' => Some(']'),
+            '{' => Some('}'),
+            '`' => Some('`'),
+            // Only pair quotes at word boundaries so apostrophes ("don't") stay single
+            '"' | '\'' if !before.chars().last().is_some_and(|p| p.is_alphanumeric()) => Some(c),
+            _ => None,
+        };
+
+        self.insert_str(&c.to_string());
+        if let Some(closer) = closer {
+            self.input.insert(self.input_cursor, closer);
+        }
+    }
+
+    /// Replace the input with `text`, the cursor at its end
+    pub fn set_input(&mut self, text: &str) {
+        self.input = text.to_string();
+        self.input_cursor = self.input.len();
+        self.completion = None;
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        self.input.insert_str(self.input_cursor, text);
+        self.input_cursor += text.len();
+    }
+
+    /// Shift+Enter: start a new line in the input, keeping the current line's indentation
+    pub fn insert_newline(&mut self) {
+        let line_start = self.input[..self.input_cursor].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let indent: String = self.input[line_start..self.input_cursor]
+            .chars()
+            .take_while(|c| *c == ' ' || *c == '\t')
+            .collect();
+        self.insert_str(&format!("\n{}", indent));
     }
 
     pub fn backspace(&mut self) {
-        self.input.pop();
+        let Some(prev) = self.input[..self.input_cursor].chars().last() else {
+            return;
+        };
+        let prev_start = self.input_cursor - prev.len_utf8();
+
+        // Deleting the opener of an empty pair removes the closer too
+        let next = self.input[self.input_cursor..].chars().next();
+        let is_empty_pair = matches!(
+            (prev, next),
+            ('(', Some(')')) | ('[', Some(']')) | ('{', Some('}')) | ('"', Some('"')) | ('\'', Some('\'')) | ('`', Some('`'))
+        );
+        if is_empty_pair {
+            self.input.remove(self.input_cursor);
+        }
+
+        self.input.remove(prev_start);
+        self.input_cursor = prev_start;
+    }
+
+    pub fn move_input_cursor_left(&mut self) {
+        if let Some(prev) = self.input[..self.input_cursor].chars().last() {
+            self.input_cursor -= prev.len_utf8();
+        }
+    }
+
+    pub fn move_input_cursor_right(&mut self) {
+        if let Some(next) = self.input[self.input_cursor..].chars().next() {
+            self.input_cursor += next.len_utf8();
+        }
+    }
+
+    /// The `@` or `#` reference being typed at the cursor: (offset of the trigger, trigger, query)
+    pub fn completion_query(&self) -> Option<(usize, char, &str)> {
+        let before = &self.input[..self.input_cursor];
+        let start = before
+            .char_indices()
+            .rev()
+            .find(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        let token = &before[start..];
+        let trigger = token.chars().next().filter(|c| matches!(c, '@' | '#'))?;
+        Some((start, trigger, &token[1..]))
+    }
+
+    /// Replace the typed reference with the selected suggestion
+    pub fn accept_completion(&mut self) {
+        let Some(completion) = self.completion.take() else {
+            return;
+        };
+        let Some(item) = completion.selected_item() else {
+            return;
+        };
+        let text = format!("{} ", item.insert);
+        self.input.replace_range(completion.start..self.input_cursor, &text);
+        self.input_cursor = completion.start + text.len();
     }
 
     pub fn get_input_and_clear(&mut self) -> String {
+        self.completion = None;
         let input = self.input.clone();
         self.input.clear();
+        self.input_cursor = 0;
         input
     }
 
-    pub fn draw(&self, frame: &mut Frame, area: Rect, is_focused: bool) {
+    /// Height of the input box: grows with multi-line input, up to a limit
+    fn input_height(&self) -> u16 {
+        (self.input.lines().count().max(1) as u16 + self.input.ends_with('\n') as u16).min(6) + 2
+    }
+
+    /// Draw the chat; with more than one thread the header lists them, the active one highlighted
+    pub fn draw(&self, frame: &mut Frame, area: Rect, is_focused: bool, threads: &[&str], active_thread: usize) {
         // Split chat area: [Messages] [Input]
         let chat_chunks = ratatui::layout::Layout::default()
             .direction(ratatui::layout::Direction::Vertical)
             .constraints([
                 ratatui::layout::Constraint::Min(4),      // Messages area
-                ratatui::layout::Constraint::Length(3),   // Input area
+                ratatui::layout::Constraint::Length(self.input_height()),   // Input area
             ])
             .split(area);
 
-        self.draw_messages(frame, chat_chunks[0], is_focused);
+        self.draw_messages(frame, chat_chunks[0], is_focused, thread_header(threads, active_thread));
         self.draw_input(frame, chat_chunks[1], is_focused);
     }
 
-    fn draw_messages(&self, frame: &mut Frame, area: Rect, is_focused: bool) {
+    fn draw_messages(&self, frame: &mut Frame, area: Rect, is_focused: bool, title: Line) {
         let border_style = if is_focused {
             Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
         } else {
@@ -161,25 +441,33 @@ impl Chat {
             let empty_text = Paragraph::new("No messages yet...")
                 .style(Style::default().fg(Color::Gray))
                 .block(Block::default()
-                    .title(" 💬 AI Chat ")
+                    .title(title)
                     .borders(Borders::ALL)
+                    .border_type(theme::border_type(is_focused))
                     .border_style(border_style));
             frame.render_widget(empty_text, area);
             return;
         }
 
         // Show recent messages
+        let editing = self.editing.as_ref().map(|(index, _)| *index);
         let visible_messages: Vec<ListItem> = self.messages
             .iter()
+            .enumerate()
             .rev() // Show newest first
             .take(20) // Limit to recent messages
-            .map(|msg| msg.to_list_item())
+            .map(|(index, msg)| if editing == Some(index) {
+                msg.to_list_item().style(Style::default().bg(Color::DarkGray))
+            } else {
+                msg.to_list_item()
+            })
             .collect();
 
         let messages_list = List::new(visible_messages)
             .block(Block::default()
-                .title(" 💬 AI Chat ")
+                .title(title)
                 .borders(Borders::ALL)
+                .border_type(theme::border_type(is_focused))
                 .border_style(border_style));
 
         frame.render_widget(messages_list, area);
@@ -204,17 +492,154 @@ impl Chat {
             Style::default().fg(Color::White)
         };
 
+        // Keep the line with the cursor in view
+        let cursor_row = self.input[..self.input_cursor].matches('\n').count() as u16;
+        let visible_rows = area.height.saturating_sub(2);
+        let scroll = cursor_row.saturating_sub(visible_rows.saturating_sub(1));
+
         let input_widget = Paragraph::new(input_text)
             .style(input_style)
+            .scroll((scroll, 0))
             .block(Block::default()
-                .title(" Message (Enter: Send, Ctrl+I: Image) ")
+                .title(match self.editing {
+                    Some(_) => " Editing an earlier message (Enter: resend from here, Alt+E: older, Esc: cancel) ",
+                    None => " Message (Enter: Send, Shift+Enter: Newline) ",
+                })
                 .borders(Borders::ALL)
+                .border_type(theme::border_type(is_focused))
                 .border_style(border_style));
 
         frame.render_widget(input_widget, area);
+
+        if let Some(completion) = self.completion.as_ref().filter(|_| is_focused) {
+            completion.draw(frame, area);
+        }
+
+        if is_focused {
+            let line_start = self.input[..self.input_cursor].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let cursor_col = self.input[line_start..self.input_cursor].chars().count() as u16;
+            frame.set_cursor_position((
+                (area.x + 1 + cursor_col).min(area.right().saturating_sub(2)),
+                area.y + 1 + cursor_row - scroll,
+            ));
+        }
     }
 }
 
+/// " 💬 AI Chat ", or the thread names with the active one highlighted
+fn thread_header<'a>(threads: &[&'a str], active: usize) -> Line<'a> {
+    if threads.len() < 2 {
+        return Line::from(" 💬 AI Chat ");
+    }
+    let mut spans = vec![Span::raw(" 💬 ")];
+    for (index, name) in threads.iter().enumerate() {
+        if index > 0 {
+            spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+        }
+        spans.push(if index == active {
+            Span::styled(*name, Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD))
+        } else {
+            Span::styled(*name, Style::default().fg(Color::Gray))
+        });
+    }
+    spans.push(Span::raw(" "));
+    Line::from(spans)
+}
+
+/// Strip markdown formatting (fences, headings, emphasis, links) so an answer can be pasted anywhere
+pub fn to_plain_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            // Code is kept verbatim
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let mut text = trimmed;
+        if text.starts_with('#') {
+            text = text.trim_start_matches('#').trim_start();
+        }
+        while let Some(rest) = text.strip_prefix('>') {
+            text = rest.trim_start();
+        }
+        if let Some(rest) = text.strip_prefix("* ").or_else(|| text.strip_prefix("+ ")) {
+            // Normalise bullet markers so they survive emphasis stripping below
+            lines.push(format!("- {}", strip_inline_markdown(rest)));
+            continue;
+        }
+
+        let indent = &line[..line.len() - trimmed.len()];
+        lines.push(format!("{}{}", indent, strip_inline_markdown(text)));
+    }
+
+    lines.join("\n").trim().to_string()
+}
+
+fn strip_inline_markdown(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' | '_' | '`' | '~' => {
+                // Only strip markers that hug a word, so `a * b` and snake_case survive
+                let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+                let next = chars.get(i + 1).copied();
+                let spaced = prev.is_none_or(char::is_whitespace) && next.is_none_or(char::is_whitespace);
+                let inside_word = prev.is_some_and(char::is_alphanumeric) && next.is_some_and(char::is_alphanumeric);
+                let is_marker = chars[i] == '`' || (!spaced && !inside_word);
+                if !is_marker {
+                    result.push(chars[i]);
+                }
+                i += 1;
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => i += 1,
+            '[' => {
+                // [label](url) becomes "label (url)"
+                let close = chars[i..].iter().position(|&c| c == ']').map(|p| p + i);
+                match close {
+                    Some(close) if chars.get(close + 1) == Some(&'(') => {
+                        match chars[close..].iter().position(|&c| c == ')').map(|p| p + close) {
+                            Some(end) => {
+                                let label: String = chars[i + 1..close].iter().collect();
+                                let url: String = chars[close + 2..end].iter().collect();
+                                result.push_str(&strip_inline_markdown(&label));
+                                if !url.is_empty() && url != label {
+                                    result.push_str(&format!(" ({})", url));
+                                }
+                                i = end + 1;
+                            }
+                            None => {
+                                result.push('[');
+                                i += 1;
+                            }
+                        }
+                    }
+                    _ => {
+                        result.push('[');
+                        i += 1;
+                    }
+                }
+            }
+            c => {
+                result.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    result
+}
+
 fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
     let mut lines = Vec::new();
     let mut current_line = String::new();
@@ -240,4 +665,65 @@ fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
     }
 
     lines
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ide::sidebar::completion::CompletionItem;
+
+    #[test]
+    fn test_completion() {
+        let mut chat = Chat::new();
+        chat.insert_str("explain #pars");
+        assert_eq!(chat.completion_query(), Some((8, '#', "pars")));
+
+        chat.completion = Some(Completion {
+            items: vec![CompletionItem {
+                label: "Parser".to_string(),
+                detail: String::new(),
+                insert: "#src/lib.rs::Parser".to_string(),
+            }],
+            selected: 0,
+            start: 8,
+        });
+        chat.accept_completion();
+        assert_eq!(chat.input, "explain #src/lib.rs::Parser ");
+        assert_eq!(chat.input_cursor, chat.input.len());
+        assert_eq!(chat.completion_query(), None);
+    }
+
+    #[test]
+    fn test_edit_previous_message() {
+        let mut chat = Chat::new();
+        let (first, second) = (Utc::now(), Utc::now() + chrono::Duration::seconds(1));
+        chat.add_user_message("first question");
+        chat.link_last_user_message(Some(first));
+        chat.add_ai_message("first answer");
+        chat.add_user_message("explain 10:4");
+        chat.add_user_message("second question");
+        chat.link_last_user_message(Some(second));
+        chat.insert_str("half typed");
+
+        assert!(chat.edit_previous());
+        assert_eq!((chat.input.as_str(), chat.editing.as_ref().map(|(index, _)| *index)), ("second question", Some(4)));
+        assert!(chat.edit_previous()); // Skips the one that never went into the conversation
+        assert_eq!(chat.input, "first question");
+        assert!(!chat.edit_previous());
+        chat.cancel_edit();
+        assert_eq!((chat.input.as_str(), chat.input_cursor), ("half typed", 10));
+
+        chat.truncate(1);
+        assert_eq!(chat.messages.len(), 1);
+        assert!(!chat.edit_previous());
+    }
+
+    #[test]
+    fn test_to_plain_text() {
+        let markdown = "## Summary\n\nUse **bold** and `code`, see [docs](https://example.com).\n* item one\n> quoted\n```rust\nlet snake_case = a * b;\n```";
+        assert_eq!(
+            to_plain_text(markdown),
+            "Summary\n\nUse bold and code, see docs (https://example.com).\n- item one\nquoted\nlet snake_case = a * b;"
+        );
+        assert_eq!(to_plain_text("keep snake_case and 2 * 3"), "keep snake_case and 2 * 3");
+    }
+}
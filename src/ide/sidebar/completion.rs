@@ -0,0 +1,68 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+pub struct CompletionItem {
+    pub label: String,
+    pub detail: String,
+    pub insert: String, // Replaces the typed `@query` / `#query`
+}
+
+/// Popup above the chat input offering `@file` and `#symbol` references
+pub struct Completion {
+    pub items: Vec<CompletionItem>,
+    pub selected: usize,
+    pub start: usize, // Byte offset of the trigger character in the input
+}
+
+impl Completion {
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.items.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn selected_item(&self) -> Option<&CompletionItem> {
+        self.items.get(self.selected)
+    }
+
+    /// Draw just above `input_area`, as wide as it
+    pub fn draw(&self, frame: &mut Frame, input_area: Rect) {
+        let height = (self.items.len() as u16 + 2).min(input_area.y);
+        if height < 3 {
+            return;
+        }
+        let area = Rect { x: input_area.x, y: input_area.y - height, width: input_area.width, height };
+        frame.render_widget(Clear, area);
+
+        let selected_style = Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD);
+        let lines: Vec<Line> = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let style = if index == self.selected { selected_style } else { Style::default().fg(Color::Gray) };
+                Line::from(vec![
+                    Span::styled(format!(" {} ", item.label), style),
+                    Span::styled(format!(" {}", item.detail), Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect();
+
+        let popup = Paragraph::new(lines).block(
+            Block::default()
+                .title(" Tab: insert  Esc: dismiss ")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        );
+        frame.render_widget(popup, area);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::agent::aiignore::AiIgnore;
+use crate::ide::explorer_filter::ExplorerFilter;
+use crate::ide::theme;
 use anyhow::Result;
 use ratatui::{
     layout::Rect,
@@ -6,7 +9,7 @@ use ratatui::{
     widgets::{Block, Borders, List, ListItem, ListState},
     Frame,
 };
-use std::{fs, path::{Path, PathBuf}};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
 
 #[derive(Debug, Clone)]
 pub struct FileNode {
@@ -19,7 +22,13 @@ pub struct FileNode {
 }
 
 impl FileNode {
-    pub fn new(path: PathBuf, depth: usize) -> Result<Self> {
+    pub fn new(path: PathBuf, depth: usize, filter: &ExplorerFilter) -> Result<Self> {
+        let entries = filter.entries(&path);
+        Ok(Self::with_entries(path, depth, &entries))
+    }
+
+    /// The node for `path` with its children from `entries`, what the explorer lists in each directory
+    fn with_entries(path: PathBuf, depth: usize, entries: &HashMap<PathBuf, Vec<PathBuf>>) -> Self {
         let name = path.file_name()
             .and_then(|n| n.to_str())
             .unwrap_or("")
@@ -29,48 +38,19 @@ impl FileNode {
         let mut children = Vec::new();
 
         if is_dir {
-            if let Ok(entries) = fs::read_dir(&path) {
-                let mut valid_entries: Vec<_> = entries
-                    .filter_map(|entry| entry.ok())
-                    .filter(|entry| {
-                        // Filter out hidden files and common ignored directories
-                        if let Some(file_name) = entry.file_name().to_str() {
-                            !file_name.starts_with('.') && 
-                            file_name != "target" && 
-                            file_name != "node_modules"
-                        } else {
-                            false
-                        }
-                    })
-                    .collect();
-
-                // Sort: directories first, then files, both alphabetically
-                valid_entries.sort_by(|a, b| {
-                    let a_is_dir = a.path().is_dir();
-                    let b_is_dir = b.path().is_dir();
-                    match (a_is_dir, b_is_dir) {
-                        (true, false) => std::cmp::Ordering::Less,
-                        (false, true) => std::cmp::Ordering::Greater,
-                        _ => a.file_name().cmp(&b.file_name()),
-                    }
-                });
-
-                for entry in valid_entries {
-                    if let Ok(child_node) = FileNode::new(entry.path(), depth + 1) {
-                        children.push(child_node);
-                    }
-                }
+            for entry in entries.get(&path).into_iter().flatten() {
+                children.push(FileNode::with_entries(entry.clone(), depth + 1, entries));
             }
         }
 
-        Ok(Self {
+        Self {
             path,
             name,
             is_dir,
             is_expanded: false,
             depth,
             children,
-        })
+        }
     }
 
     pub fn get_display_name(&self) -> String {
@@ -155,11 +135,14 @@ pub struct FileExplorer {
     pub root: FileNode,
     pub list_state: ListState,
     pub current_directory: PathBuf,
+    pub ai_ignore: AiIgnore, // Files hidden from the AI are dimmed
+    pub filter: ExplorerFilter, // Ignore globs, .gitignore and hidden files
+    pub read_only: bool, // Reader mode: create, delete and rename are refused
 }
 
 impl FileExplorer {
-    pub fn new(root_path: &Path) -> Result<Self> {
-        let root = FileNode::new(root_path.to_path_buf(), 0)?;
+    pub fn new(root_path: &Path, filter: ExplorerFilter) -> Result<Self> {
+        let root = FileNode::new(root_path.to_path_buf(), 0, &filter)?;
         let mut list_state = ListState::default();
         list_state.select(Some(0));
         
@@ -167,12 +150,16 @@ impl FileExplorer {
             root,
             list_state,
             current_directory: root_path.to_path_buf(),
+            ai_ignore: AiIgnore::load(root_path),
+            filter,
+            read_only: false,
         })
     }
 
     pub fn refresh(&mut self) -> Result<()> {
         let selected_index = self.list_state.selected().unwrap_or(0);
-        self.root = FileNode::new(self.current_directory.clone(), 0)?;
+        self.root = FileNode::new(self.current_directory.clone(), 0, &self.filter)?;
+        self.ai_ignore = AiIgnore::load(&self.current_directory);
         
         // Try to maintain selection after refresh
         let flat_list = self.root.get_flat_list();
@@ -182,6 +169,13 @@ impl FileExplorer {
         Ok(())
     }
 
+    /// Show or hide dotfiles; returns whether they are shown now
+    pub fn toggle_hidden(&mut self) -> Result<bool> {
+        self.filter.show_hidden = !self.filter.show_hidden;
+        self.refresh()?;
+        Ok(self.filter.show_hidden)
+    }
+
     pub fn navigate_up(&mut self) {
         if let Some(selected) = self.list_state.selected() {
             if selected > 0 {
@@ -216,7 +210,15 @@ impl FileExplorer {
         }
     }
 
+    fn ensure_writable(&self) -> Result<()> {
+        if self.read_only {
+            return Err(anyhow::anyhow!("The IDE was started with --read-only"));
+        }
+        Ok(())
+    }
+
     pub fn create_file(&mut self, name: &str) -> Result<PathBuf> {
+        self.ensure_writable()?;
         let selected_dir = self.get_selected_directory();
         let file_path = selected_dir.join(name);
         
@@ -230,6 +232,7 @@ impl FileExplorer {
     }
 
     pub fn create_folder(&mut self, name: &str) -> Result<PathBuf> {
+        self.ensure_writable()?;
         let selected_dir = self.get_selected_directory();
         let folder_path = selected_dir.join(name);
         
@@ -243,6 +246,7 @@ impl FileExplorer {
     }
 
     pub fn delete_file(&mut self, path: &Path) -> Result<()> {
+        self.ensure_writable()?;
         if !path.exists() {
             return Err(anyhow::anyhow!("File does not exist"));
         }
@@ -258,6 +262,7 @@ impl FileExplorer {
     }
 
     pub fn rename_file(&mut self, old_path: &Path, new_name: &str) -> Result<PathBuf> {
+        self.ensure_writable()?;
         if !old_path.exists() {
             return Err(anyhow::anyhow!("File does not exist"));
         }
@@ -299,7 +304,9 @@ impl FileExplorer {
             .iter()
             .map(|node| {
                 let display_name = node.get_display_name();
-                let style = if node.is_dir {
+                let style = if self.ai_ignore.is_ignored(&node.path, node.is_dir) {
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC)
+                } else if node.is_dir {
                     Style::default().fg(Color::Blue)
                 } else {
                     Style::default().fg(Color::White)
@@ -314,6 +321,7 @@ impl FileExplorer {
                     .and_then(|n| n.to_str())
                     .unwrap_or("Root")))
                 .borders(Borders::ALL)
+                .border_type(theme::border_type(is_focused))
                 .border_style(border_style))
             .highlight_style(
                 Style::default()
@@ -1,4 +1,5 @@
 use crate::ide::app::{NotificationMessage, NotificationType};
+use crate::ide::theme;
 use ratatui::{
     layout::Rect,
     style::{Color, Modifier, Style},
@@ -7,10 +8,27 @@ use ratatui::{
     Frame,
 };
 
+/// Icon and color a notification type is shown with
+pub fn type_style(notification_type: &NotificationType) -> (&'static str, Color) {
+    match notification_type {
+        NotificationType::MouseHover => ("🔍", Color::Gray),
+        NotificationType::MouseClick => ("🖱️", Color::Yellow),
+        NotificationType::FileOperation => ("📄", Color::Green),
+        NotificationType::Info => ("ℹ️", Color::Blue),
+        NotificationType::Debug => ("🐛", Color::Magenta),
+    }
+}
+
 pub struct NotificationPanel {
     pub list_state: ListState,
 }
 
+impl Default for NotificationPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl NotificationPanel {
     pub fn new() -> Self {
         let mut list_state = ListState::default();
@@ -33,13 +51,7 @@ impl NotificationPanel {
             .rev() // Show newest first
             .take(5) // Show only the last 5 notifications to fit in the space
             .map(|notification| {
-                let (icon, color) = match notification.notification_type {
-                    NotificationType::MouseHover => ("🔍", Color::Gray),
-                    NotificationType::MouseClick => ("🖱️", Color::Yellow),
-                    NotificationType::FileOperation => ("📄", Color::Green),
-                    NotificationType::Info => ("ℹ️", Color::Blue),
-                    NotificationType::Debug => ("🐛", Color::Magenta),
-                };
+                let (icon, color) = type_style(&notification.notification_type);
 
                 // Format timestamp (show seconds)
                 let elapsed = notification.timestamp
@@ -55,13 +67,15 @@ impl NotificationPanel {
                     format!("{}h", elapsed / 3600)
                 };
 
+                // Errors stand out by weight too, not just by their ❌
+                let mut message_style = Style::default().fg(Color::White);
+                if notification.message.starts_with('❌') {
+                    message_style = message_style.add_modifier(Modifier::BOLD);
+                }
                 let line = Line::from(vec![
                     Span::styled(icon, Style::default().fg(color)),
                     Span::raw(" "),
-                    Span::styled(
-                        format!("{} ({})", notification.message, time_str),
-                        Style::default().fg(Color::White)
-                    ),
+                    Span::styled(format!("{} ({})", notification.message, time_str), message_style),
                 ]);
 
                 ListItem::new(line)
@@ -72,6 +86,7 @@ impl NotificationPanel {
             .block(Block::default()
                 .title(" 📋 Notifications ")
                 .borders(Borders::ALL)
+                .border_type(theme::border_type(is_focused))
                 .border_style(border_style))
             .style(Style::default().fg(Color::White))
             .highlight_style(
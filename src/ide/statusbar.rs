@@ -1,3 +1,5 @@
+use crate::agent::supervisor::AutonomyLevel;
+use crate::i18n::Localizer;
 use crate::ide::app::{AppMode, FocusedPanel};
 use ratatui::{
     layout::Rect,
@@ -16,23 +18,36 @@ pub struct StatusInfo {
     pub cursor_position: (usize, usize), // (line, column)
     pub is_modified: bool,
     pub total_files: usize,
+    pub autonomy: AutonomyLevel,
+    pub encoding: String,
+    pub line_ending: &'static str,
+    pub read_only_badge: Option<&'static str>, // "READ-ONLY" or "BINARY"
+    pub language: Option<&'static str>, // Of the current tab, detected or set by hand
+    pub low_bandwidth: bool,
 }
 
 pub struct StatusBar;
 
+impl Default for StatusBar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StatusBar {
     pub fn new() -> Self {
         Self
     }
 
-    pub fn draw(&self, frame: &mut Frame, area: Rect, status_info: &StatusInfo) {
+    /// Draw the bar; returns where the file type label ended up, for mouse clicks
+    pub fn draw(&self, frame: &mut Frame, area: Rect, status_info: &StatusInfo, i18n: &Localizer) -> Rect {
         let current_time = Local::now().format("%H:%M:%S").to_string();
 
         // Left side: Mode and file info
         let mode_text = match status_info.mode {
-            AppMode::Normal => "NORMAL",
-            AppMode::Insert => "INSERT",
-            AppMode::Agentic => "AGENTIC",
+            AppMode::Normal => i18n.t("status-mode-normal"),
+            AppMode::Insert => i18n.t("status-mode-insert"),
+            AppMode::Agentic => format!("{} · {}", i18n.t("status-mode-agentic"), status_info.autonomy.label()),
         };
 
         let mode_color = match status_info.mode {
@@ -42,10 +57,10 @@ impl StatusBar {
         };
 
         let panel_text = match status_info.focused_panel {
-            FocusedPanel::FileExplorer => "FILES",
-            FocusedPanel::Editor => "EDITOR",
-            FocusedPanel::Chat => "CHAT",
-            FocusedPanel::Notifications => "NOTIFICATIONS",
+            FocusedPanel::FileExplorer => i18n.t("status-panel-files"),
+            FocusedPanel::Editor => i18n.t("status-panel-editor"),
+            FocusedPanel::Chat => i18n.t("status-panel-chat"),
+            FocusedPanel::Notifications => i18n.t("status-panel-notifications"),
         };
 
         // File information
@@ -53,11 +68,10 @@ impl StatusBar {
             let modified_indicator = if status_info.is_modified { " ●" } else { "" };
             let (line, col) = status_info.cursor_position;
             if line > 0 && col > 0 {
-                format!(" {} {} | Ln {}, Col {}{}", 
+                format!(" {} {} | {}{}", 
                     get_file_icon(filename),
                     filename,
-                    line,
-                    col,
+                    i18n.t_args("status-cursor", &[("line", &line.to_string()), ("col", &col.to_string())]),
                     modified_indicator
                 )
             } else {
@@ -68,12 +82,12 @@ impl StatusBar {
                 )
             }
         } else {
-            " No file open".to_string()
+            format!(" {}", i18n.t("status-no-file"))
         };
 
         // Tab count info
         let tab_info = if status_info.total_files > 0 {
-            format!(" ({} files)", status_info.total_files)
+            format!(" {}", i18n.t_args("status-file-count", &[("count", &status_info.total_files.to_string())]))
         } else {
             String::new()
         };
@@ -100,6 +114,13 @@ impl StatusBar {
             ),
         ];
 
+        if let Some(badge) = status_info.read_only_badge {
+            left_spans.push(Span::styled(
+                format!(" 🔒 {} ", badge),
+                Style::default().fg(Color::White).bg(Color::Red).add_modifier(Modifier::BOLD)
+            ));
+        }
+
         if !tab_info.is_empty() {
             left_spans.push(Span::styled(
                 tab_info,
@@ -108,35 +129,44 @@ impl StatusBar {
         }
 
         // Right side: Encoding, file type, and time
-        let file_type = status_info.current_file
-            .as_ref()
-            .and_then(|filename| {
-                std::path::Path::new(filename)
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-            })
-            .unwrap_or("Plain Text");
-
-        let right_spans = vec![
+        let file_type = status_info.language
+            .map(str::to_string)
+            .unwrap_or_else(|| i18n.t("status-plain-text"));
+
+        let mut right_spans = Vec::new();
+        if status_info.low_bandwidth {
+            right_spans.push(Span::styled(" 🐢 LOW-BW ", Style::default().fg(Color::Yellow)));
+        }
+        right_spans.extend([
+            Span::styled(
+                format!(" {} ", status_info.encoding),
+                Style::default().fg(if status_info.encoding.ends_with("(lossy)") { Color::Yellow } else { Color::Gray })
+            ),
             Span::styled(
-                format!(" UTF-8 "),
+                format!(" {} ", status_info.line_ending),
                 Style::default().fg(Color::Gray)
             ),
             Span::styled(
-                format!(" {} ", file_type.to_uppercase()),
+                format!(" {} ", file_type),
                 Style::default().fg(Color::Cyan)
             ),
             Span::styled(
                 format!(" {} ", current_time),
                 Style::default().fg(Color::White).bg(Color::DarkGray)
             ),
-        ];
+        ]);
 
         // Calculate spacing
         let left_width = left_spans.iter().map(|span| span.content.len()).sum::<usize>() as u16;
         let right_width = right_spans.iter().map(|span| span.content.len()).sum::<usize>() as u16;
         let available_width = area.width.saturating_sub(left_width + right_width);
 
+        // Where the file type (second-to-last on the right, before the clock) is drawn
+        let drawn_width = |spans: &[Span]| spans.iter().map(|span| span.width() as u16).sum::<u16>();
+        let label_index = right_spans.len() - 2;
+        let label_x = area.x + drawn_width(&left_spans) + available_width + drawn_width(&right_spans[..label_index]);
+        let label_area = Rect::new(label_x, area.y, right_spans[label_index].width() as u16, 1);
+
         // Create the complete status line
         let mut all_spans = left_spans;
         all_spans.push(Span::raw(" ".repeat(available_width as usize)));
@@ -147,6 +177,7 @@ impl StatusBar {
             .style(Style::default().bg(Color::DarkGray));
 
         frame.render_widget(status_paragraph, area);
+        label_area
     }
 }
 
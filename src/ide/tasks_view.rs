@@ -0,0 +1,70 @@
+use crate::agent::orchestrator::{LoopBudget, StopReason};
+use crate::agent::task_queue::{AgentTask, TaskStatus};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Overlay listing the agent tasks of this session: queued, running and finished
+#[derive(Default)]
+pub struct TasksView {
+    pub is_open: bool,
+    pub selected: usize,
+}
+
+impl TasksView {
+    pub fn open(&mut self) {
+        self.selected = 0;
+        self.is_open = true;
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+    }
+
+    pub fn select(&mut self, delta: isize, count: usize) {
+        self.selected = self.selected.saturating_add_signed(delta).min(count.saturating_sub(1));
+    }
+
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, tasks: &[AgentTask], budget: &LoopBudget) {
+        frame.render_widget(Clear, area);
+        self.selected = self.selected.min(tasks.len().saturating_sub(1));
+
+        let width = area.width.saturating_sub(4) as usize;
+        let mut lines = Vec::new();
+        if tasks.is_empty() {
+            lines.push(Line::from(Span::styled(
+                " No agent tasks yet - messages sent in agentic mode while the agent works are queued here",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        for (index, task) in tasks.iter().enumerate() {
+            let (icon, color, detail) = match &task.status {
+                TaskStatus::Queued => ("⏸", Color::Gray, "queued".to_string()),
+                TaskStatus::Running if task.cancel_requested => ("⏹", Color::Yellow, "cancelling...".to_string()),
+                TaskStatus::Running => ("▶", Color::Cyan, task.progress.clone()),
+                TaskStatus::Finished(StopReason::Completed) => ("✓", Color::Green, "finished".to_string()),
+                TaskStatus::Finished(reason) => ("✗", Color::Red, reason.describe(budget)),
+            };
+            let title: String = task.title.lines().next().unwrap_or_default().chars().take(width / 2).collect();
+            let style = if index == self.selected {
+                Style::default().bg(Color::Cyan).fg(Color::Black).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(color)
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {} #{} {} ", icon, task.id, title), style),
+                Span::styled(format!(" {}", detail), Style::default().fg(Color::DarkGray)),
+            ]));
+        }
+
+        let view = Paragraph::new(lines).block(Block::default()
+            .title(" 🗂️ Agent Tasks (j/k: select, x: cancel, c: clear finished, Esc: close) ")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)));
+        frame.render_widget(view, area);
+    }
+}
@@ -0,0 +1,303 @@
+#[cfg(feature = "tui")]
+use ratatui::{buffer::Buffer, style::Color, widgets::BorderType};
+use serde::{Deserialize, Serialize};
+
+/// Color palette the UI is drawn with. Panels keep naming the basic terminal colors and
+/// the theme swaps them when the frame is finished
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Theme {
+    #[default]
+    Default, // The terminal's own colors, dark in most terminals
+    Light,        // Dark text on a white background
+    Solarized,    // Solarized Dark
+    HighContrast, // Bright variants on the dark background, no dim grays
+    Deuteranopia, // Red/green distinctions moved to orange/blue (Okabe-Ito colors)
+    Protanopia,   // Like deuteranopia, with a lighter orange since reds look dark
+    Custom(String), // One of the user's themes, by name
+}
+
+/// The built-in themes in the order they are cycled through
+const PRESETS: [Theme; 6] = [Theme::Default, Theme::Light, Theme::Solarized, Theme::HighContrast, Theme::Deuteranopia, Theme::Protanopia];
+
+/// Colors for each role the panels use, as "#rrggbb" or a color name; unset roles keep the terminal's color
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    pub background: Option<String>,  // Everything without a background of its own
+    pub foreground: Option<String>,  // Text without a color of its own
+    pub accent: Option<String>,      // Cyan: focused borders, titles, selections, code
+    pub border: Option<String>,      // Dark gray: other borders, hints, the status bar's background, the ruler
+    pub text: Option<String>,        // Gray: secondary text
+    pub bright_text: Option<String>, // White: emphasized text and text in the status bar
+    pub inverse_text: Option<String>, // Black: text on highlighted backgrounds
+    pub warning: Option<String>,     // Yellow: system messages, insert mode, warnings
+    pub success: Option<String>,     // Green: your messages, added lines, normal mode
+    pub error: Option<String>,       // Red: errors, removed lines
+    pub info: Option<String>,        // Blue: links, the mode badge
+    pub special: Option<String>,     // Magenta: agentic mode, approvals, plans
+}
+
+/// A theme of the user's, from the config's `themes`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomTheme {
+    pub name: String,
+    #[serde(flatten)]
+    pub palette: Palette,
+}
+
+impl Palette {
+    fn new(colors: [&str; 12]) -> Self {
+        let [background, foreground, accent, border, text, bright_text, inverse_text, warning, success, error, info, special] =
+            colors.map(|color| Some(color.to_string()));
+        Self { background, foreground, accent, border, text, bright_text, inverse_text, warning, success, error, info, special }
+    }
+
+    pub fn light() -> Self {
+        Self::new([
+            "#ffffff", "#1f1f1f", "#0070c1", "#a8a8a8", "#4d4d4d", "#000000", "#ffffff", "#9a6700", "#116329", "#cf222e", "#0550ae", "#8250df",
+        ])
+    }
+
+    pub fn solarized() -> Self {
+        Self::new([
+            "#002b36", "#839496", "#2aa198", "#586e75", "#93a1a1", "#eee8d5", "#002b36", "#b58900", "#859900", "#dc322f", "#268bd2", "#d33682",
+        ])
+    }
+}
+
+impl Theme {
+    /// The next theme: the presets, then the user's themes
+    pub fn next(&self, custom: &[CustomTheme]) -> Self {
+        let mut all = PRESETS.to_vec();
+        all.extend(custom.iter().map(|theme| Theme::Custom(theme.name.clone())));
+        let index = all.iter().position(|theme| theme == self).map_or(0, |index| index + 1);
+        all.get(index).cloned().unwrap_or_default()
+    }
+
+    pub fn label(&self) -> &str {
+        match self {
+            Theme::Default => "dark",
+            Theme::Light => "light",
+            Theme::Solarized => "solarized",
+            Theme::HighContrast => "high contrast",
+            Theme::Deuteranopia => "deuteranopia-safe",
+            Theme::Protanopia => "protanopia-safe",
+            Theme::Custom(name) => name,
+        }
+    }
+
+    /// Whether it is a preset or one of the user's themes in `custom`
+    pub fn is_known(&self, custom: &[CustomTheme]) -> bool {
+        match self {
+            Theme::Custom(name) => custom.iter().any(|theme| &theme.name == name),
+            _ => true,
+        }
+    }
+
+    /// The palette to recolor with, for the themes that have one; None when a custom theme isn't defined
+    pub fn palette(&self, custom: &[CustomTheme]) -> Option<Palette> {
+        match self {
+            Theme::Light => Some(Palette::light()),
+            Theme::Solarized => Some(Palette::solarized()),
+            Theme::Custom(name) => custom.iter().find(|theme| &theme.name == name).map(|theme| theme.palette.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for Theme {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "default" | "dark" => Theme::Default,
+            "light" => Theme::Light,
+            "solarized" => Theme::Solarized,
+            "high_contrast" => Theme::HighContrast,
+            "deuteranopia" => Theme::Deuteranopia,
+            "protanopia" => Theme::Protanopia,
+            _ => Theme::Custom(name),
+        }
+    }
+}
+
+impl From<Theme> for String {
+    fn from(theme: Theme) -> Self {
+        match theme {
+            Theme::Default => "default".to_string(),
+            Theme::Light => "light".to_string(),
+            Theme::Solarized => "solarized".to_string(),
+            Theme::HighContrast => "high_contrast".to_string(),
+            Theme::Deuteranopia => "deuteranopia".to_string(),
+            Theme::Protanopia => "protanopia".to_string(),
+            Theme::Custom(name) => name,
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+impl Palette {
+    /// The colors by role name, for checking them
+    fn roles(&self) -> [(&'static str, &Option<String>); 12] {
+        [
+            ("background", &self.background),
+            ("foreground", &self.foreground),
+            ("accent", &self.accent),
+            ("border", &self.border),
+            ("text", &self.text),
+            ("bright_text", &self.bright_text),
+            ("inverse_text", &self.inverse_text),
+            ("warning", &self.warning),
+            ("success", &self.success),
+            ("error", &self.error),
+            ("info", &self.info),
+            ("special", &self.special),
+        ]
+    }
+
+    /// Roles whose color can't be read, e.g. "accent: #12345"
+    pub fn invalid_colors(&self) -> Vec<String> {
+        self.roles()
+            .into_iter()
+            .filter_map(|(role, color)| color.as_ref().filter(|color| color.parse::<Color>().is_err()).map(|color| format!("{}: {}", role, color)))
+            .collect()
+    }
+
+    /// The basic colors the panels draw with and what each becomes; unset and unreadable roles are left out
+    fn swaps(&self) -> Vec<(Color, Color)> {
+        let roles = [
+            (&self.accent, [Color::Cyan, Color::LightCyan]),
+            (&self.border, [Color::DarkGray, Color::DarkGray]),
+            (&self.text, [Color::Gray, Color::Gray]),
+            (&self.bright_text, [Color::White, Color::White]),
+            (&self.inverse_text, [Color::Black, Color::Black]),
+            (&self.warning, [Color::Yellow, Color::LightYellow]),
+            (&self.success, [Color::Green, Color::LightGreen]),
+            (&self.error, [Color::Red, Color::LightRed]),
+            (&self.info, [Color::Blue, Color::LightBlue]),
+            (&self.special, [Color::Magenta, Color::LightMagenta]),
+        ];
+        roles
+            .into_iter()
+            .filter_map(|(color, basics)| Some((parse(color)?, basics)))
+            .flat_map(|(color, basics)| basics.map(|basic| (basic, color)))
+            .collect()
+    }
+
+    /// Recolor a drawn frame
+    pub fn apply(&self, buffer: &mut Buffer) {
+        let swaps = self.swaps();
+        let swap = |color: Color| swaps.iter().find(|(basic, _)| *basic == color).map_or(color, |(_, to)| *to);
+        let (background, foreground) = (parse(&self.background), parse(&self.foreground));
+        for cell in buffer.content.iter_mut() {
+            cell.fg = if cell.fg == Color::Reset { foreground.unwrap_or(Color::Reset) } else { swap(cell.fg) };
+            cell.bg = if cell.bg == Color::Reset { background.unwrap_or(Color::Reset) } else { swap(cell.bg) };
+        }
+    }
+}
+
+#[cfg(feature = "tui")]
+fn parse(color: &Option<String>) -> Option<Color> {
+    color.as_ref()?.parse().ok()
+}
+
+#[cfg(feature = "tui")]
+impl Theme {
+    /// Color drawn in place of `color` as text
+    pub fn fg(&self, color: Color) -> Color {
+        match (self, color) {
+            (Theme::HighContrast, Color::DarkGray) => Color::Gray,
+            (Theme::HighContrast, Color::Gray) => Color::White,
+            (_, color) => self.bg(color),
+        }
+    }
+
+    /// Color drawn in place of `color` as a background; grays stay, so light text remains readable
+    pub fn bg(&self, color: Color) -> Color {
+        // Okabe-Ito colors, distinguishable with the common kinds of color blindness
+        const VERMILLION: Color = Color::Rgb(213, 94, 0);
+        const ORANGE: Color = Color::Rgb(230, 159, 0);
+        const BLUE: Color = Color::Rgb(0, 114, 178);
+        const SKY_BLUE: Color = Color::Rgb(86, 180, 233);
+        const YELLOW: Color = Color::Rgb(240, 228, 66);
+        const PURPLE: Color = Color::Rgb(204, 121, 167);
+
+        match (self, color) {
+            (Theme::HighContrast, Color::Red) => Color::LightRed,
+            (Theme::HighContrast, Color::Green) => Color::LightGreen,
+            (Theme::HighContrast, Color::Yellow) => Color::LightYellow,
+            (Theme::HighContrast, Color::Blue) => Color::LightBlue,
+            (Theme::HighContrast, Color::Magenta) => Color::LightMagenta,
+            (Theme::HighContrast, Color::Cyan) => Color::LightCyan,
+            (Theme::Deuteranopia, Color::Red | Color::LightRed) => VERMILLION,
+            (Theme::Protanopia, Color::Red | Color::LightRed) => ORANGE,
+            (Theme::Deuteranopia | Theme::Protanopia, Color::Green | Color::LightGreen) => BLUE,
+            (Theme::Deuteranopia | Theme::Protanopia, Color::Blue | Color::LightBlue) => SKY_BLUE,
+            (Theme::Deuteranopia | Theme::Protanopia, Color::Yellow | Color::LightYellow) => YELLOW,
+            (Theme::Deuteranopia | Theme::Protanopia, Color::Magenta | Color::LightMagenta) => PURPLE,
+            (_, color) => color,
+        }
+    }
+
+    /// Recolor a drawn frame; `custom` are the user's themes
+    pub fn apply(&self, buffer: &mut Buffer, custom: &[CustomTheme]) {
+        if let Some(palette) = self.palette(custom) {
+            return palette.apply(buffer);
+        }
+        if matches!(self, Theme::Default | Theme::Custom(_)) {
+            return;
+        }
+        for cell in buffer.content.iter_mut() {
+            cell.fg = self.fg(cell.fg);
+            cell.bg = self.bg(cell.bg);
+        }
+    }
+}
+
+/// Focused panels get a thick border, so focus doesn't rely on the border's color alone
+#[cfg(feature = "tui")]
+pub fn border_type(is_focused: bool) -> BorderType {
+    if is_focused {
+        BorderType::Thick
+    } else {
+        BorderType::Plain
+    }
+}
+
+#[cfg(all(test, feature = "tui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_colors() {
+        assert_eq!(Theme::Default.fg(Color::Red), Color::Red);
+        assert_eq!(Theme::HighContrast.fg(Color::DarkGray), Color::Gray);
+        assert_eq!(Theme::HighContrast.bg(Color::DarkGray), Color::DarkGray);
+        // Added and removed lines must not end up as red vs green
+        for theme in [Theme::Deuteranopia, Theme::Protanopia] {
+            assert_ne!(theme.fg(Color::Red), Color::Red);
+            assert_eq!(theme.fg(Color::Green), Color::Rgb(0, 114, 178));
+        }
+
+        let mut buffer = Buffer::empty(ratatui::layout::Rect::new(0, 0, 2, 1));
+        buffer.content[0].fg = Color::Green;
+        Theme::Protanopia.apply(&mut buffer, &[]);
+        assert_eq!(buffer.content[0].fg, Color::Rgb(0, 114, 178));
+        assert_eq!(buffer.content[1].fg, Color::Reset);
+
+        let nord = CustomTheme { name: "nord".to_string(), palette: Palette { success: Some("#a3be8c".to_string()), ..Palette::default() } };
+        let theme: Theme = serde_json::from_str("\"nord\"").unwrap();
+        buffer.content[0].fg = Color::Green;
+        theme.apply(&mut buffer, std::slice::from_ref(&nord));
+        assert_eq!(buffer.content[0].fg, Color::Rgb(163, 190, 140));
+        assert_eq!(buffer.content[1].bg, Color::Reset);
+
+        Theme::Solarized.apply(&mut buffer, &[]);
+        assert_eq!(buffer.content[1].bg, Color::Rgb(0, 43, 54));
+        assert_eq!(serde_json::from_str::<Theme>("\"high_contrast\"").unwrap(), Theme::HighContrast);
+        assert_eq!(Theme::Protanopia.next(std::slice::from_ref(&nord)), theme);
+        assert_eq!(theme.next(&[nord]), Theme::Default);
+        assert!(Palette::light().invalid_colors().is_empty());
+        assert!(!Theme::Custom("dracula".to_string()).is_known(&[]));
+        assert_eq!(Palette { info: Some("#12345".to_string()), ..Palette::default() }.invalid_colors(), vec!["info: #12345"]);
+    }
+}
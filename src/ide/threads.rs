@@ -0,0 +1,126 @@
+use crate::conversation::Conversation;
+use crate::ide::sidebar::chat::Chat;
+use crate::sessions::SavedSession;
+
+/// A conversation of its own, with what the chat panel showed of it: messages, scroll position
+/// and the message being typed
+pub struct ChatThread {
+    pub conversation: Conversation,
+    pub chat: Chat,
+    pub session: SavedSession, // Where it is saved; its name is the thread's
+    pub saved_revision: u64,   // Conversation revision last written to the session
+}
+
+/// The chat threads of the window, in the order the chat header shows them. The active thread
+/// lives in the chat panel and the app's conversation, so its slot here is empty
+pub struct Threads {
+    slots: Vec<Option<ChatThread>>,
+    active: usize,
+}
+
+impl Default for Threads {
+    fn default() -> Self {
+        Self { slots: vec![None], active: 0 }
+    }
+}
+
+impl Threads {
+    /// `parked` threads in order, with the active one's slot at `active` between them
+    pub fn with_parked(parked: Vec<ChatThread>, active: usize) -> Self {
+        let mut slots: Vec<Option<ChatThread>> = parked.into_iter().map(Some).collect();
+        let active = active.min(slots.len());
+        slots.insert(active, None);
+        Self { slots, active }
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// The threads' names, with `active` standing for the active thread's
+    pub fn names<'a>(&'a self, active: &'a SavedSession) -> Vec<&'a str> {
+        self.sessions(active).map(SavedSession::thread_name).collect()
+    }
+
+    /// The threads' sessions in order, with `active` standing for the active thread's
+    pub fn sessions<'a>(&'a self, active: &'a SavedSession) -> impl Iterator<Item = &'a SavedSession> {
+        self.slots.iter().map(move |slot| slot.as_ref().map_or(active, |thread| &thread.session))
+    }
+
+    /// The index of the thread called `name`, ignoring case
+    pub fn position(&self, name: &str, active: &SavedSession) -> Option<usize> {
+        self.names(active).iter().position(|thread| thread.eq_ignore_ascii_case(name))
+    }
+
+    /// The index `steps` away from the active thread, wrapping around
+    pub fn step(&self, steps: isize) -> usize {
+        (self.active as isize + steps).rem_euclid(self.slots.len() as isize) as usize
+    }
+
+    /// Park `current`, the active thread, and take the one at `index` to make it active
+    pub fn switch(&mut self, index: usize, current: ChatThread) -> ChatThread {
+        self.slots[self.active] = Some(current);
+        self.active = index;
+        self.slots[index].take().expect("only the active thread's slot is empty")
+    }
+
+    /// Park `current` and make room for a new active thread at the end
+    pub fn add(&mut self, current: ChatThread) {
+        self.slots[self.active] = Some(current);
+        self.slots.push(None);
+        self.active = self.slots.len() - 1;
+    }
+
+    /// Drop the active thread and take its neighbour to make active; None if it is the only one
+    pub fn close_active(&mut self) -> Option<ChatThread> {
+        if self.slots.len() < 2 {
+            return None;
+        }
+        self.slots.remove(self.active);
+        self.active = self.active.min(self.slots.len() - 1);
+        self.slots[self.active].take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn thread(name: Option<&str>) -> ChatThread {
+        let mut session = SavedSession::new(name.unwrap_or("first").to_string(), PathBuf::from("/work"));
+        session.name = name.map(str::to_string);
+        ChatThread { conversation: Conversation::new(), chat: Chat::new(), session, saved_revision: 0 }
+    }
+
+    #[test]
+    fn test_threads_switch_and_close() {
+        let mut threads = Threads::default();
+        let main = thread(None);
+        threads.add(main);
+        let auth = thread(Some("refactor auth"));
+        assert_eq!(threads.names(&auth.session), vec!["main", "refactor auth"]);
+        assert_eq!(threads.position("Refactor Auth", &auth.session), Some(1));
+        assert_eq!(threads.step(1), 0);
+
+        let main = threads.switch(threads.step(1), auth);
+        assert_eq!(main.session.thread_name(), "main");
+        assert_eq!(threads.active(), 0);
+        threads.add(main);
+        let ci = thread(Some("debug CI"));
+        assert_eq!(threads.names(&ci.session), vec!["main", "refactor auth", "debug CI"]);
+
+        let next = threads.close_active().unwrap();
+        assert_eq!(next.session.thread_name(), "refactor auth");
+        assert_eq!((threads.len(), threads.active()), (2, 1));
+        assert_eq!(threads.names(&next.session), vec!["main", "refactor auth"]);
+    }
+}
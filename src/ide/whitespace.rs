@@ -0,0 +1,35 @@
+/// Drawn for a space when visible whitespace is on
+pub const SPACE: char = '·';
+/// Drawn for a tab
+pub const TAB: char = '→';
+/// Drawn after the last character of a line
+pub const LINE_END: char = '¶';
+
+/// `text` split into runs of ordinary text and runs of whitespace markers (flagged `true`)
+pub fn mark(text: &str) -> Vec<(String, bool)> {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for c in text.chars() {
+        let (c, is_marker) = match c {
+            ' ' => (SPACE, true),
+            '\t' => (TAB, true),
+            c => (c, false),
+        };
+        match runs.last_mut() {
+            Some((run, marker)) if *marker == is_marker => run.push(c),
+            _ => runs.push((c.to_string(), is_marker)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mark_whitespace() {
+        let run = |text: &str, marker| (text.to_string(), marker);
+        assert_eq!(mark("\tlet x  = 1;"), vec![run("→", true), run("let", false), run("·", true), run("x", false), run("··", true), run("=", false), run("·", true), run("1;", false)]);
+        assert!(mark("").is_empty());
+    }
+}
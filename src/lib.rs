@@ -0,0 +1,70 @@
+//! Agent engine behind the `agent` terminal IDE.
+//!
+//! - [`api`]: Groq chat-completions client ([`api::GroqClient`]) and network profiles
+//! - [`api_error`]: failed API requests sorted into [`api_error::ApiError`] kinds with actionable messages
+//! - [`provider`]: the [`provider::LlmProvider`] trait over Groq, OpenAI, Anthropic and Ollama
+//! - [`retry`]: backoff and rate-limit retries of API requests
+//! - [`conversation`]: message history sent with each request
+//! - [`sessions`]: conversations saved as they go, to list and resume later
+//! - [`export`]: conversations rendered as Markdown or HTML documents to share
+//! - [`context_window`]: token estimates, and packing requests into the model's context window
+//! - [`diff`]: line and word diffs, and unified diffs of changed files
+//! - [`agent`]: parsing AI replies into [`agent::AgentAction`]s and executing them under
+//!   capability, `.aiignore` and risk rules, with supervised/autonomous checkpoints
+//! - [`config`]: the user's `config.toml`, layered with project, environment and command-line settings
+//! - [`config_layers`]: merging those layers, and writing the config back without losing its comments
+//! - [`secrets`]: API keys from environment variables and the OS keyring
+//! - [`cache`]: on-disk warm-start cache with version, fingerprint and age invalidation
+//! - [`workflow`]: saved conversation setups
+//! - [`persona`]: named system prompts that set the assistant's behaviour for a session
+//! - [`profile`]: named sets of provider, key, model and temperature to switch between
+//! - [`postprocess`]: stop sequences and clean-up of answers before they are shown
+//! - [`headless`]: one-shot `ask`/`run` with text or NDJSON event output
+//!
+//! The terminal UI lives in [`ide`] behind the default `tui` feature. To embed only the
+//! engine, depend on the crate with `default-features = false`:
+//!
+//! ```no_run
+//! use rust_coding_agent::agent::{actions::AgentActionParser, executor::DefaultAgentExecutor, AgentExecutor};
+//! use rust_coding_agent::api::{GroqClient, NetworkProfile};
+//! use rust_coding_agent::conversation::Conversation;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let client = GroqClient::with_profile("gsk_...".to_string(), NetworkProfile::normal());
+//! let mut conversation = Conversation::new();
+//! conversation.add_message(GroqClient::create_text_message("user", "List the files in src/"));
+//!
+//! let reply = client.send_message("llama-3.1-70b-versatile", conversation.get_messages().clone(), 0.7).await?;
+//! let mut executor = DefaultAgentExecutor::new(std::env::current_dir()?);
+//! for action in AgentActionParser::parse_agent_response(&reply) {
+//!     println!("{}", executor.execute_action(action)?.message);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod agent;
+pub mod api;
+pub mod api_error;
+pub mod cache;
+pub mod config;
+pub mod config_layers;
+pub mod context_window;
+pub mod conversation;
+pub mod diff;
+pub mod export;
+pub mod headless;
+pub mod ide;
+pub mod persona;
+pub mod postprocess;
+pub mod profile;
+pub mod provider;
+pub mod retry;
+pub mod secrets;
+pub mod sessions;
+pub mod workflow;
+
+#[cfg(feature = "tui")]
+pub mod clipboard;
+#[cfg(feature = "tui")]
+pub mod i18n;
@@ -1,19 +1,26 @@
-mod api;
-mod config;
-mod clipboard;
-mod conversation;
-mod ide;
-mod agent;
-
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use config::Config;
+use rust_coding_agent::agent::executor::DefaultAgentExecutor;
+use rust_coding_agent::agent::scratchpad::Scratchpad;
+use rust_coding_agent::agent::supervisor::AutonomyLevel;
+use rust_coding_agent::agent::undo::UndoLog;
+use rust_coding_agent::agent::AgentCapabilities;
+use rust_coding_agent::config::Config;
+use rust_coding_agent::export::{self, ExportFormat};
+use rust_coding_agent::headless::{self, Emitter, OutputFormat, RunSettings};
+use rust_coding_agent::ide;
+use rust_coding_agent::provider::{self, LlmProvider, ProviderKind};
+use rust_coding_agent::persona;
+use rust_coding_agent::secrets::KeyStore;
+use rust_coding_agent::sessions::SavedSession;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 #[derive(Parser)]
 #[command(name = "agent")]
 #[command(about = "A Rust-based TUI IDE with integrated AI coding agent")]
 #[command(long_about = "
-A terminal-based IDE with integrated AI assistant powered by Groq API.
+A terminal-based IDE with integrated AI assistant powered by Groq, OpenAI, Anthropic or Ollama.
 Features:
 • Multi-tab file editor with syntax highlighting
 • File explorer with create/delete/rename operations  
@@ -21,10 +28,22 @@ Features:
 • Vim-like navigation and keyboard shortcuts
 • Mouse support for clicking and scrolling
 
-Run without arguments to start the IDE. Use 'config' subcommand to set API keys.")]
+Run without arguments to start the IDE. Use 'config' subcommand to set API keys.
+Use 'ask' or 'run' for one-shot use from scripts; add '--output json' for NDJSON events.
+Use 'sessions list' and 'sessions resume <id>' to go back to an earlier conversation.
+Use '--read-only' to browse a checkout without being able to change it.")]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Open without write access: no saving, no file operations, no agent writes or commands
+    #[arg(long)]
+    read_only: bool,
+    /// Log API requests and responses, keys redacted, to api-debug.jsonl in the config directory
+    #[arg(long, global = true)]
+    debug_api: bool,
+    /// Override a config setting for this run, e.g. --set temperature=0.2 or --set http.timeout_secs=120
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    settings: Vec<String>,
 }
 
 #[derive(Subcommand)]
@@ -37,23 +56,155 @@ enum Commands {
         /// Set default model
         #[arg(long)]
         model: Option<String>,
+        /// Switch provider: groq, openai, anthropic or ollama
+        #[arg(long)]
+        provider: Option<ProviderKind>,
+        /// Set the API key of the chosen provider (the configured one without --provider)
+        #[arg(long)]
+        api_key: Option<String>,
+        /// Print the models the provider serves and exit
+        #[arg(long)]
+        list_models: bool,
+        /// Print the effective config, with project, environment and --set settings applied, and exit
+        #[arg(long)]
+        show: bool,
+        /// Use an OpenAI-compatible server, e.g. http://localhost:4000/v1; "default" goes back to the provider's
+        #[arg(long)]
+        base_url: Option<String>,
+        /// Switch to a profile from the config's "profiles": its provider, key, model and temperature
+        #[arg(long)]
+        profile: Option<String>,
+        /// Where to keep API keys: config (this config file) or keyring (the OS keyring); moves the keys already set
+        #[arg(long)]
+        key_store: Option<KeyStore>,
+    },
+    /// Ask a single question and print the answer
+    Ask {
+        prompt: String,
+        /// Output format: text or json (newline-delimited events)
+        #[arg(long, default_value = "text")]
+        output: OutputFormat,
+        /// Model to use instead of the configured default
+        #[arg(long)]
+        model: Option<String>,
+    },
+    /// Give the agent a task and execute the actions it replies with
+    Run {
+        task: String,
+        /// Output format: text or json (newline-delimited events)
+        #[arg(long, default_value = "text")]
+        output: OutputFormat,
+        /// Model to use instead of the configured default
+        #[arg(long)]
+        model: Option<String>,
+        /// Run modifying actions without stopping for review (destructive commands still stop)
+        #[arg(long)]
+        autonomous: bool,
+        /// Allow the agent to execute shell commands
+        #[arg(long)]
+        allow_commands: bool,
+        /// Allow the agent to run git status, diff and commit
+        #[arg(long)]
+        allow_git: bool,
+        /// Report what the agent would write, delete or run without doing it
+        #[arg(long)]
+        dry_run: bool,
+        /// Let the agent touch paths outside the current directory
+        #[arg(long)]
+        no_sandbox: bool,
+    },
+    /// Restore files from before the agent's last change in the current directory
+    Undo {
+        /// Revert every change of the last agent session instead of only the newest one
+        #[arg(long)]
+        session: bool,
+    },
+    /// List saved conversations or reopen one
+    Sessions {
+        #[command(subcommand)]
+        action: SessionsAction,
+    },
+    /// Write a saved conversation as a Markdown or HTML document
+    Export {
+        /// The session's id, or enough of its start to tell it apart
+        session: String,
+        /// md or html; by default the output's extension, then md
+        #[arg(long)]
+        format: Option<ExportFormat>,
+        /// File to write; by default conversation-<date>-<id>.<ext> in the current directory
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Replace the output file if it exists
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SessionsAction {
+    /// Print the saved conversations, most recent first
+    List,
+    /// Open the IDE in the conversation's project with its history
+    Resume {
+        /// The session's id, or enough of its start to tell it apart
+        id: String,
     },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let config = Config::load()?;
+    let mut config = Config::load_layered(&std::env::current_dir()?, &cli.settings)?;
+    config.debug_api_once = cli.debug_api;
 
     match cli.command {
-        Some(Commands::Config { groq_key, model }) => {
+        Some(Commands::Config { groq_key, model, provider, api_key, base_url, list_models, show, key_store, profile }) => {
+            if show {
+                print!("{}", config.show()?);
+                return Ok(());
+            }
+            if list_models {
+                let client = provider::connect(&config)?;
+                for id in provider::available_models(client.as_ref(), &provider::endpoint(&config), true).await? {
+                    let current = if id == config.get_model() { " (current)" } else { "" };
+                    println!("{}{}", id, current);
+                }
+                return Ok(());
+            }
             let mut config = config;
             let mut updates = Vec::new();
             
+            if let Some(store) = key_store {
+                // First, so keys set below already go to the new place
+                let moved = config.set_key_store(store)?;
+                updates.push(if moved.is_empty() { "Key store updated" } else { "Key store updated, existing keys moved" });
+            }
+            if let Some(name) = profile {
+                // Before the single settings, so those can adjust the profile's
+                config.switch_profile(&name)?;
+                updates.push("Profile switched");
+            }
             if let Some(key) = groq_key {
                 config.set_groq_key(key)?;
                 updates.push("Groq API key updated");
             }
+            if let Some(kind) = provider {
+                config.provider = kind;
+                if model.is_none() {
+                    config.default_model = kind.default_model().to_string();
+                }
+                config.save()?;
+                updates.push("Provider updated");
+            }
+            if let Some(key) = api_key {
+                config.set_api_key(config.provider, key)?;
+                updates.push("API key updated");
+            }
+            if let Some(base_url) = base_url {
+                config.api_base_url = Some(base_url).filter(|url| url != "default");
+                config.save()?;
+                updates.push("API base URL updated");
+            }
             if let Some(model) = model {
                 config.set_model(model)?;
                 updates.push("Default model updated");
@@ -62,7 +213,7 @@ async fn main() -> Result<()> {
             if updates.is_empty() {
                 // No changes made, start TUI with info
                 let mut app = ide::IdeApp::new(config).await?;
-                app.add_notification("Use config subcommand with --groq-key or --model to configure".to_string(), ide::NotificationType::Info);
+                app.add_notification("Use config subcommand with --provider, --api-key or --model to configure".to_string(), ide::NotificationType::Info);
                 return ide::run_ide_with_app(app).await;
             } else {
                 // Changes made, start TUI with success notification  
@@ -73,6 +224,127 @@ async fn main() -> Result<()> {
                 return ide::run_ide_with_app(app).await;
             }
         }
+        Some(Commands::Ask { prompt, output, model }) => {
+            let mut emitter = Emitter::new(output, std::io::stdout());
+            let code = match headless_client(&config) {
+                Ok(client) => {
+                    let model = headless_model(&config, &client, model.as_deref());
+                    let root = std::env::current_dir()?;
+                    headless::ask(&model, &prompt, &root, &mut emitter).await
+                }
+                Err(e) => emitter.fail(e),
+            };
+            std::process::exit(code);
+        }
+        Some(Commands::Run { task, output, model, autonomous, allow_commands, allow_git, dry_run, no_sandbox }) => {
+            let mut emitter = Emitter::new(output, std::io::stdout());
+            let code = match headless_client(&config) {
+                Ok(client) => {
+                    let root = std::env::current_dir()?;
+                    let mut autonomy = config.autonomy.clone();
+                    if autonomous {
+                        autonomy.level = AutonomyLevel::Autonomous;
+                    }
+                    let capabilities = if cli.read_only {
+                        AgentCapabilities::read_only()
+                    } else {
+                        AgentCapabilities {
+                            can_execute_commands: allow_commands,
+                            can_use_git: allow_git,
+                            dry_run: dry_run || config.dry_run,
+                            sandbox: config.agent_sandbox && !no_sandbox,
+                            ..AgentCapabilities::default()
+                        }
+                    };
+                    let mut executor = DefaultAgentExecutor::new(root.clone())
+                        .with_capabilities(capabilities)
+                        .with_command_limits(config.command_limits)
+                        .with_test_command(config.test_command.clone());
+                    let session = uuid::Uuid::new_v4().to_string();
+                    if let Ok(undo_log) = UndoLog::for_project(&root, &session) {
+                        executor = executor.with_undo_log(undo_log);
+                    }
+                    if let Ok(scratchpad) = Scratchpad::for_session(&root, &session) {
+                        executor = executor.with_scratchpad(scratchpad);
+                    }
+                    let model = headless_model(&config, &client, model.as_deref());
+                    headless::run(
+                        &model,
+                        &task,
+                        &mut executor,
+                        &root,
+                        RunSettings {
+                            autonomy,
+                            budget: config.agent_loop,
+                            context_entries: config.project_context_entries,
+                            strict_json: config.strict_json_actions,
+                            spend: config.spend_limits,
+                        },
+                        &mut emitter,
+                    )
+                    .await
+                }
+                Err(e) => emitter.fail(e),
+            };
+            std::process::exit(code);
+        }
+        Some(Commands::Undo { session }) => {
+            if cli.read_only {
+                return Err(anyhow::anyhow!("`undo` writes files and can't be combined with --read-only"));
+            }
+            let mut undo_log = UndoLog::for_project(&std::env::current_dir()?, "")?;
+            let undone = match undo_log.last_session().map(str::to_string) {
+                Some(last) if session => undo_log.undo_session(&last)?,
+                _ => undo_log.undo_last()?.into_iter().collect(),
+            };
+            if undone.is_empty() {
+                println!("No agent changes to undo");
+            }
+            for change in undone {
+                println!("↩️ Undid {} ({})", change.description, change.timestamp.format("%Y-%m-%d %H:%M"));
+            }
+        }
+        Some(Commands::Sessions { action: SessionsAction::List }) => {
+            let sessions = SavedSession::list(&SavedSession::dir()?);
+            if sessions.is_empty() {
+                println!("No saved sessions");
+            }
+            for session in sessions {
+                println!("{}", session.summary());
+            }
+        }
+        Some(Commands::Sessions { action: SessionsAction::Resume { id } }) => {
+            let session = SavedSession::find(&SavedSession::dir()?, &id)?;
+            // The project's own config applies there, as when starting the IDE in it
+            let config = if session.project.is_dir() && std::env::set_current_dir(&session.project).is_ok() {
+                let mut config = Config::load_layered(&session.project, &cli.settings)?;
+                config.debug_api_once = cli.debug_api;
+                config
+            } else {
+                config
+            };
+            let mut app = ide::IdeApp::new(config).await?;
+            if cli.read_only {
+                app.enter_reader_mode();
+            }
+            app.resume_session(session);
+            ide::run_ide_with_app(app).await?;
+        }
+        Some(Commands::Export { session, format, output, force }) => {
+            let session = SavedSession::find(&SavedSession::dir()?, &session)?;
+            let format = format.or_else(|| output.as_deref().and_then(ExportFormat::from_path)).unwrap_or(ExportFormat::Markdown);
+            let output = output.unwrap_or_else(|| export::default_path(Path::new("."), &session, format));
+            if output.exists() && !force {
+                return Err(anyhow::anyhow!("{} already exists - pass --force to replace it", output.display()));
+            }
+            export::write(&output, &export::render(&session, format), force)?;
+            println!("Exported to {}", output.display());
+        }
+        None if cli.read_only => {
+            let mut app = ide::IdeApp::new(config).await?;
+            app.enter_reader_mode();
+            ide::run_ide_with_app(app).await?;
+        }
         None => {
             // Always run TUI IDE by default
             ide::run_ide(config).await?;
@@ -80,4 +352,23 @@ async fn main() -> Result<()> {
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+fn headless_client(config: &Config) -> Result<Arc<dyn LlmProvider>> {
+    provider::connect(config)
+}
+
+fn headless_model<'a>(config: &'a Config, client: &'a Arc<dyn LlmProvider>, model: Option<&'a str>) -> headless::Model<'a> {
+    headless::Model {
+        client: client.as_ref(),
+        name: model.unwrap_or(config.get_model()),
+        temperature: config.get_temperature(),
+        persona: config.persona.as_deref().and_then(|name| match persona::find(&config.personas, name) {
+            Some(persona) => Some(persona::system_message(&persona)),
+            None => {
+                eprintln!("⚠️ Unknown persona '{}'; answering without one", name);
+                None
+            }
+        }),
+    }
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// First line of the system message a persona adds, so switching persona can replace it
+pub const HEADER: &str = "Persona: ";
+
+/// A named system prompt that sets how the assistant behaves for the whole session
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Persona {
+    pub name: String, // Used with `/persona <name>`
+    pub system_prompt: String,
+}
+
+/// Personas available without configuring any
+pub fn builtin() -> Vec<Persona> {
+    let persona = |name: &str, system_prompt: &str| Persona { name: name.to_string(), system_prompt: system_prompt.to_string() };
+    vec![
+        persona(
+            "concise",
+            "Answer as briefly as the question allows. Prefer a short code snippet over prose, skip pleasantries \
+and don't repeat the question.",
+        ),
+        persona(
+            "rust-reviewer",
+            "You are a strict Rust code reviewer. Point out unsound unsafe code, needless clones and allocations, \
+unwraps that can panic, missing error context and non-idiomatic APIs. Quote the offending line and suggest the fix.",
+        ),
+        persona(
+            "agentic-coder",
+            "You are a coding agent. Before changing code, read the files involved; make small, focused edits, \
+run the tests after each change and keep going until they pass. Say briefly what you did at the end.",
+        ),
+    ]
+}
+
+/// Built-in personas followed by the configured ones; a configured persona replaces a built-in one
+/// of the same name
+pub fn all(configured: &[Persona]) -> Vec<Persona> {
+    let mut personas: Vec<Persona> = builtin()
+        .into_iter()
+        .filter(|persona| !configured.iter().any(|own| own.name.eq_ignore_ascii_case(&persona.name)))
+        .collect();
+    personas.extend(configured.iter().cloned());
+    personas
+}
+
+/// The persona called `name`, ignoring case
+pub fn find(configured: &[Persona], name: &str) -> Option<Persona> {
+    all(configured).into_iter().find(|persona| persona.name.eq_ignore_ascii_case(name))
+}
+
+/// System message that puts `persona` in charge of the conversation
+pub fn system_message(persona: &Persona) -> String {
+    format!("{}{}\n{}", HEADER, persona.name, persona.system_prompt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_personas() {
+        let own = vec![Persona { name: "Concise".to_string(), system_prompt: "One line only.".to_string() }];
+        let personas = all(&own);
+        assert_eq!(personas.len(), builtin().len());
+        assert_eq!(personas.last(), own.first());
+        assert_eq!(find(&own, "concise").map(|persona| persona.system_prompt), Some("One line only.".to_string()));
+        assert!(find(&own, "RUST-REVIEWER").is_some());
+        assert!(find(&own, "pirate").is_none());
+        assert_eq!(system_message(&own[0]), "Persona: Concise\nOne line only.");
+    }
+}
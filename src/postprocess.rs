@@ -0,0 +1,128 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+/// How answers are cleaned up between the API and the chat panel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostProcessSettings {
+    pub stop_sequences: Vec<String>, // The answer is cut at the first of these
+    pub strip_thinking: bool,        // Drop `<think>…</think>` reasoning that some models write before answering
+    pub max_blank_lines: usize,      // Longer runs of blank lines outside code blocks are shortened; 0 leaves them
+    pub max_display_chars: usize,    // Longer answers are shown collapsed until clicked; 0 shows them whole
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self { stop_sequences: Vec::new(), strip_thinking: true, max_blank_lines: 2, max_display_chars: 2000 }
+    }
+}
+
+/// One step of the clean-up
+pub trait PostProcessor: Send + Sync {
+    fn process(&self, text: &str) -> String;
+}
+
+/// Cuts the text at the first stop sequence
+pub struct StopSequences(pub Vec<String>);
+
+impl PostProcessor for StopSequences {
+    fn process(&self, text: &str) -> String {
+        let end = self.0.iter().filter(|stop| !stop.is_empty()).filter_map(|stop| text.find(stop.as_str())).min();
+        text[..end.unwrap_or(text.len())].to_string()
+    }
+}
+
+/// Removes reasoning blocks; one that isn't closed yet, as while streaming, hides the rest
+pub struct StripThinking;
+
+impl PostProcessor for StripThinking {
+    fn process(&self, text: &str) -> String {
+        static THINKING: OnceLock<Regex> = OnceLock::new();
+        let thinking = THINKING.get_or_init(|| Regex::new(r"(?s)<think(ing)?>.*?(</think(ing)?>|\z)").expect("valid regex"));
+        thinking.replace_all(text, "").trim_start().to_string()
+    }
+}
+
+/// Shortens runs of blank lines outside code blocks to at most `.0` lines
+pub struct CollapseBlankLines(pub usize);
+
+impl PostProcessor for CollapseBlankLines {
+    fn process(&self, text: &str) -> String {
+        let mut lines = Vec::new();
+        let (mut in_code, mut blank_run) = (false, 0);
+        for line in text.split('\n') {
+            if line.trim_start().starts_with("```") {
+                in_code = !in_code;
+            }
+            blank_run = if line.trim().is_empty() { blank_run + 1 } else { 0 };
+            if in_code || blank_run <= self.0 {
+                lines.push(line);
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// The post-processors an answer goes through, in order
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn PostProcessor>>,
+}
+
+impl Pipeline {
+    pub fn from_settings(settings: &PostProcessSettings) -> Self {
+        let mut pipeline = Self::default();
+        if settings.strip_thinking {
+            pipeline.push(StripThinking);
+        }
+        if !settings.stop_sequences.is_empty() {
+            pipeline.push(StopSequences(settings.stop_sequences.clone()));
+        }
+        if settings.max_blank_lines > 0 {
+            pipeline.push(CollapseBlankLines(settings.max_blank_lines));
+        }
+        pipeline
+    }
+
+    pub fn push(&mut self, stage: impl PostProcessor + 'static) {
+        self.stages.push(Box::new(stage));
+    }
+
+    pub fn process(&self, text: &str) -> String {
+        let processed = self.stages.iter().fold(text.to_string(), |text, stage| stage.process(&text));
+        processed.trim_end().to_string()
+    }
+}
+
+/// The start of `text` to show while it is collapsed, ending at a line break where one is near,
+/// or None if it is short enough to show whole
+pub fn preview(text: &str, max_chars: usize) -> Option<&str> {
+    if max_chars == 0 {
+        return None;
+    }
+    let (end, _) = text.char_indices().nth(max_chars)?;
+    let head = &text[..end];
+    match head.rfind('\n') {
+        Some(line_end) if line_end > end / 2 => Some(&head[..line_end]),
+        _ => Some(head),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline() {
+        let settings = PostProcessSettings { stop_sequences: vec!["<|end|>".to_string()], ..PostProcessSettings::default() };
+        let pipeline = Pipeline::from_settings(&settings);
+        let answer = "<think>The user wants a loop.</think>\nUse a loop:\n\n\n\n```\na\n\n\n\nb\n```\n<|end|>ignored";
+        assert_eq!(pipeline.process(answer), "Use a loop:\n\n\n```\na\n\n\n\nb\n```");
+        assert_eq!(pipeline.process("Sure. <thinking>still going"), "Sure.");
+
+        assert_eq!(preview("short", 10), None);
+        assert_eq!(preview("line one\nline two", 12), Some("line one"));
+        assert_eq!(preview("abcdef", 3), Some("abc"));
+    }
+}
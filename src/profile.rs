@@ -0,0 +1,70 @@
+use crate::provider::ProviderKind;
+use serde::{Deserialize, Serialize};
+
+/// A named set of provider, key and model settings, to hop between accounts and models per task.
+/// Settings a profile leaves out stay as they are when switching to it
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String, // Used with `/profile <name>` and `agent config --profile <name>`
+    pub provider: Option<ProviderKind>,
+    pub api_key: Option<String>, // Used instead of the provider's configured key while the profile is active
+    pub model: Option<String>,   // None picks the provider's default model when the provider changes
+    pub temperature: Option<f32>,
+}
+
+/// The profile called `name`, ignoring case
+pub fn find<'a>(profiles: &'a [Profile], name: &str) -> Option<&'a Profile> {
+    profiles.iter().find(|profile| profile.name.eq_ignore_ascii_case(name))
+}
+
+impl Profile {
+    /// Whether the profile's key is meant for `kind`: it names that provider, or none
+    pub fn applies_to(&self, kind: ProviderKind) -> bool {
+        self.provider.is_none_or(|provider| provider == kind)
+    }
+
+    /// The key to use for `kind`, when the profile has one for it
+    pub fn key_for(&self, kind: ProviderKind) -> Option<&str> {
+        self.api_key.as_deref().filter(|_| self.applies_to(kind))
+    }
+
+    /// One line for the profile list, e.g. "work: anthropic, claude-3-5-sonnet-latest, 0.2"
+    pub fn summary(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        parts.extend(self.provider.map(|provider| provider.id().to_string()));
+        parts.extend(self.model.clone());
+        parts.extend(self.temperature.map(|temperature| format!("{:.1}", temperature)));
+        if self.api_key.is_some() {
+            parts.push("own key".to_string());
+        }
+        if parts.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}: {}", self.name, parts.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let work = Profile {
+            name: "work".to_string(),
+            provider: Some(ProviderKind::Anthropic),
+            api_key: Some("sk-ant-work".to_string()),
+            model: Some("claude-3-5-sonnet-latest".to_string()),
+            temperature: Some(0.2),
+        };
+        let fast = Profile { name: "fast".to_string(), model: Some("llama-3.1-8b-instant".to_string()), ..Profile::default() };
+        let profiles = vec![work, fast];
+        assert_eq!(find(&profiles, "WORK").map(|profile| profile.summary()), Some("work: anthropic, claude-3-5-sonnet-latest, 0.2, own key".to_string()));
+        assert!(find(&profiles, "smart").is_none());
+        assert_eq!(profiles[0].key_for(ProviderKind::Anthropic), Some("sk-ant-work"));
+        assert_eq!(profiles[0].key_for(ProviderKind::Groq), None);
+        assert_eq!(profiles[1].key_for(ProviderKind::Groq), None);
+    }
+}
@@ -0,0 +1,291 @@
+use super::{BoxFuture, LlmProvider, ProviderKind};
+use crate::api::{self, Choice, ContentPart, FunctionCall, GroqMessage, GroqResponse, MessageContent, NetworkProfile, ResponseMessage, Tool, ToolCall, Transport, Usage};
+use crate::retry::{self, RetryPolicy, RetryStatus};
+use anyhow::{anyhow, Result};
+use reqwest::Client;
+use serde_json::{json, Value};
+use std::sync::OnceLock;
+
+const API_VERSION: &str = "2023-06-01";
+
+/// Client for Anthropic's Messages API, translating to and from chat-completions messages
+#[derive(Clone)]
+pub struct AnthropicClient {
+    client: OnceLock<Client>,
+    api_key: String,
+    base_url: String, // Anthropic's API, or a gateway speaking the Messages API
+    pub profile: NetworkProfile,
+    transport: Transport,
+    pub context_window: Option<usize>, // Overrides the model's known window, in tokens
+    pub retry: RetryPolicy,
+    pub retry_status: RetryStatus,
+}
+
+impl AnthropicClient {
+    pub fn new(api_key: String, profile: NetworkProfile) -> Self {
+        Self {
+            client: OnceLock::new(),
+            api_key,
+            base_url: ProviderKind::Anthropic.base_url().to_string(),
+            profile,
+            transport: Transport::default(),
+            context_window: None,
+            retry: RetryPolicy::default(),
+            retry_status: RetryStatus::default(),
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: Option<String>) -> Self {
+        if let Some(base_url) = base_url {
+            self.base_url = base_url.trim_end_matches('/').to_string();
+        }
+        self
+    }
+
+    pub fn with_context_window(mut self, context_window: Option<usize>) -> Self {
+        self.context_window = context_window;
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_transport(mut self, transport: Transport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    async fn send(&self, body: &Value) -> Result<GroqResponse> {
+        let url = format!("{}/messages", self.base_url);
+        let response = retry::send(&self.retry, &self.retry_status, ProviderKind::Anthropic.label(), || {
+            self.request(self.http().post(&url)).json(body)
+        })
+        .await?;
+        from_anthropic(response.json().await?)
+    }
+
+    fn http(&self) -> &Client {
+        self.client.get_or_init(|| api::http_client(&self.profile, &self.transport))
+    }
+
+    fn request(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        builder.header("x-api-key", &self.api_key).header("anthropic-version", API_VERSION)
+    }
+}
+
+impl LlmProvider for AnthropicClient {
+    fn kind(&self) -> ProviderKind {
+        ProviderKind::Anthropic
+    }
+
+    fn profile(&self) -> &NetworkProfile {
+        &self.profile
+    }
+
+    fn retry_status(&self) -> Option<String> {
+        self.retry_status.get()
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(async move {
+            let url = format!("{}/models", self.base_url);
+            let response = retry::send(&self.retry, &self.retry_status, ProviderKind::Anthropic.label(), || {
+                self.request(self.http().get(&url))
+            })
+            .await?;
+            Ok(api::model_ids(&response.json().await?))
+        })
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: Vec<GroqMessage>, temperature: f32, tools: &'a [Tool]) -> BoxFuture<'a, Result<GroqResponse>> {
+        Box::pin(async move {
+            let mut profile = self.profile;
+            let mut attempt = 0;
+            loop {
+                let messages = api::fit_messages(&messages, model, tools, &profile, self.context_window);
+                let body = to_anthropic(model, &messages, temperature, api::answer_tokens(model, &profile, self.context_window), tools);
+                match self.send(&body).await {
+                    Err(e) if attempt < profile.retries && api::is_timeout(&e) => {
+                        attempt += 1;
+                        profile = profile.smaller();
+                    }
+                    result => break result,
+                }
+            }
+        })
+    }
+}
+
+/// A Messages API request: system messages become the `system` field, tool calls and results
+/// become content blocks, and turns of the same role are merged, as the API wants them alternating
+fn to_anthropic(model: &str, messages: &[GroqMessage], temperature: f32, max_tokens: u32, tools: &[Tool]) -> Value {
+    let mut system = Vec::new();
+    let mut turns: Vec<(String, Vec<Value>)> = Vec::new();
+    for message in messages {
+        let (role, blocks) = match message.role.as_str() {
+            "system" => {
+                system.push(text_of(&message.content));
+                continue;
+            }
+            "tool" => ("user", vec![json!({
+                "type": "tool_result",
+                "tool_use_id": message.tool_call_id.clone().unwrap_or_default(),
+                "content": text_of(&message.content),
+            })]),
+            "assistant" => {
+                let mut blocks = content_blocks(&message.content);
+                for call in message.tool_calls.iter().flatten() {
+                    let input: Value = serde_json::from_str(&call.function.arguments).unwrap_or_else(|_| json!({}));
+                    blocks.push(json!({ "type": "tool_use", "id": call.id, "name": call.function.name, "input": input }));
+                }
+                ("assistant", blocks)
+            }
+            _ => ("user", content_blocks(&message.content)),
+        };
+        match turns.last_mut() {
+            Some((last_role, last_blocks)) if last_role == role => last_blocks.extend(blocks),
+            _ => turns.push((role.to_string(), blocks)),
+        }
+    }
+
+    let mut body = json!({
+        "model": model,
+        "max_tokens": max_tokens,
+        "temperature": temperature.min(1.0), // Anthropic's range is 0-1
+        "messages": turns.into_iter().map(|(role, content)| json!({ "role": role, "content": content })).collect::<Vec<_>>(),
+    });
+    if !system.is_empty() {
+        body["system"] = json!(system.join("\n\n"));
+    }
+    if !tools.is_empty() {
+        body["tools"] = tools
+            .iter()
+            .map(|tool| json!({ "name": tool.function.name, "description": tool.function.description, "input_schema": tool.function.parameters }))
+            .collect();
+    }
+    body
+}
+
+fn content_blocks(content: &MessageContent) -> Vec<Value> {
+    match content {
+        MessageContent::Text(text) if text.is_empty() => Vec::new(),
+        MessageContent::Text(text) => vec![json!({ "type": "text", "text": text })],
+        MessageContent::MultiModal(parts) => parts
+            .iter()
+            .map(|part| match part {
+                ContentPart::Text { text } => json!({ "type": "text", "text": text }),
+                ContentPart::Image { image_url } => {
+                    // `data:image/png;base64,AAAA`
+                    let (media_type, data) = image_url
+                        .url
+                        .strip_prefix("data:")
+                        .and_then(|rest| rest.split_once(";base64,"))
+                        .unwrap_or(("image/png", &image_url.url));
+                    json!({ "type": "image", "source": { "type": "base64", "media_type": media_type, "data": data } })
+                }
+            })
+            .collect(),
+    }
+}
+
+fn text_of(content: &MessageContent) -> String {
+    match content {
+        MessageContent::Text(text) => text.clone(),
+        MessageContent::MultiModal(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                ContentPart::Image { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+/// A Messages API answer as a chat-completions response
+fn from_anthropic(body: Value) -> Result<GroqResponse> {
+    let blocks = body["content"].as_array().ok_or_else(|| anyhow!("Unexpected Anthropic response: {}", body))?;
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    for block in blocks {
+        match block["type"].as_str() {
+            Some("text") => text.push_str(block["text"].as_str().unwrap_or_default()),
+            Some("tool_use") => tool_calls.push(ToolCall {
+                id: block["id"].as_str().unwrap_or_default().to_string(),
+                kind: "function".to_string(),
+                function: FunctionCall {
+                    name: block["name"].as_str().unwrap_or_default().to_string(),
+                    arguments: block["input"].to_string(),
+                },
+            }),
+            _ => {}
+        }
+    }
+    let tokens = |key: &str| body["usage"][key].as_u64().unwrap_or(0) as u32;
+    let (prompt_tokens, completion_tokens) = (tokens("input_tokens"), tokens("output_tokens"));
+    let finish_reason = match body["stop_reason"].as_str() {
+        Some("tool_use") => "tool_calls",
+        Some("max_tokens") => "length",
+        _ => "stop",
+    };
+    Ok(GroqResponse {
+        id: body["id"].as_str().unwrap_or_default().to_string(),
+        object: "chat.completion".to_string(),
+        created: chrono::Utc::now().timestamp() as u64,
+        model: body["model"].as_str().unwrap_or_default().to_string(),
+        choices: vec![Choice {
+            index: 0,
+            message: ResponseMessage {
+                role: "assistant".to_string(),
+                content: (!text.is_empty()).then_some(text),
+                tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+            },
+            finish_reason: finish_reason.to_string(),
+        }],
+        usage: Usage { prompt_tokens, completion_tokens, total_tokens: prompt_tokens + completion_tokens },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::GroqClient;
+
+    #[test]
+    fn test_anthropic_translation() {
+        let call = ToolCall {
+            id: "toolu_1".to_string(),
+            kind: "function".to_string(),
+            function: FunctionCall { name: "read_file".to_string(), arguments: "{\"path\":\"a.rs\"}".to_string() },
+        };
+        let messages = vec![
+            GroqClient::create_text_message("system", "Be brief"),
+            GroqClient::create_text_message("user", "What is in a.rs?"),
+            GroqClient::create_tool_call_message("", vec![call]),
+            GroqClient::create_tool_result_message("toolu_1", "fn main() {}"),
+            GroqClient::create_text_message("user", "Explain it"),
+        ];
+        let body = to_anthropic("claude", &messages, 0.7, 1024, &[]);
+        assert_eq!(body["system"], "Be brief");
+        let turns = body["messages"].as_array().unwrap();
+        assert_eq!(turns.len(), 3); // The tool result and the next question share a user turn
+        assert_eq!(turns[1]["content"][0]["input"]["path"], "a.rs");
+        assert_eq!(turns[2]["content"][0]["tool_use_id"], "toolu_1");
+        assert_eq!(turns[2]["content"][1]["text"], "Explain it");
+
+        let response = from_anthropic(json!({
+            "id": "msg_1",
+            "model": "claude",
+            "content": [{ "type": "text", "text": "Let me look." }, { "type": "tool_use", "id": "toolu_2", "name": "list_directory", "input": { "path": "src" } }],
+            "stop_reason": "tool_use",
+            "usage": { "input_tokens": 20, "output_tokens": 7 },
+        }))
+        .unwrap();
+        let message = &response.choices[0].message;
+        assert_eq!(message.content.as_deref(), Some("Let me look."));
+        assert_eq!(message.tool_calls.as_ref().unwrap()[0].function.arguments, "{\"path\":\"src\"}");
+        assert_eq!((response.usage.total_tokens, response.choices[0].finish_reason.as_str()), (27, "tool_calls"));
+    }
+}
@@ -0,0 +1,188 @@
+use super::{BoxFuture, LlmProvider, ProviderKind};
+use crate::api::{GroqMessage, GroqResponse, NetworkProfile, Tool};
+use crate::config::Config;
+use anyhow::Result;
+use regex::Regex;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+pub const LOG_FILE: &str = "api-debug.jsonl";
+/// Characters of each request and response body kept in the log
+const MAX_BODY_CHARS: usize = 4000;
+
+/// One request as logged: what was asked, how long it took and what came back
+#[derive(Debug, Serialize)]
+struct Entry {
+    timestamp: String,
+    provider: &'static str,
+    model: String,
+    temperature: f32,
+    messages: usize,
+    tools: usize,
+    streamed: bool,
+    latency_ms: u128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finish_reason: Option<String>,
+    prompt_tokens: u32,
+    completion_tokens: u32,
+    request: String, // The messages as JSON, truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<String>, // The answer's message as JSON, truncated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Wraps a provider and appends every chat request to a JSONL file, keys redacted, for working
+/// out why the model answered the way it did
+pub struct LoggedProvider {
+    inner: Arc<dyn LlmProvider>,
+    path: PathBuf,
+    secret: String, // The API key, never written to the log
+}
+
+impl LoggedProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, path: PathBuf, secret: String) -> Self {
+        Self { inner, path, secret }
+    }
+
+    /// `api-debug.jsonl` next to the config file
+    pub fn default_path() -> Result<PathBuf> {
+        let config_path = Config::get_config_path()?;
+        Ok(config_path.with_file_name(LOG_FILE))
+    }
+
+    /// The entry for a request about to be sent
+    fn begin(&self, model: &str, messages: &[GroqMessage], temperature: f32, tools: &[Tool], streamed: bool) -> Entry {
+        Entry {
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            provider: self.inner.kind().id(),
+            model: model.to_string(),
+            temperature,
+            messages: messages.len(),
+            tools: tools.len(),
+            streamed,
+            latency_ms: 0,
+            finish_reason: None,
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            request: self.body(&serde_json::to_string(messages).unwrap_or_default()),
+            response: None,
+            error: None,
+        }
+    }
+
+    /// Fill in how the request went and append the entry
+    fn finish(&self, mut entry: Entry, started: Instant, result: &Result<GroqResponse>) {
+        entry.latency_ms = started.elapsed().as_millis();
+        match result {
+            Ok(response) => {
+                entry.prompt_tokens = response.usage.prompt_tokens;
+                entry.completion_tokens = response.usage.completion_tokens;
+                if let Some(choice) = response.choices.first() {
+                    entry.finish_reason = Some(choice.finish_reason.clone());
+                    entry.response = Some(self.body(&serde_json::to_string(&choice.message).unwrap_or_default()));
+                }
+            }
+            Err(e) => entry.error = Some(self.body(&e.to_string())),
+        }
+        // The log is a debugging aid; failing to write it must not fail the request
+        let _ = append(&self.path, &entry);
+    }
+
+    fn body(&self, text: &str) -> String {
+        truncate(&redact(text, &self.secret), MAX_BODY_CHARS)
+    }
+}
+
+impl LlmProvider for LoggedProvider {
+    fn kind(&self) -> ProviderKind {
+        self.inner.kind()
+    }
+
+    fn profile(&self) -> &NetworkProfile {
+        self.inner.profile()
+    }
+
+    fn retry_status(&self) -> Option<String> {
+        self.inner.retry_status()
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        self.inner.list_models()
+    }
+
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        self.inner.embed(model, inputs)
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: Vec<GroqMessage>, temperature: f32, tools: &'a [Tool]) -> BoxFuture<'a, Result<GroqResponse>> {
+        Box::pin(async move {
+            let entry = self.begin(model, &messages, temperature, tools, false);
+            let started = Instant::now();
+            let result = self.inner.chat(model, messages, temperature, tools).await;
+            self.finish(entry, started, &result);
+            result
+        })
+    }
+
+    fn chat_streaming<'a>(
+        &'a self,
+        model: &'a str,
+        messages: Vec<GroqMessage>,
+        temperature: f32,
+        tools: &'a [Tool],
+        partial: mpsc::UnboundedSender<String>,
+    ) -> BoxFuture<'a, Result<GroqResponse>> {
+        Box::pin(async move {
+            let entry = self.begin(model, &messages, temperature, tools, true);
+            let started = Instant::now();
+            let result = self.inner.chat_streaming(model, messages, temperature, tools, partial).await;
+            self.finish(entry, started, &result);
+            result
+        })
+    }
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// `text` with `secret` and anything shaped like an API key or bearer token replaced
+fn redact(text: &str, secret: &str) -> String {
+    static KEYS: OnceLock<Regex> = OnceLock::new();
+    let keys = KEYS.get_or_init(|| Regex::new(r"\b(gsk_|sk-(ant-)?)[A-Za-z0-9_\-]{16,}|Bearer [A-Za-z0-9_\-.]{16,}").expect("valid regex"));
+    let text = if secret.is_empty() { text.to_string() } else { text.replace(secret, "[REDACTED]") };
+    keys.replace_all(&text, "[REDACTED]").into_owned()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}… [{} more chars]", &text[..end], text[end..].chars().count()),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_and_truncate() {
+        let key = "gsk_0123456789abcdefghijklmnop";
+        assert_eq!(redact(&format!("my key is {}", key), key), "my key is [REDACTED]");
+        assert_eq!(redact("Authorization: Bearer sk-ant-REDACTED", ""), "Authorization: [REDACTED]");
+        assert_eq!(redact("skipped tasks", "secret-key"), "skipped tasks");
+        assert_eq!(truncate("abcdef", 4), "abcd… [2 more chars]");
+        assert_eq!(truncate("abc", 4), "abc");
+    }
+}
@@ -0,0 +1,235 @@
+//! Chat models behind one interface: Groq, OpenAI and Ollama speak the same chat-completions
+//! protocol through [`GroqClient`], Anthropic has a client of its own.
+
+pub mod anthropic;
+pub mod debug_log;
+
+use crate::api::{GroqClient, GroqMessage, GroqResponse, NetworkProfile, Tool, Transport};
+use crate::cache::WarmCache;
+use crate::config::Config;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+const MODELS_CACHE_KEY: &str = "models";
+const MODELS_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderKind {
+    #[default]
+    Groq,
+    OpenAi,
+    Anthropic,
+    Ollama, // Local models; no key needed
+}
+
+impl ProviderKind {
+    pub const ALL: [ProviderKind; 4] = [ProviderKind::Groq, ProviderKind::OpenAi, ProviderKind::Anthropic, ProviderKind::Ollama];
+
+    /// Name used in the config and on the command line
+    pub fn id(self) -> &'static str {
+        match self {
+            ProviderKind::Groq => "groq",
+            ProviderKind::OpenAi => "openai",
+            ProviderKind::Anthropic => "anthropic",
+            ProviderKind::Ollama => "ollama",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProviderKind::Groq => "Groq",
+            ProviderKind::OpenAi => "OpenAI",
+            ProviderKind::Anthropic => "Anthropic",
+            ProviderKind::Ollama => "Ollama",
+        }
+    }
+
+    pub fn base_url(self) -> &'static str {
+        match self {
+            ProviderKind::Groq => "https://api.groq.com/openai/v1",
+            ProviderKind::OpenAi => "https://api.openai.com/v1",
+            ProviderKind::Anthropic => "https://api.anthropic.com/v1",
+            ProviderKind::Ollama => "http://localhost:11434/v1",
+        }
+    }
+
+    /// Model picked when switching to the provider without naming one
+    pub fn default_model(self) -> &'static str {
+        match self {
+            ProviderKind::Groq => "llama-3.1-70b-versatile",
+            ProviderKind::OpenAi => "gpt-4o-mini",
+            ProviderKind::Anthropic => "claude-3-5-sonnet-latest",
+            ProviderKind::Ollama => "llama3.1",
+        }
+    }
+
+    pub fn needs_key(self) -> bool {
+        self != ProviderKind::Ollama
+    }
+}
+
+impl std::str::FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        ProviderKind::ALL
+            .into_iter()
+            .find(|kind| kind.id() == s.to_lowercase())
+            .ok_or_else(|| format!("unknown provider '{}' (expected groq, openai, anthropic or ollama)", s))
+    }
+}
+
+/// A chat model service. Requests and answers use the chat-completions shapes of [`crate::api`];
+/// providers with another protocol translate to and from them
+pub trait LlmProvider: Send + Sync {
+    fn kind(&self) -> ProviderKind;
+
+    fn profile(&self) -> &NetworkProfile;
+
+    /// What the request in flight is waiting for before retrying, if anything
+    fn retry_status(&self) -> Option<String> {
+        None
+    }
+
+    /// Ids of the models the provider serves
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>>;
+
+    /// Embedding vectors for `inputs`, in order
+    fn embed<'a>(&'a self, _model: &'a str, _inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(async move { Err(anyhow!("{} has no embeddings endpoint", self.kind().label())) })
+    }
+
+    /// One answer to `messages`, offering `tools` the model may call instead of answering in text
+    fn chat<'a>(&'a self, model: &'a str, messages: Vec<GroqMessage>, temperature: f32, tools: &'a [Tool]) -> BoxFuture<'a, Result<GroqResponse>>;
+
+    /// Like `chat`, sending answer text to `partial` as it arrives. Providers that can't stream
+    /// send it all at once when it is done
+    fn chat_streaming<'a>(
+        &'a self,
+        model: &'a str,
+        messages: Vec<GroqMessage>,
+        temperature: f32,
+        tools: &'a [Tool],
+        partial: mpsc::UnboundedSender<String>,
+    ) -> BoxFuture<'a, Result<GroqResponse>> {
+        Box::pin(async move {
+            let response = self.chat(model, messages, temperature, tools).await?;
+            if let Some(content) = response.choices.first().and_then(|choice| choice.message.content.clone()) {
+                let _ = partial.send(content);
+            }
+            Ok(response)
+        })
+    }
+}
+
+impl LlmProvider for GroqClient {
+    fn kind(&self) -> ProviderKind {
+        self.provider
+    }
+
+    fn profile(&self) -> &NetworkProfile {
+        &self.profile
+    }
+
+    fn retry_status(&self) -> Option<String> {
+        self.retry_status.get()
+    }
+
+    fn list_models(&self) -> BoxFuture<'_, Result<Vec<String>>> {
+        Box::pin(GroqClient::list_models(self))
+    }
+
+    fn embed<'a>(&'a self, model: &'a str, inputs: &'a [String]) -> BoxFuture<'a, Result<Vec<Vec<f32>>>> {
+        Box::pin(GroqClient::embed(self, model, inputs))
+    }
+
+    fn chat<'a>(&'a self, model: &'a str, messages: Vec<GroqMessage>, temperature: f32, tools: &'a [Tool]) -> BoxFuture<'a, Result<GroqResponse>> {
+        Box::pin(self.complete_with_tools(model, messages, temperature, tools))
+    }
+
+    fn chat_streaming<'a>(
+        &'a self,
+        model: &'a str,
+        messages: Vec<GroqMessage>,
+        temperature: f32,
+        tools: &'a [Tool],
+        partial: mpsc::UnboundedSender<String>,
+    ) -> BoxFuture<'a, Result<GroqResponse>> {
+        Box::pin(self.complete_streaming(model, messages, temperature, tools, partial))
+    }
+}
+
+/// Where model lists come from: switching provider or server must not reuse another one's list
+pub fn endpoint(config: &Config) -> String {
+    format!("{}|{}", config.provider.id(), config.api_base_url.as_deref().unwrap_or_default())
+}
+
+/// The models `endpoint` serves, from the warm cache unless it is older than a day or `refresh` is set
+pub async fn available_models(llm: &dyn LlmProvider, endpoint: &str, refresh: bool) -> Result<Vec<String>> {
+    let cache = WarmCache::new().ok();
+    if let Some(models) = cache.as_ref().filter(|_| !refresh).and_then(|cache| cache.load(MODELS_CACHE_KEY, endpoint, MODELS_MAX_AGE)) {
+        return Ok(models);
+    }
+    let models = llm.list_models().await?;
+    if let Some(cache) = &cache {
+        let _ = cache.store(MODELS_CACHE_KEY, endpoint, &models);
+    }
+    Ok(models)
+}
+
+/// The client for the configured provider
+pub fn connect(config: &Config) -> Result<Arc<dyn LlmProvider>> {
+    let kind = config.provider;
+    // A self-hosted gateway decides for itself whether it wants a key
+    let custom_server = config.api_base_url.is_some();
+    let api_key = match config.api_key(kind) {
+        Some(key) => key,
+        None if !kind.needs_key() || custom_server => String::new(),
+        None => {
+            return Err(anyhow!(
+                "No {} API key configured. Run: agent config --provider {} --api-key YOUR_KEY, or set {}",
+                kind.label(),
+                kind.id(),
+                crate::secrets::env_var(kind)
+            ))
+        }
+    };
+    let mut profile = NetworkProfile::for_mode(config.low_bandwidth);
+    // Low-bandwidth mode only ever waits longer
+    let timeout = Duration::from_secs(config.http.timeout_secs);
+    profile.timeout = if profile.low_bandwidth { profile.timeout.max(timeout) } else { timeout };
+    if let Some(max_tokens) = config.max_tokens {
+        // Low-bandwidth mode only ever asks for shorter answers
+        profile.max_tokens = if profile.low_bandwidth { profile.max_tokens.min(max_tokens) } else { max_tokens };
+    }
+    let transport = Transport::new(config.https_proxy.as_deref(), config.no_proxy.as_deref(), config.ca_cert.as_deref())?.with_http(config.http);
+    let client: Arc<dyn LlmProvider> = match kind {
+        ProviderKind::Anthropic => Arc::new(
+            anthropic::AnthropicClient::new(api_key.clone(), profile)
+                .with_base_url(config.api_base_url.clone())
+                .with_context_window(config.context_window)
+                .with_retry(config.retry)
+                .with_transport(transport),
+        ),
+        _ => Arc::new(
+            GroqClient::with_profile(api_key.clone(), profile)
+                .for_provider(kind)
+                .with_base_url(config.api_base_url.clone())
+                .with_context_window(config.context_window)
+                .with_retry(config.retry)
+                .with_transport(transport),
+        ),
+    };
+    if !(config.debug_api || config.debug_api_once) {
+        return Ok(client);
+    }
+    Ok(Arc::new(debug_log::LoggedProvider::new(client, debug_log::LoggedProvider::default_path()?, api_key)))
+}
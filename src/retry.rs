@@ -0,0 +1,153 @@
+use crate::api_error::ApiError;
+use anyhow::Result;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How often and how patiently rate-limited, overloaded or unreachable APIs are retried
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,  // Requests sent in all, the first included; 1 turns retries off
+    pub base_delay_ms: u64, // Wait before the first retry, doubled for each one after it
+    pub max_delay_secs: u64, // Longest wait; a rate limit asking for longer is reported instead
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 4, base_delay_ms: 1000, max_delay_secs: 60 }
+    }
+}
+
+impl RetryPolicy {
+    /// Wait before retry number `retry` (0 for the first): exponential, with `jitter` (0..1)
+    /// spreading it over its upper half so clients that failed together don't retry together
+    pub fn backoff(&self, retry: u32, jitter: f64) -> Duration {
+        let full = Duration::from_millis(self.base_delay_ms.saturating_mul(1u64 << retry.min(16)))
+            .min(Duration::from_secs(self.max_delay_secs));
+        full.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
+    }
+}
+
+/// What a retrying request is waiting for, e.g. "Rate limited, retrying in 8s…", for the UI to show
+#[derive(Debug, Clone, Default)]
+pub struct RetryStatus(Arc<Mutex<Option<String>>>);
+
+impl RetryStatus {
+    pub fn get(&self) -> Option<String> {
+        self.0.lock().ok().and_then(|status| status.clone())
+    }
+
+    fn set(&self, status: Option<String>) {
+        if let Ok(mut current) = self.0.lock() {
+            *current = status;
+        }
+    }
+}
+
+/// Send the request `build` makes, retrying 429s, 5xx answers and failed connections. What
+/// isn't retried, or still fails, becomes an [`ApiError`]
+pub async fn send(policy: &RetryPolicy, status: &RetryStatus, provider: &'static str, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+    let mut retry = 0;
+    loop {
+        let last = retry + 1 >= policy.max_attempts;
+        let (wait, reason) = match build().send().await {
+            Ok(response) if response.status().is_success() => {
+                status.set(None);
+                return Ok(response);
+            }
+            Ok(response) => {
+                let code = response.status();
+                let server_wait = retry_after(response.headers());
+                let body = response.text().await.unwrap_or_default();
+                let transient = code == StatusCode::TOO_MANY_REQUESTS || code.is_server_error();
+                let wait = server_wait.or_else(|| try_again_in(&body));
+                let too_long = wait.is_some_and(|wait| wait > Duration::from_secs(policy.max_delay_secs));
+                if !transient || last || too_long {
+                    status.set(None);
+                    return Err(ApiError::from_response(provider, code, &body, wait).into());
+                }
+                let reason = if code == StatusCode::TOO_MANY_REQUESTS { "Rate limited" } else { "Server error" };
+                (wait.unwrap_or_else(|| policy.backoff(retry, jitter())), format!("{} ({})", reason, code.as_u16()))
+            }
+            Err(e) if e.is_connect() && !last => (policy.backoff(retry, jitter()), "Connection failed".to_string()),
+            Err(e) => {
+                status.set(None);
+                return Err(ApiError::from_network(&e).into());
+            }
+        };
+        retry += 1;
+        status.set(Some(format!(
+            "{}, retrying in {}s… (attempt {} of {})",
+            reason,
+            wait.as_secs_f64().ceil(),
+            retry + 1,
+            policy.max_attempts
+        )));
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// `Retry-After` in seconds; the HTTP-date form isn't used by the chat APIs
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds: f64 = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// The wait in a rate-limit message such as "Please try again in 1m7.5s."
+fn try_again_in(body: &str) -> Option<Duration> {
+    let rest = &body[body.find("try again in ")? + "try again in ".len()..];
+    let text = rest.split(|c: char| c.is_whitespace() || c == '"').next()?.trim_end_matches('.');
+    let mut total = 0.0;
+    let mut number = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let value: f64 = number.parse().ok()?;
+        number.clear();
+        total += match c {
+            'h' => value * 3600.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                value / 1000.0
+            }
+            'm' => value * 60.0,
+            's' => value,
+            _ => return None,
+        };
+    }
+    (number.is_empty() && total > 0.0).then(|| Duration::from_secs_f64(total))
+}
+
+/// 0..1 from the clock; spreading retries out needs no better randomness
+fn jitter() -> f64 {
+    let nanos = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |time| time.subsec_nanos());
+    nanos as f64 / 1_000_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_waits() {
+        let body = r#"{"error":{"message":"Rate limit reached for model `llama-3.1-70b-versatile`. Please try again in 1m7.5s. Visit https://console.groq.com","type":"tokens"}}"#;
+        assert_eq!(try_again_in(body), Some(Duration::from_millis(67_500)));
+        assert_eq!(try_again_in("Please try again in 450ms."), Some(Duration::from_millis(450)));
+        assert_eq!(try_again_in("Service unavailable"), None);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "8".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(8)));
+
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff(0, 1.0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2, 0.0), Duration::from_secs(2));
+        assert_eq!(policy.backoff(10, 1.0), Duration::from_secs(60));
+    }
+}
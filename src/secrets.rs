@@ -0,0 +1,104 @@
+use crate::provider::ProviderKind;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Keyring entries are filed under this service, one per provider and one per profile with a key of its own
+#[cfg(feature = "keyring")]
+const SERVICE: &str = "rust-coding-agent";
+
+/// Where API keys set with `agent config` are kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStore {
+    #[default]
+    Config, // The config file, as plain text
+    Keyring, // The OS keyring; needs the `keyring` feature
+}
+
+impl std::str::FromStr for KeyStore {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "config" => Ok(KeyStore::Config),
+            "keyring" => Ok(KeyStore::Keyring),
+            _ => Err(format!("unknown key store '{}' (expected config or keyring)", s)),
+        }
+    }
+}
+
+/// The environment variable a key for `kind` is read from when none is configured
+pub fn env_var(kind: ProviderKind) -> &'static str {
+    match kind {
+        ProviderKind::Groq => "GROQ_API_KEY",
+        ProviderKind::OpenAi => "OPENAI_API_KEY",
+        ProviderKind::Anthropic => "ANTHROPIC_API_KEY",
+        ProviderKind::Ollama => "OLLAMA_API_KEY",
+    }
+}
+
+pub fn from_env(kind: ProviderKind) -> Option<String> {
+    std::env::var(env_var(kind)).ok().filter(|key| !key.trim().is_empty())
+}
+
+/// Keyring account of the key the profile called `name` has of its own; providers' keys are filed under their id
+pub fn profile_account(name: &str) -> String {
+    format!("profile:{}", name)
+}
+
+/// The key filed under `account` (a provider id or a `profile_account`) in the OS keyring, if there is one
+#[cfg(feature = "keyring")]
+pub fn keyring_get(account: &str) -> Result<Option<String>> {
+    off_runtime(move || match keyring::Entry::new(SERVICE, account)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.into()),
+    })
+}
+
+/// Store the key for `account` in the OS keyring, or remove it with None
+#[cfg(feature = "keyring")]
+pub fn keyring_set(account: &str, key: Option<&str>) -> Result<()> {
+    off_runtime(move || {
+        let entry = keyring::Entry::new(SERVICE, account)?;
+        match key {
+            Some(key) => entry.set_password(key)?,
+            None => match entry.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(e.into()),
+            },
+        }
+        Ok(())
+    })
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_get(_account: &str) -> Result<Option<String>> {
+    Err(anyhow!("This build has no keyring support; rebuild with `--features keyring`"))
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_set(_account: &str, _key: Option<&str>) -> Result<()> {
+    Err(anyhow!("This build has no keyring support; rebuild with `--features keyring`"))
+}
+
+/// The keyring's blocking calls start a runtime of their own, which panics on a thread of the app's runtime
+#[cfg(feature = "keyring")]
+fn off_runtime<T: Send>(access: impl FnOnce() -> Result<T> + Send) -> Result<T> {
+    std::thread::scope(|scope| scope.spawn(access).join().map_err(|_| anyhow!("Keyring access failed"))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_sources() {
+        assert_eq!(env_var(ProviderKind::Groq), "GROQ_API_KEY");
+        assert_eq!(env_var(ProviderKind::OpenAi), "OPENAI_API_KEY");
+        assert_eq!(profile_account("work"), "profile:work");
+        assert_eq!("Keyring".parse::<KeyStore>(), Ok(KeyStore::Keyring));
+        assert!("vault".parse::<KeyStore>().is_err());
+        assert_eq!(serde_json::from_str::<KeyStore>("\"config\"").unwrap(), KeyStore::default());
+    }
+}
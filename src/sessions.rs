@@ -0,0 +1,310 @@
+use crate::api::MessageContent;
+use crate::conversation::Conversation;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Characters of the first question kept as a session's title
+const TITLE_CHARS: usize = 60;
+
+/// A conversation kept on disk as it goes, so it can be listed and resumed after the IDE closes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub id: String,
+    pub project: PathBuf, // Directory the IDE ran in
+    pub title: String,    // Start of the first question
+    #[serde(default)]
+    pub name: Option<String>, // Name of its chat thread, e.g. "debug CI"; the first thread has none
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub conversation: Conversation,
+    #[serde(default)]
+    pub checkpoints: Vec<Checkpoint>, // Points of the conversation to fork new threads from
+    #[serde(default)]
+    pub forked_from: Option<Fork>,
+}
+
+/// A point of a conversation to fork from later: its messages up to the one added at `until`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub name: String, // One word, e.g. "before-refactor"
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>, // When the last message it covers was added
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub conversation: Option<Conversation>, // Whole copy, as older versions kept it
+}
+
+impl Checkpoint {
+    /// The conversation as it was at the checkpoint, cut from `conversation`, the one it was made
+    /// in; None once those messages were trimmed or truncated away
+    pub fn conversation_in(&self, conversation: &Conversation) -> Option<Conversation> {
+        if let Some(copy) = &self.conversation {
+            return Some(copy.clone());
+        }
+        let last = conversation.position_sent_at(self.until?)?;
+        let mut copy = conversation.clone();
+        copy.truncate(last + 1);
+        Some(copy)
+    }
+}
+
+/// The checkpoint a conversation was forked from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Fork {
+    pub session: String,
+    pub checkpoint: String,
+}
+
+/// One line of a branch tree: a conversation, or one of its checkpoints
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchRow {
+    pub text: String, // With the tree lines before it
+    pub session: String,
+    pub checkpoint: Option<String>,
+}
+
+impl SavedSession {
+    pub fn new(id: String, project: PathBuf) -> Self {
+        let now = Utc::now();
+        Self {
+            id,
+            project,
+            title: String::new(),
+            name: None,
+            created_at: now,
+            updated_at: now,
+            conversation: Conversation::new(),
+            checkpoints: Vec::new(),
+            forked_from: None,
+        }
+    }
+
+    /// The name shown for its chat thread
+    pub fn thread_name(&self) -> &str {
+        self.name.as_deref().unwrap_or("main")
+    }
+
+    /// `~/.local/share/i4z_ide/sessions` on Linux, where each session is `<id>.json`
+    pub fn dir() -> Result<PathBuf> {
+        let dir = dirs::data_local_dir().ok_or_else(|| anyhow!("Could not find data directory"))?;
+        Ok(dir.join("i4z_ide").join("sessions"))
+    }
+
+    /// Keep `conversation` as this session's in `dir`; one without a question or a name isn't worth keeping
+    pub fn save(&mut self, dir: &Path, conversation: &Conversation) -> Result<bool> {
+        let question = conversation.first_text_message("user");
+        if question.is_none() && self.name.is_none() {
+            return Ok(false);
+        }
+        self.title = question.map(title).unwrap_or_default();
+        self.updated_at = Utc::now();
+        self.conversation = conversation.clone();
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(format!("{}.json", self.id)), serde_json::to_string_pretty(self)?)?;
+        Ok(true)
+    }
+
+    /// The sessions in `dir`, most recently used first; unreadable files are skipped
+    pub fn list(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+        let mut sessions: Vec<Self> = entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| serde_json::from_str(&fs::read_to_string(entry.path()).ok()?).ok())
+            .collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.updated_at));
+        sessions
+    }
+
+    /// The session with exactly this id
+    pub fn load(dir: &Path, id: &str) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(dir.join(format!("{}.json", id)))?)?)
+    }
+
+    /// The session whose id starts with `prefix`, e.g. the 8 characters `sessions list` shows
+    pub fn find(dir: &Path, prefix: &str) -> Result<Self> {
+        let mut matches = Self::list(dir).into_iter().filter(|session| session.id.starts_with(prefix));
+        match (matches.next(), matches.next()) {
+            (Some(session), None) if !prefix.is_empty() => Ok(session),
+            (Some(_), _) => Err(anyhow!("More than one session starts with `{}`", prefix)),
+            (None, _) => Err(anyhow!("No saved session `{}`", prefix)),
+        }
+    }
+
+    /// Questions and answers, leaving out system messages and tool results
+    pub fn turns(&self) -> usize {
+        turns(&self.conversation)
+    }
+
+    pub fn checkpoint(&self, name: &str) -> Option<&Checkpoint> {
+        self.checkpoints.iter().find(|checkpoint| checkpoint.name.eq_ignore_ascii_case(name))
+    }
+
+    /// One line for lists: short id, last use, turns, title and project
+    pub fn summary(&self) -> String {
+        format!(
+            "{}  {}  {:>3} msgs  {}  ({})",
+            &self.id[..self.id.len().min(8)],
+            self.updated_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+            self.turns(),
+            match &self.name {
+                Some(name) => format!("[{}] {}", name, self.title),
+                None => self.title.clone(),
+            },
+            self.project.display()
+        )
+    }
+}
+
+/// The family of conversations `id` belongs to, from the one they were all forked from: each
+/// with its checkpoints, and under each checkpoint the conversations forked from it
+pub fn branch_tree(sessions: &[SavedSession], id: &str) -> Vec<BranchRow> {
+    let find = |id: &str| sessions.iter().find(|session| session.id == id);
+    let Some(mut root) = find(id) else {
+        return Vec::new();
+    };
+    // Bounded, in case hand-edited files make a loop
+    for _ in 0..sessions.len() {
+        match root.forked_from.as_ref().and_then(|fork| find(&fork.session)) {
+            Some(parent) => root = parent,
+            None => break,
+        }
+    }
+    let mut rows = Vec::new();
+    add_branch(&mut rows, &mut HashSet::new(), sessions, root, "", "");
+    rows
+}
+
+/// `session` as `lead` and its label, then its checkpoints and their forks indented by `indent`;
+/// sessions already in `visited` are left out, so hand-edited forks that loop can't recurse forever
+fn add_branch<'a>(
+    rows: &mut Vec<BranchRow>,
+    visited: &mut HashSet<&'a str>,
+    sessions: &'a [SavedSession],
+    session: &'a SavedSession,
+    lead: &str,
+    indent: &str,
+) {
+    if !visited.insert(&session.id) {
+        return;
+    }
+    let title = if session.title.is_empty() { String::new() } else { format!("  {}", session.title) };
+    let text = format!("{}{}{}  ({} msgs)", lead, session.thread_name(), title, session.turns());
+    rows.push(BranchRow { text, session: session.id.clone(), checkpoint: None });
+    for (index, checkpoint) in session.checkpoints.iter().enumerate() {
+        let (lead, more) = if index + 1 == session.checkpoints.len() { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+        let size = checkpoint.conversation_in(&session.conversation).map_or("trimmed away".to_string(), |conversation| format!("{} msgs", turns(&conversation)));
+        let text = format!("{}{}◆ {}  ({})", indent, lead, checkpoint.name, size);
+        rows.push(BranchRow { text, session: session.id.clone(), checkpoint: Some(checkpoint.name.clone()) });
+        let fork = Fork { session: session.id.clone(), checkpoint: checkpoint.name.clone() };
+        let mut forks: Vec<&SavedSession> = sessions
+            .iter()
+            .filter(|other| other.forked_from.as_ref() == Some(&fork) && !visited.contains(other.id.as_str()))
+            .collect();
+        forks.sort_by_key(|other| other.created_at);
+        for (index, other) in forks.iter().enumerate() {
+            let (fork_lead, fork_more) = if index + 1 == forks.len() { ("└─ ", "   ") } else { ("├─ ", "│  ") };
+            let indent = format!("{}{}", indent, more);
+            add_branch(rows, visited, sessions, other, &format!("{}{}", indent, fork_lead), &format!("{}{}", indent, fork_more));
+        }
+    }
+}
+
+fn turns(conversation: &Conversation) -> usize {
+    conversation
+        .get_messages()
+        .iter()
+        .filter(|message| matches!(message.role.as_str(), "user" | "assistant") && matches!(&message.content, MessageContent::Text(text) if !text.is_empty()))
+        .count()
+}
+
+/// The first line of `question`, cut short to fit a list
+pub fn title(question: &str) -> String {
+    let line = question.lines().find(|line| !line.trim().is_empty()).unwrap_or_default().trim();
+    match line.char_indices().nth(TITLE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::GroqClient;
+
+    #[test]
+    fn test_sessions_are_saved_and_found() {
+        let dir = std::env::temp_dir().join(format!("i4z-sessions-{}", uuid::Uuid::new_v4()));
+        let mut conversation = Conversation::new();
+        conversation.add_system_message("You are helpful".to_string());
+        let mut first = SavedSession::new("aaaa1111".to_string(), PathBuf::from("/work/app"));
+        assert!(!first.save(&dir, &conversation).unwrap());
+
+        conversation.add_message(GroqClient::create_text_message("user", "\nWhy does the build fail on CI?\nIt passes locally."));
+        conversation.add_message(GroqClient::create_text_message("assistant", "The lockfile is stale."));
+        assert!(first.save(&dir, &conversation).unwrap());
+        let mut second = SavedSession::new("aaaa2222".to_string(), PathBuf::from("/work/app"));
+        second.save(&dir, &conversation).unwrap();
+
+        let sessions = SavedSession::list(&dir);
+        assert_eq!(sessions.iter().map(|session| session.id.as_str()).collect::<Vec<_>>(), vec!["aaaa2222", "aaaa1111"]);
+        assert_eq!(sessions[0].title, "Why does the build fail on CI?");
+        assert_eq!(sessions[0].turns(), 2);
+        assert_eq!(SavedSession::find(&dir, "aaaa1").unwrap().conversation.message_count(), 3);
+        assert!(SavedSession::find(&dir, "aaaa").is_err());
+        assert!(SavedSession::find(&dir, "b").is_err());
+        assert_eq!(SavedSession::load(&dir, "aaaa2222").unwrap().thread_name(), "main");
+
+        let mut named = SavedSession::new("bbbb".to_string(), PathBuf::from("/work/app"));
+        named.name = Some("debug CI".to_string());
+        assert!(named.save(&dir, &Conversation::new()).unwrap());
+        assert!(SavedSession::load(&dir, "bbbb").unwrap().summary().contains("[debug CI] "));
+        assert_eq!(title(&"x".repeat(70)).chars().count(), TITLE_CHARS + 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_branch_tree() {
+        let checkpoint = |name: &str| Checkpoint { name: name.to_string(), created_at: Utc::now(), until: None, conversation: Some(Conversation::new()) };
+        let session = |id: &str, fork: Option<(&str, &str)>| {
+            let mut session = SavedSession::new(id.to_string(), PathBuf::from("/work/app"));
+            session.name = Some(id.to_string());
+            session.forked_from = fork.map(|(session, checkpoint)| Fork { session: session.to_string(), checkpoint: checkpoint.to_string() });
+            session
+        };
+        let mut main = session("main", None);
+        main.checkpoints = vec![checkpoint("plan"), checkpoint("tests")];
+        let mut strategy_a = session("a", Some(("main", "plan")));
+        strategy_a.checkpoints = vec![checkpoint("halfway")];
+        let sessions = vec![strategy_a, session("b", Some(("main", "plan"))), session("c", Some(("a", "halfway"))), main, session("other", None)];
+
+        let rows = branch_tree(&sessions, "c");
+        let text: Vec<&str> = rows.iter().map(|row| row.text.split("  (").next().unwrap()).collect();
+        assert_eq!(text, vec!["main", "├─ ◆ plan", "│  ├─ a", "│  │  └─ ◆ halfway", "│  │     └─ c", "│  └─ b", "└─ ◆ tests"]);
+        assert_eq!((rows[3].session.as_str(), rows[3].checkpoint.as_deref()), ("a", Some("halfway")));
+        assert_eq!(branch_tree(&sessions, "other").len(), 1);
+        assert!(branch_tree(&sessions, "missing").is_empty());
+
+        // Forks that loop back on each other are listed once
+        let mut x = session("x", Some(("y", "p")));
+        x.checkpoints = vec![checkpoint("p")];
+        let mut y = session("y", Some(("x", "p")));
+        y.checkpoints = vec![checkpoint("p")];
+        assert_eq!(branch_tree(&[x, y], "x").len(), 4);
+
+        // A checkpoint keeps only where it was, and is cut from its conversation
+        let mut conversation = Conversation::new();
+        conversation.add_message(GroqClient::create_text_message("user", "first"));
+        conversation.add_message(GroqClient::create_text_message("assistant", "answer"));
+        let at = Checkpoint { name: "here".to_string(), created_at: Utc::now(), until: conversation.sent_at(1), conversation: None };
+        conversation.add_message(GroqClient::create_text_message("user", "second"));
+        assert_eq!(at.conversation_in(&conversation).map(|conversation| conversation.message_count()), Some(2));
+        assert!(at.conversation_in(&Conversation::new()).is_none());
+    }
+}
@@ -0,0 +1,85 @@
+use crate::agent::supervisor::AutonomyLevel;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// Pinned files larger than this are truncated before they go into the conversation
+const MAX_PINNED_FILE_BYTES: usize = 64 * 1024;
+
+/// A saved conversation setup ("release notes", "bug triage") that can be started with one command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    #[serde(default)]
+    pub pinned_files: Vec<PathBuf>, // Relative to the project root
+    #[serde(default)]
+    pub model: Option<String>, // Overrides the default model while the workflow runs
+    #[serde(default)]
+    pub autonomy: Option<AutonomyLevel>,
+    #[serde(default)]
+    pub opening_prompt: String, // Pre-filled in the chat input, not sent
+}
+
+/// Add a template, replacing any existing one with the same name (ignoring case)
+pub fn upsert(workflows: &mut Vec<WorkflowTemplate>, template: WorkflowTemplate) {
+    match workflows.iter_mut().find(|existing| existing.name.eq_ignore_ascii_case(&template.name)) {
+        Some(existing) => *existing = template,
+        None => workflows.push(template),
+    }
+}
+
+/// Context message carrying the pinned files' contents
+pub fn pinned_context(files: &[(PathBuf, String)]) -> Option<String> {
+    if files.is_empty() {
+        return None;
+    }
+
+    let mut context = String::from("Pinned files for this conversation:\n");
+    for (path, content) in files {
+        let mut end = content.len().min(MAX_PINNED_FILE_BYTES);
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        context.push_str(&format!("\n--- {} ---\n{}\n", path.display(), &content[..end]));
+        if end < content.len() {
+            context.push_str(&format!("[truncated after {} KB]\n", MAX_PINNED_FILE_BYTES / 1024));
+        }
+    }
+    Some(context)
+}
+
+/// Path as stored in a template: relative to the project root when inside it
+pub fn relative_to(path: &Path, root: &Path) -> PathBuf {
+    path.strip_prefix(root).map(Path::to_path_buf).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(name: &str, opening_prompt: &str) -> WorkflowTemplate {
+        WorkflowTemplate {
+            name: name.to_string(),
+            system_prompt: None,
+            pinned_files: Vec::new(),
+            model: None,
+            autonomy: None,
+            opening_prompt: opening_prompt.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_upsert_and_pinned_context() {
+        let mut workflows = vec![template("Bug triage", "old")];
+        upsert(&mut workflows, template("bug triage", "new"));
+        upsert(&mut workflows, template("Release notes", ""));
+        assert_eq!(workflows.len(), 2);
+        assert_eq!(workflows[0].opening_prompt, "new");
+
+        assert_eq!(pinned_context(&[]), None);
+        let context = pinned_context(&[(PathBuf::from("CHANGELOG.md"), "## 1.0".to_string())]).unwrap();
+        assert!(context.contains("--- CHANGELOG.md ---\n## 1.0\n"));
+        assert_eq!(relative_to(Path::new("/p/src/a.rs"), Path::new("/p")), PathBuf::from("src/a.rs"));
+    }
+}